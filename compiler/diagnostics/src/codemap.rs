@@ -12,6 +12,7 @@ pub struct CodeMap {
     files: DashMap<SourceId, Arc<SourceFile>>,
     names: DashMap<FileName, SourceId>,
     seen: DashMap<PathBuf, SourceId>,
+    children: DashMap<SourceId, Vec<SourceId>>,
     next_file_id: AtomicU32,
 }
 impl CodeMap {
@@ -21,6 +22,7 @@ impl CodeMap {
             files: DashMap::new(),
             names: DashMap::new(),
             seen: DashMap::new(),
+            children: DashMap::new(),
             next_file_id: AtomicU32::new(1),
         }
     }
@@ -68,9 +70,37 @@ impl CodeMap {
             Arc::new(SourceFile::new(file_id, name.into(), source, parent)),
         );
         self.names.insert(filename, file_id);
+        if let Some(parent) = parent {
+            self.children
+                .entry(parent.source_id())
+                .or_default()
+                .push(file_id);
+        }
         file_id
     }
 
+    /// Remove a file from the map, along with its children, recursively.
+    ///
+    /// This allows long-lived maps to replace files whose contents change, e.g. the documents
+    /// edited in a language server. Spans in the removed files can no longer be resolved.
+    pub fn remove(&self, file_id: SourceId) {
+        let Some((_, file)) = self.files.remove(&file_id) else { return };
+        self.names.remove_if(file.name(), |_, id| *id == file_id);
+        if let FileName::Real(ref path) = file.name() {
+            self.seen.remove_if(path, |_, id| *id == file_id);
+        }
+        if let Some(parent) = file.parent() {
+            if let Some(mut siblings) = self.children.get_mut(&parent.source_id()) {
+                siblings.retain(|id| *id != file_id);
+            }
+        }
+        if let Some((_, children)) = self.children.remove(&file_id) {
+            for child in children {
+                self.remove(child);
+            }
+        }
+    }
+
     /// Get the file corresponding to the given id.
     pub fn get(&self, file_id: SourceId) -> Result<Arc<SourceFile>, Error> {
         if file_id == SourceId::UNKNOWN {
@@ -92,6 +122,14 @@ impl CodeMap {
         self.get(file_id).ok().and_then(|f| f.parent())
     }
 
+    /// Get the ids of the files added with a parent in the given file, e.g. the files it includes
+    pub fn children(&self, file_id: SourceId) -> Vec<SourceId> {
+        self.children
+            .get(&file_id)
+            .map(|r| r.value().clone())
+            .unwrap_or_default()
+    }
+
     /// Get the file id corresponding to the given FileName
     pub fn get_file_id(&self, filename: &FileName) -> Option<SourceId> {
         self.names.get(filename).map(|id| *id)
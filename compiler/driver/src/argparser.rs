@@ -5,7 +5,7 @@ use clap::{App, AppSettings, Arg, ArgMatches};
 
 use firefly_session::{CodegenOptions, DebuggingOptions, OptionGroup, OutputType};
 use firefly_target::Target;
-use firefly_util::diagnostics::ColorArg;
+use firefly_util::diagnostics::{ColorArg, ErrorFormat};
 
 /// Parses the provided arguments
 pub fn parse<'a>(args: impl Iterator<Item = OsString>) -> clap::Result<ArgMatches<'a>> {
@@ -32,6 +32,8 @@ pub fn parser<'a, 'b>() -> App<'a, 'b> {
         )
         .subcommand(print_command())
         .subcommand(compile_command())
+        .subcommand(fmt_command())
+        .subcommand(lsp_command())
 }
 
 /// Prints help for the given command
@@ -39,6 +41,8 @@ pub fn command_help(command: &str) {
     match command {
         "print" => print_command().print_help().unwrap(),
         "compile" => compile_command().print_help().unwrap(),
+        "fmt" => fmt_command().print_help().unwrap(),
+        "lsp" => lsp_command().print_help().unwrap(),
         other => {
             eprintln!("Help unavailable for '{}' command!", other);
         }
@@ -77,6 +81,53 @@ fn print_command<'a, 'b>() -> App<'a, 'b> {
         )
 }
 
+fn fmt_command<'a, 'b>() -> App<'a, 'b> {
+    App::new("fmt")
+        .about("Formats Erlang sources in place, leaving macros and preprocessor directives as written")
+        .arg(
+            Arg::with_name("inputs")
+                .index(1)
+                .help(
+                    "Path(s) to the source file(s) or director(y|ies) to format.\n\
+                     If not provided, the src, include and test directories of the\n\
+                     current working directory are formatted.",
+                )
+                .next_line_help(true)
+                .multiple(true)
+                .value_name("INPUTS"),
+        )
+        .arg(
+            Arg::with_name("check")
+                .help("Report files which are not formatted, without modifying them, and exit with a non-zero status if there are any")
+                .long("check"),
+        )
+}
+
+fn lsp_command<'a, 'b>() -> App<'a, 'b> {
+    App::new("lsp")
+        .about("Runs a language server for Erlang, communicating over stdin/stdout")
+        .arg(
+            Arg::with_name("include-paths")
+                .help("Add a path to the Erlang include path.")
+                .long("include")
+                .short("I")
+                .value_name("PATH")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("code-paths")
+                .help("Add a path to search for the BEAM files of behaviours used by the sources.")
+                .long("code-path")
+                .short("P")
+                .value_name("PATH")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+}
+
 fn compile_command<'a, 'b>() -> App<'a, 'b> {
     let target = self::target_arg();
     App::new("compile")
@@ -186,6 +237,21 @@ fn compile_command<'a, 'b>() -> App<'a, 'b> {
                 .possible_values(ColorArg::VARIANTS)
                 .case_insensitive(true)
         )
+        .arg(
+            Arg::with_name("error-format")
+                .help(
+                    "Configure how diagnostics are printed.\n\
+                     \n\
+                     human = rendered as text with source snippets, on stderr (the default)\n\
+                     json  = one JSON object per diagnostic, on stdout\n\
+                     sarif = a SARIF 2.1.0 log of all diagnostics, on stdout",
+                )
+                .next_line_help(true)
+                .long("error-format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(ErrorFormat::VARIANTS),
+        )
         .arg(
             Arg::with_name("source-map-prefix")
                 .help("Remap source paths in all output (i.e. FROM/foo => TO/foo)")
@@ -265,6 +331,16 @@ fn compile_command<'a, 'b>() -> App<'a, 'b> {
                 .multiple(true)
                 .number_of_values(1),
         )
+        .arg(
+            Arg::with_name("code-paths")
+                .help("Add a path to search for the BEAM files of behaviours used by the inputs.")
+                .long("code-path")
+                .short("P")
+                .value_name("PATH")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name("emit")
                 .help(OutputType::help())
@@ -1,3 +1,4 @@
+use std::panic::{self, AssertUnwindSafe};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
@@ -6,7 +7,7 @@ use clap::ArgMatches;
 use log::debug;
 
 use firefly_session::{CodegenOptions, DebuggingOptions, Options};
-use firefly_util::diagnostics::{CodeMap, Emitter};
+use firefly_util::diagnostics::{CodeMap, DiagnosticsHandler, Emitter};
 use firefly_util::time::HumanDuration;
 
 use crate::compiler::Compiler;
@@ -30,6 +31,22 @@ pub fn handle_command(
     // Set up diagnostics
     let diagnostics = options.create_diagnostics_handler(codemap.clone(), emitter);
 
+    // Fatal errors unwind, so catch them to write out any diagnostics the emitter held back
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        compile(options, codemap, diagnostics.clone())
+    }));
+    diagnostics.finish()?;
+    match result {
+        Ok(result) => result,
+        Err(err) => panic::resume_unwind(err),
+    }
+}
+
+fn compile(
+    options: Arc<Options>,
+    codemap: Arc<CodeMap>,
+    diagnostics: Arc<DiagnosticsHandler>,
+) -> anyhow::Result<()> {
     if options.input_files.is_empty() {
         diagnostics.fatal("No inputs found!").raise();
     }
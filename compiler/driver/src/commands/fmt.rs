@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::ArgMatches;
+use walkdir::{DirEntry, WalkDir};
+
+use firefly_session::{CodegenOptions, DebuggingOptions, Options};
+use firefly_syntax_erl::formatter;
+use firefly_util::diagnostics::{CodeMap, DiagnosticsHandler, Emitter};
+
+pub fn configure<'a>(
+    codemap: Arc<CodeMap>,
+    c_opts: CodegenOptions,
+    z_opts: DebuggingOptions,
+    cwd: PathBuf,
+    args: &ArgMatches<'a>,
+) -> anyhow::Result<Arc<Options>> {
+    Options::new_with_defaults(None, codemap, c_opts, z_opts, cwd, args).map(Arc::new)
+}
+
+/// The main entry point for the 'fmt' command
+///
+/// Returns the exit code of the command, which is non-zero if any file could not be formatted,
+/// or when running with `--check`, if any file is not already formatted.
+pub fn handle_command<'a>(
+    options: Arc<Options>,
+    codemap: Arc<CodeMap>,
+    emitter: Option<Arc<dyn Emitter>>,
+    matches: &ArgMatches<'a>,
+) -> anyhow::Result<i32> {
+    let diagnostics = options.create_diagnostics_handler(codemap.clone(), emitter);
+    let check = matches.is_present("check");
+
+    // Without explicit inputs, we format the sources of the project in the current directory
+    let inputs = match matches.values_of_os("inputs") {
+        Some(values) => values.map(PathBuf::from).collect(),
+        None => ["src", "include", "test"]
+            .iter()
+            .map(|dir| options.current_dir.join(dir))
+            .filter(|dir| dir.is_dir())
+            .collect::<Vec<_>>(),
+    };
+
+    let result = format_inputs(&diagnostics, &codemap, &inputs, check);
+    diagnostics.finish()?;
+    Ok(if result? { 0 } else { 1 })
+}
+
+/// Formats the sources found in `inputs`, returning false if any of them failed, see `format_file`
+fn format_inputs(
+    diagnostics: &DiagnosticsHandler,
+    codemap: &Arc<CodeMap>,
+    inputs: &[PathBuf],
+    check: bool,
+) -> anyhow::Result<bool> {
+    let mut failed = false;
+    for input in inputs.iter() {
+        for path in find_sources(input)? {
+            failed |= !format_file(diagnostics, codemap, &path, check)?;
+        }
+    }
+    Ok(!failed)
+}
+
+/// Formats the file at `path`, returning false if it could not be formatted, or if `check` is
+/// set and the file is not already formatted
+fn format_file(
+    diagnostics: &DiagnosticsHandler,
+    codemap: &Arc<CodeMap>,
+    path: &Path,
+    check: bool,
+) -> anyhow::Result<bool> {
+    let source = std::fs::read_to_string(path)?;
+    let id = codemap.add(path, source);
+    let file = codemap.get(id)?;
+    let formatted = match formatter::format(file.clone()) {
+        Ok(formatted) => formatted,
+        Err(err) => {
+            diagnostics.emit(err);
+            return Ok(false);
+        }
+    };
+
+    if formatted == file.source() {
+        return Ok(true);
+    }
+    if check {
+        diagnostics.error(format!("{} is not formatted", path.display()));
+        return Ok(false);
+    }
+    std::fs::write(path, formatted)?;
+    diagnostics.success("Formatted", path.display());
+    Ok(true)
+}
+
+/// Returns the Erlang sources and headers at the given path, searching directories recursively
+fn find_sources(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    fn is_hidden(entry: &DirEntry) -> bool {
+        entry.depth() > 0
+            && entry
+                .file_name()
+                .to_str()
+                .map(|s| s.starts_with('.') || s == "_build")
+                .unwrap_or(false)
+    }
+
+    fn is_erlang(path: &Path) -> bool {
+        path.extension()
+            .map(|ext| ext == "erl" || ext == "hrl")
+            .unwrap_or(false)
+    }
+
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut sources = vec![];
+    for entry in WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !is_hidden(e))
+    {
+        let entry = entry?;
+        if entry.file_type().is_file() && is_erlang(entry.path()) {
+            sources.push(entry.into_path());
+        }
+    }
+    Ok(sources)
+}
@@ -0,0 +1,47 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::ArgMatches;
+
+use firefly_session::{CodegenOptions, DebuggingOptions, Options};
+use firefly_util::diagnostics::CodeMap;
+
+use crate::lsp::Server;
+
+pub fn configure<'a>(
+    codemap: Arc<CodeMap>,
+    c_opts: CodegenOptions,
+    z_opts: DebuggingOptions,
+    cwd: PathBuf,
+    args: &ArgMatches<'a>,
+) -> anyhow::Result<Arc<Options>> {
+    let mut options = Options::new_with_defaults(None, codemap, c_opts, z_opts, cwd, args)?;
+
+    let local_include_path = options.current_dir.join("include");
+    if local_include_path.is_dir() {
+        options.include_path.push_front(local_include_path);
+    }
+    if let Some(values) = args.values_of_os("include-paths") {
+        for value in values {
+            options.include_path.push_front(PathBuf::from(value));
+        }
+    }
+    if let Some(values) = args.values_of_os("code-paths") {
+        for value in values {
+            options.code_path.push_front(PathBuf::from(value));
+        }
+    }
+
+    Ok(Arc::new(options))
+}
+
+/// The main entry point for the 'lsp' command
+///
+/// Returns the exit code of the server
+pub fn handle_command(options: Arc<Options>, codemap: Arc<CodeMap>) -> anyhow::Result<i32> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut server = Server::new(options, codemap);
+    server.run(&mut stdin.lock(), &mut stdout.lock())
+}
@@ -1,2 +1,4 @@
 pub(crate) mod compile;
+pub(crate) mod fmt;
+pub(crate) mod lsp;
 pub(crate) mod print;
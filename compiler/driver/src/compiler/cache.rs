@@ -0,0 +1,600 @@
+//! This module implements the on-disk cache used for incremental compilation.
+//!
+//! The cache stores the bytecode of each module compiled from a source file, so that modules which
+//! have not changed since a previous build can skip straight to linking. Entries are addressed by a
+//! hash of everything which determines the bytecode of a module:
+//!
+//! * The version of the compiler
+//! * The options which affect code generation, e.g. the target, optimization level and defines
+//! * The preprocessor options of the application the module belongs to
+//! * The path and contents of the source file
+//!
+//! Files included by a module are only known once it has been parsed, so rather than being part
+//! of the key, the hash of each included file is recorded in the entry, and an entry is only used
+//! if none of them have changed.
+//!
+//! The entry also records what cross-module verification needs to know about the module, i.e. its
+//! [`ModuleSummary`], so calls between cached and compiled modules are verified on every build.
+//! Spans in the summary are recorded as offsets in the module or one of its includes, which are
+//! known to be unchanged when the entry is used.
+//!
+//! NOTE: Diagnostics are not cached, so warnings in a module are only reported when it is compiled,
+//! and calls to functions deprecated by an unchanged module are not reported.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+
+use log::debug;
+
+use firefly_bytecode::StandardByteCode;
+use firefly_bytecode::{AtomicStr, BytecodeReader, BytecodeWriter, LocalAtomTable};
+use firefly_diagnostics::{ByteIndex, CodeMap, FileName, SourceId, SourceIndex, SourceSpan, Span};
+use firefly_intern::Symbol;
+use firefly_session::{Options, OutputType};
+use firefly_syntax_base::behaviours::Callbacks;
+use firefly_syntax_base::FunctionName;
+use firefly_syntax_erl as syntax_erl;
+use firefly_syntax_erl::passes::ModuleSummary;
+
+use super::passes::term_to_tokens;
+
+const MAGIC: &str = "firefly-incremental-v2";
+
+/// A cache of the bytecode of individual modules, see the module docs for details
+pub struct Cache {
+    dir: PathBuf,
+    options: Arc<Options>,
+    codemap: Arc<CodeMap>,
+    /// The hash of the compiler version and the options which apply to all modules
+    hash: u64,
+    /// Whether cached modules may be used in place of compiling them
+    ///
+    /// This is not the case when the output of an earlier stage of compilation is requested, as
+    /// that requires every module to be parsed. Entries are still written in that case, for the
+    /// benefit of subsequent builds.
+    reuse: bool,
+}
+impl Cache {
+    /// Returns the cache to use for the current compilation, if incremental compilation is enabled
+    pub fn new(options: Arc<Options>, codemap: Arc<CodeMap>) -> Option<Self> {
+        let dir = options.incremental_dir()?;
+        let reuse = [
+            OutputType::AST,
+            OutputType::Docs,
+            OutputType::Core,
+            OutputType::Kernel,
+            OutputType::SSA,
+        ]
+        .iter()
+        .all(|ty| !options.output_types.contains_key(ty));
+
+        let mut hasher = DefaultHasher::new();
+        crate::FIREFLY_RELEASE.hash(&mut hasher);
+        crate::FIREFLY_COMMIT_HASH.hash(&mut hasher);
+        options.target.triple().hash(&mut hasher);
+        options.opt_level.hash(&mut hasher);
+        options.debug_info.hash(&mut hasher);
+        options.debug_assertions.hash(&mut hasher);
+        options.warnings_as_errors.hash(&mut hasher);
+        options.no_warn.hash(&mut hasher);
+        options.project_type.hash(&mut hasher);
+        options.include_path.hash(&mut hasher);
+        options.code_path.hash(&mut hasher);
+        options.lib_dirs.hash(&mut hasher);
+        let mut defines = options.defines.iter().collect::<Vec<_>>();
+        defines.sort();
+        defines.hash(&mut hasher);
+
+        Some(Self {
+            dir,
+            hash: hasher.finish(),
+            options,
+            codemap,
+            reuse,
+        })
+    }
+
+    /// Computes the key of the module compiled from `source`, found at `path` in `app`
+    pub fn key(&self, app: Symbol, path: &Path, source: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash.hash(&mut hasher);
+        app.as_str().get().hash(&mut hasher);
+        if let Some(opts) = self.options.erl_opts.get(&app) {
+            for (name, value) in opts.defines.iter() {
+                name.as_str().get().hash(&mut hasher);
+                let mut tokens = vec![];
+                if let Some(value) = value {
+                    term_to_tokens(value, &mut tokens);
+                }
+                for token in tokens.iter() {
+                    token.1.to_string().hash(&mut hasher);
+                }
+            }
+            opts.include_paths.hash(&mut hasher);
+        }
+        path.hash(&mut hasher);
+        source.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the module cached under `key`, if present and none of its includes have changed
+    ///
+    /// The module was compiled from `source`, found at `path`, which are added to the code map
+    /// along with its includes, as the spans in its summary refer to them.
+    pub fn get(&self, key: u64, path: &Path, source: &[u8]) -> Option<CachedModule> {
+        if !self.reuse {
+            return None;
+        }
+        let entry_path = self.path(key);
+        let bytes = fs::read(&entry_path).ok()?;
+        let codemap = self.codemap.as_ref();
+        match CachedModule::decode(&bytes, codemap, path, source) {
+            Ok(Some(module)) => Some(module),
+            Ok(None) => {
+                debug!("cached module at {} is stale", entry_path.display());
+                None
+            }
+            Err(err) => {
+                debug!(
+                    "ignoring invalid cache entry at {}: {}",
+                    entry_path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// Stores `entry` under `key`, replacing any previous entry
+    pub fn put(
+        &self,
+        key: u64,
+        entry: &CacheEntry,
+        bytecode: &StandardByteCode,
+    ) -> anyhow::Result<()> {
+        let mut buffer = Vec::new();
+        entry.encode(&mut buffer, bytecode)?;
+
+        fs::create_dir_all(&self.dir).with_context(|| {
+            format!(
+                "unable to create incremental cache directory ({})",
+                self.dir.display()
+            )
+        })?;
+        // Write to a temporary file first, so that an interrupted write is never mistaken for a
+        // valid entry, and concurrent builds never observe a partial one
+        let path = self.path(key);
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, &buffer)
+            .and_then(|_| fs::rename(&tmp, &path))
+            .with_context(|| format!("unable to write cache entry ({})", path.display()))
+    }
+
+    /// Creates the entry for `module`, parsed from the file at `path` in `app`, and its key
+    ///
+    /// Returns `None` if the module cannot be cached, i.e. it includes a virtual file
+    pub fn entry(
+        &self,
+        app: Symbol,
+        path: &Path,
+        module: &mut syntax_erl::Module,
+        callbacks: Callbacks,
+    ) -> Option<(u64, CacheEntry)> {
+        let codemap = self.codemap.as_ref();
+        let name = module.name.name;
+        let source_id = module.span.source_id();
+        // The key is derived from the source which was parsed, rather than the file as it is now,
+        // in case it changed in the meantime
+        let key = self.key(app, path, codemap.get(source_id).ok()?.source().as_bytes());
+
+        let mut includes = vec![];
+        let mut include_ids = vec![];
+        for file in codemap.iter() {
+            // A file was included by this module if its chain of parents leads to the module
+            let mut parent = file.parent();
+            let mut included = false;
+            while let Some(span) = parent {
+                if span.source_id() == source_id {
+                    included = true;
+                    break;
+                }
+                parent = codemap.parent(span.source_id());
+            }
+            if !included {
+                continue;
+            }
+            match file.name() {
+                FileName::Real(path) => {
+                    includes.push((path.clone(), hash_bytes(file.source().as_bytes())));
+                    include_ids.push((file.id(), path.clone()));
+                }
+                FileName::Virtual(_) => return None,
+            }
+        }
+        includes.sort();
+        includes.dedup();
+
+        // The same file may be included more than once, so each copy refers to its path
+        let mut files = HashMap::new();
+        files.insert(source_id, 0);
+        for (id, path) in include_ids {
+            let index = includes.iter().position(|(p, _)| *p == path).unwrap();
+            files.insert(id, index + 1);
+        }
+
+        let summary = ModuleSummary::new(module);
+
+        // Names are stored one per line, so a name containing a newline can't be represented
+        let has_newline = |s: &str| s.contains('\n');
+        let has_newline_in = |name: &Span<FunctionName>| {
+            has_newline(name.function.as_str().get())
+                || name
+                    .module
+                    .map(|m| has_newline(m.as_str().get()))
+                    .unwrap_or(false)
+        };
+        if has_newline(name.as_str().get())
+            || includes
+                .iter()
+                .any(|(path, _)| path.to_str().map(has_newline).unwrap_or(true))
+            || callbacks
+                .keys()
+                .any(|cb| has_newline(cb.function.as_str().get()))
+            || summary.functions.iter().any(has_newline_in)
+            || summary.exports.iter().any(has_newline_in)
+            || summary.calls.iter().any(has_newline_in)
+            || summary
+                .behaviours
+                .iter()
+                .any(|b| has_newline(b.as_str().get()))
+        {
+            return None;
+        }
+
+        let entry = CacheEntry {
+            name,
+            includes,
+            callbacks,
+            summary,
+            files,
+        };
+        Some((key, entry))
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}", key))
+    }
+}
+
+/// The metadata stored with the bytecode of a module
+pub struct CacheEntry {
+    /// The name of the module
+    pub name: Symbol,
+    /// The files included by the module, and the hash of their contents when it was compiled
+    pub includes: Vec<(PathBuf, u64)>,
+    /// The callbacks declared by the module, if it defines a behaviour
+    pub callbacks: Callbacks,
+    /// What cross-module verification needs to know about the module
+    pub summary: ModuleSummary,
+    /// The index of each file which spans in `summary` may refer to, i.e. 0 for the module, or
+    /// one more than the index of the include in `includes`
+    files: HashMap<SourceId, usize>,
+}
+impl CacheEntry {
+    /// Encodes this entry and the bytecode of its module
+    ///
+    /// The entry consists of a textual header, terminated by an empty line, followed by the
+    /// encoded bytecode, e.g.:
+    ///
+    /// ```text
+    /// firefly-incremental-v2
+    /// module foo
+    /// include 8d0a3c56e1f2b4a7 /path/to/foo.hrl
+    /// callback 1 0 init
+    /// option nowarn
+    /// behaviour gen_server
+    /// function 0 120 124 1 init
+    /// export 0 30 36 1 init
+    /// call 1 310 334 2 6 loggerinfo
+    ///
+    /// <bytecode>
+    /// ```
+    ///
+    /// Spans are written as the index of their file, see `files`, followed by their start and end
+    /// offsets. Calls give the length of the module name, as both names may contain spaces.
+    fn encode(&self, buffer: &mut Vec<u8>, bytecode: &StandardByteCode) -> anyhow::Result<()> {
+        writeln!(buffer, "{}", MAGIC)?;
+        writeln!(buffer, "module {}", self.name)?;
+        for (path, hash) in self.includes.iter() {
+            writeln!(buffer, "include {:016x} {}", hash, path.display())?;
+        }
+        for (callback, optional) in self.callbacks.iter() {
+            writeln!(
+                buffer,
+                "callback {} {} {}",
+                callback.arity, *optional as u8, callback.function
+            )?;
+        }
+        let summary = &self.summary;
+        if summary.no_warn {
+            writeln!(buffer, "option nowarn")?;
+        }
+        if summary.export_all {
+            writeln!(buffer, "option export_all")?;
+        }
+        for behaviour in summary.behaviours.iter() {
+            writeln!(buffer, "behaviour {}", behaviour)?;
+        }
+        for function in summary.functions.iter() {
+            let span = self.encode_span(function.span());
+            writeln!(
+                buffer,
+                "function {} {} {}",
+                span, function.arity, function.function
+            )?;
+        }
+        for export in summary.exports.iter() {
+            let span = self.encode_span(export.span());
+            writeln!(
+                buffer,
+                "export {} {} {}",
+                span, export.arity, export.function
+            )?;
+        }
+        for call in summary.calls.iter() {
+            let span = self.encode_span(call.span());
+            let module = call.module.unwrap().as_str().get();
+            writeln!(
+                buffer,
+                "call {} {} {} {}{}",
+                span,
+                call.arity,
+                module.len(),
+                module,
+                call.function
+            )?;
+        }
+        writeln!(buffer)?;
+
+        let writer = BytecodeWriter::new(buffer);
+        writer.write(bytecode)?;
+
+        Ok(())
+    }
+
+    /// Spans outside of the module and its includes are recorded as the start of the module
+    fn encode_span(&self, span: SourceSpan) -> String {
+        match self.files.get(&span.source_id()) {
+            Some(file) => format!("{} {} {}", file, span.start_index().0, span.end_index().0),
+            None => "0 0 0".to_string(),
+        }
+    }
+}
+
+/// A module loaded from the cache
+pub struct CachedModule {
+    pub name: Symbol,
+    pub callbacks: Callbacks,
+    pub summary: ModuleSummary,
+    pub bytecode: StandardByteCode,
+}
+impl CachedModule {
+    /// Decodes an entry encoded by [`CacheEntry::encode`], for the module compiled from `source`
+    /// at `path`
+    ///
+    /// Returns `Ok(None)` if any of the files included by the module have changed
+    fn decode(
+        bytes: &[u8],
+        codemap: &CodeMap,
+        path: &Path,
+        source: &[u8],
+    ) -> anyhow::Result<Option<Self>> {
+        let header_len = bytes
+            .windows(2)
+            .position(|w| w == b"\n\n")
+            .ok_or_else(|| anyhow!("missing header"))?;
+        let header = str::from_utf8(&bytes[..header_len])?;
+        let mut lines = header.lines();
+        if lines.next() != Some(MAGIC) {
+            return Err(anyhow!("unrecognized format"));
+        }
+
+        let mut name = None;
+        let mut callbacks = Callbacks::new();
+        let mut summary = ModuleSummary {
+            name: Symbol::intern(""),
+            no_warn: false,
+            export_all: false,
+            functions: vec![],
+            exports: vec![],
+            behaviours: vec![],
+            calls: vec![],
+        };
+        let source = String::from_utf8_lossy(source).into_owned();
+        let mut files = vec![codemap.add(path.to_path_buf(), source)];
+        for line in lines {
+            let (kind, rest) = line
+                .split_once(' ')
+                .ok_or_else(|| anyhow!("invalid header line '{}'", line))?;
+            match kind {
+                "module" => name = Some(Symbol::intern(rest)),
+                "include" => {
+                    let (hash, path) = rest
+                        .split_once(' ')
+                        .ok_or_else(|| anyhow!("invalid include '{}'", rest))?;
+                    let hash = u64::from_str_radix(hash, 16)?;
+                    match fs::read(path) {
+                        Ok(contents) if hash_bytes(&contents) == hash => {
+                            let contents = String::from_utf8_lossy(&contents).into_owned();
+                            files.push(codemap.add(PathBuf::from(path), contents));
+                        }
+                        _ => return Ok(None),
+                    }
+                }
+                "callback" => {
+                    let mut parts = rest.splitn(3, ' ');
+                    let (Some(arity), Some(optional), Some(function)) =
+                        (parts.next(), parts.next(), parts.next())
+                    else {
+                        return Err(anyhow!("invalid callback '{}'", rest));
+                    };
+                    let name = FunctionName::new_local(Symbol::intern(function), arity.parse()?);
+                    callbacks.insert(name, optional == "1");
+                }
+                "option" => match rest {
+                    "nowarn" => summary.no_warn = true,
+                    "export_all" => summary.export_all = true,
+                    _ => return Err(anyhow!("invalid option '{}'", rest)),
+                },
+                "behaviour" => summary.behaviours.push(Symbol::intern(rest)),
+                "function" | "export" | "call" => {
+                    let mut parts = rest.splitn(5, ' ');
+                    let (Some(file), Some(start), Some(end), Some(arity), Some(rest)) = (
+                        parts.next(),
+                        parts.next(),
+                        parts.next(),
+                        parts.next(),
+                        parts.next(),
+                    ) else {
+                        return Err(anyhow!("invalid {} '{}'", kind, rest));
+                    };
+                    let source_id = *files
+                        .get(file.parse::<usize>()?)
+                        .ok_or_else(|| anyhow!("invalid file index in '{}'", line))?;
+                    let span = SourceSpan::new(
+                        SourceIndex::new(source_id, ByteIndex(start.parse()?)),
+                        SourceIndex::new(source_id, ByteIndex(end.parse()?)),
+                    );
+                    let arity = arity.parse()?;
+                    if kind == "call" {
+                        let (len, names) = rest
+                            .split_once(' ')
+                            .ok_or_else(|| anyhow!("invalid call '{}'", rest))?;
+                        let len = len.parse::<usize>()?;
+                        if !names.is_char_boundary(len) {
+                            return Err(anyhow!("invalid call '{}'", rest));
+                        }
+                        let (module, function) = names.split_at(len);
+                        let name = FunctionName::new(
+                            Symbol::intern(module),
+                            Symbol::intern(function),
+                            arity,
+                        );
+                        summary.calls.push(Span::new(span, name));
+                        continue;
+                    }
+                    let name =
+                        Span::new(span, FunctionName::new_local(Symbol::intern(rest), arity));
+                    if kind == "function" {
+                        summary.functions.push(name);
+                    } else {
+                        summary.exports.push(name);
+                    }
+                }
+                _ => return Err(anyhow!("invalid header line '{}'", line)),
+            }
+        }
+        let name = name.ok_or_else(|| anyhow!("missing module name"))?;
+        summary.name = name;
+
+        let reader = BytecodeReader::<AtomicStr, LocalAtomTable>::new(&bytes[(header_len + 2)..]);
+        let bytecode = reader
+            .read()
+            .map_err(|err| anyhow!("invalid bytecode: {:?}", err))?;
+
+        Ok(Some(Self {
+            name,
+            callbacks,
+            summary,
+            bytecode,
+        }))
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_bytecode::Builder;
+
+    use super::*;
+
+    #[test]
+    fn cache_entry_invalidation_test() {
+        let dir = std::env::temp_dir().join(format!("firefly-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("foo.erl");
+        let include = dir.join("foo.hrl");
+        fs::write(&include, "-define(FOO, 1).\n").unwrap();
+
+        let codemap = CodeMap::new();
+        let source_id = codemap.add(source.clone(), "-module(foo).\n".to_string());
+        let include_id = codemap.add(include.clone(), "-define(FOO, 1).\n".to_string());
+        let span = |id, start, end| {
+            SourceSpan::new(
+                SourceIndex::new(id, ByteIndex(start)),
+                SourceIndex::new(id, ByteIndex(end)),
+            )
+        };
+
+        let mut callbacks = Callbacks::new();
+        callbacks.insert(FunctionName::new_local(Symbol::intern("init"), 1), false);
+        callbacks.insert(
+            FunctionName::new_local(Symbol::intern("code change"), 3),
+            true,
+        );
+        let init = FunctionName::new_local(Symbol::intern("init"), 1);
+        let summary = ModuleSummary {
+            name: Symbol::intern("foo"),
+            no_warn: false,
+            export_all: true,
+            functions: vec![Span::new(span(source_id, 2, 6), init)],
+            exports: vec![Span::new(span(source_id, 8, 12), init)],
+            behaviours: vec![Symbol::intern("gen_server")],
+            calls: vec![Span::new(
+                span(include_id, 1, 7),
+                FunctionName::new(Symbol::intern("a b"), Symbol::intern("c d"), 2),
+            )],
+        };
+        let entry = CacheEntry {
+            name: Symbol::intern("foo"),
+            includes: vec![(include.clone(), hash_bytes(b"-define(FOO, 1).\n"))],
+            callbacks: callbacks.clone(),
+            summary: summary.clone(),
+            files: HashMap::from([(source_id, 0), (include_id, 1)]),
+        };
+        let bytecode = Builder::new(StandardByteCode::default()).finish();
+        let mut buffer = Vec::new();
+        entry.encode(&mut buffer, &bytecode).unwrap();
+
+        let module = CachedModule::decode(&buffer, &codemap, &source, b"-module(foo).\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(module.name, entry.name);
+        assert_eq!(module.callbacks, callbacks);
+        assert_eq!(module.summary, summary);
+        assert_eq!(module.summary.calls[0].span(), span(include_id, 1, 7));
+        assert_eq!(module.bytecode.code.len(), bytecode.code.len());
+
+        // Changing an included file makes the entry stale
+        fs::write(&include, "-define(FOO, 2).\n").unwrap();
+        assert!(
+            CachedModule::decode(&buffer, &codemap, &source, b"-module(foo).\n")
+                .unwrap()
+                .is_none()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,25 +1,31 @@
+mod cache;
 pub mod passes;
+mod release;
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{bail, Context};
 
-use log::debug;
+use log::{debug, warn};
 
 use rayon::prelude::*;
 
+use firefly_bytecode::StandardByteCode;
 use firefly_diagnostics::{CodeMap, FileName};
-use firefly_intern::Symbol;
+use firefly_intern::{Ident, Symbol};
 use firefly_linker::{AppArtifacts, ProjectInfo};
 use firefly_pass::Pass;
 use firefly_session::{Input, InputType, Options, OutputType};
-use firefly_syntax_base::ApplicationMetadata;
+use firefly_syntax_base::behaviours::{self, Callbacks};
+use firefly_syntax_base::{ApplicationMetadata, FunctionName, ModuleMetadata};
+use firefly_syntax_erl::passes::ModuleSummary;
 use firefly_util::diagnostics::DiagnosticsHandler;
 use firefly_util::emit::Emit;
 
-use self::passes::{ParsePipeline, PreCodegenPipeline};
+use self::cache::{Cache, CacheEntry};
+use self::passes::{LowerSsa, ParsePipeline, PreCodegenPipeline};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ErrorReported;
@@ -108,19 +114,61 @@ impl BytecodeCompiler {
     fn parse(
         &self,
         inputs: HashMap<Symbol, Vec<FileName>>,
+        cache: Option<&Cache>,
     ) -> anyhow::Result<BTreeMap<Symbol, ParsedApp>> {
         let pipeline = ParsePipeline::new(
             self.options.clone(),
             self.codemap.clone(),
             self.diagnostics.clone(),
         );
+        // The release metadata is compiled as part of the root application
+        let release = release::generate(&self.options, self.codemap.clone(), &self.diagnostics)?;
+        let root = self.options.app.name;
         inputs
             .into_par_iter()
             .panic_fuse()
             .map_with(pipeline, |pipeline, (app, files)| {
-                parse(pipeline, app, files)
+                let generated = if app == root { release.clone() } else { None };
+                parse(&mut pipeline.for_app(app), cache, app, files, generated)
             })
-            .collect()
+            .collect::<anyhow::Result<_>>()
+            .map(|mut parsed| {
+                resolve_behaviours(&self.options, &mut parsed);
+                parsed
+            })
+    }
+
+    /// Verifies calls between all of the modules being compiled, see `Xref`
+    ///
+    /// This is only meaningful when all of the modules in a program are present, so unless it is
+    /// explicitly requested, it only runs when building an executable. Modules loaded from the
+    /// cache take part through the summary stored with them.
+    fn xref(&self, parsed: &mut BTreeMap<Symbol, ParsedApp>) -> anyhow::Result<()> {
+        use firefly_syntax_erl::passes::{Program, Xref};
+
+        if !self.options.debugging_opts.xref && !self.options.project_type.is_executable() {
+            return Ok(());
+        }
+
+        let apps = parsed
+            .values_mut()
+            .map(|app| {
+                let mut modules = app
+                    .modules
+                    .iter_mut()
+                    .map(|m| ModuleSummary::new(&mut m.output))
+                    .collect::<Vec<_>>();
+                modules.extend(app.summaries.drain(..));
+                (app.metadata.as_ref(), modules)
+            })
+            .collect();
+        let program = Program {
+            app: self.options.app.name,
+            apps,
+        };
+        Xref::new(&self.diagnostics).run(program)?;
+
+        Ok(())
     }
 
     fn lower(
@@ -157,6 +205,24 @@ impl BytecodeCompiler {
                 },
             )
     }
+
+    /// Builds the bytecode of each module, see `LowerSsa`
+    fn build_bytecode(
+        &self,
+        lowered: Vec<Artifact<firefly_syntax_ssa::Module>>,
+    ) -> anyhow::Result<Vec<(Symbol, StandardByteCode)>> {
+        debug!("building bytecode for {} modules", lowered.len());
+
+        lowered
+            .into_par_iter()
+            .panic_fuse()
+            .map(|artifact| {
+                let name = artifact.output.name();
+                let mut pass = LowerSsa::new(&self.diagnostics, &self.codemap);
+                pass.run(artifact).map(|bytecode| (name, bytecode))
+            })
+            .collect()
+    }
 }
 impl Pass for BytecodeCompiler {
     type Input<'a> = HashMap<Symbol, Vec<FileName>>;
@@ -166,7 +232,7 @@ impl Pass for BytecodeCompiler {
         use self::passes::CompileBytecode;
 
         if self.options.debugging_opts.parse_only {
-            self.parse(inputs)?;
+            self.parse(inputs, None)?;
             return Ok(AppArtifacts {
                 name: self.options.app.name,
                 modules: vec![],
@@ -175,7 +241,8 @@ impl Pass for BytecodeCompiler {
         }
 
         if self.options.debugging_opts.analyze_only {
-            let parsed = self.parse(inputs)?;
+            let mut parsed = self.parse(inputs, None)?;
+            self.xref(&mut parsed)?;
             self.lower(parsed)?;
             return Ok(AppArtifacts {
                 name: self.options.app.name,
@@ -184,15 +251,36 @@ impl Pass for BytecodeCompiler {
             });
         }
 
-        let parsed = self.parse(inputs)?;
+        let cache = Cache::new(self.options.clone(), self.codemap.clone());
+        let mut parsed = self.parse(inputs, cache.as_ref())?;
+        let mut cached = vec![];
+        let mut pending = HashMap::new();
+        for app in parsed.values_mut() {
+            cached.append(&mut app.cached);
+            for (key, entry) in app.pending.drain(..) {
+                pending.insert(entry.name, (key, entry));
+            }
+        }
+        self.xref(&mut parsed)?;
         let lowered = self.lower(parsed)?;
+        let modules = self.build_bytecode(lowered)?;
 
-        let mut codegen = CompileBytecode::new(
-            self.options.clone(),
-            self.codemap.clone(),
-            self.diagnostics.clone(),
-        );
-        let compiled = codegen.run(lowered)?;
+        if let Some(cache) = cache.as_ref() {
+            for (name, bytecode) in modules.iter() {
+                let Some((key, entry)) = pending.get(name) else { continue };
+                if let Err(err) = cache.put(*key, entry, bytecode) {
+                    warn!("unable to cache bytecode for {}: {:#}", name, err);
+                }
+            }
+        }
+        let mut modules = modules
+            .into_iter()
+            .map(|(_, bytecode)| bytecode)
+            .collect::<Vec<_>>();
+        modules.append(&mut cached);
+
+        let mut codegen = CompileBytecode::new(self.options.clone(), self.diagnostics.clone());
+        let compiled = codegen.run(modules)?;
 
         Ok(AppArtifacts {
             name: self.options.app.name,
@@ -205,12 +293,20 @@ impl Pass for BytecodeCompiler {
 pub struct ParsedApp {
     pub metadata: Arc<ApplicationMetadata>,
     pub modules: Vec<Artifact<firefly_syntax_erl::Module>>,
+    /// The bytecode of modules which are unchanged since they were last compiled
+    pub cached: Vec<StandardByteCode>,
+    /// The summaries of the modules in `cached`, for cross-module verification
+    pub summaries: Vec<ModuleSummary>,
+    /// The cache entries to write once the bytecode of each parsed module is built
+    pub pending: Vec<(u64, CacheEntry)>,
 }
 
 fn parse(
     pipeline: &mut ParsePipeline,
+    cache: Option<&Cache>,
     app: Symbol,
     files: Vec<FileName>,
+    generated: Option<Input>,
 ) -> anyhow::Result<(Symbol, ParsedApp)> {
     // Load all of the inputs
     let mut inputs: Vec<Input> = files.into_par_iter().panic_fuse().map(read_input).reduce(
@@ -225,20 +321,47 @@ fn parse(
             }
         },
     )?;
+    inputs.extend(generated);
 
     let mut app_metadata = ApplicationMetadata {
         name: app,
         modules: BTreeMap::default(),
+        behaviours: BTreeMap::default(),
     };
     let mut modules = Vec::with_capacity(inputs.len());
+    let mut cached = vec![];
+    let mut summaries = vec![];
+    let mut pending = vec![];
 
     for input in inputs.drain(..) {
+        // Only Erlang sources are cached, as they are the only inputs with includes to track
+        let cache = cache.filter(|_| matches!(input.get_type(), InputType::Erlang));
+        if let (Some(cache), Input::File(path)) = (cache, &input) {
+            let module = std::fs::read(path).ok().and_then(|source| {
+                let key = cache.key(app, path, &source);
+                cache.get(key, path, &source)
+            });
+            if let Some(module) = module {
+                debug!("using cached bytecode for {}", module.name);
+                let mut metadata = ModuleMetadata::new(Ident::with_empty_span(module.name));
+                metadata.callbacks = module.callbacks;
+                app_metadata.modules.insert(module.name, metadata);
+                cached.push(module.bytecode);
+                summaries.push(module.summary);
+                continue;
+            }
+        }
+
         match pipeline.run(input) {
             Ok(Artifact {
                 input,
-                output: module,
+                output: mut module,
                 metadata,
             }) => {
+                if let (Some(cache), Input::File(path)) = (cache, &input) {
+                    let callbacks = metadata.callbacks.clone();
+                    pending.extend(cache.entry(app, path, &mut module, callbacks));
+                }
                 app_metadata.modules.insert(module.name.name, metadata);
                 modules.push(Artifact {
                     input,
@@ -253,11 +376,107 @@ fn parse(
     let result = ParsedApp {
         metadata: Arc::new(app_metadata),
         modules,
+        cached,
+        summaries,
+        pending,
     };
 
     Ok((app, result))
 }
 
+/// Resolves the callbacks of behaviours used by each application, but not defined in it
+///
+/// Behaviours defined by another application in this compilation take precedence over those found
+/// on the code path, while the standard OTP behaviours are known to the compiler and are skipped.
+fn resolve_behaviours(options: &Options, parsed: &mut BTreeMap<Symbol, ParsedApp>) {
+    let mut resolved: BTreeMap<Symbol, Option<Callbacks>> = BTreeMap::new();
+    for app in parsed.values() {
+        for (name, module) in app.metadata.modules.iter() {
+            if !module.callbacks.is_empty() {
+                resolved.insert(*name, Some(module.callbacks.clone()));
+            }
+        }
+    }
+
+    for app in parsed.values_mut() {
+        let used = app
+            .modules
+            .iter()
+            .flat_map(|artifact| artifact.output.behaviours.iter().map(|b| b.name))
+            .collect::<BTreeSet<_>>();
+        for behaviour in used {
+            let is_local = app
+                .metadata
+                .modules
+                .get(&behaviour)
+                .map(|m| !m.callbacks.is_empty())
+                .unwrap_or(false);
+            if is_local || behaviours::get(behaviour).is_some() {
+                continue;
+            }
+            let callbacks = resolved
+                .entry(behaviour)
+                .or_insert_with(|| load_behaviour_callbacks(&options.code_path, behaviour));
+            if let Some(callbacks) = callbacks {
+                Arc::make_mut(&mut app.metadata)
+                    .behaviours
+                    .insert(behaviour, callbacks.clone());
+            }
+        }
+    }
+}
+
+/// Loads the callbacks of a behaviour from its BEAM file on the code path, if present
+///
+/// NOTE: This requires the BEAM file to have been compiled with debug info, as the callbacks are
+/// read from the abstract code.
+pub(crate) fn load_behaviour_callbacks(
+    code_path: &VecDeque<PathBuf>,
+    behaviour: Symbol,
+) -> Option<Callbacks> {
+    use firefly_beam::ast::Form;
+    use firefly_beam::AbstractCode;
+
+    let filename = format!("{}.beam", behaviour);
+    for dir in code_path.iter() {
+        let path = dir.join(&filename);
+        if !path.is_file() {
+            continue;
+        }
+        match AbstractCode::from_beam_file(&path) {
+            Ok(code) => {
+                let mut callbacks = Callbacks::new();
+                for form in code.forms.iter() {
+                    match form {
+                        Form::Callback(cb) => {
+                            let name = FunctionName::new_local(cb.name.name, cb.name.arity);
+                            callbacks.entry(name).or_insert(false);
+                        }
+                        Form::OptionalCallbacks(attr) => {
+                            for fun in attr.funs.iter() {
+                                let name = FunctionName::new_local(fun.name, fun.arity);
+                                callbacks.insert(name, true);
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                return Some(callbacks);
+            }
+            Err(err) => {
+                debug!(
+                    "unable to read callbacks of behaviour {} from {}: {}",
+                    behaviour,
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+
+    None
+}
+
 #[cfg(feature = "native-compilation")]
 impl Compiler {
     pub fn lower(
@@ -306,7 +525,6 @@ pub(super) fn mlir_context(
     Arc::new(OwnedContext::new(options, diagnostics))
 }
 
-#[allow(unused)]
 pub fn maybe_emit_file_with_callback_and_opts<F>(
     options: &Options,
     input: &Input,
@@ -0,0 +1,191 @@
+//! Writes weak references to undefined functions in COFF objects
+//!
+//! On COFF, a weak reference is a weak external, a symbol followed by an auxiliary record naming
+//! the symbol it resolves to if left undefined. `object` writes weak undefined symbols as strong
+//! ones instead, so each weak function is added as a strong symbol, followed by a placeholder for
+//! the auxiliary record and its default, a null absolute symbol. Once the object is written, the
+//! symbol is then marked as a weak external, and the placeholder replaced by the auxiliary record,
+//! which leaves the index of every other symbol, and so every relocation, unchanged.
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail};
+use object::pe;
+use object::write::{Object, Symbol, SymbolId, SymbolSection};
+use object::{SymbolFlags, SymbolKind, SymbolScope};
+
+/// The name of the symbol replaced by the auxiliary record of a weak external
+const PLACEHOLDER: &[u8] = b".weak";
+
+/// Adds a weak reference to the function `name`, see the module docs
+pub(super) fn add_weak_function(file: &mut Object, name: &str) -> SymbolId {
+    let symbol = file.add_symbol(Symbol {
+        name: name.as_bytes().to_vec(),
+        value: 0,
+        size: 0,
+        kind: SymbolKind::Text,
+        scope: SymbolScope::Dynamic,
+        weak: false,
+        section: SymbolSection::Undefined,
+        flags: SymbolFlags::None,
+    });
+    file.add_symbol(null_symbol(PLACEHOLDER.to_vec()));
+    file.add_symbol(null_symbol(format!(".weak.{}.default", name).into_bytes()));
+    symbol
+}
+
+fn null_symbol(name: Vec<u8>) -> Symbol {
+    Symbol {
+        name,
+        value: 0,
+        size: 0,
+        kind: SymbolKind::Data,
+        scope: SymbolScope::Compilation,
+        weak: false,
+        section: SymbolSection::Absolute,
+        flags: SymbolFlags::None,
+    }
+}
+
+/// Makes the references to `names` in the COFF object `bytes` weak, see the module docs
+pub(super) fn write_weak_externals<'a, I>(bytes: &mut [u8], names: I) -> anyhow::Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut names = names.into_iter().map(str::as_bytes).collect::<HashSet<_>>();
+    let symtab = read_u32(bytes, 8)? as usize;
+    let count = read_u32(bytes, 12)? as usize;
+    let strtab = symtab + count * pe::IMAGE_SIZEOF_SYMBOL;
+
+    let mut index = 0;
+    while index < count && !names.is_empty() {
+        let offset = symtab + index * pe::IMAGE_SIZEOF_SYMBOL;
+        if !names.remove(symbol_name(bytes, offset, strtab)?) {
+            index += 1 + bytes[offset + 17] as usize;
+            continue;
+        }
+        let placeholder = offset + pe::IMAGE_SIZEOF_SYMBOL;
+        if index + 2 >= count || symbol_name(bytes, placeholder, strtab)? != PLACEHOLDER {
+            bail!("invalid weak external at symbol {}", index);
+        }
+
+        let (symbol, aux) = bytes[offset..(placeholder + pe::IMAGE_SIZEOF_SYMBOL)]
+            .split_at_mut(pe::IMAGE_SIZEOF_SYMBOL);
+        symbol[16] = pe::IMAGE_SYM_CLASS_WEAK_EXTERNAL;
+        symbol[17] = 1;
+        // The default follows the placeholder
+        aux.fill(0);
+        aux[..4].copy_from_slice(&((index + 2) as u32).to_le_bytes());
+        aux[4..8].copy_from_slice(&(pe::IMAGE_WEAK_EXTERN_SEARCH_ALIAS as u32).to_le_bytes());
+        index += 3;
+    }
+
+    match names.into_iter().next() {
+        None => Ok(()),
+        Some(name) => bail!(
+            "missing symbol for weak external '{}'",
+            String::from_utf8_lossy(name)
+        ),
+    }
+}
+
+/// Returns the name of the symbol at `offset`, which is either stored inline, or in the string
+/// table at `strtab` if longer than 8 bytes
+fn symbol_name(bytes: &[u8], offset: usize, strtab: usize) -> anyhow::Result<&[u8]> {
+    let name = bytes
+        .get(offset..(offset + 8))
+        .ok_or_else(|| anyhow!("invalid symbol table"))?;
+    if name[..4] != [0; 4] {
+        let len = name.iter().position(|b| *b == 0).unwrap_or(8);
+        return Ok(&name[..len]);
+    }
+    let start = strtab + read_u32(bytes, offset + 4)? as usize;
+    let name = bytes
+        .get(start..)
+        .ok_or_else(|| anyhow!("invalid string table"))?;
+    let len = name
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| anyhow!("invalid string table"))?;
+    Ok(&name[..len])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let bytes = bytes
+        .get(offset..(offset + 4))
+        .ok_or_else(|| anyhow!("invalid object"))?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod test {
+    use object::write::Relocation;
+    use object::{
+        Architecture, BinaryFormat, Endianness, RelocationEncoding, RelocationKind, SectionKind,
+    };
+
+    use super::*;
+
+    #[test]
+    fn weak_external_test() {
+        let mut file = Object::new(BinaryFormat::Coff, Architecture::X86_64, Endianness::Little);
+        let strong = file.add_symbol(Symbol {
+            name: b"erlang:display/1".to_vec(),
+            value: 0,
+            size: 0,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Dynamic,
+            weak: false,
+            section: SymbolSection::Undefined,
+            flags: SymbolFlags::None,
+        });
+        let weak = add_weak_function(&mut file, "lists:reverse/2");
+        let section = file.add_section(vec![], b".data".to_vec(), SectionKind::Data);
+        file.append_section_data(section, &[0; 16], 8);
+        for (offset, symbol) in [(0, strong), (8, weak)] {
+            file.add_relocation(
+                section,
+                Relocation {
+                    offset,
+                    size: 64,
+                    kind: RelocationKind::Absolute,
+                    encoding: RelocationEncoding::Generic,
+                    symbol,
+                    addend: 0,
+                },
+            )
+            .unwrap();
+        }
+        let mut bytes = file.write().unwrap();
+        write_weak_externals(&mut bytes, ["lists:reverse/2"]).unwrap();
+
+        let symtab = read_u32(&bytes, 8).unwrap() as usize;
+        let count = read_u32(&bytes, 12).unwrap() as usize;
+        let strtab = symtab + count * pe::IMAGE_SIZEOF_SYMBOL;
+        let symbol = |index: usize| {
+            let offset = symtab + index * pe::IMAGE_SIZEOF_SYMBOL;
+            &bytes[offset..(offset + pe::IMAGE_SIZEOF_SYMBOL)]
+        };
+        let index = (0..count)
+            .find(|index| {
+                let offset = symtab + index * pe::IMAGE_SIZEOF_SYMBOL;
+                symbol_name(&bytes, offset, strtab).unwrap() == b"lists:reverse/2"
+            })
+            .unwrap();
+        assert_eq!(symbol(index)[16], pe::IMAGE_SYM_CLASS_WEAK_EXTERNAL);
+        assert_eq!(symbol(index)[17], 1);
+        let aux = symbol(index + 1);
+        assert_eq!(aux[..4], ((index + 2) as u32).to_le_bytes());
+        assert_eq!(
+            aux[4..8],
+            (pe::IMAGE_WEAK_EXTERN_SEARCH_ALIAS as u32).to_le_bytes()
+        );
+        let default = symtab + (index + 2) * pe::IMAGE_SIZEOF_SYMBOL;
+        assert_eq!(
+            symbol_name(&bytes, default, strtab).unwrap(),
+            b".weak.lists:reverse/2.default"
+        );
+
+        // Only functions added by `add_weak_function` can be made weak
+        assert!(write_weak_externals(&mut bytes, ["erlang:display/1"]).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+//! Writes the native object containing the bytecode image of a program
+//!
+//! The object linked in to a program compiled to bytecode contains no code, only data: the
+//! encoded bytecode and its size, and the atom and dispatch tables read by the runtime on boot,
+//! the latter referring to the natively-implemented functions called from bytecode. As there is
+//! nothing to generate code for, the object is written directly in the object file format of the
+//! target, using the `object` crate for ELF, Mach-O and COFF, and [`wasm`] for WebAssembly, which
+//! `object` is unable to write. Where `object` falls short on the other formats, the object it
+//! writes is patched afterwards, see [`coff`] and `ObjectBuilder::finish`.
+mod coff;
+mod wasm;
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail};
+use object::write::{Comdat, Object, Relocation, StandardSection, Symbol, SymbolId, SymbolSection};
+use object::{
+    elf, macho, Architecture, BinaryFormat, ComdatKind, FileFlags, RelocationEncoding,
+    RelocationKind, SectionKind, SymbolFlags, SymbolKind, SymbolScope,
+};
+
+use firefly_target::{Endianness, Target};
+
+/// How a function referenced by the image is linked
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Linkage {
+    /// The function must be defined at link time
+    External,
+    /// The function may be left undefined at link time, in which case references to it are null
+    ExternalWeak,
+}
+
+/// The section in which a global is placed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Section {
+    /// The default section for read-only data
+    ReadOnlyData,
+    /// The named data section, whose bounds are used by the runtime to find its contents
+    ///
+    /// On Mach-O, the section is placed in the `__DATA` segment.
+    Named(&'static str),
+}
+
+/// The initializer of a global, built up field by field according to the layout rules of C
+pub struct Data {
+    bytes: Vec<u8>,
+    /// The offsets at which pointers to other symbols are stored, and the names of those symbols
+    relocations: Vec<(usize, String)>,
+    pointer_size: usize,
+    big_endian: bool,
+}
+impl Data {
+    /// Appends raw bytes
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Appends an 8-bit integer
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    /// Appends a pointer-sized integer
+    pub fn usize(&mut self, value: u64) {
+        self.align(self.pointer_size);
+        if self.big_endian {
+            self.bytes
+                .extend_from_slice(&value.to_be_bytes()[(8 - self.pointer_size)..]);
+        } else {
+            self.bytes
+                .extend_from_slice(&value.to_le_bytes()[..self.pointer_size]);
+        }
+    }
+
+    /// Appends a pointer to `symbol`, which must be a global or function of the same object
+    pub fn pointer(&mut self, symbol: &str) {
+        self.align(self.pointer_size);
+        self.relocations
+            .push((self.bytes.len(), symbol.to_string()));
+        self.bytes.resize(self.bytes.len() + self.pointer_size, 0);
+    }
+
+    fn align(&mut self, align: usize) {
+        let len = self.bytes.len();
+        self.bytes.resize(len.div_ceil(align) * align, 0);
+    }
+}
+
+/// A global defined by the image
+///
+/// Every global is link-once, i.e. identical definitions in other objects are merged with it.
+/// This is relied on for atoms, as those known to the runtime are also defined by it.
+struct Global {
+    name: String,
+    section: Section,
+    align: u64,
+    data: Data,
+}
+
+/// Builds the native object for the bytecode image of a program, see the module docs
+pub struct ObjectBuilder<'a> {
+    target: &'a Target,
+    globals: Vec<Global>,
+    functions: Vec<(String, Linkage)>,
+}
+impl<'a> ObjectBuilder<'a> {
+    pub fn new(target: &'a Target) -> Self {
+        Self {
+            target,
+            globals: vec![],
+            functions: vec![],
+        }
+    }
+
+    /// Returns an empty initializer for a global of this object
+    pub fn data(&self) -> Data {
+        Data {
+            bytes: vec![],
+            relocations: vec![],
+            pointer_size: self.target.pointer_width / 8,
+            // Every architecture we support is little-endian unless stated otherwise
+            big_endian: self.target.options.endianness == Endianness::Big,
+        }
+    }
+
+    /// Defines a global named `name` in `section`, initialized with `data`
+    pub fn define_global(&mut self, name: String, section: Section, align: u64, data: Data) {
+        self.globals.push(Global {
+            name,
+            section,
+            align,
+            data,
+        });
+    }
+
+    /// Declares an external function named `name`, so that pointers to it can be stored in globals
+    pub fn declare_function(&mut self, name: String, linkage: Linkage) {
+        self.functions.push((name, linkage));
+    }
+
+    /// Writes the object in the object file format of the target
+    pub fn finish(self) -> anyhow::Result<Vec<u8>> {
+        let options = &self.target.options;
+        if options.is_like_wasm {
+            return wasm::write(&self);
+        }
+
+        let format = if options.is_like_osx {
+            BinaryFormat::MachO
+        } else if options.is_like_windows {
+            BinaryFormat::Coff
+        } else {
+            BinaryFormat::Elf
+        };
+        let architecture = match self.target.arch.as_ref() {
+            "x86_64" => Architecture::X86_64,
+            "x86" => Architecture::I386,
+            "aarch64" => Architecture::Aarch64,
+            // Relocations are not implemented by `object` for 32-bit ARM on Mach-O, but the only one
+            // used here, an absolute pointer, is encoded as it is for x86, so the object is
+            // written for x86, and then marked as being for ARM
+            "arm" if format == BinaryFormat::MachO => Architecture::I386,
+            "arm" => Architecture::Arm,
+            "powerpc" => Architecture::PowerPc,
+            "powerpc64" => Architecture::PowerPc64,
+            "riscv32" => Architecture::Riscv32,
+            "riscv64" => Architecture::Riscv64,
+            "s390x" => Architecture::S390x,
+            arch => bail!(
+                "unable to write bytecode object for target '{}': unsupported architecture '{}'",
+                self.target.triple(),
+                arch
+            ),
+        };
+        let endian = if options.endianness == Endianness::Big {
+            object::Endianness::Big
+        } else {
+            object::Endianness::Little
+        };
+
+        let mut file = Object::new(format, architecture, endian);
+        match format {
+            // Allows the linker to merge link-once globals, see `Global`
+            BinaryFormat::MachO => {
+                file.flags = FileFlags::MachO {
+                    flags: macho::MH_SUBSECTIONS_VIA_SYMBOLS,
+                };
+            }
+            // Marks the stack as non-executable, as it is otherwise assumed to be
+            BinaryFormat::Elf => {
+                file.flags = FileFlags::Elf {
+                    os_abi: 0,
+                    abi_version: 0,
+                    e_flags: self.elf_flags(),
+                };
+                file.add_section(vec![], b".note.GNU-stack".to_vec(), SectionKind::Other);
+            }
+            _ => (),
+        }
+
+        // Symbols must be defined before relocations referring to them are added
+        let mut symbols = HashMap::<&str, SymbolId>::new();
+        for (name, linkage) in self.functions.iter() {
+            if *linkage == Linkage::ExternalWeak && format == BinaryFormat::Coff {
+                symbols.insert(name.as_str(), coff::add_weak_function(&mut file, name));
+                continue;
+            }
+            let symbol = file.add_symbol(Symbol {
+                name: name.as_bytes().to_vec(),
+                value: 0,
+                size: 0,
+                kind: SymbolKind::Text,
+                scope: SymbolScope::Dynamic,
+                weak: *linkage == Linkage::ExternalWeak,
+                section: SymbolSection::Undefined,
+                flags: SymbolFlags::None,
+            });
+            symbols.insert(name.as_str(), symbol);
+        }
+        let mut named_sections = HashMap::new();
+        let mut placements = Vec::with_capacity(self.globals.len());
+        for global in self.globals.iter() {
+            let symbol = file.add_symbol(Symbol {
+                name: global.name.as_bytes().to_vec(),
+                value: 0,
+                size: 0,
+                kind: SymbolKind::Data,
+                scope: SymbolScope::Dynamic,
+                // Link-once globals are weak definitions, except on COFF, where that is only
+                // expressed by placing them in a COMDAT section
+                weak: format != BinaryFormat::Coff,
+                section: SymbolSection::Undefined,
+                flags: SymbolFlags::None,
+            });
+            let bytes = global.data.bytes.as_slice();
+            let (section, offset) = match global.section {
+                Section::ReadOnlyData => file.add_subsection(
+                    StandardSection::ReadOnlyData,
+                    global.name.as_bytes(),
+                    bytes,
+                    global.align,
+                ),
+                // On Mach-O, link-once globals are merged via subsections, elsewhere each one
+                // is placed in a section of its own, which can be discarded as part of a COMDAT
+                Section::Named(name) if format == BinaryFormat::MachO => {
+                    let section = *named_sections.entry(name).or_insert_with(|| {
+                        file.add_section(
+                            b"__DATA".to_vec(),
+                            name.as_bytes().to_vec(),
+                            SectionKind::Data,
+                        )
+                    });
+                    let offset = file.append_section_data(section, bytes, global.align);
+                    (section, offset)
+                }
+                Section::Named(name) => {
+                    let section =
+                        file.add_section(vec![], name.as_bytes().to_vec(), SectionKind::Data);
+                    let offset = file.append_section_data(section, bytes, global.align);
+                    (section, offset)
+                }
+            };
+            file.set_symbol_data(symbol, section, offset, bytes.len() as u64);
+            // On COFF, the sections of a COMDAT are identified by their section symbols
+            if format == BinaryFormat::Coff {
+                file.section_symbol(section);
+            }
+            if format != BinaryFormat::MachO {
+                file.add_comdat(Comdat {
+                    kind: ComdatKind::Any,
+                    symbol,
+                    sections: vec![section],
+                });
+            }
+            symbols.insert(global.name.as_str(), symbol);
+            placements.push((section, offset));
+        }
+
+        let size = self.target.pointer_width as u8;
+        for (global, (section, offset)) in self.globals.iter().zip(placements) {
+            for (field, target) in global.data.relocations.iter() {
+                let symbol = *symbols
+                    .get(target.as_str())
+                    .ok_or_else(|| anyhow!("reference to undefined symbol '{}'", target))?;
+                file.add_relocation(
+                    section,
+                    Relocation {
+                        offset: offset + *field as u64,
+                        size,
+                        kind: RelocationKind::Absolute,
+                        encoding: RelocationEncoding::Generic,
+                        symbol,
+                        addend: 0,
+                    },
+                )?;
+            }
+        }
+
+        let mut bytes = file.write()?;
+        match format {
+            BinaryFormat::Coff => {
+                let weak = self
+                    .functions
+                    .iter()
+                    .filter(|(_, linkage)| *linkage == Linkage::ExternalWeak)
+                    .map(|(name, _)| name.as_str());
+                coff::write_weak_externals(&mut bytes, weak)?;
+            }
+            // The object was written for x86, see above, so `cputype` and `cpusubtype` are patched
+            BinaryFormat::MachO if self.target.arch == "arm" => {
+                bytes[4..8].copy_from_slice(&macho::CPU_TYPE_ARM.to_le_bytes());
+                bytes[8..12].copy_from_slice(&self.arm_cpu_subtype().to_le_bytes());
+            }
+            _ => (),
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the flags of an ELF object for the target, which are checked by linkers for
+    /// compatibility with the other objects being linked
+    fn elf_flags(&self) -> u32 {
+        let options = &self.target.options;
+        match self.target.arch.as_ref() {
+            "riscv32" | "riscv64" => match options.llvm_abiname.as_ref() {
+                "ilp32f" | "lp64f" => elf::EF_RISCV_FLOAT_ABI_SINGLE,
+                "ilp32d" | "lp64d" => elf::EF_RISCV_FLOAT_ABI_DOUBLE,
+                _ => elf::EF_RISCV_FLOAT_ABI_SOFT,
+            },
+            // Little-endian targets use version 2 of the ELF ABI
+            "powerpc64" if options.endianness == Endianness::Little => 2,
+            _ => 0,
+        }
+    }
+
+    /// Returns the Mach-O CPU subtype of a 32-bit ARM target
+    fn arm_cpu_subtype(&self) -> u32 {
+        match self.target.llvm_target.split('-').next() {
+            Some("armv7k") => macho::CPU_SUBTYPE_ARM_V7K,
+            Some("armv7s") => macho::CPU_SUBTYPE_ARM_V7S,
+            Some("armv7") => macho::CPU_SUBTYPE_ARM_V7,
+            _ => macho::CPU_SUBTYPE_ARM_ALL,
+        }
+    }
+}
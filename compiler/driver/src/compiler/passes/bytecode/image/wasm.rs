@@ -0,0 +1,287 @@
+//! Writes the image as a relocatable WebAssembly object, as understood by `wasm-ld`
+//!
+//! See <https://github.com/WebAssembly/tool-conventions/blob/main/Linking.md> for the format.
+//! As in the objects generated by LLVM, memory and the table of functions whose address is taken
+//! are imported from `env`, along with the undefined functions, and each global is placed in a
+//! data segment of its own, in a COMDAT of the same name.
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+use super::{Linkage, ObjectBuilder, Section};
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+const WASM_VERSION: u32 = 1;
+const WASM_PAGE_SIZE: u64 = 65536;
+
+const SECTION_CUSTOM: u8 = 0;
+const SECTION_TYPE: u8 = 1;
+const SECTION_IMPORT: u8 = 2;
+const SECTION_DATA: u8 = 11;
+
+const EXTERNAL_FUNCTION: u8 = 0;
+const EXTERNAL_TABLE: u8 = 1;
+const EXTERNAL_MEMORY: u8 = 2;
+
+const TYPE_FUNC: u8 = 0x60;
+const TYPE_FUNCREF: u8 = 0x70;
+
+const LIMITS_FLAG_IS_64: u8 = 0x04;
+
+const OPCODE_I32_CONST: u8 = 0x41;
+const OPCODE_I64_CONST: u8 = 0x42;
+const OPCODE_END: u8 = 0x0b;
+
+const LINKING_VERSION: u32 = 2;
+const WASM_SEGMENT_INFO: u8 = 5;
+const WASM_COMDAT_INFO: u8 = 7;
+const WASM_SYMBOL_TABLE: u8 = 8;
+
+const SYMTAB_FUNCTION: u8 = 0;
+const SYMTAB_DATA: u8 = 1;
+const SYMTAB_TABLE: u8 = 5;
+
+const SYM_BINDING_WEAK: u32 = 0x01;
+const SYM_UNDEFINED: u32 = 0x10;
+const SYM_NO_STRIP: u32 = 0x80;
+
+const COMDAT_DATA: u8 = 0;
+
+const R_WASM_TABLE_INDEX_I32: u8 = 2;
+const R_WASM_MEMORY_ADDR_I32: u8 = 5;
+const R_WASM_MEMORY_ADDR_I64: u8 = 16;
+const R_WASM_TABLE_INDEX_I64: u8 = 19;
+
+/// Writes `builder` as a WebAssembly object, see the module docs
+pub(super) fn write(builder: &ObjectBuilder) -> anyhow::Result<Vec<u8>> {
+    let is_64 = builder.target.pointer_width == 64;
+
+    // The symbol table lists the imported functions, the globals, then the imported table, so the
+    // index of each function symbol is also its function index
+    let mut symbols = HashMap::<&str, u32>::new();
+    for (index, (name, _)) in builder.functions.iter().enumerate() {
+        symbols.insert(name.as_str(), index as u32);
+    }
+    let num_functions = builder.functions.len() as u32;
+    for (index, global) in builder.globals.iter().enumerate() {
+        symbols.insert(global.name.as_str(), num_functions + index as u32);
+    }
+    let table_symbol = num_functions + builder.globals.len() as u32;
+
+    // Lay out the segments of each global in memory, and encode them in the data section,
+    // recording the relocations of the pointers they contain along the way
+    let mut data = Vec::new();
+    let mut relocations = Vec::new();
+    let mut address = 0;
+    write_uleb(&mut data, builder.globals.len() as u64);
+    for global in builder.globals.iter() {
+        address = align(address, global.align);
+        // Flags, i.e. an active segment of memory 0
+        write_uleb(&mut data, 0);
+        data.push(if is_64 {
+            OPCODE_I64_CONST
+        } else {
+            OPCODE_I32_CONST
+        });
+        write_sleb(&mut data, address as i64);
+        data.push(OPCODE_END);
+        write_uleb(&mut data, global.data.bytes.len() as u64);
+        let offset = data.len();
+        data.extend_from_slice(&global.data.bytes);
+        for (field, target) in global.data.relocations.iter() {
+            let index = *symbols
+                .get(target.as_str())
+                .ok_or_else(|| anyhow!("reference to undefined symbol '{}'", target))?;
+            let is_function = index < num_functions;
+            let ty = match (is_function, is_64) {
+                (true, false) => R_WASM_TABLE_INDEX_I32,
+                (true, true) => R_WASM_TABLE_INDEX_I64,
+                (false, false) => R_WASM_MEMORY_ADDR_I32,
+                (false, true) => R_WASM_MEMORY_ADDR_I64,
+            };
+            relocations.push(((offset + field) as u64, ty, index));
+        }
+        address += global.data.bytes.len() as u64;
+    }
+
+    let mut module = Vec::new();
+    module.extend_from_slice(WASM_MAGIC);
+    module.extend_from_slice(&WASM_VERSION.to_le_bytes());
+
+    // The sections are numbered in the order they are written, which the relocations refer to
+    let mut section_index = 0;
+
+    // All of the functions are declared with the same signature, i.e. `void ()`
+    let mut types = Vec::new();
+    write_uleb(&mut types, 1);
+    types.extend_from_slice(&[TYPE_FUNC, 0, 0]);
+    write_section(&mut module, SECTION_TYPE, &types);
+    section_index += 1;
+
+    let mut imports = Vec::new();
+    write_uleb(&mut imports, 2 + builder.functions.len() as u64);
+    write_name(&mut imports, "env");
+    write_name(&mut imports, "__linear_memory");
+    imports.push(EXTERNAL_MEMORY);
+    imports.push(if is_64 { LIMITS_FLAG_IS_64 } else { 0 });
+    write_uleb(&mut imports, address.div_ceil(WASM_PAGE_SIZE));
+    write_name(&mut imports, "env");
+    write_name(&mut imports, "__indirect_function_table");
+    imports.push(EXTERNAL_TABLE);
+    imports.push(TYPE_FUNCREF);
+    imports.push(0);
+    write_uleb(&mut imports, 0);
+    for (name, _) in builder.functions.iter() {
+        write_name(&mut imports, "env");
+        write_name(&mut imports, name);
+        imports.push(EXTERNAL_FUNCTION);
+        write_uleb(&mut imports, 0);
+    }
+    write_section(&mut module, SECTION_IMPORT, &imports);
+    section_index += 1;
+
+    let data_section_index = section_index;
+    write_section(&mut module, SECTION_DATA, &data);
+
+    let mut linking = Vec::new();
+    write_uleb(&mut linking, LINKING_VERSION as u64);
+
+    let mut symtab = Vec::new();
+    write_uleb(&mut symtab, table_symbol as u64 + 1);
+    for (index, (_, linkage)) in builder.functions.iter().enumerate() {
+        let mut flags = SYM_UNDEFINED;
+        if *linkage == Linkage::ExternalWeak {
+            flags |= SYM_BINDING_WEAK;
+        }
+        symtab.push(SYMTAB_FUNCTION);
+        write_uleb(&mut symtab, flags as u64);
+        write_uleb(&mut symtab, index as u64);
+    }
+    for (index, global) in builder.globals.iter().enumerate() {
+        symtab.push(SYMTAB_DATA);
+        write_uleb(&mut symtab, SYM_BINDING_WEAK as u64);
+        write_name(&mut symtab, &global.name);
+        write_uleb(&mut symtab, index as u64);
+        write_uleb(&mut symtab, 0);
+        write_uleb(&mut symtab, global.data.bytes.len() as u64);
+    }
+    // Function pointers are indices in this table, so it must be kept by the linker
+    symtab.push(SYMTAB_TABLE);
+    write_uleb(&mut symtab, (SYM_UNDEFINED | SYM_NO_STRIP) as u64);
+    write_uleb(&mut symtab, 0);
+    write_subsection(&mut linking, WASM_SYMBOL_TABLE, &symtab);
+
+    let mut segments = Vec::new();
+    write_uleb(&mut segments, builder.globals.len() as u64);
+    for global in builder.globals.iter() {
+        let name = match global.section {
+            Section::ReadOnlyData => format!(".rodata.{}", global.name),
+            Section::Named(name) => name.to_string(),
+        };
+        write_name(&mut segments, &name);
+        write_uleb(&mut segments, global.align.trailing_zeros() as u64);
+        write_uleb(&mut segments, 0);
+    }
+    write_subsection(&mut linking, WASM_SEGMENT_INFO, &segments);
+
+    let mut comdats = Vec::new();
+    write_uleb(&mut comdats, builder.globals.len() as u64);
+    for (index, global) in builder.globals.iter().enumerate() {
+        write_name(&mut comdats, &global.name);
+        write_uleb(&mut comdats, 0);
+        write_uleb(&mut comdats, 1);
+        comdats.push(COMDAT_DATA);
+        write_uleb(&mut comdats, index as u64);
+    }
+    write_subsection(&mut linking, WASM_COMDAT_INFO, &comdats);
+
+    write_custom_section(&mut module, "linking", &linking);
+
+    let mut reloc = Vec::new();
+    write_uleb(&mut reloc, data_section_index);
+    write_uleb(&mut reloc, relocations.len() as u64);
+    for (offset, ty, index) in relocations {
+        reloc.push(ty);
+        write_uleb(&mut reloc, offset);
+        write_uleb(&mut reloc, index as u64);
+        if ty == R_WASM_MEMORY_ADDR_I32 || ty == R_WASM_MEMORY_ADDR_I64 {
+            write_sleb(&mut reloc, 0);
+        }
+    }
+    write_custom_section(&mut module, "reloc.DATA", &reloc);
+
+    Ok(module)
+}
+
+fn align(value: u64, align: u64) -> u64 {
+    value.div_ceil(align) * align
+}
+
+fn write_section(module: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    module.push(id);
+    write_uleb(module, contents.len() as u64);
+    module.extend_from_slice(contents);
+}
+
+fn write_custom_section(module: &mut Vec<u8>, name: &str, contents: &[u8]) {
+    let mut section = Vec::with_capacity(name.len() + 1 + contents.len());
+    write_name(&mut section, name);
+    section.extend_from_slice(contents);
+    write_section(module, SECTION_CUSTOM, &section);
+}
+
+fn write_subsection(linking: &mut Vec<u8>, ty: u8, contents: &[u8]) {
+    linking.push(ty);
+    write_uleb(linking, contents.len() as u64);
+    linking.extend_from_slice(contents);
+}
+
+fn write_name(buffer: &mut Vec<u8>, name: &str) {
+    write_uleb(buffer, name.len() as u64);
+    buffer.extend_from_slice(name.as_bytes());
+}
+
+fn write_uleb(buffer: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer.push(byte);
+            break;
+        }
+        buffer.push(byte | 0x80);
+    }
+}
+
+fn write_sleb(buffer: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            buffer.push(byte);
+            break;
+        }
+        buffer.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn leb128_encoding() {
+        let mut buffer = Vec::new();
+        write_uleb(&mut buffer, 624485);
+        assert_eq!(buffer, [0xe5, 0x8e, 0x26]);
+
+        buffer.clear();
+        write_sleb(&mut buffer, -123456);
+        assert_eq!(buffer, [0xc0, 0xbb, 0x78]);
+
+        buffer.clear();
+        write_sleb(&mut buffer, 64);
+        assert_eq!(buffer, [0xc0, 0x00]);
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::bail;
+
+use log::debug;
+
+use firefly_bytecode::{InvalidBytecodeError, StandardByteCode};
+use firefly_pass::Pass;
+use firefly_session::Options;
+use firefly_util::diagnostics::{DiagnosticsHandler, Severity};
+
+/// This pass links the bytecode of individual modules into a single bytecode module, resolving
+/// calls between them, and verifying that every function called is defined.
+pub struct LinkBytecode<'a> {
+    options: &'a Options,
+    diagnostics: &'a DiagnosticsHandler,
+}
+impl<'a> LinkBytecode<'a> {
+    pub fn new(options: &'a Options, diagnostics: &'a DiagnosticsHandler) -> Self {
+        Self {
+            options,
+            diagnostics,
+        }
+    }
+}
+impl<'m> Pass for LinkBytecode<'m> {
+    type Input<'a> = Vec<StandardByteCode>;
+    type Output<'a> = StandardByteCode;
+
+    fn run<'a>(&mut self, modules: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        debug!("linking bytecode for {} modules", modules.len());
+
+        let mut module = StandardByteCode::default();
+        let result = module.link(modules).and_then(|_| module.validate());
+
+        match result {
+            Ok(_) => {
+                if let Some(path) = self.options.maybe_emit_bytecode() {
+                    crate::compiler::emit_file_with_callback(path, |f| {
+                        use std::io::Write;
+                        f.write_fmt(format_args!("{}", &module))?;
+                        Ok(())
+                    })?;
+                }
+                Ok(module)
+            }
+            Err(InvalidBytecodeError::IncompleteFunction(mfa)) => {
+                self.diagnostics
+                    .diagnostic(Severity::Error)
+                    .with_message(format!("missing function definition for {:?}", &mfa))
+                    .emit();
+                bail!("bytecode validation failed, see diagnostics for details");
+            }
+            Err(InvalidBytecodeError::DuplicateDefinition(mfa)) => {
+                self.diagnostics
+                    .diagnostic(Severity::Error)
+                    .with_message(format!("attempted to redefine {:?}", &mfa))
+                    .with_note("the module which defines it was compiled more than once")
+                    .emit();
+                bail!("bytecode validation failed, see diagnostics for details");
+            }
+        }
+    }
+}
@@ -18,7 +18,6 @@ use firefly_bytecode::{
 use firefly_intern::symbols;
 use firefly_number::Int;
 use firefly_pass::Pass;
-use firefly_session::Options;
 use firefly_syntax_base::Signature;
 use firefly_syntax_ssa as syntax_ssa;
 use firefly_syntax_ssa::ir::instructions::*;
@@ -36,59 +35,31 @@ type Builder = bc::Builder<bc::AtomicStr, bc::LocalAtomTable>;
 type FunctionBuilder<'a> = bc::FunctionBuilder<'a, bc::AtomicStr, bc::LocalAtomTable>;
 
 pub struct LowerSsa<'a> {
-    options: &'a Options,
     diagnostics: &'a DiagnosticsHandler,
     codemap: &'a CodeMap,
 }
 impl<'a> LowerSsa<'a> {
-    pub fn new(
-        options: &'a Options,
-        diagnostics: &'a DiagnosticsHandler,
-        codemap: &'a CodeMap,
-    ) -> Self {
+    pub fn new(diagnostics: &'a DiagnosticsHandler, codemap: &'a CodeMap) -> Self {
         Self {
-            options,
             diagnostics,
             codemap,
         }
     }
 }
+/// Builds the bytecode of a single module
+///
+/// Calls to functions defined in other modules are left unresolved, see `LinkBytecode`
 impl<'m> Pass for LowerSsa<'m> {
-    type Input<'a> = Vec<Artifact<syntax_ssa::Module>>;
+    type Input<'a> = Artifact<syntax_ssa::Module>;
     type Output<'a> = StandardByteCode;
 
-    fn run<'a>(&mut self, mut modules: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
-        debug!("building bytecode for {} modules", modules.len());
-
+    fn run<'a>(&mut self, artifact: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
         let mut builder = BytecodeBuilder::new(self.diagnostics, self.codemap);
         let mut bytecode = Builder::new(StandardByteCode::default());
 
-        for Artifact { output: module, .. } in modules.drain(..) {
-            builder.build_module(&mut bytecode, module)?;
-        }
-
-        let module = bytecode.finish();
+        builder.build_module(&mut bytecode, artifact.output)?;
 
-        match module.validate() {
-            Ok(_) => {
-                if let Some(path) = self.options.maybe_emit_bytecode() {
-                    crate::compiler::emit_file_with_callback(path, |f| {
-                        use std::io::Write;
-                        f.write_fmt(format_args!("{}", &module))?;
-                        Ok(())
-                    })?;
-                }
-                Ok(module)
-            }
-            Err(InvalidBytecodeError::IncompleteFunction(mfa)) => {
-                self.diagnostics
-                    .diagnostic(Severity::Error)
-                    .with_message(format!("missing function definition for {:?}", &mfa))
-                    .emit();
-                bail!("bytecode validation failed, see diagnostics for details");
-            }
-            Err(_) => unreachable!(),
-        }
+        Ok(bytecode.finish())
     }
 }
 
@@ -104,6 +75,8 @@ struct BytecodeBuilder<'a> {
     blocks: HashMap<syntax_ssa::Block, bc::BlockId>,
     // Used to track the mapping of values in the current function being translated
     values: HashMap<syntax_ssa::Value, bc::Register>,
+    // The span of the current function being translated
+    current_function_span: SourceSpan,
 }
 impl<'a> BytecodeBuilder<'a> {
     fn new(diagnostics: &'a DiagnosticsHandler, codemap: &'a CodeMap) -> Self {
@@ -115,6 +88,7 @@ impl<'a> BytecodeBuilder<'a> {
             current_block: bc::BlockId::default(),
             blocks: HashMap::default(),
             values: HashMap::default(),
+            current_function_span: SourceSpan::UNKNOWN,
         }
     }
 
@@ -310,6 +284,7 @@ impl<'a> BytecodeBuilder<'a> {
         // Reset the block/value maps for this function
         self.blocks.clear();
         self.values.clear();
+        self.current_function_span = function.span;
 
         // If this is a NIF, mark it as such so the bytecode loader can check whether
         // to load the native implementation or the bytecoded shim
@@ -317,6 +292,11 @@ impl<'a> BytecodeBuilder<'a> {
             builder.mark_as_nif();
         }
 
+        // The on_load function is run by `init` when the system boots
+        if function.signature.is_on_load() {
+            builder.mark_as_on_load();
+        }
+
         // Build lookup map for syntax_ssa blocks to bytecode blocks, creating the blocks in the
         // process
         self.blocks.extend(function.dfg.blocks().map(|(b, _data)| {
@@ -352,7 +332,15 @@ impl<'a> BytecodeBuilder<'a> {
         inst: Inst,
     ) -> anyhow::Result<()> {
         let inst_data = &dfg[inst];
-        let inst_span = inst_data.span();
+        let opcode = inst_data.opcode();
+        // Call sites and raising instructions must always have a location, as they are what
+        // stack traces are built from, so fall back to the enclosing function if necessary
+        let inst_span = match inst_data.span() {
+            span if span.is_unknown() && (opcode.is_call() || opcode.is_exception()) => {
+                self.current_function_span
+            }
+            span => span,
+        };
         debug!(
             "translating instruction with opcode {:?} to bytecode",
             inst_data.opcode()
@@ -1,42 +1,39 @@
+mod image;
+mod link_bytecode;
 mod lower_bytecode;
 mod lower_ssa;
 
+pub use self::lower_ssa::LowerSsa;
+
+use self::link_bytecode::LinkBytecode;
 use self::lower_bytecode::LowerBytecode;
-use self::lower_ssa::LowerSsa;
 
 use std::sync::Arc;
 
-use firefly_diagnostics::CodeMap;
+use firefly_bytecode::StandardByteCode;
 use firefly_pass::Pass;
 use firefly_session::Options;
 use firefly_util::diagnostics::DiagnosticsHandler;
 
-use crate::compiler::Artifact;
-
+/// Links the bytecode of all modules in the program, see `LowerSsa`, and lowers it to an object
 pub struct CompileBytecode {
     options: Arc<Options>,
-    codemap: Arc<CodeMap>,
     diagnostics: Arc<DiagnosticsHandler>,
 }
 impl CompileBytecode {
-    pub fn new(
-        options: Arc<Options>,
-        codemap: Arc<CodeMap>,
-        diagnostics: Arc<DiagnosticsHandler>,
-    ) -> Self {
+    pub fn new(options: Arc<Options>, diagnostics: Arc<DiagnosticsHandler>) -> Self {
         Self {
             options,
-            codemap,
             diagnostics,
         }
     }
 }
 impl Pass for CompileBytecode {
-    type Input<'a> = Vec<Artifact<firefly_syntax_ssa::Module>>;
+    type Input<'a> = Vec<StandardByteCode>;
     type Output<'a> = firefly_linker::ModuleArtifacts;
 
     fn run<'a>(&mut self, modules: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
-        let mut pipeline = LowerSsa::new(&self.options, &self.diagnostics, &self.codemap).chain(
+        let mut pipeline = LinkBytecode::new(&self.options, &self.diagnostics).chain(
             LowerBytecode::new(self.options.clone(), self.diagnostics.clone()),
         );
 
@@ -6,11 +6,12 @@ mod parse;
 #[cfg(feature = "native-compilation")]
 mod ssa_to_mlir;
 
-pub use self::bytecode::CompileBytecode;
+pub use self::bytecode::{CompileBytecode, LowerSsa};
 pub use self::lower_ast::LowerAst;
 pub use self::lower_core::LowerCore;
 pub use self::lower_kernel::LowerKernel;
-pub use self::parse::ParsePipeline;
+pub use self::parse::{AnalyzeMetadata, ParsePipeline};
+pub(crate) use self::parse::term_to_tokens;
 #[cfg(feature = "native-compilation")]
 pub use self::ssa_to_mlir::SsaToMlir;
 
@@ -3,14 +3,15 @@ use std::sync::Arc;
 
 use anyhow::bail;
 
-use firefly_intern::symbols;
+use firefly_intern::{symbols, Symbol};
 use firefly_pass::Pass;
-use firefly_session::{Input, InputType, Options};
+use firefly_session::{Input, InputType, Options, OutputType};
 use firefly_syntax_base::{Deprecation, ModuleMetadata};
-use firefly_syntax_erl::{self as syntax_erl, ParseConfig};
+use firefly_syntax_erl::{self as syntax_erl, LexicalToken, MacroDef, ParseConfig, Token};
+use firefly_syntax_pp::ast::Term;
 use firefly_util::diagnostics::*;
 
-use crate::compiler::Artifact;
+use crate::compiler::{maybe_emit_file_with_callback_and_opts, Artifact};
 
 #[derive(Clone)]
 pub struct ParsePipeline {
@@ -29,7 +30,7 @@ impl ParsePipeline {
         config.warnings_as_errors = options.warnings_as_errors;
         config.no_warn = options.no_warn;
         config.include_paths = options.include_path.clone();
-        config.code_paths = Default::default();
+        config.code_paths = options.lib_dirs.iter().cloned().collect();
         config.define(symbols::VSN, crate::FIREFLY_RELEASE);
         config.define(symbols::COMPILER_VSN, crate::FIREFLY_RELEASE);
 
@@ -40,7 +41,93 @@ impl ParsePipeline {
             config,
         }
     }
+
+    /// Returns a pipeline for the sources of `app`, which applies the options given for it by
+    /// the project it belongs to, if any
+    pub fn for_app(&self, app: Symbol) -> Self {
+        let mut pipeline = self.clone();
+        let Some(opts) = self.options.erl_opts.get(&app) else { return pipeline };
+        let config = &mut pipeline.config;
+        for path in opts.include_paths.iter().rev() {
+            config.include_paths.push_front(path.clone());
+        }
+        for (name, value) in opts.defines.iter() {
+            let value = match value {
+                None => MacroDef::Boolean(true),
+                Some(Term::Atom(atom)) => MacroDef::Atom(atom.item),
+                Some(Term::String(s)) => MacroDef::String(s.item),
+                Some(term) => {
+                    let mut tokens = vec![];
+                    term_to_tokens(term, &mut tokens);
+                    MacroDef::Dynamic(tokens)
+                }
+            };
+            config.define(*name, value);
+        }
+        pipeline
+    }
+}
+
+/// Converts a term given as the value of a macro into the tokens of its expansion
+pub(crate) fn term_to_tokens(term: &Term, tokens: &mut Vec<LexicalToken>) {
+    let token = |token| LexicalToken(SourceIndex::UNKNOWN, token, SourceIndex::UNKNOWN);
+    match term {
+        Term::Atom(atom) => tokens.push(token(Token::Atom(atom.item))),
+        Term::String(s) => tokens.push(token(Token::String(s.item))),
+        Term::Char(c) => tokens.push(token(Token::Char(c.item))),
+        Term::Integer(i) => tokens.push(token(Token::Integer(i.item.clone()))),
+        Term::Float(f) => tokens.push(token(Token::Float(f.item))),
+        Term::Nil(_) => {
+            tokens.push(token(Token::LBracket));
+            tokens.push(token(Token::RBracket));
+        }
+        Term::Tuple(elements) => {
+            tokens.push(token(Token::LBrace));
+            for (i, element) in elements.item.iter().enumerate() {
+                if i > 0 {
+                    tokens.push(token(Token::Comma));
+                }
+                term_to_tokens(element, tokens);
+            }
+            tokens.push(token(Token::RBrace));
+        }
+        Term::Cons(cons) => {
+            tokens.push(token(Token::LBracket));
+            term_to_tokens(&cons.item.0, tokens);
+            let mut tail = cons.item.1.as_ref();
+            loop {
+                match tail {
+                    Term::Nil(_) => break,
+                    Term::Cons(cons) => {
+                        tokens.push(token(Token::Comma));
+                        term_to_tokens(&cons.item.0, tokens);
+                        tail = cons.item.1.as_ref();
+                    }
+                    improper => {
+                        tokens.push(token(Token::Bar));
+                        term_to_tokens(improper, tokens);
+                        break;
+                    }
+                }
+            }
+            tokens.push(token(Token::RBracket));
+        }
+        Term::Map(pairs) => {
+            tokens.push(token(Token::Pound));
+            tokens.push(token(Token::LBrace));
+            for (i, (key, value)) in pairs.item.iter().enumerate() {
+                if i > 0 {
+                    tokens.push(token(Token::Comma));
+                }
+                term_to_tokens(key, tokens);
+                tokens.push(token(Token::RightArrow));
+                term_to_tokens(value, tokens);
+            }
+            tokens.push(token(Token::RBrace));
+        }
+    }
 }
+
 impl Pass for ParsePipeline {
     type Input<'a> = Input;
     type Output<'a> = Artifact<syntax_erl::Module, ModuleMetadata>;
@@ -52,6 +139,7 @@ impl Pass for ParsePipeline {
             &self.config,
             self.codemap.clone(),
         )
+        .chain(EmitDocs::new(&self.options, &self.codemap))
         .chain(AnalyzeMetadata::new(&self.diagnostics));
 
         passes.run(input)
@@ -160,11 +248,42 @@ impl<'p> Pass for ParseInput<'p> {
     }
 }
 
-struct AnalyzeMetadata<'p> {
+/// Writes the EEP 48 documentation chunk of a parsed module, if requested with `--emit=docs`
+pub struct EmitDocs<'p> {
+    options: &'p Options,
+    codemap: &'p CodeMap,
+}
+impl<'p> EmitDocs<'p> {
+    pub fn new(options: &'p Options, codemap: &'p CodeMap) -> Self {
+        Self { options, codemap }
+    }
+}
+impl<'p> Pass for EmitDocs<'p> {
+    type Input<'a> = Artifact<syntax_erl::Module>;
+    type Output<'a> = Artifact<syntax_erl::Module>;
+
+    fn run<'a>(&mut self, artifact: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        maybe_emit_file_with_callback_and_opts(
+            self.options,
+            &artifact.input,
+            OutputType::Docs,
+            |f| {
+                let docs = syntax_erl::docs::docs_v1(&artifact.output, self.codemap);
+                docs.encode(f)?;
+                Ok(())
+            },
+        )?;
+
+        Ok(artifact)
+    }
+}
+
+/// Extracts the metadata of a parsed module which is needed to analyze other modules
+pub struct AnalyzeMetadata<'p> {
     diagnostics: &'p DiagnosticsHandler,
 }
 impl<'p> AnalyzeMetadata<'p> {
-    fn new(diagnostics: &'p DiagnosticsHandler) -> Self {
+    pub fn new(diagnostics: &'p DiagnosticsHandler) -> Self {
         Self { diagnostics }
     }
 }
@@ -183,6 +302,11 @@ impl<'p> Pass for AnalyzeMetadata<'p> {
         let mut metadata = ModuleMetadata::new(module.name);
         metadata.exports = module.exports.iter().cloned().collect();
         metadata.deprecation = module.deprecation.clone();
+        metadata.callbacks = module
+            .callbacks
+            .iter()
+            .map(|(name, cb)| (*name, cb.optional))
+            .collect();
         for dep in module.deprecations.iter().copied() {
             match dep {
                 d @ Deprecation::Module { .. } if metadata.deprecation.is_none() => {
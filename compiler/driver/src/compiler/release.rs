@@ -0,0 +1,255 @@
+//! Generates the `firefly_release` module embedded in executables
+//!
+//! The application controller in the runtime has no access to `.app` files or `sys.config` once
+//! an executable is built, so the metadata of every application compiled into it is rendered as
+//! Erlang source, and compiled along with the root application:
+//!
+//! * `firefly_release:root/0` returns the name of the root application
+//! * `firefly_release:applications/0` returns the resource of each application, in the same form
+//! as a `.app` file, i.e. `{application, Name, Properties}`
+//! * `firefly_release:config/0` returns the contents of `config/sys.config` in the root application
+//! directory, or `[]` if there isn't one
+use std::fmt::{self, Write};
+use std::sync::Arc;
+
+use firefly_intern::Symbol;
+use firefly_session::{App, Input, Options};
+use firefly_syntax_pp::ast::{Root, Term};
+use firefly_syntax_pp::ParserError;
+use firefly_util::diagnostics::*;
+
+type Parser = firefly_parser::Parser<()>;
+
+/// The name of the generated module
+pub const MODULE: &str = "firefly_release";
+
+/// Generates the source of the `firefly_release` module, if building an executable
+pub fn generate(
+    options: &Options,
+    codemap: Arc<CodeMap>,
+    diagnostics: &DiagnosticsHandler,
+) -> anyhow::Result<Option<Input>> {
+    if !options.project_type.is_executable() {
+        return Ok(None);
+    }
+
+    let config = match options.app.root.as_ref() {
+        Some(root) if root.join("config/sys.config").is_file() => {
+            let path = root.join("config/sys.config");
+            let parser = Parser::new((), codemap);
+            match parser.parse_file::<Root, _, ParserError>(diagnostics, &path) {
+                Ok(root) => Some(root.term),
+                Err(err) => {
+                    diagnostics.error(err);
+                    anyhow::bail!("unable to parse {}", path.display());
+                }
+            }
+        }
+        _ => None,
+    };
+
+    // Applications are listed in the order they must be started, the root application last
+    let mut apps = options.sorted_dependencies()?;
+    apps.push(options.app.clone());
+    let apps = apps.iter().collect::<Vec<_>>();
+
+    let mut source = String::new();
+    render_module(&mut source, options.app.name, &apps, config.as_ref())?;
+    Ok(Some(Input::new(format!("{}.erl", MODULE), source)))
+}
+
+fn render_module(
+    out: &mut String,
+    root: Symbol,
+    apps: &[&Arc<App>],
+    config: Option<&Term>,
+) -> fmt::Result {
+    writeln!(out, "-module({}).", MODULE)?;
+    writeln!(out, "-export([root/0, applications/0, config/0]).")?;
+    writeln!(out)?;
+    writeln!(out, "root() -> '{}'.", root)?;
+    writeln!(out)?;
+    writeln!(out, "applications() ->")?;
+    out.push_str("    [");
+    for (i, app) in apps.iter().enumerate() {
+        if i > 0 {
+            out.push_str(",\n     ");
+        }
+        render_app(out, app)?;
+    }
+    writeln!(out, "].")?;
+    writeln!(out)?;
+    out.push_str("config() -> ");
+    match config {
+        Some(term) => render_term(out, term)?,
+        None => out.push_str("[]"),
+    }
+    writeln!(out, ".")
+}
+
+/// Renders an application resource, as it would appear in a `.app` file
+fn render_app(out: &mut String, app: &App) -> fmt::Result {
+    write!(out, "{{application, '{}', [", app.name)?;
+    write!(
+        out,
+        "{{vsn, \"{}\"}}",
+        app.version.as_deref().unwrap_or_default()
+    )?;
+    out.push_str(", {modules, ");
+    render_atoms(out, &app.modules)?;
+    out.push_str("}, {applications, ");
+    render_atoms(out, &app.applications)?;
+    out.push_str("}, {included_applications, ");
+    render_atoms(out, &app.included_applications)?;
+    out.push_str("}, {optional_applications, ");
+    render_atoms(out, &app.optional_applications)?;
+    out.push_str("}, {env, [");
+    for (i, (key, value)) in app.env.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write!(out, "{{'{}', ", key)?;
+        render_term(out, value)?;
+        out.push('}');
+    }
+    out.push_str("]}");
+    if let Some(module) = app.otp_module {
+        write!(out, ", {{mod, {{'{}', ", module)?;
+        match app.start_args.first() {
+            Some(args) => render_term(out, args)?,
+            None => out.push_str("[]"),
+        }
+        out.push_str("}}");
+    }
+    if !app.start_phases.is_empty() {
+        out.push_str(", {start_phases, [");
+        for (i, (phase, args)) in app.start_phases.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            write!(out, "{{'{}', ", phase)?;
+            render_term(out, args)?;
+            out.push('}');
+        }
+        out.push_str("]}");
+    }
+    out.push_str("]}");
+    Ok(())
+}
+
+fn render_atoms(out: &mut String, atoms: &[Symbol]) -> fmt::Result {
+    out.push('[');
+    for (i, atom) in atoms.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write!(out, "'{}'", atom)?;
+    }
+    out.push(']');
+    Ok(())
+}
+
+/// Renders a term as Erlang source
+///
+/// Atoms and strings are kept exactly as they were written, escapes included, so they are always
+/// rendered quoted.
+fn render_term(out: &mut String, term: &Term) -> fmt::Result {
+    match term {
+        Term::Atom(atom) => write!(out, "'{}'", atom.item),
+        Term::String(s) => write!(out, "\"{}\"", s.item),
+        Term::Char(c) => write!(out, "{}", c.item as u32),
+        Term::Integer(i) => write!(out, "{}", i.item),
+        Term::Float(f) => {
+            // Floats must always have a fractional part, e.g. `1.0e20` rather than `1e20`
+            let f = format!("{:?}", f.item.inner());
+            match f.split_once('e') {
+                Some((mantissa, exponent)) if !mantissa.contains('.') => {
+                    write!(out, "{}.0e{}", mantissa, exponent)
+                }
+                _ => out.write_str(&f),
+            }
+        }
+        Term::Nil(_) => out.write_str("[]"),
+        Term::Tuple(elements) => {
+            out.push('{');
+            for (i, element) in elements.item.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                render_term(out, element)?;
+            }
+            out.push('}');
+            Ok(())
+        }
+        Term::Cons(cons) => {
+            out.push('[');
+            let (head, mut tail) = (&cons.item.0, &cons.item.1);
+            render_term(out, head)?;
+            loop {
+                match tail.as_ref() {
+                    Term::Nil(_) => break,
+                    Term::Cons(cons) => {
+                        out.push_str(", ");
+                        render_term(out, &cons.item.0)?;
+                        tail = &cons.item.1;
+                    }
+                    improper => {
+                        out.push_str(" | ");
+                        render_term(out, improper)?;
+                        break;
+                    }
+                }
+            }
+            out.push(']');
+            Ok(())
+        }
+        Term::Map(pairs) => {
+            out.push_str("#{");
+            for (i, (key, value)) in pairs.item.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                render_term(out, key)?;
+                out.push_str(" => ");
+                render_term(out, value)?;
+            }
+            out.push('}');
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_intern::Symbol;
+    use firefly_session::App;
+
+    use super::*;
+
+    #[test]
+    fn release_module_test() {
+        let mut dep = App::new(Symbol::intern("dep"));
+        dep.version = Some("1.0.0".to_string());
+        let mut root = App::new(Symbol::intern("my_app"));
+        root.applications.push(dep.name);
+        root.otp_module = Some(Symbol::intern("my_app_app"));
+        let apps = [&Arc::new(dep), &Arc::new(root)];
+
+        let mut source = String::new();
+        render_module(&mut source, Symbol::intern("my_app"), &apps, None).unwrap();
+        assert_eq!(
+            source,
+            "-module(firefly_release).
+-export([root/0, applications/0, config/0]).
+
+root() -> 'my_app'.
+
+applications() ->
+    [{application, 'dep', [{vsn, \"1.0.0\"}, {modules, []}, {applications, []}, {included_applications, []}, {optional_applications, []}, {env, []}]},
+     {application, 'my_app', [{vsn, \"\"}, {modules, []}, {applications, ['dep']}, {included_applications, []}, {optional_applications, []}, {env, []}, {mod, {'my_app_app', []}}]}].
+
+config() -> [].
+"
+        );
+    }
+}
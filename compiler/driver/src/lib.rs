@@ -4,6 +4,7 @@
 mod argparser;
 mod commands;
 mod compiler;
+mod lsp;
 
 use std::ffi::OsString;
 use std::path::PathBuf;
@@ -19,7 +20,7 @@ use firefly_session::{
 use firefly_util::diagnostics::Emitter;
 use firefly_util::error::HelpRequested;
 
-use self::commands::{compile, print};
+use self::commands::{compile, fmt, lsp, print};
 
 pub const FIREFLY_RELEASE: &'static str = crate_version!();
 pub const FIREFLY_COMMIT_HASH: &'static str = env!("FIREFLY_COMMIT_HASH");
@@ -75,6 +76,21 @@ pub fn run_compiler_with_emitter(
             // Dispatch
             compile::handle_command(options, codemap, emitter).map(|_| 0)
         }
+        ("fmt", matches) => {
+            // Initialize options from current context and arguments
+            let codemap = Arc::new(CodeMap::new());
+            let matches = matches.unwrap();
+            let options = fmt::configure(codemap.clone(), c_opts, z_opts, cwd, matches)?;
+            // Dispatch
+            fmt::handle_command(options, codemap, emitter, matches)
+        }
+        ("lsp", matches) => {
+            // Initialize options from current context and arguments
+            let codemap = Arc::new(CodeMap::new());
+            let options = lsp::configure(codemap.clone(), c_opts, z_opts, cwd, matches.unwrap())?;
+            // The language server only uses the frontend, so there is no backend to initialize
+            lsp::handle_command(options, codemap)
+        }
         (subcommand, _) => Err(anyhow!(format!("Unrecognized subcommand '{}'", subcommand))),
     }
 }
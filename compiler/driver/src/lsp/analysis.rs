@@ -0,0 +1,307 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use firefly_diagnostics::LineIndex;
+use firefly_intern::symbols;
+use firefly_parser::{FileMapSource, Parse, Scanner, Source};
+use firefly_pass::Pass;
+use firefly_session::{Input, Options};
+use firefly_syntax_base::{ApplicationMetadata, ModuleMetadata};
+use firefly_syntax_erl::directives::Define;
+use firefly_syntax_erl::passes::SemanticAnalysis;
+use firefly_syntax_erl::{self as syntax_erl, Lexer, LexicalToken, ParseConfig, Token};
+use firefly_util::diagnostics::*;
+use firefly_util::error::Verbosity;
+
+use crate::compiler::passes::AnalyzeMetadata;
+use crate::compiler::{self, Artifact};
+
+/// A source file known to the language server, along with the results of its last analysis
+pub struct Document {
+    pub uri: String,
+    pub path: PathBuf,
+    /// The current contents of the document
+    pub file: Arc<SourceFile>,
+    /// The tokens of the current contents, used to determine what is under the cursor
+    pub tokens: Vec<LexicalToken>,
+    /// The diagnostics produced by the last analysis
+    pub diagnostics: Vec<Diagnostic>,
+    /// The results of the last successful parse of the document
+    ///
+    /// This may be stale with respect to the current contents, if those fail to parse.
+    pub parsed: Option<Parsed>,
+}
+
+/// The results of a successful parse of a document
+pub struct Parsed {
+    pub module: syntax_erl::Module,
+    pub metadata: ModuleMetadata,
+    /// The macros defined by the module and the files it includes
+    pub macros: Vec<Define>,
+    /// The files included by the module, with the span of the directive which included them
+    pub includes: Vec<(SourceSpan, PathBuf)>,
+}
+impl Parsed {
+    /// Returns the id of the revision of the document which was parsed
+    pub fn source_id(&self) -> SourceId {
+        self.module.span.source_id()
+    }
+}
+
+/// Analyzes documents using the same frontend as the compiler
+///
+/// Each document is analyzed on its own whenever it changes, in the context of the metadata of the
+/// other documents known to the server.
+///
+/// Every revision of a document adds a new source file to the `CodeMap`, along with the files it
+/// includes, which are removed again once the revision is superseded, see [`Analyzer::forget`].
+pub struct Analyzer {
+    options: Arc<Options>,
+    codemap: Arc<CodeMap>,
+    config: ParseConfig,
+}
+impl Analyzer {
+    pub fn new(options: Arc<Options>, codemap: Arc<CodeMap>) -> Self {
+        let mut config = ParseConfig::new();
+        config.warnings_as_errors = options.warnings_as_errors;
+        config.no_warn = options.no_warn;
+        config.include_paths = options.include_path.clone();
+        config.code_paths = options.code_path.clone();
+        config.define(symbols::VSN, crate::FIREFLY_RELEASE);
+        config.define(symbols::COMPILER_VSN, crate::FIREFLY_RELEASE);
+
+        Self {
+            options,
+            codemap,
+            config,
+        }
+    }
+
+    pub fn codemap(&self) -> &Arc<CodeMap> {
+        &self.codemap
+    }
+
+    /// Adds a directory to the include path used for all documents
+    pub fn add_include_path(&mut self, path: PathBuf) {
+        self.config.include_paths.push_back(path);
+    }
+
+    /// Analyzes the given contents of a document, superseding its `previous` revision
+    ///
+    /// If the contents fail to parse, the results of the previous parse are retained, so that
+    /// navigation continues to work while the document is being edited.
+    pub fn analyze(
+        &self,
+        uri: String,
+        path: PathBuf,
+        text: String,
+        previous: Option<Document>,
+        app: &ApplicationMetadata,
+    ) -> Document {
+        let name = FileName::Virtual(path.display().to_string().into());
+        let id = self.codemap.add(name, text);
+        let file = self.codemap.get(id).unwrap();
+
+        let tokens = lex(file.clone())
+            .filter_map(Result::ok)
+            .filter(|token| !matches!(token.1, Token::Comment | Token::Edoc | Token::EOF))
+            .collect();
+
+        let collector = Arc::new(DiagnosticsCollector::default());
+        let diagnostics = DiagnosticsHandler::new(
+            DiagnosticsConfig {
+                verbosity: Verbosity::Info,
+                warnings_as_errors: self.options.warnings_as_errors,
+                no_warn: self.options.no_warn,
+                display: DisplayConfig::default(),
+            },
+            self.codemap.clone(),
+            collector.clone(),
+        );
+
+        let parsed = match (self.parse(&diagnostics, &path, file.clone(), app), previous) {
+            (parsed, None) => parsed,
+            (Some(parsed), Some(previous)) => {
+                self.forget(previous);
+                Some(parsed)
+            }
+            (None, Some(previous)) => {
+                // The source file of the retained results must be kept, but not the previous one
+                let id = previous.file.id();
+                if previous.parsed.as_ref().map(Parsed::source_id) != Some(id) {
+                    self.codemap.remove(id);
+                }
+                previous.parsed
+            }
+        };
+
+        Document {
+            uri,
+            path,
+            file,
+            tokens,
+            diagnostics: collector.take(),
+            parsed,
+        }
+    }
+
+    /// Removes the source files of a document which is closed or superseded from the `CodeMap`
+    pub fn forget(&self, doc: Document) {
+        self.codemap.remove(doc.file.id());
+        if let Some(parsed) = doc.parsed.as_ref() {
+            self.codemap.remove(parsed.source_id());
+        }
+    }
+
+    fn parse(
+        &self,
+        diagnostics: &DiagnosticsHandler,
+        path: &Path,
+        file: Arc<SourceFile>,
+        app: &ApplicationMetadata,
+    ) -> Option<Parsed> {
+        use firefly_syntax_erl::Preprocessor;
+
+        // Includes are resolved relative to the document, as well as the conventional include
+        // directory of the application it belongs to
+        let mut config = self.config.clone();
+        if let Some(dir) = path.parent() {
+            config.include_paths.push_front(dir.join("../include"));
+            config.include_paths.push_front(dir.to_path_buf());
+        }
+
+        let id = file.id();
+        let parser = syntax_erl::Parser::new(config, self.codemap.clone());
+        let mut preprocessor = Preprocessor::new(&parser, lex(file), diagnostics);
+        let result = <syntax_erl::Module as Parse>::parse_tokens(
+            diagnostics,
+            self.codemap.clone(),
+            preprocessor.by_ref(),
+        );
+        let macros = preprocessor.macros().defines().cloned().collect();
+
+        let module = match result {
+            Ok(module) => module,
+            Err(err) => {
+                diagnostics.emit(err);
+                return None;
+            }
+        };
+
+        let includes = self
+            .codemap
+            .children(id)
+            .into_iter()
+            .filter_map(|child| {
+                let included = self.codemap.get(child).ok()?;
+                let parent = included.parent()?;
+                match included.name() {
+                    FileName::Real(path) => Some((parent, path.clone())),
+                    FileName::Virtual(_) => None,
+                }
+            })
+            .collect();
+
+        let artifact = Artifact {
+            input: Input::File(path.to_path_buf()),
+            output: module,
+            metadata: (),
+        };
+        let Artifact {
+            output: module,
+            metadata,
+            ..
+        } = AnalyzeMetadata::new(diagnostics).run(artifact).ok()?;
+
+        // Run semantic analysis in the context of the other known modules, for its diagnostics
+        let mut app = app.clone();
+        app.modules.insert(module.name(), metadata.clone());
+        for behaviour in module.behaviours.iter() {
+            if app.get_behaviour_callbacks(behaviour.name).is_some() {
+                continue;
+            }
+            if let Some(callbacks) =
+                compiler::load_behaviour_callbacks(&self.options.code_path, behaviour.name)
+            {
+                app.behaviours.insert(behaviour.name, callbacks);
+            }
+        }
+        let _ = SemanticAnalysis::new(diagnostics, &app).run(module.clone());
+
+        Some(Parsed {
+            module,
+            metadata,
+            macros,
+            includes,
+        })
+    }
+}
+
+fn lex(file: Arc<SourceFile>) -> Lexer<FileMapSource> {
+    Lexer::new(Scanner::new(FileMapSource::new(file)))
+}
+
+/// Collects the diagnostics emitted during analysis, so they can be published to the client
+#[derive(Default)]
+struct DiagnosticsCollector {
+    diagnostics: Mutex<Vec<Diagnostic>>,
+}
+impl DiagnosticsCollector {
+    fn take(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.diagnostics.lock())
+    }
+}
+impl Emitter for DiagnosticsCollector {
+    fn buffer(&self) -> Buffer {
+        Buffer::no_color()
+    }
+
+    // Progress messages are of no interest to the client, and stdout is reserved for the protocol
+    fn print(&self, _buffer: Buffer) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn emit_diagnostic(
+        &self,
+        _codemap: &CodeMap,
+        _display: &DisplayConfig,
+        diagnostic: &Diagnostic,
+    ) -> io::Result<()> {
+        self.diagnostics.lock().push(diagnostic.clone());
+        Ok(())
+    }
+}
+
+/// Converts a position, given as a line and a character offset in UTF-16 code units, to a byte
+/// offset in the given file
+pub fn offset_at(file: &SourceFile, line: usize, character: usize) -> usize {
+    let source = file.source();
+    let start = match file.line_start(LineIndex::from(line as u32)) {
+        Ok(start) => start.to_usize(),
+        Err(_) => return source.len(),
+    };
+    let mut units = 0;
+    for (offset, c) in source[start..].char_indices() {
+        if units >= character || c == '\n' {
+            return start + offset;
+        }
+        units += c.len_utf16();
+    }
+    source.len()
+}
+
+/// Converts a byte offset in the given file to a line and a character offset in UTF-16 code units
+pub fn position_of(file: &SourceFile, offset: usize) -> (usize, usize) {
+    let source = file.source();
+    let offset = offset.min(source.len());
+    let line = file.line_index(ByteIndex::from(offset as u32));
+    let start = file.line_start(line).map(|i| i.to_usize()).unwrap_or(0);
+    let character = source
+        .get(start..offset)
+        .map(|prefix| prefix.encode_utf16().count())
+        .unwrap_or(0);
+    (line.to_usize(), character)
+}
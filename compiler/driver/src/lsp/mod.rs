@@ -0,0 +1,814 @@
+//! A language server for Erlang, built on the compiler frontend
+//!
+//! The server speaks the language server protocol over stdio, and analyzes each document with the
+//! same parser, preprocessor and semantic analysis passes used by the compiler. It provides:
+//!
+//! * Diagnostics, published whenever a document is opened, changed or saved
+//! * Go-to-definition for functions, records, record fields, macros and includes
+//! * Hover, showing the `-spec` of a function, or the definition of a record or macro
+//! * Document symbols
+//! * Completion of local functions, records, record fields and macros
+mod analysis;
+mod query;
+mod transport;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::debug;
+
+use firefly_intern::Symbol;
+use firefly_session::Options;
+use firefly_syntax_base::{ApplicationMetadata, FunctionName};
+use firefly_syntax_erl as syntax_erl;
+use firefly_util::diagnostics::*;
+use firefly_util::json::Json;
+
+use self::analysis::{Analyzer, Document, Parsed};
+use self::query::{CompletionContext, Reference};
+
+// Error codes defined by JSON-RPC and the language server protocol
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+// The kinds of symbols and completion items we produce, as defined by the protocol
+const SYMBOL_FIELD: u32 = 8;
+const SYMBOL_FUNCTION: u32 = 12;
+const SYMBOL_CONSTANT: u32 = 14;
+const SYMBOL_STRUCT: u32 = 23;
+const SYMBOL_TYPE_PARAMETER: u32 = 26;
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_FIELD: u32 = 5;
+const COMPLETION_CONSTANT: u32 = 21;
+const COMPLETION_STRUCT: u32 = 22;
+
+/// An error returned in response to a request
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+impl ResponseError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// The definition of an entity referenced from a document
+struct Definition {
+    /// The span of the name of the entity at its definition
+    span: SourceSpan,
+    /// The text shown when hovering over a reference to the entity
+    hover: String,
+}
+
+pub struct Server {
+    options: Arc<Options>,
+    analyzer: Analyzer,
+    /// The documents currently open in the client, by URI
+    documents: HashMap<String, Document>,
+    /// Modules which are not open in the client, but were loaded to resolve references to them
+    external: HashMap<PathBuf, Document>,
+    initialized: bool,
+    shutdown: bool,
+}
+impl Server {
+    pub fn new(options: Arc<Options>, codemap: Arc<CodeMap>) -> Self {
+        let analyzer = Analyzer::new(options.clone(), codemap);
+        Self {
+            options,
+            analyzer,
+            documents: HashMap::new(),
+            external: HashMap::new(),
+            initialized: false,
+            shutdown: false,
+        }
+    }
+
+    /// Serves the client connected to `input` and `output` until it exits
+    ///
+    /// Returns the exit code of the server, which per the protocol is non-zero if the client
+    /// exits without first requesting a shutdown.
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        input: &mut R,
+        output: &mut W,
+    ) -> anyhow::Result<i32> {
+        while let Some(message) = transport::read_message(input)? {
+            let method = message.get("method").and_then(Json::as_str);
+            let params = message.get("params").unwrap_or(&Json::Null);
+            match (method, message.get("id")) {
+                (Some("exit"), _) => return Ok(if self.shutdown { 0 } else { 1 }),
+                (Some(method), Some(id)) => {
+                    debug!("handling request '{}'", method);
+                    let (key, value) = match self.handle_request(method, params) {
+                        Ok(result) => ("result", result),
+                        Err(error) => (
+                            "error",
+                            Json::object([
+                                ("code", Json::Number(error.code as f64)),
+                                ("message", error.message.into()),
+                            ]),
+                        ),
+                    };
+                    let response =
+                        Json::object([("jsonrpc", "2.0".into()), ("id", id.clone()), (key, value)]);
+                    transport::write_message(output, &response)?;
+                }
+                (Some(method), None) => {
+                    debug!("handling notification '{}'", method);
+                    for notification in self.handle_notification(method, params) {
+                        transport::write_message(output, &notification)?;
+                    }
+                }
+                // We make no requests of the client, so there are no responses to handle
+                (None, _) => (),
+            }
+        }
+
+        // The client went away without exiting
+        Ok(1)
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Json) -> Result<Json, ResponseError> {
+        match method {
+            "initialize" => Ok(self.initialize(params)),
+            _ if !self.initialized => Err(ResponseError::new(
+                SERVER_NOT_INITIALIZED,
+                "server has not been initialized",
+            )),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => Err(ResponseError::new(
+                METHOD_NOT_FOUND,
+                format!("unsupported request '{}'", method),
+            )),
+        }
+    }
+
+    /// Handles a notification, returning any notifications to be sent in response
+    fn handle_notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let document = params.get("textDocument");
+        let uri = document
+            .and_then(|doc| doc.get("uri"))
+            .and_then(Json::as_str)
+            .map(str::to_string);
+        match (method, uri) {
+            ("textDocument/didOpen", Some(uri)) => {
+                match document
+                    .and_then(|doc| doc.get("text"))
+                    .and_then(Json::as_str)
+                {
+                    Some(text) => vec![self.update(uri, text.to_string())],
+                    None => vec![],
+                }
+            }
+            // We only support full document synchronization, so the last change is the content
+            ("textDocument/didChange", Some(uri)) => {
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                match text {
+                    Some(text) => vec![self.update(uri, text.to_string())],
+                    None => vec![],
+                }
+            }
+            // Included files may have changed on disk, so we analyze the document again on save
+            ("textDocument/didSave", Some(uri)) => match self.documents.get(&uri) {
+                Some(doc) => {
+                    let text = doc.file.source().to_string();
+                    vec![self.update(uri, text)]
+                }
+                None => vec![],
+            },
+            ("textDocument/didClose", Some(uri)) => match self.documents.remove(&uri) {
+                Some(doc) => {
+                    self.analyzer.forget(doc);
+                    vec![publish_diagnostics(uri, vec![])]
+                }
+                None => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    fn initialize(&mut self, params: &Json) -> Json {
+        if let Some(root) = params
+            .get("rootUri")
+            .and_then(Json::as_str)
+            .and_then(uri_to_path)
+        {
+            self.analyzer.add_include_path(root.join("include"));
+        }
+        self.initialized = true;
+
+        Json::object([
+            (
+                "capabilities",
+                Json::object([
+                    (
+                        "textDocumentSync",
+                        Json::object([
+                            ("openClose", true.into()),
+                            // Full synchronization
+                            ("change", 1u32.into()),
+                            ("save", true.into()),
+                        ]),
+                    ),
+                    ("definitionProvider", true.into()),
+                    ("hoverProvider", true.into()),
+                    ("documentSymbolProvider", true.into()),
+                    (
+                        "completionProvider",
+                        Json::object([(
+                            "triggerCharacters",
+                            Json::Array(vec!["#".into(), ".".into(), "?".into()]),
+                        )]),
+                    ),
+                ]),
+            ),
+            (
+                "serverInfo",
+                Json::object([
+                    ("name", "firefly".into()),
+                    ("version", crate::FIREFLY_RELEASE.into()),
+                ]),
+            ),
+        ])
+    }
+
+    /// Analyzes the new contents of a document, returning the notification publishing its diagnostics
+    fn update(&mut self, uri: String, text: String) -> Json {
+        let path = uri_to_path(&uri).unwrap_or_else(|| PathBuf::from(&uri));
+        // The document supersedes any copy we loaded from disk
+        if let Some(external) = self.external.remove(&path) {
+            self.analyzer.forget(external);
+        }
+        let previous = self.documents.remove(&uri);
+        let app = self.application();
+        let doc = self
+            .analyzer
+            .analyze(uri.clone(), path, text, previous, &app);
+        let diagnostics = doc
+            .diagnostics
+            .iter()
+            .map(|diagnostic| self.diagnostic(&doc, diagnostic))
+            .collect();
+        self.documents.insert(uri.clone(), doc);
+        publish_diagnostics(uri, diagnostics)
+    }
+
+    /// Returns the metadata of all modules known to the server, as if they formed an application
+    fn application(&self) -> ApplicationMetadata {
+        let mut app = ApplicationMetadata {
+            name: self.options.app.name,
+            modules: BTreeMap::new(),
+            behaviours: BTreeMap::new(),
+        };
+        for doc in self.documents.values().chain(self.external.values()) {
+            if let Some(parsed) = doc.parsed.as_ref() {
+                app.modules
+                    .insert(parsed.module.name(), parsed.metadata.clone());
+            }
+        }
+        app
+    }
+
+    fn definition(&mut self, params: &Json) -> Result<Json, ResponseError> {
+        let (uri, offset) = self.document_position(params)?;
+
+        // Includes are resolved by the preprocessor, so we find them by the directive's span
+        let doc = &self.documents[&uri];
+        let include = doc.parsed.as_ref().and_then(|parsed| {
+            parsed.includes.iter().find(|(span, _)| {
+                span.source_id() == doc.file.id()
+                    && (span.start_index().to_usize()..=span.end_index().to_usize())
+                        .contains(&offset)
+            })
+        });
+        if let Some((_, path)) = include {
+            let start = Json::object([("line", 0u32.into()), ("character", 0u32.into())]);
+            return Ok(Json::object([
+                ("uri", path_to_uri(path).into()),
+                (
+                    "range",
+                    Json::object([("start", start.clone()), ("end", start)]),
+                ),
+            ]));
+        }
+
+        let reference = match query::reference_at(&doc.tokens, offset) {
+            Some(reference) => reference,
+            None => return Ok(Json::Null),
+        };
+        let location = self
+            .resolve(&uri, &reference)
+            .and_then(|definition| self.location(definition.span));
+        Ok(location.into())
+    }
+
+    fn hover(&mut self, params: &Json) -> Result<Json, ResponseError> {
+        let (uri, offset) = self.document_position(params)?;
+        let reference = match query::reference_at(&self.documents[&uri].tokens, offset) {
+            Some(reference) => reference,
+            None => return Ok(Json::Null),
+        };
+        let hover = self.resolve(&uri, &reference).map(|definition| {
+            Json::object([(
+                "contents",
+                Json::object([
+                    ("kind", "markdown".into()),
+                    (
+                        "value",
+                        format!("```erlang\n{}\n```", definition.hover).into(),
+                    ),
+                ]),
+            )])
+        });
+        Ok(hover.into())
+    }
+
+    fn document_symbols(&self, params: &Json) -> Result<Json, ResponseError> {
+        let uri = document_uri(params)?;
+        let doc = self.document(uri)?;
+        let parsed = match doc.parsed.as_ref() {
+            Some(parsed) => parsed,
+            None => return Ok(Json::Array(vec![])),
+        };
+
+        // Only symbols defined in the document itself are listed, not those from included files
+        let module = &parsed.module;
+        let id = module.span.source_id();
+        let mut symbols: Vec<(SourceSpan, Json)> = vec![];
+        for function in module.functions.values() {
+            let name = format!("{}/{}", function.name, function.arity);
+            symbols.push((
+                function.span,
+                self.symbol(
+                    name,
+                    SYMBOL_FUNCTION,
+                    function.span,
+                    function.name.span,
+                    vec![],
+                ),
+            ));
+        }
+        for record in module.records.values() {
+            let fields = record
+                .fields
+                .iter()
+                .map(|field| {
+                    let name = field.name.to_string();
+                    self.symbol(name, SYMBOL_FIELD, field.span, field.name.span, vec![])
+                })
+                .collect();
+            let name = format!("#{}", record.name);
+            symbols.push((
+                record.span,
+                self.symbol(name, SYMBOL_STRUCT, record.span, record.name.span, fields),
+            ));
+        }
+        for ty in module.types.values() {
+            let name = format!("{}/{}", ty.name, ty.params.len());
+            symbols.push((
+                ty.span,
+                self.symbol(name, SYMBOL_TYPE_PARAMETER, ty.span, ty.name.span, vec![]),
+            ));
+        }
+        for define in parsed.macros.iter() {
+            let name = format!("?{}", define.name);
+            symbols.push((
+                define.span(),
+                self.symbol(
+                    name,
+                    SYMBOL_CONSTANT,
+                    define.span(),
+                    define.name.span(),
+                    vec![],
+                ),
+            ));
+        }
+
+        symbols.retain(|(span, _)| span.source_id() == id);
+        symbols.sort_by_key(|(span, _)| span.start());
+        Ok(Json::Array(
+            symbols.into_iter().map(|(_, symbol)| symbol).collect(),
+        ))
+    }
+
+    fn completion(&self, params: &Json) -> Result<Json, ResponseError> {
+        let (uri, offset) = self.document_position(params)?;
+        let doc = &self.documents[&uri];
+        let parsed = match doc.parsed.as_ref() {
+            Some(parsed) => parsed,
+            None => return Ok(Json::Array(vec![])),
+        };
+
+        let module = &parsed.module;
+        let item = |label: String, kind: u32, detail: String| {
+            Json::object([
+                ("label", label.into()),
+                ("kind", kind.into()),
+                ("detail", detail.into()),
+            ])
+        };
+        let items = match query::completion_context(&doc.tokens, offset) {
+            CompletionContext::Functions => module
+                .functions
+                .values()
+                .map(|function| {
+                    let name = FunctionName::new(module.name(), function.name.name, function.arity);
+                    let detail = match module.specs.get(&name) {
+                        Some(spec) => self.source_text(spec.span),
+                        None => format!("{}/{}", function.name, function.arity),
+                    };
+                    item(function.name.to_string(), COMPLETION_FUNCTION, detail)
+                })
+                .collect(),
+            CompletionContext::Records => module
+                .records
+                .values()
+                .map(|record| {
+                    let detail = self.source_text(record.span);
+                    item(record.name.to_string(), COMPLETION_STRUCT, detail)
+                })
+                .collect(),
+            CompletionContext::RecordFields(name) => match module.record(name) {
+                Some(record) => record
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let detail = self.source_text(field.span);
+                        item(field.name.to_string(), COMPLETION_FIELD, detail)
+                    })
+                    .collect(),
+                None => vec![],
+            },
+            CompletionContext::Macros => parsed
+                .macros
+                .iter()
+                .map(|define| {
+                    let detail = self.source_text(define.span());
+                    item(define.name.to_string(), COMPLETION_CONSTANT, detail)
+                })
+                .collect(),
+        };
+
+        Ok(Json::Array(items))
+    }
+
+    /// Resolves a reference from the given document to the definition of the referenced entity
+    fn resolve(&mut self, uri: &str, reference: &Reference) -> Option<Definition> {
+        let doc = self.documents.get(uri)?;
+        let parsed = doc.parsed.as_ref()?;
+        match *reference {
+            Reference::Function {
+                module,
+                name,
+                arity,
+            } => {
+                let this = parsed.module.name();
+                let module = match module {
+                    Some(module) if module != this => module,
+                    _ => match self.find_function(&parsed.module, name, arity) {
+                        Some(definition) => return Some(definition),
+                        // Calls to imported functions resolve to the module they were imported from
+                        None if module.is_none() => parsed
+                            .module
+                            .imports
+                            .iter()
+                            .find(|(import, _)| {
+                                import.function == name
+                                    && arity.map(|a| a == import.arity as usize).unwrap_or(true)
+                            })
+                            .map(|(_, signature)| signature.module)?,
+                        None => return None,
+                    },
+                };
+                // Modules which are not open are loaded from the directory of the referencing document
+                if let Some(dir) = doc.path.parent().map(Path::to_path_buf) {
+                    self.load_module(module, &dir);
+                }
+                let parsed = self.module(module)?;
+                self.find_function(&parsed.module, name, arity)
+            }
+            Reference::Record(name) => {
+                let record = parsed.module.record(name)?;
+                Some(Definition {
+                    span: record.name.span,
+                    hover: self.source_text(record.span),
+                })
+            }
+            Reference::RecordField { record, field } => {
+                let record = parsed.module.record(record)?;
+                let field = record.fields.iter().find(|f| f.name.name == field)?;
+                Some(Definition {
+                    span: field.name.span,
+                    hover: self.source_text(field.span),
+                })
+            }
+            Reference::Macro(name) => {
+                let define = parsed.macros.iter().find(|d| d.name.symbol() == name)?;
+                Some(Definition {
+                    span: define.name.span(),
+                    hover: self.source_text(define.span()),
+                })
+            }
+        }
+    }
+
+    fn find_function(
+        &self,
+        module: &syntax_erl::Module,
+        name: Symbol,
+        arity: Option<usize>,
+    ) -> Option<Definition> {
+        let function = module.functions.values().find(|function| {
+            function.name.name == name
+                && arity
+                    .map(|arity| arity == function.arity as usize)
+                    .unwrap_or(true)
+        })?;
+        let spec = module
+            .specs
+            .get(&FunctionName::new(module.name(), name, function.arity));
+        let hover = match spec {
+            Some(spec) => self.source_text(spec.span),
+            None => format!("{}/{}", name, function.arity),
+        };
+        Some(Definition {
+            span: function.name.span,
+            hover,
+        })
+    }
+
+    /// Finds the module with the given name among the documents known to the server
+    fn module(&self, name: Symbol) -> Option<&Parsed> {
+        self.documents
+            .values()
+            .chain(self.external.values())
+            .filter_map(|doc| doc.parsed.as_ref())
+            .find(|parsed| parsed.module.name() == name)
+    }
+
+    /// Loads the module with the given name from `dir`, if it is not already known to the server
+    fn load_module(&mut self, name: Symbol, dir: &Path) {
+        if self.module(name).is_some() {
+            return;
+        }
+        let path = dir.join(format!("{}.erl", name));
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+        let app = self.application();
+        let doc = self
+            .analyzer
+            .analyze(path_to_uri(&path), path.clone(), text, None, &app);
+        self.external.insert(path, doc);
+    }
+
+    fn document(&self, uri: &str) -> Result<&Document, ResponseError> {
+        self.documents.get(uri).ok_or_else(|| {
+            ResponseError::new(INVALID_PARAMS, format!("unknown document '{}'", uri))
+        })
+    }
+
+    /// Returns the URI of the document and the byte offset in it given by request parameters
+    /// of the `TextDocumentPositionParams` shape
+    fn document_position(&self, params: &Json) -> Result<(String, usize), ResponseError> {
+        let uri = document_uri(params)?;
+        let doc = self.document(uri)?;
+        let position = params.get("position");
+        let line = position.and_then(|p| p.get("line")).and_then(Json::as_u64);
+        let character = position
+            .and_then(|p| p.get("character"))
+            .and_then(Json::as_u64);
+        match (line, character) {
+            (Some(line), Some(character)) => {
+                let offset = analysis::offset_at(&doc.file, line as usize, character as usize);
+                Ok((uri.to_string(), offset))
+            }
+            _ => Err(ResponseError::new(INVALID_PARAMS, "invalid position")),
+        }
+    }
+
+    /// Converts a diagnostic to its representation in the protocol
+    ///
+    /// Diagnostics in files included by the document are reported at the directive which
+    /// included them.
+    fn diagnostic(&self, doc: &Document, diagnostic: &Diagnostic) -> Json {
+        let codemap = self.analyzer.codemap();
+        let id = doc.file.id();
+        let primary = diagnostic
+            .labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary);
+        let mut range = None;
+        if let Some(label) = primary {
+            let mut file_id = label.file_id;
+            let mut span = label.range.clone();
+            loop {
+                if file_id == id {
+                    range = Some(range_json(&doc.file, span));
+                    break;
+                }
+                match codemap.parent(file_id) {
+                    Some(parent) => {
+                        file_id = parent.source_id();
+                        span = parent.start_index().to_usize()..parent.end_index().to_usize();
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let mut message = diagnostic.message.clone();
+        if let Some(label) = primary.filter(|label| !label.message.is_empty()) {
+            let _ = write!(&mut message, "\n{}", label.message);
+        }
+        for note in diagnostic.notes.iter() {
+            let _ = write!(&mut message, "\n{}", note);
+        }
+
+        let severity: u32 = match diagnostic.severity {
+            Severity::Bug | Severity::Error => 1,
+            Severity::Warning => 2,
+            Severity::Note => 3,
+            Severity::Help => 4,
+        };
+        let related = diagnostic
+            .labels
+            .iter()
+            .filter(|label| label.style == LabelStyle::Secondary)
+            .filter_map(|label| {
+                let file = codemap.get(label.file_id).ok()?;
+                Some(Json::object([
+                    (
+                        "location",
+                        Json::object([
+                            ("uri", self.uri(&file).into()),
+                            ("range", range_json(&file, label.range.clone())),
+                        ]),
+                    ),
+                    ("message", label.message.as_str().into()),
+                ]))
+            })
+            .collect::<Vec<_>>();
+
+        let start = Json::object([("line", 0u32.into()), ("character", 0u32.into())]);
+        let mut fields = vec![
+            (
+                "range",
+                range.unwrap_or_else(|| Json::object([("start", start.clone()), ("end", start)])),
+            ),
+            ("severity", severity.into()),
+            ("source", "firefly".into()),
+            ("message", message.into()),
+        ];
+        if let Some(code) = diagnostic.code.as_deref() {
+            fields.push(("code", code.into()));
+        }
+        if !related.is_empty() {
+            fields.push(("relatedInformation", Json::Array(related)));
+        }
+        Json::object(fields)
+    }
+
+    fn symbol(
+        &self,
+        name: String,
+        kind: u32,
+        span: SourceSpan,
+        selection: SourceSpan,
+        children: Vec<Json>,
+    ) -> Json {
+        let codemap = self.analyzer.codemap();
+        let file = codemap.get(span.source_id()).unwrap();
+        Json::object([
+            ("name", name.into()),
+            ("kind", kind.into()),
+            ("range", range_json(&file, span_range(span))),
+            ("selectionRange", range_json(&file, span_range(selection))),
+            ("children", Json::Array(children)),
+        ])
+    }
+
+    fn location(&self, span: SourceSpan) -> Option<Json> {
+        let file = self.analyzer.codemap().get(span.source_id()).ok()?;
+        Some(Json::object([
+            ("uri", self.uri(&file).into()),
+            ("range", range_json(&file, span_range(span))),
+        ]))
+    }
+
+    /// Returns the URI of the given source file
+    ///
+    /// Every revision of a document is a separate source file with the same name, so we match
+    /// documents by name to return the URI given by the client.
+    fn uri(&self, file: &SourceFile) -> String {
+        self.documents
+            .values()
+            .chain(self.external.values())
+            .find(|doc| doc.file.name() == file.name())
+            .map(|doc| doc.uri.clone())
+            .unwrap_or_else(|| match file.name() {
+                FileName::Real(path) => path_to_uri(path),
+                FileName::Virtual(name) => path_to_uri(Path::new(&**name)),
+            })
+    }
+
+    fn source_text(&self, span: SourceSpan) -> String {
+        self.analyzer
+            .codemap()
+            .source_slice(span.source_id(), span)
+            .map(str::to_string)
+            .unwrap_or_default()
+    }
+}
+
+fn publish_diagnostics(uri: String, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object([
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn document_uri(params: &Json) -> Result<&str, ResponseError> {
+    params
+        .get("textDocument")
+        .and_then(|doc| doc.get("uri"))
+        .and_then(Json::as_str)
+        .ok_or_else(|| ResponseError::new(INVALID_PARAMS, "missing document uri"))
+}
+
+fn span_range(span: SourceSpan) -> Range<usize> {
+    span.start_index().to_usize()..span.end_index().to_usize()
+}
+
+fn range_json(file: &SourceFile, range: Range<usize>) -> Json {
+    let position = |offset| {
+        let (line, character) = analysis::position_of(file, offset);
+        Json::object([("line", line.into()), ("character", character.into())])
+    };
+    Json::object([
+        ("start", position(range.start)),
+        ("end", position(range.end)),
+    ])
+}
+
+/// Converts a `file` URI to a path
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Converts a path to a `file` URI
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(b as char)
+            }
+            _ => {
+                let _ = write!(&mut uri, "%{:02X}", b);
+            }
+        }
+    }
+    uri
+}
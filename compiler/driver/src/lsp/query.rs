@@ -0,0 +1,330 @@
+use firefly_intern::Symbol;
+use firefly_number::Int;
+use firefly_syntax_erl::{LexicalToken, Token};
+
+/// An entity referenced at some position in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    /// A function, by name and arity if known, optionally qualified by its module
+    Function {
+        module: Option<Symbol>,
+        name: Symbol,
+        arity: Option<usize>,
+    },
+    Record(Symbol),
+    RecordField {
+        record: Symbol,
+        field: Symbol,
+    },
+    Macro(Symbol),
+}
+
+/// The kind of completions which are applicable at some position in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionContext {
+    /// Local functions, e.g. at the start of an expression
+    Functions,
+    /// Record names, i.e. following `#`
+    Records,
+    /// The fields of the given record, e.g. following `#rec.` or within `#rec{..}`
+    RecordFields(Symbol),
+    /// Macro names, i.e. following `?`
+    Macros,
+}
+
+/// Determines the entity referenced by the token at the given byte offset, if any
+///
+/// This works on tokens rather than the syntax tree, so that it continues to work while the
+/// document is being edited, and does not parse.
+pub fn reference_at(tokens: &[LexicalToken], offset: usize) -> Option<Reference> {
+    let index = token_at(tokens, offset)?;
+    let token = &tokens[index];
+    let name = match token.1 {
+        Token::Atom(name) => name,
+        Token::Ident(name) if prev(tokens, index, 1) == Some(&Token::Question) => {
+            return Some(Reference::Macro(name));
+        }
+        _ => return None,
+    };
+
+    match prev(tokens, index, 1) {
+        Some(Token::Question) => return Some(Reference::Macro(name)),
+        Some(Token::Pound) => return Some(Reference::Record(name)),
+        Some(Token::Dot) if is_record_access(tokens, index - 1) => {
+            if let Some(Token::Atom(record)) = prev(tokens, index, 2) {
+                return Some(Reference::RecordField {
+                    record: *record,
+                    field: name,
+                });
+            }
+        }
+        Some(Token::LBrace | Token::Comma) => {
+            if let Some(record) = enclosing_record(tokens, index) {
+                return Some(Reference::RecordField {
+                    record,
+                    field: name,
+                });
+            }
+        }
+        _ => (),
+    }
+
+    let module = match (prev(tokens, index, 1), prev(tokens, index, 2)) {
+        (Some(Token::Colon), Some(Token::Atom(module))) => Some(*module),
+        _ => None,
+    };
+    match (next(tokens, index, 1), next(tokens, index, 2)) {
+        // A call, e.g. `foo(..)` or `m:foo(..)`
+        (Some(Token::LParen), _) => Some(Reference::Function {
+            module,
+            name,
+            arity: Some(count_args(tokens, index + 1)),
+        }),
+        // A function reference, e.g. `fun foo/1` or in an attribute like `-export([foo/1])`
+        (Some(Token::Slash), Some(Token::Integer(Int::Small(arity)))) => {
+            Some(Reference::Function {
+                module,
+                name,
+                arity: usize::try_from(*arity).ok(),
+            })
+        }
+        _ if module.is_some() => Some(Reference::Function {
+            module,
+            name,
+            arity: None,
+        }),
+        _ => None,
+    }
+}
+
+/// Determines the kind of completions applicable at the given byte offset
+pub fn completion_context(tokens: &[LexicalToken], offset: usize) -> CompletionContext {
+    // The tokens which precede the cursor, ignoring the partial name being typed, if any
+    let mut end = tokens.partition_point(|token| start(token) < offset);
+    if end > 0 {
+        if let Token::Atom(_) | Token::Ident(_) = tokens[end - 1].1 {
+            if end_of(&tokens[end - 1]) >= offset {
+                end -= 1;
+            }
+        }
+    }
+    let preceding = &tokens[..end];
+
+    match preceding {
+        [.., LexicalToken(_, Token::Question, _)] => CompletionContext::Macros,
+        [.., LexicalToken(_, Token::Pound, _)] => CompletionContext::Records,
+        [.., LexicalToken(_, Token::Pound, _), LexicalToken(_, Token::Atom(record), _), LexicalToken(_, Token::Dot, _)] => {
+            CompletionContext::RecordFields(*record)
+        }
+        [.., LexicalToken(_, Token::LBrace | Token::Comma, _)] => {
+            match enclosing_record(tokens, end) {
+                Some(record) => CompletionContext::RecordFields(record),
+                None => CompletionContext::Functions,
+            }
+        }
+        _ => CompletionContext::Functions,
+    }
+}
+
+/// Returns the index of the token containing the given byte offset
+///
+/// If the offset lies between two tokens, e.g. at the end of an identifier, the preceding token
+/// is chosen.
+fn token_at(tokens: &[LexicalToken], offset: usize) -> Option<usize> {
+    let index = tokens.partition_point(|token| end_of(token) < offset);
+    let token = tokens.get(index)?;
+    if start(token) <= offset {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn start(token: &LexicalToken) -> usize {
+    token.0.index().to_usize()
+}
+
+#[inline]
+fn end_of(token: &LexicalToken) -> usize {
+    token.2.index().to_usize()
+}
+
+fn prev(tokens: &[LexicalToken], index: usize, n: usize) -> Option<&Token> {
+    index
+        .checked_sub(n)
+        .and_then(|i| tokens.get(i))
+        .map(|token| &token.1)
+}
+
+fn next(tokens: &[LexicalToken], index: usize, n: usize) -> Option<&Token> {
+    tokens.get(index + n).map(|token| &token.1)
+}
+
+/// Returns the name of the record whose fields are being constructed or matched at `index`, e.g.
+/// `#rec{a = 1, |}`, by searching backwards for the unmatched opening brace
+fn enclosing_record(tokens: &[LexicalToken], index: usize) -> Option<Symbol> {
+    let mut depth = 0usize;
+    for i in (0..index).rev() {
+        match tokens[i].1 {
+            Token::RParen | Token::RBrace | Token::RBracket | Token::BinaryEnd | Token::End => {
+                depth += 1
+            }
+            Token::LParen | Token::LBracket | Token::BinaryStart => {
+                depth = depth.checked_sub(1)?;
+            }
+            Token::LBrace if depth > 0 => depth -= 1,
+            Token::LBrace => {
+                return match (prev(tokens, i, 1), prev(tokens, i, 2)) {
+                    (Some(Token::Atom(record)), Some(Token::Pound)) => Some(*record),
+                    _ => None,
+                };
+            }
+            // Record field accesses, e.g. `R#rec.field`, are the only dots within an expression
+            Token::Dot if is_record_access(tokens, i) => (),
+            // Clauses and forms do not span record expressions
+            Token::Dot | Token::Semicolon | Token::RightStab => return None,
+            _ => (),
+        }
+    }
+    None
+}
+
+fn is_record_access(tokens: &[LexicalToken], dot: usize) -> bool {
+    matches!(
+        (prev(tokens, dot, 1), prev(tokens, dot, 2)),
+        (Some(Token::Atom(_)), Some(Token::Pound))
+    )
+}
+
+/// Counts the arguments of the call whose opening parenthesis is at `index`
+fn count_args(tokens: &[LexicalToken], index: usize) -> usize {
+    let mut depth = 0usize;
+    let mut args = 0;
+    for (i, token) in tokens.iter().enumerate().skip(index) {
+        match token.1 {
+            Token::LParen | Token::LBrace | Token::LBracket | Token::BinaryStart => {
+                depth += 1;
+                continue;
+            }
+            // Block expressions may contain commas which do not separate arguments
+            Token::Begin | Token::Case | Token::If | Token::Receive | Token::Try => depth += 1,
+            // Unlike `fun foo/1`, anonymous functions are terminated by `end`
+            Token::Fun if matches!(next(tokens, i, 1), Some(Token::LParen | Token::Ident(_))) => {
+                depth += 1
+            }
+            Token::RParen | Token::RBrace | Token::RBracket | Token::BinaryEnd | Token::End => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Token::Comma if depth == 1 => args += 1,
+            // The call is incomplete, so count what we have seen so far
+            Token::Dot if !is_record_access(tokens, i) => break,
+            _ => (),
+        }
+        if args == 0 && depth == 1 {
+            args = 1;
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_parser::{FileMapSource, Scanner, Source};
+    use firefly_syntax_erl::Lexer;
+    use firefly_util::diagnostics::CodeMap;
+
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<LexicalToken> {
+        let codemap = CodeMap::new();
+        let id = codemap.add("test.erl", source.to_string());
+        let file = codemap.get(id).unwrap();
+        Lexer::new(Scanner::new(FileMapSource::new(file)))
+            .filter_map(Result::ok)
+            .filter(|token| !matches!(token.1, Token::EOF | Token::Comment))
+            .collect()
+    }
+
+    fn reference(source: &str) -> Option<Reference> {
+        let offset = source.find('|').unwrap();
+        let source = source.replace('|', "");
+        reference_at(&tokens(&source), offset)
+    }
+
+    fn completion(source: &str) -> CompletionContext {
+        let offset = source.find('|').unwrap();
+        let source = source.replace('|', "");
+        completion_context(&tokens(&source), offset)
+    }
+
+    #[test]
+    fn references() {
+        let foo = Symbol::intern("foo");
+        let rec = Symbol::intern("rec");
+        let field = Symbol::intern("field");
+
+        assert_eq!(
+            reference("bar() -> fo|o(1, {2, 3}, fun() -> a, b end)."),
+            Some(Reference::Function {
+                module: None,
+                name: foo,
+                arity: Some(3),
+            })
+        );
+        assert_eq!(
+            reference("bar() -> lists:fo|o()."),
+            Some(Reference::Function {
+                module: Some(Symbol::intern("lists")),
+                name: foo,
+                arity: Some(0),
+            })
+        );
+        assert_eq!(
+            reference("-export([foo|/2])."),
+            Some(Reference::Function {
+                module: None,
+                name: foo,
+                arity: Some(2),
+            })
+        );
+        assert_eq!(reference("bar() -> #re|c{}."), Some(Reference::Record(rec)));
+        assert_eq!(
+            reference("bar(R) -> R#rec.fi|eld."),
+            Some(Reference::RecordField { record: rec, field })
+        );
+        assert_eq!(
+            reference("bar() -> #rec{a = [1, 2], fie|ld = 1}."),
+            Some(Reference::RecordField { record: rec, field })
+        );
+        assert_eq!(
+            reference("bar() -> ?FO|O."),
+            Some(Reference::Macro(Symbol::intern("FOO")))
+        );
+        assert_eq!(reference("bar() -> ?fo|o."), Some(Reference::Macro(foo)));
+        assert_eq!(reference("bar(X|) -> X."), None);
+    }
+
+    #[test]
+    fn completions() {
+        let rec = Symbol::intern("rec");
+
+        assert_eq!(completion("bar() -> |"), CompletionContext::Functions);
+        assert_eq!(completion("bar() -> fo|"), CompletionContext::Functions);
+        assert_eq!(completion("bar() -> ?|"), CompletionContext::Macros);
+        assert_eq!(completion("bar() -> #|"), CompletionContext::Records);
+        assert_eq!(completion("bar() -> #r|"), CompletionContext::Records);
+        assert_eq!(
+            completion("bar(R) -> R#rec.|"),
+            CompletionContext::RecordFields(rec)
+        );
+        assert_eq!(
+            completion("bar() -> #rec{a = 1, f|"),
+            CompletionContext::RecordFields(rec)
+        );
+        assert_eq!(completion("bar() -> {a, |"), CompletionContext::Functions);
+    }
+}
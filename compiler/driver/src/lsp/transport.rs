@@ -0,0 +1,49 @@
+use std::io::{self, BufRead, Write};
+
+use firefly_util::json::Json;
+
+/// Reads the next message sent by the client
+///
+/// Messages are framed by a header block containing the `Content-Length` of the JSON payload, as
+/// described by the base protocol. Returns `Ok(None)` once the input has been closed.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Json>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing content-length"))?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    let content = String::from_utf8(content)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Json::parse(&content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Writes a message to the client
+pub fn write_message<W: Write>(writer: &mut W, message: &Json) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
@@ -11,6 +11,7 @@ mod options;
 mod output;
 mod project;
 mod sanitizer;
+mod workspace;
 
 pub use self::app::*;
 pub use self::cfguard::*;
@@ -26,3 +27,4 @@ pub use self::options::{
 pub use self::output::{calculate_outputs, OutputType, OutputTypeError, OutputTypes};
 pub use self::project::*;
 pub use self::sanitizer::*;
+pub use self::workspace::{sort_applications, BuildTool, ErlOpts, Workspace};
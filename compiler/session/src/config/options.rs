@@ -55,6 +55,7 @@ pub struct Options {
     pub project_type: ProjectType,
     pub output_types: OutputTypes,
     pub color: ColorChoice,
+    pub error_format: ErrorFormat,
     pub warnings_as_errors: bool,
     pub no_warn: bool,
     pub verbosity: Verbosity,
@@ -76,12 +77,17 @@ pub struct Options {
     pub current_dir: PathBuf,
     /// This is the set of inputs per application
     pub input_files: HashMap<Symbol, Vec<FileName>>,
+    /// The preprocessor options of each application in a rebar3 or erlang.mk project
+    pub erl_opts: HashMap<Symbol, ErlOpts>,
+    /// Directories containing the applications of a rebar3 or erlang.mk project
+    pub lib_dirs: Vec<PathBuf>,
     pub output_file: Option<PathBuf>,
     pub output_dir: Option<PathBuf>,
     // Remap source path prefixes in all output (messages, object files, debug, etc.).
     pub source_path_prefix: Vec<(PathBuf, PathBuf)>,
     pub search_paths: Vec<SearchPath>,
     pub include_path: VecDeque<PathBuf>,
+    pub code_path: VecDeque<PathBuf>,
     pub link_libraries: Vec<(String, Option<String>, NativeLibraryKind)>,
     pub defines: HashMap<String, Option<String>>,
 
@@ -116,21 +122,32 @@ impl Options {
         });
 
         let output_file = args.value_of_os("output").map(PathBuf::from);
+        let mut workspace = None;
         let (app, dependencies, input_files) = match args.values_of_os("inputs") {
             None => {
-                // By default treat the current working directory as a standard Erlang app
-                let inputs = vec![FileName::Real(cwd.clone())];
-                let app = detect_app(
-                    &diagnostics,
-                    codemap,
-                    args,
-                    cwd.as_path(),
-                    output_file.as_deref(),
-                    inputs.as_slice(),
-                )?;
-                let mut input_files = HashMap::new();
-                input_files.insert(app.name, inputs);
-                (app, HashMap::default(), input_files)
+                // By default treat the current working directory as a standard Erlang app, unless
+                // it is the root of a rebar3 or erlang.mk project
+                let project = if has_app_args(args) {
+                    None
+                } else {
+                    Workspace::load(&diagnostics, codemap.clone(), cwd.as_path())?
+                };
+                if let Some(ws) = project {
+                    workspace.insert(ws).inputs()
+                } else {
+                    let inputs = vec![FileName::Real(cwd.clone())];
+                    let app = detect_app(
+                        &diagnostics,
+                        codemap,
+                        args,
+                        cwd.as_path(),
+                        output_file.as_deref(),
+                        inputs.as_slice(),
+                    )?;
+                    let mut input_files = HashMap::new();
+                    input_files.insert(app.name, inputs);
+                    (app, HashMap::default(), input_files)
+                }
             }
             Some(mut input_args) => {
                 let num_inputs = input_args.len();
@@ -192,7 +209,15 @@ impl Options {
                         if files.is_empty() {
                             // We have only directories to process, create an app for each one and register the inputs
                             // If we have only a single directory, treat it as the root application
-                            if dirs.len() == 1 {
+                            let project = match dirs.as_slice() {
+                                [dir] if !has_app_args(args) => {
+                                    Workspace::load(&diagnostics, codemap.clone(), dir.as_ref())?
+                                }
+                                _ => None,
+                            };
+                            if let Some(ws) = project {
+                                workspace.insert(ws).inputs()
+                            } else if dirs.len() == 1 {
                                 let app = detect_app(
                                     &diagnostics,
                                     codemap,
@@ -283,6 +308,7 @@ impl Options {
         };
         let output_types = OutputTypes::parse_option(&option!("emit"), &args)?;
         let color_arg = ColorArg::parse_option(&option!("color"), &args)?;
+        let error_format = ErrorFormat::parse_option(&option!("error-format"), &args)?;
 
         let maybe_sysroot: Option<PathBuf> = ParseOption::parse_option(&option!("sysroot"), &args)?;
         let sysroot = match &maybe_sysroot {
@@ -370,6 +396,16 @@ impl Options {
                 include_path.push_front(PathBuf::from(value));
             }
         }
+        let mut code_path = VecDeque::new();
+        if let Some(values) = args.values_of_os("code-paths") {
+            for value in values {
+                code_path.push_front(PathBuf::from(value));
+            }
+        }
+        let (erl_opts, lib_dirs) = match workspace {
+            Some(ws) => (ws.erl_opts, ws.lib_dirs),
+            None => Default::default(),
+        };
 
         Ok(Self {
             app,
@@ -377,6 +413,7 @@ impl Options {
             project_type,
             output_types,
             color: color_arg.into(),
+            error_format,
             warnings_as_errors,
             no_warn,
             verbosity,
@@ -393,11 +430,14 @@ impl Options {
             debugging_opts,
             current_dir: cwd,
             input_files,
+            erl_opts,
+            lib_dirs,
             output_file,
             output_dir,
             source_path_prefix,
             search_paths,
             include_path,
+            code_path,
             link_libraries,
             defines,
             cli_forced_thinlto_off: false,
@@ -467,6 +507,7 @@ impl Options {
             project_type: ProjectType::Executable,
             output_types: OutputTypes::default(),
             color: ColorChoice::Auto,
+            error_format: ErrorFormat::Human,
             warnings_as_errors: false,
             no_warn: false,
             verbosity: Verbosity::Info,
@@ -483,17 +524,26 @@ impl Options {
             debugging_opts,
             current_dir: cwd,
             input_files: HashMap::default(),
+            erl_opts: HashMap::default(),
+            lib_dirs: vec![],
             output_file: None,
             output_dir: None,
             source_path_prefix: vec![],
             search_paths: Default::default(),
             include_path: Default::default(),
+            code_path: Default::default(),
             link_libraries: Default::default(),
             defines,
             cli_forced_thinlto_off: false,
         })
     }
 
+    /// Returns the dependencies of the root application, ordered such that every application comes
+    /// after the applications it depends on
+    pub fn sorted_dependencies(&self) -> anyhow::Result<Vec<Arc<App>>> {
+        sort_applications(&self.app, &self.dependencies)
+    }
+
     /// Determines whether we should invoke the linker for the program being compiled
     pub fn should_link(&self) -> bool {
         !self.debugging_opts.parse_only
@@ -530,6 +580,19 @@ impl Options {
         }
     }
 
+    /// Returns the directory in which the incremental compilation cache is stored, if enabled
+    pub fn incremental_dir(&self) -> Option<PathBuf> {
+        if self.codegen_opts.no_incremental {
+            return None;
+        }
+        Some(
+            self.codegen_opts
+                .incremental
+                .clone()
+                .unwrap_or_else(|| self.output_dir().join("incremental")),
+        )
+    }
+
     pub fn lto(&self) -> Lto {
         match self.codegen_opts.lto {
             LtoCli::No => Lto::No,
@@ -661,7 +724,7 @@ impl Options {
 
     #[inline]
     pub fn default_emitter(&self) -> Arc<dyn Emitter> {
-        default_emitter(self.verbosity, self.color)
+        default_emitter(self.verbosity, self.error_format, self.color)
     }
 
     pub fn create_diagnostics_handler(
@@ -670,7 +733,7 @@ impl Options {
         emitter: Option<Arc<dyn Emitter>>,
     ) -> Arc<DiagnosticsHandler> {
         create_diagnostics_handler(
-            emitter,
+            Some(emitter.unwrap_or_else(|| self.default_emitter())),
             codemap,
             self.verbosity,
             self.warnings_as_errors,
@@ -679,10 +742,16 @@ impl Options {
     }
 }
 
-fn default_emitter(verbosity: Verbosity, color: ColorChoice) -> Arc<dyn Emitter> {
-    match verbosity {
-        Verbosity::Silent => Arc::new(NullEmitter::new(color)),
-        _ => Arc::new(DefaultEmitter::new(color)),
+fn default_emitter(
+    verbosity: Verbosity,
+    error_format: ErrorFormat,
+    color: ColorChoice,
+) -> Arc<dyn Emitter> {
+    match (verbosity, error_format) {
+        (Verbosity::Silent, _) => Arc::new(NullEmitter::new(color)),
+        (_, ErrorFormat::Human) => Arc::new(DefaultEmitter::new(color)),
+        (_, ErrorFormat::Json) => Arc::new(JsonEmitter::new(color)),
+        (_, ErrorFormat::Sarif) => Arc::new(SarifEmitter::new(color)),
     }
 }
 
@@ -701,7 +770,8 @@ fn create_diagnostics_handler(
             display: DisplayConfig::default(),
         },
         codemap.clone(),
-        emitter.unwrap_or_else(|| default_emitter(verbosity, ColorChoice::Auto)),
+        emitter
+            .unwrap_or_else(|| default_emitter(verbosity, ErrorFormat::Human, ColorChoice::Auto)),
     ))
 }
 
@@ -741,6 +811,12 @@ fn try_load_app<'a>(
     }
 }
 
+/// Returns true if the root application was given explicitly, in which case project detection
+/// is skipped
+fn has_app_args<'a>(args: &ArgMatches<'a>) -> bool {
+    args.is_present("app") || args.is_present("app-name")
+}
+
 /// Fetch or generate application metadata based on the provided inputs
 fn detect_app<'a>(
     diagnostics: &DiagnosticsHandler,
@@ -58,6 +58,10 @@ pub struct CodegenOptions {
     /// If set, disables linking the default init, requiring one to be linked manually
     #[option]
     pub no_default_init: bool,
+    #[option(value_name("DIR"), takes_value(true))]
+    /// Cache the bytecode of each module in DIR, and reuse it for modules which are unchanged
+    /// (defaults to `incremental` in the output directory)
+    pub incremental: Option<PathBuf>,
     #[option(value_name("N"), takes_value(true), hidden(true))]
     /// Set the threshold for inlining a function
     pub inline_threshold: Option<u64>,
@@ -118,6 +122,9 @@ pub struct CodegenOptions {
     #[option]
     /// Run all passes except codegen; no output
     pub no_codegen: bool,
+    #[option]
+    /// Compile every module from scratch, ignoring the incremental compilation cache
+    pub no_incremental: bool,
     /// Compile without linking
     #[option]
     pub no_link: bool,
@@ -91,6 +91,9 @@ pub struct DebuggingOptions {
     /// Parse and run semantic analysis only; do not compile, assemble, or link
     pub analyze_only: bool,
     #[option]
+    /// Verify calls between modules, and report unused exports (always enabled with --bin)
+    pub xref: bool,
+    #[option]
     pub print_artifact_sizes: bool,
     #[option]
     /// Prints the LLVM optimization passes being run
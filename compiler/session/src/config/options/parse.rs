@@ -9,7 +9,7 @@ use firefly_target::spec::{
     CodeModel, LinkerFlavor, MergeFunctions, PanicStrategy, RelocModel, RelroLevel, SplitDebugInfo,
     Target, TargetError, TlsModel,
 };
-use firefly_util::diagnostics::ColorArg;
+use firefly_util::diagnostics::{ColorArg, ErrorFormat};
 
 use super::OptionInfo;
 
@@ -190,6 +190,19 @@ impl ParseOption for Target {
         }
     }
 }
+impl ParseOption for ErrorFormat {
+    fn parse_option<'a>(info: &OptionInfo, matches: &ArgMatches<'a>) -> clap::Result<Self> {
+        matches
+            .value_of(info.name)
+            .map_or(Ok(Self::Human), |s| s.parse())
+            .map_err(|_| {
+                invalid_value(
+                    info,
+                    "unrecognized error format, expected 'human', 'json' or 'sarif'",
+                )
+            })
+    }
+}
 impl ParseOption for ColorArg {
     fn parse_option<'a>(info: &OptionInfo, matches: &ArgMatches<'a>) -> clap::Result<Self> {
         let choice = match matches.value_of(info.name) {
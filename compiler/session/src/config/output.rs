@@ -40,6 +40,8 @@ impl FromStr for OutputTypeSpec {
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
 pub enum OutputType {
     AST,
+    /// EEP 48 documentation chunks
+    Docs,
     Core,
     Kernel,
     SSA,
@@ -57,6 +59,7 @@ impl FromStr for OutputType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "ast" => Ok(Self::AST),
+            "docs" => Ok(Self::Docs),
             "core" => Ok(Self::Core),
             "kernel" => Ok(Self::Kernel),
             "ssa" => Ok(Self::SSA),
@@ -86,6 +89,7 @@ impl OutputType {
     pub fn as_str(&self) -> &'static str {
         match self {
             &Self::AST => "ast",
+            &Self::Docs => "docs",
             &Self::Core => "core",
             &Self::Kernel => "kernel",
             &Self::SSA => "ssa",
@@ -102,6 +106,7 @@ impl OutputType {
     pub fn variants() -> &'static [OutputType] {
         &[
             Self::AST,
+            Self::Docs,
             Self::Core,
             Self::Kernel,
             Self::SSA,
@@ -124,6 +129,7 @@ impl OutputType {
          Supported output types:\n  \
            all       = Emit everything\n  \
            ast       = Abstract Syntax Tree\n  \
+           docs      = EEP 48 documentation chunks\n  \
            core      = Core Erlang\n  \
            kernel    = Kernel Erlang\n  \
            ssa       = SSA IR\n  \
@@ -141,6 +147,7 @@ impl OutputType {
     pub fn extension(&self) -> &'static str {
         match *self {
             Self::AST => "ast",
+            Self::Docs => "chunk",
             Self::Core => "core",
             Self::Kernel => "kernel",
             Self::SSA => "ssa",
@@ -304,7 +311,7 @@ impl OutputTypes {
 
     pub fn should_generate_ssa(&self) -> bool {
         self.0.keys().any(|k| match *k {
-            OutputType::AST | OutputType::Core | OutputType::Kernel => false,
+            OutputType::AST | OutputType::Docs | OutputType::Core | OutputType::Kernel => false,
             _ => true,
         })
     }
@@ -312,6 +319,7 @@ impl OutputTypes {
     pub fn should_generate_mlir(&self) -> bool {
         self.0.keys().any(|k| match *k {
             OutputType::AST
+            | OutputType::Docs
             | OutputType::Core
             | OutputType::Kernel
             | OutputType::SSA
@@ -322,7 +330,11 @@ impl OutputTypes {
 
     pub fn should_generate_bytecode(&self) -> bool {
         self.0.keys().any(|k| match *k {
-            OutputType::AST | OutputType::Core | OutputType::Kernel | OutputType::SSA => false,
+            OutputType::AST
+            | OutputType::Docs
+            | OutputType::Core
+            | OutputType::Kernel
+            | OutputType::SSA => false,
             _ => true,
         })
     }
@@ -330,6 +342,7 @@ impl OutputTypes {
     pub fn should_generate_llvm(&self) -> bool {
         self.0.keys().any(|k| match *k {
             OutputType::AST
+            | OutputType::Docs
             | OutputType::Core
             | OutputType::Kernel
             | OutputType::SSA
@@ -341,6 +354,7 @@ impl OutputTypes {
     pub fn should_codegen(&self) -> bool {
         self.0.keys().any(|k| match *k {
             OutputType::AST
+            | OutputType::Docs
             | OutputType::Core
             | OutputType::Kernel
             | OutputType::SSA
@@ -0,0 +1,743 @@
+///! This module provides detection of projects managed by rebar3 or erlang.mk.
+///!
+///! When the compiler is given the root directory of such a project, the applications it depends
+///! on are found in the local checkout, i.e. `_checkouts`, `apps` and `_build/default/lib` for
+///! rebar3, or `apps` and `deps` for erlang.mk. Nothing is ever fetched, dependencies must have
+///! been fetched by the build tool beforehand, e.g. with `rebar3 get-deps`.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::bail;
+
+use firefly_intern::Symbol;
+use firefly_syntax_pp::ast::{Root, Term, Terms};
+use firefly_syntax_pp::ParserError;
+use firefly_util::diagnostics::*;
+
+use super::App;
+
+type Parser = firefly_parser::Parser<()>;
+
+/// The build tool managing a project
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BuildTool {
+    Rebar3,
+    ErlangMk,
+}
+impl BuildTool {
+    /// Detects the build tool used by the project in `dir`, if any
+    pub fn detect(dir: &Path) -> Option<Self> {
+        if dir.join("rebar.config").is_file() {
+            Some(Self::Rebar3)
+        } else if dir.join("erlang.mk").is_file() {
+            Some(Self::ErlangMk)
+        } else {
+            None
+        }
+    }
+
+    /// The directories in which the applications of a project are found, highest precedence first
+    fn lib_dirs(&self) -> &'static [&'static str] {
+        match self {
+            Self::Rebar3 => &["_checkouts", "apps", "_build/default/lib"],
+            Self::ErlangMk => &["apps", "deps"],
+        }
+    }
+
+    /// The command which fetches the dependencies of a project
+    fn fetch_command(&self) -> &'static str {
+        match self {
+            Self::Rebar3 => "rebar3 get-deps",
+            Self::ErlangMk => "make deps",
+        }
+    }
+}
+impl fmt::Display for BuildTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rebar3 => f.write_str("rebar3"),
+            Self::ErlangMk => f.write_str("erlang.mk"),
+        }
+    }
+}
+
+/// Options which affect how the sources of an application are preprocessed, as given by
+/// `erl_opts` in `rebar.config`, or `ERLC_OPTS` in the Makefile of an erlang.mk project
+#[derive(Debug, Clone, Default)]
+pub struct ErlOpts {
+    /// Macros defined with `{d, Name}` or `{d, Name, Value}`
+    pub defines: Vec<(Symbol, Option<Term>)>,
+    /// Directories to search for `-include`, the application `include` directory comes first
+    pub include_paths: Vec<PathBuf>,
+}
+
+/// A project managed by rebar3 or erlang.mk
+#[derive(Debug)]
+pub struct Workspace {
+    pub tool: BuildTool,
+    /// The root application
+    pub app: Arc<App>,
+    /// The applications the root application depends on, directly or transitively
+    pub dependencies: HashMap<Symbol, Arc<App>>,
+    /// The options for each application, including the root
+    pub erl_opts: HashMap<Symbol, ErlOpts>,
+    /// Directories containing applications, which are searched by `-include_lib`
+    pub lib_dirs: Vec<PathBuf>,
+}
+impl Workspace {
+    /// Loads the rebar3 or erlang.mk project in `dir`
+    ///
+    /// Returns `Ok(None)` if `dir` is not the root of such a project.
+    pub fn load(
+        diagnostics: &DiagnosticsHandler,
+        codemap: Arc<CodeMap>,
+        dir: &Path,
+    ) -> anyhow::Result<Option<Self>> {
+        let Some(tool) = BuildTool::detect(dir) else { return Ok(None) };
+        let loader = Loader {
+            diagnostics,
+            codemap,
+            tool,
+        };
+        loader.load(dir).map(Some)
+    }
+
+    /// Returns the root application, its dependencies, and the inputs of each application
+    ///
+    /// Every application is compiled from the sources in its own directory.
+    pub fn inputs(
+        &self,
+    ) -> (
+        Arc<App>,
+        HashMap<Symbol, Arc<App>>,
+        HashMap<Symbol, Vec<FileName>>,
+    ) {
+        let input_files = self
+            .dependencies
+            .values()
+            .chain(core::iter::once(&self.app))
+            .map(|app| {
+                let inputs = app.root.iter().cloned().map(FileName::Real).collect();
+                (app.name, inputs)
+            })
+            .collect();
+        (self.app.clone(), self.dependencies.clone(), input_files)
+    }
+}
+
+/// The configuration of a project which is relevant to the compiler
+#[derive(Default)]
+struct ProjectConfig {
+    erl_opts: ErlOpts,
+    /// The names of the dependencies declared by the project
+    deps: Vec<Symbol>,
+    /// The name, version and applications of the release declared by the project, if any
+    release: Option<(Symbol, Option<String>, Vec<Symbol>)>,
+    /// The application resource described by the Makefile of an erlang.mk project
+    app: Option<App>,
+}
+
+struct Loader<'a> {
+    diagnostics: &'a DiagnosticsHandler,
+    codemap: Arc<CodeMap>,
+    tool: BuildTool,
+}
+impl<'a> Loader<'a> {
+    fn load(&self, dir: &Path) -> anyhow::Result<Workspace> {
+        let config = self.read_config(dir)?;
+
+        // Find every application in the checkout, earlier directories take precedence
+        let mut lib_dirs = vec![];
+        let mut found: BTreeMap<Symbol, Arc<App>> = BTreeMap::new();
+        let mut umbrella = vec![];
+        for lib_dir in self.tool.lib_dirs().iter() {
+            let lib_dir = dir.join(lib_dir);
+            if !lib_dir.is_dir() {
+                continue;
+            }
+            let mut entries = fs::read_dir(&lib_dir)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_dir())
+                .collect::<Vec<_>>();
+            entries.sort();
+            for app_dir in entries {
+                if let Some(app) = self.load_app(&app_dir)? {
+                    if lib_dir.ends_with("apps") {
+                        umbrella.push(app.name);
+                    }
+                    found.entry(app.name).or_insert(app);
+                }
+            }
+            lib_dirs.push(lib_dir);
+        }
+
+        let app = match self.load_app(dir)? {
+            Some(app) => app,
+            None => root_app(dir, &config, &found, &umbrella),
+        };
+        // rebar3 links the root application into _build/default/lib
+        found.remove(&app.name);
+        umbrella.retain(|name| *name != app.name);
+
+        // Dependencies which were declared, but not found, have not been fetched yet
+        for dep in config.deps.iter() {
+            if !found.contains_key(dep) {
+                bail!(
+                    "dependency `{}` was not found, it must be fetched with `{}` first",
+                    dep,
+                    self.tool.fetch_command()
+                );
+            }
+        }
+
+        // Collect the applications reachable from the root, along with those declared as
+        // dependencies, even if they aren't listed in the root application resource.
+        // Applications which aren't found, e.g. kernel, are provided by the runtime.
+        let mut dependencies = HashMap::new();
+        let mut pending = app
+            .applications
+            .iter()
+            .chain(app.included_applications.iter())
+            .chain(config.deps.iter())
+            .chain(umbrella.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        while let Some(name) = pending.pop() {
+            if dependencies.contains_key(&name) {
+                continue;
+            }
+            if let Some(dep) = found.get(&name) {
+                pending.extend(dep.applications.iter().copied());
+                pending.extend(dep.included_applications.iter().copied());
+                dependencies.insert(name, dep.clone());
+            }
+        }
+        sort_applications(&app, &dependencies)?;
+
+        let mut erl_opts = HashMap::new();
+        for app in dependencies.values().chain(core::iter::once(&app)) {
+            let root = app.root.as_deref().unwrap_or(dir);
+            let mut opts = if root == dir {
+                config.erl_opts.clone()
+            } else if umbrella.contains(&app.name) {
+                // Applications in an umbrella project inherit the options of the project
+                let mut opts = config.erl_opts.clone();
+                let own = self.read_config(root)?.erl_opts;
+                opts.defines.extend(own.defines);
+                opts.include_paths.extend(own.include_paths);
+                opts
+            } else {
+                self.read_config(root)?.erl_opts
+            };
+            opts.include_paths.insert(0, root.join("include"));
+            erl_opts.insert(app.name, opts);
+        }
+
+        Ok(Workspace {
+            tool: self.tool,
+            app,
+            dependencies,
+            erl_opts,
+            lib_dirs,
+        })
+    }
+
+    /// Loads the resource of the application in `dir`, preferring one compiled to `ebin`
+    fn load_app(&self, dir: &Path) -> anyhow::Result<Option<Arc<App>>> {
+        for (subdir, extension) in [("ebin", ".app"), ("src", ".app.src")] {
+            if let Some(path) = find_file(&dir.join(subdir), extension)? {
+                return Ok(Some(App::parse(
+                    self.diagnostics,
+                    self.codemap.clone(),
+                    path,
+                )?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the configuration of the project in `dir`, or of one of its dependencies
+    ///
+    /// Dependencies aren't necessarily managed by the same tool as the project, or by any tool.
+    fn read_config(&self, dir: &Path) -> anyhow::Result<ProjectConfig> {
+        match BuildTool::detect(dir) {
+            Some(BuildTool::Rebar3) => self.read_rebar_config(dir),
+            _ if dir.join("Makefile").is_file() => self.read_makefile(dir),
+            _ => Ok(ProjectConfig::default()),
+        }
+    }
+
+    fn read_rebar_config(&self, dir: &Path) -> anyhow::Result<ProjectConfig> {
+        let path = dir.join("rebar.config");
+        let parser = Parser::new((), self.codemap.clone());
+        let terms = match parser.parse_file::<Terms, _, ParserError>(self.diagnostics, &path) {
+            Ok(terms) => terms.terms,
+            Err(err) => {
+                self.diagnostics.error(err);
+                bail!("unable to parse {}", path.display());
+            }
+        };
+
+        let mut config = ProjectConfig::default();
+        for term in terms {
+            let Term::Tuple(tuple) = term else { continue };
+            let Ok([Term::Atom(key), value]) = <[Term; 2]>::try_from(tuple.item) else { continue };
+            match key.item.as_str().get() {
+                "erl_opts" => {
+                    for opt in list(value) {
+                        self.erl_opt(&mut config.erl_opts, dir, opt);
+                    }
+                }
+                "deps" => {
+                    for dep in list(value) {
+                        match dep {
+                            Term::Atom(name) => config.deps.push(name.item),
+                            Term::Tuple(dep) => match dep.item.first() {
+                                Some(Term::Atom(name)) => config.deps.push(name.item),
+                                _ => self.invalid(dep.span(), "expected a dependency name"),
+                            },
+                            other => self.invalid(other.span(), "expected a dependency"),
+                        }
+                    }
+                }
+                "relx" => {
+                    for opt in list(value) {
+                        let Term::Tuple(opt) = opt else { continue };
+                        if let [Term::Atom(tag), Term::Tuple(name), apps, ..] = opt.item.as_slice()
+                        {
+                            if tag.item != "release" {
+                                continue;
+                            }
+                            let (name, version) = match name.item.as_slice() {
+                                [Term::Atom(name), Term::String(vsn)] => {
+                                    (name.item, Some(vsn.item.as_str().get().to_string()))
+                                }
+                                [Term::Atom(name), _] => (name.item, None),
+                                _ => continue,
+                            };
+                            let apps = list(apps.clone())
+                                .filter_map(|app| match app {
+                                    Term::Atom(app) => Some(app.item),
+                                    Term::Tuple(app) => match app.item.first() {
+                                        Some(Term::Atom(app)) => Some(app.item),
+                                        _ => None,
+                                    },
+                                    _ => None,
+                                })
+                                .collect();
+                            // Only the first release is built
+                            config.release.get_or_insert((name, version, apps));
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Reads the configuration of an erlang.mk project from its Makefile
+    ///
+    /// The application resource is generated from `PROJECT` and related variables, in the same
+    /// way erlang.mk does when there is no `.app.src` file.
+    fn read_makefile(&self, dir: &Path) -> anyhow::Result<ProjectConfig> {
+        let path = dir.join("Makefile");
+        let vars = parse_makefile(&fs::read_to_string(&path)?);
+        let get = |name: &str| vars.get(name).map(|s| s.as_str()).unwrap_or_default();
+        let names = |name: &str| -> Vec<Symbol> {
+            get(name).split_whitespace().map(Symbol::intern).collect()
+        };
+
+        let mut config = ProjectConfig {
+            deps: names("DEPS"),
+            ..Default::default()
+        };
+        if !get("PROJECT").is_empty() {
+            let mut app = App::new(Symbol::intern(get("PROJECT")));
+            app.root = Some(dir.to_path_buf());
+            app.version = vars.get("PROJECT_VERSION").cloned();
+            app.otp_module = vars.get("PROJECT_MOD").map(|m| Symbol::intern(m.as_str()));
+            app.applications = ["kernel", "stdlib"]
+                .into_iter()
+                .map(Symbol::intern)
+                .collect();
+            app.applications.extend(names("LOCAL_DEPS"));
+            app.applications.extend(config.deps.iter().copied());
+            config.app = Some(app);
+        }
+
+        let mut opts = shell_words(get("ERLC_OPTS")).into_iter();
+        while let Some(opt) = opts.next() {
+            let term = if let Some(include) = opt.strip_prefix("-I") {
+                let include = match include {
+                    "" => opts.next().unwrap_or_default(),
+                    include => include.to_string(),
+                };
+                format!("{{i, \"{}\"}}", include)
+            } else if let Some(define) = opt.strip_prefix("-D") {
+                match define.split_once('=') {
+                    Some((name, value)) => format!("{{d, '{}', {}}}", name, value),
+                    None => format!("{{d, '{}'}}", define),
+                }
+            } else if let Some(term) = opt.strip_prefix('+') {
+                term.to_string()
+            } else {
+                continue;
+            };
+            let parser = Parser::new((), self.codemap.clone());
+            match parser
+                .parse_string::<Root, _, ParserError>(self.diagnostics, format!("{}.", term))
+            {
+                Ok(root) => self.erl_opt(&mut config.erl_opts, dir, root.term),
+                Err(err) => {
+                    self.diagnostics.error(err);
+                    bail!("invalid option in ERLC_OPTS of {}: {}", path.display(), opt);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Handles a single compiler option, only those affecting the preprocessor are relevant
+    fn erl_opt(&self, opts: &mut ErlOpts, dir: &Path, opt: Term) {
+        let Term::Tuple(tuple) = opt else { return };
+        match tuple.item.as_slice() {
+            [Term::Atom(d), Term::Atom(name)] if d.item == "d" => {
+                opts.defines.push((name.item, None));
+            }
+            [Term::Atom(d), Term::Atom(name), value] if d.item == "d" => {
+                opts.defines.push((name.item, Some(value.clone())));
+            }
+            [Term::Atom(i), Term::String(include)] if i.item == "i" => {
+                let include = dir.join(include.item.as_str().get());
+                opts.include_paths.push(include);
+            }
+            [Term::Atom(pt), Term::Atom(module)] if pt.item == "parse_transform" => {
+                self.diagnostics
+                    .diagnostic(Severity::Warning)
+                    .with_message("parse transforms are not supported")
+                    .with_primary_label(
+                        tuple.span(),
+                        format!("`{}` will not be applied", module.item),
+                    )
+                    .with_note("dependent sources may not compile, or may behave differently")
+                    .emit();
+            }
+            _ => (),
+        }
+    }
+
+    fn invalid(&self, span: SourceSpan, message: &str) {
+        self.diagnostics
+            .diagnostic(Severity::Warning)
+            .with_message("invalid project configuration")
+            .with_primary_label(span, message)
+            .emit();
+    }
+}
+
+/// Sorts `apps` so that every application comes after the applications it depends on
+///
+/// The dependencies of `root` come first, in the order they are listed, followed by the rest,
+/// ordered by name. Returns an error if the applications depend on each other circularly.
+pub fn sort_applications(
+    root: &App,
+    apps: &HashMap<Symbol, Arc<App>>,
+) -> anyhow::Result<Vec<Arc<App>>> {
+    fn visit(
+        name: Symbol,
+        apps: &HashMap<Symbol, Arc<App>>,
+        path: &mut Vec<Symbol>,
+        sorted: &mut Vec<Arc<App>>,
+    ) -> anyhow::Result<()> {
+        if sorted.iter().any(|app| app.name == name) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|visiting| *visiting == name) {
+            let cycle = path[start..]
+                .iter()
+                .chain(core::iter::once(&name))
+                .map(|name| name.as_str().get())
+                .collect::<Vec<_>>();
+            bail!(
+                "circular dependency between applications: {}",
+                cycle.join(" -> ")
+            );
+        }
+        let Some(app) = apps.get(&name) else { return Ok(()) };
+        path.push(name);
+        for dep in app
+            .applications
+            .iter()
+            .chain(app.included_applications.iter())
+        {
+            visit(*dep, apps, path, sorted)?;
+        }
+        path.pop();
+        sorted.push(app.clone());
+        Ok(())
+    }
+
+    let mut rest = apps.keys().copied().collect::<Vec<_>>();
+    rest.sort_by(|a, b| a.as_str().get().cmp(b.as_str().get()));
+    let mut sorted = Vec::with_capacity(apps.len());
+    let mut path = vec![root.name];
+    for name in root
+        .applications
+        .iter()
+        .chain(root.included_applications.iter())
+        .copied()
+        .chain(rest)
+    {
+        visit(name, apps, &mut path, &mut sorted)?;
+    }
+    Ok(sorted)
+}
+
+/// Determines the root application of a project without an application resource of its own
+///
+/// This is either the application described by the Makefile of an erlang.mk project, or for an
+/// umbrella project, the application the release is named after, or the only application in the
+/// project. Otherwise a root application without sources is synthesized, which is named after the
+/// release and depends on the applications in it, or if there isn't a release, is named after the
+/// project directory and depends on every application in the project.
+fn root_app(
+    dir: &Path,
+    config: &ProjectConfig,
+    found: &BTreeMap<Symbol, Arc<App>>,
+    umbrella: &[Symbol],
+) -> Arc<App> {
+    if let Some(app) = config.app.as_ref() {
+        return Arc::new(app.clone());
+    }
+    let release = config.release.as_ref();
+    if let Some(app) = release.and_then(|(name, _, _)| found.get(name)) {
+        return app.clone();
+    }
+    if let ([name], None) = (umbrella, release) {
+        return found[name].clone();
+    }
+
+    let mut app = match release {
+        Some((name, version, apps)) => {
+            let mut app = App::new(*name);
+            app.version = version.clone();
+            app.applications = apps.clone();
+            app
+        }
+        None => {
+            // The directory may be given as e.g. `.`, which has no name until it is resolved
+            let path = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_else(|| "root".into());
+            let mut app = App::new(Symbol::intern(&name));
+            app.applications = umbrella.to_vec();
+            app
+        }
+    };
+    app.root = Some(dir.to_path_buf());
+    Arc::new(app)
+}
+
+/// Returns the elements of `term` if it is a proper list
+fn list(term: Term) -> impl Iterator<Item = Term> {
+    term.as_list()
+        .map(|list| list.item)
+        .unwrap_or_default()
+        .into_iter()
+}
+
+/// Finds the first file in `dir` whose name ends with `suffix`, in name order
+fn find_file(dir: &Path, suffix: &str) -> anyhow::Result<Option<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let mut files = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| name.ends_with(suffix))
+                    .unwrap_or(false)
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files.into_iter().next())
+}
+
+/// Parses the variables assigned in a Makefile
+///
+/// Only simple assignments are understood, i.e. `=`, `:=`, `?=` and `+=`, including those which
+/// are continued over multiple lines. Values are kept as written, references to other variables
+/// are not expanded.
+fn parse_makefile(source: &str) -> HashMap<String, String> {
+    let mut vars: HashMap<String, String> = HashMap::new();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let mut line = line.to_string();
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some(next) => {
+                    line.push(' ');
+                    line.push_str(next.trim());
+                }
+                None => break,
+            }
+        }
+        let line = line.split('#').next().unwrap();
+        let Some((name, value)) = line.split_once('=') else { continue };
+        let value = value.trim().to_string();
+        let name = name.trim();
+        let (name, op) = match name.strip_suffix(['+', ':', '?']) {
+            Some(stripped) => (stripped.trim_end(), name.chars().last().unwrap()),
+            None => (name, '='),
+        };
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            continue;
+        }
+        match op {
+            '+' => {
+                let var = vars.entry(name.to_string()).or_default();
+                if !var.is_empty() {
+                    var.push(' ');
+                }
+                var.push_str(&value);
+            }
+            '?' => {
+                vars.entry(name.to_string()).or_insert(value);
+            }
+            _ => {
+                vars.insert(name.to_string(), value);
+            }
+        }
+    }
+    vars
+}
+
+/// Splits `s` into words as a shell would, with quotes grouping words which contain spaces
+fn shell_words(s: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quote = None;
+    let mut in_word = false;
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(core::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn makefile_variables_test() {
+        let vars = parse_makefile(
+            "PROJECT = my_app\n\
+             PROJECT_VERSION = 1.0.0\n\
+             DEPS = cowboy \\\n\
+             \tjsx\n\
+             ERLC_OPTS ?= -Werror +debug_info\n\
+             ERLC_OPTS += +'{parse_transform, lager_transform}' # comment\n\
+             \n\
+             include erlang.mk\n",
+        );
+        assert_eq!(vars["PROJECT"], "my_app");
+        assert_eq!(vars["PROJECT_VERSION"], "1.0.0");
+        assert_eq!(vars["DEPS"], "cowboy jsx");
+        assert_eq!(
+            vars["ERLC_OPTS"],
+            "-Werror +debug_info +'{parse_transform, lager_transform}'"
+        );
+    }
+
+    #[test]
+    fn shell_words_test() {
+        assert_eq!(
+            shell_words("-DTEST -I include +'{d, name, \"a b\"}'"),
+            vec!["-DTEST", "-I", "include", "+{d, name, \"a b\"}"]
+        );
+    }
+
+    #[test]
+    fn sort_applications_test() {
+        let app = |name: &str, deps: &[&str]| {
+            let mut app = App::new(Symbol::intern(name));
+            app.applications = deps.iter().copied().map(Symbol::intern).collect();
+            Arc::new(app)
+        };
+        let root = app("root", &["web", "kernel"]);
+        let mut apps = HashMap::new();
+        for dep in [
+            app("web", &["cowlib", "ranch"]),
+            app("ranch", &[]),
+            app("cowlib", &["ranch"]),
+            app("unused", &[]),
+        ] {
+            apps.insert(dep.name, dep);
+        }
+        let sorted = sort_applications(&root, &apps).unwrap();
+        let names = sorted
+            .iter()
+            .map(|app| app.name.as_str().get())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["ranch", "cowlib", "web", "unused"]);
+
+        apps.insert(Symbol::intern("ranch"), app("ranch", &["web"]));
+        let err = sort_applications(&root, &apps).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "circular dependency between applications: web -> cowlib -> ranch -> web"
+        );
+    }
+
+    #[test]
+    fn root_app_test() {
+        let mut found = BTreeMap::new();
+        let mut umbrella = vec![];
+        for name in ["web", "db"] {
+            let app = Arc::new(App::new(Symbol::intern(name)));
+            umbrella.push(app.name);
+            found.insert(app.name, app);
+        }
+        let config = ProjectConfig::default();
+
+        // Without a release, an umbrella project is named after its directory
+        let app = root_app(Path::new("."), &config, &found, &umbrella);
+        let cwd = std::env::current_dir().unwrap();
+        let name = cwd.file_name().unwrap().to_string_lossy();
+        assert_eq!(app.name.as_str().get(), name);
+        assert_eq!(app.applications, umbrella);
+        assert_eq!(app.root.as_deref(), Some(Path::new(".")));
+    }
+}
@@ -0,0 +1,90 @@
+///! This module contains the callbacks of the standard OTP behaviours
+///!
+///! The purpose of this is to allow verifying that modules implementing one of these behaviours
+///! export the required callbacks, without needing access to the BEAM files (or sources) of OTP.
+///! Behaviours which are not listed here must be resolved from the modules being compiled, or
+///! from BEAM files on the code path.
+use std::collections::BTreeMap;
+
+use firefly_intern::Symbol;
+use lazy_static::lazy_static;
+
+use crate::FunctionName;
+
+/// The callbacks of a behaviour, mapped to whether or not each callback is optional
+pub type Callbacks = BTreeMap<FunctionName, bool>;
+
+lazy_static! {
+    static ref OTP_BEHAVIOURS: BTreeMap<Symbol, Callbacks> = {
+        let mut behaviours = BTreeMap::new();
+        behaviours.insert(
+            Symbol::intern("gen_server"),
+            callbacks(
+                &[("init", 1), ("handle_call", 3), ("handle_cast", 2)],
+                &[
+                    ("handle_info", 2),
+                    ("handle_continue", 2),
+                    ("terminate", 2),
+                    ("code_change", 3),
+                    ("format_status", 1),
+                    ("format_status", 2),
+                ],
+            ),
+        );
+        behaviours.insert(
+            Symbol::intern("gen_statem"),
+            callbacks(
+                &[("init", 1), ("callback_mode", 0)],
+                &[
+                    ("state_name", 3),
+                    ("handle_event", 4),
+                    ("terminate", 3),
+                    ("code_change", 4),
+                    ("format_status", 1),
+                    ("format_status", 2),
+                ],
+            ),
+        );
+        behaviours.insert(
+            Symbol::intern("gen_event"),
+            callbacks(
+                &[("init", 1), ("handle_event", 2), ("handle_call", 2)],
+                &[
+                    ("handle_info", 2),
+                    ("terminate", 2),
+                    ("code_change", 3),
+                    ("format_status", 1),
+                    ("format_status", 2),
+                ],
+            ),
+        );
+        behaviours.insert(Symbol::intern("supervisor"), callbacks(&[("init", 1)], &[]));
+        behaviours.insert(
+            Symbol::intern("application"),
+            callbacks(
+                &[("start", 2), ("stop", 1)],
+                &[("prep_stop", 1), ("start_phase", 3), ("config_change", 3)],
+            ),
+        );
+        behaviours
+    };
+}
+
+fn callbacks(required: &[(&str, u8)], optional: &[(&str, u8)]) -> Callbacks {
+    let required = required.iter().map(|cb| (cb, false));
+    let optional = optional.iter().map(|cb| (cb, true));
+    required
+        .chain(optional)
+        .map(|((name, arity), optional)| {
+            (
+                FunctionName::new_local(Symbol::intern(name), *arity),
+                optional,
+            )
+        })
+        .collect()
+}
+
+/// Get the callbacks of the given standard OTP behaviour, if it is one
+pub fn get(behaviour: Symbol) -> Option<&'static Callbacks> {
+    OTP_BEHAVIOURS.get(&behaviour)
+}
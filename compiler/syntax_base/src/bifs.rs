@@ -9,7 +9,7 @@
 ///! we do have. In the future we'll hopefully be able to make the type information not only
 ///! richer, but context-sensitive for those situations in which the polymorphism of a given
 ///! BIF is dependent on constant inputs which we can reason about relatively easily
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use firefly_compiler_macros::bif;
 use lazy_static::lazy_static;
@@ -74,7 +74,10 @@ lazy_static! {
             bif!(guard erlang:bit_size/1(bitstring) -> non_neg_integer),
             bif!(pub erlang:bitstring_to_list/1(bitstring) -> list),
             bif!(guard erlang:byte_size/1(bitstring) -> non_neg_integer),
+            bif!(pub erlang:cancel_timer/1(reference) -> term),
+            bif!(pub erlang:cancel_timer/2(reference, list) -> term),
             bif!(guard erlang:ceil/1(number) -> integer),
+            bif!(pub erlang:convert_time_unit/3(integer, term, term) -> integer),
             bif!(pub erlang:date/0() -> tuple),
             bif!(pub erlang:demonitor/1(reference) -> boolean),
             bif!(pub erlang:demonitor/2(reference, list) -> boolean),
@@ -147,13 +150,19 @@ lazy_static! {
             bif!(pub erlang:list_to_ref/1(string) -> reference),
             bif!(pub erlang:list_to_tuple/1(list) -> tuple),
             bif!(pub erlang:load_nif/2(string, term) -> term),
+            bif!(pub erlang:localtime/0() -> tuple),
+            bif!(pub erlang:localtime_to_universaltime/1(tuple) -> tuple),
             bif!(pub erlang:make_ref/0() -> reference),
             bif!(pub erlang:make_fun/3(atom, atom, list) -> function),
             bif!(guard erlang:map_get/2(any, map) -> any),
             bif!(guard erlang:map_size/1(map) -> non_neg_integer),
             bif!(guard erlang:match_fail/2(atom, term) -> term),
             bif!(pub erlang:max/2(term, term) -> term),
+            bif!(pub erlang:memory/0() -> list),
+            bif!(pub erlang:memory/1(term) -> term),
             bif!(pub erlang:min/2(term, term) -> term),
+            bif!(pub erlang:monotonic_time/0() -> integer),
+            bif!(pub erlang:monotonic_time/1(term) -> integer),
             bif!(pub erlang:monitor/2(atom, term) -> reference),
             bif!(pub erlang:monitor/3(atom, term, list) -> reference),
             bif!(pub erlang:monitor_node/2(node, boolean) -> boolean),
@@ -181,10 +190,14 @@ lazy_static! {
             bif!(pub erlang:put/2(term, term) -> term),
             bif!(pub erlang:raise/2(any, trace) -> term),
             bif!(pub erlang:raise/3(atom, any, list) -> term),
+            bif!(pub erlang:read_timer/1(reference) -> term),
+            bif!(pub erlang:read_timer/2(reference, list) -> term),
             bif!(pub erlang:ref_to_list/1(reference) -> string),
             bif!(pub erlang:register/2(atom, term) -> boolean),
             bif!(pub erlang:registered/0() -> list),
             bif!(guard erlang:round/1(number) -> integer),
+            bif!(pub erlang:send_after/3(non_neg_integer, term, term) -> reference),
+            bif!(pub erlang:send_after/4(integer, term, term, list) -> reference),
             bif!(pub erlang:setelement/3(pos_integer, tuple, term) -> tuple),
             bif!(guard erlang:self/0() -> pid),
             bif!(guard erlang:size/1(term) -> non_neg_integer),
@@ -212,17 +225,27 @@ lazy_static! {
             bif!(pub erlang:spawn_request/5(node, module, atom, list, list) -> reference),
             bif!(pub erlang:spawn_request_abandon/1(reference) -> boolean),
             bif!(pub erlang:split_binary/2(binary, non_neg_integer) -> binary_split),
+            bif!(pub erlang:start_timer/3(non_neg_integer, term, term) -> reference),
+            bif!(pub erlang:start_timer/4(integer, term, term, list) -> reference),
             bif!(pub erlang:statistics/1(atom) -> term),
+            bif!(pub erlang:system_flag/2(atom, term) -> term),
+            bif!(pub erlang:system_info/1(term) -> term),
+            bif!(pub erlang:system_time/0() -> integer),
+            bif!(pub erlang:system_time/1(term) -> integer),
             bif!(pub erlang:term_to_binary/1(term) -> binary),
             bif!(pub erlang:term_to_binary/2(term, list) -> binary),
             bif!(pub erlang:term_to_iovec/1(term) -> list),
             bif!(pub erlang:term_to_iovec/2(term, list) -> list),
             bif!(pub erlang:throw/1(any) -> term),
             bif!(pub erlang:time/0() -> time),
+            bif!(pub erlang:time_offset/0() -> integer),
+            bif!(pub erlang:time_offset/1(term) -> integer),
+            bif!(pub erlang:timestamp/0() -> timestamp),
             bif!(guard erlang:tl/1(nonempty_maybe_improper_list) -> term),
             bif!(guard erlang:trunc/1(number) -> integer),
             bif!(guard erlang:tuple_size/1(tuple) -> non_neg_integer),
             bif!(pub erlang:tuple_to_list/1(tuple) -> list),
+            bif!(pub erlang:universaltime/0() -> tuple),
             bif!(pub erlang:unlink/1(term) -> boolean),
             bif!(pub erlang:unregister/1(atom) -> boolean),
             bif!(pub erlang:whereis/1(atom) -> term),
@@ -232,6 +255,8 @@ lazy_static! {
             bif!(pub erlang:recv_peek_message/0() -> bool, any),
             bif!(pub erlang:recv_wait_timeout/1(timeout) -> bool),
             bif!(pub erlang:yield/0() -> bool),
+            bif!(pub os:system_time/0() -> integer),
+            bif!(pub os:system_time/1(term) -> integer),
         ]
     };
 }
@@ -247,6 +272,13 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    /// The functions implemented natively by the runtime, shared with `firefly_rt`
+    static ref RUNTIME_BIFS: BTreeSet<FunctionName> = {
+        firefly_bifs::BIFS.iter().map(|bif| bif.parse().unwrap()).collect()
+    };
+}
+
 /// Get the Signature matching the provided module/function/arity
 pub fn get(mfa: &FunctionName) -> Option<&'static Signature> {
     BIF_MAP.get(mfa).copied()
@@ -264,3 +296,10 @@ pub fn fetch(mfa: &FunctionName) -> &'static Signature {
 pub fn all() -> &'static [Signature] {
     BIF_SIGNATURES.as_slice()
 }
+
+/// Returns true if the provided module/function/arity is implemented natively by the runtime
+///
+/// Unlike `get`, this includes natively implemented functions outside of `erlang`, e.g. `os:system_time/1`
+pub fn is_runtime_bif(mfa: &FunctionName) -> bool {
+    RUNTIME_BIFS.contains(mfa)
+}
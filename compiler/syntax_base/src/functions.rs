@@ -412,6 +412,7 @@ bitflags::bitflags! {
         /// * has not been requested to be inlined
         /// * is not a closure
         /// * is not a nif
+        /// * is not the on_load function of its module
         const DEFAULT = 0;
         /// Indicates this function is visibile globally
         const PUBLIC = 1 << 0;
@@ -438,6 +439,8 @@ bitflags::bitflags! {
         const CLOSURE = 1 << 5;
         /// Indicates this function is defined as a NIF
         const NIF = 1 << 6;
+        /// Indicates this function was named by the `-on_load` attribute of its module
+        const ON_LOAD = 1 << 7;
     }
 }
 impl fmt::Display for Visibility {
@@ -458,6 +461,9 @@ impl fmt::Display for Visibility {
         if self.is_nif() {
             f.write_str("nif")?;
         }
+        if self.is_on_load() {
+            f.write_str(" on_load")?;
+        }
         Ok(())
     }
 }
@@ -499,6 +505,12 @@ impl Visibility {
         self.contains(Self::NIF)
     }
 
+    /// Returns true if this function is run when its module is loaded
+    #[inline(always)]
+    pub fn is_on_load(&self) -> bool {
+        self.contains(Self::ON_LOAD)
+    }
+
     /// Returns true if this function is globally visible
     #[inline(always)]
     pub fn is_public(&self) -> bool {
@@ -690,6 +702,11 @@ impl Signature {
         self.visibility.is_nif()
     }
 
+    /// Returns true if this function was declared as the on_load function of its module
+    pub fn is_on_load(&self) -> bool {
+        self.visibility.is_on_load()
+    }
+
     /// Returns the calling convention used by this function
     pub fn calling_convention(&self) -> CallConv {
         self.cc
@@ -4,6 +4,7 @@ mod macros;
 pub use self::macros::*;
 
 mod annotations;
+pub mod behaviours;
 pub mod bifs;
 mod deprecations;
 mod functions;
@@ -25,7 +26,7 @@ pub use self::var::Var;
 
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 
-use firefly_diagnostics::{SourceSpan, Span};
+use firefly_diagnostics::Span;
 use firefly_intern::{Ident, Symbol};
 
 /// This structure contains metadata representing an OTP application gathered during parsing and semantic analysis.
@@ -33,8 +34,23 @@ use firefly_intern::{Ident, Symbol};
 pub struct ApplicationMetadata {
     pub name: Symbol,
     pub modules: BTreeMap<Symbol, ModuleMetadata>,
+    // The callbacks of behaviours used by this application, but defined outside of it
+    pub behaviours: BTreeMap<Symbol, behaviours::Callbacks>,
 }
 impl ApplicationMetadata {
+    /// Returns the callbacks of the given behaviour, if it is known
+    ///
+    /// Behaviours defined in this application take precedence, followed by those resolved from
+    /// elsewhere, and lastly the standard OTP behaviours known to the compiler.
+    pub fn get_behaviour_callbacks(&self, name: Symbol) -> Option<&behaviours::Callbacks> {
+        self.modules
+            .get(&name)
+            .map(|m| &m.callbacks)
+            .filter(|callbacks| !callbacks.is_empty())
+            .or_else(|| self.behaviours.get(&name))
+            .or_else(|| behaviours::get(name))
+    }
+
     /// Returns the deprecation associated with the given module name, if one was declared
     pub fn get_module_deprecation(&self, name: &Symbol) -> Option<Deprecation> {
         self.modules.get(name).and_then(|m| m.deprecation)
@@ -43,11 +59,10 @@ impl ApplicationMetadata {
     /// Returns the deprecation associated with the given function name, if one was declared
     pub fn get_function_deprecation(&self, name: &FunctionName) -> Option<Deprecation> {
         let module_name = name.module.unwrap();
-        let key = Span::new(SourceSpan::default(), name.to_local());
         let deprecation = self
             .modules
             .get(&module_name)
-            .and_then(|m| m.deprecations.get(&key).cloned());
+            .and_then(|m| m.deprecations.get(name).cloned());
         if deprecation.is_some() {
             deprecation
         } else {
@@ -65,6 +80,8 @@ pub struct ModuleMetadata {
     pub exports: BTreeSet<Span<FunctionName>>,
     pub deprecation: Option<Deprecation>,
     pub deprecations: BTreeMap<FunctionName, Deprecation>,
+    // The callbacks declared by this module, if it defines a behaviour
+    pub callbacks: behaviours::Callbacks,
 }
 impl ModuleMetadata {
     pub fn new(name: Ident) -> Self {
@@ -73,6 +90,7 @@ impl ModuleMetadata {
             exports: BTreeSet::default(),
             deprecation: None,
             deprecations: BTreeMap::default(),
+            callbacks: BTreeMap::default(),
         }
     }
 }
@@ -125,6 +143,8 @@ pub struct CompileOptions {
     pub no_warn_deprecated_functions: HashSet<Span<FunctionName>>,
     pub warn_deprecated_type: bool,
     pub warn_obsolete_guard: bool,
+    // Warns about missing or misspelled behaviour callbacks
+    pub warn_behaviours: bool,
     pub inline: bool,
     // Inlines the given functions
     pub inline_functions: HashSet<Span<FunctionName>>,
@@ -164,6 +184,7 @@ impl Default for CompileOptions {
             no_warn_deprecated_functions: HashSet::new(),
             warn_deprecated_type: true,
             warn_obsolete_guard: true,
+            warn_behaviours: true,
         }
     }
 }
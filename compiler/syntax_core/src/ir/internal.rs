@@ -819,6 +819,10 @@ pub struct IGen {
     // tail_pat is the tail pattern, respectively [] and <<_/bitstring>> for list
     // and bit string generators.
     pub tail_pattern: Box<IExpr>,
+    // refill is a pattern and an action used to advance generator input which is in
+    // neither of the forms above, e.g. a map iterator for K := V <- Map. The action binds
+    // tail, and the comprehension function is then applied to it.
+    pub refill: Option<(Box<IExpr>, Box<IExpr>)>,
     // pre is the list of expressions to be inserted before the comprehension function
     pub pre: Vec<IExpr>,
     // arg is the expression that the comprehension function should be passed
@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
 use firefly_diagnostics::{SourceSpan, Span, Spanned};
 use firefly_syntax_base::{Deprecation, FunctionName};
 
-use super::{Expr, Ident, Name, Type};
+use super::{Expr, Ident, Literal, Name, Type};
 
 /// Type definitions
 ///
@@ -154,6 +157,72 @@ impl PartialEq for UserAttribute {
     }
 }
 
+/// Documentation for a module, or one of its functions, types or callbacks.
+///
+/// A `-moduledoc` attribute documents the module itself, while a `-doc` attribute
+/// documents the next function, type or callback definition which follows it. Multiple
+/// attributes may be given for the same target, in which case their metadata is merged.
+///
+/// ## Example
+///
+/// ```text
+/// -moduledoc "Provides access to the application configuration".
+///
+/// -doc "Returns the value of `Key` in the current configuration".
+/// -doc #{since => "1.0.0"}.
+/// get(Key) -> ...
+///
+/// %% Hides a definition from the generated documentation
+/// -doc false.
+/// ```
+#[derive(Debug, Clone, Spanned)]
+pub struct Doc {
+    #[span]
+    pub span: SourceSpan,
+    /// The documentation text, in Markdown
+    pub text: Option<String>,
+    /// True if this definition was hidden with `-doc false`
+    pub hidden: bool,
+    pub metadata: BTreeMap<Literal, Literal>,
+}
+impl Doc {
+    pub fn new(span: SourceSpan) -> Self {
+        Self {
+            span,
+            text: None,
+            hidden: false,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+impl PartialEq for Doc {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text && self.hidden == other.hidden && self.metadata == other.metadata
+    }
+}
+
+/// The kind of definition documented by a `-doc` attribute, as named in EEP 48
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DocKind {
+    Function,
+    Type,
+    Callback,
+}
+impl DocKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Function => "function",
+            Self::Type => "type",
+            Self::Callback => "callback",
+        }
+    }
+}
+impl fmt::Display for DocKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Represents the set of allowed attributes in the body of a module
 #[derive(Debug, Clone)]
 pub enum Attribute {
@@ -172,6 +241,8 @@ pub enum Attribute {
     Nifs(SourceSpan, Vec<Span<FunctionName>>),
     Behaviour(SourceSpan, Ident),
     Deprecation(Vec<Deprecation>),
+    Doc(SourceSpan, Expr),
+    ModuleDoc(SourceSpan, Expr),
 }
 impl Spanned for Attribute {
     fn span(&self) -> SourceSpan {
@@ -189,7 +260,9 @@ impl Spanned for Attribute {
             | Self::Author(span, _)
             | Self::OnLoad(span, _)
             | Self::Nifs(span, _)
-            | Self::Behaviour(span, _) => *span,
+            | Self::Behaviour(span, _)
+            | Self::Doc(span, _)
+            | Self::ModuleDoc(span, _) => *span,
             Self::Deprecation(deprecations) => deprecations.first().map(|d| d.span()).unwrap(),
         }
     }
@@ -219,6 +292,8 @@ impl PartialEq for Attribute {
             (&Attribute::OnLoad(_, ref x), &Attribute::OnLoad(_, ref y)) => x == y,
             (&Attribute::Nifs(_, ref x), &Attribute::Nifs(_, ref y)) => x == y,
             (&Attribute::Behaviour(_, ref x), &Attribute::Behaviour(_, ref y)) => x == y,
+            (&Attribute::Doc(_, ref x), &Attribute::Doc(_, ref y)) => x == y,
+            (&Attribute::ModuleDoc(_, ref x), &Attribute::ModuleDoc(_, ref y)) => x == y,
             _ => false,
         }
     }
@@ -38,6 +38,7 @@ pub enum Expr {
     // Comprehensions
     ListComprehension(ListComprehension),
     BinaryComprehension(BinaryComprehension),
+    MapComprehension(MapComprehension),
     Generator(Generator),
     // Complex expressions
     Begin(Begin),
@@ -51,6 +52,8 @@ pub enum Expr {
     Case(Case),
     Receive(Receive),
     Try(Try),
+    Maybe(Maybe),
+    MaybeMatch(MaybeMatch),
     Fun(Fun),
     Protect(Protect),
 }
@@ -974,6 +977,21 @@ impl PartialEq for BinaryComprehension {
     }
 }
 
+/// A map comprehension, e.g. `#{K => V || K := V <- Map}`
+#[derive(Debug, Clone, Spanned)]
+pub struct MapComprehension {
+    #[span]
+    pub span: SourceSpan,
+    pub key: Box<Expr>,
+    pub value: Box<Expr>,
+    pub qualifiers: Vec<Expr>,
+}
+impl PartialEq for MapComprehension {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.value == other.value && self.qualifiers == other.qualifiers
+    }
+}
+
 /// A generator is one of two types of expressions that act as qualifiers in a commprehension, the other is a filter
 #[derive(Debug, Clone, Spanned)]
 pub struct Generator {
@@ -993,6 +1011,8 @@ impl PartialEq for Generator {
 pub enum GeneratorType {
     Default,
     Bitstring,
+    /// A map generator, e.g. `K := V <- Map`, whose pattern is the tuple `{K, V}`
+    Map,
 }
 impl Default for GeneratorType {
     fn default() -> Self {
@@ -1230,6 +1250,38 @@ impl PartialEq for Try {
     }
 }
 
+/// Represents a `maybe .. [else ..] end` expression (EEP 49)
+///
+/// The body may contain conditional matches (`Pattern ?= Expr`) at the top level, if any of them
+/// fail to match, the unmatched value is either the result of the expression, or if there are
+/// `else` clauses, it is matched against them instead.
+#[derive(Debug, Clone, Spanned)]
+pub struct Maybe {
+    #[span]
+    pub span: SourceSpan,
+    pub body: Vec<Expr>,
+    pub else_clauses: Option<Vec<Clause>>,
+}
+impl PartialEq for Maybe {
+    fn eq(&self, other: &Self) -> bool {
+        self.body == other.body && self.else_clauses == other.else_clauses
+    }
+}
+
+/// Represents a conditional match (`Pattern ?= Expr`), only valid in the body of a `maybe`
+#[derive(Debug, Clone, Spanned)]
+pub struct MaybeMatch {
+    #[span]
+    pub span: SourceSpan,
+    pub pattern: Box<Expr>,
+    pub expr: Box<Expr>,
+}
+impl PartialEq for MaybeMatch {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern && self.expr == other.expr
+    }
+}
+
 /// Represents the `after` clause of a `receive` expression
 #[derive(Debug, Clone, Spanned)]
 pub struct After {
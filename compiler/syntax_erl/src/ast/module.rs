@@ -74,6 +74,8 @@ pub struct Module {
     pub specs: HashMap<FunctionName, TypeSpec>,
     pub behaviours: HashSet<Ident>,
     pub callbacks: HashMap<FunctionName, Callback>,
+    // Used to mark callbacks declared after `-optional_callbacks` as optional
+    pub optional_callbacks: HashSet<Span<FunctionName>>,
     pub records: HashMap<Symbol, Record>,
     pub attributes: HashMap<Ident, ast::Literal>,
     pub functions: BTreeMap<FunctionName, Function>,
@@ -81,6 +83,9 @@ pub struct Module {
     pub deprecation: Option<Deprecation>,
     // Used for function-level deprecation
     pub deprecations: HashSet<Deprecation>,
+    pub moduledoc: Option<Doc>,
+    // Documentation for functions, types and callbacks, keyed by local name
+    pub docs: BTreeMap<(DocKind, FunctionName), Doc>,
 }
 impl Emit for Module {
     fn file_type(&self) -> Option<&'static str> {
@@ -130,11 +135,14 @@ impl Module {
             specs: HashMap::new(),
             behaviours: HashSet::new(),
             callbacks: HashMap::new(),
+            optional_callbacks: HashSet::new(),
             records: HashMap::new(),
             attributes: HashMap::new(),
             functions: BTreeMap::new(),
             deprecation: None,
             deprecations: HashSet::new(),
+            moduledoc: None,
+            docs: BTreeMap::new(),
         }
     }
 
@@ -168,15 +176,24 @@ impl Module {
 
             behaviours: HashSet::new(),
             callbacks: HashMap::new(),
+            optional_callbacks: HashSet::new(),
             records: HashMap::new(),
             attributes: HashMap::new(),
             functions: BTreeMap::new(),
             deprecation: None,
             deprecations: HashSet::new(),
+            moduledoc: None,
+            docs: BTreeMap::new(),
         };
 
+        // Holds the documentation given by `-doc` until the definition it documents is reached
+        let mut doc = None;
         for form in forms.drain(0..) {
+            sema::attach_doc(&mut module, &mut doc, &form);
             match form {
+                TopLevel::Attribute(Attribute::Doc(span, value)) => {
+                    sema::analyze_doc(diagnostics, &mut doc, span, value)
+                }
                 TopLevel::Attribute(attr) => {
                     sema::analyze_attribute(diagnostics, &mut module, attr)
                 }
@@ -187,6 +204,7 @@ impl Module {
                 _ => panic!("unexpected top-level form: {:?}", &form),
             }
         }
+        sema::unused_doc(diagnostics, doc);
 
         module
     }
@@ -233,6 +251,12 @@ impl PartialEq for Module {
         if self.functions != other.functions {
             return false;
         }
+        if self.moduledoc != other.moduledoc {
+            return false;
+        }
+        if self.docs != other.docs {
+            return false;
+        }
         true
     }
 }
@@ -0,0 +1,360 @@
+//! Generates documentation chunks in the format described by [EEP 48](https://www.erlang.org/eeps/eep-0048).
+//!
+//! The chunk is a single `docs_v1` term built from the `-moduledoc` and `-doc` attributes of a
+//! module, and is what shells (e.g. `h/1`) and documentation tools such as ex_doc consume:
+//!
+//! ```text
+//! {docs_v1, Anno, erlang, <<"text/markdown">>, ModuleDoc, Metadata, Docs}
+//! ```
+//!
+//! Each entry in `Docs` describes one exported function, exported type, or callback:
+//!
+//! ```text
+//! {{Kind, Name, Arity}, Anno, Signature, Doc, Metadata}
+//! ```
+use firefly_beam::serialization::etf;
+use firefly_binary::Bitstring;
+use firefly_diagnostics::{CodeMap, SourceSpan, Span};
+use firefly_intern::Symbol;
+use firefly_number::Int;
+use firefly_syntax_base::FunctionName;
+
+use crate::ast::*;
+
+/// Builds the EEP 48 `docs_v1` term for `module`
+pub fn docs_v1(module: &Module, codemap: &CodeMap) -> etf::Term {
+    let anno_span = module
+        .moduledoc
+        .as_ref()
+        .map(|doc| doc.span)
+        .unwrap_or(module.span);
+    let metadata = module
+        .moduledoc
+        .as_ref()
+        .map(metadata)
+        .unwrap_or_else(|| etf::Map::from(vec![]).into());
+
+    let export_all = module
+        .compile
+        .as_ref()
+        .map(|opts| opts.export_all)
+        .unwrap_or(false);
+
+    let mut entries = vec![];
+    for (name, fun) in module.functions.iter() {
+        if !export_all && !module.exports.contains(&Span::new(fun.span, *name)) {
+            continue;
+        }
+        let params = fun
+            .clauses
+            .first()
+            .map(|(_, clause)| clause.patterns.iter().map(param_name).collect())
+            .unwrap_or_default();
+        entries.push(entry(
+            module,
+            codemap,
+            DocKind::Function,
+            name,
+            fun.span,
+            params,
+        ));
+    }
+    for (name, ty) in module.types.iter() {
+        if !module.exported_types.contains(&Span::new(ty.span, *name)) {
+            continue;
+        }
+        let params = ty.params.iter().map(|param| Some(param.symbol())).collect();
+        entries.push(entry(module, codemap, DocKind::Type, name, ty.span, params));
+    }
+    for (name, cb) in module.callbacks.iter() {
+        let params = cb
+            .sigs
+            .first()
+            .map(|sig| sig.params.iter().map(type_param_name).collect())
+            .unwrap_or_default();
+        entries.push(entry(
+            module,
+            codemap,
+            DocKind::Callback,
+            name,
+            cb.span,
+            params,
+        ));
+    }
+    // Symbols are ordered by when they were interned, so sort by name to keep the output stable
+    entries.sort_by_key(|((kind, name), _)| (*kind, name.function.as_str().get(), name.arity));
+
+    etf::Tuple::from(vec![
+        atom("docs_v1"),
+        anno(codemap, anno_span),
+        atom("erlang"),
+        binary("text/markdown"),
+        doc_value(module.moduledoc.as_ref()),
+        metadata,
+        etf::List::from(
+            entries
+                .into_iter()
+                .map(|(_, entry)| entry)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    ])
+    .into()
+}
+
+fn entry(
+    module: &Module,
+    codemap: &CodeMap,
+    kind: DocKind,
+    name: &FunctionName,
+    span: SourceSpan,
+    params: Vec<Option<Symbol>>,
+) -> ((DocKind, FunctionName), etf::Term) {
+    let key = (kind, *name);
+    let doc = module.docs.get(&key);
+    let signature = signature(name.function, params);
+    let entry = etf::Tuple::from(vec![
+        etf::Tuple::from(vec![
+            atom(kind.as_str()),
+            etf::Atom::from(name.function).into(),
+            etf::Term::Integer(Int::Small(name.arity as i64)),
+        ])
+        .into(),
+        anno(codemap, span),
+        etf::List::from(vec![binary(&signature)]).into(),
+        doc_value(doc),
+        doc.map(metadata)
+            .unwrap_or_else(|| etf::Map::from(vec![]).into()),
+    ]);
+    (key, entry.into())
+}
+
+/// Renders a signature such as `get(Key, Arg2)`, using the given parameter names where known
+fn signature(name: Symbol, params: Vec<Option<Symbol>>) -> String {
+    let params = params
+        .iter()
+        .enumerate()
+        .map(|(i, param)| match param {
+            Some(param) => param.to_string(),
+            None => format!("Arg{}", i + 1),
+        })
+        .collect::<Vec<_>>();
+    format!("{}({})", name, params.join(", "))
+}
+
+fn param_name(pattern: &Expr) -> Option<Symbol> {
+    match pattern {
+        Expr::Var(var) if var.is_wanted() && !var.is_compiler_generated() => Some(var.sym()),
+        Expr::Match(Match { pattern, expr, .. }) => {
+            param_name(pattern.as_ref()).or_else(|| param_name(expr.as_ref()))
+        }
+        _ => None,
+    }
+}
+
+fn type_param_name(ty: &Type) -> Option<Symbol> {
+    match ty {
+        Type::Name(Name::Var(id))
+        | Type::Annotated {
+            name: Name::Var(id),
+            ..
+        } => Some(id.name),
+        _ => None,
+    }
+}
+
+fn doc_value(doc: Option<&Doc>) -> etf::Term {
+    match doc {
+        None => atom("none"),
+        Some(doc) if doc.hidden => atom("hidden"),
+        Some(Doc { text: None, .. }) => atom("none"),
+        Some(Doc {
+            text: Some(text), ..
+        }) => etf::Map::from(vec![(binary("en"), binary(text))]).into(),
+    }
+}
+
+fn metadata(doc: &Doc) -> etf::Term {
+    let entries = doc
+        .metadata
+        .iter()
+        .map(|(key, value)| (literal_to_term(key), literal_to_term(value)))
+        .collect::<Vec<_>>();
+    etf::Map::from(entries).into()
+}
+
+fn anno(codemap: &CodeMap, span: SourceSpan) -> etf::Term {
+    let line = codemap
+        .location_for_span(span)
+        .map(|loc| loc.line.number().to_usize() as i64)
+        .unwrap_or(0);
+    etf::Term::Integer(Int::Small(line))
+}
+
+fn atom(name: &str) -> etf::Term {
+    etf::Atom::from(name).into()
+}
+
+fn binary(value: &str) -> etf::Term {
+    etf::Binary::from(value.as_bytes()).into()
+}
+
+fn literal_to_term(literal: &Literal) -> etf::Term {
+    match literal {
+        Literal::Atom(id) => etf::Atom::from(id.name).into(),
+        Literal::String(id) => etf::Str::from(id.name).into(),
+        Literal::Char(_, c) => etf::Term::Integer(Int::Small(*c as i64)),
+        Literal::Integer(_, i) => etf::Term::Integer(i.clone()),
+        Literal::Float(_, f) => etf::Term::Float(*f),
+        Literal::Nil(_) => etf::List::nil().into(),
+        Literal::Cons(_, head, tail) => {
+            let mut elements = vec![literal_to_term(head)];
+            let mut tail = tail.as_ref();
+            while let Literal::Cons(_, head, rest) = tail {
+                elements.push(literal_to_term(head));
+                tail = rest.as_ref();
+            }
+            match tail {
+                Literal::Nil(_) => etf::List::from(elements).into(),
+                last => etf::ImproperList::from((elements, literal_to_term(last))).into(),
+            }
+        }
+        Literal::Tuple(_, elements) => {
+            etf::Tuple::from(elements.iter().map(literal_to_term).collect::<Vec<_>>()).into()
+        }
+        Literal::Map(_, entries) => etf::Map::from(
+            entries
+                .iter()
+                .map(|(k, v)| (literal_to_term(k), literal_to_term(v)))
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        Literal::Binary(_, bin) => {
+            let bytes = bin.bytes().collect::<Vec<u8>>();
+            if bin.is_binary() {
+                etf::Binary::from(bytes).into()
+            } else {
+                let tail_bits_size = (bin.bit_size() % 8) as u8;
+                etf::BitBinary::from((bytes, tail_bits_size)).into()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use firefly_util::diagnostics::*;
+
+    use super::*;
+    use crate::{ParseConfig, Parser};
+
+    fn docs(source: &str) -> Vec<etf::Term> {
+        let codemap = Arc::new(CodeMap::new());
+        let emitter = Arc::new(DefaultEmitter::new(ColorChoice::Auto));
+        let diagnostics =
+            DiagnosticsHandler::new(DiagnosticsConfig::default(), codemap.clone(), emitter);
+        let parser = Parser::new(ParseConfig::default(), codemap.clone());
+        let module = parser
+            .parse_string::<Module, _, _>(&diagnostics, source)
+            .expect("parsing failed");
+        assert!(!diagnostics.has_errors());
+        match docs_v1(&module, &codemap) {
+            etf::Term::Tuple(tuple) => tuple.elements,
+            other => panic!("expected docs_v1 tuple, got {}", other),
+        }
+    }
+
+    fn elements(term: &etf::Term) -> &[etf::Term] {
+        match term {
+            etf::Term::Tuple(tuple) => tuple.elements.as_slice(),
+            etf::Term::List(list) => list.elements.as_slice(),
+            other => panic!("expected tuple or list, got {}", other),
+        }
+    }
+
+    fn text(term: &etf::Term) -> String {
+        match term {
+            etf::Term::Atom(atom) => atom.name.to_string(),
+            etf::Term::Binary(bin) => String::from_utf8(bin.bytes.clone()).unwrap(),
+            etf::Term::Integer(i) => i.to_string(),
+            // Documentation is a map of language to text, only <<"en">> is generated
+            etf::Term::Map(map) if map.entries.len() == 1 => text(&map.entries[0].1),
+            other => panic!("unexpected term {}", other),
+        }
+    }
+
+    #[test]
+    fn docs_v1_from_attributes() {
+        let docs = docs(
+            r#"-module(config).
+-moduledoc "Provides access to configuration".
+-moduledoc #{authors => ["Jane"]}.
+
+-export([get/1, reload/0, internal/0]).
+-export_type([key/0]).
+
+-doc "A configuration key".
+-type key() :: atom().
+
+-doc ~"Called when the configuration changes".
+-callback changed(Key :: key()) -> ok.
+
+-doc "Returns the value of `Key`".
+-doc #{since => ~"1.0.0"}.
+-spec get(key()) -> term().
+get(Key) -> Key.
+
+reload() -> ok.
+
+-doc false.
+internal() -> ok.
+
+-doc "Not exported, so not included".
+private() -> ok.
+"#,
+        );
+
+        assert_eq!(docs.len(), 7);
+        assert_eq!(text(&docs[0]), "docs_v1");
+        assert_eq!(text(&docs[1]), "2");
+        assert_eq!(text(&docs[2]), "erlang");
+        assert_eq!(text(&docs[3]), "text/markdown");
+        assert_eq!(text(&docs[4]), "Provides access to configuration");
+        assert_eq!(docs[5].to_string(), "#{authors=>[Jane]}");
+
+        let entries = elements(&docs[6])
+            .iter()
+            .map(|entry| {
+                let entry = elements(entry);
+                let key = elements(&entry[0]);
+                let signature = elements(&entry[2]);
+                (
+                    format!("{} {}/{}", text(&key[0]), text(&key[1]), text(&key[2])),
+                    text(&signature[0]),
+                    text(&entry[3]),
+                )
+            })
+            .collect::<Vec<_>>();
+        let expected = [
+            ("function get/1", "get(Key)", "Returns the value of `Key`"),
+            ("function internal/0", "internal()", "hidden"),
+            ("function reload/0", "reload()", "none"),
+            ("type key/0", "key()", "A configuration key"),
+            (
+                "callback changed/1",
+                "changed(Key)",
+                "Called when the configuration changes",
+            ),
+        ];
+        assert_eq!(entries.len(), expected.len());
+        for ((key, signature, doc), expected) in entries.iter().zip(expected.iter()) {
+            assert_eq!((key.as_str(), signature.as_str(), doc.as_str()), *expected);
+        }
+
+        let get = elements(&elements(&docs[6])[0]);
+        assert_eq!(text(&get[1]), "17");
+        assert_eq!(get[4].to_string(), "#{since=><<49,46,48,46,48>>}");
+    }
+}
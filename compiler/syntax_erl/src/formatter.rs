@@ -0,0 +1,801 @@
+//! A source formatter for Erlang
+//!
+//! The formatter works on the token stream of a module, rather than on the syntax tree, as the
+//! syntax tree is only available after preprocessing, at which point macros have been expanded
+//! and comments discarded. Working on tokens allows us to leave macros and preprocessor directives
+//! exactly as they were written, and to keep every comment attached to the token it precedes or
+//! follows.
+//!
+//! The layout follows the conventions of erlfmt:
+//!
+//! * Indentation is always in steps of four spaces, never aligned to an opening bracket
+//! * Clause bodies are indented one step from the clause head, and `end` lines up with the
+//!   expression which opened the block
+//! * Binary operators, `->`, `=`, `|` and `::` are surrounded by spaces, while calls, remote calls,
+//!   record and map access, function references and bit syntax type specifiers are printed tight
+//! * Lines which continue an unfinished expression are indented one step further
+//! * Runs of blank lines are collapsed to a single blank line, and trailing whitespace is removed
+//!
+//! Line breaks are kept where the author placed them, so formatting is idempotent: formatting
+//! the output again yields the same output.
+use std::sync::Arc;
+
+use firefly_diagnostics::SourceFile;
+use firefly_parser::{FileMapSource, Scanner, Source};
+
+use crate::lexer::{Lexer, LexicalError, Token};
+
+const INDENT: usize = 4;
+
+/// The preprocessor directives which are printed exactly as written
+const DIRECTIVES: &[&str] = &[
+    "define",
+    "undef",
+    "ifdef",
+    "ifndef",
+    "endif",
+    "elif",
+    "include",
+    "include_lib",
+    "error",
+    "warning",
+];
+
+/// Formats the given source file, returning the formatted source
+///
+/// Returns an error if the file cannot be tokenized.
+pub fn format(file: Arc<SourceFile>) -> Result<String, LexicalError> {
+    let source = file.source();
+    let mut lexer = Lexer::new(Scanner::new(FileMapSource::new(file.clone())));
+
+    let mut items = vec![];
+    let mut last_end = 0;
+    while let Some(lexed) = lexer.lex() {
+        let token = lexed?;
+        if let Token::EOF = token.1 {
+            break;
+        }
+        let start = token.0.index().to_usize();
+        let end = token.2.index().to_usize();
+        let newlines = source[last_end..start].matches('\n').count();
+        items.push(Item {
+            token: token.1,
+            start,
+            end,
+            newlines,
+        });
+        last_end = end;
+    }
+
+    let mut out = String::with_capacity(source.len());
+    for (i, form) in split_forms(source, &items).into_iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+            if form[0].newlines > 1 {
+                out.push('\n');
+            }
+        }
+        let printer = Printer::new(source, form);
+        match printer.print() {
+            Some(text) => out.push_str(&text),
+            None => out.push_str(verbatim(source, form)),
+        }
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+/// A token, or a comment, along with its location in the source
+struct Item {
+    token: Token,
+    start: usize,
+    end: usize,
+    /// The number of line breaks between this item and the previous one
+    newlines: usize,
+}
+impl Item {
+    fn text<'a>(&self, source: &'a str) -> &'a str {
+        let text = &source[self.start..self.end];
+        match self.token {
+            Token::Comment | Token::Edoc => text.trim_end(),
+            _ => text,
+        }
+    }
+
+    #[inline]
+    fn is_comment(&self) -> bool {
+        matches!(self.token, Token::Comment | Token::Edoc)
+    }
+}
+
+/// Splits the items of a file into forms
+///
+/// A form ends with a dot followed by whitespace, a comment, or the end of the file; a dot
+/// followed by anything else is a record field access. Comments on the same line as the end of
+/// a form belong to that form, all other comments belong to the form which follows them.
+fn split_forms<'a>(source: &str, items: &'a [Item]) -> Vec<&'a [Item]> {
+    let mut forms = vec![];
+    let mut start = 0;
+    let mut ended = false;
+    for (i, item) in items.iter().enumerate() {
+        if ended && !(item.is_comment() && item.newlines == 0) {
+            forms.push(&items[start..i]);
+            start = i;
+            ended = false;
+        }
+        if let Token::Dot = item.token {
+            ended = is_form_end(source, item);
+        }
+    }
+    if start < items.len() {
+        forms.push(&items[start..]);
+    }
+    forms
+}
+
+fn is_form_end(source: &str, dot: &Item) -> bool {
+    match source[dot.end..].chars().next() {
+        None | Some('%') => true,
+        Some(c) => c.is_whitespace(),
+    }
+}
+
+/// Returns the source text of a form, used when the form cannot be formatted
+fn verbatim<'a>(source: &'a str, form: &[Item]) -> &'a str {
+    let start = form[0].start;
+    let end = form[form.len() - 1].end;
+    source[start..end].trim_end()
+}
+
+/// The constructs which affect the indentation of the lines within them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// The top level of a form, whose clauses are not indented
+    Form,
+    /// A parenthesized, bracketed, braced or binary expression
+    Bracket(Bracket),
+    /// An expression delimited by a keyword and `end`
+    Block(Block),
+    /// The body of a clause, following `->`
+    Body,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bracket {
+    Paren,
+    Brace,
+    Square,
+    Binary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Begin,
+    Case,
+    Fun,
+    If,
+    Maybe,
+    Receive,
+    Try,
+}
+
+struct Frame {
+    kind: Kind,
+    /// The indentation of the line on which the construct began
+    base: usize,
+    /// The indentation of the lines within the construct
+    indent: usize,
+    /// Set if the current section of the construct consists of clauses, e.g. following `of`
+    clauses: bool,
+    /// The indentation of the line on which the current clause began, if within a clause
+    clause: Option<usize>,
+    /// Set within a binary element, following the `/` which begins its type specifiers
+    type_specifiers: bool,
+}
+impl Frame {
+    fn new(kind: Kind, base: usize, indent: usize) -> Self {
+        let clauses = matches!(
+            kind,
+            Kind::Form | Kind::Block(Block::Fun | Block::If | Block::Receive)
+        );
+        Self {
+            kind,
+            base,
+            indent,
+            clauses,
+            clause: None,
+            type_specifiers: false,
+        }
+    }
+}
+
+/// Prints a single form
+struct Printer<'a> {
+    source: &'a str,
+    form: &'a [Item],
+    out: String,
+    frames: Vec<Frame>,
+    /// The indentation of the current line
+    line_indent: usize,
+    /// The index of the last token printed, ignoring comments
+    prev: Option<usize>,
+    /// Set if the last token printed was a unary operator
+    prev_unary: bool,
+    /// Comments on lines of their own, waiting to be printed at the indentation of the next token
+    pending: Vec<&'a Item>,
+    /// Set if the form is an attribute, in which `fun` introduces a type rather than a block
+    is_attribute: bool,
+}
+impl<'a> Printer<'a> {
+    fn new(source: &'a str, form: &'a [Item]) -> Self {
+        let first = form.iter().position(|item| !item.is_comment());
+        let is_attribute = first
+            .map(|i| form[i].token == Token::Minus)
+            .unwrap_or(false);
+        Self {
+            source,
+            form,
+            out: String::new(),
+            frames: vec![Frame::new(Kind::Form, 0, 0)],
+            line_indent: 0,
+            prev: None,
+            prev_unary: false,
+            pending: vec![],
+            is_attribute,
+        }
+    }
+
+    /// Prints the form, returning `None` if its brackets or blocks are unbalanced, in which case
+    /// it should be left as written
+    fn print(mut self) -> Option<String> {
+        let form = self.form;
+        let mut i = 0;
+        while i < form.len() {
+            let item = &form[i];
+            if item.is_comment() {
+                self.comment(item);
+                i += 1;
+                continue;
+            }
+            if self.prev.is_none() && self.is_directive(i) {
+                i = self.directive(i);
+                continue;
+            }
+            self.token(i)?;
+            i += 1;
+        }
+
+        // Comments at the end of the file
+        if !self.pending.is_empty() {
+            self.flush_comments(0);
+        }
+
+        let balanced = self
+            .frames
+            .iter()
+            .all(|frame| matches!(frame.kind, Kind::Form | Kind::Body));
+        if balanced {
+            Some(self.out)
+        } else {
+            None
+        }
+    }
+
+    fn comment(&mut self, item: &'a Item) {
+        if item.newlines == 0 && !self.out.is_empty() && self.pending.is_empty() {
+            self.out.push(' ');
+            self.out.push_str(item.text(self.source));
+        } else {
+            self.pending.push(item);
+        }
+    }
+
+    fn flush_comments(&mut self, indent: usize) {
+        for item in std::mem::take(&mut self.pending) {
+            self.newline(item.newlines, indent);
+            self.out.push_str(item.text(self.source));
+        }
+    }
+
+    /// Starts a new line with the given indentation, unless nothing has been printed yet
+    fn newline(&mut self, newlines: usize, indent: usize) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+            if newlines > 1 {
+                self.out.push('\n');
+            }
+        }
+        for _ in 0..indent {
+            self.out.push(' ');
+        }
+        self.line_indent = indent;
+    }
+
+    fn is_directive(&self, i: usize) -> bool {
+        if self.form[i].token != Token::Minus {
+            return false;
+        }
+        match self.next_token(i) {
+            Some(Token::If | Token::Else) => true,
+            Some(Token::Atom(name)) => DIRECTIVES.contains(&name.as_str().get()),
+            _ => false,
+        }
+    }
+
+    /// Prints the directive starting at `i` as written, returning the index of the item following it
+    fn directive(&mut self, i: usize) -> usize {
+        let end = self.form[i..]
+            .iter()
+            .position(|item| item.token == Token::Dot && is_form_end(self.source, item))
+            .map(|n| i + n)
+            .unwrap_or(self.form.len() - 1);
+        self.flush_comments(0);
+        self.newline(self.form[i].newlines, 0);
+        self.out
+            .push_str(&self.source[self.form[i].start..self.form[end].end]);
+        self.prev = Some(end);
+        end + 1
+    }
+
+    fn token(&mut self, i: usize) -> Option<()> {
+        let form = self.form;
+        let item = &form[i];
+        let token = &item.token;
+
+        // Tokens which close a construct are printed at the indentation of the line which opened it
+        let dedent = self.close(i)?;
+
+        if self.prev.is_none() || item.newlines > 0 || !self.pending.is_empty() {
+            let indent = match dedent {
+                Some(base) => base,
+                None if self.is_continuation() => self.top().indent + INDENT,
+                None => self.top().indent,
+            };
+            self.flush_comments(indent);
+            self.newline(item.newlines, indent);
+        } else if self.needs_space(i) {
+            self.out.push(' ');
+        }
+        self.out.push_str(item.text(self.source));
+
+        // The first token following a separator begins a new clause
+        let line_indent = self.line_indent;
+        let separator = matches!(
+            token,
+            Token::Semicolon | Token::Of | Token::Catch | Token::After | Token::Else
+        );
+        let top = self.top_mut();
+        if matches!(top.kind, Kind::Form | Kind::Block(_)) && top.clause.is_none() && !separator {
+            top.clause = Some(line_indent);
+        }
+
+        self.prev_unary = matches!(token, Token::Minus | Token::Plus) && !self.prev_is_value();
+        self.prev = Some(i);
+        self.open(i);
+
+        Some(())
+    }
+
+    /// Pops the constructs closed by the token at `i`, returning the indentation at which it
+    /// should be printed if it begins a line
+    fn close(&mut self, i: usize) -> Option<Option<usize>> {
+        let token = &self.form[i].token;
+        let bracket = match token {
+            Token::RParen => Some(Bracket::Paren),
+            Token::RBrace => Some(Bracket::Brace),
+            Token::RBracket => Some(Bracket::Square),
+            Token::BinaryEnd => Some(Bracket::Binary),
+            _ => None,
+        };
+        if let Some(bracket) = bracket {
+            self.pop_bodies();
+            let frame = self.frames.pop()?;
+            if frame.kind != Kind::Bracket(bracket) {
+                return None;
+            }
+            return Some(Some(frame.base));
+        }
+
+        match token {
+            Token::End => {
+                self.pop_bodies();
+                let frame = self.frames.pop()?;
+                match frame.kind {
+                    Kind::Block(_) => Some(Some(frame.base)),
+                    _ => None,
+                }
+            }
+            Token::Of => Some(self.section(&[Block::Case, Block::Try], true)),
+            Token::Catch => Some(self.section(&[Block::Try], true)),
+            // The `after` section of `receive` is a clause, while that of `try` is a body
+            Token::After => {
+                let receive = self.section(&[Block::Receive], true);
+                Some(receive.or_else(|| self.section(&[Block::Try], false)))
+            }
+            Token::Else => Some(self.section(&[Block::Maybe], true)),
+            Token::Semicolon => {
+                if self.top().kind == Kind::Body {
+                    self.frames.pop();
+                    self.top_mut().clause = None;
+                }
+                Some(None)
+            }
+            Token::Comma | Token::BarBar => {
+                self.top_mut().type_specifiers = false;
+                Some(None)
+            }
+            Token::Dot if is_form_end(self.source, &self.form[i]) => {
+                self.pop_bodies();
+                Some(None)
+            }
+            _ => Some(None),
+        }
+    }
+
+    /// Handles a keyword which begins a new section of one of the given blocks, e.g. `of` in
+    /// `case`, returning the indentation at which it should be printed if it does
+    ///
+    /// Such keywords may also appear elsewhere, e.g. `catch` as a prefix operator, in which case
+    /// they are treated like any other token.
+    fn section(&mut self, blocks: &[Block], clauses: bool) -> Option<usize> {
+        let frame = self
+            .frames
+            .iter()
+            .rev()
+            .find(|frame| frame.kind != Kind::Body)?;
+        match frame.kind {
+            Kind::Block(block) if blocks.contains(&block) => {
+                self.pop_bodies();
+                let top = self.top_mut();
+                top.clauses = clauses;
+                top.clause = None;
+                Some(top.base)
+            }
+            _ => None,
+        }
+    }
+
+    /// Pushes the construct opened by the token at `i`, if any
+    fn open(&mut self, i: usize) {
+        let base = self.line_indent;
+        let indent = base + INDENT;
+        let kind = match self.form[i].token {
+            Token::LParen => Kind::Bracket(Bracket::Paren),
+            Token::LBrace => Kind::Bracket(Bracket::Brace),
+            Token::LBracket => Kind::Bracket(Bracket::Square),
+            Token::BinaryStart => Kind::Bracket(Bracket::Binary),
+            Token::Begin => Kind::Block(Block::Begin),
+            Token::Case => Kind::Block(Block::Case),
+            Token::If => Kind::Block(Block::If),
+            Token::Maybe => Kind::Block(Block::Maybe),
+            Token::Receive => Kind::Block(Block::Receive),
+            Token::Try => Kind::Block(Block::Try),
+            // `fun foo/1` and `fun m:f/1` are references, not blocks, and in attributes `fun`
+            // introduces a function type
+            Token::Fun if !self.is_attribute && self.is_fun_expr(i) => Kind::Block(Block::Fun),
+            Token::RightStab => match self.top().kind {
+                Kind::Form | Kind::Block(_) => {
+                    let clause = self.top().clause.unwrap_or(base);
+                    self.frames
+                        .push(Frame::new(Kind::Body, clause, clause + INDENT));
+                    return;
+                }
+                _ => return,
+            },
+            Token::Slash if self.top().kind == Kind::Bracket(Bracket::Binary) => {
+                self.top_mut().type_specifiers = true;
+                return;
+            }
+            _ => return,
+        };
+        self.frames.push(Frame::new(kind, base, indent));
+    }
+
+    /// Returns true if the `fun` at `i` begins an anonymous function, i.e. `fun (..) -> .. end`,
+    /// or a named one, i.e. `fun Name(..) -> .. end`
+    fn is_fun_expr(&self, i: usize) -> bool {
+        let next = self.form[i + 1..].iter().find(|item| !item.is_comment());
+        match next.map(|item| &item.token) {
+            Some(Token::LParen) => true,
+            Some(Token::Ident(_)) => self.next_token(i + 1) == Some(&Token::LParen),
+            _ => false,
+        }
+    }
+
+    fn pop_bodies(&mut self) {
+        while self.top().kind == Kind::Body {
+            self.frames.pop();
+        }
+    }
+
+    fn top(&self) -> &Frame {
+        self.frames.last().unwrap()
+    }
+
+    fn top_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn next_token(&self, i: usize) -> Option<&Token> {
+        self.form[i + 1..]
+            .iter()
+            .find(|item| !item.is_comment())
+            .map(|item| &item.token)
+    }
+
+    fn prev_token(&self) -> Option<&Token> {
+        self.prev.map(|i| &self.form[i].token)
+    }
+
+    /// Returns the token printed before the previous token, ignoring comments
+    fn prev2_token(&self) -> Option<&Token> {
+        let prev = self.prev?;
+        self.form[..prev]
+            .iter()
+            .rev()
+            .find(|item| !item.is_comment())
+            .map(|item| &item.token)
+    }
+
+    /// Returns true if the previous token ends an expression, which determines whether a
+    /// following `-` or `+` is a binary or unary operator
+    fn prev_is_value(&self) -> bool {
+        matches!(
+            self.prev_token(),
+            Some(
+                Token::Atom(_)
+                    | Token::Ident(_)
+                    | Token::Integer(_)
+                    | Token::Float(_)
+                    | Token::Char(_)
+                    | Token::String(_)
+                    | Token::BinaryString(_)
+                    | Token::RParen
+                    | Token::RBrace
+                    | Token::RBracket
+                    | Token::BinaryEnd
+                    | Token::End
+            )
+        )
+    }
+
+    /// Returns true if a line starting at the current position continues an unfinished
+    /// expression from the previous line
+    fn is_continuation(&self) -> bool {
+        let top = self.top();
+        match self.prev_token() {
+            None => false,
+            // Guards which span multiple lines, e.g. `foo(X) when is_integer(X),\n X > 0 ->`
+            Some(Token::Comma | Token::Semicolon) => {
+                matches!(top.kind, Kind::Form | Kind::Block(_))
+                    && top.clauses
+                    && top.clause.is_some()
+            }
+            Some(
+                Token::RightStab
+                | Token::Dot
+                | Token::LParen
+                | Token::LBrace
+                | Token::LBracket
+                | Token::BinaryStart
+                | Token::Begin
+                | Token::Case
+                | Token::If
+                | Token::Maybe
+                | Token::Receive
+                | Token::Try
+                | Token::Of
+                | Token::After
+                | Token::Else,
+            ) => false,
+            // `catch` and `fun` only open a new section when they are part of a block
+            Some(Token::Catch | Token::Fun) => !matches!(top.kind, Kind::Block(_)),
+            // The constraints of a spec, e.g. `-spec foo(A) -> A when\n A :: atom().`, are
+            // indented like a body, while a guard following a clause head is a continuation
+            Some(Token::When) => top.kind != Kind::Body,
+            Some(_) => true,
+        }
+    }
+
+    /// Returns true if the token at `i` should be separated from the previous token by a space
+    fn needs_space(&self, i: usize) -> bool {
+        let next = &self.form[i].token;
+        let prev = match self.prev_token() {
+            Some(prev) => prev,
+            None => return false,
+        };
+
+        // Within the type specifiers of a binary element, e.g. `X:8/big-unsigned-integer`
+        if self.top().type_specifiers {
+            return false;
+        }
+        if self.prev_unary {
+            return false;
+        }
+        match next {
+            Token::RParen
+            | Token::RBrace
+            | Token::RBracket
+            | Token::BinaryEnd
+            | Token::Comma
+            | Token::Semicolon
+            | Token::Dot
+            | Token::Colon => return false,
+            _ => (),
+        }
+        match prev {
+            Token::LParen
+            | Token::LBrace
+            | Token::LBracket
+            | Token::BinaryStart
+            | Token::Pound
+            | Token::Question
+            | Token::DoubleQuestion
+            | Token::Colon
+            | Token::Dot => return false,
+            _ => (),
+        }
+
+        let prev2 = self.prev2_token();
+        match next {
+            // Calls, e.g. `foo(..)`, `F(..)`, `fun(..)` and `(F)(..)`
+            Token::LParen => !matches!(
+                prev,
+                Token::Atom(_) | Token::Ident(_) | Token::RParen | Token::Fun
+            ),
+            // Records, e.g. `#rec{..}`
+            Token::LBrace => !matches!((prev2, prev), (Some(Token::Pound), Token::Atom(_))),
+            // Record and map access, e.g. `R#rec.field` and `M#{..}`
+            Token::Pound => !matches!(
+                (prev2, prev),
+                (_, Token::Ident(_) | Token::RParen | Token::RBrace)
+                    | (Some(Token::Question), Token::Atom(_))
+            ),
+            // Bit syntax, e.g. `<<X/binary>>`
+            Token::Slash if self.top().kind == Kind::Bracket(Bracket::Binary) => false,
+            // Function references, e.g. `foo/1`
+            Token::Slash => !matches!(
+                (prev, self.next_token(i)),
+                (Token::Atom(_), Some(Token::Integer(_)))
+            ),
+            Token::Integer(_) => !matches!((prev2, prev), (Some(Token::Atom(_)), Token::Slash)),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use firefly_diagnostics::CodeMap;
+
+    use super::*;
+
+    fn fmt(source: &str) -> String {
+        let codemap = CodeMap::new();
+        let id = codemap.add("test.erl", source.to_string());
+        let formatted = format(codemap.get(id).unwrap()).unwrap();
+
+        // Formatting is idempotent
+        let id = codemap.add("formatted.erl", formatted.clone());
+        assert_eq!(format(codemap.get(id).unwrap()).unwrap(), formatted);
+
+        formatted
+    }
+
+    #[test]
+    fn spacing() {
+        assert_eq!(
+            fmt("-module( foo ).\n-export( [ foo/1,bar/2 ] ).\n"),
+            "-module(foo).\n-export([foo/1, bar/2]).\n"
+        );
+        assert_eq!(fmt("foo(X,Y)->X+Y*-1.\n"), "foo(X, Y) -> X + Y * -1.\n");
+        assert_eq!(
+            fmt("foo(R)->{R#rec.a,#rec{a=1},M#{a=>1},[H|T],fun bar/1,lists:map(F,L)}.\n"),
+            "foo(R) -> {R#rec.a, #rec{a = 1}, M#{a => 1}, [H | T], fun bar/1, lists:map(F, L)}.\n"
+        );
+        assert_eq!(
+            fmt("foo(<<X:8/big-unsigned-integer,Rest/binary>>)-><< <<Y>>||<<Y>><=Rest>>.\n"),
+            "foo(<<X:8/big-unsigned-integer, Rest/binary>>) -> <<<<Y>> || <<Y>> <= Rest>>.\n"
+        );
+        assert_eq!(
+            fmt("-spec foo(integer())->fun((atom())->ok).\n"),
+            "-spec foo(integer()) -> fun((atom()) -> ok).\n"
+        );
+    }
+
+    #[test]
+    fn indentation() {
+        let source = "
+foo(X) ->
+  case X of
+  {ok, Y} ->
+  lists:map(fun(Z) ->
+  Z + 1
+  end, Y);
+  error when X =/= undefined,
+  X > 1 ->
+        Y = 1 +
+  2,
+  Y
+  end.
+";
+        let expected = "foo(X) ->
+    case X of
+        {ok, Y} ->
+            lists:map(fun(Z) ->
+                Z + 1
+            end, Y);
+        error when X =/= undefined,
+            X > 1 ->
+            Y = 1 +
+                2,
+            Y
+    end.
+";
+        assert_eq!(fmt(source), expected);
+
+        let source = "
+bar() ->
+try foo() of
+ok -> ok
+catch
+_:_ ->
+  receive
+  Msg -> Msg
+  after 100 ->
+  timeout
+  end
+end.
+";
+        let expected = "bar() ->
+    try foo() of
+        ok -> ok
+    catch
+        _:_ ->
+            receive
+                Msg -> Msg
+            after 100 ->
+                timeout
+            end
+    end.
+";
+        assert_eq!(fmt(source), expected);
+    }
+
+    #[test]
+    fn comments_and_directives() {
+        let source = "%% A module
+
+
+-module(foo).   % trailing
+-define(ADD(X,Y),   X+Y).
+-ifdef(TEST).
+foo() ->
+      % leading
+      ?ADD(1,2).
+-endif.
+%% the end
+";
+        let expected = "%% A module
+
+-module(foo). % trailing
+-define(ADD(X,Y),   X+Y).
+-ifdef(TEST).
+foo() ->
+    % leading
+    ?ADD(1, 2).
+-endif.
+%% the end
+";
+        assert_eq!(fmt(source), expected);
+    }
+
+    #[test]
+    fn unbalanced_forms_are_left_as_written() {
+        let source = "-define(BEGIN, begin).\nfoo() ->  ?BEGIN ok end.\n";
+        assert_eq!(fmt(source), source);
+    }
+}
@@ -24,6 +24,15 @@ pub enum LexicalError {
     #[error("Unclosed atom literal")]
     UnclosedAtom { span: SourceSpan },
 
+    /// Occurs when a string literal is malformed, e.g. a triple-quoted string whose lines are not
+    /// indented like its closing delimiter, or an escape denoting an invalid code point
+    #[error("{reason}")]
+    InvalidString { span: SourceSpan, reason: String },
+
+    /// Occurs when a sigil has an unknown type, an invalid delimiter, or an unsupported suffix
+    #[error("{reason}")]
+    InvalidSigil { span: SourceSpan, reason: String },
+
     #[error(transparent)]
     EscapeError {
         #[from]
@@ -43,6 +52,8 @@ impl Hash for LexicalError {
             LexicalError::UnclosedAtom { .. } => 3,
             LexicalError::EscapeError { .. } => 4,
             LexicalError::UnexpectedCharacter { .. } => 5,
+            LexicalError::InvalidString { .. } => 6,
+            LexicalError::InvalidSigil { .. } => 7,
         };
         id.hash(state);
     }
@@ -62,6 +73,16 @@ impl ToDiagnostic for LexicalError {
                 .with_labels(vec![
                     Label::primary(span.source_id(), span).with_message(msg)
                 ]),
+            LexicalError::InvalidString { .. } => Diagnostic::error()
+                .with_message("invalid string literal")
+                .with_labels(vec![
+                    Label::primary(span.source_id(), span).with_message(msg)
+                ]),
+            LexicalError::InvalidSigil { .. } => Diagnostic::error()
+                .with_message("invalid sigil")
+                .with_labels(vec![
+                    Label::primary(span.source_id(), span).with_message(msg)
+                ]),
             LexicalError::EscapeError { source } => source.to_diagnostic(),
             LexicalError::UnexpectedCharacter { .. } => Diagnostic::error()
                 .with_message("unexpected character")
@@ -82,6 +103,8 @@ impl LexicalError {
             LexicalError::InvalidRadix { span, .. } => *span,
             LexicalError::UnclosedString { span, .. } => *span,
             LexicalError::UnclosedAtom { span, .. } => *span,
+            LexicalError::InvalidString { span, .. } => *span,
+            LexicalError::InvalidSigil { span, .. } => *span,
             LexicalError::EscapeError { source } => source.span(),
             LexicalError::UnexpectedCharacter { start, .. } => SourceSpan::new(*start, *start),
         }
@@ -276,6 +276,7 @@ where
             '}' => pop!(self, Token::RBrace),
             '?' => match self.peek() {
                 '?' => pop2!(self, Token::DoubleQuestion),
+                '=' => pop2!(self, Token::QuestionEqual),
                 _ => pop!(self, Token::Question),
             },
             '-' => match self.peek() {
@@ -296,7 +297,14 @@ where
                 }
                 Token::Char(self.pop())
             }
+            '"' if self.peek() == '"' && self.peek_next() == '"' => {
+                match self.lex_triple_quoted_string(false) {
+                    Ok(string) => Token::String(Symbol::intern(&string)),
+                    Err(err) => Token::Error(err),
+                }
+            }
             '"' => self.lex_string(),
+            '~' => self.lex_sigil(),
             '\'' => match self.lex_string() {
                 Token::String(s) => Token::Atom(s),
                 other => other,
@@ -503,17 +511,55 @@ where
         }
     }
 
+    /// Lexes an escape sequence, pushing the character it denotes on to `buf`
+    fn lex_escaped_char(&mut self, buf: &mut String) -> Result<(), LexicalError> {
+        let start = self.index();
+        let cp = self.lex_escape_sequence()?;
+        let span = SourceSpan::new(start, self.index());
+        push_code_point(buf, cp, span)
+    }
+
+    /// Processes the escape sequences in `input`, a fragment of a string starting at `start`
+    fn unescape(
+        &mut self,
+        input: &str,
+        start: SourceIndex,
+        buf: &mut String,
+    ) -> Result<(), LexicalError> {
+        self.escape.reset();
+
+        let mut chars = input.char_indices().peekable();
+        loop {
+            let (offset, c) = match chars.peek() {
+                Some(&(offset, c)) => (offset, Some(c)),
+                None => (input.len(), None),
+            };
+            let (action, result) = self.escape.transition(c, start + offset)?;
+            if let Some(result) = result {
+                let span = SourceSpan::new(result.range.0, result.range.1);
+                push_code_point(buf, result.cp, span)?;
+            }
+            if let EscapeStmAction::Next = action {
+                if chars.next().is_none() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
     #[inline]
     fn lex_string(&mut self) -> Token {
         let quote = self.pop();
         debug_assert!(quote == '"' || quote == '\'');
-        let mut buf = None;
+        let start = self.token_start;
+        let mut buf = String::new();
         loop {
             match self.read() {
-                '\\' => match self.lex_escape_sequence() {
-                    Ok(_c) => (),
-                    Err(err) => return Token::Error(err),
-                },
+                '\\' => {
+                    if let Err(err) = self.lex_escaped_char(&mut buf) {
+                        return Token::Error(err);
+                    }
+                }
                 '\0' if quote == '"' => {
                     return Token::Error(LexicalError::UnclosedString { span: self.span() });
                 }
@@ -521,30 +567,222 @@ where
                     return Token::Error(LexicalError::UnclosedAtom { span: self.span() });
                 }
                 c if c == quote => {
-                    let span = self.span().shrink_front(ByteOffset(1));
-
                     self.skip();
+
+                    // Adjacent literals are concatenated, but the token still starts at the first
                     self.advance_start();
-                    if self.read() == quote {
+                    self.token_start = start;
+                    let is_triple_quoted =
+                        quote == '"' && self.peek() == '"' && self.peek_next() == '"';
+                    if self.read() == quote && !is_triple_quoted {
                         self.skip();
-
-                        buf = Some(self.slice_span(span).to_string());
                         continue;
                     }
 
-                    let symbol = if let Some(mut buf) = buf {
-                        buf.push_str(self.slice_span(span));
-                        Symbol::intern(&buf)
-                    } else {
-                        Symbol::intern(self.slice_span(span))
-                    };
+                    return Token::String(Symbol::intern(&buf));
+                }
+                c => {
+                    buf.push(c);
+                    self.skip();
+                }
+            }
+        }
+    }
+
+    /// Lexes a triple-quoted string (EEP 64), e.g.:
+    ///
+    /// ```erlang
+    ///     """
+    ///     Hello, "world"!
+    ///     """
+    /// ```
+    ///
+    /// The opening delimiter is three or more double quotes, followed only by whitespace on its
+    /// line. The closing delimiter is the same number of double quotes, preceded only by
+    /// whitespace on its own line; that whitespace is the indentation stripped from each line of
+    /// the content, which is every line in between. Content is verbatim unless `escapes` is set,
+    /// as it is for the `~s` and `~b` sigils.
+    fn lex_triple_quoted_string(&mut self, escapes: bool) -> Result<String, LexicalError> {
+        let mut quotes = 0;
+        while self.read() == '"' {
+            self.skip();
+            quotes += 1;
+        }
+        debug_assert!(quotes >= 3);
 
-                    let token = Token::String(symbol);
-                    return token;
+        loop {
+            match self.read() {
+                '\n' => {
+                    self.skip();
+                    break;
                 }
+                '\0' => return Err(LexicalError::UnclosedString { span: self.span() }),
+                c if c.is_whitespace() => self.skip(),
                 _ => {
+                    let start = self.index();
+                    return Err(LexicalError::InvalidString {
+                        span: SourceSpan::new(start, start),
+                        reason: "expected only whitespace on the line of the opening delimiter"
+                            .to_string(),
+                    });
+                }
+            }
+        }
+
+        // Read each line of content until we reach the closing delimiter, whose leading
+        // whitespace we keep as the indentation to strip from the other lines
+        let mut lines = vec![];
+        let indent = loop {
+            let line_start = self.index();
+            let mut line = String::new();
+            while self.read() != '\n' && self.read().is_whitespace() {
+                line.push(self.pop());
+            }
+            let mut n = 0;
+            while n < quotes && self.read() == '"' {
+                self.skip();
+                n += 1;
+            }
+            if n == quotes {
+                break line;
+            }
+            line.extend(std::iter::repeat('"').take(n));
+            loop {
+                match self.read() {
+                    '\n' => {
+                        self.skip();
+                        break;
+                    }
+                    '\0' => return Err(LexicalError::UnclosedString { span: self.span() }),
+                    c => {
+                        line.push(c);
+                        self.skip();
+                    }
+                }
+            }
+            if line.ends_with('\r') {
+                line.pop();
+            }
+            lines.push((line_start, line));
+        };
+
+        let mut buf = String::new();
+        for (i, (line_start, line)) in lines.iter().enumerate() {
+            if i > 0 {
+                buf.push('\n');
+            }
+            // Empty lines need not be indented
+            if line.is_empty() {
+                continue;
+            }
+            let Some(content) = line.strip_prefix(indent.as_str()) else {
+                return Err(LexicalError::InvalidString {
+                    span: SourceSpan::new(*line_start, *line_start + line.len()),
+                    reason: "line is not indented like the closing delimiter".to_string(),
+                });
+            };
+            if escapes {
+                self.unescape(content, *line_start + indent.len(), &mut buf)?;
+            } else {
+                buf.push_str(content);
+            }
+        }
+
+        Ok(buf)
+    }
+
+    /// Lexes a sigil (EEP 66), e.g. `~s"string"` or `~B[verbatim binary]`
+    ///
+    /// The sigil type determines the kind of literal, and whether escape sequences are processed:
+    ///
+    /// * `~s` is a string, `~S` a verbatim string
+    /// * `~b` is a binary, `~B` a verbatim binary
+    /// * `~` is the same as `~B` for triple-quoted strings, and `~b` otherwise
+    fn lex_sigil(&mut self) -> Token {
+        let c = self.pop();
+        debug_assert_eq!(c, '~');
+
+        let sigil = match self.read() {
+            c @ ('s' | 'S' | 'b' | 'B') => {
+                self.skip();
+                Some(c)
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                self.skip();
+                return Token::Error(LexicalError::InvalidSigil {
+                    span: self.span(),
+                    reason: format!("unsupported sigil type '{}'", c),
+                });
+            }
+            _ => None,
+        };
+
+        let is_triple_quoted = self.read() == '"' && self.peek() == '"' && self.peek_next() == '"';
+        let (escapes, binary) = match sigil {
+            Some('s') => (true, false),
+            Some('S') => (false, false),
+            Some('b') => (true, true),
+            Some('B') => (false, true),
+            _ => (!is_triple_quoted, true),
+        };
+
+        let result = if is_triple_quoted {
+            self.lex_triple_quoted_string(escapes)
+        } else {
+            let close = match self.read() {
+                '(' => ')',
+                '[' => ']',
+                '{' => '}',
+                '<' => '>',
+                c @ ('/' | '|' | '\'' | '"' | '`' | '#') => c,
+                c => {
+                    return Token::Error(LexicalError::InvalidSigil {
+                        span: self.span(),
+                        reason: format!("invalid sigil delimiter '{}'", c),
+                    });
+                }
+            };
+            self.skip();
+            self.lex_sigil_string(close, escapes)
+        };
+        let string = match result {
+            Ok(string) => string,
+            Err(err) => return Token::Error(err),
+        };
+
+        // The suffix is reserved for sigil modifiers, of which there are none for these sigils
+        if self.read().is_alphanumeric() || self.read() == '_' {
+            while self.read().is_alphanumeric() || self.read() == '_' {
+                self.skip();
+            }
+            return Token::Error(LexicalError::InvalidSigil {
+                span: self.span(),
+                reason: "sigil suffixes are not supported".to_string(),
+            });
+        }
+
+        let symbol = Symbol::intern(&string);
+        if binary {
+            Token::BinaryString(symbol)
+        } else {
+            Token::String(symbol)
+        }
+    }
+
+    /// Lexes the content of a sigil up to the closing delimiter `close`
+    fn lex_sigil_string(&mut self, close: char, escapes: bool) -> Result<String, LexicalError> {
+        let mut buf = String::new();
+        loop {
+            match self.read() {
+                '\\' if escapes => self.lex_escaped_char(&mut buf)?,
+                '\0' => return Err(LexicalError::UnclosedString { span: self.span() }),
+                c if c == close => {
+                    self.skip();
+                    return Ok(buf);
+                }
+                c => {
+                    buf.push(c);
                     self.skip();
-                    continue;
                 }
             }
         }
@@ -819,6 +1057,20 @@ fn to_integer_literal(literal: &str, radix: u32) -> Token {
     Token::Integer(int)
 }
 
+// Pushes the character denoted by the code point `cp` of an escape sequence on to `buf`
+fn push_code_point(buf: &mut String, cp: u64, span: SourceSpan) -> Result<(), LexicalError> {
+    match u32::try_from(cp).ok().and_then(char::from_u32) {
+        Some(c) => {
+            buf.push(c);
+            Ok(())
+        }
+        None => Err(LexicalError::InvalidString {
+            span,
+            reason: format!("invalid code point {:#x} in escape sequence", cp),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use firefly_diagnostics::{ByteIndex, CodeMap, SourceIndex, SourceSpan};
@@ -1023,6 +1275,60 @@ mod test {
         )]);
     }
 
+    #[test]
+    fn lex_string_escapes() {
+        assert_lex!(r#""a\tb\x{41}\101\n""#, |_| vec![Ok(Token::String(
+            symbol!("a\tbAA\n")
+        ))]);
+        assert_lex!(r#"'don\'t'"#, |_| vec![Ok(Token::Atom(symbol!("don't")))]);
+    }
+
+    #[test]
+    fn lex_triple_quoted_string() {
+        assert_lex!("\"\"\"\n  foo\n\n    \"bar\"\\n\n  \"\"\"", |_| vec![Ok(
+            Token::String(symbol!("foo\n\n  \"bar\"\\n"))
+        )]);
+
+        assert_lex!("\"\"\"\n foo\n  \"\"\"", |source_id| vec![Err(
+            LexicalError::InvalidString {
+                span: SourceSpan::new(
+                    SourceIndex::new(source_id, ByteIndex(4)),
+                    SourceIndex::new(source_id, ByteIndex(8))
+                ),
+                reason: "line is not indented like the closing delimiter".to_string(),
+            }
+        )]);
+    }
+
+    #[test]
+    fn lex_sigil() {
+        assert_lex!(r#"~s"a\tb""#, |_| vec![Ok(Token::String(symbol!("a\tb")))]);
+        assert_lex!(r#"~S(a\tb)"#, |_| vec![Ok(Token::String(symbol!("a\\tb")))]);
+        assert_lex!(r#"~b[x\]]"#, |_| vec![Ok(Token::BinaryString(symbol!(
+            "x]"
+        )))]);
+        assert_lex!(r#"~B<a\b>"#, |_| vec![Ok(Token::BinaryString(symbol!(
+            "a\\b"
+        )))]);
+        assert_lex!(r#"~"x\ny""#, |_| vec![Ok(Token::BinaryString(symbol!(
+            "x\ny"
+        )))]);
+        assert_lex!("~\"\"\"\n  a\\n\n  \"\"\"", |_| vec![Ok(
+            Token::BinaryString(symbol!("a\\n"))
+        )]);
+
+        assert_lex!(r#"~x"a""#, |source_id| vec![
+            Err(LexicalError::InvalidSigil {
+                span: SourceSpan::new(
+                    SourceIndex::new(source_id, ByteIndex(0)),
+                    SourceIndex::new(source_id, ByteIndex(2))
+                ),
+                reason: "unsupported sigil type 'x'".to_string(),
+            }),
+            Ok(Token::String(symbol!("a"))),
+        ]);
+    }
+
     #[test]
     fn lex_whitespace() {
         assert_lex!("      \n \t", |_| vec![]);
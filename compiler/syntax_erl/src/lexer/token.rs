@@ -87,6 +87,9 @@ impl TryFrom<LexicalToken> for AtomToken {
             LexicalToken(start, Token::If, end) => {
                 return Ok(AtomToken(start, Token::Atom(symbols::If), end));
             }
+            LexicalToken(start, Token::Else, end) => {
+                return Ok(AtomToken(start, Token::Atom(symbols::Else), end));
+            }
             t => Err(TokenConvertError {
                 span: t.span(),
                 token: t.token(),
@@ -247,6 +250,7 @@ impl TryFrom<LexicalToken> for SymbolToken {
             LexicalToken(_, Token::Atom(_), _) => (),
             LexicalToken(_, Token::Ident(_), _) => (),
             LexicalToken(_, Token::String(_), _) => (),
+            LexicalToken(_, Token::BinaryString(_), _) => (),
             LexicalToken(start, token, end) => return Ok(SymbolToken(start, token, end)),
         }
         Err(TokenConvertError {
@@ -306,6 +310,8 @@ pub enum Token {
     Float(Float),
     Atom(Symbol),
     String(Symbol),
+    // A binary string, as produced by the ~b and ~B sigils
+    BinaryString(Symbol),
     Ident(Symbol),
     // Keywords and Symbols
     LParen,
@@ -335,6 +341,9 @@ pub enum Token {
     Of,
     Receive,
     When,
+    // Keywords of the maybe_expr feature
+    Maybe,
+    Else,
     // Attributes
     Record,
     Spec,
@@ -352,6 +361,8 @@ pub enum Token {
     Nifs,
     Behaviour,
     Deprecated,
+    Doc,
+    ModuleDoc,
     Type,
     Opaque,
     File,
@@ -410,6 +421,8 @@ pub enum Token {
     DotDotDot,
     Question,
     DoubleQuestion,
+    // ?=
+    QuestionEqual,
 }
 impl PartialEq for Token {
     fn eq(&self, other: &Token) -> bool {
@@ -449,6 +462,11 @@ impl PartialEq for Token {
                     return *s == *s2;
                 }
             }
+            Token::BinaryString(ref s) => {
+                if let Token::BinaryString(s2) = other {
+                    return *s == *s2;
+                }
+            }
             _ => return mem::discriminant(self) == mem::discriminant(other),
         }
         return false;
@@ -463,6 +481,7 @@ impl Hash for Token {
             Token::Atom(ref a) => a.hash(state),
             Token::Ident(ref i) => i.hash(state),
             Token::String(ref s) => s.hash(state),
+            Token::BinaryString(ref s) => s.hash(state),
             Token::Char(c) => c.hash(state),
             ref token => token.to_string().hash(state),
         }
@@ -484,6 +503,8 @@ impl Token {
             "of" => Token::Of,
             "receive" => Token::Receive,
             "when" => Token::When,
+            "maybe" => Token::Maybe,
+            "else" => Token::Else,
             "andalso" => Token::AndAlso,
             "orelse" => Token::OrElse,
             "bnot" => Token::Bnot,
@@ -532,8 +553,9 @@ impl fmt::Display for Token {
             Token::Char(ref c) => write!(f, "{}", c),
             Token::Integer(ref i) => write!(f, "{}", i),
             Token::Float(ref n) => write!(f, "{}", n),
-            Token::Atom(ref s) => write!(f, "'{}'", s),
-            Token::String(ref s) => write!(f, "\"{}\"", s),
+            Token::Atom(ref s) => write!(f, "'{}'", Escaped(s.as_str().get(), '\'')),
+            Token::String(ref s) => write!(f, "\"{}\"", Escaped(s.as_str().get(), '"')),
+            Token::BinaryString(ref s) => write!(f, "~\"{}\"", Escaped(s.as_str().get(), '"')),
             Token::Ident(ref s) => write!(f, "{}", s),
             Token::LParen => write!(f, "("),
             Token::RParen => write!(f, ")"),
@@ -561,6 +583,8 @@ impl fmt::Display for Token {
             Token::Of => write!(f, "of"),
             Token::Receive => write!(f, "receive"),
             Token::When => write!(f, "when"),
+            Token::Maybe => write!(f, "maybe"),
+            Token::Else => write!(f, "else"),
             Token::Record => write!(f, "record"),
             Token::Spec => write!(f, "spec"),
             Token::Callback => write!(f, "callback"),
@@ -577,6 +601,8 @@ impl fmt::Display for Token {
             Token::Nifs => write!(f, "nifs"),
             Token::Behaviour => write!(f, "behaviour"),
             Token::Deprecated => write!(f, "deprecated"),
+            Token::Doc => write!(f, "doc"),
+            Token::ModuleDoc => write!(f, "moduledoc"),
             Token::Type => write!(f, "type"),
             Token::Opaque => write!(f, "opaque"),
             Token::File => write!(f, "file"),
@@ -620,6 +646,30 @@ impl fmt::Display for Token {
             Token::DotDotDot => write!(f, "..."),
             Token::Question => write!(f, "?"),
             Token::DoubleQuestion => write!(f, "??"),
+            Token::QuestionEqual => write!(f, "?="),
+        }
+    }
+}
+
+/// Displays the value of a string or quoted atom escaped such that it can be read back in
+struct Escaped<'a>(&'a str, char);
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::fmt::Write;
+
+        for c in self.0.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c == self.1 => {
+                    f.write_char('\\')?;
+                    f.write_char(c)?;
+                }
+                c => f.write_char(c)?,
+            }
         }
+        Ok(())
     }
 }
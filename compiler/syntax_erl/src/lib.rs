@@ -8,8 +8,10 @@
 #[macro_use]
 mod macros;
 mod ast;
+pub mod docs;
 mod evaluator;
 pub mod features;
+pub mod formatter;
 mod lexer;
 mod parser;
 pub mod passes;
@@ -899,6 +899,65 @@ bar() -> yay.
         );
     }
 
+    #[test]
+    fn parse_maybe_expr() {
+        let _result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            r#"-module(foo).
+-feature(maybe_expr, enable).
+
+bar() ->
+    maybe
+        {ok, A} ?= a(),
+        true = A >= 0,
+        {ok, B} ?= b(),
+        A + B
+    else
+        error -> error;
+        {error, Reason} -> {error, Reason}
+    end.
+"#,
+        );
+    }
+
+    #[test]
+    fn parse_map_comprehension() {
+        let _result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            r#"-module(foo).
+
+invert(Map) ->
+    #{V => K || K := V <- Map}.
+
+keys(Map) ->
+    [K || K := _ <- Map, is_atom(K)].
+
+values(Map) ->
+    << <<V>> || _ := V <- Map >>.
+"#,
+        );
+    }
+
+    #[test]
+    fn parse_sigils_and_triple_quoted_strings() {
+        let _result: Module = parse(
+            ParseConfig::default(),
+            Arc::new(CodeMap::new()),
+            r#"-module(foo).
+
+query(~b"users") ->
+    ~"SELECT * FROM users";
+query(Table) ->
+    {~S(SELECT * FROM \), Table, """
+        WHERE id = ?
+          AND name = "foo"
+        """}.
+"#,
+        );
+    }
+
     #[test]
     #[ignore]
     fn parse_elixir_enum_erl() {
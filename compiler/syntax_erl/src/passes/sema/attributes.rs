@@ -235,6 +235,13 @@ pub fn analyze_attribute(diagnostics: &DiagnosticsHandler, module: &mut Module,
             let local_cb_name = cb_name.to_local();
             match module.callbacks.get(&local_cb_name) {
                 None => {
+                    let mut callback = callback;
+                    if module
+                        .optional_callbacks
+                        .contains(&Span::new(callback.span, local_cb_name))
+                    {
+                        callback.optional = true;
+                    }
                     module.callbacks.insert(local_cb_name, callback);
                     return;
                 }
@@ -386,6 +393,17 @@ pub fn analyze_attribute(diagnostics: &DiagnosticsHandler, module: &mut Module,
                 }
             }
         }
+        Attribute::ModuleDoc(span, value) => {
+            let doc = module.moduledoc.get_or_insert_with(|| Doc::new(span));
+            super::docs::merge_doc(diagnostics, doc, span, value);
+        }
+        // A `-doc` attribute is attached to the definition following it while the module is
+        // being constructed, so one seen in isolation has nothing to document
+        Attribute::Doc(span, value) => {
+            let mut doc = None;
+            super::docs::analyze_doc(diagnostics, &mut doc, span, value);
+            super::docs::unused_doc(diagnostics, doc);
+        }
         Attribute::Custom(attr) => {
             match attr.name.name.as_str().get() {
                 "module" => {
@@ -398,6 +416,7 @@ pub fn analyze_attribute(diagnostics: &DiagnosticsHandler, module: &mut Module,
                     return;
                 }
                 "optional_callbacks" => {
+                    optional_callbacks(module, &to_list_simple(&attr.value), diagnostics);
                     return;
                 }
                 // Drop dialyzer attributes as they are unused
@@ -551,6 +570,9 @@ fn set_compile_option(
                 "warn_nif_inline" => options.warn_nif_inline = true,
                 "nowarn_nif_inline" => options.warn_nif_inline = false,
 
+                "warn_behaviours" => options.warn_behaviours = true,
+                "nowarn_behaviours" => options.warn_behaviours = false,
+
                 _name => {
                     diagnostics
                         .diagnostic(Severity::Warning)
@@ -690,6 +712,30 @@ fn no_warn_unused_functions(
     }
 }
 
+fn optional_callbacks(module: &mut Module, funs: &[Expr], diagnostics: &DiagnosticsHandler) {
+    for fun in funs {
+        match fun {
+            Expr::FunctionVar(FunctionVar::PartiallyResolved(name)) => {
+                let local_name = name.item.to_local();
+                if let Some(callback) = module.callbacks.get_mut(&local_name) {
+                    callback.optional = true;
+                }
+                module
+                    .optional_callbacks
+                    .insert(Span::new(name.span(), local_name));
+            }
+            other => {
+                let other_span = other.span();
+                diagnostics
+                    .diagnostic(Severity::Warning)
+                    .with_message("invalid -optional_callbacks attribute")
+                    .with_primary_label(other_span, "expected function name/arity term")
+                    .emit();
+            }
+        }
+    }
+}
+
 fn no_warn_deprecated_functions(
     options: &mut CompileOptions,
     _module: Ident,
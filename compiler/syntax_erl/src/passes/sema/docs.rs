@@ -0,0 +1,118 @@
+use firefly_binary::Bitstring;
+use firefly_intern::symbols;
+use firefly_syntax_base::FunctionName;
+use firefly_util::diagnostics::*;
+
+use crate::ast::*;
+use crate::evaluator;
+
+/// Handles a `-doc` attribute, which documents the next function, type or callback definition.
+///
+/// The documentation is accumulated in `pending` until `attach_doc` finds its target.
+pub fn analyze_doc(
+    diagnostics: &DiagnosticsHandler,
+    pending: &mut Option<Doc>,
+    span: SourceSpan,
+    value: Expr,
+) {
+    let doc = pending.get_or_insert_with(|| Doc::new(span));
+    merge_doc(diagnostics, doc, span, value);
+}
+
+/// Attaches the pending `-doc` documentation to `form`, if it is a function, type or callback
+pub fn attach_doc(module: &mut Module, pending: &mut Option<Doc>, form: &TopLevel) {
+    if pending.is_none() {
+        return;
+    }
+
+    let target = match form {
+        TopLevel::Function(fun) => (
+            DocKind::Function,
+            FunctionName::new_local(fun.name.name, fun.arity),
+        ),
+        TopLevel::Attribute(Attribute::Type(ty)) => (
+            DocKind::Type,
+            FunctionName::new_local(ty.name.name, ty.params.len().try_into().unwrap()),
+        ),
+        TopLevel::Attribute(Attribute::Callback(cb)) => {
+            let arity = cb.sigs.first().map(|sig| sig.params.len()).unwrap_or(0);
+            (
+                DocKind::Callback,
+                FunctionName::new_local(cb.function.name, arity.try_into().unwrap()),
+            )
+        }
+        _ => return,
+    };
+
+    // Redefinitions of the target itself are reported when it is analyzed
+    module.docs.insert(target, pending.take().unwrap());
+}
+
+/// Warns about documentation which was not followed by a definition it could be attached to
+pub fn unused_doc(diagnostics: &DiagnosticsHandler, pending: Option<Doc>) {
+    if let Some(doc) = pending {
+        diagnostics
+            .diagnostic(Severity::Warning)
+            .with_message("unused documentation")
+            .with_primary_label(
+                doc.span,
+                "expected a function, type or callback definition after this attribute",
+            )
+            .with_note("this documentation will be ignored")
+            .emit();
+    }
+}
+
+/// Merges the value of a `-doc` or `-moduledoc` attribute into `doc`.
+///
+/// The value may be the documentation text, `false` to hide the documented item, or a map of
+/// metadata, which is merged with the metadata of any previous attributes for the same item.
+pub fn merge_doc(diagnostics: &DiagnosticsHandler, doc: &mut Doc, span: SourceSpan, value: Expr) {
+    let value_span = value.span();
+    let text = match evaluator::eval_expr(&value, None) {
+        Ok(Literal::Atom(id)) if id.name == symbols::False => {
+            doc.hidden = true;
+            return;
+        }
+        Ok(Literal::String(id)) => id.as_str().get().to_string(),
+        Ok(Literal::Binary(_, ref bin)) if bin.as_str().is_some() => {
+            bin.as_str().unwrap().to_string()
+        }
+        Ok(Literal::Map(_, metadata)) => {
+            doc.metadata.extend(metadata);
+            return;
+        }
+        Ok(Literal::Tuple(_, ref elements))
+            if elements.len() == 2
+                && matches!(elements[0], Literal::Atom(id) if id.name == symbols::File) =>
+        {
+            diagnostics
+                .diagnostic(Severity::Warning)
+                .with_message("unsupported documentation value")
+                .with_primary_label(span, "documentation in external files is not supported")
+                .with_note("this attribute will be ignored")
+                .emit();
+            return;
+        }
+        _ => {
+            diagnostics
+                .diagnostic(Severity::Error)
+                .with_message("invalid documentation value")
+                .with_primary_label(span, "expected a string, binary, map or 'false'")
+                .with_secondary_label(value_span, "this value is not valid here")
+                .emit();
+            return;
+        }
+    };
+
+    if doc.text.is_some() {
+        diagnostics
+            .diagnostic(Severity::Warning)
+            .with_message("redefined documentation")
+            .with_primary_label(span, "redefinition occurs here")
+            .with_secondary_label(doc.span, "previously documented here")
+            .emit();
+    }
+    doc.span = span;
+    doc.text = Some(text);
+}
@@ -110,6 +110,7 @@ impl VisitMut<bool> for IsNifVisitor {
             Expr::Catch(ref mut expr) => self.visit_mut_catch(expr),
             Expr::Case(ref mut case) => self.visit_mut_case(case),
             Expr::Try(ref mut expr) => self.visit_mut_try(expr),
+            Expr::Maybe(ref mut expr) => self.visit_mut_maybe(expr),
             Expr::Protect(ref mut protect) => self.visit_mut_protect(protect),
             _ => ControlFlow::Continue(()),
         }
@@ -0,0 +1,1099 @@
+use core::ops::ControlFlow;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use anyhow::anyhow;
+
+use firefly_intern::{Ident, Symbol};
+use firefly_pass::Pass;
+use firefly_syntax_base::{BinaryOp, FunctionName};
+use firefly_util::diagnostics::*;
+
+use crate::ast::*;
+use crate::visit::{self, VisitMut};
+
+/// Warns about functions which are neither exported nor reachable from an exported function
+///
+/// This honors `-compile(export_all)`, `-compile(nowarn_unused_function)`, and
+/// `-compile({nowarn_unused_function, [...]})`.
+pub struct LintUnusedFunctions<'p> {
+    diagnostics: &'p DiagnosticsHandler,
+}
+impl<'p> LintUnusedFunctions<'p> {
+    pub fn new(diagnostics: &'p DiagnosticsHandler) -> Self {
+        Self { diagnostics }
+    }
+}
+impl<'p> Pass for LintUnusedFunctions<'p> {
+    type Input<'a> = &'a mut Module;
+    type Output<'a> = &'a mut Module;
+
+    fn run<'a>(&mut self, module: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        let enabled = module
+            .compile
+            .as_ref()
+            .map(|c| !c.no_warn && !c.export_all && c.warn_unused_function)
+            .unwrap_or(true);
+        if !enabled {
+            return Ok(module);
+        }
+
+        let module_name = module.name();
+        let mut callees = BTreeMap::new();
+        for (name, function) in module.functions.iter_mut() {
+            let mut visitor = LocalCallsVisitor::new(module_name);
+            visitor.visit_mut_function(function);
+            callees.insert(*name, visitor.calls);
+        }
+
+        let mut worklist = module
+            .exports
+            .iter()
+            .chain(module.on_load.iter())
+            .chain(module.nifs.iter())
+            .map(|name| name.to_local())
+            .collect::<VecDeque<_>>();
+        let mut reachable = BTreeSet::new();
+        while let Some(name) = worklist.pop_front() {
+            if !reachable.insert(name) {
+                continue;
+            }
+            if let Some(calls) = callees.get(&name) {
+                worklist.extend(calls.iter().copied());
+            }
+        }
+
+        let ignored = module
+            .compile
+            .as_ref()
+            .map(|c| {
+                c.no_warn_unused_functions
+                    .iter()
+                    .map(|name| name.to_local())
+                    .collect::<BTreeSet<_>>()
+            })
+            .unwrap_or_default();
+        for (name, function) in module.functions.iter() {
+            if reachable.contains(name) || ignored.contains(name) {
+                continue;
+            }
+            self.diagnostics
+                .diagnostic(Severity::Warning)
+                .with_message("unused function")
+                .with_primary_label(
+                    function.name.span,
+                    format!("the function {} is never called", name),
+                )
+                .emit();
+        }
+
+        Ok(module)
+    }
+}
+
+/// Warns about explicitly imported functions which are never called
+///
+/// NOTE: This must run before auto-imported BIFs are added to the module, as those are
+/// indistinguishable from explicit imports afterwards.
+pub struct LintUnusedImports<'p> {
+    diagnostics: &'p DiagnosticsHandler,
+}
+impl<'p> LintUnusedImports<'p> {
+    pub fn new(diagnostics: &'p DiagnosticsHandler) -> Self {
+        Self { diagnostics }
+    }
+}
+impl<'p> Pass for LintUnusedImports<'p> {
+    type Input<'a> = &'a mut Module;
+    type Output<'a> = &'a mut Module;
+
+    fn run<'a>(&mut self, module: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        let enabled = module
+            .compile
+            .as_ref()
+            .map(|c| !c.no_warn && c.warn_unused_import)
+            .unwrap_or(true);
+        if !enabled || module.imports.is_empty() {
+            return Ok(module);
+        }
+
+        let mut visitor = LocalCallsVisitor::new(module.name());
+        for function in module.functions.values_mut() {
+            visitor.visit_mut_function(function);
+        }
+
+        for (name, sig) in module.imports.iter() {
+            if visitor.calls.contains(name) {
+                continue;
+            }
+            self.diagnostics
+                .diagnostic(Severity::Warning)
+                .with_message("unused import")
+                .with_primary_label(
+                    sig.span(),
+                    format!("{} is imported here, but never called", &sig.mfa()),
+                )
+                .emit();
+        }
+
+        Ok(module)
+    }
+}
+
+/// Warns about records defined in the current module which are never referenced
+///
+/// Records defined in included files are not considered, as headers commonly define
+/// records for consumers which do not use all of them.
+pub struct LintUnusedRecords<'p> {
+    diagnostics: &'p DiagnosticsHandler,
+}
+impl<'p> LintUnusedRecords<'p> {
+    pub fn new(diagnostics: &'p DiagnosticsHandler) -> Self {
+        Self { diagnostics }
+    }
+}
+impl<'p> Pass for LintUnusedRecords<'p> {
+    type Input<'a> = &'a mut Module;
+    type Output<'a> = &'a mut Module;
+
+    fn run<'a>(&mut self, module: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        let enabled = module
+            .compile
+            .as_ref()
+            .map(|c| !c.no_warn && c.warn_unused_record)
+            .unwrap_or(true);
+        if !enabled || module.records.is_empty() {
+            return Ok(module);
+        }
+
+        let mut visitor = RecordUsageVisitor::default();
+        for function in module.functions.values_mut() {
+            visitor.visit_mut_function(function);
+        }
+        for (name, record) in module.records.iter_mut() {
+            for field in record.fields.iter_mut() {
+                if let Some(ty) = field.ty.as_ref() {
+                    collect_type_references(ty, &mut BTreeSet::new(), &mut visitor.records);
+                }
+                // A record referenced only by its own field defaults is still unused
+                let mut fields = RecordUsageVisitor::default();
+                fields.visit_mut_record_field(field);
+                fields.records.remove(name);
+                visitor.records.extend(fields.records);
+            }
+        }
+        for ty in module.types.values() {
+            collect_type_references(&ty.ty, &mut BTreeSet::new(), &mut visitor.records);
+        }
+        for sig in module
+            .specs
+            .values()
+            .flat_map(|spec| spec.sigs.iter())
+            .chain(module.callbacks.values().flat_map(|cb| cb.sigs.iter()))
+        {
+            collect_sig_references(sig, &mut BTreeSet::new(), &mut visitor.records);
+        }
+
+        let source_id = module.span.source_id();
+        for (name, record) in module.records.iter() {
+            if visitor.records.contains(name) || record.span.source_id() != source_id {
+                continue;
+            }
+            self.diagnostics
+                .diagnostic(Severity::Warning)
+                .with_message("unused record")
+                .with_primary_label(
+                    record.name.span,
+                    format!("the record {} is never used", name),
+                )
+                .emit();
+        }
+
+        Ok(module)
+    }
+}
+
+/// Warns about types defined in the current module which are neither exported nor
+/// reachable from a spec, callback, record definition, or exported type
+pub struct LintUnusedTypes<'p> {
+    diagnostics: &'p DiagnosticsHandler,
+}
+impl<'p> LintUnusedTypes<'p> {
+    pub fn new(diagnostics: &'p DiagnosticsHandler) -> Self {
+        Self { diagnostics }
+    }
+}
+impl<'p> Pass for LintUnusedTypes<'p> {
+    type Input<'a> = &'a mut Module;
+    type Output<'a> = &'a mut Module;
+
+    fn run<'a>(&mut self, module: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        let enabled = module
+            .compile
+            .as_ref()
+            .map(|c| !c.no_warn && c.warn_unused_type)
+            .unwrap_or(true);
+        if !enabled || module.types.is_empty() {
+            return Ok(module);
+        }
+
+        let mut roots = module
+            .exported_types
+            .iter()
+            .map(|name| name.to_local())
+            .collect::<BTreeSet<_>>();
+        let mut records = BTreeSet::new();
+        for sig in module
+            .specs
+            .values()
+            .flat_map(|spec| spec.sigs.iter())
+            .chain(module.callbacks.values().flat_map(|cb| cb.sigs.iter()))
+        {
+            collect_sig_references(sig, &mut roots, &mut records);
+        }
+        for ty in module
+            .records
+            .values()
+            .flat_map(|record| record.fields.iter())
+            .filter_map(|field| field.ty.as_ref())
+        {
+            collect_type_references(ty, &mut roots, &mut records);
+        }
+
+        let mut worklist = roots.into_iter().collect::<VecDeque<_>>();
+        let mut reachable = BTreeSet::new();
+        while let Some(name) = worklist.pop_front() {
+            if !reachable.insert(name) {
+                continue;
+            }
+            if let Some(def) = module.types.get(&name) {
+                let mut referenced = BTreeSet::new();
+                collect_type_references(&def.ty, &mut referenced, &mut records);
+                worklist.extend(referenced);
+            }
+        }
+
+        let source_id = module.span.source_id();
+        for (name, def) in module.types.iter() {
+            if reachable.contains(name) || def.span.source_id() != source_id {
+                continue;
+            }
+            self.diagnostics
+                .diagnostic(Severity::Warning)
+                .with_message("unused type")
+                .with_primary_label(def.name.span, format!("the type {} is never used", name))
+                .emit();
+        }
+
+        Ok(module)
+    }
+}
+
+/// Checks variable usage in every function of the module, in the manner of `erl_lint`
+///
+/// This produces warnings for variables which are unused, which shadow a variable
+/// of an enclosing scope in a `fun` head or generator pattern, and which are exported from
+/// a `case`, `if`, or `receive` expression. It produces an error when a variable is used
+/// after an expression which only binds it conditionally, e.g. in only some clauses of a
+/// `case`, or anywhere in a `try`.
+pub struct LintVariables<'p> {
+    diagnostics: &'p DiagnosticsHandler,
+}
+impl<'p> LintVariables<'p> {
+    pub fn new(diagnostics: &'p DiagnosticsHandler) -> Self {
+        Self { diagnostics }
+    }
+}
+impl<'p> Pass for LintVariables<'p> {
+    type Input<'a> = &'a mut Module;
+    type Output<'a> = &'a mut Module;
+
+    fn run<'a>(&mut self, module: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        let (warn_unused, warn_shadow, warn_export) = match module.compile.as_ref() {
+            None => (true, true, true),
+            Some(c) if c.no_warn => (false, false, false),
+            Some(c) => (c.warn_unused_var, c.warn_shadow_vars, c.warn_export_vars),
+        };
+
+        let mut has_errors = false;
+        for function in module.functions.values() {
+            let mut lint = VariableLint {
+                diagnostics: self.diagnostics,
+                warn_shadow,
+                warn_export,
+                definitions: vec![],
+                has_errors: false,
+            };
+            for (_, clause) in function.clauses.iter() {
+                lint.clause(&Env::new(), clause, None);
+            }
+            if warn_unused {
+                lint.report_unused();
+            }
+            has_errors |= lint.has_errors;
+        }
+
+        if has_errors {
+            Err(anyhow!(
+                "semantic analysis found one or more errors, see diagnostics for details"
+            ))
+        } else {
+            Ok(module)
+        }
+    }
+}
+
+/// Collects the set of local functions referenced by calls or `fun` expressions
+struct LocalCallsVisitor {
+    module: Symbol,
+    calls: BTreeSet<FunctionName>,
+}
+impl LocalCallsVisitor {
+    fn new(module: Symbol) -> Self {
+        Self {
+            module,
+            calls: BTreeSet::new(),
+        }
+    }
+}
+impl VisitMut<()> for LocalCallsVisitor {
+    fn visit_mut_apply(&mut self, apply: &mut Apply) -> ControlFlow<()> {
+        if let Expr::Literal(Literal::Atom(id)) = apply.callee.as_ref() {
+            let arity = apply.args.len() as u8;
+            self.calls.insert(FunctionName::new_local(id.name, arity));
+        }
+        visit::visit_mut_apply(self, apply)
+    }
+
+    fn visit_mut_function_var(&mut self, var: &mut FunctionVar) -> ControlFlow<()> {
+        match var {
+            FunctionVar::PartiallyResolved(name) => {
+                self.calls.insert(name.to_local());
+            }
+            FunctionVar::Resolved(name) if name.module == Some(self.module) => {
+                self.calls.insert(name.to_local());
+            }
+            FunctionVar::Unresolved(UnresolvedFunctionName {
+                module,
+                function: Name::Atom(f),
+                arity: Arity::Int(arity),
+                ..
+            }) => match module {
+                None => {
+                    self.calls.insert(FunctionName::new_local(f.name, *arity));
+                }
+                Some(Name::Atom(m)) if m.name == self.module => {
+                    self.calls.insert(FunctionName::new_local(f.name, *arity));
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Collects the names of all records referenced in expressions or patterns
+#[derive(Default)]
+struct RecordUsageVisitor {
+    records: BTreeSet<Symbol>,
+}
+impl VisitMut<()> for RecordUsageVisitor {
+    fn visit_mut_record(&mut self, record: &mut Record) -> ControlFlow<()> {
+        self.records.insert(record.name.name);
+        visit::visit_mut_record(self, record)
+    }
+
+    fn visit_mut_record_access(&mut self, expr: &mut RecordAccess) -> ControlFlow<()> {
+        self.records.insert(expr.name.name);
+        visit::visit_mut_record_access(self, expr)
+    }
+
+    fn visit_mut_record_index(&mut self, expr: &mut RecordIndex) -> ControlFlow<()> {
+        self.records.insert(expr.name.name);
+        ControlFlow::Continue(())
+    }
+
+    fn visit_mut_record_update(&mut self, expr: &mut RecordUpdate) -> ControlFlow<()> {
+        self.records.insert(expr.name.name);
+        visit::visit_mut_record_update(self, expr)
+    }
+}
+
+fn collect_sig_references(
+    sig: &TypeSig,
+    types: &mut BTreeSet<FunctionName>,
+    records: &mut BTreeSet<Symbol>,
+) {
+    for ty in sig.params.iter() {
+        collect_type_references(ty, types, records);
+    }
+    collect_type_references(sig.ret.as_ref(), types, records);
+    for guard in sig.guards.iter().flatten() {
+        collect_type_references(&guard.ty, types, records);
+    }
+}
+
+/// Collects the local types and records referenced by `ty`
+fn collect_type_references(
+    ty: &Type,
+    types: &mut BTreeSet<FunctionName>,
+    records: &mut BTreeSet<Symbol>,
+) {
+    match ty {
+        Type::Name(_) | Type::Nil(_) | Type::Integer(..) | Type::Char(..) => (),
+        Type::Annotated { ty, .. } => collect_type_references(ty, types, records),
+        Type::Union { types: tys, .. } | Type::Map(_, tys) | Type::Tuple(_, tys) => {
+            for ty in tys.iter() {
+                collect_type_references(ty, types, records);
+            }
+        }
+        Type::Range { start, end, .. } => {
+            collect_type_references(start, types, records);
+            collect_type_references(end, types, records);
+        }
+        Type::BinaryOp { lhs, rhs, .. } => {
+            collect_type_references(lhs, types, records);
+            collect_type_references(rhs, types, records);
+        }
+        Type::UnaryOp { rhs, .. } => collect_type_references(rhs, types, records),
+        Type::Generic { fun, params, .. } => {
+            let arity = params.len() as u8;
+            types.insert(FunctionName::new_local(fun.name, arity));
+            for ty in params.iter() {
+                collect_type_references(ty, types, records);
+            }
+        }
+        Type::Remote { args, .. } => {
+            for ty in args.iter() {
+                collect_type_references(ty, types, records);
+            }
+        }
+        Type::List(_, ty) | Type::NonEmptyList(_, ty) => {
+            collect_type_references(ty, types, records)
+        }
+        Type::Record(_, name, fields) => {
+            records.insert(name.name);
+            for ty in fields.iter() {
+                collect_type_references(ty, types, records);
+            }
+        }
+        Type::Binary(_, lhs, rhs) | Type::KeyValuePair(_, lhs, rhs) => {
+            collect_type_references(lhs, types, records);
+            collect_type_references(rhs, types, records);
+        }
+        Type::AnyFun { ret, .. } => {
+            if let Some(ret) = ret.as_deref() {
+                collect_type_references(ret, types, records);
+            }
+        }
+        Type::Fun { params, ret, .. } => {
+            for ty in params.iter() {
+                collect_type_references(ty, types, records);
+            }
+            collect_type_references(ret, types, records);
+        }
+        Type::Field(_, _, ty) => collect_type_references(ty, types, records),
+    }
+}
+
+/// The variables in scope at some point in a function body
+type Env = BTreeMap<Symbol, VarBinding>;
+
+#[derive(Debug, Clone)]
+struct VarBinding {
+    /// Indices into `VariableLint::definitions` of the bindings this variable may refer to
+    ///
+    /// A variable bound in multiple clauses of a `case` has one definition per clause
+    defs: Vec<usize>,
+    state: VarState,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum VarState {
+    Safe,
+    /// The variable was bound in every clause of the given expression
+    Exported(SourceSpan, &'static str),
+    /// The variable was bound in only some paths through the given expression
+    Unsafe(SourceSpan, &'static str),
+}
+
+struct Definition {
+    name: Ident,
+    used: bool,
+}
+
+struct VariableLint<'a> {
+    diagnostics: &'a DiagnosticsHandler,
+    warn_shadow: bool,
+    warn_export: bool,
+    definitions: Vec<Definition>,
+    has_errors: bool,
+}
+impl<'a> VariableLint<'a> {
+    fn report_unused(&self) {
+        for def in self.definitions.iter().filter(|def| !def.used) {
+            let var = Var(def.name);
+            if !var.is_wanted() || var.is_compiler_generated() {
+                continue;
+            }
+            self.diagnostics
+                .diagnostic(Severity::Warning)
+                .with_message("unused variable")
+                .with_primary_label(
+                    def.name.span,
+                    format!("the variable '{}' is bound here, but never used", def.name),
+                )
+                .emit();
+        }
+    }
+
+    fn define(&mut self, env: &mut Env, name: Ident) {
+        let id = self.definitions.len();
+        self.definitions.push(Definition { name, used: false });
+        env.insert(
+            name.name,
+            VarBinding {
+                defs: vec![id],
+                state: VarState::Safe,
+            },
+        );
+    }
+
+    fn use_var(&mut self, env: &mut Env, var: Ident) {
+        let Some(binding) = env.get_mut(&var.name) else {
+            return;
+        };
+        for id in binding.defs.iter() {
+            self.definitions[*id].used = true;
+        }
+        match binding.state {
+            VarState::Safe => return,
+            VarState::Exported(span, kind) => {
+                if self.warn_export {
+                    self.diagnostics
+                        .diagnostic(Severity::Warning)
+                        .with_message("exported variable")
+                        .with_primary_label(
+                            var.span,
+                            format!("the variable '{}' is exported from '{}'", var, kind),
+                        )
+                        .with_secondary_label(span, "it was bound here")
+                        .emit();
+                }
+            }
+            VarState::Unsafe(span, kind) => {
+                self.has_errors = true;
+                self.diagnostics
+                    .diagnostic(Severity::Error)
+                    .with_message("unsafe variable")
+                    .with_primary_label(
+                        var.span,
+                        format!("the variable '{}' is unsafe in '{}'", var, kind),
+                    )
+                    .with_secondary_label(
+                        span,
+                        format!("this '{}' does not bind '{}' on every path", kind, var),
+                    )
+                    .emit();
+            }
+        }
+        // Only report the first offending use of a variable
+        binding.state = VarState::Safe;
+    }
+
+    /// Visits a clause of a function, `fun`, or branching expression in a new scope derived from `env`
+    ///
+    /// If `shadowing` is set, variables in the clause head are fresh, and shadow any variable of the
+    /// same name in `env`, otherwise they match against it.
+    fn clause(&mut self, env: &Env, clause: &Clause, shadowing: Option<&'static str>) -> Env {
+        let mut env = env.clone();
+        let mut bound = BTreeSet::new();
+        for pattern in clause.patterns.iter() {
+            self.pattern(&mut env, &mut bound, pattern, shadowing);
+        }
+        for guard in clause.guards.iter() {
+            for condition in guard.conditions.iter() {
+                self.expr(&mut env, condition);
+            }
+        }
+        self.exprs(&mut env, clause.body.as_slice());
+        env
+    }
+
+    fn pattern(
+        &mut self,
+        env: &mut Env,
+        bound: &mut BTreeSet<Symbol>,
+        pattern: &Expr,
+        shadowing: Option<&'static str>,
+    ) {
+        match pattern {
+            Expr::Var(var) if var.is_wildcard() => (),
+            Expr::Var(Var(id)) => {
+                if bound.contains(&id.name) {
+                    return self.use_var(env, *id);
+                }
+                let prev = env.get(&id.name).map(|binding| binding.defs[0]);
+                match (prev, shadowing) {
+                    (None, _) => (),
+                    (Some(prev), Some(kind)) => {
+                        if self.warn_shadow {
+                            let prev = self.definitions[prev].name.span;
+                            self.diagnostics
+                                .diagnostic(Severity::Warning)
+                                .with_message("shadowed variable")
+                                .with_primary_label(
+                                    id.span,
+                                    format!("the variable '{}' is shadowed in '{}'", id, kind),
+                                )
+                                .with_secondary_label(prev, "the shadowed variable is bound here")
+                                .emit();
+                        }
+                    }
+                    (Some(_), None) => return self.use_var(env, *id),
+                }
+                bound.insert(id.name);
+                self.define(env, *id);
+            }
+            Expr::Cons(Cons { head, tail, .. }) => {
+                self.pattern(env, bound, head, shadowing);
+                self.pattern(env, bound, tail, shadowing);
+            }
+            Expr::Tuple(Tuple { elements, .. }) => {
+                for element in elements.iter() {
+                    self.pattern(env, bound, element, shadowing);
+                }
+            }
+            Expr::Map(Map { fields, .. }) => {
+                for field in fields.iter() {
+                    self.expr(env, field.key_ref());
+                    self.pattern(env, bound, field.value_ref(), shadowing);
+                }
+            }
+            Expr::Binary(Binary { elements, .. }) => {
+                for element in elements.iter() {
+                    self.pattern(env, bound, &element.bit_expr, shadowing);
+                    if let Some(size) = element.bit_size.as_ref() {
+                        self.expr(env, size);
+                    }
+                }
+            }
+            Expr::Record(Record { fields, .. }) => {
+                for value in fields.iter().filter_map(|f| f.value.as_ref()) {
+                    self.pattern(env, bound, value, shadowing);
+                }
+            }
+            Expr::Match(Match { pattern, expr, .. }) => {
+                self.pattern(env, bound, pattern, shadowing);
+                self.pattern(env, bound, expr, shadowing);
+            }
+            Expr::BinaryExpr(BinaryExpr { lhs, rhs, .. }) => {
+                self.pattern(env, bound, lhs, shadowing);
+                self.pattern(env, bound, rhs, shadowing);
+            }
+            Expr::UnaryExpr(UnaryExpr { operand, .. }) => {
+                self.pattern(env, bound, operand, shadowing);
+            }
+            other => self.expr(env, other),
+        }
+    }
+
+    fn exprs(&mut self, env: &mut Env, exprs: &[Expr]) {
+        for expr in exprs.iter() {
+            self.expr(env, expr);
+        }
+    }
+
+    fn expr(&mut self, env: &mut Env, expr: &Expr) {
+        match expr {
+            Expr::Var(var) if var.is_wildcard() => (),
+            Expr::Var(Var(id)) => self.use_var(env, *id),
+            Expr::Literal(_) | Expr::DelayedSubstitution(_) | Expr::RecordIndex(_) => (),
+            Expr::FunctionVar(FunctionVar::Unresolved(name)) => {
+                if let Some(Name::Var(id)) = name.module {
+                    self.use_var(env, id);
+                }
+                if let Name::Var(id) = name.function {
+                    self.use_var(env, id);
+                }
+                if let Arity::Var(id) = name.arity {
+                    self.use_var(env, id);
+                }
+            }
+            Expr::FunctionVar(_) => (),
+            Expr::Cons(Cons { head, tail, .. }) => {
+                self.expr(env, head);
+                self.expr(env, tail);
+            }
+            Expr::Tuple(Tuple { elements, .. }) => self.exprs(env, elements.as_slice()),
+            Expr::Map(Map { fields, .. }) => self.map_fields(env, fields.as_slice()),
+            Expr::MapUpdate(MapUpdate { map, updates, .. }) => {
+                self.expr(env, map);
+                self.map_fields(env, updates.as_slice());
+            }
+            Expr::Binary(Binary { elements, .. }) => {
+                for element in elements.iter() {
+                    self.expr(env, &element.bit_expr);
+                    if let Some(size) = element.bit_size.as_ref() {
+                        self.expr(env, size);
+                    }
+                }
+            }
+            Expr::Record(Record {
+                fields, default, ..
+            }) => {
+                for value in fields.iter().filter_map(|f| f.value.as_ref()) {
+                    self.expr(env, value);
+                }
+                if let Some(default) = default.as_deref() {
+                    self.expr(env, default);
+                }
+            }
+            Expr::RecordAccess(RecordAccess { record, .. }) => self.expr(env, record),
+            Expr::RecordUpdate(RecordUpdate {
+                record, updates, ..
+            }) => {
+                self.expr(env, record);
+                for value in updates.iter().filter_map(|f| f.value.as_ref()) {
+                    self.expr(env, value);
+                }
+            }
+            Expr::ListComprehension(ListComprehension {
+                body, qualifiers, ..
+            })
+            | Expr::BinaryComprehension(BinaryComprehension {
+                body, qualifiers, ..
+            }) => {
+                let mut env = self.qualifiers(env, qualifiers.as_slice());
+                self.expr(&mut env, body);
+            }
+            Expr::MapComprehension(MapComprehension {
+                key,
+                value,
+                qualifiers,
+                ..
+            }) => {
+                let mut env = self.qualifiers(env, qualifiers.as_slice());
+                self.expr(&mut env, key);
+                self.expr(&mut env, value);
+            }
+            Expr::Generator(Generator { pattern, expr, .. }) => {
+                self.expr(env, expr);
+                self.pattern(env, &mut BTreeSet::new(), pattern, Some("generate"));
+            }
+            Expr::Begin(Begin { body, .. }) => self.exprs(env, body.as_slice()),
+            Expr::Apply(Apply { callee, args, .. }) => {
+                self.expr(env, callee);
+                self.exprs(env, args.as_slice());
+            }
+            Expr::Remote(Remote {
+                module, function, ..
+            }) => {
+                self.expr(env, module);
+                self.expr(env, function);
+            }
+            Expr::BinaryExpr(BinaryExpr {
+                span,
+                lhs,
+                op: op @ (BinaryOp::AndAlso | BinaryOp::OrElse),
+                rhs,
+            }) => {
+                self.expr(env, lhs);
+                let kind = if *op == BinaryOp::AndAlso {
+                    "andalso"
+                } else {
+                    "orelse"
+                };
+                let mut rhs_env = env.clone();
+                self.expr(&mut rhs_env, rhs);
+                self.make_unsafe(env, vec![rhs_env], *span, kind);
+            }
+            Expr::BinaryExpr(BinaryExpr { lhs, rhs, .. }) => {
+                self.expr(env, lhs);
+                self.expr(env, rhs);
+            }
+            Expr::UnaryExpr(UnaryExpr { operand, .. }) => self.expr(env, operand),
+            Expr::Match(Match { pattern, expr, .. })
+            | Expr::MaybeMatch(MaybeMatch { pattern, expr, .. }) => {
+                self.expr(env, expr);
+                self.pattern(env, &mut BTreeSet::new(), pattern, None);
+            }
+            Expr::If(If { span, clauses }) => {
+                let branches = clauses
+                    .iter()
+                    .map(|clause| self.clause(env, clause, None))
+                    .collect();
+                self.merge(env, branches, *span, "if");
+            }
+            Expr::Case(Case {
+                span,
+                expr,
+                clauses,
+            }) => {
+                self.expr(env, expr);
+                let branches = clauses
+                    .iter()
+                    .map(|clause| self.clause(env, clause, None))
+                    .collect();
+                self.merge(env, branches, *span, "case");
+            }
+            Expr::Receive(Receive {
+                span,
+                clauses,
+                after,
+            }) => {
+                let mut branches = clauses
+                    .iter()
+                    .flatten()
+                    .map(|clause| self.clause(env, clause, None))
+                    .collect::<Vec<_>>();
+                if let Some(after) = after.as_ref() {
+                    self.expr(env, after.timeout.as_ref());
+                    let mut after_env = env.clone();
+                    self.exprs(&mut after_env, after.body.as_slice());
+                    branches.push(after_env);
+                }
+                self.merge(env, branches, *span, "receive");
+            }
+            Expr::Catch(Catch { span, expr }) => {
+                let mut inner = env.clone();
+                self.expr(&mut inner, expr);
+                self.make_unsafe(env, vec![inner], *span, "catch");
+            }
+            Expr::Try(Try {
+                span,
+                exprs,
+                clauses,
+                catch_clauses,
+                after,
+            }) => {
+                let mut body_env = env.clone();
+                self.exprs(&mut body_env, exprs.as_slice());
+                let mut branches = clauses
+                    .iter()
+                    .flatten()
+                    .map(|clause| self.clause(&body_env, clause, None))
+                    .collect::<Vec<_>>();
+                // Handlers may run before the body has bound anything
+                let mut handler_env = env.clone();
+                self.make_unsafe(&mut handler_env, vec![body_env.clone()], *span, "try");
+                for clause in catch_clauses.iter().flatten() {
+                    branches.push(self.clause(&handler_env, clause, None));
+                }
+                if let Some(after) = after.as_ref() {
+                    let mut after_env = handler_env.clone();
+                    self.exprs(&mut after_env, after.as_slice());
+                    branches.push(after_env);
+                }
+                branches.push(body_env);
+                self.make_unsafe(env, branches, *span, "try");
+            }
+            Expr::Maybe(Maybe {
+                span,
+                body,
+                else_clauses,
+            }) => {
+                let mut body_env = env.clone();
+                self.exprs(&mut body_env, body.as_slice());
+                let mut else_env = env.clone();
+                self.make_unsafe(&mut else_env, vec![body_env.clone()], *span, "maybe");
+                let mut branches = else_clauses
+                    .iter()
+                    .flatten()
+                    .map(|clause| self.clause(&else_env, clause, None))
+                    .collect::<Vec<_>>();
+                branches.push(body_env);
+                self.make_unsafe(env, branches, *span, "maybe");
+            }
+            Expr::Fun(Fun::Anonymous(fun)) => {
+                for clause in fun.clauses.iter() {
+                    self.clause(env, clause, Some("fun"));
+                }
+            }
+            Expr::Fun(Fun::Recursive(fun)) => {
+                let mut fun_env = env.clone();
+                self.pattern(
+                    &mut fun_env,
+                    &mut BTreeSet::new(),
+                    &Expr::Var(Var(fun.self_name)),
+                    Some("named fun"),
+                );
+                // The name of a named fun is not expected to be used
+                if let Some(binding) = fun_env.get(&fun.self_name.name) {
+                    self.definitions[binding.defs[0]].used = true;
+                }
+                for (_, clause) in fun.clauses.iter() {
+                    self.clause(&fun_env, clause, Some("fun"));
+                }
+            }
+            Expr::Protect(Protect { body, .. }) => self.expr(env, body),
+        }
+    }
+
+    fn map_fields(&mut self, env: &mut Env, fields: &[MapField]) {
+        for field in fields.iter() {
+            self.expr(env, field.key_ref());
+            self.expr(env, field.value_ref());
+        }
+    }
+
+    /// Visits the qualifiers of a comprehension, returning the scope in which its body is evaluated
+    fn qualifiers(&mut self, env: &Env, qualifiers: &[Expr]) -> Env {
+        let mut env = env.clone();
+        self.exprs(&mut env, qualifiers);
+        env
+    }
+
+    /// Brings variables newly bound in `branches` into `env`, as exported if they were bound in
+    /// every branch, or as unsafe otherwise
+    fn merge(&mut self, env: &mut Env, branches: Vec<Env>, span: SourceSpan, kind: &'static str) {
+        let num_branches = branches.len();
+        for (name, (count, defs)) in new_bindings(env, branches) {
+            let state = if count == num_branches {
+                VarState::Exported(span, kind)
+            } else {
+                VarState::Unsafe(span, kind)
+            };
+            env.insert(name, VarBinding { defs, state });
+        }
+    }
+
+    /// Brings variables newly bound in `branches` into `env` as unsafe
+    fn make_unsafe(
+        &mut self,
+        env: &mut Env,
+        branches: Vec<Env>,
+        span: SourceSpan,
+        kind: &'static str,
+    ) {
+        for (name, (_, defs)) in new_bindings(env, branches) {
+            let state = VarState::Unsafe(span, kind);
+            env.insert(name, VarBinding { defs, state });
+        }
+    }
+}
+
+/// Returns the variables bound in `branches` which are not bound in `env`, along with the number
+/// of branches binding each of them, and all of their definitions
+fn new_bindings(env: &Env, branches: Vec<Env>) -> BTreeMap<Symbol, (usize, Vec<usize>)> {
+    let mut new = BTreeMap::<Symbol, (usize, Vec<usize>)>::new();
+    for branch in branches.into_iter() {
+        for (name, binding) in branch.into_iter() {
+            if env.contains_key(&name) {
+                continue;
+            }
+            let (count, defs) = new.entry(name).or_default();
+            *count += 1;
+            defs.extend(binding.defs);
+        }
+    }
+    new
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use firefly_pass::Pass;
+    use firefly_util::diagnostics::*;
+
+    use super::*;
+    use crate::parser::{ParseConfig, Parser};
+
+    /// Runs the lints over `input`, returning the rendered diagnostics
+    fn lint(input: &str) -> String {
+        let codemap = Arc::new(CodeMap::new());
+        let emitter = Arc::new(CaptureEmitter::default());
+        let diagnostics = DiagnosticsHandler::new(
+            DiagnosticsConfig::default(),
+            codemap.clone(),
+            emitter.clone(),
+        );
+        let parser = Parser::new(ParseConfig::default(), codemap);
+        let mut module: Module = parser
+            .parse_string(&diagnostics, input)
+            .expect("parsing failed");
+        let mut passes = LintUnusedFunctions::new(&diagnostics)
+            .chain(LintUnusedImports::new(&diagnostics))
+            .chain(LintUnusedRecords::new(&diagnostics))
+            .chain(LintUnusedTypes::new(&diagnostics))
+            .chain(LintVariables::new(&diagnostics));
+        let _ = passes.run(&mut module);
+        emitter.captured()
+    }
+
+    #[test]
+    fn lint_unused_definitions() {
+        let output = lint(
+            "-module(foo).
+-export([main/0]).
+-import(lists, [map/2, reverse/1]).
+-record(used, {a}).
+-record(unused, {a}).
+-type used() :: #used{}.
+-type unused() :: integer().
+
+-spec main() -> used().
+main() -> helper(reverse([])).
+
+helper(X) -> #used{a = X}.
+dead() -> dead().
+",
+        );
+        assert!(output.contains("the function dead/0 is never called"));
+        assert!(!output.contains("helper/1 is never called"));
+        assert!(output.contains("lists:map/2 is imported here, but never called"));
+        assert!(!output.contains("lists:reverse/1 is imported"));
+        assert!(output.contains("the record unused is never used"));
+        assert!(!output.contains("the record used is never used"));
+        assert!(output.contains("the type unused/0 is never used"));
+        assert!(!output.contains("the type used/0 is never used"));
+    }
+
+    #[test]
+    fn lint_variables() {
+        let output = lint(
+            "-module(foo).
+-export([unused/1, shadowed/1, exported/1, unsafe/1]).
+
+unused(X) -> Y = X, _Z = X, ok.
+
+shadowed(X) -> fun(X) -> X end.
+
+exported(X) ->
+    case X of
+        a -> Y = 1;
+        _ -> Y = 2
+    end,
+    Y.
+
+unsafe(X) ->
+    case X of
+        a -> Y = 1;
+        _ -> ok
+    end,
+    Y.
+",
+        );
+        assert!(output.contains("the variable 'Y' is bound here, but never used"));
+        assert!(!output.contains("the variable '_Z' is bound here"));
+        assert!(output.contains("the variable 'X' is shadowed in 'fun'"));
+        assert!(output.contains("the variable 'Y' is exported from 'case'"));
+        assert!(output.contains("the variable 'Y' is unsafe in 'case'"));
+    }
+
+    #[test]
+    fn lint_honors_nowarn_options() {
+        let output = lint(
+            "-module(foo).
+-compile([nowarn_unused_function, nowarn_unused_vars]).
+-export([main/1]).
+
+main(X) -> Y = X, ok.
+dead() -> ok.
+",
+        );
+        assert!(!output.contains("never called"));
+        assert!(!output.contains("never used"));
+    }
+}
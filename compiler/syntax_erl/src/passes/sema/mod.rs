@@ -1,8 +1,11 @@
 mod attributes;
+mod docs;
 mod functions;
 mod inject;
+mod lints;
 mod records;
 mod verify;
+mod xref;
 
 use firefly_intern::Ident;
 use firefly_pass::Pass;
@@ -12,8 +15,10 @@ use firefly_util::diagnostics::*;
 use crate::ast;
 
 pub use self::attributes::analyze_attribute;
+pub use self::docs::{analyze_doc, attach_doc, unused_doc};
 pub use self::functions::analyze_function;
 pub use self::records::analyze_record;
+pub use self::xref::{ModuleSummary, Program, Xref};
 
 /// This pass is responsible for taking a set of top-level forms and
 /// analyzing them in the context of a new module to produce a fully
@@ -24,11 +29,15 @@ pub use self::records::analyze_record;
 /// * If configured to do so, warns if functions are missing type specs
 /// * Warns about type specs for undefined functions
 /// * Warns about redefined attributes
+/// * Warns about unused functions, imports, records, types and variables
+/// * Warns about shadowed variables, and variables exported from `case`, `if` and `receive`
+/// * Warns about missing or misspelled behaviour callbacks
 /// * Errors on invalid nif declarations
 /// * Errors on invalid syntax in built-in attributes (e.g. -import(..))
 /// * Errors on mismatched function clauses (name/arity)
 /// * Errors on unterminated function clauses
 /// * Errors on redefined functions
+/// * Errors on use of unsafe variables, i.e. those bound in only some branches of an expression
 ///
 /// And a few other similar lints
 pub struct SemanticAnalysis<'p> {
@@ -45,8 +54,16 @@ impl<'p> Pass for SemanticAnalysis<'p> {
     type Output<'a> = ast::Module;
 
     fn run<'a>(&mut self, mut module: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
-        let mut passes = inject::AddAutoImports
+        // The lints run first, as later passes add auto-imports and pseudo-locals which are
+        // indistinguishable from user definitions, and would otherwise be reported as unused
+        let mut passes = lints::LintUnusedFunctions::new(self.diagnostics)
+            .chain(lints::LintUnusedImports::new(self.diagnostics))
+            .chain(lints::LintUnusedRecords::new(self.diagnostics))
+            .chain(lints::LintUnusedTypes::new(self.diagnostics))
+            .chain(lints::LintVariables::new(self.diagnostics))
+            .chain(inject::AddAutoImports)
             .chain(verify::VerifyExports::new(self.diagnostics))
+            .chain(verify::VerifyBehaviours::new(self.diagnostics, self.app))
             .chain(verify::VerifyOnLoadFunctions::new(self.diagnostics))
             .chain(verify::VerifyTypeSpecs::new(self.diagnostics))
             .chain(verify::VerifyNifs::new(self.diagnostics))
@@ -81,6 +81,169 @@ impl<'p> Pass for VerifyExports<'p> {
     }
 }
 
+/// Verifies that modules export the callbacks required by the behaviours they declare
+///
+/// Callbacks are resolved via `ApplicationMetadata::get_behaviour_callbacks`, so a behaviour may be
+/// defined by another module in the same compilation, be one of the standard OTP behaviours, or have
+/// been loaded from a BEAM file on the code path. As in erlc, missing callbacks only raise warnings.
+///
+/// Additionally, we warn about exported functions which look like a misspelling of an unimplemented
+/// callback, as a misspelled optional callback (e.g. `handle_info/2`) would otherwise go unnoticed.
+pub struct VerifyBehaviours<'p> {
+    diagnostics: &'p DiagnosticsHandler,
+    app: &'p ApplicationMetadata,
+}
+impl<'p> VerifyBehaviours<'p> {
+    pub fn new(diagnostics: &'p DiagnosticsHandler, app: &'p ApplicationMetadata) -> Self {
+        Self { diagnostics, app }
+    }
+}
+impl<'p> Pass for VerifyBehaviours<'p> {
+    type Input<'a> = &'a mut Module;
+    type Output<'a> = &'a mut Module;
+
+    fn run<'a>(&mut self, module: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        let should_warn = module
+            .compile
+            .as_ref()
+            .map(|c| !c.no_warn && c.warn_behaviours)
+            .unwrap_or(true);
+        if !should_warn || module.behaviours.is_empty() {
+            return Ok(module);
+        }
+
+        let export_all = module
+            .compile
+            .as_ref()
+            .map(|c| c.export_all)
+            .unwrap_or(false);
+        let exported = module
+            .functions
+            .iter()
+            .filter(|(name, _)| {
+                export_all
+                    || module
+                        .exports
+                        .contains(&Span::new(SourceSpan::UNKNOWN, **name))
+            })
+            .map(|(name, function)| (*name, function))
+            .collect::<BTreeMap<_, _>>();
+
+        // Collect the callbacks of all behaviours, so that we don't suggest a function which
+        // implements a callback of one behaviour as a misspelling of a callback of another
+        let mut behaviours = Vec::with_capacity(module.behaviours.len());
+        for behaviour in module.behaviours.iter() {
+            match self.app.get_behaviour_callbacks(behaviour.name) {
+                None => {
+                    self.diagnostics
+                        .diagnostic(Severity::Warning)
+                        .with_message("undefined behaviour")
+                        .with_primary_label(
+                            behaviour.span,
+                            "the callbacks of this behaviour could not be found",
+                        )
+                        .emit();
+                }
+                Some(callbacks) => behaviours.push((behaviour, callbacks)),
+            }
+        }
+        let implemented = behaviours
+            .iter()
+            .flat_map(|(_, callbacks)| callbacks.keys())
+            .filter(|cb| exported.contains_key(*cb))
+            .copied()
+            .collect::<BTreeSet<FunctionName>>();
+
+        for (behaviour, callbacks) in behaviours.iter() {
+            for (callback, optional) in callbacks.iter() {
+                if *optional || exported.contains_key(callback) {
+                    continue;
+                }
+
+                let name = callback.function.as_str().get();
+                let most_similar = exported
+                    .iter()
+                    .filter(|(f, _)| f.arity == callback.arity && !implemented.contains(*f))
+                    .map(|(f, function)| {
+                        let score = strsim::jaro_winkler(name, f.function.as_str().get());
+                        (score, f, function)
+                    })
+                    .max_by(|(x_score, _, _), (ref y_score, _, _)| x_score.total_cmp(y_score))
+                    .and_then(|(score, f, function)| {
+                        if score < 0.85 {
+                            None
+                        } else {
+                            Some((f, function))
+                        }
+                    });
+
+                let message = format!(
+                    "the callback {} required by {} is not exported by this module",
+                    callback, behaviour
+                );
+                let mut diagnostic = self
+                    .diagnostics
+                    .diagnostic(Severity::Warning)
+                    .with_message("undefined callback function")
+                    .with_primary_label(behaviour.span, message);
+                if let Some((f, function)) = most_similar {
+                    diagnostic = diagnostic.with_secondary_label(
+                        function.name.span,
+                        format!("maybe you meant to name {} {} instead?", f, callback),
+                    );
+                }
+                diagnostic.emit();
+            }
+        }
+
+        // Missing optional callbacks are fine, but an exported function which is most similar to
+        // one of them is likely a misspelling. We use a stricter threshold here than above, as
+        // callbacks often share a prefix with other functions (e.g. `handle_`)
+        for (f, function) in exported.iter() {
+            if implemented.contains(f) {
+                continue;
+            }
+            let name = f.function.as_str().get();
+            let most_similar = behaviours
+                .iter()
+                .flat_map(|(behaviour, callbacks)| {
+                    callbacks
+                        .iter()
+                        .map(move |(callback, optional)| (*behaviour, callback, *optional))
+                })
+                .filter(|(_, callback, _)| {
+                    callback.arity == f.arity && !exported.contains_key(*callback)
+                })
+                .map(|(behaviour, callback, optional)| {
+                    let score = strsim::jaro_winkler(name, callback.function.as_str().get());
+                    (score, behaviour, callback, optional)
+                })
+                .max_by(|(x_score, _, _, _), (ref y_score, _, _, _)| x_score.total_cmp(y_score));
+
+            if let Some((score, behaviour, callback, true)) = most_similar {
+                if score < 0.9 {
+                    continue;
+                }
+                let message = format!(
+                    "{} is similar to the optional callback {}, which is not implemented",
+                    f, callback
+                );
+                self.diagnostics
+                    .diagnostic(Severity::Warning)
+                    .with_message("possibly misspelled callback function")
+                    .with_primary_label(function.name.span, message)
+                    .with_secondary_label(
+                        behaviour.span,
+                        format!("{} is declared by this behaviour", callback),
+                    )
+                    .emit();
+            }
+        }
+
+        Ok(module)
+    }
+}
+
 /// Verifies that the on_load function exists, if -on_load is present
 pub struct VerifyOnLoadFunctions<'p> {
     diagnostics: &'p DiagnosticsHandler,
@@ -192,10 +355,8 @@ impl<'p> Pass for VerifyTypeSpecs<'p> {
 ///
 /// Additionally, checks if the callee is known to be deprecated and raises appropriate diagnostics.
 ///
-/// NOTE: We could extend this analysis to cover calls to other modules, since at the point this analysis is run, we have
-/// access to the entire set of modules that was provided to the compiler, however this does not account for cases in which
-/// we're only compiling a library and thus only a subset of the modules is known - we could make such analysis optional and
-/// only perform it when the full set of modules is known.
+/// NOTE: Calls to other modules are not verified here, as when we're only compiling a library, only a subset of the modules
+/// is known. See `Xref` for the analysis which verifies them when the full set of modules is known.
 pub struct VerifyCalls<'p> {
     diagnostics: &'p DiagnosticsHandler,
     app: &'p ApplicationMetadata,
@@ -638,3 +799,54 @@ impl<'a> VisitMut<()> for VerifyCallsVisitor<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use firefly_pass::Pass;
+
+    use super::*;
+    use crate::parser::{ParseConfig, Parser};
+
+    /// Runs behaviour verification over `input`, returning the rendered diagnostics
+    fn verify_behaviours(input: &str) -> String {
+        let codemap = Arc::new(CodeMap::new());
+        let emitter = Arc::new(CaptureEmitter::default());
+        let diagnostics = DiagnosticsHandler::new(
+            DiagnosticsConfig::default(),
+            codemap.clone(),
+            emitter.clone(),
+        );
+        let parser = Parser::new(ParseConfig::default(), codemap);
+        let mut module: Module = parser
+            .parse_string(&diagnostics, input)
+            .expect("parsing failed");
+        let app = ApplicationMetadata {
+            name: Symbol::intern("test"),
+            modules: BTreeMap::new(),
+            behaviours: BTreeMap::new(),
+        };
+        let _ = VerifyBehaviours::new(&diagnostics, &app).run(&mut module);
+        emitter.captured()
+    }
+
+    #[test]
+    fn verify_gen_server_callbacks() {
+        let output = verify_behaviours(
+            "-module(foo).
+-behaviour(gen_server).
+-behaviour(undefined_behaviour).
+-export([init/1, handle_cast/2, handle_infos/2]).
+
+init(_) -> {ok, nil}.
+handle_cast(_, State) -> {noreply, State}.
+handle_infos(_, State) -> {noreply, State}.
+",
+        );
+        assert!(output.contains("the callbacks of this behaviour could not be found"));
+        assert!(output.contains("the callback handle_call/3 required by gen_server"));
+        assert!(!output.contains("the callback init/1 required by gen_server"));
+        assert!(output.contains("handle_infos/2 is similar to the optional callback handle_info/2"));
+    }
+}
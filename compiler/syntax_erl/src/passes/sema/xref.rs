@@ -0,0 +1,460 @@
+use core::ops::ControlFlow;
+use std::collections::{BTreeMap, BTreeSet};
+
+use firefly_intern::{symbols, Symbol};
+use firefly_pass::Pass;
+use firefly_syntax_base::{bifs, ApplicationMetadata, Deprecation, FunctionName};
+use firefly_util::diagnostics::*;
+
+use crate::ast::*;
+use crate::visit::{self, VisitMut};
+
+/// The modules of a whole program, grouped by the application they belong to
+pub struct Program<'a> {
+    /// The application being built, the exports of which are expected to be used
+    pub app: Symbol,
+    pub apps: Vec<(&'a ApplicationMetadata, Vec<ModuleSummary>)>,
+}
+
+/// The parts of a module which are relevant to `Xref`
+///
+/// This is all that is needed of a module to verify the calls between it and the rest of a program,
+/// so it can be kept in place of the module itself, e.g. when the module is loaded from a cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleSummary {
+    pub name: Symbol,
+    /// True if the module is compiled with `nowarn`
+    pub no_warn: bool,
+    /// True if the module is compiled with `export_all`
+    pub export_all: bool,
+    /// The functions defined in the module, with the span of their name
+    pub functions: Vec<Span<FunctionName>>,
+    /// The functions exported by the module, as local names
+    pub exports: Vec<Span<FunctionName>>,
+    /// The behaviours implemented by the module
+    pub behaviours: Vec<Symbol>,
+    /// All statically known calls to remote functions in the module, see `remote_calls`
+    pub calls: Vec<Span<FunctionName>>,
+}
+impl ModuleSummary {
+    pub fn new(module: &mut Module) -> Self {
+        let compile = module.compile.as_ref();
+        let mut exports = module
+            .exports
+            .iter()
+            .map(|export| Span::new(export.span(), export.item.to_local()))
+            .collect::<Vec<_>>();
+        exports.sort();
+        let mut behaviours = module.behaviours.iter().map(|b| b.name).collect::<Vec<_>>();
+        behaviours.sort();
+        Self {
+            name: module.name(),
+            no_warn: compile.map(|c| c.no_warn).unwrap_or(false),
+            export_all: compile.map(|c| c.export_all).unwrap_or(false),
+            functions: module
+                .functions
+                .iter()
+                .map(|(name, function)| Span::new(function.name.span, *name))
+                .collect(),
+            exports,
+            behaviours,
+            calls: remote_calls(module),
+        }
+    }
+}
+
+/// Verifies remote calls across all of the modules in a program, in the style of `xref`
+///
+/// Unlike `VerifyCalls`, this requires the full set of modules in the program to be known, so it
+/// is run separately from `SemanticAnalysis`, and only when requested or when building an executable.
+///
+/// The following are reported as warnings:
+///
+/// * Calls to functions which are neither defined in the program, nor implemented by the runtime
+/// * Calls to functions which are defined, but not exported
+/// * Calls to deprecated functions of other applications, as `VerifyCalls` handles those within
+/// the same application
+/// * Exports of the application being built which are never called, excluding behaviour callbacks
+pub struct Xref<'p> {
+    diagnostics: &'p DiagnosticsHandler,
+}
+impl<'p> Xref<'p> {
+    pub fn new(diagnostics: &'p DiagnosticsHandler) -> Self {
+        Self { diagnostics }
+    }
+}
+impl<'p> Pass for Xref<'p> {
+    type Input<'a> = Program<'a>;
+    type Output<'a> = Program<'a>;
+
+    fn run<'a>(&mut self, program: Self::Input<'a>) -> anyhow::Result<Self::Output<'a>> {
+        // Gather the definitions of every module in the program up front
+        let mut definitions = BTreeMap::new();
+        for (app, modules) in program.apps.iter() {
+            for module in modules.iter() {
+                definitions.insert(module.name, Definitions::new(*app, module));
+            }
+        }
+
+        let mut used = BTreeSet::new();
+        for (app, modules) in program.apps.iter() {
+            for module in modules.iter() {
+                for call in module.calls.iter() {
+                    used.insert(call.item);
+                    if !module.no_warn {
+                        self.verify_call(app.name, &definitions, call);
+                    }
+                }
+            }
+        }
+
+        // The entry point of an executable is called by the runtime
+        used.insert(FunctionName::new(
+            Symbol::intern("init"),
+            Symbol::intern("boot"),
+            1,
+        ));
+
+        for (app, modules) in program.apps.iter() {
+            if app.name != program.app {
+                continue;
+            }
+            for module in modules.iter() {
+                self.verify_exports_used(app, module, &used);
+            }
+        }
+
+        Ok(program)
+    }
+}
+impl<'p> Xref<'p> {
+    fn verify_call(
+        &self,
+        app: Symbol,
+        definitions: &BTreeMap<Symbol, Definitions<'_>>,
+        call: &Span<FunctionName>,
+    ) {
+        let callee = call.item;
+        let local_callee = callee.to_local();
+        if is_module_info(&local_callee) {
+            return;
+        }
+
+        let Some(definition) = definitions.get(&callee.module.unwrap()) else {
+            if !bifs::is_runtime_bif(&callee) && bifs::get(&callee).is_none() {
+                let message = format!(
+                    "{} is not defined in this program, nor implemented by the runtime",
+                    &callee
+                );
+                self.diagnostics
+                    .diagnostic(Severity::Warning)
+                    .with_message("call to undefined function")
+                    .with_primary_label(call.span(), message)
+                    .emit();
+            }
+            return;
+        };
+
+        match definition.functions.get(&local_callee) {
+            None => {
+                let message = format!("{} is not defined", &callee);
+                self.diagnostics
+                    .diagnostic(Severity::Warning)
+                    .with_message("call to undefined function")
+                    .with_primary_label(call.span(), message)
+                    .emit();
+            }
+            Some(span) if !definition.exports.contains(&local_callee) => {
+                let message = format!("{} is defined, but not exported", &callee);
+                self.diagnostics
+                    .diagnostic(Severity::Warning)
+                    .with_message("call to unexported function")
+                    .with_primary_label(call.span(), message)
+                    .with_secondary_label(*span, "defined here")
+                    .emit();
+            }
+            Some(_) if definition.app.name != app => {
+                match definition.app.get_function_deprecation(&callee) {
+                    None => (),
+                    Some(Deprecation::Module { span: dspan, flag }) => {
+                        let note = format!("this module will be deprecated {}", &flag);
+                        self.diagnostics
+                            .diagnostic(Severity::Warning)
+                            .with_message("use of deprecated module")
+                            .with_primary_label(call.span(), note)
+                            .with_secondary_label(dspan, "deprecated here")
+                            .emit();
+                    }
+                    Some(Deprecation::Function {
+                        span: dspan, flag, ..
+                    }) => {
+                        let note = format!("this function will be deprecated {}", &flag);
+                        self.diagnostics
+                            .diagnostic(Severity::Warning)
+                            .with_message("use of deprecated function")
+                            .with_primary_label(call.span(), note)
+                            .with_secondary_label(dspan, "deprecated here")
+                            .emit();
+                    }
+                    // These deprecation types have all been converted to Deprecation::Function
+                    Some(Deprecation::FunctionAnyArity { .. }) => unreachable!(),
+                }
+            }
+            Some(_) => (),
+        }
+    }
+
+    fn verify_exports_used(
+        &self,
+        app: &ApplicationMetadata,
+        module: &ModuleSummary,
+        used: &BTreeSet<FunctionName>,
+    ) {
+        if module.no_warn || module.export_all {
+            return;
+        }
+
+        // Behaviour callbacks are called by the behaviour module, often dynamically
+        let callbacks = module
+            .behaviours
+            .iter()
+            .filter_map(|b| app.get_behaviour_callbacks(*b))
+            .flat_map(|callbacks| callbacks.keys())
+            .copied()
+            .collect::<BTreeSet<_>>();
+
+        for export in module.exports.iter() {
+            let local_name = export.item.to_local();
+            if is_module_info(&local_name) || callbacks.contains(&local_name) {
+                continue;
+            }
+            if used.contains(&local_name.resolve(module.name)) {
+                continue;
+            }
+            let message = format!(
+                "{} is exported, but never called from another module",
+                &local_name
+            );
+            self.diagnostics
+                .diagnostic(Severity::Warning)
+                .with_message("unused export")
+                .with_primary_label(export.span(), message)
+                .emit();
+        }
+    }
+}
+
+/// The functions defined and exported by a module, and the application it belongs to
+struct Definitions<'a> {
+    app: &'a ApplicationMetadata,
+    functions: BTreeMap<FunctionName, SourceSpan>,
+    exports: BTreeSet<FunctionName>,
+}
+impl<'a> Definitions<'a> {
+    fn new(app: &'a ApplicationMetadata, module: &ModuleSummary) -> Self {
+        let functions = module
+            .functions
+            .iter()
+            .map(|name| (name.item, name.span()))
+            .collect::<BTreeMap<_, _>>();
+        let exports = if module.export_all {
+            functions.keys().copied().collect()
+        } else {
+            module.exports.iter().map(|name| name.item).collect()
+        };
+        Self {
+            app,
+            functions,
+            exports,
+        }
+    }
+}
+
+fn is_module_info(name: &FunctionName) -> bool {
+    name.function == symbols::ModuleInfo && name.arity <= 1
+}
+
+/// Returns all statically known calls to remote functions in `module`, including calls to imports
+fn remote_calls(module: &mut Module) -> Vec<Span<FunctionName>> {
+    let imports = module
+        .imports
+        .iter()
+        .map(|(name, sig)| (*name, sig.mfa()))
+        .collect::<BTreeMap<FunctionName, FunctionName>>();
+
+    let mut visitor = RemoteCallsVisitor {
+        imports: &imports,
+        calls: vec![],
+    };
+    for (_, function) in module.functions.iter_mut() {
+        visitor.visit_mut_function(function);
+    }
+    visitor.calls
+}
+
+struct RemoteCallsVisitor<'a> {
+    imports: &'a BTreeMap<FunctionName, FunctionName>,
+    calls: Vec<Span<FunctionName>>,
+}
+impl<'a> VisitMut<()> for RemoteCallsVisitor<'a> {
+    fn visit_mut_apply(&mut self, apply: &mut Apply) -> ControlFlow<()> {
+        let arity = apply.args.len() as u8;
+        match apply.callee.as_ref() {
+            Expr::Remote(Remote {
+                span,
+                module,
+                function,
+                ..
+            }) => {
+                if let (Some(m), Some(f)) = (module.as_atom(), function.as_atom()) {
+                    let name = FunctionName::new(m.name, f.name, arity);
+                    self.calls.push(Span::new(*span, name));
+                }
+            }
+            Expr::Literal(Literal::Atom(f)) => {
+                let local_name = FunctionName::new_local(f.name, arity);
+                if let Some(imported) = self.imports.get(&local_name) {
+                    self.calls.push(Span::new(f.span, *imported));
+                }
+            }
+            _ => (),
+        }
+        if let Some(applied) = applied_function(apply) {
+            self.calls.push(applied);
+        }
+        visit::visit_mut_apply(self, apply)
+    }
+
+    fn visit_mut_function_var(&mut self, var: &mut FunctionVar) -> ControlFlow<()> {
+        match var {
+            FunctionVar::Resolved(name) => {
+                self.calls.push(Span::new(name.span(), name.item));
+            }
+            FunctionVar::Unresolved(UnresolvedFunctionName {
+                span,
+                module: Some(Name::Atom(m)),
+                function: Name::Atom(f),
+                arity: Arity::Int(arity),
+            }) => {
+                let name = FunctionName::new(m.name, f.name, *arity);
+                self.calls.push(Span::new(*span, name));
+            }
+            _ => (),
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Returns the function called by `apply/3` or one of the `spawn` BIFs, if it is statically known,
+/// i.e. the module and function are atoms and the arguments are a proper list
+fn applied_function(apply: &Apply) -> Option<Span<FunctionName>> {
+    let bif = match apply.callee.as_ref() {
+        Expr::Literal(Literal::Atom(f)) => f.name,
+        Expr::Remote(Remote {
+            module, function, ..
+        }) if module.as_atom_symbol() == Some(symbols::Erlang) => function.as_atom_symbol()?,
+        _ => return None,
+    };
+    let (m, f, args) = match (bif.as_str().get(), apply.args.as_slice()) {
+        ("apply" | "spawn" | "spawn_link" | "spawn_monitor", [m, f, args]) => (m, f, args),
+        ("spawn_opt", [m, f, args, _]) => (m, f, args),
+        _ => return None,
+    };
+
+    let mut arity = 0u8;
+    let mut args = args;
+    loop {
+        match args {
+            Expr::Cons(Cons { tail, .. }) => {
+                arity += 1;
+                args = tail.as_ref();
+            }
+            Expr::Literal(Literal::Nil(_)) => break,
+            _ => return None,
+        }
+    }
+
+    let name = FunctionName::new(m.as_atom_symbol()?, f.as_atom_symbol()?, arity);
+    Some(Span::new(apply.span, name))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use firefly_pass::Pass;
+
+    use super::*;
+    use crate::parser::{ParseConfig, Parser};
+
+    /// Runs xref over the modules in `inputs`, as a single application, returning the rendered diagnostics
+    fn xref(inputs: &[&str]) -> String {
+        let codemap = Arc::new(CodeMap::new());
+        let emitter = Arc::new(CaptureEmitter::default());
+        let diagnostics = DiagnosticsHandler::new(
+            DiagnosticsConfig::default(),
+            codemap.clone(),
+            emitter.clone(),
+        );
+        let parser = Parser::new(ParseConfig::default(), codemap);
+        let modules = inputs
+            .iter()
+            .map(|input| {
+                let mut module = parser
+                    .parse_string::<Module, _, _>(&diagnostics, input)
+                    .expect("parsing failed");
+                ModuleSummary::new(&mut module)
+            })
+            .collect::<Vec<_>>();
+        let app = ApplicationMetadata {
+            name: Symbol::intern("test"),
+            modules: BTreeMap::new(),
+            behaviours: BTreeMap::new(),
+        };
+        let program = Program {
+            app: app.name,
+            apps: vec![(&app, modules)],
+        };
+        let _ = Xref::new(&diagnostics).run(program);
+        emitter.captured()
+    }
+
+    #[test]
+    fn xref_remote_calls() {
+        let output = xref(&[
+            "-module(foo).
+-export([main/0, unused/0]).
+-import(bar, [exported/0]).
+
+main() ->
+    exported(),
+    bar:private(),
+    bar:missing(),
+    erlang:self(),
+    apply(bar, spawned, [1]),
+    nonexistent:call().
+
+unused() -> ok.
+",
+            "-module(bar).
+-behaviour(gen_server).
+-export([exported/0, spawned/1, init/1, handle_call/3, handle_cast/2]).
+
+exported() -> private().
+private() -> ok.
+spawned(_) -> ok.
+init(_) -> {ok, nil}.
+handle_call(_, _, State) -> {reply, ok, State}.
+handle_cast(_, State) -> {noreply, State}.
+",
+        ]);
+        assert!(output.contains("bar:private/0 is defined, but not exported"));
+        assert!(output.contains("bar:missing/0 is not defined"));
+        assert!(output.contains("nonexistent:call/0 is not defined in this program"));
+        assert!(!output.contains("erlang:self/0"));
+        assert!(output.contains("unused/0 is exported, but never called from another module"));
+        assert!(!output.contains("exported/0 is exported"));
+        assert!(!output.contains("spawned/1 is exported"));
+        assert!(!output.contains("init/1 is exported"));
+    }
+}
@@ -450,6 +450,20 @@ impl<'p> AbstractErlangToAst<'p> {
                             _other => None,
                         }
                     }
+                    name if name.as_str().get() == "doc" => {
+                        let value = self.etf_term_to_literal(span, attr.value);
+                        Some(Ok(Left(TopLevel::Attribute(Attribute::Doc(
+                            span,
+                            Expr::Literal(value),
+                        )))))
+                    }
+                    name if name.as_str().get() == "moduledoc" => {
+                        let value = self.etf_term_to_literal(span, attr.value);
+                        Some(Ok(Left(TopLevel::Attribute(Attribute::ModuleDoc(
+                            span,
+                            Expr::Literal(value),
+                        )))))
+                    }
                     name => {
                         let name = Ident::new(name, span);
                         let value = self.etf_term_to_literal(span, attr.value);
@@ -1113,6 +1113,25 @@ impl<'p> TranslateAst<'p> {
                 let qualifiers = self.preprocess_quals(qualifiers)?;
                 self.bc_tq(span, *body, qualifiers)
             }
+            ast::Expr::MapComprehension(ast::MapComprehension {
+                span,
+                key,
+                value,
+                qualifiers,
+            }) => {
+                // A map comprehension is a list comprehension of {K, V} tuples, converted
+                // to a map with maps:from_list/1, so later keys take precedence
+                let qualifiers = self.preprocess_quals(qualifiers)?;
+                let body = ast::Expr::Tuple(ast::Tuple {
+                    span,
+                    elements: vec![*key, *value],
+                });
+                let (list, mut pre) = self.lc_tq(span, body, qualifiers, inil!(span))?;
+                let (list, mut pre2) = force_safe(self.context_mut(), list);
+                pre.append(&mut pre2);
+                let call = ICall::new(span, symbols::Maps, symbols::FromList, vec![list]);
+                Ok((IExpr::Call(call), pre))
+            }
             ast::Expr::Tuple(ast::Tuple { span, elements }) => {
                 let (elements, pre) = self.safe_list(span, elements)?;
                 Ok((IExpr::Tuple(ITuple::new(span, elements)), pre))
@@ -1305,6 +1324,57 @@ impl<'p> TranslateAst<'p> {
                 });
                 self.expr(outer)
             }
+            // maybe .. [else .. ] end
+            ast::Expr::Maybe(ast::Maybe {
+                span,
+                body,
+                else_clauses,
+            }) => {
+                let Some(else_clauses) = else_clauses else {
+                    let mut exprs = self.maybe_body(body, None)?;
+                    let expr = exprs.pop().unwrap();
+                    return Ok((expr, exprs));
+                };
+                // The else clauses are lowered once, into a local fun which each conditional
+                // match in the body applies to the value it failed to match
+                let name = self.context_mut().new_fun_name(Some("maybe_else"));
+                let f = Var::new_with_arity(Ident::new(name, span), 1);
+                let var = self.context_mut().next_var(Some(span));
+                let clauses = self.clauses(else_clauses)?;
+                let reason = ituple!(
+                    span,
+                    iatom!(span, symbols::ElseClause),
+                    IExpr::Var(var.clone())
+                );
+                let fun = IExpr::Fun(IFun {
+                    span,
+                    annotations: Annotations::default(),
+                    id: Some(Ident::new(name, span)),
+                    name: Some(Ident::new(name, span)),
+                    vars: vec![var.clone()],
+                    clauses,
+                    fail: fail_clause(span, vec![IExpr::Var(var)], reason),
+                });
+                let body = self.maybe_body(body, Some(&f))?;
+                let expr = IExpr::LetRec(ILetRec {
+                    span,
+                    annotations: Annotations::default(),
+                    defs: vec![(f, fun)],
+                    body,
+                });
+                Ok((expr, vec![]))
+            }
+            ast::Expr::MaybeMatch(ast::MaybeMatch { span, .. }) => {
+                self.diagnostics
+                    .diagnostic(Severity::Error)
+                    .with_message("invalid conditional match")
+                    .with_primary_label(
+                        span,
+                        "?= is only permitted in the body of a maybe expression",
+                    )
+                    .emit();
+                bail!("invalid conditional match")
+            }
             ast::Expr::Catch(ast::Catch { span, expr }) => {
                 let (expr, mut pre) = self.expr(*expr)?;
                 pre.push(expr);
@@ -1761,7 +1831,14 @@ impl<'p> TranslateAst<'p> {
                     guards: vec![],
                     body: vec![last],
                 };
-                let clauses = match (gen.acc_pattern, gen.skip_pattern) {
+                let refill_clause = gen.refill.map(|(pattern, action)| IClause {
+                    span,
+                    annotations: Annotations::default_compiler_generated(),
+                    patterns: vec![*pattern],
+                    guards: vec![],
+                    body: vec![*action, nc.clone()],
+                });
+                let mut clauses = match (gen.acc_pattern, gen.skip_pattern) {
                     (None, None) => vec![tail_clause],
                     (None, Some(skip_pat)) => {
                         let skip_clause = IClause {
@@ -1800,6 +1877,7 @@ impl<'p> TranslateAst<'p> {
                     }
                     _ => unreachable!(),
                 };
+                clauses.extend(refill_clause);
                 let fun = IExpr::Fun(IFun {
                     span,
                     annotations: Annotations::default(),
@@ -1879,7 +1957,24 @@ impl<'p> TranslateAst<'p> {
                     guards: vec![],
                     body: vec![IExpr::Var(acc_var.clone())],
                 };
-                let clauses = match (gen.acc_pattern, gen.skip_pattern) {
+                let refill_clause = gen.refill.map(|(pattern, action)| {
+                    let nc = IExpr::Apply(IApply::new(
+                        span,
+                        IExpr::Var(f.clone()),
+                        vec![
+                            IExpr::Var(gen.tail.clone().unwrap()),
+                            IExpr::Var(acc_var.clone()),
+                        ],
+                    ));
+                    IClause {
+                        span,
+                        annotations: Annotations::default_compiler_generated(),
+                        patterns: vec![*pattern, IExpr::Var(ignore.clone())],
+                        guards: vec![],
+                        body: vec![*action, nc],
+                    }
+                });
+                let mut clauses = match (gen.acc_pattern, gen.skip_pattern) {
                     (None, None) => vec![tail_clause],
                     (None, Some(skip_pat)) => {
                         let skip_clause = IClause {
@@ -1893,7 +1988,7 @@ impl<'p> TranslateAst<'p> {
                             body: vec![IExpr::Apply(IApply::new(
                                 span,
                                 IExpr::Var(f.clone()),
-                                vec![IExpr::Var(gen.tail.unwrap())],
+                                vec![IExpr::Var(gen.tail.unwrap()), IExpr::Var(acc_var.clone())],
                             ))],
                         };
                         vec![skip_clause, tail_clause]
@@ -1902,7 +1997,7 @@ impl<'p> TranslateAst<'p> {
                         let nc = IExpr::Apply(IApply::new(
                             span,
                             IExpr::Var(f.clone()),
-                            vec![IExpr::Var(gen.tail.unwrap())],
+                            vec![IExpr::Var(gen.tail.unwrap()), IExpr::Var(acc_var.clone())],
                         ));
                         let skip_clause = IClause {
                             span,
@@ -1929,6 +2024,7 @@ impl<'p> TranslateAst<'p> {
                     }
                     _ => unreachable!(),
                 };
+                clauses.extend(refill_clause);
                 let fun = IExpr::Fun(IFun {
                     span,
                     annotations: Annotations::default(),
@@ -2190,6 +2286,9 @@ impl<'p> TranslateAst<'p> {
             ast::GeneratorType::Bitstring => {
                 self.bit_generator(gen.span, *gen.pattern, *gen.expr, guards)
             }
+            ast::GeneratorType::Map => {
+                self.map_generator(gen.span, *gen.pattern, *gen.expr, guards)
+            }
         }
     }
 
@@ -2250,11 +2349,96 @@ impl<'p> TranslateAst<'p> {
             skip_pattern,
             tail: Some(tail),
             tail_pattern: Box::new(IExpr::Literal(Literal::nil(span))),
+            refill: None,
             pre,
             arg: Box::new(arg),
         })
     }
 
+    fn map_generator(
+        &mut self,
+        span: SourceSpan,
+        pattern: ast::Expr,
+        expr: ast::Expr,
+        guards: Vec<ast::Expr>,
+    ) -> anyhow::Result<IGen> {
+        // Map generators walk the map using maps:iterator/1 and maps:next/1. The comprehension
+        // function is first applied to the iterator itself, which is matched by the refill
+        // clause, where the next element is fetched and the function reapplied to it. Elements
+        // are of the form {K, V, NextIter}, and the input is exhausted when we reach 'none'.
+        let tail = self.context_mut().next_var(Some(span));
+        let skip_key = IExpr::Var(self.context_mut().next_var(Some(span)));
+        let skip_value = IExpr::Var(self.context_mut().next_var(Some(span)));
+        let acc_guards = self.lc_guard_tests(span, guards);
+        let acc_pattern = match self.pattern(pattern).ok() {
+            Some(IExpr::Tuple(ITuple { mut elements, .. })) => {
+                elements.push(IExpr::Var(tail.clone()));
+                Some(Box::new(IExpr::Tuple(ITuple::new(span, elements))))
+            }
+            // If it never matches, there is no need for an accumulator clause.
+            _ => None,
+        };
+        let skip_pattern = Box::new(IExpr::Tuple(ITuple::new(
+            span,
+            vec![skip_key, skip_value, IExpr::Var(tail.clone())],
+        )));
+        let iter = self.context_mut().next_var(Some(span));
+        let next = IExpr::Call(ICall::new(
+            span,
+            symbols::Maps,
+            symbols::Next,
+            vec![IExpr::Var(iter.clone())],
+        ));
+        let refill = (
+            Box::new(IExpr::Var(iter)),
+            Box::new(IExpr::Set(ISet::new(span, tail.clone(), next))),
+        );
+        // The generator input must be a map, otherwise we raise {bad_generator, Input}
+        let (arg, mut pre) = self.safe(expr)?;
+        let iter = self.context_mut().next_var(Some(span));
+        let is_map = IExpr::Call(ICall::new(
+            span,
+            symbols::Erlang,
+            symbols::IsMap,
+            vec![arg.clone()],
+        ));
+        let iterator = IExpr::Call(ICall::new(
+            span,
+            symbols::Maps,
+            symbols::Iterator,
+            vec![arg.clone()],
+        ));
+        let bad_generator = ituple!(
+            span,
+            IExpr::Literal(lit_atom!(span, symbols::BadGenerator)),
+            arg
+        );
+        let init = IExpr::If(IIf {
+            span,
+            annotations: Annotations::default_compiler_generated(),
+            guards: vec![is_map],
+            then_body: vec![iterator],
+            else_body: vec![IExpr::PrimOp(IPrimOp::new(
+                span,
+                symbols::Error,
+                vec![bad_generator],
+            ))],
+        });
+        pre.push(IExpr::Set(ISet::new(span, iter.clone(), init)));
+        Ok(IGen {
+            span,
+            annotations: Annotations::default(),
+            acc_pattern,
+            acc_guards,
+            skip_pattern: Some(skip_pattern),
+            tail: Some(tail),
+            tail_pattern: Box::new(iatom!(span, symbols::None)),
+            refill: Some(refill),
+            pre,
+            arg: Box::new(IExpr::Var(iter)),
+        })
+    }
+
     fn bit_generator(
         &mut self,
         span: SourceSpan,
@@ -2296,6 +2480,7 @@ impl<'p> TranslateAst<'p> {
                         annotations: Annotations::default(),
                         segments: vec![tail_segment],
                     })),
+                    refill: None,
                     pre,
                     arg: Box::new(arg),
                 })
@@ -2314,6 +2499,7 @@ impl<'p> TranslateAst<'p> {
                         symbols::Underscore,
                         span,
                     )))),
+                    refill: None,
                     pre,
                     arg: Box::new(arg),
                 })
@@ -3067,6 +3253,75 @@ impl<'p> TranslateAst<'p> {
         (vec![tag, value, info], handler)
     }
 
+    // maybe_body([Expr], ElseFun) -> [IExpr]
+    //  Each conditional match in the body of a maybe becomes a case, with the remainder
+    //  of the body in the success clause. On failure, the unmatched value is either the
+    //  result of the maybe, or is passed to the fun implementing the else clauses, if present.
+    fn maybe_body(
+        &mut self,
+        body: Vec<ast::Expr>,
+        else_fun: Option<&Var>,
+    ) -> anyhow::Result<Vec<IExpr>> {
+        let mut output = Vec::with_capacity(body.len());
+        let mut body = body.into_iter();
+        while let Some(expr) = body.next() {
+            let (span, pattern, expr) = match expr {
+                ast::Expr::MaybeMatch(ast::MaybeMatch {
+                    span,
+                    pattern,
+                    expr,
+                }) => (span, pattern, expr),
+                expr => {
+                    let (expr, mut pre) = self.expr(expr)?;
+                    output.append(&mut pre);
+                    output.push(expr);
+                    continue;
+                }
+            };
+            // The arg is made safe, so that it can be used as the value of the maybe when this
+            // is the last expression in the body
+            let (arg, mut pre) = self.safe(*expr)?;
+            output.append(&mut pre);
+            let rest = body.collect::<Vec<_>>();
+            let pattern = self.pattern(*pattern)?;
+            let success = if rest.is_empty() {
+                vec![arg.clone()]
+            } else {
+                self.maybe_body(rest, else_fun)?
+            };
+            let fpat = self.context_mut().next_var(Some(span));
+            let failure = match else_fun {
+                None => IExpr::Var(fpat.clone()),
+                Some(f) => IExpr::Apply(IApply::new(
+                    span,
+                    IExpr::Var(f.clone()),
+                    vec![IExpr::Var(fpat.clone())],
+                )),
+            };
+            output.push(IExpr::Case(ICase {
+                span,
+                annotations: Annotations::default(),
+                args: vec![arg],
+                clauses: vec![IClause {
+                    span,
+                    annotations: Annotations::default(),
+                    patterns: vec![pattern],
+                    guards: vec![],
+                    body: success,
+                }],
+                fail: Box::new(IClause {
+                    span,
+                    annotations: Annotations::default_compiler_generated(),
+                    patterns: vec![IExpr::Var(fpat)],
+                    guards: vec![],
+                    body: vec![failure],
+                }),
+            }));
+            break;
+        }
+        Ok(output)
+    }
+
     fn set_wanted(&mut self, expr: &ast::Expr) -> bool {
         match expr {
             ast::Expr::Var(ast::Var(id)) => {
@@ -3938,3 +4193,87 @@ fn force_safe(context: &mut FunctionContext, expr: IExpr) -> (IExpr, Vec<IExpr>)
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use firefly_pass::Pass;
+    use firefly_util::diagnostics::*;
+
+    use super::*;
+    use crate::parser::{ParseConfig, Parser};
+
+    /// Lowers `input` to Core, returning the printed module
+    fn lower(input: &str) -> String {
+        let codemap = Arc::new(CodeMap::new());
+        let emitter = Arc::new(CaptureEmitter::default());
+        let diagnostics = Arc::new(DiagnosticsHandler::new(
+            DiagnosticsConfig::default(),
+            codemap.clone(),
+            emitter.clone(),
+        ));
+        let parser = Parser::new(ParseConfig::default(), codemap);
+        let module: ast::Module = parser
+            .parse_string(&diagnostics, input)
+            .expect("parsing failed");
+        let mut pass = AstToCore::new(diagnostics);
+        match pass.run(module) {
+            Ok(module) => module.to_string(),
+            Err(err) => panic!("lowering failed: {}\n{}", err, emitter.captured()),
+        }
+    }
+
+    #[test]
+    fn lower_binary_comprehension_filter() {
+        let output = lower(
+            "-module(foo).
+-export([bar/1]).
+
+bar(L) ->
+    << <<X>> || X <- L, X > 1 >>.
+",
+        );
+        // The comprehension fun takes the remaining input and the accumulated binary, including
+        // when it skips elements rejected by the filter
+        let applies = output
+            .lines()
+            .filter(|line| line.contains("apply lbc"))
+            .collect::<Vec<_>>();
+        assert!(!applies.is_empty(), "{}", output);
+        for apply in applies {
+            assert!(apply.contains("/2"), "{}", output);
+        }
+    }
+
+    #[test]
+    fn lower_maybe_else_clauses_once() {
+        let output = lower(
+            "-module(foo).
+-feature(maybe_expr, enable).
+-export([bar/0]).
+
+bar() ->
+    maybe
+        {ok, A} ?= a(),
+        {ok, B} ?= b(A),
+        {ok, C} ?= c(B),
+        C
+    else
+        {error, Reason} -> {else_marker, Reason};
+        undefined -> else_marker
+    end.
+
+a() -> {ok, 1}.
+b(X) -> {ok, X}.
+c(X) -> {ok, X}.
+",
+        );
+        // The else clauses are lowered into a single local fun, which each of the three
+        // conditional matches applies to the unmatched value
+        assert_eq!(output.matches("else_marker").count(), 2, "{}", output);
+        assert_eq!(output.matches("else_clause").count(), 1, "{}", output);
+        assert_eq!(output.matches("letrec").count(), 1, "{}", output);
+        assert_eq!(output.matches("apply maybe_else").count(), 3, "{}", output);
+    }
+}
@@ -25,6 +25,7 @@ pub enum Directive {
     Error(directives::Error),
     Warning(directives::Warning),
     File(directives::File),
+    Feature(directives::Feature),
 }
 impl Directive {
     pub fn span(&self) -> SourceSpan {
@@ -43,6 +44,7 @@ impl Directive {
             Directive::Error(ref t) => t.span(),
             Directive::Warning(ref t) => t.span(),
             Directive::File(ref t) => t.span(),
+            Directive::Feature(ref t) => t.span(),
         }
     }
 }
@@ -63,6 +65,7 @@ impl fmt::Display for Directive {
             Directive::Error(ref t) => t.fmt(f),
             Directive::Warning(ref t) => t.fmt(f),
             Directive::File(ref t) => t.fmt(f),
+            Directive::Feature(ref t) => t.fmt(f),
         }
     }
 }
@@ -113,6 +116,8 @@ impl ReadFrom for Directive {
             "nifs" => unread_token!(reader, _hyphen.into(), name, Token::Nifs),
             "behaviour" => unread_token!(reader, _hyphen.into(), name, Token::Behaviour),
             "deprecated" => unread_token!(reader, _hyphen.into(), name, Token::Deprecated),
+            "doc" => unread_token!(reader, _hyphen.into(), name, Token::Doc),
+            "moduledoc" => unread_token!(reader, _hyphen.into(), name, Token::ModuleDoc),
             "type" => unread_token!(reader, _hyphen.into(), name, Token::Type),
             "opaque" => unread_token!(reader, _hyphen.into(), name, Token::Opaque),
             _ => {
@@ -138,6 +143,7 @@ impl ReadFrom for Directive {
             "error" => reader.read().map(Directive::Error).map(Some),
             "warning" => reader.read().map(Directive::Warning).map(Some),
             "file" => reader.read().map(Directive::File).map(Some),
+            "feature" => reader.read().map(Directive::Feature).map(Some),
             _ => Ok(None),
         }
     }
@@ -121,6 +121,17 @@ impl MacroContainer {
     pub fn defined_func(&self, symbol: &Symbol) -> bool {
         self.func_defines.contains_key(symbol)
     }
+
+    /// Returns an iterator over the macros defined by `-define` directives
+    pub fn defines(&self) -> impl Iterator<Item = &Define> + '_ {
+        self.const_defines
+            .values()
+            .chain(self.func_defines.values().flat_map(|defs| defs.values()))
+            .filter_map(|def| match def {
+                MacroDef::Static(define) => Some(define),
+                _ => None,
+            })
+    }
 }
 
 /// Macro Definition.
@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -9,6 +9,7 @@ use firefly_util::diagnostics::*;
 
 use crate::ast::Literal;
 use crate::evaluator;
+use crate::features::{self, Feature};
 use crate::lexer::Lexer;
 use crate::lexer::{DelayedSubstitution, IdentToken, Lexed, LexicalToken, Token};
 use crate::parser::{Parser, ParserError};
@@ -29,6 +30,8 @@ pub struct Preprocessor<'a, Reader: TokenReader> {
     macros: MacroContainer,
     macro_calls: BTreeMap<SourceIndex, MacroCall>,
     expanded_tokens: VecDeque<LexicalToken>,
+    /// The features which were enabled for this module via `-feature(Name, enable).`
+    features: BTreeSet<Symbol>,
     warnings_as_errors: bool,
     no_warn: bool,
 }
@@ -73,6 +76,7 @@ where
             macros,
             macro_calls: BTreeMap::new(),
             expanded_tokens: VecDeque::new(),
+            features: BTreeSet::new(),
             warnings_as_errors: parser.config.warnings_as_errors,
             no_warn: parser.config.no_warn,
         }
@@ -96,19 +100,51 @@ where
             macros: self.macros.clone(),
             macro_calls: BTreeMap::new(),
             expanded_tokens: VecDeque::new(),
+            features: self.features.clone(),
             warnings_as_errors: self.warnings_as_errors,
             no_warn: self.no_warn,
         }
     }
 
+    /// Returns the macros defined at the current point of preprocessing
+    ///
+    /// Once the token stream has been consumed, this is the set of macros defined by the module
+    /// and the files it includes.
+    pub fn macros(&self) -> &MacroContainer {
+        &self.macros
+    }
+
     fn ignore(&self) -> bool {
         self.branches.iter().any(|b| !b.entered)
     }
 
+    fn is_feature_enabled(&self, feature: &Feature) -> bool {
+        feature.enabled || self.features.contains(&feature.name)
+    }
+
+    /// The reserved words introduced by a feature are only keywords when that feature is enabled,
+    /// otherwise they are plain atoms
+    fn reserved_word(&self, token: LexicalToken) -> LexicalToken {
+        let maybe_expr = || {
+            features::get(&symbols::MaybeExpr)
+                .map(|feature| self.is_feature_enabled(feature))
+                .unwrap_or_default()
+        };
+        match token {
+            LexicalToken(start, Token::Maybe, end) if !maybe_expr() => {
+                LexicalToken(start, Token::Atom(symbols::Maybe), end)
+            }
+            LexicalToken(start, Token::Else, end) if !maybe_expr() => {
+                LexicalToken(start, Token::Atom(symbols::Else), end)
+            }
+            token => token,
+        }
+    }
+
     fn next_token(&mut self) -> Result<Option<LexicalToken>, ParserError> {
         loop {
             if let Some(token) = self.expanded_tokens.pop_front() {
-                return Ok(Some(token));
+                return Ok(Some(self.reserved_word(token)));
             }
             if self.can_directive_start {
                 match self.reader.try_read().map_err(ParserError::from)? {
@@ -140,7 +176,7 @@ where
                 } else {
                     self.can_directive_start = false;
                 }
-                return Ok(Some(token));
+                return Ok(Some(self.reserved_word(token)));
             } else {
                 break;
             }
@@ -200,7 +236,7 @@ where
                         let arg = iter.next().unwrap();
                         match arg.tokens.as_slice() {
                             [LexicalToken(_, Token::Atom(feature), _)] => {
-                                match crate::features::get(feature) {
+                                match features::get(feature) {
                                     Some(_) => LexicalToken(
                                         span.start(),
                                         Token::Atom(symbols::True),
@@ -239,8 +275,8 @@ where
                         let arg = iter.next().unwrap();
                         match arg.tokens.as_slice() {
                             [LexicalToken(_, Token::Atom(feature), _)] => {
-                                match crate::features::get(feature) {
-                                    Some(feat) if feat.enabled => LexicalToken(
+                                match features::get(feature) {
+                                    Some(feat) if self.is_feature_enabled(feat) => LexicalToken(
                                         span.start(),
                                         Token::Atom(symbols::True),
                                         span.end(),
@@ -509,6 +545,36 @@ where
                         .emit();
                 }
             }
+            Directive::Feature(ref d) if !ignore => {
+                let span = d.span();
+                let name = d.name.symbol();
+                let Some(feature) = features::get(&name) else {
+                    self.diagnostics
+                        .diagnostic(Severity::Error)
+                        .with_message(format!("unknown feature {}", name))
+                        .with_primary_label(span, "this is not a recognized feature")
+                        .emit();
+                    return Ok(());
+                };
+                match d.action.symbol() {
+                    symbols::Enable => {
+                        self.features.insert(feature.name);
+                    }
+                    symbols::Disable => {
+                        self.features.remove(&feature.name);
+                    }
+                    _ => {
+                        self.diagnostics
+                            .diagnostic(Severity::Error)
+                            .with_message("invalid -feature attribute")
+                            .with_primary_label(
+                                span,
+                                "expected the action to be either 'enable' or 'disable'",
+                            )
+                            .emit();
+                    }
+                }
+            }
             Directive::File(ref f) if !ignore => {
                 // TODO
                 let span = f.span();
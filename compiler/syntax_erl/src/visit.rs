@@ -68,6 +68,8 @@ visitor! {
     anonymous_fun => AnonymousFun
     recursive_fun => RecursiveFun
     try => Try
+    maybe => Maybe
+    maybe_match => MaybeMatch
     catch => Catch
     receive => Receive
     after => After
@@ -83,6 +85,7 @@ visitor! {
     begin => Begin
     generator => Generator
     binary_comprehension => BinaryComprehension
+    map_comprehension => MapComprehension
     list_comprehension => ListComprehension
     record => Record
     record_access => RecordAccess
@@ -197,6 +200,7 @@ where
         Expr::RecordUpdate(ref mut up) => visitor.visit_mut_record_update(up),
         Expr::ListComprehension(ref mut comp) => visitor.visit_mut_list_comprehension(comp),
         Expr::BinaryComprehension(ref mut comp) => visitor.visit_mut_binary_comprehension(comp),
+        Expr::MapComprehension(ref mut comp) => visitor.visit_mut_map_comprehension(comp),
         Expr::Generator(ref mut gen) => visitor.visit_mut_generator(gen),
         Expr::Begin(ref mut begin) => visitor.visit_mut_begin(begin),
         Expr::Apply(ref mut apply) => visitor.visit_mut_apply(apply),
@@ -209,6 +213,8 @@ where
         Expr::Case(ref mut case) => visitor.visit_mut_case(case),
         Expr::Receive(ref mut receive) => visitor.visit_mut_receive(receive),
         Expr::Try(ref mut expr) => visitor.visit_mut_try(expr),
+        Expr::Maybe(ref mut expr) => visitor.visit_mut_maybe(expr),
+        Expr::MaybeMatch(ref mut expr) => visitor.visit_mut_maybe_match(expr),
         Expr::Fun(ref mut fun) => visitor.visit_mut_fun(fun),
         Expr::Protect(ref mut protect) => visitor.visit_mut_protect(protect),
     }
@@ -432,6 +438,21 @@ where
     ControlFlow::Continue(())
 }
 
+pub fn visit_mut_map_comprehension<V, T>(
+    visitor: &mut V,
+    comp: &mut MapComprehension,
+) -> ControlFlow<T>
+where
+    V: ?Sized + VisitMut<T>,
+{
+    visitor.visit_mut_expr(comp.key.as_mut())?;
+    visitor.visit_mut_expr(comp.value.as_mut())?;
+    for expr in comp.qualifiers.iter_mut() {
+        visitor.visit_mut_expr(expr)?;
+    }
+    ControlFlow::Continue(())
+}
+
 pub fn visit_mut_generator<V, T>(visitor: &mut V, gen: &mut Generator) -> ControlFlow<T>
 where
     V: ?Sized + VisitMut<T>,
@@ -560,6 +581,29 @@ where
     ControlFlow::Continue(())
 }
 
+pub fn visit_mut_maybe<V, T>(visitor: &mut V, maybe: &mut Maybe) -> ControlFlow<T>
+where
+    V: ?Sized + VisitMut<T>,
+{
+    for expr in maybe.body.iter_mut() {
+        visitor.visit_mut_expr(expr)?;
+    }
+    if let Some(clauses) = maybe.else_clauses.as_mut() {
+        for clause in clauses.iter_mut() {
+            visitor.visit_mut_clause(clause)?;
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+pub fn visit_mut_maybe_match<V, T>(visitor: &mut V, expr: &mut MaybeMatch) -> ControlFlow<T>
+where
+    V: ?Sized + VisitMut<T>,
+{
+    visitor.visit_mut_pattern(expr.pattern.as_mut())?;
+    visitor.visit_mut_expr(expr.expr.as_mut())
+}
+
 pub fn visit_mut_after<V, T>(visitor: &mut V, after: &mut After) -> ControlFlow<T>
 where
     V: ?Sized + VisitMut<T>,
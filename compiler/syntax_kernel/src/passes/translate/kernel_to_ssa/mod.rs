@@ -49,11 +49,16 @@ impl Pass for KernelToSsa {
             } else {
                 Visibility::DEFAULT
             };
-            let visibility = if kfunction.has_annotation(symbols::Nif) {
+            let mut visibility = if kfunction.has_annotation(symbols::Nif) {
                 base_visibility | Visibility::NIF
             } else {
                 base_visibility
             };
+            // The on_load function is called by `init` when the system boots, so it must be
+            // reachable from outside of its module even if it isn't exported
+            if module.on_load.as_ref().map(|f| f.as_ref()) == Some(&kfunction.name) {
+                visibility |= Visibility::PUBLIC | Visibility::ON_LOAD;
+            }
             let params = vec![Type::Term(TermType::Any); name.arity as usize];
             let signature = Signature {
                 visibility,
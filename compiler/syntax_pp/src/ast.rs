@@ -23,6 +23,12 @@ pub struct Root {
     pub term: Term,
 }
 
+/// This is the root of a document containing zero or more terms, e.g. `rebar.config`
+#[derive(Debug, Clone)]
+pub struct Terms {
+    pub terms: Vec<Term>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Spanned)]
 pub enum Term {
     Atom(Span<Symbol>),
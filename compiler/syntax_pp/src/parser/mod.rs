@@ -121,6 +121,46 @@ impl GParse for ast::Root {
     }
 }
 
+impl GParse for ast::Terms {
+    type Parser = grammar::TermsParser;
+    type Error = ParserError;
+    type Config = ();
+    type Token = Result<(SourceIndex, Token, SourceIndex), ParserError>;
+
+    fn root_file_error(source: std::io::Error, path: std::path::PathBuf) -> Self::Error {
+        ParserError::RootFile { source, path }
+    }
+
+    fn parse<S>(
+        parser: &GParser<Self::Config>,
+        diagnostics: &DiagnosticsHandler,
+        source: S,
+    ) -> Result<Self, Self::Error>
+    where
+        S: Source,
+    {
+        let scanner = Scanner::new(source);
+        let lexer = Lexer::new(scanner);
+        Self::parse_tokens(diagnostics, parser.codemap.clone(), lexer)
+    }
+
+    fn parse_tokens<S>(
+        diagnostics: &DiagnosticsHandler,
+        codemap: Arc<CodeMap>,
+        tokens: S,
+    ) -> Result<Self, Self::Error>
+    where
+        S: IntoIterator<Item = Self::Token>,
+    {
+        let result = Self::Parser::new().parse(diagnostics, &codemap, tokens);
+        match result {
+            Ok(terms) => Ok(terms),
+            Err(lalrpop_util::ParseError::User { error }) => Err(error.into()),
+            Err(err) => Err(ParserError::from(err).into()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::sync::Arc;
@@ -175,6 +215,20 @@ mod test {
         let _: Root = parse(codemap.clone(), RICH);
     }
 
+    #[test]
+    fn multiple_terms_test() {
+        let codemap = Arc::new(CodeMap::new());
+        let terms: Terms = parse(
+            codemap.clone(),
+            r#"
+%% A rebar.config
+{erl_opts, [debug_info, {d, 'TEST', true}]}.
+{deps, [cowboy, {jsx, "3.1.0"}]}.
+"#,
+        );
+        assert_eq!(terms.terms.len(), 2);
+    }
+
     #[test]
     fn complex_ast() {
         let codemap = Arc::new(CodeMap::new());
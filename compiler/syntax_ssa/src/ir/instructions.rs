@@ -365,6 +365,13 @@ impl Opcode {
         }
     }
 
+    pub fn is_call(&self) -> bool {
+        match self {
+            Self::Call | Self::Enter | Self::CallIndirect | Self::EnterIndirect => true,
+            _ => false,
+        }
+    }
+
     pub fn is_exception(&self) -> bool {
         match self {
             Self::Halt | Self::Throw | Self::Error | Self::Exit1 | Self::Raise => true,
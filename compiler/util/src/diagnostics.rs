@@ -19,6 +19,10 @@ pub use firefly_diagnostics::{Diagnostic, Label, LabelStyle, Severity};
 
 use crate::error::{FatalError, Verbosity};
 
+mod structured;
+
+pub use self::structured::{ErrorFormat, JsonEmitter, SarifEmitter};
+
 #[derive(Debug, Clone)]
 pub struct DiagnosticsConfig {
     pub verbosity: Verbosity,
@@ -40,6 +44,30 @@ impl Default for DiagnosticsConfig {
 pub trait Emitter: Send + Sync {
     fn buffer(&self) -> Buffer;
     fn print(&self, buffer: Buffer) -> std::io::Result<()>;
+
+    /// Emits a single diagnostic
+    ///
+    /// By default, the diagnostic is rendered as human-readable text and printed via `print`
+    fn emit_diagnostic(
+        &self,
+        codemap: &CodeMap,
+        display: &DisplayConfig,
+        diagnostic: &Diagnostic,
+    ) -> std::io::Result<()> {
+        use firefly_diagnostics::term;
+
+        let mut buffer = self.buffer();
+        term::emit(&mut buffer, display, codemap, diagnostic)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        self.print(buffer)
+    }
+
+    /// Writes out any diagnostics this emitter has held back, once all of them have been emitted
+    ///
+    /// By default this does nothing, as diagnostics are written as they are emitted
+    fn finish(&self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 pub struct DefaultEmitter {
@@ -309,6 +337,13 @@ impl DiagnosticsHandler {
         self.err_count.load(Ordering::Relaxed) > 0
     }
 
+    /// Writes out any diagnostics held back by the emitter, see [`Emitter::finish`]
+    ///
+    /// This must be called once no more diagnostics will be emitted.
+    pub fn finish(&self) -> std::io::Result<()> {
+        self.emitter.finish()
+    }
+
     pub fn abort_if_errors(&self) {
         if self.has_errors() {
             FatalError.raise();
@@ -427,8 +462,6 @@ impl DiagnosticsHandler {
     /// Emits the given diagnostic
     #[inline(always)]
     pub fn emit(&self, diagnostic: impl ToDiagnostic) {
-        use firefly_diagnostics::term;
-
         if self.silent {
             return;
         }
@@ -443,15 +476,9 @@ impl DiagnosticsHandler {
             _ => (),
         }
 
-        let mut buffer = self.emitter.buffer();
-        term::emit(
-            &mut buffer,
-            &self.display,
-            self.codemap.deref(),
-            &diagnostic,
-        )
-        .unwrap();
-        self.emitter.print(buffer).unwrap();
+        self.emitter
+            .emit_diagnostic(self.codemap.deref(), &self.display, &diagnostic)
+            .unwrap();
     }
 }
 
@@ -0,0 +1,411 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use parking_lot::Mutex;
+
+use crate::json::Json;
+
+use super::*;
+
+/// The format in which diagnostics are emitted
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Human-readable text, rendered with source snippets
+    #[default]
+    Human,
+    /// One JSON object per diagnostic, each on its own line
+    Json,
+    /// A single SARIF 2.1.0 log containing all diagnostics, written once compilation is done
+    Sarif,
+}
+impl ErrorFormat {
+    pub const VARIANTS: &'static [&'static str] = &["human", "json", "sarif"];
+}
+impl FromStr for ErrorFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Emits diagnostics as JSON records on stdout, one per line
+///
+/// Each record has the following shape, where lines and columns are 1-based:
+///
+/// ```json
+/// {"severity":"warning","code":null,"message":"unused variable",
+///  "labels":[{"style":"primary","file":"src/foo.erl","message":"...",
+///             "start":{"line":4,"column":5},"end":{"line":4,"column":6}}],
+///  "notes":[]}
+/// ```
+///
+/// Other messages, e.g. progress notices, are written to stderr as text.
+pub struct JsonEmitter {
+    writer: BufferWriter,
+}
+impl JsonEmitter {
+    pub fn new(color: ColorChoice) -> Self {
+        Self {
+            writer: BufferWriter::stderr(color),
+        }
+    }
+}
+impl Emitter for JsonEmitter {
+    #[inline(always)]
+    fn buffer(&self) -> Buffer {
+        self.writer.buffer()
+    }
+
+    #[inline(always)]
+    fn print(&self, buffer: Buffer) -> io::Result<()> {
+        self.writer.print(&buffer)
+    }
+
+    fn emit_diagnostic(
+        &self,
+        codemap: &CodeMap,
+        _display: &DisplayConfig,
+        diagnostic: &Diagnostic,
+    ) -> io::Result<()> {
+        let json = json_record(codemap, diagnostic);
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        writeln!(&mut stdout, "{}", json)
+    }
+}
+
+/// Emits diagnostics as a SARIF 2.1.0 log on stdout
+///
+/// As a SARIF log is a single document, diagnostics are collected as they are emitted, and the log
+/// is written by [`Emitter::finish`], i.e. once compilation has finished. Other messages, e.g.
+/// progress notices, are written to stderr as text.
+///
+/// Source files are referred to by `file://` URIs when their path is absolute, and otherwise by
+/// URIs relative to `%SRCROOT%`, which is defined as the current working directory.
+pub struct SarifEmitter {
+    writer: BufferWriter,
+    results: Mutex<Vec<Json>>,
+}
+impl SarifEmitter {
+    pub fn new(color: ColorChoice) -> Self {
+        Self {
+            writer: BufferWriter::stderr(color),
+            results: Mutex::new(vec![]),
+        }
+    }
+}
+impl Emitter for SarifEmitter {
+    #[inline(always)]
+    fn buffer(&self) -> Buffer {
+        self.writer.buffer()
+    }
+
+    #[inline(always)]
+    fn print(&self, buffer: Buffer) -> io::Result<()> {
+        self.writer.print(&buffer)
+    }
+
+    fn emit_diagnostic(
+        &self,
+        codemap: &CodeMap,
+        _display: &DisplayConfig,
+        diagnostic: &Diagnostic,
+    ) -> io::Result<()> {
+        let result = sarif_result(codemap, diagnostic);
+        self.results.lock().push(result);
+        Ok(())
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        let results = std::mem::take(&mut *self.results.lock());
+        let srcroot = std::env::current_dir()?;
+        let log = sarif_log(&srcroot, results);
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        writeln!(&mut stdout, "{}", log)?;
+        stdout.flush()
+    }
+}
+
+/// The position of one end of a label, with 1-based line and column numbers
+struct Position {
+    line: usize,
+    column: usize,
+}
+impl Position {
+    fn to_json(&self) -> Json {
+        Json::object([("line", self.line.into()), ("column", self.column.into())])
+    }
+}
+
+/// A label resolved against the `CodeMap`
+struct ResolvedLabel<'a> {
+    primary: bool,
+    file: String,
+    start: Position,
+    end: Position,
+    message: &'a str,
+}
+
+fn resolve_labels<'a>(codemap: &CodeMap, diagnostic: &'a Diagnostic) -> Vec<ResolvedLabel<'a>> {
+    let position = |file_id, index: usize| -> Option<Position> {
+        let location = codemap
+            .location(file_id, ByteIndex::from(index as u32))
+            .ok()?;
+        Some(Position {
+            line: location.line.to_usize() + 1,
+            column: location.column.to_usize() + 1,
+        })
+    };
+
+    diagnostic
+        .labels
+        .iter()
+        .filter_map(|label| {
+            let file = codemap.name(label.file_id).ok()?;
+            Some(ResolvedLabel {
+                primary: label.style == LabelStyle::Primary,
+                file: file.to_string(),
+                start: position(label.file_id, label.range.start)?,
+                end: position(label.file_id, label.range.end)?,
+                message: label.message.as_str(),
+            })
+        })
+        .collect()
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Bug => "bug",
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+        Severity::Help => "help",
+    }
+}
+
+fn json_record(codemap: &CodeMap, diagnostic: &Diagnostic) -> Json {
+    let labels = resolve_labels(codemap, diagnostic)
+        .iter()
+        .map(|label| {
+            let style = if label.primary {
+                "primary"
+            } else {
+                "secondary"
+            };
+            Json::object([
+                ("style", style.into()),
+                ("file", label.file.as_str().into()),
+                ("message", label.message.into()),
+                ("start", label.start.to_json()),
+                ("end", label.end.to_json()),
+            ])
+        })
+        .collect::<Vec<_>>();
+    let notes = diagnostic
+        .notes
+        .iter()
+        .map(|note| note.as_str().into())
+        .collect::<Vec<Json>>();
+    Json::object([
+        ("severity", severity_name(diagnostic.severity).into()),
+        ("code", diagnostic.code.as_deref().into()),
+        ("message", diagnostic.message.as_str().into()),
+        ("labels", labels.into()),
+        ("notes", notes.into()),
+    ])
+}
+
+fn sarif_log(srcroot: &Path, results: Vec<Json>) -> Json {
+    // The base URI of a relative reference must end with a slash to be treated as a directory
+    let mut srcroot = file_uri(&srcroot.to_string_lossy()).unwrap_or_default();
+    if !srcroot.ends_with('/') {
+        srcroot.push('/');
+    }
+    let driver = Json::object([
+        ("name", "firefly".into()),
+        ("version", env!("CARGO_PKG_VERSION").into()),
+    ]);
+    let run = Json::object([
+        ("tool", Json::object([("driver", driver)])),
+        (
+            "originalUriBaseIds",
+            Json::object([(SRCROOT, Json::object([("uri", srcroot.into())]))]),
+        ),
+        ("results", results.into()),
+    ]);
+    Json::object([
+        (
+            "$schema",
+            "https://json.schemastore.org/sarif-2.1.0.json".into(),
+        ),
+        ("version", "2.1.0".into()),
+        ("runs", vec![run].into()),
+    ])
+}
+
+fn sarif_result(codemap: &CodeMap, diagnostic: &Diagnostic) -> Json {
+    let level = match diagnostic.severity {
+        Severity::Bug | Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "note",
+    };
+    // SARIF has no equivalent of notes, so they are appended to the message text
+    let mut text = diagnostic.message.clone();
+    for note in diagnostic.notes.iter() {
+        text.push('\n');
+        text.push_str(note);
+    }
+
+    let mut fields = vec![];
+    if let Some(code) = diagnostic.code.as_deref() {
+        fields.push(("ruleId", code.into()));
+    }
+    fields.push(("level", level.into()));
+    fields.push(("message", Json::object([("text", text.into())])));
+
+    let labels = resolve_labels(codemap, diagnostic);
+    let (primary, secondary): (Vec<_>, Vec<_>) = labels.iter().partition(|label| label.primary);
+    for (key, labels) in [("locations", primary), ("relatedLocations", secondary)] {
+        if labels.is_empty() {
+            continue;
+        }
+        let locations = labels.into_iter().map(sarif_location).collect::<Vec<_>>();
+        fields.push((key, locations.into()));
+    }
+    Json::object(fields)
+}
+
+fn sarif_location(label: &ResolvedLabel) -> Json {
+    let artifact = match file_uri(&label.file) {
+        Some(uri) => Json::object([("uri", uri.into())]),
+        None => Json::object([
+            ("uri", encode_path(&label.file.replace('\\', "/")).into()),
+            ("uriBaseId", SRCROOT.into()),
+        ]),
+    };
+    let region = Json::object([
+        ("startLine", label.start.line.into()),
+        ("startColumn", label.start.column.into()),
+        ("endLine", label.end.line.into()),
+        ("endColumn", label.end.column.into()),
+    ]);
+    let mut fields = vec![(
+        "physicalLocation",
+        Json::object([("artifactLocation", artifact), ("region", region)]),
+    )];
+    if !label.message.is_empty() {
+        fields.push(("message", Json::object([("text", label.message.into())])));
+    }
+    Json::object(fields)
+}
+
+/// The base id against which relative artifact URIs are resolved
+const SRCROOT: &str = "%SRCROOT%";
+
+/// Returns the `file://` URI of `path`, or `None` if it is not absolute
+fn file_uri(path: &str) -> Option<String> {
+    let path = path.replace('\\', "/");
+    if path.starts_with('/') {
+        return Some(format!("file://{}", encode_path(&path)));
+    }
+    // Windows paths with a drive letter, e.g. `C:/src`
+    match path.as_bytes() {
+        [drive, b':', b'/', ..] if drive.is_ascii_alphabetic() => {
+            Some(format!("file:///{}{}", &path[..2], encode_path(&path[2..])))
+        }
+        _ => None,
+    }
+}
+
+/// Percent-encodes the characters of `path` which may not appear in the path of a URI
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn diagnostic(codemap: &CodeMap) -> Diagnostic {
+        let id = codemap.add(
+            "test.erl",
+            "-module(test).\nfoo() -> \"bar\".\n".to_string(),
+        );
+        Diagnostic::warning()
+            .with_message("unused function")
+            .with_labels(vec![
+                Label::primary(id, 15..18).with_message("never \"called\""),
+                Label::secondary(id, 0..31),
+            ])
+            .with_notes(vec!["a note".to_string()])
+    }
+
+    #[test]
+    fn json_record_of_diagnostic() {
+        let codemap = Arc::new(CodeMap::new());
+        let diagnostic = diagnostic(&codemap);
+        let json = json_record(&codemap, &diagnostic).to_string();
+        assert_eq!(
+            json,
+            "{\"severity\":\"warning\",\"code\":null,\"message\":\"unused function\",\"labels\":[\
+             {\"style\":\"primary\",\"file\":\"test.erl\",\"message\":\"never \\\"called\\\"\",\
+             \"start\":{\"line\":2,\"column\":1},\"end\":{\"line\":2,\"column\":4}},\
+             {\"style\":\"secondary\",\"file\":\"test.erl\",\"message\":\"\",\
+             \"start\":{\"line\":1,\"column\":1},\"end\":{\"line\":3,\"column\":1}}],\
+             \"notes\":[\"a note\"]}"
+        );
+    }
+
+    #[test]
+    fn sarif_result_of_diagnostic() {
+        let codemap = Arc::new(CodeMap::new());
+        let diagnostic = diagnostic(&codemap);
+        let json = sarif_result(&codemap, &diagnostic).to_string();
+        assert!(json.starts_with(
+            "{\"level\":\"warning\",\"message\":{\"text\":\"unused function\\na note\"}"
+        ));
+        assert!(json.contains(
+            "\"locations\":[{\"physicalLocation\":{\"artifactLocation\":{\"uri\":\"test.erl\",\
+             \"uriBaseId\":\"%SRCROOT%\"},\
+             \"region\":{\"startLine\":2,\"startColumn\":1,\"endLine\":2,\"endColumn\":4}}"
+        ));
+        assert!(json.contains("\"relatedLocations\":["));
+    }
+
+    #[test]
+    fn sarif_uris() {
+        assert_eq!(
+            file_uri("/src/my app/foo.erl").as_deref(),
+            Some("file:///src/my%20app/foo.erl")
+        );
+        assert_eq!(
+            file_uri("C:\\src\\foo.erl").as_deref(),
+            Some("file:///C:/src/foo.erl")
+        );
+        assert_eq!(file_uri("src/foo.erl"), None);
+
+        let log = sarif_log(Path::new("/src/app"), vec![]).to_string();
+        assert!(
+            log.contains("\"originalUriBaseIds\":{\"%SRCROOT%\":{\"uri\":\"file:///src/app/\"}}")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+-module(application).
+
+%% The subset of the OTP application API supported by releases, see application_controller
+
+-export([start/1, start/2,
+         ensure_all_started/1, ensure_all_started/2,
+         stop/1,
+         which_applications/0, loaded_applications/0,
+         get_env/2, get_env/3, get_all_env/1,
+         set_env/3, unset_env/2]).
+
+-type restart_type() :: permanent | transient | temporary.
+
+-spec start(Application) -> ok | {error, Reason} when
+      Application :: atom(),
+      Reason :: term().
+start(Application) ->
+    start(Application, temporary).
+
+%% Starts an application, whose dependencies must already be started
+-spec start(Application, Type) -> ok | {error, Reason} when
+      Application :: atom(),
+      Type :: restart_type(),
+      Reason :: term().
+start(Application, Type) ->
+    application_controller:call({start, Application, Type}).
+
+-spec ensure_all_started(Application) -> {ok, Started} | {error, Reason} when
+      Application :: atom(),
+      Started :: [atom()],
+      Reason :: term().
+ensure_all_started(Application) ->
+    ensure_all_started(Application, temporary).
+
+%% Starts an application and any of its dependencies which are not already started, returning
+%% the applications which were started, in the order they were started
+-spec ensure_all_started(Application, Type) -> {ok, Started} | {error, Reason} when
+      Application :: atom(),
+      Type :: restart_type(),
+      Started :: [atom()],
+      Reason :: term().
+ensure_all_started(Application, Type) ->
+    case ensure_started(Application, Type, []) of
+        {ok, Started} ->
+            {ok, lists:reverse(Started)};
+        {error, Reason, Started} ->
+            stop_all(Started),
+            {error, Reason}
+    end.
+
+ensure_started(Application, Type, Started) ->
+    case application_controller:call({info, Application}) of
+        {ok, _Props, true} ->
+            {ok, Started};
+        {ok, Props, false} ->
+            Deps = get_value(applications, Props, []),
+            case ensure_deps_started(Deps, Started) of
+                {ok, Started1} ->
+                    case start(Application, Type) of
+                        ok -> {ok, [Application | Started1]};
+                        {error, {already_started, _}} -> {ok, Started1};
+                        {error, Reason} -> {error, Reason, Started1}
+                    end;
+                Error ->
+                    Error
+            end;
+        undefined ->
+            {error, {not_loaded, Application}, Started}
+    end.
+
+%% Dependencies which aren't part of the release, e.g. kernel, are provided by the runtime
+ensure_deps_started([Dep | Deps], Started) ->
+    case application_controller:call({info, Dep}) of
+        undefined ->
+            ensure_deps_started(Deps, Started);
+        _ ->
+            case ensure_started(Dep, temporary, Started) of
+                {ok, Started1} -> ensure_deps_started(Deps, Started1);
+                Error -> Error
+            end
+    end;
+ensure_deps_started([], Started) ->
+    {ok, Started}.
+
+stop_all([Application | Rest]) ->
+    stop(Application),
+    stop_all(Rest);
+stop_all([]) ->
+    ok.
+
+-spec stop(Application) -> ok | {error, Reason} when
+      Application :: atom(),
+      Reason :: term().
+stop(Application) ->
+    application_controller:call({stop, Application}).
+
+%% Returns the running applications, most recently started first
+-spec which_applications() -> [{Application, Description, Vsn}] when
+      Application :: atom(),
+      Description :: string(),
+      Vsn :: string().
+which_applications() ->
+    application_controller:call(which_applications).
+
+-spec loaded_applications() -> [{Application, Description, Vsn}] when
+      Application :: atom(),
+      Description :: string(),
+      Vsn :: string().
+loaded_applications() ->
+    application_controller:call(loaded_applications).
+
+-spec get_env(Application, Par) -> undefined | {ok, Val} when
+      Application :: atom(),
+      Par :: atom(),
+      Val :: term().
+get_env(Application, Par) ->
+    application_controller:call({get_env, Application, Par}).
+
+-spec get_env(Application, Par, Def) -> Val when
+      Application :: atom(),
+      Par :: atom(),
+      Def :: term(),
+      Val :: term().
+get_env(Application, Par, Def) ->
+    case get_env(Application, Par) of
+        {ok, Val} -> Val;
+        undefined -> Def
+    end.
+
+-spec get_all_env(Application) -> Env when
+      Application :: atom(),
+      Env :: [{Par :: atom(), Val :: term()}].
+get_all_env(Application) ->
+    application_controller:call({get_all_env, Application}).
+
+-spec set_env(Application, Par, Val) -> ok when
+      Application :: atom(),
+      Par :: atom(),
+      Val :: term().
+set_env(Application, Par, Val) ->
+    application_controller:call({set_env, Application, Par, Val}).
+
+-spec unset_env(Application, Par) -> ok when
+      Application :: atom(),
+      Par :: atom().
+unset_env(Application, Par) ->
+    application_controller:call({unset_env, Application, Par}).
+
+get_value(Key, [{Key, Value} | _], _Default) -> Value;
+get_value(Key, [_ | Rest], Default) -> get_value(Key, Rest, Default);
+get_value(_Key, [], Default) -> Default.
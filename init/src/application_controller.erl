@@ -0,0 +1,316 @@
+-module(application_controller).
+
+%% The application controller.
+%%
+%% This process is started by init, registered as `application_controller', and keeps track of
+%% the applications compiled into the release, their environment, and which of them are running.
+%%
+%% Each running application with a `mod' callback has a master process, which calls
+%% `Mod:start/2', and waits for the top process it returns to exit, or for a request to stop the
+%% application. Starting and stopping are asynchronous from the point of view of the controller,
+%% so that `Mod:start/2' can itself call into the controller, e.g. to read its environment.
+
+-export([start/2, call/1]).
+
+-record(state, {%% The resource of each loaded application, i.e. {Name, Properties}
+                loaded = [] :: [{atom(), list()}],
+                %% The environment of each application
+                env = [] :: [{atom(), [{atom(), term()}]}],
+                %% Running applications, most recently started first
+                running = [] :: [{atom(), pid() | undefined, Type :: atom()}],
+                %% Callers waiting on an application to start or stop
+                pending = [] :: [{atom(), {pid(), reference()}}]}).
+
+%% Starts the controller with the given application resources, and configuration, which is a list
+%% of `{Application, [{Key, Value}]}' which overrides the environment in the resources.
+-spec start(Resources, Config) -> pid() when
+      Resources :: [{application, atom(), list()}],
+      Config :: [{atom(), [{atom(), term()}]}].
+start(Resources, Config) ->
+    Parent = self(),
+    Ref = make_ref(),
+    Pid = spawn(fun() -> init(Parent, Ref, Resources, Config) end),
+    receive
+        {Ref, started} -> Pid
+    end.
+
+%% Sends a request to the controller, and waits for its reply
+-spec call(term()) -> term().
+call(Request) ->
+    case whereis(application_controller) of
+        undefined ->
+            exit({noproc, {?MODULE, call, [Request]}});
+        Pid ->
+            %% As in gen:call/3, the monitor reference tags the request, and the call exits
+            %% if the controller goes down before replying, e.g. with noproc if it is gone
+            Mref = erlang:monitor(process, Pid),
+            Pid ! {call, {self(), Mref}, Request},
+            receive
+                {Mref, Reply} ->
+                    erlang:demonitor(Mref, [flush]),
+                    Reply;
+                {'DOWN', Mref, _, _, Reason} ->
+                    exit({Reason, {?MODULE, call, [Request]}})
+            end
+    end.
+
+init(Parent, Ref, Resources, Config) ->
+    register(application_controller, self()),
+    State0 = load(Resources, #state{}),
+    State = configure(Config, State0),
+    Parent ! {Ref, started},
+    loop(State).
+
+load([{application, Name, Props} | Rest], State = #state{loaded = Loaded, env = Env}) ->
+    AppEnv = get_value(env, Props, []),
+    load(Rest, State#state{loaded = [{Name, Props} | Loaded],
+                           env = store(Name, AppEnv, Env)});
+load([], State = #state{loaded = Loaded}) ->
+    State#state{loaded = reverse(Loaded)}.
+
+configure([{App, Pairs} | Rest], State) when is_atom(App), is_list(Pairs) ->
+    configure(Rest, set_env_pairs(App, Pairs, State));
+configure([_ | Rest], State) ->
+    configure(Rest, State);
+configure([], State) ->
+    State.
+
+set_env_pairs(App, [{Key, Value} | Rest], State) ->
+    set_env_pairs(App, Rest, set_env(App, Key, Value, State));
+set_env_pairs(App, [_ | Rest], State) ->
+    set_env_pairs(App, Rest, State);
+set_env_pairs(_App, [], State) ->
+    State.
+
+set_env(App, Key, Value, State = #state{env = Env}) ->
+    AppEnv = get_value(App, Env, []),
+    State#state{env = store(App, store(Key, Value, AppEnv), Env)}.
+
+loop(State0) ->
+    receive
+        {call, From, Request} ->
+            State = handle_call(Request, From, State0),
+            loop(State);
+        {Master, started, App, Result} ->
+            loop(handle_started(App, Master, Result, State0));
+        {_Master, stopped, App} ->
+            loop(handle_stopped(App, State0));
+        {'DOWN', _Ref, process, Master, Reason} ->
+            loop(handle_down(Master, Reason, State0));
+        _Other ->
+            loop(State0)
+    end.
+
+handle_call({start, App, Type}, From, State = #state{loaded = Loaded, running = Running}) ->
+    case find(App, Loaded) of
+        false ->
+            reply(From, {error, {not_loaded, App}}),
+            State;
+        {ok, Props} ->
+            case find(App, Running) of
+                {ok, _} ->
+                    reply(From, {error, {already_started, App}}),
+                    State;
+                false ->
+                    case not_started(get_value(applications, Props, []), State) of
+                        [Dep | _] ->
+                            reply(From, {error, {not_started, Dep}}),
+                            State;
+                        [] ->
+                            start_application(App, Type, Props, From, State)
+                    end
+            end
+    end;
+handle_call({stop, App}, From, State = #state{running = Running, pending = Pending}) ->
+    case find(App, Running) of
+        {ok, {undefined, _Type}} ->
+            reply(From, ok),
+            State#state{running = delete(App, Running)};
+        {ok, {Master, _Type}} ->
+            Master ! {stop, self()},
+            State#state{pending = [{App, From} | Pending]};
+        false ->
+            reply(From, {error, {not_started, App}}),
+            State
+    end;
+handle_call({info, App}, From, State = #state{loaded = Loaded, running = Running}) ->
+    Reply = case find(App, Loaded) of
+                {ok, Props} ->
+                    {ok, Props, find(App, Running) =/= false};
+                false ->
+                    undefined
+            end,
+    reply(From, Reply),
+    State;
+handle_call(which_applications, From, State = #state{loaded = Loaded, running = Running}) ->
+    reply(From, describe([App || {App, _, _} <- Running], Loaded)),
+    State;
+handle_call(loaded_applications, From, State = #state{loaded = Loaded}) ->
+    reply(From, describe([App || {App, _} <- Loaded], Loaded)),
+    State;
+handle_call({get_env, App, Key}, From, State = #state{env = Env}) ->
+    Reply = case find(Key, get_value(App, Env, [])) of
+                {ok, Value} -> {ok, Value};
+                false -> undefined
+            end,
+    reply(From, Reply),
+    State;
+handle_call({get_all_env, App}, From, State = #state{env = Env}) ->
+    reply(From, get_value(App, Env, [])),
+    State;
+handle_call({set_env, App, Key, Value}, From, State) ->
+    reply(From, ok),
+    set_env(App, Key, Value, State);
+handle_call({unset_env, App, Key}, From, State = #state{env = Env}) ->
+    reply(From, ok),
+    AppEnv = get_value(App, Env, []),
+    State#state{env = store(App, delete(Key, AppEnv), Env)};
+handle_call(_Request, From, State) ->
+    reply(From, {error, badarg}),
+    State.
+
+start_application(App, Type, Props, From, State = #state{running = Running, pending = Pending}) ->
+    case get_value(mod, Props, []) of
+        {Mod, Args} ->
+            Controller = self(),
+            {Master, _Ref} = spawn_monitor(fun() -> master(Controller, App, Mod, Args) end),
+            State#state{running = [{App, Master, Type} | Running],
+                        pending = [{App, From} | Pending]};
+        [] ->
+            %% Library applications have nothing to start
+            reply(From, ok),
+            State#state{running = [{App, undefined, Type} | Running]}
+    end.
+
+handle_started(App, _Master, ok, State = #state{pending = Pending}) ->
+    reply_pending(App, ok, Pending),
+    State#state{pending = delete(App, Pending)};
+handle_started(App, _Master, Error, State = #state{running = Running, pending = Pending}) ->
+    reply_pending(App, Error, Pending),
+    State#state{running = delete(App, Running), pending = delete(App, Pending)}.
+
+handle_stopped(App, State = #state{running = Running, pending = Pending}) ->
+    reply_pending(App, ok, Pending),
+    State#state{running = delete(App, Running), pending = delete(App, Pending)}.
+
+%% A master process exited, if the application is still running, its top process exited
+handle_down(Master, Reason, State = #state{running = Running, pending = Pending}) ->
+    case find_master(Master, Running) of
+        {ok, App, Type} ->
+            reply_pending(App, {error, {Reason, App}}, Pending),
+            State1 = State#state{running = delete(App, Running), pending = delete(App, Pending)},
+            exited(App, Type, Reason),
+            State1;
+        false ->
+            State
+    end.
+
+%% Like OTP, the node is shut down when a permanent application exits, or a transient application
+%% exits abnormally
+exited(App, Type, Reason) ->
+    Shutdown = case {Type, Reason} of
+                   {permanent, _} -> true;
+                   {transient, normal} -> false;
+                   {transient, _} -> true;
+                   _ -> false
+               end,
+    erlang:display({application_exited, App, Reason, Type}),
+    case Shutdown of
+        true -> erlang:halt(1);
+        false -> ok
+    end.
+
+reply_pending(App, Reply, Pending) ->
+    case find(App, Pending) of
+        {ok, From} -> reply(From, Reply);
+        false -> ok
+    end.
+
+reply({Pid, Ref}, Reply) ->
+    Pid ! {Ref, Reply},
+    ok.
+
+not_started([Dep | Deps], State = #state{loaded = Loaded, running = Running}) ->
+    %% Dependencies which aren't part of the release, e.g. kernel, are provided by the runtime
+    case {find(Dep, Loaded), find(Dep, Running)} of
+        {{ok, _}, false} -> [Dep | not_started(Deps, State)];
+        _ -> not_started(Deps, State)
+    end;
+not_started([], _State) ->
+    [].
+
+describe([App | Apps], Loaded) ->
+    Props = get_value(App, Loaded, []),
+    Description = get_value(description, Props, ""),
+    Vsn = get_value(vsn, Props, ""),
+    [{App, Description, Vsn} | describe(Apps, Loaded)];
+describe([], _Loaded) ->
+    [].
+
+%% The application master.
+%%
+%% Exits are trapped so that the master is notified when the top process it is linked to exits.
+master(Controller, App, Mod, Args) ->
+    process_flag(trap_exit, true),
+    StartMFA = {Mod, start, [normal, Args]},
+    try Mod:start(normal, Args) of
+        {ok, Pid} when is_pid(Pid) ->
+            Controller ! {self(), started, App, ok},
+            master_loop(Controller, App, Mod, Pid, []);
+        {ok, Pid, State} when is_pid(Pid) ->
+            Controller ! {self(), started, App, ok},
+            master_loop(Controller, App, Mod, Pid, State);
+        {error, Reason} ->
+            Controller ! {self(), started, App, {error, {Reason, StartMFA}}};
+        Other ->
+            Controller ! {self(), started, App, {error, {bad_return, {StartMFA, Other}}}}
+    catch
+        _:Reason ->
+            Controller ! {self(), started, App, {error, {bad_return, {StartMFA, {'EXIT', Reason}}}}}
+    end.
+
+master_loop(Controller, App, Mod, Pid, State) ->
+    receive
+        {'EXIT', Pid, Reason} ->
+            exit(Reason);
+        {stop, Controller} ->
+            exit(Pid, shutdown),
+            receive
+                {'EXIT', Pid, _} -> ok
+            after 5000 ->
+                exit(Pid, kill)
+            end,
+            catch Mod:stop(State),
+            Controller ! {self(), stopped, App};
+        _Other ->
+            master_loop(Controller, App, Mod, Pid, State)
+    end.
+
+%% Helpers for lists of {Key, Value}, or {Key, Value, Extra} tuples
+
+find(Key, [{Key, Value} | _]) -> {ok, Value};
+find(Key, [{Key, Value, Extra} | _]) -> {ok, {Value, Extra}};
+find(Key, [_ | Rest]) -> find(Key, Rest);
+find(_Key, []) -> false.
+
+find_master(Master, [{App, Master, Type} | _]) -> {ok, App, Type};
+find_master(Master, [_ | Rest]) -> find_master(Master, Rest);
+find_master(_Master, []) -> false.
+
+get_value(Key, List, Default) ->
+    case find(Key, List) of
+        {ok, Value} -> Value;
+        false -> Default
+    end.
+
+store(Key, Value, [{Key, _} | Rest]) -> [{Key, Value} | Rest];
+store(Key, Value, [Other | Rest]) -> [Other | store(Key, Value, Rest)];
+store(Key, Value, []) -> [{Key, Value}].
+
+delete(Key, [{Key, _} | Rest]) -> Rest;
+delete(Key, [{Key, _, _} | Rest]) -> Rest;
+delete(Key, [Other | Rest]) -> [Other | delete(Key, Rest)];
+delete(_Key, []) -> [].
+
+reverse(L) ->
+    lists:reverse(L).
@@ -1,8 +1,16 @@
 -module(file).
 
--export([native_name_encoding/0]).
--nifs([native_name_encoding/0]).
+-export([native_name_encoding/0, consult/1]).
+-nifs([native_name_encoding/0, consult/1]).
 
 -spec native_name_encoding() -> latin1 | utf8.
 native_name_encoding() ->
     erlang:nif_error(undef).
+
+%% Reads the Erlang terms in a file, each of which must be terminated by a period
+-spec consult(Filename) -> {ok, Terms} | {error, Reason} when
+      Filename :: file:name_all(),
+      Terms :: [term()],
+      Reason :: file:posix() | badarg | {Line :: pos_integer(), Mod :: module(), Term :: term()}.
+consult(_) ->
+    erlang:nif_error(undef).
@@ -0,0 +1,28 @@
+-module(firefly_prof).
+
+-export([start/0, start/1, stop/0]).
+-nifs([start/1, stop/0]).
+
+-type option() :: calls
+                | sample
+                | {sample, IntervalMs :: pos_integer()}
+                | {procs, all | [pid()]}
+                | {file, file:filename()}.
+
+%% Starts counting the calls, reductions and time spent in each function, in all processes
+-spec start() -> ok | {error, already_started}.
+start() ->
+    start([]).
+
+%% Starts the profiler, `calls' counts calls, reductions and time spent in each function, while
+%% `sample' periodically samples the call stacks of running processes, producing folded stacks
+%% for use with flamegraph tools. The results are written to standard error unless a file is given.
+-spec start(Options) -> ok | {error, already_started} when
+      Options :: [option()].
+start(_) ->
+    erlang:nif_error(undef).
+
+%% Stops the profiler and writes its results
+-spec stop() -> ok | {error, not_started | file:posix()}.
+stop() ->
+    erlang:nif_error(undef).
@@ -1,6 +1,7 @@
 -module(init).
 
--export([boot/1]).
+-export([boot/1, stop/0, stop/1]).
+-nifs([on_load_functions/0]).
 
 -spec boot(BootArgs) -> no_return() when
       BootArgs :: [binary()].
@@ -9,18 +10,174 @@ boot(BootArgs) ->
     Start = map(fun prepare_run_args/1, Start0),
     boot(Start, Flags, Args).
 
-boot(Start, Flags, Args) ->
-    erlang:display(Start),
-    erlang:display(Flags),
-    erlang:display(Args),
+%% Starts the application controller, then the root application of the release, if there is one,
+%% along with its dependencies, and finally evaluates the -s and -run arguments
+%%
+%% Without a root application, the system halts as soon as init returns, otherwise it runs until
+%% init:stop/0,1 is called, or the root application exits.
+boot(Start, Flags, _Args) ->
+    register(init, self()),
+    run_on_load(on_load_functions()),
+    {Root, Resources, Config0} = release(),
+    Config = load_config(Flags, include_config(Config0, [])),
+    application_controller:start(Resources, Config),
+    start_root(Root),
+    start_em(Start),
+    case Root of
+        undefined -> ok;
+        _ -> wait()
+    end.
+
+%% Stops all running applications, then halts the system
+-spec stop() -> ok.
+stop() ->
+    stop(0).
+
+-spec stop(Status) -> ok when
+      Status :: non_neg_integer().
+stop(Status) ->
+    init ! {stop, Status},
     ok.
 
+wait() ->
+    receive
+        {stop, Status} ->
+            stop_applications(application:which_applications()),
+            erlang:halt(Status);
+        _Other ->
+            wait()
+    end.
+
+stop_applications([{App, _, _} | Apps]) ->
+    application:stop(App),
+    stop_applications(Apps);
+stop_applications([]) ->
+    ok.
+
+%% Runs the -on_load function of each module, which must return ok
+%%
+%% Modules are loaded along with the system, so a failing on_load function can't prevent its module
+%% from being loaded as it would in OTP, instead the failure is reported and booting continues.
+run_on_load([{M, F} | Rest]) ->
+    try M:F() of
+        ok -> ok;
+        Other -> erlang:display({init, on_load_function_failed, M, Other})
+    catch
+        Class:Reason -> erlang:display({init, on_load_function_failed, M, {Class, Reason}})
+    end,
+    run_on_load(Rest);
+run_on_load([]) ->
+    ok.
+
+%% Returns {Module, Function} for the -on_load function of each module
+on_load_functions() ->
+    erlang:nif_error(undef).
+
+%% The release metadata embedded by the compiler, see firefly_release
+release() ->
+    try
+        {firefly_release:root(), firefly_release:applications(), firefly_release:config()}
+    catch
+        error:undef -> {undefined, [], []}
+    end.
+
+%% Applies the configuration files given with -config, in order, on top of sys.config
+load_config([{config, Files} | Flags], Config) ->
+    load_config(Flags, load_config_files(Files, Config));
+load_config([_ | Flags], Config) ->
+    load_config(Flags, Config);
+load_config([], Config) ->
+    Config.
+
+load_config_files([File | Files], Config0) ->
+    Config = merge_config(Config0, consult_config(config_file(b2s(File)))),
+    load_config_files(Files, Config);
+load_config_files([], Config) ->
+    Config.
+
+config_file(Name) ->
+    case reverse(Name) of
+        "gifnoc." ++ _ -> Name;
+        _ -> Name ++ ".config"
+    end.
+
+%% Config files contain a single list, where elements are either {Application, Env} or the names
+%% of further config files to include
+consult_config(File) ->
+    case file:consult(File) of
+        {ok, [Config]} when is_list(Config) ->
+            include_config(Config, []);
+        {ok, _} ->
+            boot_error({bad_config, File});
+        {error, Reason} ->
+            boot_error({File, Reason})
+    end.
+
+include_config([Include = [C | _] | Config], Acc) when is_integer(C) ->
+    include_config(Config, merge_config(Acc, consult_config(config_file(Include))));
+include_config([AppConfig | Config], Acc) ->
+    include_config(Config, merge_config(Acc, [AppConfig]));
+include_config([], Acc) ->
+    Acc.
+
+%% Later values for the same application and key override earlier ones
+merge_config(Config, [{App, Env} | Rest]) ->
+    merge_config(merge_app_config(App, Env, Config), Rest);
+merge_config(Config, [_ | Rest]) ->
+    merge_config(Config, Rest);
+merge_config(Config, []) ->
+    Config.
+
+merge_app_config(App, Env, [{App, Env0} | Rest]) ->
+    [{App, merge_env(Env0, Env)} | Rest];
+merge_app_config(App, Env, [Other | Rest]) ->
+    [Other | merge_app_config(App, Env, Rest)];
+merge_app_config(App, Env, []) ->
+    [{App, Env}].
+
+merge_env(Env, [{Key, Value} | Rest]) ->
+    merge_env(store(Key, Value, Env), Rest);
+merge_env(Env, [_ | Rest]) ->
+    merge_env(Env, Rest);
+merge_env(Env, []) ->
+    Env.
+
+store(Key, Value, [{Key, _} | Rest]) -> [{Key, Value} | Rest];
+store(Key, Value, [Other | Rest]) -> [Other | store(Key, Value, Rest)];
+store(Key, Value, []) -> [{Key, Value}].
+
+start_root(undefined) ->
+    ok;
+start_root(Root) ->
+    case application:ensure_all_started(Root, permanent) of
+        {ok, _} -> ok;
+        {error, Reason} -> boot_error({could_not_start, Root, Reason})
+    end.
+
+start_em([[M] | Start]) ->
+    M:start(),
+    start_em(Start);
+start_em([[M, F] | Start]) ->
+    M:F(),
+    start_em(Start);
+start_em([[M, F | Args] | Start]) ->
+    M:F(Args),
+    start_em(Start);
+start_em([]) ->
+    ok.
+
+boot_error(Reason) ->
+    erlang:display({init, boot_failed, Reason}),
+    erlang:halt(1).
+
 prepare_run_args({_, L=[]}) ->
     bs2as(L);
 prepare_run_args({_, L=[_]}) ->
     bs2as(L);
 prepare_run_args({s, [M,F|Args]}) ->
-    [b2a(M), b2a(F) | bs2as(Args)].
+    [b2a(M), b2a(F) | bs2as(Args)];
+prepare_run_args({run, [M,F|Args]}) ->
+    [b2a(M), b2a(F) | map(fun b2s/1, Args)].
 
 b2a(Bin) when is_binary(Bin) ->
     list_to_atom(b2s(Bin));
@@ -0,0 +1,97 @@
+-module(prim_file).
+
+-export([open/2, read/2, write/2, pread/3, pwrite/3, position/2, close/1]).
+-export([read_file/1, write_file/2, read_file_info/1, list_dir/1,
+         make_dir/1, del_file/1, rename/2, get_cwd/0]).
+-nifs([open/2, read/2, write/2, pread/3, pwrite/3, position/2, close/1,
+       read_file/1, write_file/2, read_file_info/1, list_dir/1,
+       make_dir/1, del_file/1, rename/2, get_cwd/0]).
+
+-type fd() :: {file_descriptor, prim_file, reference()}.
+-type name() :: string() | atom() | binary().
+-type posix() :: atom().
+-type location() :: integer() | {bof | cur | eof, integer()} | bof | cur | eof.
+
+-spec open(Name, Modes) -> {ok, fd()} | {error, posix() | badarg} when
+      Name :: name(),
+      Modes :: [atom() | tuple()].
+open(_, _) ->
+    erlang:nif_error(undef).
+
+-spec read(Fd, Size) -> {ok, string() | binary()} | eof | {error, posix()} when
+      Fd :: fd(),
+      Size :: non_neg_integer().
+read(_, _) ->
+    erlang:nif_error(undef).
+
+-spec write(Fd, Data) -> ok | {error, posix()} when
+      Fd :: fd(),
+      Data :: iodata().
+write(_, _) ->
+    erlang:nif_error(undef).
+
+-spec pread(Fd, Location, Size) -> {ok, string() | binary()} | eof | {error, posix()} when
+      Fd :: fd(),
+      Location :: location(),
+      Size :: non_neg_integer().
+pread(_, _, _) ->
+    erlang:nif_error(undef).
+
+-spec pwrite(Fd, Location, Data) -> ok | {error, posix()} when
+      Fd :: fd(),
+      Location :: location(),
+      Data :: iodata().
+pwrite(_, _, _) ->
+    erlang:nif_error(undef).
+
+-spec position(Fd, Location) -> {ok, non_neg_integer()} | {error, posix()} when
+      Fd :: fd(),
+      Location :: location().
+position(_, _) ->
+    erlang:nif_error(undef).
+
+-spec close(Fd) -> ok | {error, posix()} when
+      Fd :: fd().
+close(_) ->
+    erlang:nif_error(undef).
+
+-spec read_file(Name) -> {ok, binary()} | {error, posix()} when
+      Name :: name().
+read_file(_) ->
+    erlang:nif_error(undef).
+
+-spec write_file(Name, Data) -> ok | {error, posix()} when
+      Name :: name(),
+      Data :: iodata().
+write_file(_, _) ->
+    erlang:nif_error(undef).
+
+-spec read_file_info(Name) -> {ok, tuple()} | {error, posix()} when
+      Name :: name().
+read_file_info(_) ->
+    erlang:nif_error(undef).
+
+-spec list_dir(Name) -> {ok, [string()]} | {error, posix()} when
+      Name :: name().
+list_dir(_) ->
+    erlang:nif_error(undef).
+
+-spec make_dir(Name) -> ok | {error, posix()} when
+      Name :: name().
+make_dir(_) ->
+    erlang:nif_error(undef).
+
+-spec del_file(Name) -> ok | {error, posix()} when
+      Name :: name().
+del_file(_) ->
+    erlang:nif_error(undef).
+
+-spec rename(Source, Destination) -> ok | {error, posix()} when
+      Source :: name(),
+      Destination :: name().
+rename(_, _) ->
+    erlang:nif_error(undef).
+
+-spec get_cwd() -> {ok, string()} | {error, posix()}.
+get_cwd() ->
+    erlang:nif_error(undef).
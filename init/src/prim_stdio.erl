@@ -0,0 +1,19 @@
+-module(prim_stdio).
+
+-export([write/3, read_line/1]).
+-nifs([write/3, read_line/1]).
+
+-type encoding() :: latin1 | unicode | utf8.
+
+-spec write(Chars, InEncoding, OutEncoding) -> ok | {error, Reason} when
+      Chars :: unicode:chardata() | unicode:latin1_chardata(),
+      InEncoding :: encoding(),
+      OutEncoding :: encoding(),
+      Reason :: atom() | {no_translation, unicode, latin1}.
+write(_, _, _) ->
+    erlang:nif_error(undef).
+
+-spec read_line(Encoding) -> {ok, string()} | eof | {error, atom()} when
+      Encoding :: encoding().
+read_line(_) ->
+    erlang:nif_error(undef).
@@ -1,7 +1,8 @@
 -module(unicode).
 
--export([characters_to_list/1, characters_to_list/2]).
--nifs([characters_to_list/2]).
+-export([characters_to_list/1, characters_to_list/2,
+         characters_to_binary/1, characters_to_binary/2]).
+-nifs([characters_to_list/2, characters_to_binary/2]).
 
 -export_type([chardata/0, charlist/0, encoding/0, external_chardata/0,
               external_charlist/0, latin1_char/0, latin1_chardata/0,
@@ -59,6 +60,32 @@ characters_to_list(ML) ->
             error_with_info(Reason, [ML])
     end.
 
+-spec characters_to_binary(Data, InEncoding) -> Result when
+      Data :: latin1_chardata() | chardata() | external_chardata(),
+      InEncoding :: encoding(),
+      Result :: unicode_binary()
+              | {error, unicode_binary(), RestData}
+              | {incomplete, unicode_binary(), binary()},
+      RestData :: latin1_chardata() | chardata() | external_chardata().
+
+characters_to_binary(_, _) ->
+    erlang:nif_error(undef).
+
+-spec characters_to_binary(Data) -> Result when
+      Data :: latin1_chardata() | chardata() | external_chardata(),
+      Result :: unicode_binary()
+              | {error, unicode_binary(), RestData}
+              | {incomplete, unicode_binary(), binary()},
+      RestData :: latin1_chardata() | chardata() | external_chardata().
+
+characters_to_binary(ML) ->
+    try
+        unicode:characters_to_binary(ML, unicode)
+    catch
+        error:Reason ->
+            error_with_info(Reason, [ML])
+    end.
+
 %% We must inline these functions so that the stacktrace points to
 %% the correct function.
 -compile({inline, [error_with_info/2]}).
@@ -0,0 +1,300 @@
+-module(user).
+
+%% The I/O server for standard input and output.
+%%
+%% This process is started by the runtime before init, registered as `user', and
+%% made the group leader of init, and by extension, every process spawned by it.
+%% It speaks the Erlang I/O protocol, reading and writing the real stdin/stdout
+%% via the prim_stdio NIFs, which perform the actual I/O off of the scheduler.
+%%
+%% Reading a line blocks until input is available, so it is done by a helper
+%% process, the reader, which sends each line back as a message. Input requests
+%% which cannot be answered from the buffer are queued until then, in order,
+%% while output and option requests keep being answered in the meantime.
+
+-export([server/0]).
+
+-record(state, {binary = false :: boolean(),
+                encoding = unicode :: latin1 | unicode,
+                %% Characters read from stdin but not yet consumed by a request
+                buffer = [] :: [char()],
+                reader :: pid() | undefined,
+                %% Whether a line has been requested from the reader
+                reading = false :: boolean(),
+                %% Whether the end of input was reached while serving the
+                %% pending requests
+                eof = false :: boolean(),
+                %% Input requests waiting for more input, oldest first
+                pending = [] :: [{pid(), term(), term()}]}).
+
+-spec server() -> no_return().
+server() ->
+    register(user, self()),
+    Server = self(),
+    Reader = spawn_link(fun() -> reader(Server) end),
+    loop(#state{reader = Reader}).
+
+loop(State = #state{reader = Reader}) ->
+    receive
+        {io_request, From, ReplyAs, Req} when is_pid(From) ->
+            loop(io_request(From, ReplyAs, Req, State));
+        {Reader, Result} ->
+            loop(input(Result, State#state{reading = false}));
+        _Other ->
+            loop(State)
+    end.
+
+%% Reads a line from stdin whenever the server asks for one
+reader(Server) ->
+    receive
+        {read, Enc} ->
+            Server ! {self(), prim_stdio:read_line(Enc)},
+            reader(Server)
+    end.
+
+io_request(From, ReplyAs, Req, State = #state{pending = []}) ->
+    serve(State#state{pending = [{From, ReplyAs, Req}]});
+io_request(From, ReplyAs, Req, State0) ->
+    case input_request(Req) of
+        true ->
+            Pending = State0#state.pending ++ [{From, ReplyAs, Req}],
+            State0#state{pending = Pending};
+        false ->
+            %% Only input requests wait for input
+            {Reply, State} = request(Req, State0),
+            From ! {io_reply, ReplyAs, Reply},
+            State
+    end.
+
+%% Serves the pending requests in order, until one needs more input
+serve(State = #state{pending = []}) ->
+    State;
+serve(State0 = #state{pending = [{From, ReplyAs, Req} | Pending]}) ->
+    case request(Req, State0#state{pending = Pending}) of
+        {more, Cont, State} ->
+            read(State#state{pending = [{From, ReplyAs, Cont} | Pending]});
+        {Reply, State} ->
+            From ! {io_reply, ReplyAs, Reply},
+            %% The end of input is only reported to the request it was read for
+            serve(State#state{eof = false})
+    end.
+
+input({ok, Line}, State = #state{buffer = Buffer}) ->
+    serve(State#state{buffer = lists:reverse(lists:reverse(Buffer), Line)});
+input(eof, State) ->
+    serve(State#state{eof = true});
+input({error, _} = Err, State = #state{pending = [{From, ReplyAs, _} | Pending]}) ->
+    From ! {io_reply, ReplyAs, Err},
+    serve(State#state{pending = Pending});
+input({error, _}, State) ->
+    State.
+
+%% Requests a line from the reader, unless one was already requested
+read(State = #state{reading = true}) ->
+    State;
+read(State = #state{reader = Reader, encoding = Enc}) ->
+    Reader ! {read, Enc},
+    State#state{reading = true}.
+
+%% Whether the request must wait on the input requests queued before it
+input_request({requests, [Req|Reqs]}) ->
+    input_request(Req) orelse input_request({requests, Reqs});
+input_request(Req) when is_tuple(Req), tuple_size(Req) > 0 ->
+    case element(1, Req) of
+        get_line -> true;
+        get_chars -> true;
+        get_until -> true;
+        '$get_until' -> true;
+        _ -> false
+    end;
+input_request(_Req) ->
+    false.
+
+%% Output requests
+request({put_chars, Enc, Chars}, State) ->
+    {put_chars(Chars, Enc, State), State};
+request({put_chars, Enc, M, F, As}, State) ->
+    try apply(M, F, As) of
+        Chars -> {put_chars(Chars, Enc, State), State}
+    catch
+        _:_ -> {{error, F}, State}
+    end;
+request({put_chars, Chars}, State) ->
+    request({put_chars, latin1, Chars}, State);
+request({put_chars, M, F, As}, State) ->
+    request({put_chars, latin1, M, F, As}, State);
+%% Input requests
+request({get_line, Enc, Prompt}, State) ->
+    get_line(Enc, Prompt, State);
+request({get_chars, Enc, Prompt, N}, State) when is_integer(N), N >= 0 ->
+    get_chars(Enc, Prompt, N, State);
+request({get_until, Enc, Prompt, M, F, As}, State) ->
+    get_until(Enc, Prompt, M, F, As, State);
+request({get_line, Prompt}, State) ->
+    request({get_line, latin1, Prompt}, State);
+request({get_chars, Prompt, N}, State) ->
+    request({get_chars, latin1, Prompt, N}, State);
+request({get_until, Prompt, M, F, As}, State) ->
+    request({get_until, latin1, Prompt, M, F, As}, State);
+%% The continuation of a get_until request waiting for more input
+request({'$get_until', Enc, M, F, As, Cont}, State) ->
+    get_until(Enc, M, F, As, Cont, State);
+%% Options
+request({setopts, Opts}, State0) when is_list(Opts) ->
+    case setopts(Opts, State0) of
+        {ok, State} -> {ok, State};
+        error -> {{error, enotsup}, State0}
+    end;
+request(getopts, State) ->
+    {[{binary, State#state.binary}, {encoding, State#state.encoding}], State};
+request({get_geometry, _}, State) ->
+    {{error, enotsup}, State};
+request({requests, Reqs}, State) when is_list(Reqs) ->
+    requests(Reqs, {ok, State});
+request(_Other, State) ->
+    {{error, request}, State}.
+
+requests([], Result) ->
+    Result;
+requests([Req|Reqs], {ok, State0}) ->
+    case request(Req, State0) of
+        {more, Cont, State} ->
+            {more, {requests, [Cont|Reqs]}, State};
+        Result ->
+            requests(Reqs, Result)
+    end;
+requests(_, Result) ->
+    Result.
+
+setopts([], State) ->
+    {ok, State};
+setopts([binary|Opts], State) ->
+    setopts(Opts, State#state{binary = true});
+setopts([list|Opts], State) ->
+    setopts(Opts, State#state{binary = false});
+setopts([{binary, Bool}|Opts], State) when is_boolean(Bool) ->
+    setopts(Opts, State#state{binary = Bool});
+setopts([{encoding, latin1}|Opts], State) ->
+    setopts(Opts, State#state{encoding = latin1});
+setopts([{encoding, Enc}|Opts], State) when Enc =:= unicode; Enc =:= utf8 ->
+    setopts(Opts, State#state{encoding = unicode});
+setopts(_, _State) ->
+    error.
+
+put_chars(Chars, Enc, State) ->
+    try
+        prim_stdio:write(Chars, Enc, State#state.encoding)
+    catch
+        error:badarg -> {error, badarg}
+    end.
+
+prompt('', _State) ->
+    ok;
+prompt(Prompt, State) when is_atom(Prompt) ->
+    put_chars(atom_to_binary(Prompt), unicode, State);
+prompt({format, Format, Args}, State) ->
+    try io_lib:format(Format, Args) of
+        Chars -> put_chars(Chars, unicode, State)
+    catch
+        _:_ -> ok
+    end;
+prompt(Prompt, State) ->
+    put_chars(Prompt, unicode, State).
+
+%% Input requests return `{more, Cont, State}' when the buffer holds too little
+%% input, where `Cont' is the request to retry once more input is read, or the
+%% end of input is reached.
+get_line(Enc, Prompt, State) ->
+    prompt(Prompt, State),
+    case collect_line(State#state.buffer, []) of
+        {Line, Rest} ->
+            {result(Line, Enc, State), State#state{buffer = Rest}};
+        more when not State#state.eof ->
+            {more, {get_line, Enc, ''}, State};
+        more when State#state.buffer =:= [] ->
+            {eof, State};
+        more ->
+            Line = State#state.buffer,
+            {result(Line, Enc, State), State#state{buffer = []}}
+    end.
+
+collect_line([$\n|Rest], Acc) ->
+    {lists:reverse(Acc, [$\n]), Rest};
+collect_line([C|Rest], Acc) ->
+    collect_line(Rest, [C|Acc]);
+collect_line([], _Acc) ->
+    more.
+
+get_chars(Enc, Prompt, N, State) ->
+    prompt(Prompt, State),
+    case take(N, State#state.buffer, []) of
+        {Chars, Rest} ->
+            {result(Chars, Enc, State), State#state{buffer = Rest}};
+        more when not State#state.eof ->
+            {more, {get_chars, Enc, '', N}, State};
+        more when State#state.buffer =:= [] ->
+            {eof, State};
+        more ->
+            Chars = State#state.buffer,
+            {result(Chars, Enc, State), State#state{buffer = []}}
+    end.
+
+take(0, Rest, Acc) ->
+    {lists:reverse(Acc), Rest};
+take(N, [C|Rest], Acc) ->
+    take(N - 1, Rest, [C|Acc]);
+take(_N, [], _Acc) ->
+    more.
+
+get_until(Enc, Prompt, M, F, As, State) ->
+    prompt(Prompt, State),
+    get_until(Enc, M, F, As, [], State).
+
+get_until(Enc, M, F, As, Cont, State = #state{buffer = [], eof = false}) ->
+    {more, {'$get_until', Enc, M, F, As, Cont}, State};
+get_until(Enc, M, F, As, Cont, State = #state{buffer = [], eof = true}) ->
+    get_until(Enc, M, F, As, Cont, eof, State);
+get_until(Enc, M, F, As, Cont, State = #state{buffer = Buffer}) ->
+    get_until(Enc, M, F, As, Cont, Buffer, State#state{buffer = []}).
+
+get_until(Enc, M, F, As, Cont0, Data, State) ->
+    try apply(M, F, [Cont0, Data | As]) of
+        {done, Result, Rest} ->
+            {Result, State#state{buffer = rest(Rest)}};
+        {more, _} when Data =:= eof ->
+            {eof, State};
+        {more, Cont} ->
+            {more, {'$get_until', Enc, M, F, As, Cont}, State}
+    catch
+        _:_ -> {{error, F}, State}
+    end.
+
+rest(eof) ->
+    [];
+rest(Rest) when is_binary(Rest) ->
+    unicode:characters_to_list(Rest, unicode);
+rest(Rest) when is_list(Rest) ->
+    Rest.
+
+%% Converts the characters read to the representation expected by the client
+result(Chars, latin1, #state{binary = false}) ->
+    case latin1(Chars) of
+        true -> Chars;
+        false -> {error, {no_translation, unicode, latin1}}
+    end;
+result(Chars, _Enc, #state{binary = false}) ->
+    Chars;
+result(Chars, latin1, #state{binary = true}) ->
+    case latin1(Chars) of
+        true -> iolist_to_binary(Chars);
+        false -> {error, {no_translation, unicode, latin1}}
+    end;
+result(Chars, _Enc, #state{binary = true}) ->
+    unicode:characters_to_binary(Chars, unicode).
+
+latin1([C|Rest]) when C =< 255 ->
+    latin1(Rest);
+latin1([_|_]) ->
+    false;
+latin1([]) ->
+    true.
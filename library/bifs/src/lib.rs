@@ -0,0 +1,7 @@
+#![no_std]
+
+/// The functions implemented natively by the runtime, as `module:function/arity`.
+///
+/// The runtime uses this to populate its dispatch table, and the compiler uses it to determine
+/// which remote functions exist without a definition in Erlang.
+pub const BIFS: &'static [&'static str] = include!("bifs.in");
@@ -312,6 +312,17 @@ where
         }
     }
 
+    pub fn mark_as_on_load(&mut self) {
+        match self.builder.code.function_mut(self.function) {
+            Function::Bytecode {
+                ref mut is_on_load, ..
+            } => {
+                *is_on_load = true;
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     fn alloc_register(&mut self) -> Register {
         let next = self
@@ -1949,31 +1960,16 @@ where
             block.offset = block_offset;
             // Append code
             self.builder.code.code.append(&mut block.code);
-            // Insert corresponding debug info by calculating ranges of instructions covered by the
-            // same location
+            // Insert corresponding debug info for the start of each range of instructions covered
+            // by the same location. Instructions without a location of their own are attributed
+            // to the nearest preceding one when resolved.
             let mut range_loc = None;
-            let mut range_start = block_offset;
             for (i, loc) in block.locations.iter().copied().enumerate() {
-                match loc {
-                    None => {
-                        if let Some(prev_id) = range_loc {
-                            self.builder
-                                .code
-                                .set_instruction_location(range_start, prev_id);
-                        }
-                        range_start = range_start + i;
-                        range_loc = None;
-                    }
-                    Some(id) => {
-                        if let Some(prev_id) = range_loc {
-                            if prev_id == id {
-                                continue;
-                            }
-                            self.builder
-                                .code
-                                .set_instruction_location(range_start, prev_id);
-                        }
-                        range_start = range_start + i;
+                if let Some(id) = loc {
+                    if range_loc != Some(id) {
+                        self.builder
+                            .code
+                            .set_instruction_location(block_offset + i, id);
                         range_loc = Some(id);
                     }
                 }
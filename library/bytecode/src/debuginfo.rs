@@ -139,6 +139,37 @@ impl DebugInfoTable {
         self.location_to_source_location(*id)
     }
 
+    /// Like `offset_to_source_location`, but returns the compressed [`Location`]
+    pub(super) fn offset_to_location(&self, fp: usize, ip: usize) -> Option<Location> {
+        let mut iter = self.offsets.range(fp..=ip);
+        let (_, id) = iter.next_back()?;
+        self.locations.get(*id as usize).copied()
+    }
+
+    /// Returns the name of the source file with the given id
+    ///
+    /// NOTE: This function will panic if `id` is not a valid file id
+    #[inline]
+    pub fn file(&self, id: FileId) -> &str {
+        &self.files[id as usize]
+    }
+
+    /// Returns an iterator over all source files known to this table, with their ids
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &str)> + '_ {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(id, file)| (id as FileId, &**file))
+    }
+
+    /// Returns an iterator over each instruction offset at which a new source location begins,
+    /// in offset order
+    pub fn offsets(&self) -> impl Iterator<Item = (usize, Location)> + '_ {
+        self.offsets
+            .iter()
+            .map(|(offset, id)| (*offset, self.locations[*id as usize]))
+    }
+
     #[inline]
     pub(super) fn function_pointer_to_source_location(&self, fp: usize) -> Option<SourceLocation> {
         self.location_to_source_location(self.offsets.get(&fp).copied()?)
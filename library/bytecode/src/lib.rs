@@ -43,6 +43,7 @@ use alloc::{vec, vec::Vec};
 use core::assert_matches::assert_matches;
 use core::fmt;
 use core::mem;
+use core::ops::Range;
 use core::ptr::{self, NonNull};
 use core::str;
 
@@ -199,6 +200,8 @@ pub enum Function<A: Atom> {
     Bytecode {
         id: FunId,
         is_nif: bool,
+        /// Set if this function is named by the `-on_load` attribute of its module
+        is_on_load: bool,
         mfa: ModuleFunctionArity<A>,
         offset: usize,
         frame_size: usize,
@@ -226,12 +229,13 @@ impl<A: Atom> writer::Encode<A> for Function<A> {
             Self::Bytecode {
                 id: _id,
                 is_nif,
+                is_on_load,
                 mfa,
                 offset,
                 frame_size,
             } => {
                 writer.write_byte(2)?;
-                writer.write_byte(*is_nif as u8)?;
+                writer.write_byte(*is_nif as u8 | ((*is_on_load as u8) << 1))?;
                 writer.write_atom(mfa.module)?;
                 writer.write_atom(mfa.function)?;
                 writer.write_byte(mfa.arity)?;
@@ -265,7 +269,9 @@ impl<A: Atom, T: AtomTable<Atom = A>> reader::Decode<A, T> for Function<A> {
                 Ok(Self::Bif { id: 0, mfa })
             }
             2 => {
-                let is_nif = reader.read_byte().map_err(reader::eof_to_invalid)? != 0;
+                let flags = reader.read_byte().map_err(reader::eof_to_invalid)?;
+                let is_nif = flags & 1 != 0;
+                let is_on_load = flags & 2 != 0;
                 let module_offset = reader.read_integer().map_err(reader::eof_to_invalid)?;
                 let module = reader.atom_from_offset(module_offset);
                 let function_offset = reader.read_integer().map_err(reader::eof_to_invalid)?;
@@ -281,6 +287,7 @@ impl<A: Atom, T: AtomTable<Atom = A>> reader::Decode<A, T> for Function<A> {
                 Ok(Self::Bytecode {
                     id: 0,
                     is_nif,
+                    is_on_load,
                     mfa,
                     offset,
                     frame_size,
@@ -440,7 +447,7 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
     /// Validation ensures that no function definition conflicts with those defined in other modules
     pub fn link(&mut self, mut other: Vec<Self>) -> Result<(), InvalidBytecodeError<A>> {
         use self::ops::{Call, CallNative, CallStatic, Enter, EnterNative, EnterStatic};
-        use self::ops::{Closure, FuncInfo, LoadAtom, LoadBinary, LoadBitstring};
+        use self::ops::{Closure, FuncInfo, LoadAtom, LoadBinary, LoadBitstring, Spawn3};
 
         struct ModuleMap {
             range: core::ops::Range<usize>,
@@ -463,8 +470,14 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
                 self.atoms.get_or_insert(s).unwrap();
             }
             self.binaries.append(&module.binaries);
+            // The atoms of `module` are owned by its atom table, so they must be replaced with
+            // those of `self` in any function metadata carried over
+            let atoms = &mut self.atoms;
             self.functions
-                .append(&module.functions, current_ix_offset)?;
+                .append(&module.functions, current_ix_offset, |atom| {
+                    let s = unsafe { str::from_utf8_unchecked(atom.as_bytes()) };
+                    atoms.get_or_insert(s).unwrap()
+                })?;
             self.debug_info
                 .append(&module.debug_info, current_ix_offset);
             self.code.append(&mut module.code);
@@ -489,7 +502,7 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
                                 offset: *offset,
                             });
                         }
-                        Function::Bytecode { mfa, .. } => {
+                        Function::Bytecode { offset: 0, mfa, .. } => {
                             return Err(InvalidBytecodeError::IncompleteFunction(*mfa));
                         }
                         Function::Native { arity, .. } => {
@@ -513,7 +526,7 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
                         } if *offset > 0 => {
                             *op = Opcode::Enter(Enter { offset: *offset });
                         }
-                        Function::Bytecode { mfa, .. } => {
+                        Function::Bytecode { offset: 0, mfa, .. } => {
                             return Err(InvalidBytecodeError::IncompleteFunction(*mfa));
                         }
                         Function::Native { arity, .. } => {
@@ -536,15 +549,17 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
         // and binary/bitstring literals.
         let remap_callee = |callee: FunId, module_index: usize| {
             let module = &other[module_index];
-            self.functions
-                .find_by_mfa(module.functions.get(callee).mfa().unwrap())
-                .unwrap()
-                .id()
+            let function = match module.functions.get(callee) {
+                Function::Native { name, .. } => self.functions.find_by_name(*name),
+                function => self.functions.find_by_mfa(function.mfa().unwrap()),
+            };
+            function.unwrap().id()
         };
         let mut module_map_iter = module_map.iter();
-        let mut current_module = module_map_iter.next().unwrap();
+        let Some(mut current_module) = module_map_iter.next() else { return Ok(()) };
         for (i, op) in rest.iter_mut().enumerate() {
-            if i >= current_module.range.end {
+            // The module ranges are absolute offsets in the merged code
+            if start + i >= current_module.range.end {
                 current_module = module_map_iter.next().unwrap();
             }
             match op {
@@ -591,7 +606,7 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
                                 offset: *offset,
                             });
                         }
-                        Function::Bytecode { mfa, .. } => {
+                        Function::Bytecode { offset: 0, mfa, .. } => {
                             return Err(InvalidBytecodeError::IncompleteFunction(*mfa));
                         }
                         Function::Native { arity, .. } => {
@@ -616,7 +631,7 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
                         } if *offset > 0 => {
                             *op = Opcode::Enter(Enter { offset: *offset });
                         }
-                        Function::Bytecode { mfa, .. } => {
+                        Function::Bytecode { offset: 0, mfa, .. } => {
                             return Err(InvalidBytecodeError::IncompleteFunction(*mfa));
                         }
                         Function::Native { arity, .. } => {
@@ -636,6 +651,9 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
                     let new_function = remap_callee(*function, current_module.index);
                     *function = new_function;
                 }
+                Opcode::Spawn3(Spawn3 { ref mut fun, .. }) => {
+                    *fun = remap_callee(*fun, current_module.index);
+                }
                 Opcode::LoadAtom(LoadAtom { ref mut value, .. }) => {
                     let name = unsafe { str::from_utf8_unchecked(value.as_bytes()) };
                     let new_value = self.atoms.get_or_insert(name).unwrap();
@@ -775,6 +793,31 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
         self.functions.locate(ip)
     }
 
+    /// Returns true if `ip` is one of the stub instructions used for exits and traps, which every
+    /// bytecode module begins with (see `Builder::new`), or is out of bounds
+    ///
+    /// Stubs do not belong to any function, so have no source location, and a call frame which
+    /// returns to one is the last on the call stack.
+    pub fn is_stub(&self, ip: usize) -> bool {
+        match self.code.get(ip) {
+            None
+            | Some(
+                Opcode::Nop(_)
+                | Opcode::NormalExit(_)
+                | Opcode::ContinueExit(_)
+                | Opcode::Await(_)
+                | Opcode::Trap(_),
+            ) => true,
+            Some(_) => false,
+        }
+    }
+
+    /// Returns the range of instruction offsets belonging to the bytecode function containing `ip`
+    #[inline]
+    pub fn function_range(&self, ip: usize) -> Range<usize> {
+        self.functions.range(ip, self.code.len())
+    }
+
     /// Look up a function by [`FunId`]
     #[inline]
     pub fn function_by_id(&self, id: FunId) -> &Function<A> {
@@ -829,6 +872,17 @@ impl<A: Atom, T: AtomTable<Atom = A>> ByteCode<A, T> {
         }
     }
 
+    /// Returns the source location of the given instruction offset, if known
+    ///
+    /// Unlike [`instruction_symbol`], this does not allocate, and is suitable for use in hot paths
+    /// such as single-stepping in a debugger.
+    pub fn instruction_location(&self, ip: usize) -> Option<Location> {
+        match self.functions.locate(ip) {
+            Function::Bytecode { offset, .. } => self.debug_info.offset_to_location(*offset, ip),
+            Function::Bif { .. } | Function::Native { .. } => None,
+        }
+    }
+
     /// Attempts to symbolicate the given [`FunId`] in this bytecode module.
     ///
     /// NOTE: This function will panic if `id` is not valid in this module.
@@ -1176,6 +1230,25 @@ impl<A: Atom> FunctionTable<A> {
             .expect("invalid instruction pointer")
     }
 
+    /// Maps an instruction pointer/offset to the range of offsets of the function it belongs to
+    ///
+    /// The last function in the table extends to `end`.
+    pub fn range(&self, ip: usize, end: usize) -> Range<usize> {
+        let start = self
+            .id_by_offset
+            .range(..=ip)
+            .next_back()
+            .map(|(offset, _)| *offset)
+            .expect("invalid instruction pointer");
+        let end = self
+            .id_by_offset
+            .range((ip + 1)..)
+            .next()
+            .map(|(offset, _)| *offset)
+            .unwrap_or(end);
+        start..end
+    }
+
     pub fn load(&mut self, function: Function<A>) {
         assert_eq!(self.registered.len(), function.id() as usize);
 
@@ -1210,6 +1283,7 @@ impl<A: Atom> FunctionTable<A> {
                 self.registered.push(Function::Bytecode {
                     id,
                     is_nif: false,
+                    is_on_load: false,
                     mfa,
                     offset,
                     frame_size: 0,
@@ -1253,6 +1327,7 @@ impl<A: Atom> FunctionTable<A> {
                             mfa,
                             offset,
                             is_nif: true,
+                            is_on_load: false,
                             frame_size: 0,
                         };
                         Ok(id)
@@ -1306,14 +1381,28 @@ impl<A: Atom> FunctionTable<A> {
         self.id_by_offset.insert(offset, id);
     }
 
-    pub fn append(
+    /// Appends the functions of `other`, whose code begins at `base_offset`
+    ///
+    /// The atoms referenced by `other` are converted with `intern`, so that they belong to the
+    /// same atom table as those of `self`.
+    pub fn append<F>(
         &mut self,
         other: &Self,
         base_offset: usize,
-    ) -> Result<(), InvalidBytecodeError<A>> {
+        mut intern: F,
+    ) -> Result<(), InvalidBytecodeError<A>>
+    where
+        F: FnMut(A) -> A,
+    {
+        let mut intern_mfa = |mfa: &ModuleFunctionArity<A>| ModuleFunctionArity {
+            module: intern(mfa.module),
+            function: intern(mfa.function),
+            arity: mfa.arity,
+        };
         for fun in other.registered.iter() {
             match fun {
                 Function::Native { name, arity, .. } => {
+                    let name = &intern(*name);
                     if !self.id_by_name.contains_key(name) {
                         let id = self.registered.len() as FunId;
                         self.registered.push(Function::Native {
@@ -1325,6 +1414,7 @@ impl<A: Atom> FunctionTable<A> {
                     }
                 }
                 Function::Bif { mfa, .. } => {
+                    let mfa = &intern_mfa(mfa);
                     if !self.id_by_mfa.contains_key(mfa) {
                         let id = self.registered.len() as FunId;
                         self.registered.push(Function::Bif { id, mfa: *mfa });
@@ -1333,11 +1423,13 @@ impl<A: Atom> FunctionTable<A> {
                 }
                 Function::Bytecode {
                     is_nif,
+                    is_on_load,
                     mfa,
                     offset,
                     frame_size,
                     ..
                 } => {
+                    let mfa = &intern_mfa(mfa);
                     let offset = *offset;
                     if !self.id_by_mfa.contains_key(mfa) {
                         let new_offset = if offset > 0 { offset + base_offset } else { 0 };
@@ -1345,6 +1437,7 @@ impl<A: Atom> FunctionTable<A> {
                         self.registered.push(Function::Bytecode {
                             id,
                             is_nif: *is_nif,
+                            is_on_load: *is_on_load,
                             mfa: *mfa,
                             offset: new_offset,
                             frame_size: *frame_size,
@@ -1361,10 +1454,11 @@ impl<A: Atom> FunctionTable<A> {
                             (_, 0) => continue,
                             // The merging definition is canonical, so apply the changes
                             (0, offset) => {
-                                self.id_by_offset.insert(offset, id);
+                                self.id_by_offset.insert(base_offset + offset, id);
                                 self.registered[id as usize] = Function::Bytecode {
                                     id,
                                     is_nif: *is_nif,
+                                    is_on_load: *is_on_load,
                                     mfa: *mfa,
                                     frame_size: *frame_size,
                                     offset: base_offset + offset,
@@ -121,6 +121,7 @@ fn bytecode_function_encoding_test() {
         Some(&Function::Bytecode {
             id: 0,
             is_nif: false,
+            is_on_load: false,
             mfa: test_main_1,
             offset: 3,
         })
@@ -151,6 +152,120 @@ fn bytecode_integration_test() {
     }
 }
 
+#[test]
+fn bytecode_instruction_location_test() {
+    let mut builder = Builder::new(StandardByteCode::new());
+    let test_main_0 = ModuleFunctionArity {
+        module: builder.insert_atom("test"),
+        function: builder.insert_atom("main"),
+        arity: 0,
+    };
+    let file = builder.get_or_insert_file("test.erl");
+    let location = |line| Location {
+        file,
+        line,
+        column: 5,
+    };
+    let line2 = builder.get_or_insert_location(location(2));
+    let line3 = builder.get_or_insert_location(location(3));
+
+    let mut function = builder.build_function(test_main_0, None).unwrap();
+    let entry = function.create_block(0);
+    function.switch_to_block(entry);
+    function.build_nil(Some(line2));
+    function.build_nil(Some(line2));
+    let reason = function.build_atom("badarg", None);
+    function.build_error(reason, Some(line3));
+    function.finish();
+    let code = builder.finish();
+
+    let offset = match code.function_by_mfa(&test_main_0) {
+        Some(Function::Bytecode { offset, .. }) => *offset,
+        other => panic!("expected bytecode function, got {:?}", other),
+    };
+    let line = |ip| match code.instruction_symbol(ip) {
+        Some(Symbol::Erlang { loc: Some(loc), .. }) => loc.line,
+        other => panic!("expected location for instruction {}, got {:?}", ip, other),
+    };
+    // Instructions without a location are attributed to the nearest preceding one
+    assert_eq!(line(offset + 1), 2);
+    assert_eq!(line(offset + 2), 2);
+    assert_eq!(line(offset + 3), 2);
+    assert_eq!(line(offset + 4), 3);
+}
+
+#[test]
+fn bytecode_link_test() {
+    // a:main/0 calls b:value/0, which is defined in a separately built module
+    let mut builder = Builder::new(StandardByteCode::new());
+    let a_main_0 = ModuleFunctionArity {
+        module: builder.insert_atom("a"),
+        function: builder.insert_atom("main"),
+        arity: 0,
+    };
+    let b_value_0 = ModuleFunctionArity {
+        module: builder.insert_atom("b"),
+        function: builder.insert_atom("value"),
+        arity: 0,
+    };
+    let mut function = builder.build_function(a_main_0, None).unwrap();
+    let callee = function.get_or_define_function(b_value_0);
+    let entry = function.create_block(0);
+    function.switch_to_block(entry);
+    let result = function.build_call(callee, &[], None);
+    function.build_ret(result, None);
+    function.finish();
+    let a = builder.finish();
+
+    let mut builder = Builder::new(StandardByteCode::new());
+    let b_value_0 = ModuleFunctionArity {
+        module: builder.insert_atom("b"),
+        function: builder.insert_atom("value"),
+        arity: 0,
+    };
+    let mut function = builder.build_function(b_value_0, None).unwrap();
+    let entry = function.create_block(0);
+    function.switch_to_block(entry);
+    let ok = function.build_atom("ok", None);
+    function.build_ret(ok, None);
+    function.finish();
+    let b = builder.finish();
+
+    let mut code = StandardByteCode::default();
+    code.link(vec![a, b]).unwrap();
+
+    // The linked modules have been dropped, so everything must refer to atoms of `code`
+    let mfa = |code: &mut StandardByteCode, m, f| ModuleFunctionArity {
+        module: code.insert_atom(m).unwrap(),
+        function: code.insert_atom(f).unwrap(),
+        arity: 0,
+    };
+    let a_main_0 = mfa(&mut code, "a", "main");
+    let b_value_0 = mfa(&mut code, "b", "value");
+    let offset_of = |mfa| match code.function_by_mfa(&mfa) {
+        Some(Function::Bytecode { offset, .. }) if *offset > 0 => *offset,
+        other => panic!("expected bytecode function, got {:?}", other),
+    };
+    let main_offset = offset_of(a_main_0);
+    let value_offset = offset_of(b_value_0);
+    assert!(value_offset > main_offset);
+
+    // The cross-module call was resolved to the linked definition of b:value/0
+    let calls = code.code[main_offset..value_offset]
+        .iter()
+        .filter_map(|op| match op {
+            Opcode::Call(Call { offset, .. }) => Some(*offset),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(calls, vec![value_offset]);
+
+    let ok = code.insert_atom("ok").unwrap();
+    assert!(code.code[value_offset..]
+        .iter()
+        .any(|op| matches!(op, Opcode::LoadAtom(LoadAtom { value, .. }) if *value == ok)));
+}
+
 fn generate_code() -> ByteCode<AtomicStr, LocalAtomTable> {
     let mut builder = Builder::new(ByteCode::new());
     let test_main_1 = ModuleFunctionArity {
@@ -28,7 +28,7 @@ pub fn print(process: &mut ProcessLock) -> io::Result<()> {
         for frame in trace.frames().iter().rev() {
             let Some(symbol) = frame.symbolicate() else { continue; };
             let line = symbol.line();
-            let column = symbol.line();
+            let column = symbol.column();
             let filename = symbol.filename();
             let Some(symbol) = symbol.symbol() else { continue; };
 
@@ -107,6 +107,10 @@ pub fn print(process: &mut ProcessLock) -> io::Result<()> {
                     Symbol::Native(name) => writeln!(writer, "{}", &name)?,
                 }
             }
+
+            if let (Some(file), Some(line)) = (filename, line) {
+                write_snippet(&mut writer, file, line, column)?;
+            }
         }
     }
 
@@ -130,6 +134,45 @@ pub fn print(process: &mut ProcessLock) -> io::Result<()> {
     stderr.print(&writer)
 }
 
+/// Prints the source line referenced by a frame, with a marker under the column if known.
+///
+/// Nothing is printed if the source file is not available, e.g. when running a release on a
+/// machine other than the one it was built on.
+fn write_snippet(
+    writer: &mut dyn WriteColor,
+    file: &str,
+    line: u32,
+    column: Option<u32>,
+) -> io::Result<()> {
+    let Ok(source) = std::fs::read_to_string(file) else {
+        return Ok(());
+    };
+    let Some(text) = source.lines().nth((line as usize).saturating_sub(1)) else {
+        return Ok(());
+    };
+    let trimmed = text.trim_start();
+    if trimmed.is_empty() {
+        return Ok(());
+    }
+
+    let mut dimmed = ColorSpec::new();
+    dimmed.set_dimmed(true);
+    writer.set_color(&dimmed)?;
+    writeln!(writer, "    {}", trimmed.trim_end())?;
+    if let Some(column) = column {
+        let indent = text.len() - trimmed.len();
+        let column = (column as usize).saturating_sub(1);
+        if column >= indent {
+            let offset = text[indent..]
+                .char_indices()
+                .take_while(|(i, _)| *i < column - indent)
+                .count();
+            writeln!(writer, "    {:>width$}", "^", width = offset + 1)?;
+        }
+    }
+    writer.reset()
+}
+
 fn write_filename(writer: &mut dyn WriteColor, file: &str) -> io::Result<()> {
     if file.starts_with("/rustc/") {
         if let Some(filename) = file.get(48..) {
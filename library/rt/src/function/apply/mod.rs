@@ -24,210 +24,7 @@ use crate::term::{Atom, OpaqueTerm};
 use super::{ErlangResult, FunctionSymbol, ModuleFunctionArity};
 
 #[cfg(all(feature = "std", any(unix, windows)))]
-const BIFS: &'static [&'static str] = &[
-    "erlang:++/2",
-    "erlang:--/2",
-    "erlang:=:=/2",
-    "erlang:==/2",
-    "erlang:=/=/2",
-    "erlang:/=/2",
-    "erlang:>=/2",
-    "erlang:=</2",
-    "erlang:</2",
-    "erlang:>/2",
-    "erlang:and/2",
-    "erlang:andalso/2",
-    "erlang:or/2",
-    "erlang:orelse/2",
-    "erlang:xor/2",
-    "erlang:not/1",
-    "erlang:+/2",
-    "erlang:-/2",
-    "erlang:-/1",
-    "erlang:*/2",
-    "erlang://2",
-    "erlang:div/2",
-    "erlang:rem/2",
-    "erlang:band/2",
-    "erlang:bor/2",
-    "erlang:bxor/2",
-    "erlang:bsl/2",
-    "erlang:bsr/2",
-    "erlang:bnot/1",
-    "erlang:abs/1",
-    "erlang:alias/0",
-    "erlang:alias/0",
-    "erlang:apply/2",
-    "erlang:apply/3",
-    "erlang:atom_to_binary/1",
-    "erlang:atom_to_binary/2",
-    "erlang:atom_to_list/1",
-    "erlang:binary_part/2",
-    "erlang:binary_part/3",
-    "erlang:binary_to_atom/1",
-    "erlang:binary_to_atom/2",
-    "erlang:binary_to_existing_atom/1",
-    "erlang:binary_to_existing_atom/2",
-    "erlang:binary_to_float/1",
-    "erlang:binary_to_integer/1",
-    "erlang:binary_to_integer/2",
-    "erlang:binary_to_list/1",
-    "erlang:binary_to_list/3",
-    "erlang:binary_to_term/1",
-    "erlang:binary_to_term/2",
-    "erlang:bit_size/1",
-    "erlang:bitstring_to_list/1",
-    "erlang:byte_size/1",
-    "erlang:ceil/1",
-    "erlang:date/0",
-    "erlang:demonitor/1",
-    "erlang:demonitor/2",
-    "erlang:disconnect_node/1",
-    "erlang:display/1",
-    "erlang:element/2",
-    "erlang:erase/0",
-    "erlang:erase/1",
-    "erlang:error/1",
-    "erlang:error/2",
-    "erlang:error/3",
-    "erlang:exit/1",
-    "erlang:exit/2",
-    "erlang:float/1",
-    "erlang:float_to_binary/1",
-    "erlang:float_to_binary/2",
-    "erlang:float_to_list/1",
-    "erlang:float_to_list/2",
-    "erlang:floor/1",
-    "erlang:garbage_collect/0",
-    "erlang:garbage_collect/1",
-    "erlang:garbage_collect/2",
-    "erlang:get/0",
-    "erlang:get/1",
-    "erlang:get_keys/0",
-    "erlang:get_keys/1",
-    "erlang:group_leader/0",
-    "erlang:group_leader/2",
-    "erlang:halt/0",
-    "erlang:halt/1",
-    "erlang:halt/2",
-    "erlang:hd/1",
-    "erlang:integer_to_binary/1",
-    "erlang:integer_to_binary/2",
-    "erlang:integer_to_list/1",
-    "erlang:integer_to_list/2",
-    "erlang:iolist_size/1",
-    "erlang:iolist_to_binary/1",
-    "erlang:is_alive/0",
-    "erlang:is_atom/1",
-    "erlang:is_binary/1",
-    "erlang:is_bitstring/1",
-    "erlang:is_boolean/1",
-    "erlang:is_float/1",
-    "erlang:is_function/1",
-    "erlang:is_function/2",
-    "erlang:is_integer/1",
-    "erlang:is_list/1",
-    "erlang:is_map/1",
-    "erlang:is_map_key/2",
-    "erlang:is_number/1",
-    "erlang:is_pid/1",
-    "erlang:is_port/1",
-    "erlang:is_process_alive/1",
-    "erlang:is_record/2",
-    "erlang:is_record/3",
-    "erlang:is_reference/1",
-    "erlang:is_tuple/1",
-    "erlang:length/1",
-    "erlang:link/1",
-    "erlang:list_to_atom/1",
-    "erlang:list_to_binary/1",
-    "erlang:list_to_bitstring/1",
-    "erlang:list_to_existing_atom/1",
-    "erlang:list_to_float/1",
-    "erlang:list_to_integer/1",
-    "erlang:list_to_integer/2",
-    "erlang:list_to_pid/1",
-    "erlang:list_to_port/1",
-    "erlang:list_to_ref/1",
-    "erlang:list_to_tuple/1",
-    "erlang:load_nif/2",
-    "erlang:make_ref/0",
-    "erlang:map_get/2",
-    "erlang:map_size/1",
-    "erlang:max/2",
-    "erlang:min/2",
-    "erlang:monitor/2",
-    "erlang:monitor/3",
-    "erlang:monitor_node/2",
-    "erlang:monitor_node/3",
-    "erlang:node/0",
-    "erlang:node/1",
-    "erlang:nodes/0",
-    "erlang:nodes/1",
-    "erlang:now/0",
-    "erlang:open_port/2",
-    "erlang:pid_to_list/1",
-    "erlang:port_close/1",
-    "erlang:port_command/2",
-    "erlang:port_command/3",
-    "erlang:port_connect/2",
-    "erlang:port_control/3",
-    "erlang:port_to_list/1",
-    "erlang:process_flag/2",
-    "erlang:process_flag/3",
-    "erlang:process_info/1",
-    "erlang:process_info/2",
-    "erlang:processes/0",
-    "erlang:put/2",
-    "erlang:raise/2",
-    "erlang:raise/3",
-    "erlang:ref_to_list/1",
-    "erlang:register/2",
-    "erlang:registered/0",
-    "erlang:round/1",
-    "erlang:setelement/3",
-    "erlang:self/0",
-    "erlang:size/1",
-    "erlang:spawn/1",
-    "erlang:spawn/2",
-    "erlang:spawn/3",
-    "erlang:spawn/4",
-    "erlang:spawn_link/1",
-    "erlang:spawn_link/2",
-    "erlang:spawn_link/3",
-    "erlang:spawn_link/4",
-    "erlang:spawn_monitor/1",
-    "erlang:spawn_monitor/2",
-    "erlang:spawn_monitor/3",
-    "erlang:spawn_monitor/4",
-    "erlang:spawn_opt/1",
-    "erlang:spawn_opt/2",
-    "erlang:spawn_opt/3",
-    "erlang:spawn_opt/4",
-    "erlang:spawn_opt/5",
-    "erlang:spawn_request/1",
-    "erlang:spawn_request/2",
-    "erlang:spawn_request/3",
-    "erlang:spawn_request/4",
-    "erlang:spawn_request/5",
-    "erlang:spawn_request_abandon/1",
-    "erlang:split_binary/2",
-    "erlang:statistics/1",
-    "erlang:term_to_binary/1",
-    "erlang:term_to_binary/2",
-    "erlang:term_to_iovec/1",
-    "erlang:term_to_iovec/2",
-    "erlang:throw/1",
-    "erlang:time/0",
-    "erlang:tl/1",
-    "erlang:trunc/1",
-    "erlang:tuple_size/1",
-    "erlang:tuple_to_list/1",
-    "erlang:unlink/1",
-    "erlang:unregister/1",
-    "erlang:whereis/1",
-    "erlang:yield/0",
-];
+use firefly_bifs::BIFS;
 
 /// The symbol table used by the runtime system
 static SYMBOLS: OnceLock<SymbolTable> = OnceLock::new();
@@ -23,6 +23,7 @@ use core::hash::{Hash, Hasher};
 use core::mem::MaybeUninit;
 use core::ops::{Deref, DerefMut};
 use core::ptr::{self, NonNull, Pointee};
+use core::sync::atomic::{AtomicU64, Ordering};
 
 use log::trace;
 
@@ -135,11 +136,28 @@ pub trait CollectionType {
     fn collect(&mut self, roots: RootSet) -> Result<usize, GcError>;
 }
 
+static GC_COUNT: AtomicU64 = AtomicU64::new(0);
+static GC_WORDS_RECLAIMED: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of garbage collections performed since the system started, and the
+/// total number of words they reclaimed, as `(count, words_reclaimed)`
+pub fn statistics() -> (u64, u64) {
+    (
+        GC_COUNT.load(Ordering::Relaxed),
+        GC_WORDS_RECLAIMED.load(Ordering::Relaxed),
+    )
+}
+
+/// Records the completion of a garbage collection which reclaimed `words` words
+pub(crate) fn record_collection(words: usize) {
+    GC_COUNT.fetch_add(1, Ordering::Relaxed);
+    GC_WORDS_RECLAIMED.fetch_add(words as u64, Ordering::Relaxed);
+}
+
 /// Garbage collection intrinsic for use by natively-implemented functions
 #[inline(never)]
 pub fn garbage_collect(process: &mut ProcessLock, roots: RootSet) -> Result<(), ()> {
     use crate::services::error_logger;
-    use core::sync::atomic::Ordering;
     use smallvec::SmallVec;
 
     const MAX_HEAP_ERROR_FORMAT: &'static str = "      Process:            ~p~n\
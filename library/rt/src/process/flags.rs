@@ -241,6 +241,10 @@ bitflags::bitflags! {
         const OFF_HEAP_MSGQ = 1 << 18;
         /// Process has sensitive data, so disable certain introspection features
         const SENSITIVE = 1 << 19;
+        /// Process has work to do on a dirty CPU scheduler, and is not run by normal schedulers
+        const DIRTY_CPU_PROC = 1 << 20;
+        /// Process has work to do on a dirty I/O scheduler, and is not run by normal schedulers
+        const DIRTY_IO_PROC = 1 << 21;
 
         /// The default process flags
         const DEFAULT = Self::PRIORITY_NORMAL.bits | Self::OFF_HEAP_MSGQ.bits;
@@ -289,6 +293,11 @@ impl StatusFlags {
         self.contains(Self::RUNNING | Self::RUNNING_SYS)
     }
 
+    /// Returns true if this process has been migrated to a dirty scheduler
+    pub fn is_dirty(&self) -> bool {
+        self.intersects(Self::DIRTY_CPU_PROC | Self::DIRTY_IO_PROC)
+    }
+
     /// Returns true if this process has any unhandled signals pending
     pub fn has_signals(&self) -> bool {
         self.contains(
@@ -2,11 +2,15 @@ use alloc::alloc::{AllocError, Allocator, Global, Layout};
 use core::cell::UnsafeCell;
 use core::mem;
 use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use firefly_alloc::heap::{Heap, HeapMut};
 
 use crate::term::OpaqueTerm;
 
+/// The total number of bytes allocated for process heaps
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
 pub struct ProcessHeap {
     range: *mut [u8],
     top: UnsafeCell<*mut u8>,
@@ -19,6 +23,7 @@ impl ProcessHeap {
     pub fn new(size: usize) -> Self {
         let layout = Layout::from_size_align(size, mem::align_of::<OpaqueTerm>()).unwrap();
         let nonnull = Global.allocate(layout).unwrap();
+        ALLOCATED.fetch_add(size, Ordering::Relaxed);
         let top = nonnull.as_non_null_ptr().as_ptr();
         Self {
             range: nonnull.as_ptr(),
@@ -40,6 +45,11 @@ impl ProcessHeap {
         self.range.len() == 0
     }
 
+    /// Returns the total number of bytes currently allocated for all process heaps
+    pub fn total_allocated() -> usize {
+        ALLOCATED.load(Ordering::Relaxed)
+    }
+
     pub fn next_size(size: usize) -> usize {
         match Self::SIZES.binary_search(&size) {
             Ok(i) | Err(i) => {
@@ -88,6 +98,7 @@ impl Drop for ProcessHeap {
         let size = ptr::metadata(self.range) as usize;
         let layout = Layout::from_size_align(size, mem::align_of::<OpaqueTerm>()).unwrap();
         unsafe { Global.deallocate(NonNull::new_unchecked(self.range.cast()), layout) }
+        ALLOCATED.fetch_sub(size, Ordering::Relaxed);
     }
 }
 unsafe impl Allocator for ProcessHeap {
@@ -28,6 +28,9 @@ impl ProcessId {
     const SERIAL_MAX: u64 = Self::NUMBER_MAX;
     const SERIAL_MASK: u64 = u32::MAX as u64;
 
+    /// The maximum number of processes which can have distinct process numbers at the same time
+    pub const LIMIT: usize = Self::NUMBER_MAX as usize;
+
     /// Return the raw representation of this ProcessId as a u64 value
     #[inline(always)]
     pub const fn raw(&self) -> u64 {
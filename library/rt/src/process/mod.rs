@@ -1313,11 +1313,24 @@ impl<'a> ProcessLock<'a> {
             self.guard.flags |= ProcessFlags::NEED_FULLSWEEP;
         }
 
-        if self.guard.flags.contains(ProcessFlags::NEED_FULLSWEEP) {
+        let size_before = self.heap_used();
+        let result = if self.guard.flags.contains(ProcessFlags::NEED_FULLSWEEP) {
             self.gc_full(needed, roots)
         } else {
             self.gc_minor(needed, roots)
+        };
+        if result.is_ok() {
+            let reclaimed = size_before.saturating_sub(self.heap_used());
+            crate::gc::record_collection(reclaimed / mem::size_of::<OpaqueTerm>());
         }
+        result
+    }
+
+    /// Returns the number of bytes used across both generations of the process heap
+    fn heap_used(&self) -> usize {
+        use firefly_alloc::heap::GenerationalHeap;
+
+        self.guard.heap.immature().heap_used() + self.guard.heap.mature().heap_used()
     }
 
     fn gc_full(&mut self, needed: usize, roots: RootSet) -> Result<usize, GcError> {
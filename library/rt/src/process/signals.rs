@@ -138,6 +138,21 @@ impl Signal {
         SignalEntry::new(Self::IsAlive(IsAlive { sender, reference }))
     }
 
+    #[inline]
+    pub fn process_info(
+        sender: Arc<Process>,
+        item: Atom,
+        reference: Reference,
+        need_msgq_len: bool,
+    ) -> Box<SignalEntry> {
+        SignalEntry::new(Self::ProcessInfo(ProcessInfo {
+            sender: Some(sender),
+            item,
+            reference,
+            need_msgq_len,
+        }))
+    }
+
     #[inline]
     pub fn flush(ty: FlushType) -> Box<SignalEntry> {
         SignalEntry::new(Self::Flush(Flush { sender: None, ty }))
@@ -737,6 +752,41 @@ impl<'a> SignalQueueLock<'a> {
         self.queue.last_seen = ptr::null();
     }
 
+    /// Removes the first message in the private queue for which `predicate` returns true
+    ///
+    /// Messages still in-transit are not visited, so this is only suitable for messages which
+    /// are known to be placed in the private queue on receipt, such as `DOWN` messages, which are
+    /// converted from signals by the receiving process.
+    ///
+    /// If the removed message was under the receive cursor, the receive marker is reset.
+    pub fn remove_first_message<P>(&mut self, predicate: P) -> Option<Message>
+    where
+        P: Fn(&Message) -> bool,
+    {
+        let mut cursor = self.queue.received.messages.front_mut();
+        while let Some(sig) = cursor.get() {
+            let found = match sig.signal {
+                Signal::Message(ref msg) => predicate(msg),
+                _ => false,
+            };
+            if !found {
+                cursor.move_next();
+                continue;
+            }
+            let sig = cursor.remove().unwrap();
+            let ptr = sig.as_ref() as *const SignalEntry;
+            if self.queue.cursor == ptr || self.queue.last_seen == ptr {
+                self.end_receive();
+            }
+            self.queue.received.len -= 1;
+            match sig.signal {
+                Signal::Message(msg) => return Some(msg),
+                _ => unreachable!(),
+            }
+        }
+        None
+    }
+
     /// Pushes the given message to the front of the message queue
     ///
     /// # SAFETY
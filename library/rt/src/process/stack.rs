@@ -356,6 +356,17 @@ impl ProcessStack {
         self.stack[self.fp + reg as usize] = value;
     }
 
+    /// Select the stack slots between the absolute offsets `start` and `end`
+    ///
+    /// Unlike [`select_registers`], this is not relative to the current frame, which allows
+    /// inspecting frames other than the current one, e.g. from a debugger.
+    ///
+    /// NOTE: This function will panic if the range is out of bounds
+    #[inline]
+    pub fn slots(&self, start: usize, end: usize) -> &[OpaqueTerm] {
+        &self.stack[start..end]
+    }
+
     /// Select a slice of `n` registers, beginning with register `start`
     ///
     /// NOTE: This function will panic if the range is out of bounds
@@ -1,13 +1,15 @@
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::mem::MaybeUninit;
 
 use firefly_number::Int;
 use firefly_system::sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use firefly_system::time::Duration;
 
 use crate::function::ModuleFunctionArity;
 use crate::gc::Gc;
 use crate::process::{Process, ProcessLock, SpawnOpts};
-use crate::services::timers::{Timer, TimerError};
+use crate::services::timers::{Timer, TimerError, TimerRequest};
 use crate::term::{OpaqueTerm, Reference, ReferenceId};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -102,6 +104,13 @@ pub trait Scheduler: Send + Sync {
     /// Returns `Ok` if successful, `Err` if no such timer exists
     fn cancel_timer(&self, timer_ref: ReferenceId) -> Result<(), ()>;
 
+    /// Relays `request` to this scheduler, to be handled by its timer service
+    ///
+    /// This is used to operate on timers owned by this scheduler from other schedulers, and
+    /// unlike the other timer functions, may be called from any thread. The reply callback of
+    /// the request is invoked on this scheduler's thread once the request has been handled.
+    fn relay_timer_request(&self, request: TimerRequest);
+
     /// Spawn a new process with the given module/function/arguments
     ///
     /// The spawned process will exit with an error if the given MFA is invalid, or the arguments
@@ -122,6 +131,18 @@ pub trait Scheduler: Send + Sync {
 
     /// Reschedules `process` using this scheduler's run queue
     fn reschedule(&self, process: Arc<Process>);
+
+    /// Returns the total number of reductions executed by this scheduler
+    fn reductions(&self) -> u64;
+
+    /// Returns the number of processes currently in this scheduler's run queue
+    ///
+    /// This may be called from any thread, so the result is only an approximation
+    fn run_queue_len(&self) -> usize;
+
+    /// Returns the time this scheduler has spent executing processes, and the total time
+    /// that has elapsed since it started, as `(active, total)`
+    fn wall_time(&self) -> (Duration, Duration);
 }
 
 /// Returns a strong reference to the scheduler corresponding to `id`
@@ -131,6 +152,25 @@ pub fn get(id: SchedulerId) -> Arc<dyn Scheduler> {
     with_schedulers_readonly(|schedulers| schedulers.fetch(id))
 }
 
+/// Returns a strong reference to the scheduler corresponding to `id`, if it is online
+///
+/// Unlike [`get`], this function can be used with ids derived from untrusted sources, such
+/// as the scheduler id embedded in a reference.
+pub fn try_get(id: SchedulerId) -> Option<Arc<dyn Scheduler>> {
+    with_schedulers_readonly(|schedulers| {
+        if schedulers.is_online(id) {
+            Some(schedulers.fetch(id))
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns strong references to all schedulers which are online, ordered by id
+pub fn all() -> Vec<Arc<dyn Scheduler>> {
+    with_schedulers_readonly(|schedulers| schedulers.iter().cloned().collect())
+}
+
 /// Creates a new scheduler using the provided constructor function.
 ///
 /// The function provided can expect a scheduler id to be provided which is safe for use
@@ -176,16 +216,27 @@ pub fn online() -> u32 {
     with_schedulers_readonly(|schedulers| schedulers.online())
 }
 
-/// Returns the number of schedulers online
+/// Returns the number of dirty CPU schedulers online
 pub fn dirty_cpu() -> u32 {
     with_schedulers_readonly(|schedulers| schedulers.dirty_cpu())
 }
 
-/// Returns the number of schedulers online
+/// Returns the number of dirty I/O schedulers online
 pub fn dirty_io() -> u32 {
     with_schedulers_readonly(|schedulers| schedulers.dirty_io())
 }
 
+/// Records the number of dirty CPU and dirty I/O schedulers which have been started
+///
+/// Dirty schedulers are not registered in the scheduler set like normal schedulers, as they do
+/// not own processes or timers, so the runtime only keeps track of how many are online.
+pub fn set_dirty(cpu: u32, io: u32) {
+    with_schedulers(|mut schedulers| {
+        schedulers.dirty_cpu = cpu;
+        schedulers.dirty_io = io;
+    })
+}
+
 #[inline]
 fn with_schedulers_readonly<F, T>(callback: F) -> T
 where
@@ -216,6 +267,10 @@ struct SchedulerSet {
     online: u64,
     /// A bitset defining which scheduler slots are available for assignment
     assigned: u64,
+    /// The number of dirty CPU schedulers online
+    dirty_cpu: u32,
+    /// The number of dirty I/O schedulers online
+    dirty_io: u32,
 }
 impl SchedulerSet {
     const MAX_SCHEDULERS: usize = 64;
@@ -230,6 +285,8 @@ impl SchedulerSet {
             schedulers: unsafe { MaybeUninit::array_assume_init(slots) },
             online: 0,
             assigned: 0,
+            dirty_cpu: 0,
+            dirty_io: 0,
         }
     }
 
@@ -355,12 +412,20 @@ impl SchedulerSet {
     }
 
     /// Returns true if the scheduler with the given `id` is online
-    #[allow(unused)]
     pub fn is_online(&self, id: SchedulerId) -> bool {
-        let bit = 1 << (id.as_u16() as u64);
+        let id = id.as_u16() as usize;
+        if id >= Self::MAX_SCHEDULERS {
+            return false;
+        }
+        let bit = 1 << (id as u64);
         self.online & bit == bit
     }
 
+    /// Returns an iterator over the schedulers which are online, ordered by id
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Scheduler>> + '_ {
+        self.schedulers.iter().filter_map(|slot| slot.as_ref())
+    }
+
     /// Returns the number of schedulers online
     pub fn online(&self) -> u32 {
         self.online.count_ones()
@@ -368,11 +433,11 @@ impl SchedulerSet {
 
     /// Returns the number of dirty CPU schedulers online
     pub fn dirty_cpu(&self) -> u32 {
-        0
+        self.dirty_cpu
     }
 
     /// Returns the number of dirty I/O schedulers online
     pub fn dirty_io(&self) -> u32 {
-        0
+        self.dirty_io
     }
 }
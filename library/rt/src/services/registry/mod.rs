@@ -23,6 +23,7 @@ cfg_if::cfg_if! {
 pub use self::imp::*;
 
 use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 use core::hash::{Hash, Hasher};
 use core::ptr;
 
@@ -343,6 +344,16 @@ pub fn get_by_port_id(id: PortId) -> Option<Arc<Port>> {
     with_port_table(|registry, guard| registry.get_by_port_id(id, guard))
 }
 
+/// Returns the number of processes in the registry, i.e. the number of processes alive
+pub fn process_count() -> usize {
+    with_process_table(|registry, guard| registry.registered_processes(guard))
+}
+
+/// Returns all processes in the registry, i.e. all processes alive
+pub fn processes() -> Vec<Arc<Process>> {
+    with_process_table(|registry, guard| registry.processes(guard).collect())
+}
+
 /// Inserts a process in the registry
 ///
 /// This function will panic if the registry already contains a registration for the same pid
@@ -143,6 +143,19 @@ impl Registry {
         self.names.iter().map(|e| (*e.key(), e.value().clone()))
     }
 
+    /// Returns an iterator over all processes in the registry, in no particular order
+    pub fn processes<'g>(
+        &'g self,
+        _guard: &'g ProcessTableGuard<'_>,
+    ) -> impl Iterator<Item = Arc<Process>> + 'g {
+        self.processes.iter().map(|e| e.value().clone())
+    }
+
+    /// Returns the number of processes in the registry
+    pub fn registered_processes<'g>(&'g self, _guard: &'g ProcessTableGuard<'_>) -> usize {
+        self.processes.len()
+    }
+
     /// Returns the number of registered names in the registry
     pub fn registered_names<'g>(&'g self, _guard: &'g NameTableGuard<'_>) -> usize {
         self.names.len()
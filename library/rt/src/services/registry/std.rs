@@ -241,6 +241,19 @@ impl Registry {
         self.names.iter(guard).map(|(k, v)| (*k, v.clone()))
     }
 
+    /// Returns an iterator over all processes in the registry, in no particular order
+    pub fn processes<'g>(
+        &'g self,
+        guard: &'g ProcessTableGuard<'_>,
+    ) -> impl Iterator<Item = Arc<Process>> + 'g {
+        self.processes.iter(guard).map(|(_, v)| v.clone())
+    }
+
+    /// Returns the number of processes in the registry
+    pub fn registered_processes<'g>(&'g self, _guard: &'g ProcessTableGuard<'_>) -> usize {
+        self.processes.len()
+    }
+
     /// Returns the number of registered names in the registry
     pub fn registered_names<'g>(&'g self, _guard: &'g NameTableGuard<'_>) -> usize {
         self.names.len()
@@ -9,7 +9,7 @@ use alloc::sync::{Arc, Weak};
 use core::fmt;
 
 use firefly_alloc::fragment::HeapFragment;
-use firefly_system::time::{MonotonicTime, Timeout};
+use firefly_system::time::{Duration, MonotonicTime, Timeout};
 
 use intrusive_collections::UnsafeRef;
 
@@ -145,6 +145,41 @@ impl fmt::Debug for TimerEvent {
     }
 }
 
+/// The callback invoked with the result of a [`TimerRequest`]
+///
+/// The value given is `None` if the timer could not be found, i.e. it has already expired or was
+/// cancelled, otherwise it is the time that remained until the timer would expire.
+pub type TimerReply = Box<dyn FnOnce(Option<Duration>) + Send + 'static>;
+
+/// A request to operate on a timer which may be owned by another scheduler
+///
+/// Timers are always registered with the timer service of the scheduler which started them, as
+/// identified by the scheduler id of the timer reference. Operations on a timer from any other
+/// scheduler must be relayed to the owning scheduler, which handles the request during its core
+/// loop and then invokes the reply callback with the result.
+pub enum TimerRequest {
+    /// Cancel the timer, replying with the time that remained until it would have expired
+    Cancel { id: ReferenceId, reply: TimerReply },
+    /// Read the time remaining until the timer expires
+    Read { id: ReferenceId, reply: TimerReply },
+}
+impl fmt::Debug for TimerRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cancel { id, .. } => f.debug_struct("Cancel").field("id", id).finish(),
+            Self::Read { id, .. } => f.debug_struct("Read").field("id", id).finish(),
+        }
+    }
+}
+impl TimerRequest {
+    /// Returns the reference id of the timer this request applies to
+    pub fn id(&self) -> ReferenceId {
+        match self {
+            Self::Cancel { id, .. } | Self::Read { id, .. } => *id,
+        }
+    }
+}
+
 /// This trait provides the minimum interface required for implementations of the runtime timer service.
 ///
 /// Timer services might be designed for use a couple different ways:
@@ -199,6 +234,25 @@ pub trait TimerService {
     /// Returns `Ok` if the timer was successfully canceled.
     fn cancel_timer(&mut self, timer_ref: ReferenceId) -> Result<(), ()>;
 
+    /// Returns the time remaining until the timer identified by `timer_ref` expires
+    ///
+    /// Returns `None` if the timer could not be found.
+    fn read_timer(&self, timer_ref: ReferenceId) -> Option<Duration>;
+
+    /// Handles `request`, invoking its reply callback with the result
+    fn handle_request(&mut self, request: TimerRequest) {
+        match request {
+            TimerRequest::Cancel { id, reply } => {
+                let remaining = self.read_timer(id);
+                match self.cancel_timer(id) {
+                    Ok(_) => reply(remaining),
+                    Err(_) => reply(None),
+                }
+            }
+            TimerRequest::Read { id, reply } => reply(self.read_timer(id)),
+        }
+    }
+
     /// Creates and starts a new one-shot [`Timer`] which sends `message` to `recipient` after `timeout`.
     ///
     /// The provided `timer_ref` will be used as the reference for the created timer.
@@ -325,6 +379,11 @@ impl TimerService for PerSchedulerTimerService {
     fn cancel_timer(&mut self, timer_ref: ReferenceId) -> Result<(), ()> {
         self.wheel.cancel(timer_ref)
     }
+
+    #[inline]
+    fn read_timer(&self, timer_ref: ReferenceId) -> Option<Duration> {
+        self.wheel.remaining(timer_ref).map(Duration::from_millis)
+    }
 }
 impl PerSchedulerTimerService {
     /// Returns true if there are no timers registered
@@ -420,14 +479,18 @@ impl PerSchedulerTimerService {
                     recipient,
                     message,
                 } => match recipient.try_resolve() {
+                    // Registered names are resolved at the time of expiry, and like dead processes,
+                    // it is not an error if the name is no longer registered
                     None => {
-                        trace!(target: "timers", "send_after timer expired, but process is dead");
+                        trace!(target: "timers", "send_after timer expired, but recipient {:?} no longer exists", &recipient);
                     }
                     Some(Registrant::Process(process)) => {
                         trace!(target: "timers", "send_after timer expired for {}", process.pid());
                         process.send_fragment(sender, message).ok();
                     }
-                    _ => unimplemented!(),
+                    Some(Registrant::Port(_)) => {
+                        trace!(target: "timers", "send_after timer expired, but ports are not supported as timer destinations");
+                    }
                 },
             },
             Timer::Recurring { id, event, .. } => {
@@ -576,6 +576,28 @@ impl HierarchicalTimerWheel {
         }
     }
 
+    /// Returns the number of milliseconds remaining until the timer with the given `id` expires
+    ///
+    /// Returns `None` if no such timer could be found, i.e. it has already expired or was cancelled.
+    pub fn remaining(&self, id: ReferenceId) -> Option<u64> {
+        let entry = self.timers.get(&id)?;
+        match entry.expiration {
+            Expiration::Soon(time)
+            | Expiration::Short(time)
+            | Expiration::Medium(time)
+            | Expiration::Long(time) => {
+                let current = self.current_time_in_cycle();
+                Some(time.as_u32().wrapping_sub(current.as_u32()) as u64)
+            }
+            // Timers in the overflow list have their timeout adjusted relative to the end of
+            // the current cycle, so we need to add back the time remaining in this cycle
+            Expiration::Overflow => {
+                let timeout = entry.timer.timeout().as_duration().as_millis() as u64;
+                Some(self.remaining_time_in_cycle() + timeout)
+            }
+        }
+    }
+
     /// Advances the timer wheel one tick, where each tick is intended to represent one millisecond of real time.
     ///
     /// The actual real time that elapses between ticks may actually be more or less than one millisecond though,
@@ -990,4 +1012,28 @@ mod tests {
 
         assert!(SUCCESS.load(Ordering::SeqCst));
     }
+
+    #[test]
+    fn hierarchical_wheel_remaining_time_test() {
+        let mut wheel = HierarchicalTimerWheel::new();
+
+        let timer_ref = unsafe { ReferenceId::new(SchedulerId::from_raw(0), 1) };
+        let timer = Timer::Once {
+            id: timer_ref,
+            timeout: Timeout::from_millis(300),
+            event: TimerEvent::Callback(Box::new(|| ())),
+        };
+
+        wheel.insert(timer).unwrap();
+
+        assert_eq!(Some(300), wheel.remaining(timer_ref));
+
+        assert_eq!(Ok(()), wheel.try_skip(100));
+
+        assert_eq!(Some(200), wheel.remaining(timer_ref));
+
+        assert_eq!(Ok(()), wheel.cancel(timer_ref));
+
+        assert_eq!(None, wheel.remaining(timer_ref));
+    }
 }
@@ -27,12 +27,16 @@ use super::OpaqueTerm;
 /// The maximum length of an atom (255)
 pub const MAX_ATOM_LENGTH: usize = u16::max_value() as usize;
 
+/// The maximum number of atoms which can exist in the atom table
+pub const MAX_ATOMS: usize = 1_048_576;
+
 /// Produced by operations which create atoms
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum AtomError {
     InvalidLength(usize),
     NonExistent,
     InvalidString(Utf8Error),
+    SystemLimit,
 }
 #[cfg(feature = "std")]
 impl std::error::Error for AtomError {
@@ -59,6 +63,11 @@ impl Display for AtomError {
             ),
             Self::NonExistent => f.write_str("tried to convert to an atom that doesn't exist"),
             Self::InvalidString(err) => write!(f, "invalid utf-8 bytes: {}", &err),
+            Self::SystemLimit => write!(
+                f,
+                "the atom table is full, maximum number of atoms is {}",
+                MAX_ATOMS
+            ),
         }
     }
 }
@@ -9,7 +9,7 @@ use core::str;
 use firefly_arena::DroplessArena;
 use firefly_system::sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use super::{Atom, AtomError};
+use super::{Atom, AtomError, MAX_ATOMS};
 
 /// The atom table used by the runtime system
 static ATOMS: OnceLock<RwLock<AtomTable>> = OnceLock::new();
@@ -230,6 +230,13 @@ impl AtomTable {
         self.ids.values().copied()
     }
 
+    /// Returns the number of bytes used by the atoms in this table, including their metadata
+    pub fn memory(&self) -> usize {
+        self.iter()
+            .map(|data| mem::size_of::<AtomData>() + unsafe { data.as_ref().size })
+            .sum()
+    }
+
     fn extend(&mut self, data: &'static [AtomData]) {
         for atom in data {
             let ptr = unsafe { NonNull::new_unchecked(atom as *const AtomData as *mut AtomData) };
@@ -288,6 +295,10 @@ impl AtomTable {
     unsafe fn insert(&mut self, name: &str) -> Result<NonNull<AtomData>, AtomError> {
         use core::intrinsics::unlikely;
 
+        if unlikely(self.ids.len() >= MAX_ATOMS) {
+            return Err(AtomError::SystemLimit);
+        }
+
         if unlikely(name.len() == 0) {
             let data = self.alloc_data(AtomData {
                 ptr: ptr::null_mut(),
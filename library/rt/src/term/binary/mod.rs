@@ -15,6 +15,7 @@ use core::hash::{Hash, Hasher};
 use core::ops::{Index, IndexMut};
 use core::ptr::{self, NonNull};
 use core::slice::SliceIndex;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use firefly_alloc::heap::Heap;
 use firefly_binary::{Aligned, Binary, BinaryFlags, Bitstring, Encoding, Selection};
@@ -27,6 +28,9 @@ use super::{Boxable, Header, Metadata, Tag};
 pub const EMPTY_BIN: &'static BinaryData =
     BinaryData::make_constant(BinaryFlags::new(0, Encoding::Raw), &[]);
 
+/// The total number of bytes held by reference-counted binaries, see [`BinaryData::total_allocated`]
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
 /// This struct is used to represent both binary _and_ bitstring data.
 ///
 /// Data is always stored aligned, but with a possibly non-zero number of trailing bits.
@@ -111,6 +115,21 @@ impl BinaryData {
         self.data.len()
     }
 
+    /// Returns the total number of bytes currently held by reference-counted binaries
+    ///
+    /// Only binaries too large to be stored on a process heap are accounted for, as smaller
+    /// binaries can't be distinguished from heap binaries when they are dropped.
+    pub fn total_allocated() -> usize {
+        ALLOCATED.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn record_allocation(size: usize) {
+        if size > Self::MAX_HEAP_BYTES {
+            ALLOCATED.fetch_add(size, Ordering::Relaxed);
+        }
+    }
+
     /// Copies the bytes from the given slice into `self`
     ///
     /// NOTE: The length of the slice must match the capacity of `self`, or the function will panic
@@ -180,6 +199,7 @@ impl BinaryData {
             boxed.header = Header::new(Tag::Binary, meta);
             boxed.copy_from_slice(bytes);
         }
+        Self::record_allocation(boxed.len());
         Arc::from(boxed)
     }
 
@@ -219,6 +239,7 @@ impl BinaryData {
             let meta = <BinaryFlags as Metadata<Self>>::pack(BinaryFlags::new(cap, Encoding::Raw));
             boxed.header = Header::new(Tag::Binary, meta);
         }
+        Self::record_allocation(boxed.len());
         Arc::from(boxed)
     }
 
@@ -273,9 +294,17 @@ impl BinaryData {
             boxed.header = Header::new(Tag::Binary, meta);
             boxed.copy_from_slice(bytes);
         }
+        Self::record_allocation(boxed.len());
         Arc::from(boxed)
     }
 }
+impl Drop for BinaryData {
+    fn drop(&mut self) {
+        if self.len() > Self::MAX_HEAP_BYTES {
+            ALLOCATED.fetch_sub(self.len(), Ordering::Relaxed);
+        }
+    }
+}
 impl fmt::Debug for BinaryData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self)
@@ -26,7 +26,7 @@ pub use self::index::{NonPrimitiveIndex, OneBasedIndex, TupleIndex, ZeroBasedInd
 pub use self::integer::BigInt;
 pub use self::layout::LayoutBuilder;
 pub use self::list::{Cons, ImproperList, ListBuilder};
-pub use self::map::{Map, MapError, SmallMap};
+pub use self::map::{Map, MapError, SmallMap, SMALL_MAP_LIMIT};
 pub use self::opaque::{OpaqueTerm, TermType};
 pub use self::pid::Pid;
 pub use self::port::{Port, PortId};
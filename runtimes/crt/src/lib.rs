@@ -45,16 +45,30 @@ pub extern "C" fn main(argc: i32, argv: *const *const std::os::raw::c_char) -> i
 /// up the schedulers and other high-level runtime functionality.
 #[rustc_main]
 pub fn main_internal() -> i32 {
+    if let Err(code) = init() {
+        return code;
+    }
+
+    // Invoke platform-specific entry point
+    unsafe { firefly_entry() }
+}
+
+/// Performs the same global initialization as [`main_internal`], without invoking the
+/// platform-specific entry point
+///
+/// This is used when the runtime is embedded in a program with its own entry point. It must be
+/// called exactly once, before any atoms are created. On failure, the exit code which
+/// [`main_internal`] would have returned is given as the error.
+pub fn init() -> Result<(), i32> {
     // Initialize atom table
     if unsafe { atoms::init(atoms::start(), atoms::end()) } == false {
-        return 102;
+        return Err(102);
     }
 
     // Initialize the dispatch table
     if unsafe { symbols::init(symbols::start(), symbols::end()) } == false {
-        return 103;
+        return Err(103);
     }
 
-    // Invoke platform-specific entry point
-    unsafe { firefly_entry() }
+    Ok(())
 }
@@ -0,0 +1,395 @@
+/*
+ * The erl_nif interface of the Firefly emulator
+ *
+ * This is source compatible with the `erl_nif.h` shipped with Erlang/OTP, so that existing NIF
+ * libraries can be loaded via `-on_load` and `erlang:load_nif/2` after being rebuilt against it.
+ * It is not binary compatible, libraries built against the OTP header can't be loaded.
+ *
+ * The differences from OTP which may affect existing code are:
+ *
+ * - Terms are always 64 bits wide.
+ * - `ErlNifPid` holds a process identifier rather than a term, use `enif_make_pid` to get the term,
+ *   and `enif_compare_pids` to compare them. Both are functions rather than macros.
+ * - Scheduled and dirty NIFs are supported, but `enif_consume_timeslice` never asks a NIF to
+ *   yield, and dirty NIFs run on the blocking thread pool of the emulator.
+ * - Large maps, ports, timers, threads, thread-specific data, `enif_snprintf` and the external
+ *   term format functions are not supported.
+ *
+ * The functions declared here are defined by the executable which loads the library, so it must
+ * export them to the libraries it loads, e.g. by linking with `-Wl,--export-dynamic` on Linux.
+ */
+#ifndef __ERL_NIF_H__
+#define __ERL_NIF_H__
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define ERL_NIF_MAJOR_VERSION 2
+#define ERL_NIF_MINOR_VERSION 16
+#define ERL_NIF_VM_VARIANT "firefly"
+#define ERL_NIF_MIN_ERTS_VERSION "erts-13.0"
+
+typedef uint64_t ERL_NIF_TERM;
+typedef uint64_t ERL_NIF_UINT;
+typedef int64_t ErlNifSInt64;
+typedef uint64_t ErlNifUInt64;
+
+typedef struct enif_environment_t ErlNifEnv;
+
+typedef struct enif_func_t {
+    const char *name;
+    unsigned arity;
+    ERL_NIF_TERM (*fptr)(ErlNifEnv *env, int argc, const ERL_NIF_TERM argv[]);
+    unsigned flags;
+} ErlNifFunc;
+
+typedef struct enif_entry_t {
+    int major;
+    int minor;
+    const char *name;
+    int num_of_funcs;
+    ErlNifFunc *funcs;
+    int (*load)(ErlNifEnv *, void **priv_data, ERL_NIF_TERM load_info);
+    /* Never called, as libraries can't be reloaded */
+    int (*reload)(ErlNifEnv *, void **priv_data, ERL_NIF_TERM load_info);
+    /* Never called, as libraries can't be upgraded */
+    int (*upgrade)(ErlNifEnv *, void **priv_data, void **old_priv_data, ERL_NIF_TERM load_info);
+    /* Never called, as libraries are never unloaded */
+    void (*unload)(ErlNifEnv *, void *priv_data);
+    const char *vm_variant;
+    unsigned options;
+    size_t sizeof_ErlNifResourceTypeInit;
+    const char *min_erts;
+} ErlNifEntry;
+
+typedef struct {
+    size_t size;
+    unsigned char *data;
+    /* Internal, non-NULL if the data is owned by this binary */
+    void *ref_bin;
+    void *__spare__[2];
+} ErlNifBinary;
+
+typedef struct {
+    /* Internal, this is a process identifier, not a term */
+    ERL_NIF_TERM pid;
+} ErlNifPid;
+
+typedef struct {
+    unsigned char data[sizeof(void *) * 4];
+} ErlNifMonitor;
+
+typedef int ErlNifEvent;
+
+typedef struct enif_resource_type_t ErlNifResourceType;
+typedef void ErlNifResourceDtor(ErlNifEnv *, void *);
+typedef void ErlNifResourceStop(ErlNifEnv *, void *, ErlNifEvent, int is_direct_call);
+typedef void ErlNifResourceDown(ErlNifEnv *, void *, ErlNifPid *, ErlNifMonitor *);
+typedef void ErlNifResourceDynCall(ErlNifEnv *, void *obj, void *call_data);
+
+typedef struct {
+    ErlNifResourceDtor *dtor;
+    /* Never called, as select is not supported */
+    ErlNifResourceStop *stop;
+    ErlNifResourceDown *down;
+    int members;
+    /* Never called, as dynamic calls are not supported */
+    ErlNifResourceDynCall *dyncall;
+} ErlNifResourceTypeInit;
+
+typedef enum {
+    ERL_NIF_RT_CREATE = 1,
+    ERL_NIF_RT_TAKEOVER = 2
+} ErlNifResourceFlags;
+
+typedef enum {
+    ERL_NIF_LATIN1 = 1,
+    ERL_NIF_UTF8 = 2
+} ErlNifCharEncoding;
+
+typedef enum {
+    ERL_NIF_DIRTY_JOB_CPU_BOUND = 1,
+    ERL_NIF_DIRTY_JOB_IO_BOUND = 2
+} ErlNifDirtyTaskFlags;
+
+typedef enum {
+    ERL_NIF_THR_UNDEFINED = 0,
+    ERL_NIF_THR_NORMAL_SCHEDULER = 1,
+    ERL_NIF_THR_DIRTY_CPU_SCHEDULER = 2,
+    ERL_NIF_THR_DIRTY_IO_SCHEDULER = 3
+} ErlNifThreadType;
+
+typedef enum {
+    ERL_NIF_TERM_TYPE_ATOM = 1,
+    ERL_NIF_TERM_TYPE_BITSTRING = 2,
+    ERL_NIF_TERM_TYPE_FLOAT = 3,
+    ERL_NIF_TERM_TYPE_FUN = 4,
+    ERL_NIF_TERM_TYPE_INTEGER = 5,
+    ERL_NIF_TERM_TYPE_LIST = 6,
+    ERL_NIF_TERM_TYPE_MAP = 7,
+    ERL_NIF_TERM_TYPE_PID = 8,
+    ERL_NIF_TERM_TYPE_PORT = 9,
+    ERL_NIF_TERM_TYPE_REFERENCE = 10,
+    ERL_NIF_TERM_TYPE_TUPLE = 11
+} ErlNifTermType;
+
+typedef enum {
+    ERL_NIF_MAP_ITERATOR_FIRST = 1,
+    ERL_NIF_MAP_ITERATOR_LAST = 2,
+    ERL_NIF_MAP_ITERATOR_HEAD = ERL_NIF_MAP_ITERATOR_FIRST,
+    ERL_NIF_MAP_ITERATOR_TAIL = ERL_NIF_MAP_ITERATOR_LAST
+} ErlNifMapIteratorEntry;
+
+typedef struct {
+    ERL_NIF_TERM map;
+    size_t size;
+    size_t idx;
+    const ERL_NIF_TERM *keys;
+    const ERL_NIF_TERM *values;
+    void *__spare__[2];
+} ErlNifMapIterator;
+
+typedef struct enif_mutex_t ErlNifMutex;
+typedef struct enif_rwlock_t ErlNifRWLock;
+typedef struct enif_cond_t ErlNifCond;
+
+/* Memory */
+void *enif_alloc(size_t size);
+void *enif_realloc(void *ptr, size_t size);
+void enif_free(void *ptr);
+
+/* Environments */
+ErlNifEnv *enif_alloc_env(void);
+void enif_free_env(ErlNifEnv *env);
+void enif_clear_env(ErlNifEnv *env);
+ERL_NIF_TERM enif_make_copy(ErlNifEnv *dst_env, ERL_NIF_TERM src_term);
+void *enif_priv_data(ErlNifEnv *env);
+
+/* Exceptions */
+ERL_NIF_TERM enif_make_badarg(ErlNifEnv *env);
+ERL_NIF_TERM enif_raise_exception(ErlNifEnv *env, ERL_NIF_TERM reason);
+int enif_has_pending_exception(ErlNifEnv *env, ERL_NIF_TERM *reason);
+int enif_is_exception(ErlNifEnv *env, ERL_NIF_TERM term);
+
+/* Type tests and comparison */
+int enif_is_atom(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_binary(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_empty_list(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_fun(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_list(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_map(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_number(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_pid(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_port(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_ref(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_tuple(ErlNifEnv *env, ERL_NIF_TERM term);
+int enif_is_identical(ERL_NIF_TERM lhs, ERL_NIF_TERM rhs);
+int enif_compare(ERL_NIF_TERM lhs, ERL_NIF_TERM rhs);
+ErlNifTermType enif_term_type(ErlNifEnv *env, ERL_NIF_TERM term);
+
+/* Numbers */
+ERL_NIF_TERM enif_make_int(ErlNifEnv *env, int i);
+ERL_NIF_TERM enif_make_uint(ErlNifEnv *env, unsigned i);
+ERL_NIF_TERM enif_make_long(ErlNifEnv *env, long i);
+ERL_NIF_TERM enif_make_ulong(ErlNifEnv *env, unsigned long i);
+ERL_NIF_TERM enif_make_int64(ErlNifEnv *env, ErlNifSInt64 i);
+ERL_NIF_TERM enif_make_uint64(ErlNifEnv *env, ErlNifUInt64 i);
+ERL_NIF_TERM enif_make_double(ErlNifEnv *env, double d);
+int enif_get_int(ErlNifEnv *env, ERL_NIF_TERM term, int *ip);
+int enif_get_uint(ErlNifEnv *env, ERL_NIF_TERM term, unsigned *ip);
+int enif_get_long(ErlNifEnv *env, ERL_NIF_TERM term, long *ip);
+int enif_get_ulong(ErlNifEnv *env, ERL_NIF_TERM term, unsigned long *ip);
+int enif_get_int64(ErlNifEnv *env, ERL_NIF_TERM term, ErlNifSInt64 *ip);
+int enif_get_uint64(ErlNifEnv *env, ERL_NIF_TERM term, ErlNifUInt64 *ip);
+int enif_get_double(ErlNifEnv *env, ERL_NIF_TERM term, double *dp);
+
+/* Atoms and strings */
+ERL_NIF_TERM enif_make_atom(ErlNifEnv *env, const char *name);
+ERL_NIF_TERM enif_make_atom_len(ErlNifEnv *env, const char *name, size_t len);
+int enif_make_existing_atom(ErlNifEnv *env, const char *name, ERL_NIF_TERM *atom,
+                            ErlNifCharEncoding encoding);
+int enif_make_existing_atom_len(ErlNifEnv *env, const char *name, size_t len,
+                                ERL_NIF_TERM *atom, ErlNifCharEncoding encoding);
+int enif_get_atom(ErlNifEnv *env, ERL_NIF_TERM atom, char *buf, unsigned size,
+                  ErlNifCharEncoding encoding);
+int enif_get_atom_length(ErlNifEnv *env, ERL_NIF_TERM atom, unsigned *len,
+                         ErlNifCharEncoding encoding);
+ERL_NIF_TERM enif_make_string(ErlNifEnv *env, const char *string, ErlNifCharEncoding encoding);
+ERL_NIF_TERM enif_make_string_len(ErlNifEnv *env, const char *string, size_t len,
+                                  ErlNifCharEncoding encoding);
+int enif_get_string(ErlNifEnv *env, ERL_NIF_TERM list, char *buf, unsigned size,
+                    ErlNifCharEncoding encoding);
+
+/* Tuples */
+ERL_NIF_TERM enif_make_tuple(ErlNifEnv *env, unsigned cnt, ...);
+ERL_NIF_TERM enif_make_tuple_from_array(ErlNifEnv *env, const ERL_NIF_TERM arr[], unsigned cnt);
+int enif_get_tuple(ErlNifEnv *env, ERL_NIF_TERM tpl, int *arity, const ERL_NIF_TERM **array);
+
+#define enif_make_tuple1(ENV, E1) enif_make_tuple(ENV, 1, E1)
+#define enif_make_tuple2(ENV, E1, E2) enif_make_tuple(ENV, 2, E1, E2)
+#define enif_make_tuple3(ENV, E1, E2, E3) enif_make_tuple(ENV, 3, E1, E2, E3)
+#define enif_make_tuple4(ENV, E1, E2, E3, E4) enif_make_tuple(ENV, 4, E1, E2, E3, E4)
+#define enif_make_tuple5(ENV, E1, E2, E3, E4, E5) enif_make_tuple(ENV, 5, E1, E2, E3, E4, E5)
+#define enif_make_tuple6(ENV, E1, E2, E3, E4, E5, E6) \
+    enif_make_tuple(ENV, 6, E1, E2, E3, E4, E5, E6)
+#define enif_make_tuple7(ENV, E1, E2, E3, E4, E5, E6, E7) \
+    enif_make_tuple(ENV, 7, E1, E2, E3, E4, E5, E6, E7)
+#define enif_make_tuple8(ENV, E1, E2, E3, E4, E5, E6, E7, E8) \
+    enif_make_tuple(ENV, 8, E1, E2, E3, E4, E5, E6, E7, E8)
+#define enif_make_tuple9(ENV, E1, E2, E3, E4, E5, E6, E7, E8, E9) \
+    enif_make_tuple(ENV, 9, E1, E2, E3, E4, E5, E6, E7, E8, E9)
+
+/* Lists */
+ERL_NIF_TERM enif_make_list(ErlNifEnv *env, unsigned cnt, ...);
+ERL_NIF_TERM enif_make_list_from_array(ErlNifEnv *env, const ERL_NIF_TERM arr[], unsigned cnt);
+ERL_NIF_TERM enif_make_list_cell(ErlNifEnv *env, ERL_NIF_TERM car, ERL_NIF_TERM cdr);
+int enif_make_reverse_list(ErlNifEnv *env, ERL_NIF_TERM term, ERL_NIF_TERM *list);
+int enif_get_list_cell(ErlNifEnv *env, ERL_NIF_TERM term, ERL_NIF_TERM *head, ERL_NIF_TERM *tail);
+int enif_get_list_length(ErlNifEnv *env, ERL_NIF_TERM term, unsigned *len);
+
+#define enif_make_list1(ENV, E1) enif_make_list(ENV, 1, E1)
+#define enif_make_list2(ENV, E1, E2) enif_make_list(ENV, 2, E1, E2)
+#define enif_make_list3(ENV, E1, E2, E3) enif_make_list(ENV, 3, E1, E2, E3)
+#define enif_make_list4(ENV, E1, E2, E3, E4) enif_make_list(ENV, 4, E1, E2, E3, E4)
+#define enif_make_list5(ENV, E1, E2, E3, E4, E5) enif_make_list(ENV, 5, E1, E2, E3, E4, E5)
+#define enif_make_list6(ENV, E1, E2, E3, E4, E5, E6) \
+    enif_make_list(ENV, 6, E1, E2, E3, E4, E5, E6)
+#define enif_make_list7(ENV, E1, E2, E3, E4, E5, E6, E7) \
+    enif_make_list(ENV, 7, E1, E2, E3, E4, E5, E6, E7)
+#define enif_make_list8(ENV, E1, E2, E3, E4, E5, E6, E7, E8) \
+    enif_make_list(ENV, 8, E1, E2, E3, E4, E5, E6, E7, E8)
+#define enif_make_list9(ENV, E1, E2, E3, E4, E5, E6, E7, E8, E9) \
+    enif_make_list(ENV, 9, E1, E2, E3, E4, E5, E6, E7, E8, E9)
+
+/* Maps, which are limited to 64 entries */
+ERL_NIF_TERM enif_make_new_map(ErlNifEnv *env);
+int enif_make_map_from_arrays(ErlNifEnv *env, ERL_NIF_TERM keys[], ERL_NIF_TERM values[],
+                              size_t cnt, ERL_NIF_TERM *map_out);
+int enif_make_map_put(ErlNifEnv *env, ERL_NIF_TERM map_in, ERL_NIF_TERM key, ERL_NIF_TERM value,
+                      ERL_NIF_TERM *map_out);
+int enif_make_map_update(ErlNifEnv *env, ERL_NIF_TERM map_in, ERL_NIF_TERM key,
+                         ERL_NIF_TERM value, ERL_NIF_TERM *map_out);
+int enif_make_map_remove(ErlNifEnv *env, ERL_NIF_TERM map_in, ERL_NIF_TERM key,
+                         ERL_NIF_TERM *map_out);
+int enif_get_map_size(ErlNifEnv *env, ERL_NIF_TERM term, size_t *size);
+int enif_get_map_value(ErlNifEnv *env, ERL_NIF_TERM map, ERL_NIF_TERM key, ERL_NIF_TERM *value);
+int enif_map_iterator_create(ErlNifEnv *env, ERL_NIF_TERM map, ErlNifMapIterator *iter,
+                             ErlNifMapIteratorEntry entry);
+void enif_map_iterator_destroy(ErlNifEnv *env, ErlNifMapIterator *iter);
+int enif_map_iterator_is_head(ErlNifEnv *env, ErlNifMapIterator *iter);
+int enif_map_iterator_is_tail(ErlNifEnv *env, ErlNifMapIterator *iter);
+int enif_map_iterator_next(ErlNifEnv *env, ErlNifMapIterator *iter);
+int enif_map_iterator_prev(ErlNifEnv *env, ErlNifMapIterator *iter);
+int enif_map_iterator_get_pair(ErlNifEnv *env, ErlNifMapIterator *iter, ERL_NIF_TERM *key,
+                               ERL_NIF_TERM *value);
+
+/* Binaries */
+int enif_alloc_binary(size_t size, ErlNifBinary *bin);
+int enif_realloc_binary(ErlNifBinary *bin, size_t size);
+void enif_release_binary(ErlNifBinary *bin);
+int enif_inspect_binary(ErlNifEnv *env, ERL_NIF_TERM bin_term, ErlNifBinary *bin);
+int enif_inspect_iolist_as_binary(ErlNifEnv *env, ERL_NIF_TERM term, ErlNifBinary *bin);
+ERL_NIF_TERM enif_make_binary(ErlNifEnv *env, ErlNifBinary *bin);
+unsigned char *enif_make_new_binary(ErlNifEnv *env, size_t size, ERL_NIF_TERM *termp);
+ERL_NIF_TERM enif_make_sub_binary(ErlNifEnv *env, ERL_NIF_TERM bin_term, size_t pos, size_t size);
+
+/* References */
+ERL_NIF_TERM enif_make_ref(ErlNifEnv *env);
+
+/* Processes */
+ErlNifPid *enif_self(ErlNifEnv *caller_env, ErlNifPid *pid);
+int enif_get_local_pid(ErlNifEnv *env, ERL_NIF_TERM term, ErlNifPid *pid);
+ERL_NIF_TERM enif_make_pid(ErlNifEnv *env, const ErlNifPid *pid);
+int enif_compare_pids(const ErlNifPid *pid1, const ErlNifPid *pid2);
+int enif_is_process_alive(ErlNifEnv *env, ErlNifPid *pid);
+int enif_is_current_process_alive(ErlNifEnv *env);
+int enif_whereis_pid(ErlNifEnv *env, ERL_NIF_TERM name, ErlNifPid *pid);
+int enif_send(ErlNifEnv *caller_env, const ErlNifPid *to_pid, ErlNifEnv *msg_env,
+              ERL_NIF_TERM msg);
+
+/* Resources */
+ErlNifResourceType *enif_open_resource_type(ErlNifEnv *env, const char *module_str,
+                                            const char *name_str, ErlNifResourceDtor *dtor,
+                                            ErlNifResourceFlags flags,
+                                            ErlNifResourceFlags *tried);
+ErlNifResourceType *enif_open_resource_type_x(ErlNifEnv *env, const char *name_str,
+                                              const ErlNifResourceTypeInit *init,
+                                              ErlNifResourceFlags flags,
+                                              ErlNifResourceFlags *tried);
+void *enif_alloc_resource(ErlNifResourceType *type, size_t size);
+void enif_release_resource(void *obj);
+void enif_keep_resource(void *obj);
+ERL_NIF_TERM enif_make_resource(ErlNifEnv *env, void *obj);
+int enif_get_resource(ErlNifEnv *env, ERL_NIF_TERM term, ErlNifResourceType *type, void **objp);
+size_t enif_sizeof_resource(void *obj);
+int enif_monitor_process(ErlNifEnv *caller_env, void *obj, const ErlNifPid *target_pid,
+                         ErlNifMonitor *mon);
+int enif_demonitor_process(ErlNifEnv *caller_env, void *obj, const ErlNifMonitor *mon);
+int enif_compare_monitors(const ErlNifMonitor *monitor1, const ErlNifMonitor *monitor2);
+
+/* Scheduling */
+ERL_NIF_TERM enif_schedule_nif(ErlNifEnv *caller_env, const char *fun_name, int flags,
+                               ERL_NIF_TERM (*fp)(ErlNifEnv *env, int argc,
+                                                  const ERL_NIF_TERM argv[]),
+                               int argc, const ERL_NIF_TERM argv[]);
+int enif_consume_timeslice(ErlNifEnv *env, int percent);
+ErlNifThreadType enif_thread_type(void);
+
+/* Synchronization */
+ErlNifMutex *enif_mutex_create(char *name);
+void enif_mutex_destroy(ErlNifMutex *mtx);
+int enif_mutex_trylock(ErlNifMutex *mtx);
+void enif_mutex_lock(ErlNifMutex *mtx);
+void enif_mutex_unlock(ErlNifMutex *mtx);
+ErlNifRWLock *enif_rwlock_create(char *name);
+void enif_rwlock_destroy(ErlNifRWLock *rwlck);
+int enif_rwlock_tryrlock(ErlNifRWLock *rwlck);
+void enif_rwlock_rlock(ErlNifRWLock *rwlck);
+void enif_rwlock_runlock(ErlNifRWLock *rwlck);
+int enif_rwlock_tryrwlock(ErlNifRWLock *rwlck);
+void enif_rwlock_rwlock(ErlNifRWLock *rwlck);
+void enif_rwlock_rwunlock(ErlNifRWLock *rwlck);
+ErlNifCond *enif_cond_create(char *name);
+void enif_cond_destroy(ErlNifCond *cnd);
+void enif_cond_signal(ErlNifCond *cnd);
+void enif_cond_broadcast(ErlNifCond *cnd);
+void enif_cond_wait(ErlNifCond *cnd, ErlNifMutex *mtx);
+
+#ifdef __cplusplus
+}
+#define ERL_NIF_INIT_EXTERN extern "C" __attribute__((visibility("default")))
+#else
+#define ERL_NIF_INIT_EXTERN __attribute__((visibility("default")))
+#endif
+
+/*
+ * Defines the entry point of a NIF library for the module NAME
+ *
+ * The RELOAD, UPGRADE and UNLOAD callbacks are accepted for compatibility, but never called.
+ */
+#define ERL_NIF_INIT(NAME, FUNCS, LOAD, RELOAD, UPGRADE, UNLOAD)   \
+    ERL_NIF_INIT_EXTERN ErlNifEntry *nif_init(void);               \
+    ERL_NIF_INIT_EXTERN ErlNifEntry *nif_init(void) {              \
+        static ErlNifEntry entry = {                               \
+            ERL_NIF_MAJOR_VERSION,                                 \
+            ERL_NIF_MINOR_VERSION,                                 \
+            #NAME,                                                 \
+            sizeof(FUNCS) / sizeof(*FUNCS),                        \
+            FUNCS,                                                 \
+            LOAD,                                                  \
+            RELOAD,                                                \
+            UPGRADE,                                               \
+            UNLOAD,                                                \
+            ERL_NIF_VM_VARIANT,                                    \
+            1,                                                     \
+            sizeof(ErlNifResourceTypeInit),                        \
+            ERL_NIF_MIN_ERTS_VERSION                               \
+        };                                                         \
+        return &entry;                                             \
+    }
+
+#endif /* __ERL_NIF_H__ */
@@ -0,0 +1,153 @@
+/*
+ * The C interface for embedding the Firefly emulator in another program
+ *
+ * A program links against the emulator library, starts a runtime with
+ * `firefly_runtime_start`, and then calls into Erlang by spawning processes and
+ * waiting for their results, or by sending messages to registered processes.
+ * Erlang code calls back into the program through functions registered with
+ * `firefly_runtime_register_nif`.
+ *
+ * Terms are exchanged as `firefly_value_t`, which own all of their data.
+ * Functions which take a `firefly_value_t *` take ownership of it, while those
+ * which take a `const firefly_value_t *` only borrow it. Values returned via an
+ * out parameter are owned by the caller, and must be freed with
+ * `firefly_value_free`; values returned directly by accessors are borrowed from
+ * the value they were accessed from.
+ *
+ * The runtime relies on global state, so it can only be started once per program.
+ */
+#ifndef FIREFLY_H
+#define FIREFLY_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct firefly_value firefly_value_t;
+typedef struct firefly_runtime firefly_runtime_t;
+typedef struct firefly_call firefly_call_t;
+
+/* The status codes returned by the runtime functions */
+#define FIREFLY_OK 0
+/* The process exited before returning, the result is its exit reason */
+#define FIREFLY_EXITED 1
+/* The function called is not defined */
+#define FIREFLY_UNDEF 2
+/* There is no process registered under the given name */
+#define FIREFLY_NOPROC 3
+/* The runtime stopped before the request could be completed */
+#define FIREFLY_STOPPED 4
+/* An argument is NULL, or a name is not valid UTF-8 or too long for an atom */
+#define FIREFLY_BADARG 5
+/* A runtime was already started by this program */
+#define FIREFLY_ALREADY_STARTED 6
+#define FIREFLY_INVALID_BYTECODE 7
+#define FIREFLY_INIT_FAILED 8
+/* The system was halted with a non-zero status */
+#define FIREFLY_HALTED 9
+
+typedef enum firefly_value_kind {
+    FIREFLY_ATOM,
+    FIREFLY_INTEGER,
+    FIREFLY_FLOAT,
+    FIREFLY_BINARY,
+    FIREFLY_TUPLE,
+    FIREFLY_LIST,
+    FIREFLY_MAP,
+    FIREFLY_PID,
+    /* A term with no representation outside of a process, e.g. a fun or reference */
+    FIREFLY_OPAQUE,
+} firefly_value_kind_t;
+
+/*
+ * A function implemented by the program, callable from Erlang
+ *
+ * Returns zero with the returned value in `result`, or non-zero to raise an
+ * error with the reason in `result`, or `badarg` if it is left NULL.
+ */
+typedef int (*firefly_nif_t)(void *data, size_t argc,
+                             const firefly_value_t *const argv[],
+                             firefly_value_t **result);
+
+/* Constructing values */
+firefly_value_t *firefly_value_atom(const char *name);
+firefly_value_t *firefly_value_int(int64_t i);
+firefly_value_t *firefly_value_float(double x);
+firefly_value_t *firefly_value_binary(const uint8_t *bytes, size_t len);
+firefly_value_t *firefly_value_tuple(firefly_value_t *const elements[], size_t len);
+/* `tail` may be NULL, in which case the list is proper */
+firefly_value_t *firefly_value_list(firefly_value_t *const elements[], size_t len,
+                                    firefly_value_t *tail);
+firefly_value_t *firefly_value_map(firefly_value_t *const keys[],
+                                   firefly_value_t *const values[], size_t len);
+firefly_value_t *firefly_value_clone(const firefly_value_t *value);
+void firefly_value_free(firefly_value_t *value);
+
+/* Inspecting values */
+firefly_value_kind_t firefly_value_kind(const firefly_value_t *value);
+/* Returns false if the value is not an integer, or does not fit in 64 bits */
+bool firefly_value_get_int(const firefly_value_t *value, int64_t *out);
+bool firefly_value_get_float(const firefly_value_t *value, double *out);
+/*
+ * Returns the name of an atom, the contents of a binary, or the printed form of
+ * an opaque value, which are not NUL-terminated; NULL for any other value
+ */
+const uint8_t *firefly_value_bytes(const firefly_value_t *value, size_t *len);
+/* Returns the number of elements in a tuple or list, or entries in a map */
+size_t firefly_value_len(const firefly_value_t *value);
+const firefly_value_t *firefly_value_element(const firefly_value_t *value, size_t index);
+/* Returns the tail of an improper list, or NULL */
+const firefly_value_t *firefly_value_list_tail(const firefly_value_t *value);
+const firefly_value_t *firefly_value_map_key(const firefly_value_t *value, size_t index);
+const firefly_value_t *firefly_value_map_value(const firefly_value_t *value, size_t index);
+/* Writes the printed form of the value like `snprintf`, returning its full length */
+size_t firefly_value_format(const firefly_value_t *value, char *buf, size_t size);
+
+/*
+ * Starts the runtime with `len` bytes of bytecode, or the bytecode linked into
+ * the program if `bytecode` is NULL, running processes on `schedulers` threads
+ */
+int firefly_runtime_start(const uint8_t *bytecode, size_t len, size_t schedulers,
+                          firefly_runtime_t **runtime);
+/*
+ * Calls `module:function(args...)` in a new process, blocking until it exits
+ *
+ * Takes ownership of the arguments. On FIREFLY_OK, `result` receives the
+ * returned value; on FIREFLY_EXITED, it receives the exit reason.
+ */
+int firefly_runtime_call(const firefly_runtime_t *runtime, const char *module,
+                         const char *function, firefly_value_t *const args[],
+                         size_t argc, firefly_value_t **result);
+/* Like `firefly_runtime_call`, but returns a handle to wait on later */
+int firefly_runtime_spawn(const firefly_runtime_t *runtime, const char *module,
+                          const char *function, firefly_value_t *const args[],
+                          size_t argc, firefly_call_t **call);
+/* Blocks until the call completes, consuming it */
+int firefly_call_wait(firefly_call_t *call, firefly_value_t **result);
+/* Abandons the call without waiting for it */
+void firefly_call_free(firefly_call_t *call);
+/* Sends `message` to the process registered as `name`, taking ownership of it */
+int firefly_runtime_send(const firefly_runtime_t *runtime, const char *name,
+                         firefly_value_t *message);
+/*
+ * Registers `fun` as the implementation of `module:function/arity`, which must
+ * be declared as a NIF in the loaded bytecode
+ *
+ * `fun` is called on scheduler threads with `data`, which must be safe to use
+ * from any thread.
+ */
+int firefly_runtime_register_nif(const firefly_runtime_t *runtime, const char *module,
+                                 const char *function, uint8_t arity,
+                                 firefly_nif_t fun, void *data);
+/* Stops the schedulers, waits for them to exit, and frees the runtime */
+int firefly_runtime_shutdown(firefly_runtime_t *runtime);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FIREFLY_H */
@@ -1,10 +1,18 @@
 mod debugging;
+mod nif;
 mod operators;
 mod signals;
+mod system;
+mod time;
+mod timers;
 
 pub use self::debugging::*;
+pub use self::nif::*;
 pub use self::operators::*;
 pub use self::signals::*;
+pub use self::system::*;
+pub use self::time::*;
+pub use self::timers::*;
 
 use std::cmp;
 use std::sync::atomic::Ordering;
@@ -595,6 +603,21 @@ pub extern "C-unwind" fn process_flag3(
     }
 }
 
+/// Returns the group leader of the calling process, or the process itself if it has none
+#[export_name = "erlang:group_leader/0"]
+pub extern "C-unwind" fn group_leader0(process: &mut ProcessLock) -> ErlangResult {
+    let group_leader = process
+        .group_leader()
+        .cloned()
+        .unwrap_or_else(|| process.pid());
+    loop {
+        match Gc::new_in(group_leader.clone(), process) {
+            Ok(pid) => return ErlangResult::Ok(pid.into()),
+            Err(_) => assert!(garbage_collect(process, Default::default()).is_ok()),
+        }
+    }
+}
+
 #[export_name = "erlang:yield/0"]
 pub extern "C-unwind" fn yield0(process: &mut ProcessLock) -> ErlangResult {
     // Force a yield when this function returns
@@ -0,0 +1,33 @@
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::bifs::erlang::alloc_result;
+use crate::emulator::current_scheduler;
+use crate::erl_nif;
+use crate::nifs::file::filename_from_term;
+
+/// Loads the NIF library at `path` on behalf of the calling module, see [`crate::erl_nif`]
+#[export_name = "erlang:load_nif/2"]
+pub extern "C-unwind" fn load_nif2(
+    process: &mut ProcessLock,
+    path: OpaqueTerm,
+    load_info: OpaqueTerm,
+) -> ErlangResult {
+    let Some(path) = filename_from_term(path) else { badarg!(process, path) };
+    // The library is loaded on behalf of the module which called us
+    let code = current_scheduler().code();
+    let Some(caller) = code.function_by_ip(process.ip - 1).mfa() else { badarg!(process, path) };
+    let module = caller.module;
+    match erl_nif::load(process, module, &path, load_info) {
+        Ok(()) => ErlangResult::Ok(atoms::Ok.into()),
+        Err(err) => alloc_result(process, |heap| {
+            let text = Cons::charlist_from_str(err.text(), heap)?
+                .map(|list| list.into())
+                .unwrap_or(OpaqueTerm::NIL);
+            let reason = Tuple::from_slice(&[err.reason().into(), text], heap)?;
+            Ok(Tuple::from_slice(&[atoms::Error.into(), reason.into()], heap)?.into())
+        }),
+    }
+}
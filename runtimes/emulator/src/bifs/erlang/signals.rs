@@ -6,14 +6,17 @@ use std::sync::Arc;
 use firefly_alloc::heap::Heap;
 use firefly_rt::function::{ErlangResult, ModuleFunctionArity};
 use firefly_rt::gc::{garbage_collect, Gc, RootSet};
-use firefly_rt::process::monitor::{Monitor, MonitorEntry, MonitorFlags, UnaliasMode};
-use firefly_rt::process::signals::Signal;
+use firefly_rt::process::monitor::{
+    LocalMonitorInfo, Monitor, MonitorEntry, MonitorFlags, UnaliasMode,
+};
+use firefly_rt::process::signals::{self, Message, Signal, SignalEntry};
 use firefly_rt::process::{Process, ProcessFlags, ProcessLock, StatusFlags, SystemTask, ARG0_REG};
 use firefly_rt::scheduler::Scheduler;
 use firefly_rt::services::registry::{self, Registrant, WeakAddress};
 use firefly_rt::term::*;
 
 use crate::badarg;
+use crate::bifs::erlang::alloc_result;
 use crate::emulator::{current_scheduler, Action};
 
 #[export_name = "erlang:unlink/1"]
@@ -130,6 +133,200 @@ pub extern "C-unwind" fn unalias(process: &mut ProcessLock, alias: OpaqueTerm) -
     badarg!(process, alias)
 }
 
+/// Monitors the process `item`, i.e. a local pid or registered name, returning the reference
+/// which identifies the monitor
+///
+/// Only `process` monitors without options are supported. If the process does not exist, the
+/// `DOWN` message is delivered right away, with reason `noproc`.
+#[export_name = "erlang:monitor/2"]
+pub extern "C-unwind" fn monitor2(
+    process: &mut ProcessLock,
+    mut ty: OpaqueTerm,
+    mut item: OpaqueTerm,
+) -> ErlangResult {
+    let heap_available = process.heap.heap_available();
+    if heap_available < mem::size_of::<Reference>() {
+        process.gc_needed = mem::size_of::<Reference>();
+        let mut roots = RootSet::default();
+        roots += &mut ty as *mut _;
+        roots += &mut item as *mut _;
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    if !ty.is_atom() || ty.as_atom() != atoms::Process {
+        badarg!(process, ty);
+    }
+
+    let (target, name) = match item.into() {
+        Term::Pid(pid) if pid.is_local() => (registry::get_by_pid(&pid), None),
+        Term::Atom(name) => match registry::get_by_name(name) {
+            Some(Registrant::Process(target)) => (Some(target), Some(name)),
+            _ => (None, Some(name)),
+        },
+        _ => badarg!(process, item),
+    };
+
+    let reference_id = current_scheduler().next_reference_id();
+    let monitor_ref = Gc::new_in(Reference::new(reference_id), process).unwrap();
+
+    let Some(target) = target else {
+        send_noproc_down(process, reference_id, item.into());
+        return ErlangResult::Ok(monitor_ref.into());
+    };
+
+    let name_or_tag = name.map(OpaqueTerm::from).unwrap_or(OpaqueTerm::NONE);
+    let monitor = MonitorEntry::new(Monitor::LocalProcess {
+        origin: process.id(),
+        target: target.id(),
+        info: LocalMonitorInfo {
+            reference: reference_id,
+            name_or_tag: TermFragment::new(name_or_tag.into()).unwrap(),
+        },
+    });
+    process.monitored.insert(monitor.clone());
+    // If the target is exiting, it answers the monitor signal with a `noproc` DOWN
+    target.send_signal(Signal::monitor(monitor)).ok();
+
+    ErlangResult::Ok(monitor_ref.into())
+}
+
+#[export_name = "erlang:demonitor/1"]
+pub extern "C-unwind" fn demonitor1(
+    process: &mut ProcessLock,
+    reference: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(monitor_ref) = reference.into() else { badarg!(process, reference) };
+
+    demonitor(process, monitor_ref.id());
+
+    ErlangResult::Ok(true.into())
+}
+
+/// Like `demonitor/1`, but with `flush` also removing a `DOWN` message for the monitor from the
+/// message queue, and with `info` returning whether the monitor was still active
+#[export_name = "erlang:demonitor/2"]
+pub extern "C-unwind" fn demonitor2(
+    process: &mut ProcessLock,
+    reference: OpaqueTerm,
+    opts: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(monitor_ref) = reference.into() else { badarg!(process, reference) };
+
+    let mut flush = false;
+    let mut info = false;
+    match opts.into() {
+        Term::Nil => (),
+        Term::Cons(demonitor_opts) => {
+            for result in demonitor_opts.iter_raw() {
+                match result {
+                    Ok(opt) if opt.is_atom() && opt.as_atom() == atoms::Flush => flush = true,
+                    Ok(opt) if opt.is_atom() && opt.as_atom() == atoms::Info => info = true,
+                    _ => badarg!(process, opts),
+                }
+            }
+        }
+        _ => badarg!(process, opts),
+    }
+
+    let id = monitor_ref.id();
+    let removed = demonitor(process, id);
+    if flush {
+        // DOWN messages are placed in the private queue when converted from their signal, and the
+        // monitor is gone now, so no more can arrive
+        let mut signals = process.signals().lock();
+        signals.remove_first_message(|message| is_down_message(message, id));
+    }
+
+    ErlangResult::Ok((removed || !info).into())
+}
+
+/// Removes the monitor identified by `reference` from those set up by `process`, and tells its
+/// target to do the same
+///
+/// Returns false if there is no such monitor, e.g. because it has already been triggered.
+fn demonitor(process: &mut ProcessLock, reference: ReferenceId) -> bool {
+    let mut cursor = process.monitored.find_mut(&reference);
+    let Some(entry) = cursor.get() else { return false; };
+    // Aliases are only removed by unalias/1
+    if let Monitor::Alias { .. } = entry.monitor {
+        return false;
+    }
+    let monitor = cursor.remove().unwrap();
+
+    let target = monitor.target().and_then(|target| target.try_resolve());
+    if let Some(Registrant::Process(target)) = target {
+        target
+            .send_signal(SignalEntry::new(Signal::Demonitor(signals::Demonitor {
+                sender: process.addr(),
+                monitor,
+            })))
+            .ok();
+    }
+
+    true
+}
+
+/// Returns true if `message` is the `DOWN` message of the monitor identified by `reference`, i.e.
+/// `{_, Reference, _, _, _}`
+fn is_down_message(message: &Message, reference: ReferenceId) -> bool {
+    let Term::Tuple(down) = message.message.term.into() else { return false; };
+    if down.len() != 5 {
+        return false;
+    }
+    match down[1].into() {
+        Term::Reference(monitor_ref) => monitor_ref.id() == reference,
+        _ => false,
+    }
+}
+
+/// Sends `{'DOWN', Reference, process, Item, noproc}` to `process`, for a monitor of `item` which
+/// could not be set up because there is no such process
+///
+/// As with other monitors by name, a registered name is reported as `{Name, Node}`.
+fn send_noproc_down(process: &mut ProcessLock, reference: ReferenceId, item: Term) {
+    let mut layout = LayoutBuilder::new();
+    if let Term::Atom(_) = item {
+        layout.build_tuple(2);
+    } else {
+        layout += item.layout();
+    }
+    layout.build_reference().build_tuple(5);
+    let fragment_ptr = layout.into_fragment().unwrap();
+    let fragment = unsafe { fragment_ptr.as_ref() };
+
+    let item: OpaqueTerm = match item {
+        Term::Atom(name) => {
+            Tuple::from_slice(&[name.into(), atoms::NoNodeAtNoHost.into()], fragment)
+                .unwrap()
+                .into()
+        }
+        item => unsafe { item.unsafe_clone_to_heap(fragment) }.into(),
+    };
+    let mref = Gc::new_in(Reference::new(reference), fragment).unwrap();
+    let down = Tuple::from_slice(
+        &[
+            atoms::DOWN.into(),
+            mref.into(),
+            atoms::Process.into(),
+            item,
+            atoms::Noproc.into(),
+        ],
+        fragment,
+    )
+    .unwrap();
+
+    let sender = process.addr();
+    process
+        .send_fragment(
+            sender,
+            TermFragment {
+                term: down.into(),
+                fragment: Some(fragment_ptr),
+            },
+        )
+        .ok();
+}
+
 static HANDLE_SIGNALS_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
     module: atoms::ErtsInternal,
     function: atoms::HandleSignals,
@@ -225,6 +422,146 @@ pub extern "C-unwind" fn is_process_alive2(
     ErlangResult::Ok(atoms::Ok.into())
 }
 
+/// The items of information supported by `process_info/2`, all of which have immediate values
+const PROCESS_INFO_ITEMS: [Atom; 8] = [
+    atoms::Status,
+    atoms::Priority,
+    atoms::RegisteredName,
+    atoms::MessageQueueLen,
+    atoms::TrapExit,
+    atoms::Reductions,
+    atoms::HeapSize,
+    atoms::StackSize,
+];
+
+/// Returns `{Item, Value}` for the given item of information about a local process, or
+/// `undefined` if the process is not alive
+///
+/// The `status` and `priority` of another process are read from its status flags, so that they
+/// can be reported while it is running on a dirty scheduler. Other items are read by the process
+/// itself when it handles the signal sent by `erts_internal:process_info/3`.
+#[export_name = "erlang:process_info/2"]
+pub extern "C-unwind" fn process_info2(
+    process: &mut ProcessLock,
+    pid_term: OpaqueTerm,
+    item: OpaqueTerm,
+) -> ErlangResult {
+    static PROCESS_INFO_TRAP_EXPORT: ModuleFunctionArity = ModuleFunctionArity {
+        module: atoms::ErtsInternal,
+        function: atoms::ProcessInfo,
+        arity: 2,
+    };
+
+    let Term::Pid(pid) = pid_term.into() else { badarg!(process, pid_term) };
+    if !pid.is_local() {
+        badarg!(process, pid_term);
+    }
+    let Term::Atom(name) = item.into() else { badarg!(process, item) };
+    if !PROCESS_INFO_ITEMS.contains(&name) {
+        badarg!(process, item);
+    }
+    let value = if process.id() == pid.id() {
+        let msgq_len = process.signals().lock().len();
+        process_info_value(process, name, msgq_len)
+    } else {
+        let status = match registry::get_by_pid(&pid) {
+            Some(other) => other.status(Ordering::Acquire),
+            None => return ErlangResult::Ok(atoms::Undefined.into()),
+        };
+        match name {
+            a if a == atoms::Status => status_to_atom(status).into(),
+            a if a == atoms::Priority => status.priority().into(),
+            _ => {
+                process.stack.store(ARG0_REG, pid_term);
+                process.stack.store(ARG0_REG + 1, item);
+                return ErlangResult::Trap(&PROCESS_INFO_TRAP_EXPORT);
+            }
+        }
+    };
+    alloc_result(process, |heap| {
+        Ok(Tuple::from_slice(&[item, value], heap)?.into())
+    })
+}
+
+/// Sends a request for `item` to the process `pid`, which replies with `{Ref, Result}`, where
+/// `Result` is the value `process_info/2` returns, returning false if the process is not alive
+#[export_name = "erts_internal:process_info/3"]
+pub extern "C-unwind" fn process_info3(
+    process: &mut ProcessLock,
+    pid_term: OpaqueTerm,
+    item: OpaqueTerm,
+    ref_term: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Pid(pid) = pid_term.into() else { badarg!(process, pid_term) };
+    let Term::Atom(name) = item.into() else { badarg!(process, item) };
+    if !PROCESS_INFO_ITEMS.contains(&name) {
+        badarg!(process, item);
+    }
+    let Term::Reference(req_ref) = ref_term.into() else { badarg!(process, ref_term) };
+
+    let sent = match registry::get_by_pid(&pid) {
+        None => false,
+        Some(other) => {
+            let need_msgq_len = name == atoms::MessageQueueLen;
+            let sig = Signal::process_info(
+                process.strong(),
+                name,
+                req_ref.deref().clone(),
+                need_msgq_len,
+            );
+            other.send_signal(sig).is_ok()
+        }
+    };
+
+    ErlangResult::Ok(sent.into())
+}
+
+/// Returns the value of `item` for `process`, as reported by `process_info/2`, given the length
+/// of its message queue
+///
+/// This must only be called with one of the items in `PROCESS_INFO_ITEMS`.
+pub(crate) fn process_info_value(process: &ProcessLock, item: Atom, msgq_len: usize) -> OpaqueTerm {
+    match item {
+        a if a == atoms::Status => status_to_atom(process.status(Ordering::Acquire)).into(),
+        a if a == atoms::Priority => process.status(Ordering::Acquire).priority().into(),
+        a if a == atoms::RegisteredName => match process.registered_name() {
+            Some(name) => name.into(),
+            None => OpaqueTerm::NIL,
+        },
+        a if a == atoms::MessageQueueLen => Term::Int(msgq_len as i64).into(),
+        a if a == atoms::TrapExit => process.flags.contains(ProcessFlags::TRAP_EXIT).into(),
+        a if a == atoms::Reductions => Term::Int(process.reductions as i64).into(),
+        // Sizes are reported in words, as in OTP
+        a if a == atoms::HeapSize => {
+            Term::Int((process.heap.heap_size() / mem::size_of::<OpaqueTerm>()) as i64).into()
+        }
+        a if a == atoms::StackSize => Term::Int(process.stack.size() as i64).into(),
+        _ => unreachable!(),
+    }
+}
+
+/// Returns the atom used by `process_info/2` to describe a process with `status`
+///
+/// A process which has been migrated to a dirty scheduler is `running` while the dirty scheduler
+/// executes it, and `runnable` while it waits for one.
+fn status_to_atom(status: StatusFlags) -> Atom {
+    if status.intersects(StatusFlags::EXITING | StatusFlags::FREE) {
+        atoms::Exiting
+    } else if status.contains(StatusFlags::GC) {
+        atoms::GarbageCollecting
+    } else if status.intersects(StatusFlags::RUNNING | StatusFlags::RUNNING_SYS) {
+        atoms::Running
+    } else if status.is_dirty() {
+        atoms::Runnable
+    } else if status.contains(StatusFlags::SUSPENDED) {
+        atoms::Suspended
+    } else if status.intersects(StatusFlags::ACTIVE | StatusFlags::ACTIVE_SYS) {
+        atoms::Runnable
+    } else {
+        atoms::Waiting
+    }
+}
+
 #[export_name = "erts_internal:handle_signals/2"]
 pub extern "C-unwind" fn handle_signals2(
     process: &mut ProcessLock,
@@ -397,30 +734,31 @@ pub extern "C-unwind" fn garbage_collect1(
 
     let target = target.unwrap();
 
-    if signal {
-        let status = target.status(Ordering::Acquire);
-        if status.contains(StatusFlags::EXITING) {
-            notify_sys_task_executed(process, system_task, false.into());
-            return ErlangResult::Ok(atoms::Ok.into());
-        }
-        if status.intersects(StatusFlags::HAS_PENDING_SIGNALS | StatusFlags::HAS_IN_TRANSIT_SIGNALS)
+    let status = target.status(Ordering::Acquire);
+    if signal && status.contains(StatusFlags::EXITING) {
+        notify_sys_task_executed(process, system_task, false.into());
+        return ErlangResult::Ok(atoms::Ok.into());
+    }
+    // A process on a dirty scheduler is locked until its work there is done, so rather than
+    // waiting on it, the task is scheduled by the process itself once it handles its signals
+    let has_signals =
+        status.intersects(StatusFlags::HAS_PENDING_SIGNALS | StatusFlags::HAS_IN_TRANSIT_SIGNALS);
+    if status.is_dirty() || (signal && has_signals) {
+        // Send rpc request signal without reply, and reply from the system task...
+        let st = Box::into_raw(system_task);
+        if target
+            .send_signal(Signal::rpc_noreply(
+                process.pid(),
+                schedule_sig_sys_task,
+                st.cast(),
+                priority,
+            ))
+            .is_err()
         {
-            // Send rpc request signal without reply, and reply from the system task...
-            let st = Box::into_raw(system_task);
-            if target
-                .send_signal(Signal::rpc_noreply(
-                    process.pid(),
-                    schedule_sig_sys_task,
-                    st.cast(),
-                    priority,
-                ))
-                .is_err()
-            {
-                notify_sys_task_executed(process, unsafe { Box::from_raw(st) }, false.into());
-            }
-            // Signal sent
-            return ErlangResult::Ok(atoms::Ok.into());
+            notify_sys_task_executed(process, unsafe { Box::from_raw(st) }, false.into());
         }
+        // Signal sent
+        return ErlangResult::Ok(atoms::Ok.into());
     }
 
     if let Err(system_task) = schedule_sys_task(process, target, system_task) {
@@ -540,3 +878,93 @@ fn send_sys_task_executed_reply(
     )
     .ok();
 }
+
+#[cfg(test)]
+mod tests {
+    use crossbeam::deque::Injector;
+
+    use firefly_rt::scheduler::SchedulerId;
+
+    use super::*;
+
+    #[test]
+    fn process_status_test() {
+        assert_eq!(status_to_atom(StatusFlags::empty()), atoms::Waiting);
+        assert_eq!(status_to_atom(StatusFlags::ACTIVE), atoms::Runnable);
+        assert_eq!(
+            status_to_atom(StatusFlags::ACTIVE | StatusFlags::RUNNING),
+            atoms::Running
+        );
+        assert_eq!(status_to_atom(StatusFlags::SUSPENDED), atoms::Suspended);
+        assert_eq!(
+            status_to_atom(StatusFlags::RUNNING | StatusFlags::GC),
+            atoms::GarbageCollecting
+        );
+        assert_eq!(
+            status_to_atom(StatusFlags::RUNNING | StatusFlags::EXITING),
+            atoms::Exiting
+        );
+
+        // A process on a dirty scheduler is running, or waiting to be run by one
+        let dirty_io = StatusFlags::ACTIVE | StatusFlags::DIRTY_IO_PROC;
+        assert_eq!(status_to_atom(dirty_io), atoms::Runnable);
+        assert_eq!(
+            status_to_atom(dirty_io | StatusFlags::RUNNING),
+            atoms::Running
+        );
+        let dirty_cpu = StatusFlags::ACTIVE | StatusFlags::DIRTY_CPU_PROC;
+        assert_eq!(
+            status_to_atom(dirty_cpu | StatusFlags::SUSPENDED),
+            atoms::Runnable
+        );
+        assert_eq!(
+            status_to_atom(dirty_cpu | StatusFlags::RUNNING),
+            atoms::Running
+        );
+    }
+
+    #[test]
+    fn process_info_items_test() {
+        let mfa = ModuleFunctionArity {
+            module: Atom::try_from("process_info_test").unwrap(),
+            function: Atom::try_from("start").unwrap(),
+            arity: 0,
+        };
+        let process = Process::new(
+            SchedulerId::INVALID,
+            None,
+            None,
+            mfa,
+            &[],
+            Arc::new(Injector::new()),
+            Default::default(),
+        );
+        let mut locked = process.lock();
+        let info = |locked: &ProcessLock, item| Term::from(process_info_value(locked, item, 3));
+
+        // New processes are ready to run
+        assert_eq!(info(&locked, atoms::Status), Term::Atom(atoms::Runnable));
+        assert_eq!(info(&locked, atoms::Priority), Term::Atom(atoms::Normal));
+        assert_eq!(info(&locked, atoms::RegisteredName), Term::Nil);
+        assert_eq!(info(&locked, atoms::MessageQueueLen), Term::Int(3));
+        assert_eq!(info(&locked, atoms::TrapExit), Term::Bool(false));
+        locked.flags |= ProcessFlags::TRAP_EXIT;
+        assert_eq!(info(&locked, atoms::TrapExit), Term::Bool(true));
+        locked.reductions = 42;
+        assert_eq!(info(&locked, atoms::Reductions), Term::Int(42));
+        let heap_size = locked.heap.heap_size() / mem::size_of::<OpaqueTerm>();
+        assert_ne!(heap_size, 0);
+        assert_eq!(info(&locked, atoms::HeapSize), Term::Int(heap_size as i64));
+        let stack_size = locked.stack.size();
+        assert_eq!(
+            info(&locked, atoms::StackSize),
+            Term::Int(stack_size as i64)
+        );
+
+        process.set_status_flags(
+            StatusFlags::DIRTY_CPU_PROC | StatusFlags::RUNNING,
+            Ordering::Release,
+        );
+        assert_eq!(info(&locked, atoms::Status), Term::Atom(atoms::Running));
+    }
+}
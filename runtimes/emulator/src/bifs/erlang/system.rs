@@ -0,0 +1,341 @@
+use std::alloc::AllocError;
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+
+use firefly_number::Int;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{self, garbage_collect, Gc};
+use firefly_rt::process::{ProcessHeap, ProcessId, ProcessLock};
+use firefly_rt::scheduler;
+use firefly_rt::services::registry;
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+use crate::time;
+
+/// The OTP release we are compatible with, as reported by `erlang:system_info(otp_release)`
+const OTP_RELEASE: &'static str = match option_env!("OTP_RELEASE") {
+    Some(release) => release,
+    None => "25",
+};
+
+/// The name of the runtime system, as reported by `erlang:system_info(machine)`
+const MACHINE: &'static str = "Firefly";
+
+static SCHEDULER_WALL_TIME: AtomicBool = AtomicBool::new(false);
+static LAST_REDUCTIONS: AtomicU64 = AtomicU64::new(0);
+static LAST_RUNTIME: AtomicU64 = AtomicU64::new(0);
+static LAST_WALL_CLOCK: AtomicU64 = AtomicU64::new(0);
+
+#[export_name = "erlang:system_info/1"]
+pub extern "C-unwind" fn system_info1(process: &mut ProcessLock, item: OpaqueTerm) -> ErlangResult {
+    match item.into() {
+        Term::Atom(a) if a == atoms::TimeWarpMode => {
+            ErlangResult::Ok(time::time_warp_mode().as_atom().into())
+        }
+        Term::Atom(a) if a == atoms::TimeOffset => {
+            ErlangResult::Ok(time::time_offset_state().as_atom().into())
+        }
+        Term::Atom(a) if a == atoms::Schedulers => {
+            ErlangResult::Ok(Term::Int(crate::NUM_SCHEDULERS as i64).into())
+        }
+        Term::Atom(a) if a == atoms::SchedulersOnline => {
+            ErlangResult::Ok(Term::Int(scheduler::online() as i64).into())
+        }
+        Term::Atom(a) if a == atoms::DirtyCpuSchedulers || a == atoms::DirtyCpuSchedulersOnline => {
+            ErlangResult::Ok(Term::Int(scheduler::dirty_cpu() as i64).into())
+        }
+        Term::Atom(a) if a == atoms::DirtyIoSchedulers => {
+            ErlangResult::Ok(Term::Int(scheduler::dirty_io() as i64).into())
+        }
+        Term::Atom(a) if a == atoms::ProcessCount => {
+            ErlangResult::Ok(Term::Int(registry::process_count() as i64).into())
+        }
+        Term::Atom(a) if a == atoms::ProcessLimit => {
+            ErlangResult::Ok(Term::Int(ProcessId::LIMIT as i64).into())
+        }
+        Term::Atom(a) if a == atoms::AtomCount => {
+            let count = atom::with_atom_table_readonly(|atoms| atoms.len());
+            ErlangResult::Ok(Term::Int(count as i64).into())
+        }
+        Term::Atom(a) if a == atoms::AtomLimit => {
+            ErlangResult::Ok(Term::Int(atom::MAX_ATOMS as i64).into())
+        }
+        Term::Atom(a) if a == atoms::OtpRelease => charlist_result(process, OTP_RELEASE),
+        Term::Atom(a) if a == atoms::Version => charlist_result(process, env!("CARGO_PKG_VERSION")),
+        Term::Atom(a) if a == atoms::Wordsize => {
+            ErlangResult::Ok(Term::Int(mem::size_of::<usize>() as i64).into())
+        }
+        Term::Atom(a) if a == atoms::LogicalProcessors => match thread::available_parallelism() {
+            Ok(n) => ErlangResult::Ok(Term::Int(n.get() as i64).into()),
+            Err(_) => ErlangResult::Ok(atoms::Unknown.into()),
+        },
+        Term::Atom(a) if a == atoms::Machine => charlist_result(process, MACHINE),
+        _ => badarg!(process, item),
+    }
+}
+
+#[export_name = "erlang:system_flag/2"]
+pub extern "C-unwind" fn system_flag2(
+    process: &mut ProcessLock,
+    flag: OpaqueTerm,
+    value: OpaqueTerm,
+) -> ErlangResult {
+    match flag.into() {
+        Term::Atom(a) if a == atoms::TimeOffset => {
+            if value != atoms::Finalize {
+                badarg!(process, value);
+            }
+            ErlangResult::Ok(time::finalize_time_offset().as_atom().into())
+        }
+        Term::Atom(a) if a == atoms::SchedulerWallTime => {
+            let enabled = match value {
+                OpaqueTerm::TRUE => true,
+                OpaqueTerm::FALSE => false,
+                _ => badarg!(process, value),
+            };
+            ErlangResult::Ok(SCHEDULER_WALL_TIME.swap(enabled, Ordering::Relaxed).into())
+        }
+        _ => badarg!(process, flag),
+    }
+}
+
+#[export_name = "erlang:statistics/1"]
+pub extern "C-unwind" fn statistics1(process: &mut ProcessLock, item: OpaqueTerm) -> ErlangResult {
+    match item.into() {
+        Term::Atom(a) if a == atoms::Reductions => {
+            let total = scheduler::all().iter().map(|s| s.reductions()).sum();
+            since_last_call_result(process, total, &LAST_REDUCTIONS)
+        }
+        Term::Atom(a) if a == atoms::Runtime => {
+            let total = time::cpu_time().as_millis() as u64;
+            since_last_call_result(process, total, &LAST_RUNTIME)
+        }
+        Term::Atom(a) if a == atoms::WallClock => {
+            let total = (time::monotonic_time() / 1_000_000) as u64;
+            since_last_call_result(process, total, &LAST_WALL_CLOCK)
+        }
+        Term::Atom(a) if a == atoms::RunQueue => {
+            let local: usize = scheduler::all().iter().map(|s| s.run_queue_len()).sum();
+            let total = local + current_scheduler().global_run_queue_len();
+            ErlangResult::Ok(Term::Int(total as i64).into())
+        }
+        Term::Atom(a) if a == atoms::RunQueueLengths => {
+            let lengths = scheduler::all()
+                .iter()
+                .map(|s| Term::Int(s.run_queue_len() as i64).into())
+                .collect::<Vec<OpaqueTerm>>();
+            alloc_result(process, |heap| list_to_term(&lengths, heap))
+        }
+        Term::Atom(a) if a == atoms::SchedulerWallTime => {
+            if !SCHEDULER_WALL_TIME.load(Ordering::Relaxed) {
+                return ErlangResult::Ok(atoms::Undefined.into());
+            }
+            // Scheduler ids are zero-based internally, but one-based in Erlang
+            let wall_times = scheduler::all()
+                .iter()
+                .map(|s| {
+                    let (active, total) = s.wall_time();
+                    let id = s.id().as_u16() as u64 + 1;
+                    (id, active.as_nanos() as u64, total.as_nanos() as u64)
+                })
+                .collect::<Vec<_>>();
+            alloc_result(process, |heap| {
+                let mut elements = Vec::with_capacity(wall_times.len());
+                for (id, active, total) in wall_times.iter().copied() {
+                    let tuple = Tuple::from_slice(
+                        &[
+                            int_to_term(id, heap)?,
+                            int_to_term(active, heap)?,
+                            int_to_term(total, heap)?,
+                        ],
+                        heap,
+                    )?;
+                    elements.push(tuple.into());
+                }
+                list_to_term(&elements, heap)
+            })
+        }
+        Term::Atom(a) if a == atoms::GarbageCollection => {
+            let (count, words_reclaimed) = gc::statistics();
+            alloc_result(process, |heap| {
+                let elements = [
+                    int_to_term(count, heap)?,
+                    int_to_term(words_reclaimed, heap)?,
+                    Term::Int(0).into(),
+                ];
+                Ok(Tuple::from_slice(&elements, heap)?.into())
+            })
+        }
+        _ => badarg!(process, item),
+    }
+}
+
+/// Returns a list of `{Type, Size}` for each memory type, where `Size` is in bytes
+#[export_name = "erlang:memory/0"]
+pub extern "C-unwind" fn memory0(process: &mut ProcessLock) -> ErlangResult {
+    let types = [
+        atoms::Total,
+        atoms::Processes,
+        atoms::System,
+        atoms::AtomMemory,
+        atoms::Binary,
+        atoms::Code,
+        atoms::Ets,
+    ];
+    let usage = types.map(|ty| (ty, memory_usage(ty).unwrap()));
+    alloc_result(process, |heap| memory_to_term(&usage, heap))
+}
+
+/// Returns the memory used by the given memory type in bytes, or when given a list of memory
+/// types, a list of `{Type, Size}`
+#[export_name = "erlang:memory/1"]
+pub extern "C-unwind" fn memory1(process: &mut ProcessLock, types: OpaqueTerm) -> ErlangResult {
+    match types.into() {
+        Term::Atom(ty) => {
+            let Some(size) = memory_usage(ty) else { badarg!(process, types) };
+            alloc_result(process, |heap| int_to_term(size as u64, heap))
+        }
+        Term::Cons(list) => {
+            let mut usage = Vec::new();
+            for ty in list.iter_raw() {
+                let Ok(Term::Atom(ty)) = ty.map(Term::from) else { badarg!(process, types) };
+                let Some(size) = memory_usage(ty) else { badarg!(process, types) };
+                usage.push((ty, size));
+            }
+            alloc_result(process, |heap| memory_to_term(&usage, heap))
+        }
+        _ => badarg!(process, types),
+    }
+}
+
+/// Returns the number of bytes currently used by the memory type `ty`, or `None` if it isn't one
+/// of the types reported by `erlang:memory/0`
+///
+/// There are no ETS tables in this runtime, so `ets` is always zero.
+fn memory_usage(ty: Atom) -> Option<usize> {
+    let usage = match ty {
+        ty if ty == atoms::Total => memory_usage(atoms::Processes)? + memory_usage(atoms::System)?,
+        ty if ty == atoms::Processes => ProcessHeap::total_allocated(),
+        ty if ty == atoms::System => {
+            memory_usage(atoms::AtomMemory)?
+                + memory_usage(atoms::Binary)?
+                + memory_usage(atoms::Code)?
+                + memory_usage(atoms::Ets)?
+        }
+        ty if ty == atoms::AtomMemory => atom::with_atom_table_readonly(|atoms| atoms.memory()),
+        ty if ty == atoms::Binary => BinaryData::total_allocated(),
+        ty if ty == atoms::Code => crate::bytecode().len(),
+        ty if ty == atoms::Ets => 0,
+        _ => return None,
+    };
+    Some(usage)
+}
+
+/// Returns `{Total, SinceLastCall}`, where `SinceLastCall` is the difference between `total`
+/// and the value of `total` the last time this was called with `last`
+fn since_last_call_result(process: &mut ProcessLock, total: u64, last: &AtomicU64) -> ErlangResult {
+    let since = total.saturating_sub(last.swap(total, Ordering::Relaxed));
+    alloc_result(process, |heap| {
+        let elements = [int_to_term(total, heap)?, int_to_term(since, heap)?];
+        Ok(Tuple::from_slice(&elements, heap)?.into())
+    })
+}
+
+fn charlist_result(process: &mut ProcessLock, s: &str) -> ErlangResult {
+    alloc_result(process, |heap| {
+        Ok(Cons::charlist_from_str(s, heap)?
+            .map(|list| list.into())
+            .unwrap_or(OpaqueTerm::NIL))
+    })
+}
+
+/// Constructs a term using `build`, garbage collecting and retrying until the process heap has
+/// enough space for it
+pub(crate) fn alloc_result<F>(process: &mut ProcessLock, build: F) -> ErlangResult
+where
+    F: Fn(&ProcessLock) -> Result<OpaqueTerm, AllocError>,
+{
+    loop {
+        match build(process) {
+            Ok(term) => return ErlangResult::Ok(term),
+            Err(_) => assert!(garbage_collect(process, Default::default()).is_ok()),
+        }
+    }
+}
+
+fn memory_to_term(usage: &[(Atom, usize)], heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    let mut elements = Vec::with_capacity(usage.len());
+    for (ty, size) in usage.iter().copied() {
+        let tuple = Tuple::from_slice(&[ty.into(), int_to_term(size as u64, heap)?], heap)?;
+        elements.push(tuple.into());
+    }
+    list_to_term(&elements, heap)
+}
+
+pub(crate) fn list_to_term(
+    elements: &[OpaqueTerm],
+    heap: &ProcessLock,
+) -> Result<OpaqueTerm, AllocError> {
+    Ok(Cons::from_slice(elements, heap)?
+        .map(|list| list.into())
+        .unwrap_or(OpaqueTerm::NIL))
+}
+
+/// Converts `value` to an integer term, allocating a bigint on `heap` if necessary
+pub(crate) fn int_to_term(value: u64, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    match Int::from(value) {
+        Int::Small(i) => Ok(i.try_into().unwrap()),
+        Int::Big(i) => Ok(Gc::new_in(BigInt::from(i), heap)?.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crossbeam::deque::Injector;
+
+    use firefly_rt::function::ModuleFunctionArity;
+    use firefly_rt::process::Process;
+    use firefly_rt::scheduler::SchedulerId;
+
+    use super::*;
+
+    #[test]
+    fn dirty_schedulers_test() {
+        crate::dirty::init(1);
+        let mfa = ModuleFunctionArity {
+            module: Atom::try_from("system_info_test").unwrap(),
+            function: Atom::try_from("start").unwrap(),
+            arity: 0,
+        };
+        let process = Process::new(
+            SchedulerId::INVALID,
+            None,
+            None,
+            mfa,
+            &[],
+            Arc::new(Injector::new()),
+            Default::default(),
+        );
+        let mut locked = process.lock();
+
+        let items = [
+            (atoms::DirtyCpuSchedulers, scheduler::dirty_cpu()),
+            (atoms::DirtyCpuSchedulersOnline, scheduler::dirty_cpu()),
+            (atoms::DirtyIoSchedulers, scheduler::dirty_io()),
+        ];
+        for (item, expected) in items {
+            assert_ne!(expected, 0);
+            match system_info1(&mut locked, item.into()) {
+                ErlangResult::Ok(value) => {
+                    assert_eq!(Term::from(value), Term::Int(expected as i64))
+                }
+                _ => panic!("expected system_info({}) to succeed", item),
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::alloc::AllocError;
+
+use firefly_number::Int;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+use firefly_system::time::TimeUnit;
+
+use crate::badarg;
+use crate::time::{self, DateTime};
+
+#[export_name = "erlang:monotonic_time/0"]
+pub extern "C-unwind" fn monotonic_time0(process: &mut ProcessLock) -> ErlangResult {
+    time_result(process, time::monotonic_time(), TimeUnit::Native)
+}
+
+#[export_name = "erlang:monotonic_time/1"]
+pub extern "C-unwind" fn monotonic_time1(
+    process: &mut ProcessLock,
+    unit: OpaqueTerm,
+) -> ErlangResult {
+    let Some(unit) = time::time_unit_from_term(unit) else { badarg!(process, unit) };
+    time_result(process, time::monotonic_time(), unit)
+}
+
+#[export_name = "erlang:system_time/0"]
+pub extern "C-unwind" fn system_time0(process: &mut ProcessLock) -> ErlangResult {
+    time_result(process, time::system_time(), TimeUnit::Native)
+}
+
+#[export_name = "erlang:system_time/1"]
+pub extern "C-unwind" fn system_time1(process: &mut ProcessLock, unit: OpaqueTerm) -> ErlangResult {
+    let Some(unit) = time::time_unit_from_term(unit) else { badarg!(process, unit) };
+    time_result(process, time::system_time(), unit)
+}
+
+#[export_name = "erlang:time_offset/0"]
+pub extern "C-unwind" fn time_offset0(process: &mut ProcessLock) -> ErlangResult {
+    time_result(process, time::time_offset(), TimeUnit::Native)
+}
+
+#[export_name = "erlang:time_offset/1"]
+pub extern "C-unwind" fn time_offset1(process: &mut ProcessLock, unit: OpaqueTerm) -> ErlangResult {
+    let Some(unit) = time::time_unit_from_term(unit) else { badarg!(process, unit) };
+    time_result(process, time::time_offset(), unit)
+}
+
+#[export_name = "erlang:convert_time_unit/3"]
+pub extern "C-unwind" fn convert_time_unit3(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    from: OpaqueTerm,
+    to: OpaqueTerm,
+) -> ErlangResult {
+    let value: Result<Int, _> = Term::from(time).try_into();
+    let Ok(value) = value else { badarg!(process, time) };
+    let Some(from_unit) = time::time_unit_from_term(from) else { badarg!(process, from) };
+    let Some(to_unit) = time::time_unit_from_term(to) else { badarg!(process, to) };
+    integer_result(process, time::convert_time_unit(value, from_unit, to_unit))
+}
+
+/// Returns Erlang system time as `{MegaSecs, Secs, MicroSecs}`
+#[export_name = "erlang:timestamp/0"]
+pub extern "C-unwind" fn timestamp0(process: &mut ProcessLock) -> ErlangResult {
+    let micros = time::system_time().div_euclid(1_000);
+    let secs = micros.div_euclid(1_000_000);
+    let elements = [
+        Term::Int(secs.div_euclid(1_000_000)).into(),
+        Term::Int(secs.rem_euclid(1_000_000)).into(),
+        Term::Int(micros.rem_euclid(1_000_000)).into(),
+    ];
+    loop {
+        match Tuple::from_slice(&elements, process) {
+            Ok(tuple) => return ErlangResult::Ok(tuple.into()),
+            Err(_) => assert!(garbage_collect(process, Default::default()).is_ok()),
+        }
+    }
+}
+
+#[export_name = "erlang:localtime/0"]
+pub extern "C-unwind" fn localtime0(process: &mut ProcessLock) -> ErlangResult {
+    datetime_result(process, DateTime::local_now())
+}
+
+#[export_name = "erlang:universaltime/0"]
+pub extern "C-unwind" fn universaltime0(process: &mut ProcessLock) -> ErlangResult {
+    datetime_result(process, DateTime::universal_now())
+}
+
+#[export_name = "erlang:localtime_to_universaltime/1"]
+pub extern "C-unwind" fn localtime_to_universaltime1(
+    process: &mut ProcessLock,
+    localtime: OpaqueTerm,
+) -> ErlangResult {
+    let Some(datetime) = datetime_from_term(localtime) else { badarg!(process, localtime) };
+    let Some(universal) = datetime.local_to_universal() else { badarg!(process, localtime) };
+    datetime_result(process, universal)
+}
+
+/// Converts `time`, a value in nanoseconds, to `unit` and returns it as an integer term
+fn time_result(process: &mut ProcessLock, time: i64, unit: TimeUnit) -> ErlangResult {
+    let time = time::convert_time_unit(Int::new(time), TimeUnit::Nanosecond, unit);
+    integer_result(process, time)
+}
+
+/// Returns `value` as an integer term, allocating a bigint on the process heap if necessary
+pub(crate) fn integer_result(process: &mut ProcessLock, value: Int) -> ErlangResult {
+    match value {
+        Int::Small(i) => ErlangResult::Ok(i.try_into().unwrap()),
+        Int::Big(i) => {
+            let i = BigInt::from(i);
+            loop {
+                match Gc::new_uninit_in(process) {
+                    Ok(mut empty) => unsafe {
+                        empty.write(i);
+                        return ErlangResult::Ok(empty.assume_init().into());
+                    },
+                    Err(_) => assert!(garbage_collect(process, Default::default()).is_ok()),
+                }
+            }
+        }
+    }
+}
+
+fn datetime_result(process: &mut ProcessLock, datetime: DateTime) -> ErlangResult {
+    loop {
+        match datetime_to_term(datetime, process) {
+            Ok(term) => return ErlangResult::Ok(term),
+            Err(_) => assert!(garbage_collect(process, Default::default()).is_ok()),
+        }
+    }
+}
+
+/// Converts `datetime` to a term of the form `{{Year, Month, Day}, {Hour, Minute, Second}}`
+pub(crate) fn datetime_to_term(
+    datetime: DateTime,
+    process: &ProcessLock,
+) -> Result<OpaqueTerm, AllocError> {
+    let date = Tuple::from_slice(
+        &[
+            Term::Int(datetime.year).into(),
+            Term::Int(datetime.month as i64).into(),
+            Term::Int(datetime.day as i64).into(),
+        ],
+        process,
+    )?;
+    let time = Tuple::from_slice(
+        &[
+            Term::Int(datetime.hour as i64).into(),
+            Term::Int(datetime.minute as i64).into(),
+            Term::Int(datetime.second as i64).into(),
+        ],
+        process,
+    )?;
+    let tuple = Tuple::from_slice(&[date.into(), time.into()], process)?;
+    Ok(tuple.into())
+}
+
+/// Parses a term of the form `{{Year, Month, Day}, {Hour, Minute, Second}}`
+///
+/// The resulting date and time is not guaranteed to be valid, only that each field is in range of its type
+fn datetime_from_term(term: OpaqueTerm) -> Option<DateTime> {
+    let Term::Tuple(datetime) = term.into() else { return None; };
+    let [date, time] = datetime.as_slice() else { return None; };
+    let Term::Tuple(date) = (*date).into() else { return None; };
+    let Term::Tuple(time) = (*time).into() else { return None; };
+    let [year, month, day] = date.as_slice() else { return None; };
+    let [hour, minute, second] = time.as_slice() else { return None; };
+    let field = |term: &OpaqueTerm| -> Option<u8> {
+        let Term::Int(i) = (*term).into() else { return None; };
+        i.try_into().ok()
+    };
+    let Term::Int(year) = (*year).into() else { return None; };
+    Some(DateTime {
+        year,
+        month: field(month)?,
+        day: field(day)?,
+        hour: field(hour)?,
+        minute: field(minute)?,
+        second: field(second)?,
+    })
+}
@@ -0,0 +1,325 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use firefly_alloc::fragment::HeapFragment;
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::{garbage_collect, Gc};
+use firefly_rt::process::{ContinuationResult, Generator, ProcessLock};
+use firefly_rt::scheduler::Scheduler;
+use firefly_rt::services::registry::{Registrant, WeakAddress};
+use firefly_rt::services::timers::{self, TimerError, TimerReply, TimerRequest};
+use firefly_rt::term::*;
+use firefly_system::time::{Duration, TimeUnit, Timeout};
+
+use crate::badarg;
+use crate::emulator::current_scheduler;
+use crate::time;
+
+#[export_name = "erlang:send_after/3"]
+pub extern "C-unwind" fn send_after3(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    message: OpaqueTerm,
+) -> ErlangResult {
+    start_timer(process, time, dest, message, OpaqueTerm::NIL, false)
+}
+
+#[export_name = "erlang:send_after/4"]
+pub extern "C-unwind" fn send_after4(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    message: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    start_timer(process, time, dest, message, options, false)
+}
+
+#[export_name = "erlang:start_timer/3"]
+pub extern "C-unwind" fn start_timer3(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    message: OpaqueTerm,
+) -> ErlangResult {
+    start_timer(process, time, dest, message, OpaqueTerm::NIL, true)
+}
+
+#[export_name = "erlang:start_timer/4"]
+pub extern "C-unwind" fn start_timer4(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    message: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    start_timer(process, time, dest, message, options, true)
+}
+
+#[export_name = "erlang:cancel_timer/1"]
+pub extern "C-unwind" fn cancel_timer1(
+    process: &mut ProcessLock,
+    timer_ref: OpaqueTerm,
+) -> ErlangResult {
+    cancel_timer2(process, timer_ref, OpaqueTerm::NIL)
+}
+
+#[export_name = "erlang:cancel_timer/2"]
+pub extern "C-unwind" fn cancel_timer2(
+    process: &mut ProcessLock,
+    timer_ref: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(reference) = timer_ref.into() else { badarg!(process, timer_ref) };
+    let Ok([is_async, info]) = parse_options(options, [atoms::Async, atoms::Info], [false, true]) else { badarg!(process, options) };
+
+    let id = reference.id();
+    let scheduler = current_scheduler();
+    if is_async {
+        let reply_to: WeakAddress = process.pid().into();
+        scheduler.request_timer(TimerRequest::Cancel {
+            id,
+            reply: Box::new(move |result| {
+                if info {
+                    send_timer_reply(reply_to, atoms::CancelTimer, id, result);
+                }
+            }),
+        });
+        return ErlangResult::Ok(atoms::Ok.into());
+    }
+
+    await_timer_request(|reply| TimerRequest::Cancel { id, reply }, info)
+}
+
+#[export_name = "erlang:read_timer/1"]
+pub extern "C-unwind" fn read_timer1(
+    process: &mut ProcessLock,
+    timer_ref: OpaqueTerm,
+) -> ErlangResult {
+    read_timer2(process, timer_ref, OpaqueTerm::NIL)
+}
+
+#[export_name = "erlang:read_timer/2"]
+pub extern "C-unwind" fn read_timer2(
+    process: &mut ProcessLock,
+    timer_ref: OpaqueTerm,
+    options: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Reference(reference) = timer_ref.into() else { badarg!(process, timer_ref) };
+    let Ok([is_async]) = parse_options(options, [atoms::Async], [false]) else { badarg!(process, options) };
+
+    let id = reference.id();
+    let scheduler = current_scheduler();
+    if is_async {
+        let reply_to: WeakAddress = process.pid().into();
+        scheduler.request_timer(TimerRequest::Read {
+            id,
+            reply: Box::new(move |result| {
+                send_timer_reply(reply_to, atoms::ReadTimer, id, result);
+            }),
+        });
+        return ErlangResult::Ok(atoms::Ok.into());
+    }
+
+    await_timer_request(|reply| TimerRequest::Read { id, reply }, true)
+}
+
+/// Performs a timer request on behalf of the calling process, returning the result as the
+/// result of the BIF if `info` is true, or `ok` otherwise
+///
+/// If the timer is owned by another scheduler, the process is suspended until that scheduler
+/// has handled the request, rather than holding up this one.
+fn await_timer_request<F>(request: F, info: bool) -> ErlangResult
+where
+    F: FnOnce(TimerReply) -> TimerRequest,
+{
+    let (sender, receiver) = mpsc::sync_channel(1);
+    current_scheduler().request_timer(request(Box::new(move |result| {
+        sender.send(result).ok();
+    })));
+    // Requests for timers owned by this scheduler are handled immediately
+    match receiver.try_recv() {
+        Ok(result) => ErlangResult::Ok(reply_to_term(result, info)),
+        Err(TryRecvError::Disconnected) => ErlangResult::Ok(reply_to_term(None, info)),
+        Err(TryRecvError::Empty) => {
+            let state = Box::into_raw(Box::new(PendingRequest { receiver, info }));
+            ErlangResult::Await(Box::new(Generator::new(poll_request, state.cast())))
+        }
+    }
+}
+
+/// The state of a process awaiting the reply to a timer request relayed to another scheduler
+struct PendingRequest {
+    receiver: Receiver<Option<Duration>>,
+    info: bool,
+}
+
+/// The continuation used by `await_timer_request`, which completes once the reply is received
+extern "C-unwind" fn poll_request(
+    _process: &mut ProcessLock,
+    state: *mut (),
+) -> ContinuationResult {
+    let pending = state.cast::<PendingRequest>();
+    let result = match unsafe { (*pending).receiver.try_recv() } {
+        Ok(result) => result,
+        Err(TryRecvError::Empty) => return ContinuationResult::Yield(poll_request, ()),
+        // The reply was dropped without being invoked, so the timer can't be found
+        Err(TryRecvError::Disconnected) => None,
+    };
+    let pending = unsafe { Box::from_raw(pending) };
+    ContinuationResult::Complete(Ok(reply_to_term(result, pending.info)))
+}
+
+fn reply_to_term(result: Option<Duration>, info: bool) -> OpaqueTerm {
+    if info {
+        timer_result(result)
+    } else {
+        atoms::Ok.into()
+    }
+}
+
+/// Shared implementation of `send_after/3,4` and `start_timer/3,4`
+///
+/// When `wrap` is true, the message is delivered as `{timeout, TimerRef, Message}`
+fn start_timer(
+    process: &mut ProcessLock,
+    time: OpaqueTerm,
+    dest: OpaqueTerm,
+    message: OpaqueTerm,
+    options: OpaqueTerm,
+    wrap: bool,
+) -> ErlangResult {
+    let Ok([abs]) = parse_options(options, [atoms::Abs], [false]) else { badarg!(process, options) };
+    let Some(timeout) = to_timeout(time, abs) else { badarg!(process, time) };
+    // Registered names are resolved when the timer expires, not when it is started
+    let recipient = match dest.into() {
+        Term::Pid(pid) if pid.is_local() => WeakAddress::Process((&*pid).clone()),
+        Term::Atom(name) => WeakAddress::Name(name),
+        _ => badarg!(process, dest),
+    };
+
+    let scheduler = current_scheduler();
+    let message: Term = message.into();
+    let sender: WeakAddress = process.pid().into();
+    let timer_ref = scheduler.next_reference_id();
+    let fragment = if wrap {
+        timers::timeout_triple(timer_ref, message)
+    } else {
+        TermFragment::clone_from(&message)
+    };
+    let Ok(fragment) = fragment else { return system_limit(process, time) };
+    match scheduler.send_after(timer_ref, sender, recipient, fragment, timeout) {
+        Ok(_) => (),
+        Err(TimerError::InvalidTimeout(_)) => badarg!(process, time),
+        Err(_) => return system_limit(process, time),
+    }
+
+    loop {
+        match Gc::new_uninit_in(process) {
+            Ok(mut empty) => unsafe {
+                empty.write(Reference::new(timer_ref));
+                return ErlangResult::Ok(empty.assume_init().into());
+            },
+            Err(_) => {
+                // All of our live data was copied into the timer message
+                if garbage_collect(process, Default::default()).is_err() {
+                    return system_limit(process, time);
+                }
+            }
+        }
+    }
+}
+
+/// Raises a `system_limit` error, used when there isn't enough memory to start a timer
+fn system_limit(process: &mut ProcessLock, time: OpaqueTerm) -> ErlangResult {
+    process.exception_info.flags = ExceptionFlags::ERROR;
+    process.exception_info.reason = atoms::SystemLimit.into();
+    process.exception_info.value = time;
+    process.exception_info.args = None;
+    process.exception_info.trace = None;
+    ErlangResult::Err
+}
+
+/// Converts the `Time` argument of a timer BIF to a relative timeout
+///
+/// If `abs` is true, `time` is an absolute point in Erlang monotonic time, in milliseconds,
+/// otherwise it is a non-negative number of milliseconds from now. Absolute times in the
+/// past produce an immediate timeout.
+fn to_timeout(time: OpaqueTerm, abs: bool) -> Option<Timeout> {
+    let Term::Int(time) = time.into() else { return None };
+    if abs {
+        let now: i64 = time::monotonic_time_in(TimeUnit::Millisecond).try_into().ok()?;
+        Some(Timeout::from_millis(time.saturating_sub(now).max(0) as u64))
+    } else {
+        Timeout::try_from(time).ok()
+    }
+}
+
+/// Parses a proper list of `{Key, boolean()}` timer options
+///
+/// Each key in `keys` has a corresponding default value in `defaults`, and the result contains
+/// the value of each option in the same order. The last occurrence of an option takes precedence.
+///
+/// Returns `Err` if the list is improper, or contains anything other than the given options.
+fn parse_options<const N: usize>(
+    options: OpaqueTerm,
+    keys: [Atom; N],
+    defaults: [bool; N],
+) -> Result<[bool; N], ()> {
+    let mut values = defaults;
+    match options.into() {
+        Term::Nil => Ok(values),
+        Term::Cons(list) => {
+            for option in list.iter_raw() {
+                let Ok(Term::Tuple(option)) = option.map(Term::from) else { return Err(()) };
+                if option.len() != 2 {
+                    return Err(());
+                }
+                let Term::Atom(key) = option[0].into() else { return Err(()) };
+                let index = keys.iter().position(|k| *k == key).ok_or(())?;
+                values[index] = match option[1] {
+                    OpaqueTerm::TRUE => true,
+                    OpaqueTerm::FALSE => false,
+                    _ => return Err(()),
+                };
+            }
+            Ok(values)
+        }
+        _ => Err(()),
+    }
+}
+
+/// Converts the result of a timer request to the term returned by `cancel_timer` and `read_timer`
+///
+/// This is the time remaining in milliseconds, or `false` if the timer could not be found
+fn timer_result(result: Option<Duration>) -> OpaqueTerm {
+    match result {
+        Some(remaining) => Term::Int(remaining.as_millis() as i64).into(),
+        None => false.into(),
+    }
+}
+
+/// Sends `{Tag, TimerRef, Result}` to `to`, as used by the asynchronous timer BIFs
+fn send_timer_reply(to: WeakAddress, tag: Atom, id: ReferenceId, result: Option<Duration>) {
+    let Some(Registrant::Process(recipient)) = to.try_resolve() else { return; };
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_reference().build_tuple(3);
+    // This runs on whichever scheduler owns the timer, so the reply is dropped rather than
+    // bringing that scheduler down if there is no memory for it
+    let Ok(fragment) = HeapFragment::new(layout.finish(), None) else { return; };
+    let heap = unsafe { fragment.as_ref() };
+    let reference = Gc::new_in(Reference::new(id), heap).unwrap();
+    let tuple = Tuple::from_slice(&[tag.into(), reference.into(), timer_result(result)], heap)
+        .unwrap();
+    recipient
+        .send_fragment(
+            WeakAddress::System,
+            TermFragment {
+                term: tuple.into(),
+                fragment: Some(fragment),
+            },
+        )
+        .ok();
+}
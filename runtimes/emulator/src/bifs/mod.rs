@@ -1 +1,2 @@
 pub mod erlang;
+pub mod os;
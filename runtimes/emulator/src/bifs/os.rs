@@ -0,0 +1,28 @@
+use firefly_number::Int;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::OpaqueTerm;
+use firefly_system::time::TimeUnit;
+
+use crate::badarg;
+use crate::bifs::erlang::integer_result;
+use crate::time;
+
+#[export_name = "os:system_time/0"]
+pub extern "C-unwind" fn system_time0(process: &mut ProcessLock) -> ErlangResult {
+    let time = Int::new(time::os_system_time());
+    integer_result(
+        process,
+        time::convert_time_unit(time, TimeUnit::Nanosecond, TimeUnit::Native),
+    )
+}
+
+#[export_name = "os:system_time/1"]
+pub extern "C-unwind" fn system_time1(process: &mut ProcessLock, unit: OpaqueTerm) -> ErlangResult {
+    let Some(unit) = time::time_unit_from_term(unit) else { badarg!(process, unit) };
+    let time = Int::new(time::os_system_time());
+    integer_result(
+        process,
+        time::convert_time_unit(time, TimeUnit::Nanosecond, unit),
+    )
+}
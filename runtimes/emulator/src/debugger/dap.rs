@@ -0,0 +1,397 @@
+//! The Debug Adapter Protocol server
+//!
+//! Clients connect over TCP, one at a time. Each message is a JSON payload preceded by a header
+//! block containing its `Content-Length`. Requests are handled on the thread reading from the
+//! socket, while responses and events are written by a separate thread, so that schedulers never
+//! block on the client when reporting that a process stopped.
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam::deque::Injector;
+use log::{trace, warn};
+
+use firefly_bytecode::ByteCode;
+use firefly_rt::process::Process;
+use firefly_rt::term::{atom::GlobalAtomTable, Atom};
+use firefly_util::json::Json;
+
+use super::{BreakpointInfo, Debugger, StepKind, DEBUGGER};
+
+/// Starts listening for a debugger client on `addr`
+///
+/// This blocks until a client has connected and finished configuring its breakpoints, so that
+/// it has a chance to stop in any code executed during boot.
+pub fn start(
+    addr: &str,
+    code: Arc<ByteCode<Atom, GlobalAtomTable>>,
+    injector: Arc<Injector<Arc<Process>>>,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let debugger = DEBUGGER.get_or_init(|| Debugger::new(code, injector));
+    eprintln!(
+        "Waiting for a debugger to attach on {}..",
+        listener.local_addr()?
+    );
+
+    let (ready, configured) = mpsc::channel();
+    thread::Builder::new()
+        .name("debugger".to_string())
+        .spawn(move || serve(listener, debugger, ready))?;
+    configured.recv().ok();
+
+    Ok(())
+}
+
+/// Accepts clients until the listener fails, handling one session at a time
+fn serve(listener: TcpListener, debugger: &'static Debugger, ready: Sender<()>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!(target: "debugger", "failed to accept debugger client: {}", err);
+                continue;
+            }
+        };
+        match Session::new(debugger, stream, ready.clone()) {
+            Ok(session) => {
+                if let Err(err) = session.run() {
+                    warn!(target: "debugger", "debugger session failed: {}", err);
+                }
+            }
+            Err(err) => warn!(target: "debugger", "failed to start debugger session: {}", err),
+        }
+        // Never leave processes stopped without a client to resume them
+        debugger.detach();
+    }
+}
+
+struct Session {
+    debugger: &'static Debugger,
+    reader: BufReader<TcpStream>,
+    outgoing: Sender<Json>,
+    /// Used to signal that the client finished configuring, if it has not done so yet
+    ready: Option<Sender<()>>,
+}
+impl Session {
+    fn new(debugger: &'static Debugger, stream: TcpStream, ready: Sender<()>) -> io::Result<Self> {
+        let writer = stream.try_clone()?;
+        let (outgoing, messages) = mpsc::channel();
+        thread::Builder::new()
+            .name("debugger-writer".to_string())
+            .spawn(move || write_messages(writer, messages))?;
+        Ok(Self {
+            debugger,
+            reader: BufReader::new(stream),
+            outgoing,
+            ready: Some(ready),
+        })
+    }
+
+    fn run(mut self) -> io::Result<()> {
+        *self.debugger.events.lock().unwrap() = Some(self.outgoing.clone());
+        let result = loop {
+            match read_message(&mut self.reader) {
+                Ok(Some(message)) => {
+                    if !self.handle(message) {
+                        break Ok(());
+                    }
+                }
+                Ok(None) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        *self.debugger.events.lock().unwrap() = None;
+        result
+    }
+
+    /// Handles a single message, returning false if the client has disconnected
+    fn handle(&mut self, message: Json) -> bool {
+        if message.get("type").and_then(Json::as_str) != Some("request") {
+            return true;
+        }
+        let seq = message.get("seq").and_then(Json::as_u64).unwrap_or(0);
+        let command = message
+            .get("command")
+            .and_then(Json::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let arguments = message.get("arguments").cloned().unwrap_or(Json::Null);
+        trace!(target: "debugger", "handling request {}: {}", seq, &command);
+
+        let result = self.dispatch(&command, &arguments);
+        let mut response = vec![
+            ("type", "response".into()),
+            ("request_seq", seq.into()),
+            ("success", result.is_ok().into()),
+            ("command", command.as_str().into()),
+        ];
+        match result {
+            Ok(Json::Null) => (),
+            Ok(body) => response.push(("body", body)),
+            Err(message) => response.push(("message", message.into())),
+        }
+        self.outgoing.send(Json::object(response)).ok();
+
+        match command.as_str() {
+            // The client waits for this before sending its configuration
+            "initialize" => {
+                self.outgoing.send(event("initialized", Json::Null)).ok();
+                true
+            }
+            "disconnect" => false,
+            _ => true,
+        }
+    }
+
+    fn dispatch(&mut self, command: &str, arguments: &Json) -> Result<Json, String> {
+        let debugger = self.debugger;
+        match command {
+            "initialize" => Ok(Json::object([
+                ("supportsConfigurationDoneRequest", true.into()),
+                ("supportsFunctionBreakpoints", true.into()),
+            ])),
+            // The program is already running, there is nothing to launch
+            "launch" | "attach" | "disconnect" | "setExceptionBreakpoints" => Ok(Json::Null),
+            "configurationDone" => {
+                if let Some(ready) = self.ready.take() {
+                    ready.send(()).ok();
+                }
+                Ok(Json::Null)
+            }
+            "setBreakpoints" => {
+                let source = arguments.get("source");
+                let path = source
+                    .and_then(|source| source.get("path").or_else(|| source.get("name")))
+                    .and_then(Json::as_str)
+                    .ok_or("expected a source path")?;
+                let lines = arguments
+                    .get("breakpoints")
+                    .and_then(Json::as_array)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|bp| bp.get("line").and_then(Json::as_u64))
+                    .map(|line| line as u32)
+                    .collect::<Vec<_>>();
+                let breakpoints = debugger.set_source_breakpoints(path, &lines);
+                Ok(breakpoints_body(breakpoints))
+            }
+            "setFunctionBreakpoints" => {
+                let names = arguments
+                    .get("breakpoints")
+                    .and_then(Json::as_array)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|bp| bp.get("name").and_then(Json::as_str))
+                    .collect::<Vec<_>>();
+                let breakpoints = debugger.set_function_breakpoints(&names);
+                Ok(breakpoints_body(breakpoints))
+            }
+            "threads" => {
+                let threads = debugger
+                    .threads()
+                    .into_iter()
+                    .map(|thread| {
+                        Json::object([("id", thread.id.into()), ("name", thread.name.into())])
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Json::object([("threads", threads.into())]))
+            }
+            "stackTrace" => {
+                let frames = debugger.stack_trace(thread_id(arguments)?)?;
+                let total = frames.len();
+                let start = arguments
+                    .get("startFrame")
+                    .and_then(Json::as_u64)
+                    .unwrap_or(0) as usize;
+                let levels = match arguments.get("levels").and_then(Json::as_u64) {
+                    None | Some(0) => total,
+                    Some(levels) => levels as usize,
+                };
+                let frames = frames
+                    .into_iter()
+                    .skip(start)
+                    .take(levels)
+                    .map(|frame| {
+                        Json::object([
+                            ("id", frame.id.into()),
+                            ("name", frame.name.into()),
+                            ("source", frame.file.as_deref().map(source).into()),
+                            ("line", frame.line.into()),
+                            ("column", frame.column.into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Json::object([
+                    ("stackFrames", frames.into()),
+                    ("totalFrames", total.into()),
+                ]))
+            }
+            "scopes" => {
+                let frame_id = arguments
+                    .get("frameId")
+                    .and_then(Json::as_u64)
+                    .ok_or("expected a frame id")?;
+                let scopes = debugger
+                    .scopes(frame_id as usize)?
+                    .into_iter()
+                    .map(|scope| {
+                        Json::object([
+                            ("name", scope.name.into()),
+                            ("variablesReference", scope.reference.into()),
+                            ("expensive", false.into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Json::object([("scopes", scopes.into())]))
+            }
+            "variables" => {
+                let reference = arguments
+                    .get("variablesReference")
+                    .and_then(Json::as_u64)
+                    .ok_or("expected a variables reference")?;
+                let variables = debugger
+                    .variables(reference as usize)?
+                    .into_iter()
+                    .map(|variable| {
+                        Json::object([
+                            ("name", variable.name.into()),
+                            ("value", variable.value.into()),
+                            ("variablesReference", 0u32.into()),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Json::object([("variables", variables.into())]))
+            }
+            "continue" => {
+                debugger.resume(thread_id(arguments)?, None)?;
+                Ok(Json::object([("allThreadsContinued", false.into())]))
+            }
+            "next" => {
+                debugger.resume(thread_id(arguments)?, Some(StepKind::Over))?;
+                Ok(Json::Null)
+            }
+            "stepIn" => {
+                debugger.resume(thread_id(arguments)?, Some(StepKind::Into))?;
+                Ok(Json::Null)
+            }
+            "stepOut" => {
+                debugger.resume(thread_id(arguments)?, Some(StepKind::Out))?;
+                Ok(Json::Null)
+            }
+            "pause" => {
+                debugger.pause(thread_id(arguments)?)?;
+                Ok(Json::Null)
+            }
+            _ => Err(format!("unsupported request '{}'", command)),
+        }
+    }
+}
+
+/// Constructs an event message, the sequence number is assigned when it is written
+pub(super) fn event(event: &str, body: Json) -> Json {
+    let mut fields = vec![("type", "event".into()), ("event", event.into())];
+    if body != Json::Null {
+        fields.push(("body", body));
+    }
+    Json::object(fields)
+}
+
+fn thread_id(arguments: &Json) -> Result<u64, &'static str> {
+    arguments
+        .get("threadId")
+        .and_then(Json::as_u64)
+        .ok_or("expected a thread id")
+}
+
+fn breakpoints_body(breakpoints: Vec<BreakpointInfo>) -> Json {
+    let breakpoints = breakpoints
+        .into_iter()
+        .map(|bp| {
+            let mut fields = vec![("verified", bp.verified.into())];
+            if let Some(line) = bp.line {
+                fields.push(("line", line.into()));
+            }
+            if let Some(message) = bp.message {
+                fields.push(("message", message.into()));
+            }
+            Json::object(fields)
+        })
+        .collect::<Vec<_>>();
+    Json::object([("breakpoints", breakpoints.into())])
+}
+
+/// Describes a source file, resolving paths recorded relative to the working directory
+fn source(file: &str) -> Json {
+    let path = Path::new(file);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file.to_string());
+    let path = path
+        .canonicalize()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| file.to_string());
+    Json::object([("name", name.into()), ("path", path.into())])
+}
+
+fn write_messages(mut stream: TcpStream, messages: Receiver<Json>) {
+    for (seq, message) in (1u64..).zip(messages) {
+        let message = match message {
+            Json::Object(mut fields) => {
+                fields.insert(0, ("seq".to_string(), seq.into()));
+                Json::Object(fields)
+            }
+            message => message,
+        };
+        if let Err(err) = write_message(&mut stream, &message) {
+            warn!(target: "debugger", "failed to write to debugger client: {}", err);
+            break;
+        }
+    }
+}
+
+/// Reads the next message sent by the client, returning `Ok(None)` once the connection closes
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Json>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing content-length"))?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    let content = String::from_utf8(content)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Json::parse(&content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Json) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
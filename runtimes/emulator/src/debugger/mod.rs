@@ -0,0 +1,773 @@
+//! A source-level debugger for the emulator
+//!
+//! When the `ERTS_DEBUGGER` environment variable is set to a socket address, the emulator listens
+//! on that address for a client speaking the [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/),
+//! and waits for it to finish configuring breakpoints before booting the system.
+//!
+//! Breakpoints and stepping are resolved against the debug info of the loaded bytecode. A process
+//! which stops is scheduled out and parked here instead of being placed back in a run queue, so
+//! all other processes, and the scheduler it was running on, keep running while it is inspected.
+//! Signals sent to a stopped process are queued, and handled once it is resumed.
+mod dap;
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crossbeam::deque::Injector;
+
+use firefly_bytecode::{ByteCode, DebugInfoTable, FileId, Function};
+use firefly_rt::process::{Process, ProcessId, ProcessLock, ARG0_REG};
+use firefly_rt::services::registry;
+use firefly_rt::term::{atom::GlobalAtomTable, Atom};
+use firefly_util::json::Json;
+
+use crate::emulator::{call_frames, CallFrame};
+
+pub use self::dap::start;
+
+static DEBUGGER: OnceLock<Debugger> = OnceLock::new();
+
+/// The number of buckets in which the processes watched by the debugger are counted
+const WATCHED_BUCKETS: usize = 256;
+
+/// Set whenever the debugger needs to observe instructions as they are executed, i.e. when there
+/// are breakpoints, or processes which are stepping or have been asked to pause
+static ARMED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if schedulers must call [`should_stop`] before executing each instruction
+#[inline(always)]
+pub fn is_armed() -> bool {
+    ARMED.load(Ordering::Relaxed)
+}
+
+/// Called by a scheduler before it executes the instruction at `ip` in `process`
+///
+/// Returns true if the process has stopped, in which case the instruction must not be executed,
+/// and the process must be scheduled out.
+pub fn should_stop(process: &ProcessLock, ip: usize) -> bool {
+    match DEBUGGER.get() {
+        Some(debugger) => debugger.should_stop(process, ip),
+        None => false,
+    }
+}
+
+/// Called by a scheduler when it is about to place `process` back in its run queue
+///
+/// Returns true if the process has stopped, and is now parked by the debugger until resumed, in
+/// which case it must not be placed in the run queue.
+pub fn intercept(process: &Arc<Process>) -> bool {
+    match DEBUGGER.get() {
+        Some(debugger) => debugger.intercept(process),
+        None => false,
+    }
+}
+
+/// The ways in which a stopped process can be resumed one source line at a time
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StepKind {
+    /// Stop on the next line executed, including in called functions
+    Into,
+    /// Stop on the next line executed in the current function, or its caller
+    Over,
+    /// Stop on the next line executed in the caller of the current function
+    Out,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum StopReason {
+    Breakpoint,
+    Step,
+    Pause,
+}
+impl StopReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Breakpoint => "breakpoint",
+            Self::Step => "step",
+            Self::Pause => "pause",
+        }
+    }
+}
+
+/// The result of resolving a breakpoint requested by the client
+pub struct BreakpointInfo {
+    pub verified: bool,
+    pub line: Option<u32>,
+    pub message: Option<&'static str>,
+}
+
+pub struct ThreadInfo {
+    pub id: u64,
+    pub name: String,
+}
+
+pub struct FrameInfo {
+    pub id: usize,
+    pub name: String,
+    pub file: Option<String>,
+    pub line: u32,
+    pub column: u32,
+}
+
+pub struct ScopeInfo {
+    pub name: &'static str,
+    pub reference: usize,
+}
+
+pub struct VariableInfo {
+    pub name: String,
+    pub value: String,
+}
+
+struct Step {
+    kind: StepKind,
+    /// The number of frames on the call stack when the step began
+    depth: usize,
+    /// The source line the step began on
+    line: Option<(FileId, u32)>,
+}
+impl Step {
+    /// Returns true if a process stepping from this step's line should stop on `line`, when it has
+    /// `depth` frames on its call stack
+    fn is_complete(&self, depth: usize, line: Option<(FileId, u32)>) -> bool {
+        match self.kind {
+            StepKind::Into => depth != self.depth || line != self.line,
+            StepKind::Over => depth < self.depth || (depth == self.depth && line != self.line),
+            StepKind::Out => depth < self.depth,
+        }
+    }
+}
+
+struct Stopped {
+    /// The instruction at which the process stopped, which has not been executed yet
+    ip: usize,
+    /// The process, once it has been scheduled out
+    process: Option<Arc<Process>>,
+    /// Set if the process was resumed before it was scheduled out
+    resumed: bool,
+}
+
+/// A call frame of a stopped process, as handed out to the client
+#[derive(Copy, Clone)]
+struct FrameRef {
+    process: ProcessId,
+    ip: usize,
+    fp: usize,
+    sp: usize,
+}
+
+#[derive(Default)]
+struct State {
+    /// The instruction offsets at which processes stop
+    breakpoints: BTreeSet<usize>,
+    /// The instruction offsets of source breakpoints, by the path of the file they were set in
+    source_breakpoints: HashMap<String, Vec<usize>>,
+    /// The instruction offsets of function breakpoints
+    function_breakpoints: Vec<usize>,
+    steps: HashMap<ProcessId, Step>,
+    pauses: BTreeSet<ProcessId>,
+    stopped: HashMap<ProcessId, Stopped>,
+    /// Processes which were resumed, and the instruction they stopped at, which must be executed
+    /// before they can stop again
+    resumed: HashMap<ProcessId, usize>,
+    frames: HashMap<usize, FrameRef>,
+    next_frame_id: usize,
+}
+
+/// The debugger state shared by the client connection and all schedulers
+///
+/// Schedulers check every instruction they execute while the debugger is armed, so they only
+/// take the state lock when a process may stop at an instruction. This is decided by `breakpoints`
+/// and `watched`, which mirror `State` and are republished whenever it changes.
+pub struct Debugger {
+    code: Arc<ByteCode<Atom, GlobalAtomTable>>,
+    /// The global run queue, used to reschedule processes when they are resumed
+    injector: Arc<Injector<Arc<Process>>>,
+    state: Mutex<State>,
+    /// A bit for each instruction, set if it has a breakpoint
+    breakpoints: Box<[AtomicU64]>,
+    /// The number of processes which are stepping, paused, or were just resumed, bucketed by id
+    watched: Box<[AtomicU32]>,
+    /// Used to send events to the connected client, if any
+    events: Mutex<Option<Sender<Json>>>,
+}
+impl Debugger {
+    fn new(
+        code: Arc<ByteCode<Atom, GlobalAtomTable>>,
+        injector: Arc<Injector<Arc<Process>>>,
+    ) -> Self {
+        let breakpoints = (0..code.code.len().div_ceil(64))
+            .map(|_| AtomicU64::new(0))
+            .collect();
+        let watched = (0..WATCHED_BUCKETS).map(|_| AtomicU32::new(0)).collect();
+        Self {
+            code,
+            injector,
+            state: Mutex::new(State {
+                next_frame_id: 1,
+                ..State::default()
+            }),
+            breakpoints,
+            watched,
+            events: Mutex::new(None),
+        }
+    }
+
+    fn should_stop(&self, process: &ProcessLock, ip: usize) -> bool {
+        let id = process.id();
+        if !self.is_breakpoint(ip) && self.watched[bucket(id)].load(Ordering::Acquire) == 0 {
+            return false;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(resumed_at) = state.resumed.remove(&id) {
+            if resumed_at == ip {
+                self.publish(&state);
+                return false;
+            }
+        }
+
+        let reason = if state.breakpoints.contains(&ip) {
+            StopReason::Breakpoint
+        } else if state.pauses.contains(&id) {
+            StopReason::Pause
+        } else if state
+            .steps
+            .get(&id)
+            .map(|step| self.is_step_complete(step, process, ip))
+            .unwrap_or(false)
+        {
+            StopReason::Step
+        } else {
+            return false;
+        };
+
+        state.steps.remove(&id);
+        state.pauses.remove(&id);
+        state.stopped.insert(
+            id,
+            Stopped {
+                ip,
+                process: None,
+                resumed: false,
+            },
+        );
+        self.publish(&state);
+        drop(state);
+
+        self.send_event(
+            "stopped",
+            Json::object([
+                ("reason", reason.as_str().into()),
+                ("threadId", id.raw().into()),
+                ("allThreadsStopped", false.into()),
+            ]),
+        );
+        true
+    }
+
+    fn is_step_complete(&self, step: &Step, process: &ProcessLock, ip: usize) -> bool {
+        if self.code.is_stub(ip) {
+            return false;
+        }
+        // Only stop on instructions we can show the source of
+        let Some(loc) = self.code.instruction_location(ip) else {
+            return false;
+        };
+        let depth = process.stack.trace(None).count();
+        step.is_complete(depth, Some((loc.file, loc.line)))
+    }
+
+    #[inline]
+    fn is_breakpoint(&self, ip: usize) -> bool {
+        self.breakpoints
+            .get(ip / 64)
+            .map(|word| word.load(Ordering::Acquire) & (1 << (ip % 64)) != 0)
+            .unwrap_or(false)
+    }
+
+    /// Recomputes the set of instructions with breakpoints from the source and function
+    /// breakpoints in `state`
+    fn rebuild_breakpoints(&self, state: &mut State) {
+        state.breakpoints = state
+            .source_breakpoints
+            .values()
+            .flatten()
+            .chain(state.function_breakpoints.iter())
+            .copied()
+            .collect();
+
+        // Each word is replaced at once, so a breakpoint which is kept is never missed
+        let mut words = vec![0u64; self.breakpoints.len()];
+        for ip in state.breakpoints.iter().copied() {
+            if let Some(word) = words.get_mut(ip / 64) {
+                *word |= 1 << (ip % 64);
+            }
+        }
+        for (word, bits) in self.breakpoints.iter().zip(words) {
+            word.store(bits, Ordering::Release);
+        }
+    }
+
+    /// Publishes the parts of `state` which schedulers read without taking the state lock
+    fn publish(&self, state: &State) {
+        let mut watched = vec![0u32; self.watched.len()];
+        let ids = state
+            .steps
+            .keys()
+            .chain(state.pauses.iter())
+            .chain(state.resumed.keys());
+        for id in ids {
+            watched[bucket(*id)] += 1;
+        }
+        for (bucket, count) in self.watched.iter().zip(watched) {
+            bucket.store(count, Ordering::Release);
+        }
+
+        let armed = !state.breakpoints.is_empty()
+            || !state.steps.is_empty()
+            || !state.pauses.is_empty()
+            || !state.resumed.is_empty();
+        ARMED.store(armed, Ordering::Release);
+    }
+
+    fn intercept(&self, process: &Arc<Process>) -> bool {
+        let id = process.id();
+        let mut state = self.state.lock().unwrap();
+        match state.stopped.get_mut(&id) {
+            None => false,
+            Some(stopped) if stopped.resumed => {
+                state.stopped.remove(&id);
+                false
+            }
+            Some(stopped) => {
+                stopped.process = Some(process.clone());
+                true
+            }
+        }
+    }
+
+    fn send_event(&self, event: &str, body: Json) {
+        if let Some(events) = self.events.lock().unwrap().as_ref() {
+            events.send(dap::event(event, body)).ok();
+        }
+    }
+
+    /// Replaces all breakpoints in the source file at `path` with breakpoints on `lines`
+    ///
+    /// A breakpoint on a line without code is moved to the next line which has some.
+    pub fn set_source_breakpoints(&self, path: &str, lines: &[u32]) -> Vec<BreakpointInfo> {
+        let debug_info = &self.code.debug_info;
+        let files = debug_info
+            .files()
+            .filter(|(_, file)| is_same_file(file, path))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        let mut offsets = vec![];
+        let mut results = Vec::with_capacity(lines.len());
+        for line in lines.iter().copied() {
+            let Some(actual) = resolve_line(debug_info, &files, line) else {
+                results.push(BreakpointInfo {
+                    verified: false,
+                    line: Some(line),
+                    message: Some("there is no code at or after this line"),
+                });
+                continue;
+            };
+            // A line may be split in multiple ranges of instructions, only stop at the first one
+            // in each function, so that a breakpoint is hit once each time the line is executed
+            let mut functions = BTreeSet::new();
+            for (offset, loc) in debug_info.offsets() {
+                if files.contains(&loc.file) && loc.line == actual {
+                    let function = self.code.function_by_ip(offset).id();
+                    if functions.insert(function) {
+                        offsets.push(offset);
+                    }
+                }
+            }
+            results.push(BreakpointInfo {
+                verified: true,
+                line: Some(actual),
+                message: None,
+            });
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if offsets.is_empty() {
+            state.source_breakpoints.remove(path);
+        } else {
+            state.source_breakpoints.insert(path.to_string(), offsets);
+        }
+        self.rebuild_breakpoints(&mut state);
+        self.publish(&state);
+        results
+    }
+
+    /// Replaces all function breakpoints with breakpoints on entry to the functions named by
+    /// `names`, given in the form `module:function/arity`
+    pub fn set_function_breakpoints(&self, names: &[&str]) -> Vec<BreakpointInfo> {
+        let mut offsets = vec![];
+        let mut results = Vec::with_capacity(names.len());
+        for name in names {
+            match self.code.function_by_name(name) {
+                Some(Function::Bytecode { offset, .. }) => {
+                    // The first instruction of every function describes its frame
+                    let entry = *offset + 1;
+                    offsets.push(entry);
+                    results.push(BreakpointInfo {
+                        verified: true,
+                        line: self.code.instruction_location(entry).map(|loc| loc.line),
+                        message: None,
+                    });
+                }
+                Some(_) => results.push(BreakpointInfo {
+                    verified: false,
+                    line: None,
+                    message: Some("breakpoints can only be set on functions implemented in erlang"),
+                }),
+                None => results.push(BreakpointInfo {
+                    verified: false,
+                    line: None,
+                    message: Some("no such function, expected module:function/arity"),
+                }),
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.function_breakpoints = offsets;
+        self.rebuild_breakpoints(&mut state);
+        self.publish(&state);
+        results
+    }
+
+    /// Returns all live processes
+    pub fn threads(&self) -> Vec<ThreadInfo> {
+        let mut threads = registry::processes()
+            .iter()
+            .map(|process| {
+                let name = match process.registered_name() {
+                    Some(name) => format!("{} {}", process.pid(), name),
+                    None => format!("{} {}", process.pid(), &process.initial_call),
+                };
+                ThreadInfo {
+                    id: process.id().raw(),
+                    name,
+                }
+            })
+            .collect::<Vec<_>>();
+        threads.sort_by_key(|thread| thread.id);
+        threads
+    }
+
+    /// Returns the id of the process with the given thread id, if it is alive
+    fn process_id(&self, thread: u64) -> Option<ProcessId> {
+        registry::processes()
+            .iter()
+            .map(|process| process.id())
+            .find(|id| id.raw() == thread)
+    }
+
+    /// Returns the process with the given thread id, if it is stopped and has been scheduled out
+    fn parked(&self, thread: u64) -> Result<(Arc<Process>, usize), &'static str> {
+        let state = self.state.lock().unwrap();
+        let stopped = state
+            .stopped
+            .iter()
+            .find(|(id, _)| id.raw() == thread)
+            .map(|(_, stopped)| stopped)
+            .ok_or("the process is not stopped")?;
+        match stopped.process.as_ref() {
+            Some(process) => Ok((process.clone(), stopped.ip)),
+            None => Err("the process is still being suspended, try again"),
+        }
+    }
+
+    /// Returns the call stack of a stopped process, most recent call first
+    pub fn stack_trace(&self, thread: u64) -> Result<Vec<FrameInfo>, &'static str> {
+        let (process, ip) = self.parked(thread)?;
+        let id = process.id();
+        let frames = {
+            let process = process.lock();
+            call_frames(&self.code, &process, ip).collect::<Vec<_>>()
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let mut infos = Vec::with_capacity(frames.len());
+        for CallFrame { ip, fp, sp } in frames {
+            let frame_id = state.next_frame_id;
+            state.next_frame_id += 1;
+            state.frames.insert(
+                frame_id,
+                FrameRef {
+                    process: id,
+                    ip,
+                    fp,
+                    sp,
+                },
+            );
+
+            let name = match self.code.function_by_ip(ip).mfa() {
+                Some(mfa) => mfa.to_string(),
+                None => "<native>".to_string(),
+            };
+            let loc = self.code.instruction_location(ip);
+            infos.push(FrameInfo {
+                id: frame_id,
+                name,
+                file: loc.map(|loc| self.code.debug_info.file(loc.file).to_string()),
+                line: loc.map(|loc| loc.line).unwrap_or(0),
+                column: loc.map(|loc| loc.column).unwrap_or(0),
+            });
+        }
+        Ok(infos)
+    }
+
+    /// Returns the scopes of variables available in the given frame
+    pub fn scopes(&self, frame_id: usize) -> Result<Vec<ScopeInfo>, &'static str> {
+        let state = self.state.lock().unwrap();
+        if !state.frames.contains_key(&frame_id) {
+            return Err("invalid frame, the process may have been resumed");
+        }
+        Ok(vec![
+            ScopeInfo {
+                name: "Arguments",
+                reference: frame_id * 2,
+            },
+            ScopeInfo {
+                name: "Registers",
+                reference: frame_id * 2 + 1,
+            },
+        ])
+    }
+
+    /// Returns the variables in the scope with the given reference, see [`scopes`]
+    pub fn variables(&self, reference: usize) -> Result<Vec<VariableInfo>, &'static str> {
+        let frame_id = reference / 2;
+        let arguments = reference % 2 == 0;
+        let frame = {
+            let state = self.state.lock().unwrap();
+            state
+                .frames
+                .get(&frame_id)
+                .copied()
+                .ok_or("invalid frame, the process may have been resumed")?
+        };
+        let (process, _) = self.parked(frame.process.raw())?;
+
+        let arity = self.code.function_by_ip(frame.ip).arity();
+        let process = process.lock();
+        let slots = process.stack.slots(frame.fp, frame.sp);
+        let first_arg = ARG0_REG as usize;
+        let registers = if arguments {
+            first_arg..(first_arg + arity).min(slots.len())
+        } else {
+            (first_arg + arity).min(slots.len())..slots.len()
+        };
+
+        let mut variables = vec![];
+        for register in registers {
+            let value = slots[register];
+            // Skip unused slots, and return addresses or catch handlers
+            if value.is_none() || value.is_code() {
+                continue;
+            }
+            let name = if arguments {
+                format!("Arg{}", register - first_arg + 1)
+            } else {
+                format!("r{}", register)
+            };
+            variables.push(VariableInfo {
+                name,
+                value: value.to_string(),
+            });
+        }
+        Ok(variables)
+    }
+
+    /// Asks the given process to stop the next time it executes an instruction
+    pub fn pause(&self, thread: u64) -> Result<(), &'static str> {
+        let id = self.process_id(thread).ok_or("no such process")?;
+        let mut state = self.state.lock().unwrap();
+        if !state.stopped.contains_key(&id) {
+            state.pauses.insert(id);
+            self.publish(&state);
+        }
+        Ok(())
+    }
+
+    /// Resumes a stopped process, optionally stopping it again after stepping
+    pub fn resume(&self, thread: u64, step: Option<StepKind>) -> Result<(), &'static str> {
+        let (id, ip) = {
+            let state = self.state.lock().unwrap();
+            state
+                .stopped
+                .iter()
+                .find(|(id, stopped)| id.raw() == thread && !stopped.resumed)
+                .map(|(id, stopped)| (*id, stopped.ip))
+                .ok_or("the process is not stopped")?
+        };
+
+        let step = match step {
+            None => None,
+            Some(kind) => {
+                // The process lock must not be acquired while holding the state lock, as the
+                // scheduler does the opposite when checking for breakpoints
+                let process = registry::get_by_process_id(id).ok_or("no such process")?;
+                let depth = process.lock().stack.trace(None).count();
+                let line = self
+                    .code
+                    .instruction_location(ip)
+                    .map(|loc| (loc.file, loc.line));
+                Some(Step { kind, depth, line })
+            }
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let stopped = state.stopped.remove(&id).unwrap();
+        state.frames.retain(|_, frame| frame.process != id);
+        state.resumed.insert(id, ip);
+        if let Some(step) = step {
+            state.steps.insert(id, step);
+        }
+        if stopped.process.is_none() {
+            // The process has not been scheduled out yet, so its scheduler will place it back in
+            // the run queue itself
+            state.stopped.insert(
+                id,
+                Stopped {
+                    ip,
+                    process: None,
+                    resumed: true,
+                },
+            );
+        }
+        // The process must not run again before schedulers can see it is being watched
+        self.publish(&state);
+        drop(state);
+        if let Some(process) = stopped.process {
+            self.injector.push(process);
+        }
+        Ok(())
+    }
+
+    /// Removes all breakpoints and resumes all stopped processes, e.g. when the client detaches
+    pub fn detach(&self) {
+        let stopped = {
+            let state = self.state.lock().unwrap();
+            state
+                .stopped
+                .iter()
+                .filter(|(_, stopped)| !stopped.resumed)
+                .map(|(id, _)| id.raw())
+                .collect::<Vec<_>>()
+        };
+        {
+            let mut state = self.state.lock().unwrap();
+            state.source_breakpoints.clear();
+            state.function_breakpoints.clear();
+            self.rebuild_breakpoints(&mut state);
+            state.pauses.clear();
+            self.publish(&state);
+        }
+        for thread in stopped {
+            self.resume(thread, None).ok();
+        }
+    }
+}
+
+/// Returns the bucket in which `id` is counted in [`Debugger::watched`]
+#[inline]
+fn bucket(id: ProcessId) -> usize {
+    id.raw() as usize % WATCHED_BUCKETS
+}
+
+/// Returns the first line at or after `line` which has code, in any of the given files
+fn resolve_line(debug_info: &DebugInfoTable, files: &[FileId], line: u32) -> Option<u32> {
+    debug_info
+        .offsets()
+        .filter(|(_, loc)| files.contains(&loc.file) && loc.line >= line)
+        .map(|(_, loc)| loc.line)
+        .min()
+}
+
+/// Returns true if `a` and `b` refer to the same file
+///
+/// The paths recorded in debug info are those given to the compiler, which may be relative, so
+/// the shorter path only needs to be a suffix of the longer one, on a path component boundary.
+fn is_same_file(a: &str, b: &str) -> bool {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    match long.strip_suffix(short) {
+        None => false,
+        Some(prefix) => prefix.is_empty() || prefix.ends_with('/') || prefix.ends_with('\\'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use firefly_bytecode::Location;
+
+    #[test]
+    fn same_file_matches_on_path_component_boundaries() {
+        assert!(is_same_file("/src/app/src/foo.erl", "/src/app/src/foo.erl"));
+        assert!(is_same_file("/src/app/src/foo.erl", "src/foo.erl"));
+        assert!(is_same_file("foo.erl", "/src/app/src/foo.erl"));
+        assert!(is_same_file("C:\\app\\src\\foo.erl", "foo.erl"));
+        assert!(!is_same_file("/src/app/src/barfoo.erl", "foo.erl"));
+        assert!(!is_same_file("/src/app/foo.erl", "/src/app/bar.erl"));
+    }
+
+    #[test]
+    fn breakpoints_move_to_the_next_line_with_code() {
+        let mut debug_info = DebugInfoTable::default();
+        let foo = debug_info.get_or_insert_file("src/foo.erl");
+        let bar = debug_info.get_or_insert_file("src/bar.erl");
+        for (offset, file, line) in [(1, foo, 3), (4, foo, 7), (6, foo, 5), (8, bar, 10)] {
+            let loc = debug_info.get_or_insert_location(Location {
+                file,
+                line,
+                column: 1,
+            });
+            debug_info.register_offset(offset, loc);
+        }
+
+        assert_eq!(resolve_line(&debug_info, &[foo], 3), Some(3));
+        assert_eq!(resolve_line(&debug_info, &[foo], 4), Some(5));
+        assert_eq!(resolve_line(&debug_info, &[foo], 6), Some(7));
+        assert_eq!(resolve_line(&debug_info, &[foo], 8), None);
+        assert_eq!(resolve_line(&debug_info, &[bar], 1), Some(10));
+        assert_eq!(resolve_line(&debug_info, &[], 1), None);
+    }
+
+    #[test]
+    fn steps_complete_according_to_call_depth() {
+        let step = |kind| Step {
+            kind,
+            depth: 2,
+            line: Some((0, 10)),
+        };
+        let same = Some((0, 10));
+        let next = Some((0, 11));
+
+        let into = step(StepKind::Into);
+        assert!(!into.is_complete(2, same));
+        assert!(into.is_complete(2, next));
+        assert!(into.is_complete(3, same));
+        assert!(into.is_complete(1, same));
+
+        let over = step(StepKind::Over);
+        assert!(!over.is_complete(2, same));
+        assert!(over.is_complete(2, next));
+        assert!(!over.is_complete(3, next));
+        assert!(over.is_complete(1, same));
+
+        let out = step(StepKind::Out);
+        assert!(!out.is_complete(2, next));
+        assert!(!out.is_complete(3, next));
+        assert!(out.is_complete(1, same));
+    }
+}
@@ -0,0 +1,328 @@
+//! Dirty schedulers, on which native functions which may run for a long time, or block, are run
+//!
+//! Normal schedulers must never be held up by a single process for long, as every other process
+//! on them is held up with it. A native function which can't promise that instead returns a
+//! generator which does the work, as natives which yield do, after marking the calling process
+//! with [`schedule`]. When the process is scheduled out, its scheduler migrates it to the pool
+//! of dirty schedulers of the requested kind, which resume the generator until it completes, or
+//! until it calls [`leave`] to finish its work on a normal scheduler. The process is then put
+//! back in the run queue of the normal schedulers.
+//!
+//! A process on a dirty scheduler remains active, so signals sent to it don't reschedule it, and
+//! are handled once it is back on a normal scheduler. Its lock is held while its generator runs,
+//! so other processes must not wait on it, e.g. system tasks are scheduled with a signal instead.
+//!
+//! Unlike normal schedulers, dirty schedulers are plain threads with no state of their own, so
+//! code run on them must not use `current_scheduler`.
+use std::env;
+use std::fmt;
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+use crossbeam::channel::{self, Receiver, Sender};
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::{
+    ContinuationResult, Generator, GeneratorState, Process, ProcessLock, StatusFlags, RETURN_REG,
+};
+use firefly_rt::scheduler;
+use firefly_rt::term::OpaqueTerm;
+
+/// The number of dirty I/O schedulers started unless configured otherwise, as in OTP
+const DEFAULT_IO_SCHEDULERS: usize = 10;
+
+/// The kinds of work done by dirty schedulers, each of which has its own pool
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DirtyKind {
+    /// Work which is bound by the CPU, e.g. long-running computations
+    Cpu,
+    /// Work which is bound by I/O, e.g. blocking system calls
+    Io,
+}
+impl DirtyKind {
+    /// Returns the kind of dirty scheduler a process with `status` has been migrated to, if any
+    pub fn of(status: StatusFlags) -> Option<Self> {
+        if status.contains(StatusFlags::DIRTY_CPU_PROC) {
+            Some(Self::Cpu)
+        } else if status.contains(StatusFlags::DIRTY_IO_PROC) {
+            Some(Self::Io)
+        } else {
+            None
+        }
+    }
+
+    fn status(self) -> StatusFlags {
+        match self {
+            Self::Cpu => StatusFlags::DIRTY_CPU_PROC,
+            Self::Io => StatusFlags::DIRTY_IO_PROC,
+        }
+    }
+}
+impl fmt::Display for DirtyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cpu => f.write_str("cpu"),
+            Self::Io => f.write_str("io"),
+        }
+    }
+}
+
+/// The run queues of the dirty scheduler pools
+struct Pools {
+    cpu: Sender<Arc<Process>>,
+    io: Sender<Arc<Process>>,
+}
+
+static POOLS: OnceLock<Pools> = OnceLock::new();
+
+/// Starts the dirty scheduler pools
+///
+/// By default, there are as many dirty CPU schedulers as normal schedulers, and 10 dirty I/O
+/// schedulers. These can be changed with `ERTS_DIRTY_CPU_SCHEDULERS` and
+/// `ERTS_DIRTY_IO_SCHEDULERS` respectively.
+pub fn init(num_schedulers: usize) {
+    POOLS.get_or_init(|| {
+        let cpu = count_from_env("ERTS_DIRTY_CPU_SCHEDULERS", num_schedulers);
+        let io = count_from_env("ERTS_DIRTY_IO_SCHEDULERS", DEFAULT_IO_SCHEDULERS);
+        scheduler::set_dirty(cpu as u32, io as u32);
+        Pools {
+            cpu: start(DirtyKind::Cpu, cpu),
+            io: start(DirtyKind::Io, io),
+        }
+    });
+}
+
+fn count_from_env(var: &str, default: usize) -> usize {
+    match env::var(var) {
+        Ok(value) => match value.parse::<usize>() {
+            Ok(count) if count > 0 => count,
+            _ => {
+                eprintln!(
+                    "Ignoring invalid {} value, expected a positive integer, got '{}'. Using {} instead..",
+                    var, value, default
+                );
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+fn start(kind: DirtyKind, count: usize) -> Sender<Arc<Process>> {
+    let (sender, receiver) = channel::unbounded();
+    for i in 0..count {
+        let receiver = receiver.clone();
+        thread::Builder::new()
+            .name(format!("dirty_{}_{}", kind, i + 1))
+            .spawn(move || run(kind, receiver))
+            .expect("unable to start dirty scheduler");
+    }
+    sender
+}
+
+/// Suspends the calling process until `generator` has run on a dirty scheduler of type `kind`
+///
+/// This is returned by native functions in place of their result, see the module docs.
+pub fn schedule(process: &mut ProcessLock, kind: DirtyKind, generator: Generator) -> ErlangResult {
+    enter(process, kind);
+    ErlangResult::Await(Box::new(generator))
+}
+
+/// Marks `process` as having work to do on a dirty scheduler of type `kind`
+///
+/// The process is migrated when it is next scheduled out, e.g. when a generator yields.
+pub fn enter(process: &ProcessLock, kind: DirtyKind) {
+    process.remove_status_flags(
+        StatusFlags::DIRTY_CPU_PROC | StatusFlags::DIRTY_IO_PROC,
+        Ordering::Relaxed,
+    );
+    process.set_status_flags(kind.status(), Ordering::Release);
+}
+
+/// Marks `process` as having no more work to do on a dirty scheduler
+///
+/// The process is migrated back to the normal schedulers when it is next scheduled out.
+pub fn leave(process: &ProcessLock) {
+    process.remove_status_flags(
+        StatusFlags::DIRTY_CPU_PROC | StatusFlags::DIRTY_IO_PROC,
+        Ordering::Release,
+    );
+}
+
+/// Hands `process`, which has been scheduled out with `status`, to the dirty scheduler pool it
+/// requested with [`enter`]
+pub(crate) fn migrate(process: Arc<Process>, status: StatusFlags) {
+    let pools = POOLS.get().expect("dirty schedulers have not been started");
+    let pool = match DirtyKind::of(status).unwrap() {
+        DirtyKind::Cpu => &pools.cpu,
+        DirtyKind::Io => &pools.io,
+    };
+    // The pools are never shut down, so this can't fail
+    pool.send(process).unwrap();
+}
+
+/// The core loop of a dirty scheduler of type `kind`
+fn run(kind: DirtyKind, runq: Receiver<Arc<Process>>) {
+    for process in runq.iter() {
+        process.set_status_flags(StatusFlags::RUNNING, Ordering::Release);
+        // The lock is released each time the generator yields, so that others waiting on it, e.g.
+        // to schedule a system task, are only held up for a single step of the work
+        let injector = loop {
+            let mut process = process.lock();
+            let mut generator = process.awaiting.take().unwrap();
+            match generator.resume(&mut process) {
+                GeneratorState::Yielded(_) => {
+                    process.awaiting = Some(generator);
+                    if DirtyKind::of(process.status(Ordering::Acquire)) == Some(kind) {
+                        drop(process);
+                        thread::yield_now();
+                        continue;
+                    }
+                }
+                GeneratorState::Completed(result) => {
+                    leave(&process);
+                    process.awaiting = Some(completed(&mut process, result));
+                }
+            }
+            break process.injector.clone();
+        };
+        let status = process.remove_status_flags(StatusFlags::RUNNING, Ordering::Release);
+        if status.is_dirty() {
+            // The generator has more work to do on the other pool
+            migrate(process, status);
+        } else {
+            injector.push(process);
+        }
+    }
+}
+
+/// Returns a generator which completes with `result` when the process is next scheduled
+///
+/// A returned term is kept in the return register of the process until then, so that it
+/// survives garbage collection.
+fn completed(process: &mut ProcessLock, result: Result<OpaqueTerm, ()>) -> Generator {
+    match result {
+        Ok(value) => {
+            process.stack.store(RETURN_REG, value);
+            Generator::new(returned, ptr::null_mut())
+        }
+        Err(()) => Generator::new(raised, ptr::null_mut()),
+    }
+}
+
+extern "C-unwind" fn returned(process: &mut ProcessLock, _state: *mut ()) -> ContinuationResult {
+    ContinuationResult::Complete(Ok(process.stack.load(RETURN_REG)))
+}
+
+extern "C-unwind" fn raised(_process: &mut ProcessLock, _state: *mut ()) -> ContinuationResult {
+    ContinuationResult::Complete(Err(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    use crossbeam::deque::{Injector, Steal};
+
+    use firefly_rt::function::ModuleFunctionArity;
+    use firefly_rt::scheduler::SchedulerId;
+    use firefly_rt::term::{atoms, Atom};
+
+    use super::*;
+
+    /// The names of the threads on which each step of `work` ran
+    type Threads = Mutex<Vec<String>>;
+
+    fn record(state: *mut ()) {
+        let threads = unsafe { &*state.cast::<Threads>() };
+        let name = thread::current().name().unwrap_or_default().to_string();
+        threads.lock().unwrap().push(name);
+    }
+
+    /// Starts on a dirty I/O scheduler, then moves to a dirty CPU scheduler to finish
+    extern "C-unwind" fn work(process: &mut ProcessLock, state: *mut ()) -> ContinuationResult {
+        record(state);
+        enter(process, DirtyKind::Cpu);
+        ContinuationResult::Yield(finish, ())
+    }
+
+    extern "C-unwind" fn finish(_process: &mut ProcessLock, state: *mut ()) -> ContinuationResult {
+        record(state);
+        ContinuationResult::Complete(Ok(atoms::Ok.into()))
+    }
+
+    #[test]
+    fn pool_migration_test() {
+        init(1);
+        let injector = Arc::new(Injector::new());
+        let mfa = ModuleFunctionArity {
+            module: Atom::try_from("dirty_test").unwrap(),
+            function: Atom::try_from("work").unwrap(),
+            arity: 0,
+        };
+        let process = Process::new(
+            SchedulerId::INVALID,
+            None,
+            None,
+            mfa,
+            &[],
+            injector.clone(),
+            Default::default(),
+        );
+
+        let state = Box::into_raw(Box::<Threads>::default());
+        {
+            let mut locked = process.lock();
+            let generator = Generator::new(work, state.cast());
+            match schedule(&mut locked, DirtyKind::Io, generator) {
+                ErlangResult::Await(generator) => locked.awaiting = Some(*generator),
+                _ => panic!("expected the call to await its dirty work"),
+            }
+        }
+        let status = process.status(Ordering::Acquire);
+        assert_eq!(DirtyKind::of(status), Some(DirtyKind::Io));
+        migrate(process.clone(), status);
+
+        // The process is handed back to the normal schedulers once all of its work is done
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let returned = loop {
+            match injector.steal() {
+                Steal::Success(returned) => break returned,
+                _ => {
+                    assert!(Instant::now() < deadline, "the process was never requeued");
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+        };
+        assert!(Arc::ptr_eq(&returned, &process));
+        let status = process.status(Ordering::Acquire);
+        assert!(!status.is_dirty());
+        assert!(!status.contains(StatusFlags::RUNNING));
+
+        let threads = unsafe { Box::from_raw(state) }.into_inner().unwrap();
+        assert_eq!(threads.len(), 2);
+        assert!(
+            threads[0].starts_with("dirty_io_"),
+            "ran on {}",
+            &threads[0]
+        );
+        assert!(
+            threads[1].starts_with("dirty_cpu_"),
+            "ran on {}",
+            &threads[1]
+        );
+
+        // The result is returned when the process is next run by a normal scheduler
+        let mut locked = process.lock();
+        let mut generator = locked.awaiting.take().unwrap();
+        match generator.resume(&mut locked) {
+            GeneratorState::Completed(Ok(result)) => {
+                assert!(result.is_atom() && result.as_atom() == atoms::Ok)
+            }
+            _ => panic!("expected the call to have completed"),
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! The C interface to the embedding API, as declared in `include/firefly.h`
+//!
+//! Values and runtimes are handed to C as opaque pointers to boxed Rust objects. Functions which
+//! take a `firefly_value_t *` by value take ownership of it, while those taking a
+//! `const firefly_value_t *` only borrow it.
+#![allow(non_camel_case_types)]
+
+use std::ffi::{c_char, c_int, c_void, CStr};
+use std::ptr;
+use std::slice;
+
+use firefly_number::Int;
+
+use super::{Call, Error, Runtime, Value};
+
+pub type firefly_value_t = Value;
+pub type firefly_runtime_t = Runtime;
+pub type firefly_call_t = Call;
+
+/// A function implemented by the host, see `firefly_runtime_register_nif`
+pub type firefly_nif_t = unsafe extern "C" fn(
+    data: *mut c_void,
+    argc: usize,
+    argv: *const *const firefly_value_t,
+    result: *mut *mut firefly_value_t,
+) -> c_int;
+
+pub const FIREFLY_OK: c_int = 0;
+pub const FIREFLY_EXITED: c_int = 1;
+pub const FIREFLY_UNDEF: c_int = 2;
+pub const FIREFLY_NOPROC: c_int = 3;
+pub const FIREFLY_STOPPED: c_int = 4;
+pub const FIREFLY_BADARG: c_int = 5;
+pub const FIREFLY_ALREADY_STARTED: c_int = 6;
+pub const FIREFLY_INVALID_BYTECODE: c_int = 7;
+pub const FIREFLY_INIT_FAILED: c_int = 8;
+pub const FIREFLY_HALTED: c_int = 9;
+
+#[repr(C)]
+pub enum firefly_value_kind_t {
+    FIREFLY_ATOM,
+    FIREFLY_INTEGER,
+    FIREFLY_FLOAT,
+    FIREFLY_BINARY,
+    FIREFLY_TUPLE,
+    FIREFLY_LIST,
+    FIREFLY_MAP,
+    FIREFLY_PID,
+    FIREFLY_OPAQUE,
+}
+
+impl Error {
+    fn code(&self) -> c_int {
+        match self {
+            Self::InitFailed(_) => FIREFLY_INIT_FAILED,
+            Self::AlreadyStarted => FIREFLY_ALREADY_STARTED,
+            Self::InvalidBytecode(_) => FIREFLY_INVALID_BYTECODE,
+            Self::InvalidAtom(_) => FIREFLY_BADARG,
+            Self::Undefined(_) => FIREFLY_UNDEF,
+            Self::NoProcess(_) => FIREFLY_NOPROC,
+            Self::Exited(_) => FIREFLY_EXITED,
+            Self::Stopped => FIREFLY_STOPPED,
+            Self::Halted(_) => FIREFLY_HALTED,
+        }
+    }
+}
+
+/// The data given with a NIF, which the host is responsible for making safe to share
+struct NifData(*mut c_void);
+unsafe impl Send for NifData {}
+unsafe impl Sync for NifData {}
+impl NifData {
+    // The callback must capture this, not the pointer it wraps, to be sendable
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_atom(name: *const c_char) -> *mut firefly_value_t {
+    let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+    into_raw(Value::Atom(name))
+}
+
+#[no_mangle]
+pub extern "C" fn firefly_value_int(i: i64) -> *mut firefly_value_t {
+    into_raw(Value::from(i))
+}
+
+#[no_mangle]
+pub extern "C" fn firefly_value_float(x: f64) -> *mut firefly_value_t {
+    into_raw(Value::Float(x))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_binary(
+    bytes: *const u8,
+    len: usize,
+) -> *mut firefly_value_t {
+    into_raw(Value::Binary(borrow_slice(bytes, len).to_vec()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_tuple(
+    elements: *const *mut firefly_value_t,
+    len: usize,
+) -> *mut firefly_value_t {
+    into_raw(Value::Tuple(take_values(elements, len)))
+}
+
+/// `tail` may be NULL, in which case the list is proper
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_list(
+    elements: *const *mut firefly_value_t,
+    len: usize,
+    tail: *mut firefly_value_t,
+) -> *mut firefly_value_t {
+    let tail = (!tail.is_null()).then(|| Box::from_raw(tail));
+    into_raw(Value::List(take_values(elements, len), tail))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_map(
+    keys: *const *mut firefly_value_t,
+    values: *const *mut firefly_value_t,
+    len: usize,
+) -> *mut firefly_value_t {
+    let keys = take_values(keys, len);
+    let values = take_values(values, len);
+    into_raw(Value::Map(keys.into_iter().zip(values).collect()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_clone(
+    value: *const firefly_value_t,
+) -> *mut firefly_value_t {
+    into_raw((*value).clone())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_free(value: *mut firefly_value_t) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_kind(value: *const firefly_value_t) -> firefly_value_kind_t {
+    use firefly_value_kind_t::*;
+
+    match &*value {
+        Value::Atom(_) => FIREFLY_ATOM,
+        Value::Integer(_) => FIREFLY_INTEGER,
+        Value::Float(_) => FIREFLY_FLOAT,
+        Value::Binary(_) => FIREFLY_BINARY,
+        Value::Tuple(_) => FIREFLY_TUPLE,
+        Value::List(..) => FIREFLY_LIST,
+        Value::Map(_) => FIREFLY_MAP,
+        Value::Pid(_) => FIREFLY_PID,
+        Value::Opaque(_) => FIREFLY_OPAQUE,
+    }
+}
+
+/// Returns false if `value` is not an integer, or does not fit in 64 bits
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_get_int(
+    value: *const firefly_value_t,
+    out: *mut i64,
+) -> bool {
+    match &*value {
+        Value::Integer(Int::Small(i)) => {
+            out.write(*i);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_get_float(
+    value: *const firefly_value_t,
+    out: *mut f64,
+) -> bool {
+    match &*value {
+        Value::Float(x) => {
+            out.write(*x);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Returns the name of an atom, the contents of a binary, or the printed form of an opaque
+/// value, which are not NUL-terminated; returns NULL for any other value
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_bytes(
+    value: *const firefly_value_t,
+    len: *mut usize,
+) -> *const u8 {
+    let bytes = match &*value {
+        Value::Atom(s) | Value::Opaque(s) => s.as_bytes(),
+        Value::Binary(bytes) => bytes.as_slice(),
+        _ => return ptr::null(),
+    };
+    len.write(bytes.len());
+    bytes.as_ptr()
+}
+
+/// Returns the number of elements in a tuple or list, or entries in a map; zero otherwise
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_len(value: *const firefly_value_t) -> usize {
+    match &*value {
+        Value::Tuple(elements) | Value::List(elements, _) => elements.len(),
+        Value::Map(pairs) => pairs.len(),
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_element(
+    value: *const firefly_value_t,
+    index: usize,
+) -> *const firefly_value_t {
+    match &*value {
+        Value::Tuple(elements) | Value::List(elements, _) => borrow(elements.get(index)),
+        _ => ptr::null(),
+    }
+}
+
+/// Returns the tail of an improper list, or NULL
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_list_tail(
+    value: *const firefly_value_t,
+) -> *const firefly_value_t {
+    match &*value {
+        Value::List(_, tail) => borrow(tail.as_deref()),
+        _ => ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_map_key(
+    value: *const firefly_value_t,
+    index: usize,
+) -> *const firefly_value_t {
+    match &*value {
+        Value::Map(pairs) => borrow(pairs.get(index).map(|(k, _)| k)),
+        _ => ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_map_value(
+    value: *const firefly_value_t,
+    index: usize,
+) -> *const firefly_value_t {
+    match &*value {
+        Value::Map(pairs) => borrow(pairs.get(index).map(|(_, v)| v)),
+        _ => ptr::null(),
+    }
+}
+
+/// Writes the printed form of `value` to `buf` like `snprintf`, returning its full length
+#[no_mangle]
+pub unsafe extern "C" fn firefly_value_format(
+    value: *const firefly_value_t,
+    buf: *mut c_char,
+    size: usize,
+) -> usize {
+    let formatted = (*value).to_string();
+    if size > 0 {
+        let len = formatted.len().min(size - 1);
+        ptr::copy_nonoverlapping(formatted.as_ptr(), buf.cast(), len);
+        buf.add(len).write(0);
+    }
+    formatted.len()
+}
+
+/// Starts the runtime with `len` bytes of `bytecode`, or with the bytecode linked into the
+/// program if NULL
+#[no_mangle]
+pub unsafe extern "C" fn firefly_runtime_start(
+    bytecode: *const u8,
+    len: usize,
+    schedulers: usize,
+    runtime: *mut *mut firefly_runtime_t,
+) -> c_int {
+    if schedulers == 0 {
+        return FIREFLY_BADARG;
+    }
+    let code = if bytecode.is_null() {
+        super::linked_bytecode()
+    } else {
+        super::read_bytecode(borrow_slice(bytecode, len))
+    };
+    match code.and_then(|code| Runtime::start(code, schedulers)) {
+        Ok(rt) => {
+            runtime.write(Box::into_raw(Box::new(rt)));
+            FIREFLY_OK
+        }
+        Err(err) => err.code(),
+    }
+}
+
+/// Calls `module:function(args..)`, taking ownership of the arguments
+///
+/// On success, `result` receives the returned value; if the process exits, it receives the exit
+/// reason, and `FIREFLY_EXITED` is returned.
+#[no_mangle]
+pub unsafe extern "C" fn firefly_runtime_call(
+    runtime: *const firefly_runtime_t,
+    module: *const c_char,
+    function: *const c_char,
+    args: *const *mut firefly_value_t,
+    argc: usize,
+    result: *mut *mut firefly_value_t,
+) -> c_int {
+    let mut call = ptr::null_mut();
+    match firefly_runtime_spawn(runtime, module, function, args, argc, &mut call) {
+        FIREFLY_OK => firefly_call_wait(call, result),
+        code => code,
+    }
+}
+
+/// Like `firefly_runtime_call`, but returns a handle for the call rather than waiting for it
+#[no_mangle]
+pub unsafe extern "C" fn firefly_runtime_spawn(
+    runtime: *const firefly_runtime_t,
+    module: *const c_char,
+    function: *const c_char,
+    args: *const *mut firefly_value_t,
+    argc: usize,
+    call: *mut *mut firefly_call_t,
+) -> c_int {
+    let args = take_values(args, argc);
+    let (Some(runtime), Some(module), Some(function)) =
+        (runtime.as_ref(), borrow_str(module), borrow_str(function))
+    else {
+        return FIREFLY_BADARG;
+    };
+    match runtime.spawn(module, function, args) {
+        Ok(spawned) => {
+            call.write(Box::into_raw(Box::new(spawned)));
+            FIREFLY_OK
+        }
+        Err(err) => err.code(),
+    }
+}
+
+/// Blocks until `call` completes, consuming it; `result` is set as by `firefly_runtime_call`
+#[no_mangle]
+pub unsafe extern "C" fn firefly_call_wait(
+    call: *mut firefly_call_t,
+    result: *mut *mut firefly_value_t,
+) -> c_int {
+    match Box::from_raw(call).wait() {
+        Ok(value) => {
+            result.write(into_raw(value));
+            FIREFLY_OK
+        }
+        Err(Error::Exited(reason)) => {
+            result.write(into_raw(reason));
+            FIREFLY_EXITED
+        }
+        Err(err) => err.code(),
+    }
+}
+
+/// Abandons `call` without waiting for it
+#[no_mangle]
+pub unsafe extern "C" fn firefly_call_free(call: *mut firefly_call_t) {
+    if !call.is_null() {
+        drop(Box::from_raw(call));
+    }
+}
+
+/// Sends `message` to the process registered as `name`, taking ownership of the message
+#[no_mangle]
+pub unsafe extern "C" fn firefly_runtime_send(
+    runtime: *const firefly_runtime_t,
+    name: *const c_char,
+    message: *mut firefly_value_t,
+) -> c_int {
+    let (Some(runtime), Some(name), false) =
+        (runtime.as_ref(), borrow_str(name), message.is_null())
+    else {
+        return FIREFLY_BADARG;
+    };
+    match runtime.send(name, *Box::from_raw(message)) {
+        Ok(_) => FIREFLY_OK,
+        Err(err) => err.code(),
+    }
+}
+
+/// Registers `fun` as the implementation of `module:function/arity`
+///
+/// `fun` is called with `data` and the arguments, which it borrows. It returns zero on success,
+/// with the returned value in `result`; otherwise it raises an error with the reason in
+/// `result`, or `badarg` if none is given. `data` must be safe to use from any thread.
+#[no_mangle]
+pub unsafe extern "C" fn firefly_runtime_register_nif(
+    runtime: *const firefly_runtime_t,
+    module: *const c_char,
+    function: *const c_char,
+    arity: u8,
+    fun: firefly_nif_t,
+    data: *mut c_void,
+) -> c_int {
+    let (Some(runtime), Some(module), Some(function)) =
+        (runtime.as_ref(), borrow_str(module), borrow_str(function))
+    else {
+        return FIREFLY_BADARG;
+    };
+    let data = NifData(data);
+    let callback = move |args: &[Value]| {
+        let argv = args
+            .iter()
+            .map(|arg| arg as *const Value)
+            .collect::<Vec<_>>();
+        let mut result = ptr::null_mut();
+        let status = unsafe { fun(data.get(), argv.len(), argv.as_ptr(), &mut result) };
+        let result = match result.is_null() {
+            true => None,
+            false => Some(*unsafe { Box::from_raw(result) }),
+        };
+        match (status, result) {
+            (FIREFLY_OK, Some(value)) => Ok(value),
+            (_, Some(reason)) => Err(reason),
+            (_, None) => Err(Value::atom("badarg")),
+        }
+    };
+    match runtime.register_nif(module, function, arity, callback) {
+        Ok(_) => FIREFLY_OK,
+        Err(err) => err.code(),
+    }
+}
+
+/// Shuts down and frees `runtime`, see [`Runtime::shutdown`]
+#[no_mangle]
+pub unsafe extern "C" fn firefly_runtime_shutdown(runtime: *mut firefly_runtime_t) -> c_int {
+    match Box::from_raw(runtime).shutdown() {
+        Ok(_) => FIREFLY_OK,
+        Err(err) => err.code(),
+    }
+}
+
+fn into_raw(value: Value) -> *mut firefly_value_t {
+    Box::into_raw(Box::new(value))
+}
+
+fn borrow(value: Option<&Value>) -> *const firefly_value_t {
+    value.map(|v| v as *const Value).unwrap_or(ptr::null())
+}
+
+unsafe fn borrow_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+unsafe fn borrow_slice<'a, T>(data: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+/// Takes ownership of `len` values in `values`
+unsafe fn take_values(values: *const *mut firefly_value_t, len: usize) -> Vec<Value> {
+    borrow_slice(values, len)
+        .iter()
+        .map(|value| *Box::from_raw(*value))
+        .collect()
+}
@@ -0,0 +1,587 @@
+//! An API for hosting the emulator in another program
+//!
+//! Rather than booting the system via `init` and running until it halts, as the default entry
+//! point does, a host program starts a [`Runtime`] from its own entry point, and calls into
+//! Erlang by spawning processes whose results it can wait for, or by sending messages to
+//! registered processes. Erlang code can call back into the host through functions registered as
+//! NIFs with [`Runtime::register_nif`].
+//!
+//! Terms are exchanged with the host as [`Value`]s, which own all of their data. The same API is
+//! exposed to C via the functions declared in `include/firefly.h`.
+//!
+//! The runtime relies on global state, so it can only be started once per program, and must be
+//! initialized, via [`init`], before any atoms are created, e.g. by reading bytecode.
+mod ffi;
+mod value;
+
+pub use self::value::Value;
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::panic;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crossbeam::deque::Injector;
+
+use firefly_bytecode::{ByteCode, BytecodeReader, Function};
+use firefly_rt::function::ModuleFunctionArity;
+use firefly_rt::process::{Process, ProcessId};
+use firefly_rt::scheduler::{self, Scheduler};
+use firefly_rt::services::registry::{self, Registrant, WeakAddress};
+use firefly_rt::term::{
+    atom::GlobalAtomTable, Atom, LayoutBuilder, OpaqueTerm, Pid, Term, TermFragment,
+};
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::emulator::{Emulator, EmulatorError};
+
+/// A function implemented by the host, callable from Erlang
+///
+/// Returning `Err` raises an error with the given reason in the calling process.
+pub type Nif = Arc<dyn Fn(&[Value]) -> Result<Value, Value> + Send + Sync>;
+
+/// The emulator was started via the default entry point, not by a host
+const STANDALONE: u8 = 0;
+/// The emulator was started by a host, and is running
+const RUNNING: u8 = 1;
+/// The emulator was started by a host, and its schedulers are stopping
+const STOPPING: u8 = 2;
+
+static STATE: AtomicU8 = AtomicU8::new(STANDALONE);
+
+/// Set once a runtime has been started, as it can't be started again, even after shutdown
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// The result of global initialization, see [`init`]
+static INIT: OnceLock<Result<(), i32>> = OnceLock::new();
+
+/// The processes spawned by the host whose results are being waited for
+static WAITERS: Mutex<BTreeMap<ProcessId, oneshot::Sender<Result<Value, Value>>>> =
+    Mutex::new(BTreeMap::new());
+
+/// The functions registered by the host
+static NIFS: RwLock<BTreeMap<ModuleFunctionArity, Nif>> = RwLock::new(BTreeMap::new());
+
+/// Returns true if the emulator was started by a host
+#[inline(always)]
+pub fn is_embedded() -> bool {
+    STATE.load(Ordering::Relaxed) != STANDALONE
+}
+
+/// Returns true if the host has requested the schedulers to stop, or one of them has halted
+#[inline(always)]
+pub fn is_stopping() -> bool {
+    STATE.load(Ordering::Relaxed) == STOPPING
+}
+
+/// Called by a scheduler when `process` returns `value` from its initial call
+pub fn returned(process: ProcessId, value: OpaqueTerm) {
+    if is_embedded() {
+        complete(process, Ok(value));
+    }
+}
+
+/// Called by a scheduler when `process` exits with `reason`, just before it is freed
+///
+/// If the process returned from its initial call, the result was already delivered by
+/// [`returned`], so this only has an effect on processes which exited for any other reason.
+pub fn exited(process: ProcessId, reason: OpaqueTerm) {
+    if is_embedded() {
+        complete(process, Err(reason));
+    }
+}
+
+/// Returns the function registered by the host for `mfa`, if any
+///
+/// Schedulers check this before any native implementation when calling a function which is
+/// declared as a NIF.
+#[inline]
+pub fn find_nif(mfa: &ModuleFunctionArity) -> Option<Nif> {
+    if is_embedded() {
+        NIFS.read().unwrap().get(mfa).cloned()
+    } else {
+        None
+    }
+}
+
+fn complete(process: ProcessId, result: Result<OpaqueTerm, OpaqueTerm>) {
+    let Some(waiter) = WAITERS.lock().unwrap().remove(&process) else { return };
+    // The receiver is gone if the host is no longer interested in the result
+    waiter
+        .send(result.map(Value::from_term).map_err(Value::from_term))
+        .ok();
+}
+
+/// Called when a scheduler stops, so that the others follow, and nobody waits on a process which
+/// will never run again
+fn stopped() {
+    STATE.store(STOPPING, Ordering::Relaxed);
+    WAITERS.lock().unwrap().clear();
+}
+
+/// An error which occurred while interacting with a [`Runtime`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// Global initialization failed with the given exit code
+    InitFailed(i32),
+    /// A runtime was already started by this program
+    AlreadyStarted,
+    /// The bytecode given to the runtime is invalid
+    InvalidBytecode(String),
+    /// The name is not valid as an atom
+    InvalidAtom(String),
+    /// The function called is not defined in the loaded bytecode
+    Undefined(String),
+    /// There is no process registered under the name a message was sent to
+    NoProcess(String),
+    /// The process exited with the given reason before returning
+    Exited(Value),
+    /// The runtime stopped before the request could be completed
+    Stopped,
+    /// The system was halted with the given non-zero status
+    Halted(u32),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InitFailed(code) => write!(f, "runtime initialization failed with code {}", code),
+            Self::AlreadyStarted => f.write_str("the runtime can only be started once"),
+            Self::InvalidBytecode(reason) => write!(f, "invalid bytecode: {}", reason),
+            Self::InvalidAtom(name) => write!(f, "invalid atom '{}'", name),
+            Self::Undefined(mfa) => write!(f, "undefined function {}", mfa),
+            Self::NoProcess(name) => write!(f, "no process registered as '{}'", name),
+            Self::Exited(reason) => write!(f, "process exited with reason {}", reason),
+            Self::Stopped => f.write_str("the runtime has stopped"),
+            Self::Halted(status) => write!(f, "the system halted with status {}", status),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// Performs the one-time initialization of global state required by the runtime
+///
+/// This must be called before any atoms are created, and is called by [`read_bytecode`] and
+/// [`Runtime::start`], so it only needs to be called explicitly if atoms are created before
+/// either of those. Calling it more than once has no effect.
+pub fn init() -> Result<(), Error> {
+    let result = *INIT.get_or_init(|| {
+        // The host may have installed its own logger
+        crate::logger().try_init().ok();
+        firefly_crt::init()
+    });
+    result.map_err(Error::InitFailed)
+}
+
+/// Reads bytecode in the binary format in which the compiler links it into programs, for use
+/// with [`Runtime::start`]
+pub fn read_bytecode(bytes: &[u8]) -> Result<Arc<ByteCode<Atom, GlobalAtomTable>>, Error> {
+    init()?;
+    let reader = BytecodeReader::new(bytes);
+    reader
+        .read()
+        .map(Arc::new)
+        .map_err(|err| Error::InvalidBytecode(format!("{:?}", err)))
+}
+
+/// Reads the bytecode linked into the program by the compiler, for use with [`Runtime::start`]
+pub fn linked_bytecode() -> Result<Arc<ByteCode<Atom, GlobalAtomTable>>, Error> {
+    read_bytecode(crate::bytecode())
+}
+
+/// A running instance of the emulator, hosted by another program
+///
+/// Dropping the runtime shuts it down, see [`Runtime::shutdown`].
+pub struct Runtime {
+    code: Arc<ByteCode<Atom, GlobalAtomTable>>,
+    /// The global run queue, in which processes spawned by the host are placed
+    injector: Arc<Injector<Arc<Process>>>,
+    schedulers: Vec<Arc<Emulator>>,
+    handles: Vec<JoinHandle<Result<(), EmulatorError>>>,
+    /// The async runtime on which the schedulers run, until shutdown
+    runtime: Option<tokio::runtime::Runtime>,
+    /// The group leader of processes spawned by the host
+    group_leader: Option<Pid>,
+}
+impl Runtime {
+    /// Starts the runtime with `code`, running processes on `schedulers` scheduler threads
+    ///
+    /// If `code` contains the standard I/O server, it is started, and made the group leader of
+    /// every process spawned by the host. Unlike the default entry point, `init` is not booted,
+    /// and the runtime keeps running when there are no processes left.
+    ///
+    /// This can only be called once per program, and panics if `schedulers` is zero.
+    pub fn start(
+        code: Arc<ByteCode<Atom, GlobalAtomTable>>,
+        schedulers: usize,
+    ) -> Result<Self, Error> {
+        assert_ne!(schedulers, 0, "at least one scheduler is required");
+
+        init()?;
+        if STARTED.swap(true, Ordering::AcqRel) {
+            return Err(Error::AlreadyStarted);
+        }
+        STATE.store(RUNNING, Ordering::Relaxed);
+
+        crate::init(&code, schedulers);
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("unable to start scheduler");
+        // Signals are left to the host to handle, but the system dispatcher is still required
+        runtime.spawn(crate::sys::dispatcher::start());
+        let injector = Arc::new(Injector::new());
+
+        // Each scheduler hands itself back once created, so that it can be woken up when the
+        // host gives it work to do
+        let (sender, receiver) = mpsc::channel();
+        let mut handles = Vec::with_capacity(schedulers);
+        for _ in 0..schedulers {
+            let emu_injector = injector.clone();
+            let emu_code = code.clone();
+            let sender = sender.clone();
+            handles.push(runtime.spawn_blocking(move || {
+                let emulator = scheduler::create(move |id| {
+                    Ok::<_, Infallible>(Emulator::new(id, emu_code, emu_injector))
+                })
+                .unwrap();
+                sender.send(emulator.clone()).ok();
+                let result = emulator.start(false);
+                stopped();
+                result
+            }));
+        }
+        drop(sender);
+
+        let mut this = Self {
+            code,
+            injector,
+            schedulers: receiver.iter().take(schedulers).collect(),
+            handles,
+            runtime: Some(runtime),
+            group_leader: None,
+        };
+
+        let user = "user:server/0".parse::<ModuleFunctionArity>().unwrap();
+        if this.is_defined(&user) {
+            let user = this.spawn_process(user, vec![], None);
+            this.group_leader = Some(user.pid());
+        }
+
+        Ok(this)
+    }
+
+    /// Spawns a process which calls `module:function(args..)`, returning a handle which can be
+    /// used to wait for its result
+    pub fn spawn(&self, module: &str, function: &str, args: Vec<Value>) -> Result<Call, Error> {
+        if is_stopping() {
+            return Err(Error::Stopped);
+        }
+        let mfa = ModuleFunctionArity::new(atom(module)?, atom(function)?, args.len());
+        if !self.is_defined(&mfa) {
+            return Err(Error::Undefined(mfa.to_string()));
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let process = self.spawn_process(mfa, args, Some(sender));
+        Ok(Call {
+            pid: process.pid(),
+            receiver,
+        })
+    }
+
+    /// Calls `module:function(args..)` in a new process, blocking the current thread until it
+    /// returns
+    ///
+    /// See [`Call::wait`] for details.
+    pub fn call(&self, module: &str, function: &str, args: Vec<Value>) -> Result<Value, Error> {
+        self.spawn(module, function, args)?.wait()
+    }
+
+    /// Sends `message` to the process registered as `name`
+    ///
+    /// Like a send in Erlang, this succeeds even if the process exits before receiving it.
+    pub fn send(&self, name: &str, message: Value) -> Result<(), Error> {
+        if is_stopping() {
+            return Err(Error::Stopped);
+        }
+        let Some(Registrant::Process(process)) = registry::get_by_name(atom(name)?) else {
+            return Err(Error::NoProcess(name.to_owned()));
+        };
+        process
+            .send_fragment(WeakAddress::System, to_fragment(&message))
+            .ok();
+        self.wake();
+        Ok(())
+    }
+
+    /// Registers `callback` as the implementation of `module:function/arity`
+    ///
+    /// The function must be declared as a NIF in the loaded bytecode, i.e. via the `-nifs`
+    /// attribute, and the callback replaces both its Erlang definition and any native
+    /// implementation. Callbacks are run on the scheduler thread of the calling process, so they
+    /// should not block for long.
+    ///
+    /// Returns [`Error::Undefined`] if the function is not declared as a NIF in the loaded bytecode.
+    pub fn register_nif<F>(
+        &self,
+        module: &str,
+        function: &str,
+        arity: u8,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&[Value]) -> Result<Value, Value> + Send + Sync + 'static,
+    {
+        let mfa = ModuleFunctionArity::new(atom(module)?, atom(function)?, arity as usize);
+        match self.code.function_by_mfa(&mfa.into()) {
+            Some(Function::Bytecode { is_nif: true, .. }) => (),
+            _ => return Err(Error::Undefined(mfa.to_string())),
+        }
+        NIFS.write().unwrap().insert(mfa, Arc::new(callback));
+        Ok(())
+    }
+
+    /// Stops all schedulers and waits for them to exit
+    ///
+    /// Any processes still running are abandoned, and calls waiting on them fail with
+    /// [`Error::Stopped`]. Returns [`Error::Halted`] if the system was halted by Erlang code with
+    /// a non-zero status.
+    ///
+    /// This blocks the current thread, so it must not be called from within an async context.
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        let Some(runtime) = self.runtime.take() else { return Ok(()) };
+
+        STATE.store(STOPPING, Ordering::Relaxed);
+        self.wake();
+
+        let mut status = 0;
+        for handle in self.handles.drain(..) {
+            match runtime.block_on(handle) {
+                Err(join_err) => {
+                    if let Ok(reason) = join_err.try_into_panic() {
+                        panic::resume_unwind(reason);
+                    }
+                    status = 1;
+                }
+                Ok(Ok(_)) | Ok(Err(EmulatorError::Halt(0))) => (),
+                Ok(Err(EmulatorError::Halt(n))) => status = n,
+                Ok(Err(_)) => status = 1,
+            }
+        }
+        stopped();
+
+        crate::write_profile();
+        // Give some time for any outstanding background tasks to clean up
+        runtime.shutdown_timeout(Duration::from_millis(50));
+
+        match status {
+            0 => Ok(()),
+            n => Err(Error::Halted(n)),
+        }
+    }
+
+    fn is_defined(&self, mfa: &ModuleFunctionArity) -> bool {
+        self.code.function_by_mfa(&(*mfa).into()).is_some()
+    }
+
+    /// Spawns a parentless process which calls `mfa` with `args`, and schedules it for execution
+    /// immediately
+    ///
+    /// If given, `waiter` receives the result of the process once it exits.
+    fn spawn_process(
+        &self,
+        mfa: ModuleFunctionArity,
+        args: Vec<Value>,
+        waiter: Option<oneshot::Sender<Result<Value, Value>>>,
+    ) -> Arc<Process> {
+        // The arguments are copied to the process heap when it is created
+        let args = to_fragment(&Value::Tuple(args));
+        let Term::Tuple(argv) = args.term.into() else { unreachable!() };
+        let process = Process::new(
+            self.schedulers[0].id(),
+            None,
+            self.group_leader.clone(),
+            mfa,
+            argv.as_slice(),
+            self.injector.clone(),
+            Default::default(),
+        );
+
+        registry::register_process(process.clone());
+        // The waiter must be in place before the process can run
+        if let Some(waiter) = waiter {
+            WAITERS.lock().unwrap().insert(process.id(), waiter);
+        }
+        self.injector.push(process.clone());
+        self.wake();
+
+        process
+    }
+
+    /// Wakes up any idle schedulers so that they pick up new work
+    fn wake(&self) {
+        for scheduler in self.schedulers.iter() {
+            scheduler.wake();
+        }
+    }
+}
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.stop().ok();
+    }
+}
+
+/// A call made via [`Runtime::spawn`]
+///
+/// The result of the call can be obtained either by awaiting this, or by blocking on it with
+/// [`Call::wait`].
+pub struct Call {
+    pid: Pid,
+    receiver: oneshot::Receiver<Result<Value, Value>>,
+}
+impl Call {
+    /// The pid of the process making the call
+    pub fn pid(&self) -> &Pid {
+        &self.pid
+    }
+
+    /// Blocks the current thread until the process making the call exits
+    ///
+    /// Returns the value returned by the process, or [`Error::Exited`] with the exit reason if
+    /// it exited any other way. This must not be called from within an async context, in which
+    /// case the call should be awaited instead.
+    pub fn wait(self) -> Result<Value, Error> {
+        into_result(self.receiver.blocking_recv())
+    }
+}
+impl Future for Call {
+    type Output = Result<Value, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx).map(into_result)
+    }
+}
+
+fn into_result(
+    result: Result<Result<Value, Value>, oneshot::error::RecvError>,
+) -> Result<Value, Error> {
+    match result {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(reason)) => Err(Error::Exited(reason)),
+        // The waiter is dropped without a result when the runtime stops
+        Err(_) => Err(Error::Stopped),
+    }
+}
+
+fn atom(name: &str) -> Result<Atom, Error> {
+    Atom::try_from(name).map_err(|_| Error::InvalidAtom(name.to_owned()))
+}
+
+/// Allocates `value` in a new heap fragment
+fn to_fragment(value: &Value) -> TermFragment {
+    let mut layout = LayoutBuilder::new();
+    value.layout(&mut layout);
+    let fragment = layout.into_fragment().unwrap();
+    let term = value.to_term(unsafe { fragment.as_ref() }).unwrap();
+    TermFragment {
+        term,
+        fragment: Some(fragment),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firefly_bytecode::Builder;
+
+    use super::*;
+
+    /// Builds `test:add/2`, which returns the sum of its arguments, and `test:hello/0`, a NIF which
+    /// returns `not_loaded` until it is registered
+    fn build_code() -> ByteCode<Atom, GlobalAtomTable> {
+        let mut builder = Builder::new(ByteCode::new());
+        let add = firefly_bytecode::ModuleFunctionArity {
+            module: builder.insert_atom("test"),
+            function: builder.insert_atom("add"),
+            arity: 2,
+        };
+        let mut function = builder.build_function(add, None).unwrap();
+        let entry = function.create_block(2);
+        function.switch_to_block(entry);
+        let args = function.block_args(entry).to_vec();
+        let sum = function.build_add(args[0], args[1], None);
+        function.build_ret(sum, None);
+        function.finish();
+
+        let hello = firefly_bytecode::ModuleFunctionArity {
+            module: builder.insert_atom("test"),
+            function: builder.insert_atom("hello"),
+            arity: 0,
+        };
+        let mut function = builder.build_function(hello, None).unwrap();
+        function.mark_as_nif();
+        let entry = function.create_block(0);
+        function.switch_to_block(entry);
+        let result = function.build_atom("not_loaded", None);
+        function.build_ret(result, None);
+        function.finish();
+
+        builder.finish()
+    }
+
+    /// A runtime can only be started once per program, so this covers its whole lifecycle
+    #[test]
+    fn runtime_lifecycle_test() {
+        init().unwrap();
+        let runtime = Runtime::start(Arc::new(build_code()), 1).unwrap();
+
+        let args = vec![Value::Integer(1.into()), Value::Integer(2.into())];
+        assert_eq!(
+            runtime.call("test", "add", args),
+            Ok(Value::Integer(3.into()))
+        );
+        assert_eq!(
+            runtime.call("test", "missing", vec![]),
+            Err(Error::Undefined("test:missing/0".to_string()))
+        );
+
+        // Only functions declared as NIFs can be replaced
+        assert_eq!(
+            runtime.register_nif("test", "add", 2, |_| Ok(Value::atom("ok"))),
+            Err(Error::Undefined("test:add/2".to_string()))
+        );
+        assert_eq!(
+            runtime.register_nif("test", "missing", 0, |_| Ok(Value::atom("ok"))),
+            Err(Error::Undefined("test:missing/0".to_string()))
+        );
+        assert_eq!(
+            runtime.call("test", "hello", vec![]),
+            Ok(Value::atom("not_loaded"))
+        );
+        runtime
+            .register_nif("test", "hello", 0, |_| Ok(Value::atom("world")))
+            .unwrap();
+        assert_eq!(
+            runtime.call("test", "hello", vec![]),
+            Ok(Value::atom("world"))
+        );
+
+        assert_eq!(
+            Runtime::start(Arc::new(build_code()), 1).err(),
+            Some(Error::AlreadyStarted)
+        );
+        runtime.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+use std::alloc::{AllocError, Allocator};
+use std::fmt;
+
+use firefly_binary::Bitstring;
+use firefly_number::Int;
+use firefly_rt::gc::Gc;
+use firefly_rt::term::*;
+
+/// A term decoded from, or to be encoded to, a term on a process heap
+///
+/// This is how terms are exchanged with the embedding host, as unlike terms, values own all of
+/// their data and are not tied to the lifetime of any process.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Atom(String),
+    Integer(Int),
+    Float(f64),
+    Binary(Vec<u8>),
+    Tuple(Vec<Value>),
+    /// A list, with its tail if improper
+    List(Vec<Value>, Option<Box<Value>>),
+    Map(Vec<(Value, Value)>),
+    Pid(Pid),
+    /// A term which has no representation outside of a process, e.g. a fun, port, reference or
+    /// bitstring, in its printed form
+    Opaque(String),
+}
+impl Value {
+    /// Returns the atom `name`
+    pub fn atom<S: Into<String>>(name: S) -> Self {
+        Self::Atom(name.into())
+    }
+
+    /// Returns a proper list of `elements`
+    pub fn list(elements: Vec<Value>) -> Self {
+        Self::List(elements, None)
+    }
+
+    /// Returns the name of this value if it is an atom
+    pub fn as_atom(&self) -> Option<&str> {
+        match self {
+            Self::Atom(name) => Some(name.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns this value as an `i64` if it is an integer in range
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(Int::Small(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Decodes `term`, copying any data it references
+    pub fn from_term(term: OpaqueTerm) -> Self {
+        match term.into() {
+            Term::Nil => Self::List(vec![], None),
+            Term::Bool(b) => Self::Atom(b.to_string()),
+            Term::Atom(a) => Self::Atom(a.as_str().to_owned()),
+            Term::Int(i) => Self::Integer(Int::Small(i)),
+            Term::BigInt(i) => Self::Integer(Int::Big(i.inner().clone())),
+            Term::Float(f) => Self::Float(f.inner()),
+            Term::Cons(cons) => {
+                let mut elements = vec![];
+                let mut tail = None;
+                for result in cons.iter_raw() {
+                    match result {
+                        Ok(element) => elements.push(Self::from_term(element)),
+                        Err(improper) => tail = Some(Box::new(Self::from_term(improper))),
+                    }
+                }
+                Self::List(elements, tail)
+            }
+            Term::Tuple(tuple) => Self::Tuple(
+                tuple
+                    .as_slice()
+                    .iter()
+                    .copied()
+                    .map(Self::from_term)
+                    .collect(),
+            ),
+            Term::Map(map) => Self::Map(
+                map.keys()
+                    .iter()
+                    .copied()
+                    .zip(map.values().iter().copied())
+                    .map(|(k, v)| (Self::from_term(k), Self::from_term(v)))
+                    .collect(),
+            ),
+            Term::Pid(pid) => Self::Pid(Pid::clone(&pid)),
+            other => match other.as_binary() {
+                Some(bytes) if bytes.is_aligned() => {
+                    Self::Binary(unsafe { bytes.as_bytes_unchecked() }.to_vec())
+                }
+                Some(bytes) => Self::Binary(bytes.bytes().collect()),
+                None => Self::Opaque(other.to_string()),
+            },
+        }
+    }
+
+    /// Extends `layout` with the space required to allocate this value in a heap fragment
+    pub fn layout(&self, layout: &mut LayoutBuilder) {
+        match self {
+            Self::Atom(_) => {
+                layout.build_atom();
+            }
+            Self::Integer(Int::Small(i)) => {
+                layout.build_for_i64(*i);
+            }
+            Self::Integer(Int::Big(_)) => {
+                layout.build_bigint();
+            }
+            Self::Float(_) => {
+                layout.build_float();
+            }
+            Self::Binary(bytes) if bytes.len() > BinaryData::MAX_HEAP_BYTES => (),
+            Self::Binary(bytes) => {
+                layout.build_heap_binary(bytes.len());
+            }
+            Self::Tuple(elements) => {
+                layout.build_tuple(elements.len());
+                elements.iter().for_each(|element| element.layout(layout));
+            }
+            Self::List(elements, tail) => {
+                layout.build_list(elements.len());
+                elements.iter().for_each(|element| element.layout(layout));
+                if let Some(tail) = tail {
+                    tail.layout(layout);
+                }
+            }
+            Self::Map(pairs) => {
+                layout.build_map(pairs.len());
+                for (k, v) in pairs.iter() {
+                    k.layout(layout);
+                    v.layout(layout);
+                }
+            }
+            Self::Pid(_) => {
+                layout.build_pid();
+            }
+            Self::Opaque(s) => {
+                layout.build_heap_binary(s.len());
+            }
+        }
+    }
+
+    /// Encodes this value as a term allocated in `heap`
+    ///
+    /// Opaque values cannot be converted back to the term they were decoded from, so they are
+    /// encoded as a binary containing their printed form.
+    pub fn to_term<A: ?Sized + Allocator>(&self, heap: &A) -> Result<OpaqueTerm, AllocError> {
+        match self {
+            Self::Atom(name) => Ok(Atom::str_to_term(name)),
+            Self::Integer(Int::Small(i)) => match (*i).try_into() {
+                Ok(term) => Ok(term),
+                Err(_) => {
+                    let i = BigInt::from(firefly_number::BigInt::from(*i));
+                    Ok(Gc::new_in(i, heap)?.into())
+                }
+            },
+            Self::Integer(Int::Big(i)) => Ok(Gc::new_in(BigInt::from(i.clone()), heap)?.into()),
+            Self::Float(f) => Ok((*f).into()),
+            Self::Binary(bytes) => binary_to_term(bytes, heap),
+            Self::Opaque(s) => binary_to_term(s.as_bytes(), heap),
+            Self::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.to_term(heap))
+                    .try_collect::<Vec<_>>()?;
+                Ok(Tuple::from_slice(&elements, heap)?.into())
+            }
+            Self::List(elements, tail) => {
+                let mut list = match tail {
+                    None => OpaqueTerm::NIL,
+                    Some(tail) => tail.to_term(heap)?,
+                };
+                for element in elements.iter().rev() {
+                    let head = element.to_term(heap)?;
+                    list = Cons::new_in(Cons::cons(head.into(), list.into()), heap)?.into();
+                }
+                Ok(list)
+            }
+            Self::Map(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(k, v)| Ok::<_, AllocError>((k.to_term(heap)?, v.to_term(heap)?)))
+                    .try_collect::<Vec<_>>()?;
+                let map: Term = Map::from_iter(pairs.into_iter(), heap)
+                    .map_err(|_| AllocError)?
+                    .into();
+                Ok(map.into())
+            }
+            Self::Pid(pid) => Ok(Gc::new_in(pid.clone(), heap)?.into()),
+        }
+    }
+}
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Atom(name) => write!(f, "'{}'", name),
+            Self::Integer(i) => write!(f, "{}", i),
+            Self::Float(x) => write!(f, "{:?}", x),
+            Self::Binary(bytes) => {
+                f.write_str("<<")?;
+                for (i, byte) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", byte)?;
+                }
+                f.write_str(">>")
+            }
+            Self::Tuple(elements) => {
+                f.write_str("{")?;
+                write_sequence(f, elements)?;
+                f.write_str("}")
+            }
+            Self::List(elements, tail) => {
+                f.write_str("[")?;
+                write_sequence(f, elements)?;
+                if let Some(tail) = tail {
+                    write!(f, "|{}", tail)?;
+                }
+                f.write_str("]")
+            }
+            Self::Map(pairs) => {
+                f.write_str("#{")?;
+                for (i, (k, v)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{} => {}", k, v)?;
+                }
+                f.write_str("}")
+            }
+            Self::Pid(pid) => write!(f, "{}", pid),
+            Self::Opaque(s) => f.write_str(s),
+        }
+    }
+}
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Self::Integer(Int::from(i))
+    }
+}
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Self::Float(x)
+    }
+}
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Atom(b.to_string())
+    }
+}
+impl From<&[u8]> for Value {
+    fn from(bytes: &[u8]) -> Self {
+        Self::Binary(bytes.to_vec())
+    }
+}
+
+fn write_sequence(f: &mut fmt::Formatter, elements: &[Value]) -> fmt::Result {
+    for (i, element) in elements.iter().enumerate() {
+        if i > 0 {
+            f.write_str(",")?;
+        }
+        write!(f, "{}", element)?;
+    }
+    Ok(())
+}
+
+fn binary_to_term<A: ?Sized + Allocator>(bytes: &[u8], heap: &A) -> Result<OpaqueTerm, AllocError> {
+    if bytes.len() > BinaryData::MAX_HEAP_BYTES {
+        Ok(BinaryData::from_bytes(bytes).into())
+    } else {
+        Ok(BinaryData::from_small_bytes(bytes, heap)?.into())
+    }
+}
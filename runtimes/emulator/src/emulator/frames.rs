@@ -0,0 +1,48 @@
+use std::iter;
+
+use firefly_bytecode::ByteCode;
+use firefly_rt::process::{ProcessLock, CP_REG};
+use firefly_rt::term::{atom::GlobalAtomTable, Atom};
+
+/// A call frame on the stack of a process executing bytecode
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct CallFrame {
+    /// The instruction being executed in this frame, which for callers is the call site
+    pub ip: usize,
+    pub fp: usize,
+    pub sp: usize,
+}
+
+/// Returns the call frames of `process`, which is about to execute the instruction at `ip`, most
+/// recent call first
+///
+/// The walk ends at the frame which returns to one of the stub instructions that every bytecode
+/// module begins with, as that is where the process exits.
+pub(crate) fn call_frames<'a>(
+    code: &'a ByteCode<Atom, GlobalAtomTable>,
+    process: &'a ProcessLock,
+    ip: usize,
+) -> impl Iterator<Item = CallFrame> + 'a {
+    let stack = &process.stack;
+    let current = CallFrame {
+        ip,
+        fp: stack.frame_pointer(),
+        sp: stack.stack_pointer(),
+    };
+    // Each frame holds the address its caller resumes at, which is just past the call site
+    let cp = stack.load(CP_REG);
+    let mut ret = if cp.is_code() { cp.as_code() } else { 0 };
+    let callers = stack.trace(None).map_while(move |frame| {
+        if code.is_stub(ret) {
+            return None;
+        }
+        let caller = CallFrame {
+            ip: ret - 1,
+            fp: frame.fp,
+            sp: frame.sp,
+        };
+        ret = frame.ret;
+        Some(caller)
+    });
+    iter::once(current).chain(callers)
+}
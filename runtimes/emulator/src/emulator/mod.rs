@@ -1,11 +1,14 @@
+mod frames;
 mod scheduler;
 
 use std::cell::{Cell, RefCell, UnsafeCell};
 use std::ptr;
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
 use crossbeam::deque::Injector;
+use crossbeam::queue::SegQueue;
 
 use firefly_alloc::fragment::HeapFragment;
 use firefly_bytecode::ByteCode;
@@ -13,12 +16,11 @@ use firefly_rt::function::ModuleFunctionArity;
 use firefly_rt::process::Process;
 use firefly_rt::scheduler::SchedulerId;
 use firefly_rt::services::{registry, timers};
-use firefly_rt::term::{atom, Atom, Cons, LayoutBuilder, ReferenceId, Term};
-
-use tokio::runtime::Handle;
+use firefly_rt::term::{atom, Atom, Cons, LayoutBuilder, OpaqueTerm, Pid, ReferenceId, Term};
 
 use crate::queue::{LocalProcessQueue, RunQueue};
 
+pub(crate) use self::frames::{call_frames, CallFrame};
 pub(crate) use self::scheduler::Action;
 
 /// Represents a failure in the emulator during execution
@@ -43,9 +45,8 @@ pub fn current_scheduler<'a>() -> &'a Emulator {
 ///
 /// One of these is run for each scheduler thread on which processes are scheduled.
 ///
-/// For other forms of tasks, a handle to the async runtime is provided which can be used
-/// to spawn both async and synchronous tasks. Virtually all system tasks and I/O are performed
-/// using the async runtime rather than our process schedulers.
+/// Blocking work on behalf of a process, e.g. file I/O, is not performed on these threads, but
+/// offloaded to the dirty IO schedulers, see `crate::nifs::blocking`.
 pub struct Emulator {
     /// The current scheduler id. Set once at creation and never changes.
     id: SchedulerId,
@@ -58,11 +59,6 @@ pub struct Emulator {
     /// queue in which newly spawned processes are placed.
     runq: RunQueue<LocalProcessQueue>,
     injector: Arc<Injector<Arc<Process>>>,
-    /// A handle to the async runtime
-    ///
-    /// This should be used for scheduling tasks which aren't backed by a process
-    #[allow(unused)]
-    handle: Handle,
     /// This is internal state to the scheduler, containing the value of the next unique reference
     /// id for this scheduler.
     reference_id: UnsafeCell<u64>,
@@ -74,8 +70,14 @@ pub struct Emulator {
     /// scheduler starts and never changes after that (schedulers are not permitted to migrate
     /// threads).
     thread_id: std::thread::ThreadId,
+    /// A handle to the thread this scheduler runs on, used to wake it when parked
+    thread: std::thread::Thread,
     /// The total reduction count executed by this scheduler
     reductions: AtomicU64,
+    /// The time at which this scheduler was created
+    started: Instant,
+    /// The total time, in nanoseconds, this scheduler has spent executing processes
+    active_time: AtomicU64,
     /// This is internal state to the scheduler, providing the timer service for processes
     /// scheduled on this scheduler
     ///
@@ -86,6 +88,13 @@ pub struct Emulator {
     /// received, it will look up the scheduler id in the timer reference and relay the
     /// cancellation to the scheduler on which the timer was registered.
     timers: RefCell<timers::PerSchedulerTimerService>,
+    /// Timer requests relayed to this scheduler from other schedulers
+    ///
+    /// These are handled by this scheduler during its core loop, see `handle_timer_requests`
+    timer_requests: SegQueue<timers::TimerRequest>,
+    /// Set once the core loop of this scheduler has exited, after which relayed timer requests
+    /// are rejected rather than queued, as they would never be handled
+    stopped: AtomicBool,
 }
 unsafe impl Send for Emulator {}
 unsafe impl Sync for Emulator {}
@@ -95,7 +104,6 @@ impl Emulator {
         id: SchedulerId,
         code: Arc<ByteCode<Atom, atom::GlobalAtomTable>>,
         injector: Arc<Injector<Arc<Process>>>,
-        handle: Handle,
     ) -> Arc<Self> {
         let runq = RunQueue::new(injector.clone());
         Arc::new(Self {
@@ -103,15 +111,37 @@ impl Emulator {
             code,
             runq,
             injector,
-            handle,
             reference_id: UnsafeCell::new(ReferenceId::init()),
             unique_id: UnsafeCell::new(0),
             thread_id: std::thread::current().id(),
+            thread: std::thread::current(),
             reductions: AtomicU64::new(0),
+            started: Instant::now(),
+            active_time: AtomicU64::new(0),
             timers: RefCell::new(timers::PerSchedulerTimerService::new()),
+            timer_requests: SegQueue::new(),
+            stopped: AtomicBool::new(false),
         })
     }
 
+    /// Returns the bytecode this emulator is executing
+    pub fn code(&self) -> &Arc<ByteCode<Atom, atom::GlobalAtomTable>> {
+        &self.code
+    }
+
+    /// Returns the number of processes in the global run queue, waiting to be picked up by
+    /// a scheduler
+    pub fn global_run_queue_len(&self) -> usize {
+        self.injector.len()
+    }
+
+    /// Wakes this scheduler if it is parked waiting for work
+    ///
+    /// This is used by the embedding host, which schedules processes from other threads.
+    pub fn wake(&self) {
+        self.thread.unpark();
+    }
+
     /// This function starts the scheduler loop of this emulator, returning a join handle
     /// which can be used to await the exit status of the emulator from the calling thread.
     pub fn start(self: Arc<Self>, spawn_init: bool) -> Result<(), EmulatorError> {
@@ -130,7 +160,14 @@ impl Emulator {
         }
 
         // Start the scheduler control loop
-        self.run()
+        let result = self.run();
+
+        // Processes on other schedulers may be awaiting the reply to a timer request they relayed
+        // to this one, so make sure they get one
+        self.stopped.store(true, Ordering::SeqCst);
+        self.reject_timer_requests();
+
+        result
     }
 
     /// # SAFETY
@@ -138,16 +175,11 @@ impl Emulator {
     /// This function must only be called once, and only on one scheduler in the system, otherwise
     /// I suspect all hell will break loose.
     unsafe fn spawn_init(&self) -> Result<(), EmulatorError> {
-        use crate::queue::TaskQueue;
-
-        // Fetch offset of `init:start/0` in the loaded bytecode
-        let init = "init:boot/1".parse::<ModuleFunctionArity>().unwrap();
-        let init_mfa = init.into();
-        let init_offset = self
-            .code
-            .function_by_mfa(&init_mfa)
-            .and_then(|f| f.offset())
-            .ok_or(EmulatorError::InvalidInit)?;
+        // Spawn the I/O server for standard input/output first, so that it can be made the group
+        // leader of init, and by extension, every process spawned from it. If the server is not
+        // present in the loaded bytecode, init is started without a group leader.
+        let user = "user:server/0".parse::<ModuleFunctionArity>().unwrap();
+        let user = self.spawn_system_process(user, &[], None).ok();
 
         // Spawn with the initial arguments vector as the sole argument
         let initial_args = crate::sys::env::argv();
@@ -159,27 +191,52 @@ impl Emulator {
             .map(Term::Cons)
             .unwrap_or(Term::Nil);
 
+        let init = "init:boot/1".parse::<ModuleFunctionArity>().unwrap();
+        self.spawn_system_process(init, &[initial_args.into()], user.map(|p| p.pid()))?;
+
+        Ok(())
+    }
+
+    /// Spawns a parentless process which starts executing `mfa` with `args`, and schedules it
+    /// for execution immediately
+    ///
+    /// Returns `EmulatorError::InvalidInit` if `mfa` is not defined in the loaded bytecode.
+    unsafe fn spawn_system_process(
+        &self,
+        mfa: ModuleFunctionArity,
+        args: &[OpaqueTerm],
+        group_leader: Option<Pid>,
+    ) -> Result<Arc<Process>, EmulatorError> {
+        use crate::queue::TaskQueue;
+
+        // Fetch offset of `mfa` in the loaded bytecode
+        let offset = self
+            .code
+            .function_by_mfa(&mfa.into())
+            .and_then(|f| f.offset())
+            .ok_or(EmulatorError::InvalidInit)?;
+
         // Initialize fresh process state
-        let mut init_p = Process::new(
+        let mut process = Process::new(
             self.id,
             None,
-            None,
-            init,
-            &[initial_args.into()],
+            group_leader,
+            mfa,
+            args,
             self.injector.clone(),
             Default::default(),
         );
         {
-            let proc = Arc::get_mut(&mut init_p).unwrap();
-            proc.set_instruction_pointer(init_offset);
+            let proc = Arc::get_mut(&mut process).unwrap();
+            proc.set_instruction_pointer(offset);
         }
 
         // Register the process in the global registry
-        registry::register_process(init_p.clone());
+        registry::register_process(process.clone());
 
         // Schedule the process for execution immediately
-        self.runq.push(init_p);
+        self.runq.push(process.clone());
 
-        Ok(())
+        Ok(process)
     }
 }
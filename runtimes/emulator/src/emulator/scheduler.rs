@@ -29,7 +29,7 @@ use firefly_rt::process::{
 use firefly_rt::scheduler::{Scheduler, SchedulerId};
 use firefly_rt::services::error_logger;
 use firefly_rt::services::registry::{self, Registrant, WeakAddress};
-use firefly_rt::services::timers::{Timer, TimerError, TimerService};
+use firefly_rt::services::timers::{Timer, TimerError, TimerEvent, TimerRequest, TimerService};
 use firefly_rt::term::{
     atoms, BigInt, BinaryData, BitSlice, Closure, ClosureFlags, Cons, Map, MapError, MatchContext,
     OpaqueTerm, Pid, Reference, Term, Tuple, Value,
@@ -41,6 +41,11 @@ use intrusive_collections::UnsafeRef;
 use log::{log_enabled, trace};
 use smallvec::{smallvec, SmallVec};
 
+use crate::debugger;
+use crate::dirty;
+use crate::embed;
+use crate::erl_nif;
+use crate::profiler;
 use crate::queue::TaskQueue;
 
 use super::*;
@@ -81,11 +86,32 @@ impl Scheduler for Emulator {
     }
 
     fn cancel_timer(&self, timer_ref: ReferenceId) -> Result<(), ()> {
+        // If the process which started this timer has since migrated, the timer is
+        // owned by another scheduler, so relay the cancellation to that scheduler
+        if timer_ref.scheduler_id() != self.id {
+            self.request_timer(TimerRequest::Cancel {
+                id: timer_ref,
+                reply: Box::new(|_| ()),
+            });
+            return Ok(());
+        }
         // We only allow timer management from processes running on the same scheduler
         assert_eq!(self.thread_id, std::thread::current().id());
         self.timers.borrow_mut().cancel_timer(timer_ref)
     }
 
+    fn relay_timer_request(&self, request: TimerRequest) {
+        trace!(target: "timers", "relaying timer request {:?} to scheduler {:?}", &request, self.id);
+        self.timer_requests.push(request);
+        // If this scheduler has stopped in the meantime, nothing else will handle the request
+        if self.stopped.load(Ordering::SeqCst) {
+            self.reject_timer_requests();
+            return;
+        }
+        // Make sure the scheduler wakes up to handle the request if it is parked
+        self.thread.unpark();
+    }
+
     /// Spawn a new process with the given module/function/arguments
     fn spawn(
         &self,
@@ -171,22 +197,55 @@ impl Scheduler for Emulator {
     fn reschedule(&self, process: Arc<Process>) {
         self.runq.push(process);
     }
+
+    fn reductions(&self) -> u64 {
+        self.reductions.load(Ordering::Relaxed)
+    }
+
+    fn run_queue_len(&self) -> usize {
+        self.runq.len()
+    }
+
+    fn wall_time(&self) -> (Duration, Duration) {
+        let active = Duration::from_nanos(self.active_time.load(Ordering::Relaxed));
+        (active, self.started.elapsed())
+    }
 }
 
 const MAX_REDUCTIONS: usize = Process::MAX_REDUCTIONS;
 const ERTS_SIGNAL_REDUCTIONS_COUNT_FACTOR: usize = 4;
+/// The longest an idle scheduler sleeps when embedded, as work may be scheduled by other threads
+/// without waking it, e.g. messages sent to processes suspended in a receive
+const EMBEDDED_IDLE_TIMEOUT: Duration = Duration::from_millis(1);
 
 impl Emulator {
     /// Run the scheduler core loop indefinitely or until an error occurs
     pub(super) fn run(&self) -> Result<(), EmulatorError> {
         loop {
-            if !self.run_once()? {
+            if embed::is_stopping() {
+                trace!(target: "scheduler", "the embedding host is stopping the runtime, shutting down");
+                return Err(EmulatorError::Halt(0));
+            }
+            let start = Instant::now();
+            if self.run_once()? {
+                let elapsed = start.elapsed().as_nanos() as u64;
+                self.active_time.fetch_add(elapsed, Ordering::Relaxed);
+            } else {
                 // There are no processes available, sleep for a few seconds
                 // and try scheduling again. This avoids busy looping with no
                 // work.
-                if let Some(ms) = self.timers.borrow().skippable() {
+                let timeout = self
+                    .timers
+                    .borrow()
+                    .skippable()
+                    .map(|ms| Duration::from_millis(ms as u64));
+                if embed::is_embedded() {
+                    let timeout =
+                        timeout.map_or(EMBEDDED_IDLE_TIMEOUT, |t| t.min(EMBEDDED_IDLE_TIMEOUT));
+                    std::thread::park_timeout(timeout);
+                } else if let Some(timeout) = timeout {
                     trace!(target: "scheduler", "scheduler has no processes available to schedule, parking until next timer expires");
-                    std::thread::park_timeout(Duration::from_millis(ms as u64));
+                    std::thread::park_timeout(timeout);
                 }
             }
         }
@@ -226,7 +285,9 @@ impl Emulator {
                             // Already running?
                             let is_running =
                                 status.intersects(StatusFlags::RUNNING | StatusFlags::RUNNING_SYS);
-                            let run = is_active && !is_suspended && !is_running;
+                            // Migrated to a dirty scheduler? (it is requeued when done there)
+                            let is_dirty = status.is_dirty();
+                            let run = is_active && !is_suspended && !is_running && !is_dirty;
 
                             // Mark this process as running with the correct flag
                             if run {
@@ -241,8 +302,12 @@ impl Emulator {
                                 Ok(current) => {
                                     status = current;
                                     if !run {
-                                        trace!(target: "scheduler", "process will not be run, placing back in run queue");
-                                        self.runq.push(process);
+                                        if is_dirty {
+                                            trace!(target: "scheduler", "process will not be run, it is on a dirty scheduler");
+                                        } else {
+                                            trace!(target: "scheduler", "process will not be run, placing back in run queue");
+                                            self.runq.push(process);
+                                        }
                                         break 'next;
                                     }
                                     break;
@@ -478,7 +543,14 @@ impl Emulator {
                         let is_active_sys = status.contains(StatusFlags::ACTIVE_SYS);
                         assert!(is_not_suspended || is_active_sys);
                         trace!(target: "scheduler", "process is being requeued with status {:?}", status);
-                        self.runq.push(process);
+                        if status.is_dirty() {
+                            trace!(target: "scheduler", "process is migrating to a dirty scheduler");
+                            dirty::migrate(process, status);
+                        } else if debugger::intercept(&process) {
+                            trace!(target: "scheduler", "process was stopped by the debugger");
+                        } else {
+                            self.runq.push(process);
+                        }
                     } else {
                         trace!(target: "scheduler", "process is not being requeued, status is {:?}", status);
 
@@ -488,7 +560,8 @@ impl Emulator {
                         }
                     }
 
-                    // Tick the timer service
+                    // Handle requests relayed from other schedulers, then tick the timer service
+                    self.handle_timer_requests();
                     {
                         trace!(target: "scheduler", "ticking timer wheel");
                         self.timers.borrow_mut().tick();
@@ -502,9 +575,14 @@ impl Emulator {
                     return Ok(true);
                 }
                 None => {
-                    // Tick the timer service
+                    // Handle requests relayed from other schedulers, then tick the timer service
+                    self.handle_timer_requests();
                     let mut timers = self.timers.borrow_mut();
                     if timers.is_empty() {
+                        // When embedded, the host may spawn more processes at any time
+                        if embed::is_embedded() {
+                            return Ok(false);
+                        }
                         trace!(target: "scheduler", "there are no processes to schedule, and no timers, shutting down");
                         return Err(EmulatorError::Halt(0));
                     }
@@ -539,6 +617,15 @@ impl Emulator {
                 // Handle the case where a process is spawned with an MFA
                 if likely(current_ip > 0) {
                     trace!(target: "process", "ip: {}, reductions {}", current_ip, reductions);
+                    // If stopped by the debugger, the process is parked when scheduled out, and
+                    // executes this instruction when resumed
+                    if unlikely(debugger::is_armed()) && debugger::should_stop(process, current_ip)
+                    {
+                        break;
+                    }
+                    if unlikely(profiler::is_active()) {
+                        profiler::observe(&self.code, process, current_ip);
+                    }
                     process.ip += 1;
                     &self.code.code[current_ip]
                 } else {
@@ -687,6 +774,7 @@ impl Emulator {
             }
         }
 
+        profiler::schedule_out(reductions);
         self.reductions
             .fetch_add(reductions as u64, Ordering::Relaxed);
 
@@ -811,34 +899,22 @@ impl Emulator {
                                     // Create a DOWN message and replace the signal with it
                                     let mut layout = LayoutBuilder::new();
                                     layout += reason.layout();
-                                    let mut tag;
-                                    let origin;
-                                    if !flags.contains(MonitorFlags::TAG) {
-                                        // Registered name 2-tuple
-                                        layout.build_tuple(2);
-                                        origin = WeakAddress::System;
-                                        tag = Term::Atom(atoms::DOWN);
-                                    } else {
-                                        match monitor.origin().unwrap_or(WeakAddress::System) {
-                                            addr @ WeakAddress::Process(_) => {
-                                                origin = addr;
-                                                layout.build_pid();
-                                            }
-                                            addr @ WeakAddress::Port(_) => {
-                                                origin = addr;
-                                                layout.build_port();
-                                            }
-                                            addr @ WeakAddress::System => {
-                                                origin = addr;
-                                            }
-                                            _ => panic!("expected pid or port"),
-                                        }
-                                        if let Some(t) = monitor.tag() {
-                                            tag = t.into();
+                                    let tag = match monitor.tag() {
+                                        None => Term::Atom(atoms::DOWN),
+                                        Some(t) => {
+                                            let tag: Term = t.into();
                                             layout += tag.layout();
-                                        } else {
-                                            tag = Term::Atom(atoms::DOWN);
+                                            tag
                                         }
+                                    };
+                                    // The monitored item is reported the way it was given to
+                                    // monitor/2, i.e. `{Name, Node}` when monitored by name
+                                    let name = monitor.name();
+                                    let target = monitor.target().unwrap_or(WeakAddress::System);
+                                    if name.is_some() {
+                                        layout.build_tuple(2);
+                                    } else if let WeakAddress::Process(_) = target {
+                                        layout.build_pid();
                                     }
                                     layout.build_reference();
                                     layout.build_tuple(5);
@@ -846,52 +922,30 @@ impl Emulator {
                                     let fragment = unsafe { fragment_ptr.as_ref() };
                                     let reason =
                                         unsafe { reason.unsafe_clone_to_heap(fragment).into() };
-
-                                    let from;
-                                    let ty;
-                                    if !flags.contains(MonitorFlags::TAG) {
-                                        let name = monitor.name().unwrap();
-                                        let node = monitor.node_name();
-                                        from = Term::Tuple(
+                                    let from = match (name, &target) {
+                                        (Some(name), _) => Term::Tuple(
                                             Tuple::from_slice(
-                                                &[name.into(), node.into()],
+                                                &[name.into(), monitor.node_name().into()],
                                                 fragment,
                                             )
                                             .unwrap(),
-                                        );
-                                    } else {
-                                        match origin {
-                                            WeakAddress::Process(ref pid) => {
-                                                from = Term::Pid(
-                                                    Gc::new_in(pid.clone(), fragment).unwrap(),
-                                                );
-                                            }
-                                            WeakAddress::Port(port) => {
-                                                from = match registry::get_by_port_id(port) {
-                                                    None => Term::Atom(atoms::System),
-                                                    Some(p) => Term::Port(p),
-                                                };
-                                            }
-                                            WeakAddress::System => {
-                                                from = Term::Atom(atoms::System);
-                                            }
-                                            _ => unreachable!(),
-                                        }
-                                    }
-                                    match &monitor.monitor {
-                                        Monitor::LocalPort { .. } => {
-                                            ty = atoms::Port;
-                                        }
-                                        Monitor::LocalProcess { .. }
-                                        | Monitor::ToExternalProcess { .. } => {
-                                            ty = atoms::Process;
+                                        ),
+                                        (None, WeakAddress::Process(pid)) => {
+                                            Term::Pid(Gc::new_in(pid.clone(), fragment).unwrap())
                                         }
-                                        Monitor::FromExternalProcess { .. } => {
-                                            ty = atoms::Process;
+                                        (None, WeakAddress::Port(port)) => {
+                                            match registry::get_by_port_id(*port) {
+                                                None => Term::Atom(atoms::System),
+                                                Some(p) => Term::Port(p),
+                                            }
                                         }
-                                        _ => panic!("unexpected monitor type"),
-                                    }
-                                    tag = unsafe { tag.unsafe_clone_to_heap(fragment) };
+                                        (None, _) => Term::Atom(atoms::System),
+                                    };
+                                    let ty = match &monitor.monitor {
+                                        Monitor::LocalPort { .. } => atoms::Port,
+                                        _ => atoms::Process,
+                                    };
+                                    let tag = unsafe { tag.unsafe_clone_to_heap(fragment) };
                                     let mref = Gc::new_in(monitor_ref.clone(), fragment).unwrap();
                                     let term = Tuple::from_slice(
                                         &[tag.into(), mref.into(), ty.into(), from.into(), reason],
@@ -899,12 +953,14 @@ impl Emulator {
                                     )
                                     .unwrap();
                                     message = Message {
-                                        sender: origin,
+                                        sender: target,
                                         message: TermFragment {
                                             term: term.into(),
                                             fragment: Some(fragment_ptr),
                                         },
                                     };
+                                    // The monitor is no longer active once it has triggered
+                                    cursor.remove();
                                 }
                                 count += 4;
                                 unsafe {
@@ -1013,7 +1069,8 @@ impl Emulator {
                 }
                 Signal::ProcessInfo(sig) => {
                     let is_alive = !status.contains(StatusFlags::EXITING);
-                    self.handle_process_info_signal(process, sig, is_alive);
+                    let msgq_len = signals.len();
+                    self.handle_process_info_signal(process, sig, msgq_len, is_alive);
                 }
                 Signal::Rpc(sig) => {
                     count += self.handle_rpc(process, sig);
@@ -1141,9 +1198,9 @@ impl Emulator {
         monitor: Arc<MonitorEntry>,
     ) {
         match &monitor.monitor {
-            Monitor::Suspend { origin, .. } | Monitor::LocalProcess { origin, .. } => {
-                if let Some(origin) = registry::get_by_process_id(*origin) {
-                    origin
+            Monitor::Suspend { target, .. } | Monitor::LocalProcess { target, .. } => {
+                if let Some(target) = registry::get_by_process_id(*target) {
+                    target
                         .send_signal(SignalEntry::new(Signal::Demonitor(signals::Demonitor {
                             sender: process.addr(),
                             monitor,
@@ -1301,13 +1358,43 @@ impl Emulator {
         (count, exit)
     }
 
+    /// Replies to a request sent by `process_info/2` with `{Ref, {Item, Value}}`, or with
+    /// `{Ref, undefined}` if this process is exiting
     fn handle_process_info_signal(
         &self,
-        _process: &mut ProcessLock,
-        _sig: signals::ProcessInfo,
-        _is_alive: bool,
+        process: &mut ProcessLock,
+        sig: signals::ProcessInfo,
+        msgq_len: usize,
+        is_alive: bool,
     ) {
-        todo!()
+        // Requests arriving via distribution are not supported
+        let Some(sender) = sig.sender else { return };
+
+        let mut layout = LayoutBuilder::new();
+        layout.build_reference().build_tuple(2).build_tuple(2);
+        let fragment_ptr = layout.into_fragment().unwrap();
+        let fragment = unsafe { fragment_ptr.as_ref() };
+
+        let result: OpaqueTerm = if is_alive {
+            let value = crate::bifs::erlang::process_info_value(process, sig.item, msgq_len);
+            Tuple::from_slice(&[sig.item.into(), value], fragment)
+                .unwrap()
+                .into()
+        } else {
+            atoms::Undefined.into()
+        };
+        let reference = Gc::new_in(sig.reference, fragment).unwrap();
+        let reply = Tuple::from_slice(&[reference.into(), result], fragment).unwrap();
+
+        sender
+            .send_fragment(
+                WeakAddress::System,
+                TermFragment {
+                    term: reply.into(),
+                    fragment: Some(fragment_ptr),
+                },
+            )
+            .ok();
     }
 
     fn handle_rpc(&self, process: &mut ProcessLock, sig: signals::Rpc) -> usize {
@@ -1492,6 +1579,92 @@ impl Emulator {
             .map(|_| timer_ref)
     }
 
+    /// Starts a one-shot timer identified by `timer_ref`, which delivers `message` to `recipient`
+    /// from `sender` after `timeout`
+    ///
+    /// If the timeout has effectively already expired, the message is delivered immediately.
+    ///
+    /// Returns `Err` if the timer could not be started, e.g. if `timeout` is infinite.
+    pub(crate) fn send_after(
+        &self,
+        timer_ref: ReferenceId,
+        sender: WeakAddress,
+        recipient: WeakAddress,
+        message: TermFragment,
+        timeout: Timeout,
+    ) -> Result<(), TimerError> {
+        let timer = Timer::Once {
+            id: timer_ref,
+            timeout,
+            event: TimerEvent::Message {
+                sender,
+                recipient,
+                message,
+            },
+        };
+        match self.start_timer(timer) {
+            Err(TimerError::Expired(Timer::Once {
+                event:
+                    TimerEvent::Message {
+                        sender,
+                        recipient,
+                        message,
+                    },
+                ..
+            })) => {
+                if let Some(Registrant::Process(process)) = recipient.try_resolve() {
+                    process.send_fragment(sender, message).ok();
+                }
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Performs `request` against the timer service which owns the referenced timer
+    ///
+    /// If the timer was started on this scheduler, the request is handled immediately, otherwise
+    /// it is relayed to the owning scheduler, and the reply is invoked once that scheduler has
+    /// handled it. If the timer reference doesn't correspond to any online scheduler, the timer
+    /// cannot exist, so the reply is invoked immediately.
+    pub(crate) fn request_timer(&self, request: TimerRequest) {
+        let owner = request.id().scheduler_id();
+        if owner == self.id {
+            self.timers.borrow_mut().handle_request(request);
+            return;
+        }
+        match firefly_rt::scheduler::try_get(owner) {
+            Some(scheduler) => scheduler.relay_timer_request(request),
+            None => match request {
+                TimerRequest::Cancel { reply, .. } | TimerRequest::Read { reply, .. } => {
+                    reply(None)
+                }
+            },
+        }
+    }
+
+    /// Handles all of the timer requests relayed to this scheduler by other schedulers
+    pub(crate) fn handle_timer_requests(&self) {
+        while let Some(request) = self.timer_requests.pop() {
+            trace!(target: "timers", "handling relayed timer request {:?}", &request);
+            self.timers.borrow_mut().handle_request(request);
+        }
+    }
+
+    /// Replies to all of the timer requests relayed to this scheduler as if their timers did not
+    /// exist, which is used once this scheduler has stopped
+    ///
+    /// Unlike `handle_timer_requests`, this may be called from any thread.
+    pub(crate) fn reject_timer_requests(&self) {
+        while let Some(request) = self.timer_requests.pop() {
+            match request {
+                TimerRequest::Cancel { reply, .. } | TimerRequest::Read { reply, .. } => {
+                    reply(None)
+                }
+            }
+        }
+    }
+
     /// Generate a stack trace for the current process
     fn get_stacktrace(&self, process: &mut ProcessLock) -> Arc<Trace> {
         use firefly_rt::backtrace::Frame;
@@ -1510,8 +1683,14 @@ impl Emulator {
                 if ip == 0 {
                     None
                 } else {
-                    let function = self.code.function_by_ip(ip);
-                    let symbol = self.code.function_symbol(function.id());
+                    // The instruction pointer is bumped before an instruction executes, and return
+                    // addresses point just past the call, so look at the preceding instruction to
+                    // resolve the location of the faulting instruction or call site.
+                    let ip = ip - 1;
+                    let symbol = self.code.instruction_symbol(ip).unwrap_or_else(|| {
+                        let function = self.code.function_by_ip(ip);
+                        self.code.function_symbol(function.id())
+                    });
                     let frame: Box<dyn Frame> = Box::new(symbol);
                     Some(TraceFrame::from(frame))
                 }
@@ -1865,6 +2044,70 @@ impl Emulator {
 
         Action::Continue
     }
+
+    /// Calls `nif`, a function registered by the embedding host, with the arguments in the
+    /// current frame, returning from the frame with its result
+    ///
+    /// If the function returns an error, it is raised as if by `erlang:error/1`
+    fn call_host_nif(&self, process: &mut ProcessLock, nif: &embed::Nif, arity: u8) -> Action {
+        let args = process
+            .stack
+            .select_registers(ARG0_REG, arity as usize)
+            .iter()
+            .copied()
+            .map(embed::Value::from_term)
+            .collect::<Vec<_>>();
+        match nif(&args) {
+            Ok(value) => {
+                let result = crate::bifs::erlang::alloc_result(process, |heap| value.to_term(heap));
+                let ErlangResult::Ok(result) = result else { unreachable!() };
+                process.stack.store(RETURN_REG, result);
+                let op = ops::Ret { reg: RETURN_REG };
+                op.dispatch(self, process)
+            }
+            Err(reason) => {
+                let reason =
+                    crate::bifs::erlang::alloc_result(process, |heap| reason.to_term(heap));
+                let ErlangResult::Ok(reason) = reason else { unreachable!() };
+                process.exception_info.flags = ExceptionFlags::ERROR;
+                process.exception_info.value = reason;
+                process.exception_info.reason = match reason.into() {
+                    Term::Atom(a) => a.into(),
+                    // The host may raise an empty tuple
+                    Term::Tuple(tuple) => match tuple.get(0).map(Term::from) {
+                        Some(Term::Atom(a)) => a.into(),
+                        _ => atoms::Error.into(),
+                    },
+                    _ => atoms::Error.into(),
+                };
+                self.handle_error(process)
+            }
+        }
+    }
+
+    /// Calls `nif`, a function loaded from a native library by `erlang:load_nif/2`, with the
+    /// arguments in the current frame, returning from the frame with its result
+    fn call_erl_nif(&self, process: &mut ProcessLock, nif: &erl_nif::Nif, arity: u8) -> Action {
+        let args = process
+            .stack
+            .select_registers(ARG0_REG, arity as usize)
+            .to_vec();
+        match erl_nif::call(process, nif, &args) {
+            ErlangResult::Ok(result) => {
+                process.stack.store(RETURN_REG, result);
+                let op = ops::Ret { reg: RETURN_REG };
+                op.dispatch(self, process)
+            }
+            ErlangResult::Err | ErlangResult::Exit => self.handle_error(process),
+            ErlangResult::Await(generator) => {
+                // See the comment in CallNative
+                process.awaiting = Some(Box::into_inner(generator));
+                process.ip = AWAIT_IP;
+                Action::Yield
+            }
+            ErlangResult::Trap(_) => unreachable!(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -1906,6 +2149,7 @@ trait Inst {
 const GC: ops::GarbageCollect = ops::GarbageCollect { fullsweep: false };
 const NORMAL_EXIT_IP: usize = 1;
 const CONTINUE_EXIT_IP: usize = 2;
+const AWAIT_IP: usize = 3;
 const TRAP_IP: usize = 4;
 
 impl Inst for Opcode<Atom> {
@@ -2332,8 +2576,10 @@ impl Inst for ops::CallNative {
             }
             ErlangResult::Err | ErlangResult::Exit => emulator.handle_error(process),
             ErlangResult::Await(generator) => {
-                // We're blocked on some generator which needs to be run to completion
+                // We're blocked on some generator which needs to be run to completion,
+                // so when the process is next scheduled, we resume it via `Await`
                 process.awaiting = Some(Box::into_inner(generator));
+                process.ip = AWAIT_IP;
                 Action::Yield
             }
             ErlangResult::Trap(mfa) => {
@@ -2398,6 +2644,20 @@ impl Inst for ops::CallStatic {
             }
             Function::Bytecode { mfa, offset, .. } => {
                 let mfa = (*mfa).into();
+                // Functions registered by the embedding host take precedence
+                if let Some(nif) = embed::find_nif(&mfa) {
+                    process.stack.push_frame(self.dest);
+                    let cp = OpaqueTerm::code(process.ip);
+                    process.stack.store(CP_REG, cp);
+                    return emulator.call_host_nif(process, &nif, mfa.arity);
+                }
+                // Followed by those loaded with `erlang:load_nif/2`
+                if let Some(nif) = erl_nif::find(&mfa) {
+                    process.stack.push_frame(self.dest);
+                    let cp = OpaqueTerm::code(process.ip);
+                    process.stack.store(CP_REG, cp);
+                    return emulator.call_erl_nif(process, &nif, mfa.arity);
+                }
                 // Try to call the native implementation
                 match function::find_symbol(&mfa) {
                     Some(symbol) => {
@@ -2656,6 +2916,7 @@ impl Inst for ops::EnterNative {
             ErlangResult::Await(generator) => {
                 // See the comment in CallNative regarding awaits
                 process.awaiting = Some(Box::into_inner(generator));
+                process.ip = AWAIT_IP;
                 Action::Yield
             }
             ErlangResult::Trap(mfa) => {
@@ -2696,6 +2957,13 @@ impl Inst for ops::EnterStatic {
             }
             Function::Bytecode { mfa, offset, .. } => {
                 let mfa = (*mfa).into();
+                // See the comment in CallStatic regarding functions registered by the host
+                if let Some(nif) = embed::find_nif(&mfa) {
+                    return emulator.call_host_nif(process, &nif, mfa.arity);
+                }
+                if let Some(nif) = erl_nif::find(&mfa) {
+                    return emulator.call_erl_nif(process, &nif, mfa.arity);
+                }
                 match function::find_symbol(&mfa) {
                     Some(symbol) => {
                         let op = ops::EnterNative {
@@ -4783,6 +5051,7 @@ impl Inst for ops::Await {
                 // to poll a generator that is just going to yield cheap enough that it
                 // isn't important.
                 process.awaiting = Some(generator);
+                process.ip = AWAIT_IP;
                 Action::Yield
             }
             GeneratorState::Completed(Ok(result)) => {
@@ -4835,6 +5104,8 @@ impl Inst for ops::NormalExit {
         process.exception_info.reason = atoms::Normal.into();
         process.exception_info.value = atoms::Normal.into();
         process.exception_info.trace = None;
+        // Deliver the result of the initial call to the embedding host, if it is waiting for it
+        embed::returned(process.id(), process.stack.load(RETURN_REG));
         emulator.terminate_process(process, atoms::Normal.into())
     }
 }
@@ -4882,6 +5153,8 @@ impl Inst for ops::ContinueExit {
 
                     // This is the point at which the process is actually dead
                     registry::unregister_process(process.id()).unwrap();
+                    embed::exited(process.id(), process.exception_info.value);
+                    erl_nif::exited(process.id());
 
                     // All erlang resources have too be deallocated before this point,
                     // e.g. registered name, so monitoring and linked processes can be
@@ -5020,7 +5293,7 @@ impl Inst for ops::ContinueExit {
                                 }
                             }
                             Signal::ProcessInfo(sig) => {
-                                emulator.handle_process_info_signal(process, sig, false);
+                                emulator.handle_process_info_signal(process, sig, 0, false);
                             }
                             Signal::Flush(_sig) => {
                                 assert!(sigq.flags().contains(SignalQueueFlags::FLUSHING));
@@ -0,0 +1,1442 @@
+//! The `enif_*` functions for working with environments and terms, see `include/erl_nif.h`
+use std::alloc::{self, Layout};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::ffi::{c_char, c_double, c_int, c_long, c_uint, c_ulong, c_void, VaListImpl};
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::str;
+use std::sync::Arc;
+
+use firefly_binary::Bitstring;
+use firefly_number::{Int, ToPrimitive};
+use firefly_rt::cmp::ExactEq;
+use firefly_rt::gc::Gc;
+use firefly_rt::process::ProcessId;
+use firefly_rt::services::registry::{self, Registrant, WeakAddress};
+use firefly_rt::term::*;
+
+use super::Env;
+
+/// The values of `ErlNifThreadType`
+pub(super) const THR_UNDEFINED: c_int = 0;
+pub(super) const THR_NORMAL_SCHEDULER: c_int = 1;
+pub(super) const THR_DIRTY_CPU_SCHEDULER: c_int = 2;
+pub(super) const THR_DIRTY_IO_SCHEDULER: c_int = 3;
+
+/// The values of `ErlNifCharEncoding`
+const LATIN1: c_int = 1;
+const UTF8: c_int = 2;
+
+/// The values of `ErlNifMapIteratorEntry`
+const MAP_ITERATOR_FIRST: c_int = 1;
+const MAP_ITERATOR_LAST: c_int = 2;
+
+thread_local! {
+    /// The type of the current thread, as seen by the NIF running on it, see `enif_thread_type`
+    pub(super) static THREAD_TYPE: Cell<c_int> = Cell::new(THR_UNDEFINED);
+}
+
+/// The size of the header in which `enif_alloc` records the size of an allocation
+const ALLOC_HEADER: usize = 16;
+
+/// A process identifier, see `ErlNifPid` in `include/erl_nif.h`
+#[repr(C)]
+pub struct ErlNifPid {
+    pid: u64,
+}
+impl ErlNifPid {
+    pub(super) fn new(id: ProcessId) -> Self {
+        Self { pid: id.raw() }
+    }
+
+    pub(super) fn id(&self) -> ProcessId {
+        unsafe { ProcessId::from_raw(self.pid) }
+    }
+}
+
+/// A binary, see `ErlNifBinary` in `include/erl_nif.h`
+///
+/// When allocated by `enif_alloc_binary`, `ref_bin` is set, as the data is owned by the binary,
+/// rather than borrowed from a term.
+#[repr(C)]
+pub struct ErlNifBinary {
+    size: usize,
+    data: *mut u8,
+    ref_bin: *mut c_void,
+    _spare: [*mut c_void; 2],
+}
+
+/// An iterator over the entries of a map, see `ErlNifMapIterator` in `include/erl_nif.h`
+#[repr(C)]
+pub struct ErlNifMapIterator {
+    map: OpaqueTerm,
+    size: usize,
+    idx: usize,
+    keys: *const OpaqueTerm,
+    values: *const OpaqueTerm,
+    _spare: [*mut c_void; 2],
+}
+
+#[inline(always)]
+unsafe fn env<'a>(env: *mut Env) -> &'a Env {
+    &*env
+}
+
+/// Signals a `badarg` error in `env`, returning the term the NIF should return
+pub(super) fn badarg(env: &Env) -> OpaqueTerm {
+    env.exception.set(Some(atoms::Badarg.into()));
+    OpaqueTerm::NONE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_alloc(size: usize) -> *mut c_void {
+    let layout = Layout::from_size_align(size + ALLOC_HEADER, ALLOC_HEADER);
+    let Ok(layout) = layout else { return ptr::null_mut() };
+    let ptr = alloc::alloc(layout);
+    if ptr.is_null() {
+        return ptr::null_mut();
+    }
+    ptr.cast::<usize>().write(size);
+    ptr.add(ALLOC_HEADER).cast()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    if ptr.is_null() {
+        return enif_alloc(size);
+    }
+    let base = ptr.cast::<u8>().sub(ALLOC_HEADER);
+    let layout =
+        Layout::from_size_align_unchecked(base.cast::<usize>().read() + ALLOC_HEADER, ALLOC_HEADER);
+    let base = alloc::realloc(base, layout, size + ALLOC_HEADER);
+    if base.is_null() {
+        return ptr::null_mut();
+    }
+    base.cast::<usize>().write(size);
+    base.add(ALLOC_HEADER).cast()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_free(ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    let base = ptr.cast::<u8>().sub(ALLOC_HEADER);
+    let layout =
+        Layout::from_size_align_unchecked(base.cast::<usize>().read() + ALLOC_HEADER, ALLOC_HEADER);
+    alloc::dealloc(base, layout);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_alloc_env() -> *mut Env {
+    Box::into_raw(Box::new(Env::new(None, None)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_free_env(env: *mut Env) {
+    drop(Box::from_raw(env));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_clear_env(env: *mut Env) {
+    self::env(env).clear();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_copy(dst_env: *mut Env, src_term: OpaqueTerm) -> OpaqueTerm {
+    env(dst_env).copy(src_term).unwrap()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_priv_data(env: *mut Env) -> *mut c_void {
+    match self::env(env).library.as_ref() {
+        Some(library) => library.priv_data.load(std::sync::atomic::Ordering::Acquire),
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_badarg(env: *mut Env) -> OpaqueTerm {
+    badarg(self::env(env))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_raise_exception(env: *mut Env, reason: OpaqueTerm) -> OpaqueTerm {
+    self::env(env).exception.set(Some(reason));
+    OpaqueTerm::NONE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_has_pending_exception(
+    env: *mut Env,
+    reason: *mut OpaqueTerm,
+) -> c_int {
+    match self::env(env).exception.get() {
+        Some(exception) => {
+            if !reason.is_null() {
+                reason.write(exception);
+            }
+            1
+        }
+        None => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_exception(env: *mut Env, term: OpaqueTerm) -> c_int {
+    (term.is_none() && self::env(env).exception.get().is_some()) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_atom(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    term.is_atom() as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_binary(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    let term: Term = term.into();
+    term.as_binary().map(|bin| bin.is_binary()).unwrap_or(false) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_empty_list(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    term.is_nil() as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_fun(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    matches!(term.into(), Term::Closure(_)) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_list(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    term.is_list() as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_map(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    matches!(term.into(), Term::Map(_)) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_number(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    term.is_number() as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_pid(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    matches!(term.into(), Term::Pid(_)) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_port(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    matches!(term.into(), Term::Port(_)) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_ref(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    matches!(term.into(), Term::Reference(_)) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_tuple(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    term.is_tuple() as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_identical(lhs: OpaqueTerm, rhs: OpaqueTerm) -> c_int {
+    lhs.exact_eq(&rhs) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_compare(lhs: OpaqueTerm, rhs: OpaqueTerm) -> c_int {
+    match lhs.cmp(&rhs) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_term_type(_env: *mut Env, term: OpaqueTerm) -> c_int {
+    match term.into() {
+        Term::Atom(_) | Term::Bool(_) => 1,
+        Term::HeapBinary(_) | Term::RcBinary(_) | Term::RefBinary(_) | Term::ConstantBinary(_) => 2,
+        Term::Float(_) => 3,
+        Term::Closure(_) => 4,
+        Term::Int(_) | Term::BigInt(_) => 5,
+        Term::Nil | Term::Cons(_) => 6,
+        Term::Map(_) => 7,
+        Term::Pid(_) => 8,
+        Term::Port(_) => 9,
+        Term::Reference(_) => 10,
+        Term::Tuple(_) => 11,
+        Term::None | Term::Catch(_) | Term::Code(_) => 0,
+    }
+}
+
+fn make_i64(env: &Env, i: i64) -> OpaqueTerm {
+    make_int(env, Int::from(i))
+}
+
+fn make_u64(env: &Env, i: u64) -> OpaqueTerm {
+    make_int(env, Int::from(i))
+}
+
+fn make_int(env: &Env, i: Int) -> OpaqueTerm {
+    match i {
+        Int::Small(i) => i.try_into().unwrap(),
+        Int::Big(i) => Gc::new_in(BigInt::from(i), env).unwrap().into(),
+    }
+}
+
+fn get_i64(term: OpaqueTerm) -> Option<i64> {
+    match term.into() {
+        Term::Int(i) => Some(i),
+        Term::BigInt(i) => i.inner().to_i64(),
+        _ => None,
+    }
+}
+
+fn get_u64(term: OpaqueTerm) -> Option<u64> {
+    match term.into() {
+        Term::Int(i) => i.try_into().ok(),
+        Term::BigInt(i) => i.inner().to_u64(),
+        _ => None,
+    }
+}
+
+/// Writes the integer `term` to `out` if it fits in `T`, returning true if it does
+unsafe fn get_integer<T: TryFrom<i64> + TryFrom<u64>>(term: OpaqueTerm, out: *mut T) -> c_int {
+    let value = match get_i64(term) {
+        Some(i) => T::try_from(i).ok(),
+        None => get_u64(term).and_then(|i| T::try_from(i).ok()),
+    };
+    match value {
+        Some(value) => {
+            out.write(value);
+            1
+        }
+        None => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_int(env: *mut Env, i: c_int) -> OpaqueTerm {
+    make_i64(self::env(env), i as i64)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_uint(env: *mut Env, i: c_uint) -> OpaqueTerm {
+    make_u64(self::env(env), i as u64)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_long(env: *mut Env, i: c_long) -> OpaqueTerm {
+    make_i64(self::env(env), i as i64)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_ulong(env: *mut Env, i: c_ulong) -> OpaqueTerm {
+    make_u64(self::env(env), i as u64)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_int64(env: *mut Env, i: i64) -> OpaqueTerm {
+    make_i64(self::env(env), i)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_uint64(env: *mut Env, i: u64) -> OpaqueTerm {
+    make_u64(self::env(env), i)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_double(env: *mut Env, d: c_double) -> OpaqueTerm {
+    if d.is_finite() {
+        d.into()
+    } else {
+        badarg(self::env(env))
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_int(_env: *mut Env, term: OpaqueTerm, ip: *mut c_int) -> c_int {
+    get_integer(term, ip)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_uint(_env: *mut Env, term: OpaqueTerm, ip: *mut c_uint) -> c_int {
+    get_integer(term, ip)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_long(_env: *mut Env, term: OpaqueTerm, ip: *mut c_long) -> c_int {
+    get_integer(term, ip)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_ulong(
+    _env: *mut Env,
+    term: OpaqueTerm,
+    ip: *mut c_ulong,
+) -> c_int {
+    get_integer(term, ip)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_int64(_env: *mut Env, term: OpaqueTerm, ip: *mut i64) -> c_int {
+    get_integer(term, ip)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_uint64(_env: *mut Env, term: OpaqueTerm, ip: *mut u64) -> c_int {
+    get_integer(term, ip)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_double(
+    _env: *mut Env,
+    term: OpaqueTerm,
+    dp: *mut c_double,
+) -> c_int {
+    match term.into() {
+        Term::Float(f) => {
+            dp.write(f.inner());
+            1
+        }
+        _ => 0,
+    }
+}
+
+/// Decodes `len` bytes at `name` as a string in `encoding`
+///
+/// Atom names are stored as UTF-8, so Latin-1 strings are converted to UTF-8.
+unsafe fn decode<'a>(
+    name: *const c_char,
+    len: usize,
+    encoding: c_int,
+) -> Option<std::borrow::Cow<'a, str>> {
+    let bytes = slice::from_raw_parts(name.cast::<u8>(), len);
+    match encoding {
+        LATIN1 => Some(bytes.iter().map(|b| *b as char).collect::<String>().into()),
+        UTF8 => str::from_utf8(bytes).ok().map(|s| s.into()),
+        _ => None,
+    }
+}
+
+/// Encodes `s` in `encoding`, returning `None` if it can't be represented
+fn encode(s: &str, encoding: c_int) -> Option<Vec<u8>> {
+    match encoding {
+        LATIN1 => s.chars().map(|c| u8::try_from(c as u32).ok()).collect(),
+        UTF8 => Some(s.as_bytes().to_vec()),
+        _ => None,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_atom(env: *mut Env, name: *const c_char) -> OpaqueTerm {
+    let len = std::ffi::CStr::from_ptr(name).to_bytes().len();
+    enif_make_atom_len(env, name, len)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_atom_len(
+    env: *mut Env,
+    name: *const c_char,
+    len: usize,
+) -> OpaqueTerm {
+    match decode(name, len, LATIN1).and_then(|name| Atom::try_from(name.as_ref()).ok()) {
+        Some(atom) => atom.into(),
+        None => badarg(self::env(env)),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_existing_atom(
+    env: *mut Env,
+    name: *const c_char,
+    atom: *mut OpaqueTerm,
+    encoding: c_int,
+) -> c_int {
+    let len = std::ffi::CStr::from_ptr(name).to_bytes().len();
+    enif_make_existing_atom_len(env, name, len, atom, encoding)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_existing_atom_len(
+    _env: *mut Env,
+    name: *const c_char,
+    len: usize,
+    atom: *mut OpaqueTerm,
+    encoding: c_int,
+) -> c_int {
+    match decode(name, len, encoding).and_then(|name| Atom::try_from_str_existing(name).ok()) {
+        Some(existing) => {
+            atom.write(existing.into());
+            1
+        }
+        None => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_atom(
+    _env: *mut Env,
+    atom: OpaqueTerm,
+    buf: *mut c_char,
+    size: c_uint,
+    encoding: c_int,
+) -> c_int {
+    let Term::Atom(atom) = atom.into() else { return 0 };
+    let Some(bytes) = encode(atom.as_str(), encoding) else { return 0 };
+    // The name must fit along with its NUL terminator
+    if bytes.len() >= size as usize {
+        return 0;
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), buf.cast::<u8>(), bytes.len());
+    buf.add(bytes.len()).write(0);
+    bytes.len() as c_int + 1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_atom_length(
+    _env: *mut Env,
+    atom: OpaqueTerm,
+    len: *mut c_uint,
+    encoding: c_int,
+) -> c_int {
+    let Term::Atom(atom) = atom.into() else { return 0 };
+    let Some(bytes) = encode(atom.as_str(), encoding) else { return 0 };
+    len.write(bytes.len() as c_uint);
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_string(
+    env: *mut Env,
+    string: *const c_char,
+    encoding: c_int,
+) -> OpaqueTerm {
+    let len = std::ffi::CStr::from_ptr(string).to_bytes().len();
+    enif_make_string_len(env, string, len, encoding)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_string_len(
+    env: *mut Env,
+    string: *const c_char,
+    len: usize,
+    encoding: c_int,
+) -> OpaqueTerm {
+    let env = self::env(env);
+    let Some(string) = decode(string, len, encoding) else { return badarg(env) };
+    let chars = string
+        .chars()
+        .map(|c| c.into())
+        .collect::<Vec<OpaqueTerm>>();
+    make_list(env, &chars, OpaqueTerm::NIL)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_string(
+    _env: *mut Env,
+    list: OpaqueTerm,
+    buf: *mut c_char,
+    size: c_uint,
+    encoding: c_int,
+) -> c_int {
+    if size == 0 {
+        return 0;
+    }
+    let mut string = String::new();
+    match list.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for element in cons.iter_raw() {
+                let Ok(Term::Int(c)) = element.map(Term::from) else { return 0 };
+                let Some(c) = u32::try_from(c).ok().and_then(char::from_u32) else { return 0 };
+                string.push(c);
+            }
+        }
+        _ => return 0,
+    }
+    let Some(bytes) = encode(&string, encoding) else { return 0 };
+    let size = size as usize;
+    if bytes.len() < size {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), buf.cast::<u8>(), bytes.len());
+        buf.add(bytes.len()).write(0);
+        bytes.len() as c_int + 1
+    } else {
+        // As much of the string as fits is written, and the result is negative to indicate that
+        // it was truncated
+        ptr::copy_nonoverlapping(bytes.as_ptr(), buf.cast::<u8>(), size - 1);
+        buf.add(size - 1).write(0);
+        -(size as c_int)
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_tuple(env: *mut Env, cnt: c_uint, mut args: ...) -> OpaqueTerm {
+    enif_make_tuple_from_array(env, va_terms(&mut args, cnt).as_ptr(), cnt)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_tuple_from_array(
+    env: *mut Env,
+    arr: *const OpaqueTerm,
+    cnt: c_uint,
+) -> OpaqueTerm {
+    let elements = terms(arr, cnt as usize);
+    Tuple::from_slice(elements, self::env(env)).unwrap().into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_tuple(
+    _env: *mut Env,
+    tpl: OpaqueTerm,
+    arity: *mut c_int,
+    array: *mut *const OpaqueTerm,
+) -> c_int {
+    let Term::Tuple(tuple) = tpl.into() else { return 0 };
+    let elements = tuple.as_slice();
+    arity.write(elements.len() as c_int);
+    array.write(elements.as_ptr());
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_list(env: *mut Env, cnt: c_uint, mut args: ...) -> OpaqueTerm {
+    enif_make_list_from_array(env, va_terms(&mut args, cnt).as_ptr(), cnt)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_list_from_array(
+    env: *mut Env,
+    arr: *const OpaqueTerm,
+    cnt: c_uint,
+) -> OpaqueTerm {
+    make_list(self::env(env), terms(arr, cnt as usize), OpaqueTerm::NIL)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_list_cell(
+    env: *mut Env,
+    car: OpaqueTerm,
+    cdr: OpaqueTerm,
+) -> OpaqueTerm {
+    make_list(self::env(env), &[car], cdr)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_reverse_list(
+    env: *mut Env,
+    term: OpaqueTerm,
+    list: *mut OpaqueTerm,
+) -> c_int {
+    let mut elements = Vec::new();
+    match term.into() {
+        Term::Nil => (),
+        Term::Cons(cons) => {
+            for element in cons.iter_raw() {
+                let Ok(element) = element else { return 0 };
+                elements.push(element);
+            }
+        }
+        _ => return 0,
+    }
+    elements.reverse();
+    list.write(make_list(self::env(env), &elements, OpaqueTerm::NIL));
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_list_cell(
+    _env: *mut Env,
+    term: OpaqueTerm,
+    head: *mut OpaqueTerm,
+    tail: *mut OpaqueTerm,
+) -> c_int {
+    let Term::Cons(cons) = term.into() else { return 0 };
+    head.write(cons.head);
+    tail.write(cons.tail);
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_list_length(
+    _env: *mut Env,
+    term: OpaqueTerm,
+    len: *mut c_uint,
+) -> c_int {
+    match term.into() {
+        Term::Nil => len.write(0),
+        Term::Cons(cons) => match cons.length() {
+            Ok(length) => len.write(length as c_uint),
+            Err(_) => return 0,
+        },
+        _ => return 0,
+    }
+    1
+}
+
+/// Builds a list of `elements` ending in `tail` in `env`
+fn make_list(env: &Env, elements: &[OpaqueTerm], tail: OpaqueTerm) -> OpaqueTerm {
+    let mut list = tail;
+    for element in elements.iter().rev().copied() {
+        let cons = Cons {
+            head: element,
+            tail: list,
+        };
+        list = Cons::new_in(cons, env).unwrap().into();
+    }
+    list
+}
+
+/// Returns the `cnt` terms at `arr` as a slice
+unsafe fn terms<'a>(arr: *const OpaqueTerm, cnt: usize) -> &'a [OpaqueTerm] {
+    if cnt == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(arr, cnt)
+    }
+}
+
+/// Reads `cnt` terms from the variadic arguments `args`
+unsafe fn va_terms(args: &mut VaListImpl<'_>, cnt: c_uint) -> Vec<OpaqueTerm> {
+    // Terms are passed as their raw 64-bit representation
+    (0..cnt)
+        .map(|_| mem::transmute::<u64, OpaqueTerm>(args.arg::<u64>()))
+        .collect()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_new_map(env: *mut Env) -> OpaqueTerm {
+    Map::new_in(self::env(env)).unwrap().into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_map_from_arrays(
+    env: *mut Env,
+    keys: *const OpaqueTerm,
+    values: *const OpaqueTerm,
+    cnt: usize,
+    map_out: *mut OpaqueTerm,
+) -> c_int {
+    let keys = terms(keys, cnt);
+    let values = terms(values, cnt);
+    // Duplicate keys are not permitted
+    let mut sorted = keys.to_vec();
+    sorted.sort();
+    if sorted.windows(2).any(|pair| pair[0].exact_eq(&pair[1])) {
+        return 0;
+    }
+    let entries = keys.iter().copied().zip(values.iter().copied());
+    match Map::from_iter(entries, self::env(env)) {
+        Ok(map) => {
+            map_out.write(map.into());
+            1
+        }
+        Err(_) => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_map_put(
+    env: *mut Env,
+    map_in: OpaqueTerm,
+    key: OpaqueTerm,
+    value: OpaqueTerm,
+    map_out: *mut OpaqueTerm,
+) -> c_int {
+    let Term::Map(map) = map_in.into() else { return 0 };
+    match map.put(key, value, self::env(env)) {
+        Ok(map) => {
+            map_out.write(map.into());
+            1
+        }
+        Err(_) => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_map_update(
+    env: *mut Env,
+    map_in: OpaqueTerm,
+    key: OpaqueTerm,
+    value: OpaqueTerm,
+    map_out: *mut OpaqueTerm,
+) -> c_int {
+    let Term::Map(map) = map_in.into() else { return 0 };
+    match map.update(key, value, self::env(env)) {
+        Ok(map) => {
+            map_out.write(map.into());
+            1
+        }
+        Err(_) => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_map_remove(
+    env: *mut Env,
+    map_in: OpaqueTerm,
+    key: OpaqueTerm,
+    map_out: *mut OpaqueTerm,
+) -> c_int {
+    let Term::Map(map) = map_in.into() else { return 0 };
+    if !map.contains_key(key) {
+        // Removing a key which isn't present returns the map unchanged
+        map_out.write(map_in);
+        return 1;
+    }
+    match map.take(key, self::env(env)) {
+        Ok((_, map)) => {
+            map_out.write(map.into());
+            1
+        }
+        Err(_) => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_map_size(
+    _env: *mut Env,
+    term: OpaqueTerm,
+    size: *mut usize,
+) -> c_int {
+    let Term::Map(map) = term.into() else { return 0 };
+    size.write(map.size());
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_map_value(
+    _env: *mut Env,
+    map: OpaqueTerm,
+    key: OpaqueTerm,
+    value: *mut OpaqueTerm,
+) -> c_int {
+    let Term::Map(map) = map.into() else { return 0 };
+    match map.get(key) {
+        Some(found) => {
+            value.write(found);
+            1
+        }
+        None => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_map_iterator_create(
+    _env: *mut Env,
+    map: OpaqueTerm,
+    iter: *mut ErlNifMapIterator,
+    entry: c_int,
+) -> c_int {
+    let Term::Map(m) = map.into() else { return 0 };
+    let size = m.size();
+    let idx = match entry {
+        MAP_ITERATOR_FIRST => 0,
+        MAP_ITERATOR_LAST => size.saturating_sub(1),
+        _ => return 0,
+    };
+    // Maps are immutable, so the iterator can refer directly to the keys and values of the map
+    iter.write(ErlNifMapIterator {
+        map,
+        size,
+        idx,
+        keys: m.keys().as_ptr(),
+        values: m.values().as_ptr(),
+        _spare: [ptr::null_mut(); 2],
+    });
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_map_iterator_destroy(_env: *mut Env, _iter: *mut ErlNifMapIterator) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_map_iterator_is_head(
+    _env: *mut Env,
+    iter: *mut ErlNifMapIterator,
+) -> c_int {
+    let iter = &*iter;
+    (iter.size == 0 || iter.idx == usize::MAX) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_map_iterator_is_tail(
+    _env: *mut Env,
+    iter: *mut ErlNifMapIterator,
+) -> c_int {
+    let iter = &*iter;
+    (iter.size == 0 || iter.idx == iter.size) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_map_iterator_next(
+    _env: *mut Env,
+    iter: *mut ErlNifMapIterator,
+) -> c_int {
+    let iter = &mut *iter;
+    // The position before the first entry is represented by `usize::MAX`, so this wraps to 0
+    if iter.idx == iter.size {
+        return 0;
+    }
+    iter.idx = iter.idx.wrapping_add(1);
+    (iter.idx < iter.size) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_map_iterator_prev(
+    _env: *mut Env,
+    iter: *mut ErlNifMapIterator,
+) -> c_int {
+    let iter = &mut *iter;
+    if iter.idx == usize::MAX || iter.size == 0 {
+        return 0;
+    }
+    iter.idx = iter.idx.wrapping_sub(1);
+    (iter.idx != usize::MAX) as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_map_iterator_get_pair(
+    _env: *mut Env,
+    iter: *mut ErlNifMapIterator,
+    key: *mut OpaqueTerm,
+    value: *mut OpaqueTerm,
+) -> c_int {
+    let iter = &*iter;
+    if iter.idx >= iter.size {
+        return 0;
+    }
+    key.write(iter.keys.add(iter.idx).read());
+    value.write(iter.values.add(iter.idx).read());
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_alloc_binary(size: usize, bin: *mut ErlNifBinary) -> c_int {
+    let data = enif_alloc(size);
+    if data.is_null() {
+        return 0;
+    }
+    bin.write(ErlNifBinary {
+        size,
+        data: data.cast(),
+        ref_bin: data,
+        _spare: [ptr::null_mut(); 2],
+    });
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_realloc_binary(bin: *mut ErlNifBinary, size: usize) -> c_int {
+    let bin = &mut *bin;
+    let data = if bin.ref_bin.is_null() {
+        // The binary was inspected, so its data must be copied rather than reallocated
+        let data = enif_alloc(size);
+        if !data.is_null() {
+            ptr::copy_nonoverlapping(bin.data, data.cast(), bin.size.min(size));
+        }
+        data
+    } else {
+        enif_realloc(bin.ref_bin, size)
+    };
+    if data.is_null() {
+        return 0;
+    }
+    bin.size = size;
+    bin.data = data.cast();
+    bin.ref_bin = data;
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_release_binary(bin: *mut ErlNifBinary) {
+    let bin = &mut *bin;
+    if !bin.ref_bin.is_null() {
+        enif_free(bin.ref_bin);
+        bin.ref_bin = ptr::null_mut();
+        bin.data = ptr::null_mut();
+        bin.size = 0;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_inspect_binary(
+    env: *mut Env,
+    bin_term: OpaqueTerm,
+    bin: *mut ErlNifBinary,
+) -> c_int {
+    let term: Term = bin_term.into();
+    let Some(bytes) = term.as_binary() else { return 0 };
+    if !bytes.is_binary() {
+        return 0;
+    }
+    let data = if bytes.is_aligned() {
+        bytes.as_bytes_unchecked().as_ptr().cast_mut()
+    } else {
+        self::env(env).buffer(bytes.bytes().collect())
+    };
+    bin.write(ErlNifBinary {
+        size: bytes.byte_size(),
+        data,
+        ref_bin: ptr::null_mut(),
+        _spare: [ptr::null_mut(); 2],
+    });
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_inspect_iolist_as_binary(
+    env: *mut Env,
+    term: OpaqueTerm,
+    bin: *mut ErlNifBinary,
+) -> c_int {
+    let Some(data) = crate::nifs::file::iodata_from_term(term) else { return 0 };
+    let size = data.len();
+    let data = self::env(env).buffer(data);
+    bin.write(ErlNifBinary {
+        size,
+        data,
+        ref_bin: ptr::null_mut(),
+        _spare: [ptr::null_mut(); 2],
+    });
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_binary(env: *mut Env, bin: *mut ErlNifBinary) -> OpaqueTerm {
+    let bin = &mut *bin;
+    let term = make_binary(self::env(env), slice::from_raw_parts(bin.data, bin.size));
+    // The binary is owned by the term from now on, which we represent by copying its data
+    enif_release_binary(bin);
+    term
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_new_binary(
+    env: *mut Env,
+    size: usize,
+    termp: *mut OpaqueTerm,
+) -> *mut u8 {
+    if size <= BinaryData::MAX_HEAP_BYTES {
+        let mut bin = BinaryData::with_capacity_small(size, self::env(env)).unwrap();
+        let data = bin[..].as_mut_ptr();
+        termp.write(bin.into());
+        data
+    } else {
+        let mut bin = BinaryData::with_capacity_large(size);
+        let data = Arc::get_mut(&mut bin).unwrap()[..].as_mut_ptr();
+        termp.write(Term::RcBinary(bin).into());
+        data
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_sub_binary(
+    env: *mut Env,
+    bin_term: OpaqueTerm,
+    pos: usize,
+    size: usize,
+) -> OpaqueTerm {
+    let env = self::env(env);
+    let term: Term = bin_term.into();
+    let Some(bytes) = term.as_binary() else { return badarg(env) };
+    let Some(end) = pos.checked_add(size) else { return badarg(env) };
+    if !bytes.is_binary() || end > bytes.byte_size() {
+        return badarg(env);
+    }
+    let data = bytes.bytes().skip(pos).take(size).collect::<Vec<u8>>();
+    make_binary(env, &data)
+}
+
+/// Copies `bytes` into a new binary in `env`
+fn make_binary(env: &Env, bytes: &[u8]) -> OpaqueTerm {
+    if bytes.len() <= BinaryData::MAX_HEAP_BYTES {
+        BinaryData::from_small_bytes(bytes, env).unwrap().into()
+    } else {
+        Term::RcBinary(BinaryData::from_bytes(bytes)).into()
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_ref(env: *mut Env) -> OpaqueTerm {
+    Gc::new_in(Reference::make(), self::env(env))
+        .unwrap()
+        .into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_self(caller_env: *mut Env, pid: *mut ErlNifPid) -> *mut ErlNifPid {
+    match env(caller_env).process {
+        Some(id) => {
+            pid.write(ErlNifPid::new(id));
+            pid
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_local_pid(
+    _env: *mut Env,
+    term: OpaqueTerm,
+    pid: *mut ErlNifPid,
+) -> c_int {
+    match term.into() {
+        Term::Pid(p) if p.is_local() => {
+            pid.write(ErlNifPid::new(p.id()));
+            1
+        }
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_pid(env: *mut Env, pid: *const ErlNifPid) -> OpaqueTerm {
+    let pid = Pid::new_local((*pid).id());
+    Gc::new_in(pid, self::env(env)).unwrap().into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_compare_pids(
+    pid1: *const ErlNifPid,
+    pid2: *const ErlNifPid,
+) -> c_int {
+    match (*pid1).pid.cmp(&(*pid2).pid) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_process_alive(_env: *mut Env, pid: *mut ErlNifPid) -> c_int {
+    registry::get_by_process_id((*pid).id()).is_some() as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_is_current_process_alive(env: *mut Env) -> c_int {
+    match self::env(env).process {
+        Some(id) => registry::get_by_process_id(id).is_some() as c_int,
+        None => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_whereis_pid(
+    _env: *mut Env,
+    name: OpaqueTerm,
+    pid: *mut ErlNifPid,
+) -> c_int {
+    let Term::Atom(name) = name.into() else { return 0 };
+    match registry::get_by_name(name) {
+        Some(Registrant::Process(process)) => {
+            pid.write(ErlNifPid::new(process.id()));
+            1
+        }
+        _ => 0,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_send(
+    caller_env: *mut Env,
+    to_pid: *const ErlNifPid,
+    msg_env: *mut Env,
+    msg: OpaqueTerm,
+) -> c_int {
+    let sender = match caller_env.as_ref().and_then(|env| env.process) {
+        Some(id) => WeakAddress::from(id),
+        None => WeakAddress::System,
+    };
+    let sent = match registry::get_by_process_id((*to_pid).id()) {
+        // The message is copied, as its terms may belong to the heap of the calling process
+        Some(recipient) => {
+            let term: Term = msg.into();
+            let fragment = term.clone_to_fragment().unwrap();
+            recipient.send_fragment(sender, fragment).is_ok()
+        }
+        None => false,
+    };
+    if !msg_env.is_null() {
+        env(msg_env).clear();
+    }
+    sent as c_int
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_consume_timeslice(_env: *mut Env, _percent: c_int) -> c_int {
+    // Processes are never preempted while running a NIF, so there is never a reason to yield
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_thread_type() -> c_int {
+    THREAD_TYPE.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn allocated_size(ptr: *mut c_void) -> usize {
+        ptr.cast::<u8>().sub(ALLOC_HEADER).cast::<usize>().read()
+    }
+
+    #[test]
+    fn alloc_header_test() {
+        unsafe {
+            let ptr = enif_alloc(8);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % ALLOC_HEADER, 0);
+            assert_eq!(allocated_size(ptr), 8);
+            ptr::copy_nonoverlapping(b"firefly!".as_ptr(), ptr.cast(), 8);
+
+            let ptr = enif_realloc(ptr, 64);
+            assert!(!ptr.is_null());
+            assert_eq!(allocated_size(ptr), 64);
+            assert_eq!(slice::from_raw_parts(ptr.cast::<u8>(), 8), b"firefly!");
+
+            let ptr = enif_realloc(ptr, 4);
+            assert_eq!(allocated_size(ptr), 4);
+            assert_eq!(slice::from_raw_parts(ptr.cast::<u8>(), 4), b"fire");
+            enif_free(ptr);
+
+            // Reallocating null is the same as allocating
+            let ptr = enif_realloc(ptr::null_mut(), 16);
+            assert_eq!(allocated_size(ptr), 16);
+            enif_free(ptr);
+            enif_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn term_type_test() {
+        let env = Env::new(None, None);
+        let env = env.as_ptr();
+        unsafe {
+            let mut bin = ErlNifBinary {
+                size: 0,
+                data: ptr::null_mut(),
+                ref_bin: ptr::null_mut(),
+                _spare: [ptr::null_mut(); 2],
+            };
+            assert_eq!(enif_alloc_binary(3, &mut bin), 1);
+            ptr::copy_nonoverlapping(b"abc".as_ptr(), bin.data, 3);
+            let binary = enif_make_binary(env, &mut bin);
+            let mut map = OpaqueTerm::NONE;
+            assert_eq!(
+                enif_make_map_from_arrays(env, ptr::null(), ptr::null(), 0, &mut map),
+                1
+            );
+            let elements = [atoms::Ok.into()];
+
+            assert_eq!(enif_term_type(env, atoms::Ok.into()), 1);
+            assert_eq!(enif_term_type(env, true.into()), 1);
+            assert_eq!(enif_term_type(env, binary), 2);
+            assert_eq!(enif_term_type(env, enif_make_double(env, 1.5)), 3);
+            assert_eq!(enif_term_type(env, enif_make_int(env, 42)), 5);
+            assert_eq!(enif_term_type(env, enif_make_uint64(env, u64::MAX)), 5);
+            assert_eq!(enif_term_type(env, OpaqueTerm::NIL), 6);
+            assert_eq!(
+                enif_term_type(env, enif_make_list_from_array(env, elements.as_ptr(), 1)),
+                6
+            );
+            assert_eq!(enif_term_type(env, map), 7);
+            assert_eq!(
+                enif_term_type(env, enif_make_tuple_from_array(env, elements.as_ptr(), 1)),
+                11
+            );
+        }
+    }
+
+    #[test]
+    fn integer_round_trip_test() {
+        let env = Env::new(None, None);
+        let env = env.as_ptr();
+        unsafe {
+            let mut i = 0;
+            assert_eq!(enif_get_int(env, enif_make_int(env, -42), &mut i), 1);
+            assert_eq!(i, -42);
+
+            let mut u = 0;
+            assert_eq!(
+                enif_get_uint(env, enif_make_uint(env, c_uint::MAX), &mut u),
+                1
+            );
+            assert_eq!(u, c_uint::MAX);
+            assert_eq!(enif_get_uint(env, enif_make_int(env, -1), &mut u), 0);
+
+            let mut l = 0;
+            assert_eq!(
+                enif_get_int64(env, enif_make_int64(env, i64::MIN), &mut l),
+                1
+            );
+            assert_eq!(l, i64::MIN);
+
+            // Values outside the range of small integers are made as big integers
+            let mut ul = 0;
+            assert_eq!(
+                enif_get_uint64(env, enif_make_uint64(env, u64::MAX), &mut ul),
+                1
+            );
+            assert_eq!(ul, u64::MAX);
+            assert_eq!(
+                enif_get_int64(env, enif_make_uint64(env, u64::MAX), &mut l),
+                0
+            );
+            assert_eq!(enif_get_int(env, enif_make_int64(env, i64::MAX), &mut i), 0);
+
+            let mut d = 0.0;
+            assert_eq!(enif_get_double(env, enif_make_double(env, 1.5), &mut d), 1);
+            assert_eq!(d, 1.5);
+            assert_eq!(enif_get_double(env, enif_make_int(env, 1), &mut d), 0);
+        }
+    }
+
+    #[test]
+    fn binary_round_trip_test() {
+        let env = Env::new(None, None);
+        let env = env.as_ptr();
+        unsafe {
+            let mut bin = ErlNifBinary {
+                size: 0,
+                data: ptr::null_mut(),
+                ref_bin: ptr::null_mut(),
+                _spare: [ptr::null_mut(); 2],
+            };
+            for size in [5, BinaryData::MAX_HEAP_BYTES + 1] {
+                let bytes = (0..size).map(|i| i as u8).collect::<Vec<_>>();
+                let mut term = OpaqueTerm::NONE;
+                let data = enif_make_new_binary(env, size, &mut term);
+                ptr::copy_nonoverlapping(bytes.as_ptr(), data, size);
+                assert_eq!(enif_is_binary(env, term), 1);
+                assert_eq!(enif_inspect_binary(env, term, &mut bin), 1);
+                assert_eq!(slice::from_raw_parts(bin.data, bin.size), bytes.as_slice());
+
+                let sub = enif_make_sub_binary(env, term, 1, 3);
+                assert_eq!(enif_inspect_binary(env, sub, &mut bin), 1);
+                assert_eq!(slice::from_raw_parts(bin.data, bin.size), &bytes[1..4]);
+            }
+
+            assert_eq!(enif_alloc_binary(4, &mut bin), 1);
+            ptr::copy_nonoverlapping(b"abcd".as_ptr(), bin.data, 4);
+            assert_eq!(enif_realloc_binary(&mut bin, 6), 1);
+            ptr::copy_nonoverlapping(b"ef".as_ptr(), bin.data.add(4), 2);
+            let term = enif_make_binary(env, &mut bin);
+            assert!(bin.ref_bin.is_null());
+            assert_eq!(enif_inspect_binary(env, term, &mut bin), 1);
+            assert_eq!(slice::from_raw_parts(bin.data, bin.size), b"abcdef");
+            assert_eq!(enif_inspect_binary(env, atoms::Ok.into(), &mut bin), 0);
+        }
+    }
+
+    #[test]
+    fn tuple_round_trip_test() {
+        let env = Env::new(None, None);
+        let env = env.as_ptr();
+        unsafe {
+            let elements = [atoms::Ok.into(), enif_make_int(env, 1), OpaqueTerm::NIL];
+            let tuple = enif_make_tuple_from_array(env, elements.as_ptr(), 3);
+            let mut arity = 0;
+            let mut array = ptr::null();
+            assert_eq!(enif_get_tuple(env, tuple, &mut arity, &mut array), 1);
+            assert_eq!(arity, 3);
+            let found = slice::from_raw_parts(array, arity as usize);
+            assert!(found
+                .iter()
+                .zip(elements.iter())
+                .all(|(a, b)| a.exact_eq(b)));
+
+            let empty = enif_make_tuple_from_array(env, ptr::null(), 0);
+            assert_eq!(enif_get_tuple(env, empty, &mut arity, &mut array), 1);
+            assert_eq!(arity, 0);
+            assert_eq!(
+                enif_get_tuple(env, OpaqueTerm::NIL, &mut arity, &mut array),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn list_round_trip_test() {
+        let env = Env::new(None, None);
+        let env = env.as_ptr();
+        unsafe {
+            let elements = [
+                enif_make_int(env, 1),
+                enif_make_int(env, 2),
+                enif_make_int(env, 3),
+            ];
+            let list = enif_make_list_from_array(env, elements.as_ptr(), 3);
+            let mut len = 0;
+            assert_eq!(enif_get_list_length(env, list, &mut len), 1);
+            assert_eq!(len, 3);
+
+            let mut reversed = OpaqueTerm::NONE;
+            assert_eq!(enif_make_reverse_list(env, list, &mut reversed), 1);
+            let mut tail = reversed;
+            for expected in elements.iter().rev() {
+                let mut head = OpaqueTerm::NONE;
+                let cell = tail;
+                assert_eq!(enif_get_list_cell(env, cell, &mut head, &mut tail), 1);
+                assert!(head.exact_eq(expected));
+            }
+            assert_eq!(enif_is_empty_list(env, tail), 1);
+
+            // Improper lists have no length
+            let improper = enif_make_list_cell(env, elements[0], elements[1]);
+            assert_eq!(enif_get_list_length(env, improper, &mut len), 0);
+        }
+    }
+
+    #[test]
+    fn map_round_trip_test() {
+        let env = Env::new(None, None);
+        let env = env.as_ptr();
+        unsafe {
+            let keys = [atoms::Ok.into(), atoms::Error.into()];
+            let values = [enif_make_int(env, 1), enif_make_int(env, 2)];
+            let mut map = OpaqueTerm::NONE;
+            assert_eq!(
+                enif_make_map_from_arrays(env, keys.as_ptr(), values.as_ptr(), 2, &mut map),
+                1
+            );
+            let mut size = 0;
+            assert_eq!(enif_get_map_size(env, map, &mut size), 1);
+            assert_eq!(size, 2);
+            for (key, expected) in keys.iter().zip(values.iter()) {
+                let mut value = OpaqueTerm::NONE;
+                assert_eq!(enif_get_map_value(env, map, *key, &mut value), 1);
+                assert!(value.exact_eq(expected));
+            }
+
+            let mut updated = OpaqueTerm::NONE;
+            let value = enif_make_int(env, 3);
+            assert_eq!(
+                enif_make_map_put(env, map, OpaqueTerm::NIL, value, &mut updated),
+                1
+            );
+            assert_eq!(enif_get_map_size(env, updated, &mut size), 1);
+            assert_eq!(size, 3);
+            let mut removed = OpaqueTerm::NONE;
+            assert_eq!(enif_make_map_remove(env, updated, keys[0], &mut removed), 1);
+            let mut found = OpaqueTerm::NONE;
+            assert_eq!(enif_get_map_value(env, removed, keys[0], &mut found), 0);
+
+            // Duplicate keys are rejected
+            let keys = [atoms::Ok.into(), atoms::Ok.into()];
+            assert_eq!(
+                enif_make_map_from_arrays(env, keys.as_ptr(), values.as_ptr(), 2, &mut map),
+                0
+            );
+        }
+    }
+}
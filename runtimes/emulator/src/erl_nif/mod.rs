@@ -0,0 +1,517 @@
+//! An implementation of the `erl_nif` API, for loading native libraries written against OTP
+//!
+//! A module declares the functions implemented natively with the `-nifs` attribute, and loads a
+//! library implementing them with `erlang:load_nif/2`, usually from its `-on_load` function. The
+//! library is opened with the dynamic loader of the platform, and its `nif_init` entry point
+//! describes the functions it implements, which from then on are called in place of their Erlang
+//! definitions, just like functions registered by an embedding host (see [`crate::embed`]).
+//!
+//! The `enif_*` functions called by libraries are declared for C in `include/erl_nif.h`, and are
+//! resolved against the executable when a library is loaded, so the executable must export them,
+//! e.g. by linking with `-Wl,--export-dynamic`.
+//!
+//! Terms created by a NIF are allocated in heap fragments owned by its [`Env`], which are attached
+//! to the calling process when the NIF returns, just like the fragments of received messages.
+mod ffi;
+mod resource;
+mod schedule;
+mod sync;
+
+use std::alloc::{AllocError, Allocator, Layout};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr, OsString};
+use std::path::Path;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use firefly_alloc::fragment::HeapFragment;
+use firefly_bytecode::Function;
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::{ErlangResult, ModuleFunctionArity};
+use firefly_rt::process::{ProcessId, ProcessLock};
+use firefly_rt::term::*;
+
+use intrusive_collections::UnsafeRef;
+
+use crate::emulator::current_scheduler;
+
+use self::schedule::Scheduled;
+
+pub use self::resource::exited;
+
+/// The version of the `erl_nif` API implemented, see `include/erl_nif.h`
+const MAJOR_VERSION: c_int = 2;
+const MINOR_VERSION: c_int = 16;
+
+/// The variant of the runtime libraries must be built for, so that libraries built against the
+/// header shipped with OTP, which are not binary compatible, are rejected
+const VM_VARIANT: &'static str = "firefly";
+
+/// The default size of the heap fragments allocated by an [`Env`]
+const FRAGMENT_SIZE: usize = 4 * 1024;
+
+/// Flags of an `ErlNifFunc` indicating the function must run on a dirty scheduler
+const DIRTY_JOB_CPU_BOUND: c_uint = 1;
+const DIRTY_JOB_IO_BOUND: c_uint = 2;
+
+/// The signature of a function implemented by a NIF library
+pub type NifFn = unsafe extern "C" fn(*mut Env, c_int, *const OpaqueTerm) -> OpaqueTerm;
+
+type LoadFn = unsafe extern "C" fn(*mut Env, *mut *mut c_void, OpaqueTerm) -> c_int;
+
+/// Describes a function implemented by a library, see `ErlNifFunc` in `include/erl_nif.h`
+#[repr(C)]
+struct ErlNifFunc {
+    name: *const c_char,
+    arity: c_uint,
+    fptr: NifFn,
+    flags: c_uint,
+}
+
+/// Describes a library, as returned by its `nif_init` entry point, see `ErlNifEntry` in
+/// `include/erl_nif.h`
+#[repr(C)]
+#[allow(dead_code)]
+struct ErlNifEntry {
+    major: c_int,
+    minor: c_int,
+    name: *const c_char,
+    num_of_funcs: c_int,
+    funcs: *const ErlNifFunc,
+    load: Option<LoadFn>,
+    reload: *const c_void,
+    upgrade: *const c_void,
+    unload: *const c_void,
+    vm_variant: *const c_char,
+    options: c_uint,
+    sizeof_resource_type_init: usize,
+    min_erts: *const c_char,
+}
+
+/// A NIF library loaded by a module
+///
+/// Libraries are never unloaded, so this lives as long as the functions it implements are
+/// registered.
+pub struct Library {
+    /// The module which loaded this library
+    module: Atom,
+    /// The data set by the `load` callback of this library, see `enif_priv_data`
+    priv_data: AtomicPtr<c_void>,
+    /// The handle keeping the library loaded
+    _handle: libloading::Library,
+}
+
+/// A function implemented by a NIF library
+#[derive(Clone)]
+pub struct Nif {
+    fun: NifFn,
+    flags: c_uint,
+    library: Arc<Library>,
+}
+impl Nif {
+    /// Returns true if this function must always be run on a dirty scheduler
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.flags != 0
+    }
+}
+
+/// Set once any library has been loaded, so that calls to functions declared as NIFs don't have
+/// to take the lock on the registry before then
+static LOADED: AtomicBool = AtomicBool::new(false);
+
+/// The libraries loaded so far, by the module which loaded them
+static LIBRARIES: Mutex<BTreeMap<Atom, Arc<Library>>> = Mutex::new(BTreeMap::new());
+
+/// The functions implemented by the libraries loaded so far
+static NIFS: RwLock<BTreeMap<ModuleFunctionArity, Nif>> = RwLock::new(BTreeMap::new());
+
+/// Returns the function implemented by a NIF library for `mfa`, if any
+///
+/// Schedulers check this before any native implementation built into the executable when
+/// calling a function which is declared as a NIF.
+#[inline]
+pub fn find(mfa: &ModuleFunctionArity) -> Option<Nif> {
+    if LOADED.load(Ordering::Acquire) {
+        NIFS.read().unwrap().get(mfa).cloned()
+    } else {
+        None
+    }
+}
+
+/// An error which occurred while loading a NIF library, returned by `erlang:load_nif/2` as
+/// `{error, {Reason, Text}}`
+#[derive(Debug)]
+pub enum LoadError {
+    /// The library couldn't be opened by the dynamic loader
+    LoadFailed(String),
+    /// The library is not a valid NIF library for the calling module
+    BadLib(String),
+    /// The `load` callback of the library failed
+    Load(String),
+    /// The calling module has already loaded a library
+    Reload(String),
+}
+impl LoadError {
+    /// Returns the reason for this error, as returned by `erlang:load_nif/2`
+    pub fn reason(&self) -> Atom {
+        match self {
+            Self::LoadFailed(_) => atoms::LoadFailed,
+            Self::BadLib(_) => atoms::BadLib,
+            Self::Load(_) => atoms::Load,
+            Self::Reload(_) => atoms::Reload,
+        }
+    }
+
+    /// Returns the description of this error
+    pub fn text(&self) -> &str {
+        match self {
+            Self::LoadFailed(text) | Self::BadLib(text) | Self::Load(text) | Self::Reload(text) => {
+                text.as_str()
+            }
+        }
+    }
+}
+
+/// Loads the NIF library at `path` on behalf of `module`, running its `load` callback with
+/// `load_info` in `process`
+///
+/// As with OTP, `path` is given without the platform-specific file extension of the library.
+pub fn load(
+    process: &mut ProcessLock,
+    module: Atom,
+    path: &Path,
+    load_info: OpaqueTerm,
+) -> Result<(), LoadError> {
+    if LIBRARIES.lock().unwrap().contains_key(&module) {
+        return Err(LoadError::Reload(
+            "NIF library already loaded (reload disallowed since OTP 20).".to_string(),
+        ));
+    }
+
+    let mut filename = OsString::from(path.as_os_str());
+    if cfg!(target_os = "macos") {
+        filename.push(".dylib");
+    } else if cfg!(windows) {
+        filename.push(".dll");
+    } else {
+        filename.push(".so");
+    }
+    let handle = unsafe { libloading::Library::new(&filename) }.map_err(|err| {
+        LoadError::LoadFailed(format!(
+            "Failed to load NIF library {}: '{}'",
+            Path::new(&filename).display(),
+            err
+        ))
+    })?;
+    let entry = unsafe {
+        let init = handle
+            .get::<unsafe extern "C" fn() -> *const ErlNifEntry>(b"nif_init\0")
+            .map_err(|_| {
+                LoadError::LoadFailed(format!(
+                    "Failed to find library init function: '{}'",
+                    Path::new(&filename).display()
+                ))
+            })?;
+        &*init()
+    };
+
+    let variant = unsafe { cstr(entry.vm_variant) };
+    if entry.major != MAJOR_VERSION || entry.minor > MINOR_VERSION || variant != Some(VM_VARIANT) {
+        return Err(LoadError::BadLib(format!(
+            "Library version ({}.{}) not compatible (with {}.{}), or not built against the erl_nif.h of this runtime.",
+            entry.major, entry.minor, MAJOR_VERSION, MINOR_VERSION
+        )));
+    }
+    let name = unsafe { cstr(entry.name) }.unwrap_or_default();
+    if name != module.as_str() {
+        return Err(LoadError::BadLib(format!(
+            "Library module name '{}' does not match calling module '{}'",
+            name, module
+        )));
+    }
+
+    // Validate all of the functions before registering any of them
+    let code = current_scheduler().code();
+    let funcs = if entry.num_of_funcs > 0 {
+        unsafe { std::slice::from_raw_parts(entry.funcs, entry.num_of_funcs as usize) }
+    } else {
+        &[]
+    };
+    let mut functions = Vec::with_capacity(funcs.len());
+    for func in funcs {
+        let name = unsafe { cstr(func.name) }.unwrap_or_default();
+        let (Ok(function), Ok(arity)) = (Atom::try_from(name), u8::try_from(func.arity)) else {
+            return Err(LoadError::BadLib(format!(
+                "Invalid NIF function {}/{}",
+                name, func.arity
+            )));
+        };
+        let mfa = ModuleFunctionArity {
+            module,
+            function,
+            arity,
+        };
+        let is_nif = match code.function_by_mfa(&mfa.into()) {
+            Some(Function::Bytecode { is_nif, .. }) => *is_nif,
+            _ => false,
+        };
+        if !is_nif {
+            return Err(LoadError::BadLib(format!(
+                "Function not declared as NIF: {}",
+                &mfa
+            )));
+        }
+        if func.flags > DIRTY_JOB_IO_BOUND {
+            return Err(LoadError::BadLib(format!(
+                "Illegal flags field value {} for NIF {}",
+                func.flags, &mfa
+            )));
+        }
+        functions.push((mfa, func.fptr, func.flags));
+    }
+
+    let library = Arc::new(Library {
+        module,
+        priv_data: AtomicPtr::new(ptr::null_mut()),
+        _handle: handle,
+    });
+    if let Some(load) = entry.load {
+        let env = Env::new(Some(process.id()), Some(library.clone()));
+        let mut priv_data = ptr::null_mut();
+        let result = env.with_thread_type(ffi::THR_NORMAL_SCHEDULER, || unsafe {
+            load(env.as_ptr(), &mut priv_data, load_info)
+        });
+        env.attach_to(process);
+        if result != 0 {
+            return Err(LoadError::Load(format!(
+                "Library load-call unsuccessful ({}).",
+                result
+            )));
+        }
+        library.priv_data.store(priv_data, Ordering::Release);
+    }
+
+    let mut nifs = NIFS.write().unwrap();
+    for (mfa, fun, flags) in functions {
+        let library = library.clone();
+        nifs.insert(
+            mfa,
+            Nif {
+                fun,
+                flags,
+                library,
+            },
+        );
+    }
+    LIBRARIES.lock().unwrap().insert(module, library);
+    LOADED.store(true, Ordering::Release);
+
+    Ok(())
+}
+
+/// Calls `nif` with `args` in `process`, returning its result, or raising the exception it
+/// signalled
+///
+/// If the function must run on a dirty scheduler, or schedules another function to run in its
+/// place, the process awaits its completion instead.
+pub fn call(process: &mut ProcessLock, nif: &Nif, args: &[OpaqueTerm]) -> ErlangResult {
+    if nif.is_dirty() {
+        let library = Some(nif.library.clone());
+        let call = Scheduled::new(process.id(), library, nif.fun, nif.flags, args);
+        return schedule::start(process, call);
+    }
+    let env = Env::new(Some(process.id()), Some(nif.library.clone()));
+    let result = unsafe { env.call(nif.fun, args, ffi::THR_NORMAL_SCHEDULER) };
+    match env.settle(process, result) {
+        Outcome::Return(result) => ErlangResult::Ok(result),
+        Outcome::Raise => ErlangResult::Err,
+        Outcome::Schedule(call) => schedule::start(process, call),
+    }
+}
+
+/// The outcome of a single call to a NIF, see [`Env::settle`]
+enum Outcome {
+    /// The function returned a term
+    Return(OpaqueTerm),
+    /// The function raised an exception, which has been stored in the process
+    Raise,
+    /// The function scheduled another function to run in its place
+    Schedule(Scheduled),
+}
+
+/// The environment in which a NIF runs, known to C as `ErlNifEnv`
+///
+/// An environment either belongs to the process calling a NIF, in which case the terms allocated
+/// in it become part of the heap of that process when the NIF returns, or is independent of any
+/// process, as allocated with `enif_alloc_env`, in which case its terms live until it is cleared
+/// or freed.
+pub struct Env {
+    /// The process on whose behalf the NIF is running, if any
+    process: Option<ProcessId>,
+    /// The library of the running NIF, if any
+    library: Option<Arc<Library>>,
+    /// The heap fragments in which terms created in this environment are allocated
+    fragments: RefCell<Vec<NonNull<HeapFragment>>>,
+    /// Copies of binaries which had to be made to inspect them as contiguous bytes
+    buffers: RefCell<Vec<Box<[u8]>>>,
+    /// The exception signalled by `enif_raise_exception` or `enif_make_badarg`, if any
+    exception: Cell<Option<OpaqueTerm>>,
+    /// The function to be run in place of the NIF when it returns, see `enif_schedule_nif`
+    scheduled: RefCell<Option<Scheduled>>,
+}
+impl Env {
+    fn new(process: Option<ProcessId>, library: Option<Arc<Library>>) -> Self {
+        Self {
+            process,
+            library,
+            fragments: RefCell::new(Vec::new()),
+            buffers: RefCell::new(Vec::new()),
+            exception: Cell::new(None),
+            scheduled: RefCell::new(None),
+        }
+    }
+
+    /// Returns this environment as the pointer handed to C
+    #[inline]
+    fn as_ptr(&self) -> *mut Env {
+        self as *const Env as *mut Env
+    }
+
+    /// Calls `fun` with `args`, with the current thread reported as being of type `thread_type`
+    unsafe fn call(&self, fun: NifFn, args: &[OpaqueTerm], thread_type: c_int) -> OpaqueTerm {
+        self.with_thread_type(thread_type, || {
+            fun(self.as_ptr(), args.len() as c_int, args.as_ptr())
+        })
+    }
+
+    fn with_thread_type<T, F: FnOnce() -> T>(&self, thread_type: c_int, f: F) -> T {
+        let previous = ffi::THREAD_TYPE.replace(thread_type);
+        let result = f();
+        ffi::THREAD_TYPE.set(previous);
+        result
+    }
+
+    /// Copies `term` into this environment, unless it is immediate or reference-counted
+    fn copy(&self, term: OpaqueTerm) -> Result<OpaqueTerm, AllocError> {
+        if term.is_immediate() || term.is_rc() || term.is_literal() {
+            return Ok(term);
+        }
+        let term: Term = term.into();
+        let layout = term.layout();
+        let fragment = HeapFragment::new(layout, None)?;
+        self.fragments.borrow_mut().push(fragment);
+        Ok(unsafe { term.unsafe_clone_to_heap(fragment.as_ref()) }.into())
+    }
+
+    /// Keeps `data` alive as long as the terms of this environment, returning a pointer to it
+    fn buffer(&self, data: Vec<u8>) -> *mut u8 {
+        let mut data = data.into_boxed_slice();
+        let ptr = data.as_mut_ptr();
+        self.buffers.borrow_mut().push(data);
+        ptr
+    }
+
+    /// Completes a call to a NIF in this environment which returned `result` to `process`
+    ///
+    /// The terms allocated in this environment are attached to the process, and if the NIF
+    /// signalled an exception, it is raised as if by `erlang:error/1`.
+    fn settle(self, process: &mut ProcessLock, result: OpaqueTerm) -> Outcome {
+        let scheduled = self.scheduled.take();
+        let exception = self.exception.get();
+        self.attach_to(process);
+        if let Some(call) = scheduled {
+            return Outcome::Schedule(call);
+        }
+        match exception {
+            // A NIF returning nothing without signalling an exception is treated as a badarg
+            None if result.is_none() => {
+                raise(process, atoms::Badarg.into());
+                Outcome::Raise
+            }
+            None => Outcome::Return(result),
+            Some(reason) => {
+                raise(process, reason);
+                Outcome::Raise
+            }
+        }
+    }
+
+    /// Attaches the heap fragments of this environment to `process`, making the terms allocated
+    /// in this environment part of its heap
+    fn attach_to(self, process: &mut ProcessLock) {
+        for fragment in self.fragments.borrow_mut().drain(..) {
+            process
+                .heap_fragments
+                .push_back(unsafe { UnsafeRef::from_raw(fragment.as_ptr().cast_const()) });
+        }
+    }
+
+    /// Frees all of the terms allocated in this environment
+    fn clear(&self) {
+        use firefly_alloc::heap::Heap;
+        use firefly_rt::gc::Reap;
+
+        for fragment in self.fragments.borrow_mut().drain(..) {
+            unsafe {
+                let range = fragment.as_ref().used_range();
+                range.reap();
+                ptr::drop_in_place(fragment.as_ptr());
+            }
+        }
+        self.buffers.borrow_mut().clear();
+        self.exception.set(None);
+    }
+}
+impl Drop for Env {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+unsafe impl Allocator for Env {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let mut fragments = self.fragments.borrow_mut();
+        if let Some(fragment) = fragments.last() {
+            if let Ok(ptr) = unsafe { fragment.as_ref() }.allocate(layout) {
+                return Ok(ptr);
+            }
+        }
+        let size = cmp::max(layout.size(), FRAGMENT_SIZE);
+        let fragment_layout =
+            Layout::from_size_align(size, layout.align()).map_err(|_| AllocError)?;
+        let fragment = HeapFragment::new(fragment_layout, None)?;
+        fragments.push(fragment);
+        unsafe { fragment.as_ref() }.allocate(layout)
+    }
+
+    /// Terms are freed with the environment they were allocated in
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+/// Stores `reason` in `process` as an error, as raised by `erlang:error/1`
+fn raise(process: &mut ProcessLock, reason: OpaqueTerm) {
+    process.exception_info.flags = ExceptionFlags::ERROR;
+    process.exception_info.value = reason;
+    process.exception_info.reason = match reason.into() {
+        Term::Atom(a) => a.into(),
+        Term::Tuple(tuple) => match tuple.get(0).map(Term::from) {
+            Some(Term::Atom(a)) => a.into(),
+            _ => atoms::Error.into(),
+        },
+        _ => atoms::Error.into(),
+    };
+    process.exception_info.args = None;
+    process.exception_info.trace = None;
+}
+
+/// Returns the UTF-8 string `s` points to, if it isn't null
+unsafe fn cstr<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
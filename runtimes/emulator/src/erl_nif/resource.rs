@@ -0,0 +1,449 @@
+//! Resources, i.e. native objects which can be handed to Erlang as terms, and the monitors they
+//! can place on processes
+//!
+//! A resource is allocated with a header recording its type and reference count, followed by the
+//! data seen by the library. Terms referring to a resource are magic references holding a
+//! [`ResourceHandle`], which keeps the resource alive until the term is garbage collected.
+use std::alloc::{self, Layout};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::ffi::{c_char, c_int, c_void, CStr};
+use std::mem;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use firefly_rt::gc::Gc;
+use firefly_rt::process::ProcessId;
+use firefly_rt::services::registry;
+use firefly_rt::term::*;
+
+use super::ffi::ErlNifPid;
+use super::{Env, Library};
+
+/// The values of `ErlNifResourceFlags`
+const RT_CREATE: c_int = 1;
+const RT_TAKEOVER: c_int = 2;
+
+type DtorFn = unsafe extern "C" fn(*mut Env, *mut c_void);
+type DownFn = unsafe extern "C" fn(*mut Env, *mut c_void, *mut ErlNifPid, *mut ErlNifMonitor);
+
+/// The callbacks of a resource type, see `ErlNifResourceTypeInit` in `include/erl_nif.h`
+#[repr(C)]
+#[allow(dead_code)]
+pub struct ErlNifResourceTypeInit {
+    dtor: Option<DtorFn>,
+    stop: *const c_void,
+    down: Option<DownFn>,
+    members: c_int,
+    dyncall: *const c_void,
+}
+
+/// A monitor placed on a process by a resource, see `ErlNifMonitor` in `include/erl_nif.h`
+#[repr(C)]
+pub struct ErlNifMonitor {
+    id: u64,
+    _reserved: [u64; 3],
+}
+
+#[derive(Clone, Copy)]
+struct Callbacks {
+    dtor: Option<DtorFn>,
+    down: Option<DownFn>,
+}
+
+/// A type of resource, known to C as `ErlNifResourceType`
+///
+/// Resource types live for the life of the program, as libraries are never unloaded.
+pub struct ResourceType {
+    /// The library which opened this type, or took it over
+    library: RwLock<Arc<Library>>,
+    callbacks: RwLock<Callbacks>,
+}
+
+/// The resource types opened so far, by module and name
+static RESOURCE_TYPES: Mutex<BTreeMap<(Atom, String), &'static ResourceType>> =
+    Mutex::new(BTreeMap::new());
+
+/// The header preceding the data of every resource
+#[repr(C, align(16))]
+struct ResourceHeader {
+    ty: &'static ResourceType,
+    refs: AtomicUsize,
+    size: usize,
+    /// The identifier of the references to this resource, so that they compare equal
+    id: ReferenceId,
+}
+
+/// A reference to a resource held by a term, released when the term is garbage collected
+struct ResourceHandle(NonNull<ResourceHeader>);
+// Resources are reference-counted atomically, and libraries are required to synchronize access
+// to the data of their resources themselves
+unsafe impl Send for ResourceHandle {}
+unsafe impl Sync for ResourceHandle {}
+impl Drop for ResourceHandle {
+    fn drop(&mut self) {
+        unsafe { release(self.0) }
+    }
+}
+
+/// The monitors placed on processes by resources, by the monitored process
+static MONITORS: Mutex<BTreeMap<ProcessId, Vec<ResourceMonitor>>> = Mutex::new(BTreeMap::new());
+
+static NEXT_MONITOR_ID: AtomicU64 = AtomicU64::new(1);
+
+struct ResourceMonitor {
+    id: u64,
+    resource: NonNull<ResourceHeader>,
+}
+// Monitors only hold a weak reference to their resource, see `release`
+unsafe impl Send for ResourceMonitor {}
+
+#[inline]
+unsafe fn header(obj: *mut c_void) -> NonNull<ResourceHeader> {
+    NonNull::new_unchecked(obj.cast::<ResourceHeader>().sub(1))
+}
+
+#[inline]
+unsafe fn data(header: NonNull<ResourceHeader>) -> *mut c_void {
+    header.as_ptr().add(1).cast()
+}
+
+fn layout(size: usize) -> Option<Layout> {
+    let size = mem::size_of::<ResourceHeader>().checked_add(size)?;
+    Layout::from_size_align(size, mem::align_of::<ResourceHeader>()).ok()
+}
+
+/// Drops a reference to the resource with `header`, destroying it if it was the last one
+unsafe fn release(header: NonNull<ResourceHeader>) {
+    let resource = header.as_ref();
+    if resource.refs.fetch_sub(1, Ordering::AcqRel) != 1 {
+        return;
+    }
+    // Monitors don't keep their resource alive, so they are removed before it is freed. A
+    // process exiting at the same time may still see them, but won't use a resource with no
+    // references, see `exited`.
+    MONITORS.lock().unwrap().retain(|_, monitors| {
+        monitors.retain(|monitor| monitor.resource != header);
+        !monitors.is_empty()
+    });
+    let ty = resource.ty;
+    let callbacks = *ty.callbacks.read().unwrap();
+    if let Some(dtor) = callbacks.dtor {
+        let library = ty.library.read().unwrap().clone();
+        let env = Env::new(None, Some(library));
+        dtor(env.as_ptr(), data(header));
+    }
+    let layout = layout(resource.size).unwrap();
+    alloc::dealloc(header.as_ptr().cast(), layout);
+}
+
+/// Called by a scheduler when `process` exits, just before it is freed, to run the `down`
+/// callbacks of the resources monitoring it
+pub fn exited(process: ProcessId) {
+    let mut monitors = MONITORS.lock().unwrap();
+    let Some(triggered) = monitors.remove(&process) else { return };
+    // Each resource is kept alive while its callback runs, unless it is already being destroyed
+    let triggered = triggered
+        .into_iter()
+        .filter(|monitor| {
+            let refs = unsafe { &monitor.resource.as_ref().refs };
+            refs.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                if n == 0 {
+                    None
+                } else {
+                    Some(n + 1)
+                }
+            })
+            .is_ok()
+        })
+        .collect::<Vec<_>>();
+    drop(monitors);
+
+    for monitor in triggered {
+        unsafe {
+            let ty = monitor.resource.as_ref().ty;
+            let callbacks = *ty.callbacks.read().unwrap();
+            if let Some(down) = callbacks.down {
+                let library = ty.library.read().unwrap().clone();
+                let env = Env::new(None, Some(library));
+                let mut pid = ErlNifPid::new(process);
+                let mut mon = ErlNifMonitor {
+                    id: monitor.id,
+                    _reserved: [0; 3],
+                };
+                down(env.as_ptr(), data(monitor.resource), &mut pid, &mut mon);
+            }
+            release(monitor.resource);
+        }
+    }
+}
+
+unsafe fn open_resource_type(
+    env: *mut Env,
+    name: *const c_char,
+    callbacks: Callbacks,
+    flags: c_int,
+    tried: *mut c_int,
+) -> *const ResourceType {
+    // Resource types can only be opened by a library, i.e. from its `load` callback
+    let Some(library) = (*env).library.clone() else { return ptr::null() };
+    let Ok(name) = CStr::from_ptr(name).to_str() else { return ptr::null() };
+    let key = (library.module, name.to_string());
+    let mut types = RESOURCE_TYPES.lock().unwrap();
+    let (ty, result) = match types.get(&key) {
+        Some(ty) if flags & RT_TAKEOVER == RT_TAKEOVER => {
+            *ty.library.write().unwrap() = library;
+            *ty.callbacks.write().unwrap() = callbacks;
+            (*ty as *const ResourceType, RT_TAKEOVER)
+        }
+        None if flags & RT_CREATE == RT_CREATE => {
+            let ty: &'static ResourceType = Box::leak(Box::new(ResourceType {
+                library: RwLock::new(library),
+                callbacks: RwLock::new(callbacks),
+            }));
+            types.insert(key, ty);
+            (ty as *const ResourceType, RT_CREATE)
+        }
+        _ => return ptr::null(),
+    };
+    if !tried.is_null() {
+        tried.write(result);
+    }
+    ty
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_open_resource_type(
+    env: *mut Env,
+    _module_str: *const c_char,
+    name_str: *const c_char,
+    dtor: Option<DtorFn>,
+    flags: c_int,
+    tried: *mut c_int,
+) -> *const ResourceType {
+    let callbacks = Callbacks { dtor, down: None };
+    open_resource_type(env, name_str, callbacks, flags, tried)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_open_resource_type_x(
+    env: *mut Env,
+    name_str: *const c_char,
+    init: *const ErlNifResourceTypeInit,
+    flags: c_int,
+    tried: *mut c_int,
+) -> *const ResourceType {
+    let init = &*init;
+    let callbacks = Callbacks {
+        dtor: init.dtor,
+        down: init.down,
+    };
+    open_resource_type(env, name_str, callbacks, flags, tried)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_alloc_resource(ty: *const ResourceType, size: usize) -> *mut c_void {
+    let Some(layout) = layout(size) else { return ptr::null_mut() };
+    let ptr = alloc::alloc(layout).cast::<ResourceHeader>();
+    let Some(header) = NonNull::new(ptr) else { return ptr::null_mut() };
+    let mut id = ReferenceId::next();
+    id.set_magic();
+    header.as_ptr().write(ResourceHeader {
+        ty: &*ty,
+        refs: AtomicUsize::new(1),
+        size,
+        id,
+    });
+    data(header)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_release_resource(obj: *mut c_void) {
+    release(header(obj));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_keep_resource(obj: *mut c_void) {
+    header(obj).as_ref().refs.fetch_add(1, Ordering::Relaxed);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_make_resource(env: *mut Env, obj: *mut c_void) -> OpaqueTerm {
+    enif_keep_resource(obj);
+    let header = header(obj);
+    let handle: Arc<dyn Any + Send + Sync> = Arc::new(ResourceHandle(header));
+    let reference = Reference::new_magic(header.as_ref().id, handle);
+    Gc::new_in(reference, &*env).unwrap().into()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_get_resource(
+    _env: *mut Env,
+    term: OpaqueTerm,
+    ty: *const ResourceType,
+    objp: *mut *mut c_void,
+) -> c_int {
+    let Term::Reference(reference) = term.into() else { return 0 };
+    let handle = reference
+        .magic()
+        .and_then(|magic| magic.downcast::<ResourceHandle>().ok());
+    let Some(handle) = handle else { return 0 };
+    if !ptr::eq(handle.0.as_ref().ty, ty) {
+        return 0;
+    }
+    objp.write(data(handle.0));
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_sizeof_resource(obj: *mut c_void) -> usize {
+    header(obj).as_ref().size
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_monitor_process(
+    _caller_env: *mut Env,
+    obj: *mut c_void,
+    target_pid: *const ErlNifPid,
+    mon: *mut ErlNifMonitor,
+) -> c_int {
+    let header = header(obj);
+    if header.as_ref().ty.callbacks.read().unwrap().down.is_none() {
+        return -1;
+    }
+    let target = (*target_pid).id();
+    let id = NEXT_MONITOR_ID.fetch_add(1, Ordering::Relaxed);
+    // The monitor is registered before checking that the process is alive, so that it is
+    // either seen by `exited`, or the process is found to be dead here
+    {
+        let mut monitors = MONITORS.lock().unwrap();
+        monitors.entry(target).or_default().push(ResourceMonitor {
+            id,
+            resource: header,
+        });
+    }
+    if registry::get_by_process_id(target).is_none() {
+        let mut monitors = MONITORS.lock().unwrap();
+        if let Some(entries) = monitors.get_mut(&target) {
+            entries.retain(|monitor| monitor.id != id);
+            if entries.is_empty() {
+                monitors.remove(&target);
+            }
+        }
+        return 1;
+    }
+    if !mon.is_null() {
+        mon.write(ErlNifMonitor {
+            id,
+            _reserved: [0; 3],
+        });
+    }
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_demonitor_process(
+    _caller_env: *mut Env,
+    obj: *mut c_void,
+    mon: *const ErlNifMonitor,
+) -> c_int {
+    let header = header(obj);
+    let id = (*mon).id;
+    let mut monitors = MONITORS.lock().unwrap();
+    let mut found = None;
+    for (process, entries) in monitors.iter_mut() {
+        let before = entries.len();
+        entries.retain(|monitor| monitor.id != id || monitor.resource != header);
+        if entries.len() != before {
+            found = Some((*process, entries.is_empty()));
+            break;
+        }
+    }
+    match found {
+        Some((process, empty)) => {
+            if empty {
+                monitors.remove(&process);
+            }
+            0
+        }
+        None => 1,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_compare_monitors(
+    monitor1: *const ErlNifMonitor,
+    monitor2: *const ErlNifMonitor,
+) -> c_int {
+    match (*monitor1).id.cmp(&(*monitor2).id) {
+        std::cmp::Ordering::Less => -1,
+        std::cmp::Ordering::Equal => 0,
+        std::cmp::Ordering::Greater => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::AtomicPtr;
+
+    static DESTROYED: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn dtor(_env: *mut Env, obj: *mut c_void) {
+        assert_eq!(obj.cast::<u64>().read(), 0xdeadbeef);
+        DESTROYED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns a library standing in for one loaded by a module, as resource types are opened by
+    /// the `load` callback of a library
+    fn library() -> Arc<Library> {
+        #[cfg(unix)]
+        let handle = libloading::os::unix::Library::this().into();
+        #[cfg(windows)]
+        let handle = libloading::os::windows::Library::this().unwrap().into();
+        Arc::new(Library {
+            module: Atom::try_from("resource_test").unwrap(),
+            priv_data: AtomicPtr::new(ptr::null_mut()),
+            _handle: handle,
+        })
+    }
+
+    #[test]
+    fn resource_destructor_test() {
+        let env = Env::new(None, Some(library()));
+        let init = ErlNifResourceTypeInit {
+            dtor: Some(dtor),
+            stop: ptr::null(),
+            down: None,
+            members: 1,
+            dyncall: ptr::null(),
+        };
+        unsafe {
+            let mut tried = 0;
+            let ty = enif_open_resource_type_x(
+                env.as_ptr(),
+                b"counter\0".as_ptr().cast(),
+                &init,
+                RT_CREATE,
+                &mut tried,
+            );
+            assert!(!ty.is_null());
+            assert_eq!(tried, RT_CREATE);
+
+            let obj = enif_alloc_resource(ty, mem::size_of::<u64>());
+            assert!(!obj.is_null());
+            assert_eq!(obj as usize % mem::align_of::<ResourceHeader>(), 0);
+            assert_eq!(enif_sizeof_resource(obj), mem::size_of::<u64>());
+            obj.cast::<u64>().write(0xdeadbeef);
+
+            // The destructor only runs once the last reference is released
+            enif_keep_resource(obj);
+            enif_release_resource(obj);
+            assert_eq!(DESTROYED.load(Ordering::SeqCst), 0);
+            enif_release_resource(obj);
+            assert_eq!(DESTROYED.load(Ordering::SeqCst), 1);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Support for NIFs which run on dirty schedulers, or schedule other functions to run in their
+//! place with `enif_schedule_nif`
+//!
+//! In both cases the calling process awaits a generator which runs the scheduled function once
+//! the process is next scheduled, on a dirty scheduler of the requested kind if any.
+use std::ffi::{c_char, c_int, c_uint};
+use std::slice;
+use std::sync::Arc;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::{ContinuationResult, Generator, ProcessId, ProcessLock};
+use firefly_rt::term::*;
+
+use crate::dirty::{self, DirtyKind};
+
+use super::ffi::{self, badarg};
+use super::{Env, Library, NifFn, Outcome, DIRTY_JOB_CPU_BOUND, DIRTY_JOB_IO_BOUND};
+
+/// A call to a NIF which is to be run in place of the calling NIF, or on a dirty scheduler
+pub(super) struct Scheduled {
+    fun: NifFn,
+    flags: c_uint,
+    /// The environment the call runs in, which owns copies of its arguments
+    env: Env,
+    args: Vec<OpaqueTerm>,
+}
+impl Scheduled {
+    pub(super) fn new(
+        process: ProcessId,
+        library: Option<Arc<Library>>,
+        fun: NifFn,
+        flags: c_uint,
+        args: &[OpaqueTerm],
+    ) -> Self {
+        // The arguments are copied, as the process may be garbage collected before the call runs
+        let env = Env::new(Some(process), library);
+        let args = args
+            .iter()
+            .map(|arg| env.copy(*arg).unwrap())
+            .collect::<Vec<_>>();
+        Self {
+            fun,
+            flags,
+            env,
+            args,
+        }
+    }
+
+    /// Returns the kind of dirty scheduler this call must run on, if any
+    fn kind(&self) -> Option<DirtyKind> {
+        match self.flags {
+            DIRTY_JOB_CPU_BOUND => Some(DirtyKind::Cpu),
+            DIRTY_JOB_IO_BOUND => Some(DirtyKind::Io),
+            _ => None,
+        }
+    }
+
+    /// Runs this call on the current thread, returning the environment it ran in and its result
+    fn run(self) -> (Env, OpaqueTerm) {
+        let thread_type = match self.kind() {
+            Some(DirtyKind::Cpu) => ffi::THR_DIRTY_CPU_SCHEDULER,
+            Some(DirtyKind::Io) => ffi::THR_DIRTY_IO_SCHEDULER,
+            None => ffi::THR_NORMAL_SCHEDULER,
+        };
+        let result = unsafe { self.env.call(self.fun, &self.args, thread_type) };
+        (self.env, result)
+    }
+}
+
+/// Suspends the calling process until `call`, and any calls it schedules in turn, complete
+pub(super) fn start(process: &mut ProcessLock, call: Scheduled) -> ErlangResult {
+    let kind = call.kind();
+    let state = Box::into_raw(Box::new(Some(call)));
+    let generator = Generator::new(resume, state.cast());
+    match kind {
+        Some(kind) => dirty::schedule(process, kind, generator),
+        None => ErlangResult::Await(Box::new(generator)),
+    }
+}
+
+/// The continuation used by `start` to run scheduled calls
+///
+/// Each call runs on the type of scheduler it was scheduled for, so when a call schedules one for
+/// another type of scheduler, the process is migrated before it is resumed again.
+extern "C-unwind" fn resume(process: &mut ProcessLock, state: *mut ()) -> ContinuationResult {
+    let pending = unsafe { &mut *state.cast::<Option<Scheduled>>() };
+    let (env, result) = pending.take().unwrap().run();
+    let outcome = env.settle(process, result);
+    if let Outcome::Schedule(call) = outcome {
+        match call.kind() {
+            Some(kind) => dirty::enter(process, kind),
+            None => dirty::leave(process),
+        }
+        *pending = Some(call);
+        return ContinuationResult::Yield(resume, ());
+    }
+    drop(unsafe { Box::from_raw(state.cast::<Option<Scheduled>>()) });
+    match outcome {
+        Outcome::Return(result) => ContinuationResult::Complete(Ok(result)),
+        _ => ContinuationResult::Complete(Err(())),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_schedule_nif(
+    caller_env: *mut Env,
+    _fun_name: *const c_char,
+    flags: c_int,
+    fp: NifFn,
+    argc: c_int,
+    argv: *const OpaqueTerm,
+) -> OpaqueTerm {
+    let env = &*caller_env;
+    // Only a NIF called by a process can schedule another in its place
+    let Some(process) = env.process else { return badarg(env) };
+    let flags = match flags as c_uint {
+        flags @ (0 | DIRTY_JOB_CPU_BOUND | DIRTY_JOB_IO_BOUND) => flags,
+        _ => return badarg(env),
+    };
+    let args = match argc {
+        0 => &[][..],
+        n if n > 0 => slice::from_raw_parts(argv, n as usize),
+        _ => return badarg(env),
+    };
+    let call = Scheduled::new(process, env.library.clone(), fp, flags, args);
+    *env.scheduled.borrow_mut() = Some(call);
+    // The value returned by the calling NIF is ignored in favor of the scheduled call
+    OpaqueTerm::NONE
+}
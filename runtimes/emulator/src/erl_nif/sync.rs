@@ -0,0 +1,126 @@
+//! Thread synchronization primitives for native libraries
+//!
+//! These are thin wrappers around the locks in `firefly_system::sync`. Since the C API has no
+//! notion of guards, locks are acquired by forgetting the guard, and released by force.
+use std::ffi::{c_char, c_int};
+use std::mem;
+
+use firefly_system::sync::{Condvar, Mutex, RwLock};
+
+pub struct ErlNifMutex(Mutex<()>);
+
+pub struct ErlNifRWLock(RwLock<()>);
+
+pub struct ErlNifCond(Condvar);
+
+#[no_mangle]
+pub extern "C" fn enif_mutex_create(_name: *const c_char) -> *mut ErlNifMutex {
+    Box::into_raw(Box::new(ErlNifMutex(Mutex::new(()))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_mutex_destroy(mtx: *mut ErlNifMutex) {
+    drop(Box::from_raw(mtx));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_mutex_trylock(mtx: *mut ErlNifMutex) -> c_int {
+    match (*mtx).0.try_lock() {
+        Some(guard) => {
+            mem::forget(guard);
+            0
+        }
+        None => libc::EBUSY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_mutex_lock(mtx: *mut ErlNifMutex) {
+    mem::forget((*mtx).0.lock());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_mutex_unlock(mtx: *mut ErlNifMutex) {
+    (*mtx).0.force_unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn enif_rwlock_create(_name: *const c_char) -> *mut ErlNifRWLock {
+    Box::into_raw(Box::new(ErlNifRWLock(RwLock::new(()))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_rwlock_destroy(rwlck: *mut ErlNifRWLock) {
+    drop(Box::from_raw(rwlck));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_rwlock_tryrlock(rwlck: *mut ErlNifRWLock) -> c_int {
+    match (*rwlck).0.try_read() {
+        Some(guard) => {
+            mem::forget(guard);
+            0
+        }
+        None => libc::EBUSY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_rwlock_rlock(rwlck: *mut ErlNifRWLock) {
+    mem::forget((*rwlck).0.read());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_rwlock_runlock(rwlck: *mut ErlNifRWLock) {
+    (*rwlck).0.force_unlock_read();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_rwlock_tryrwlock(rwlck: *mut ErlNifRWLock) -> c_int {
+    match (*rwlck).0.try_write() {
+        Some(guard) => {
+            mem::forget(guard);
+            0
+        }
+        None => libc::EBUSY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_rwlock_rwlock(rwlck: *mut ErlNifRWLock) {
+    mem::forget((*rwlck).0.write());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_rwlock_rwunlock(rwlck: *mut ErlNifRWLock) {
+    (*rwlck).0.force_unlock_write();
+}
+
+#[no_mangle]
+pub extern "C" fn enif_cond_create(_name: *const c_char) -> *mut ErlNifCond {
+    Box::into_raw(Box::new(ErlNifCond(Condvar::new())))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_cond_destroy(cnd: *mut ErlNifCond) {
+    drop(Box::from_raw(cnd));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_cond_signal(cnd: *mut ErlNifCond) {
+    (*cnd).0.notify_one();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_cond_broadcast(cnd: *mut ErlNifCond) {
+    (*cnd).0.notify_all();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn enif_cond_wait(cnd: *mut ErlNifCond, mtx: *mut ErlNifMutex) {
+    // The caller holds the lock, so we temporarily recover its guard to wait on the condition,
+    // and forget it again once the lock has been reacquired
+    let mut guard = (*mtx).0.make_guard_unchecked();
+    (*cnd).0.wait(&mut guard);
+    mem::forget(guard);
+}
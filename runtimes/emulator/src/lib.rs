@@ -15,14 +15,21 @@
 #![feature(slice_as_chunks)]
 #![feature(local_key_cell_methods)]
 #![feature(box_into_inner)]
+#![feature(c_variadic)]
 
 extern crate firefly_crt;
 
 mod bifs;
+mod debugger;
+mod dirty;
+pub mod embed;
 mod emulator;
+mod erl_nif;
 mod nifs;
+mod profiler;
 mod queue;
 mod sys;
+mod time;
 mod unique;
 
 use std::convert::Infallible;
@@ -40,6 +47,7 @@ use firefly_rt::services::{self, distribution::NoDistribution};
 use firefly_rt::term::{atom::GlobalAtomTable, Atom};
 
 use self::emulator::{Emulator, EmulatorError};
+use self::time::TimeWarpMode;
 
 const NUM_SCHEDULERS: usize = 1;
 
@@ -71,35 +79,12 @@ macro_rules! unwrap_or_badarg {
 pub fn main() -> i32 {
     use std::process::Termination;
 
-    let mut builder = env_logger::Builder::from_env("ERTS_TRACE");
-    builder.format_indent(Some(2));
-    if let Ok(precision) = env::var("ERTS_TRACE_WITH_TIME") {
-        match precision.as_str() {
-            "s" => builder.format_timestamp_secs(),
-            "ms" => builder.format_timestamp_millis(),
-            "us" => builder.format_timestamp_micros(),
-            "ns" => builder.format_timestamp_nanos(),
-            other => {
-                eprintln!("Ignoring invalid ERTS_TRACE_WITH_TIME value, expected one of [s, ms, us, ns], got '{}'. Using 'ms' instead..", other);
-                builder.format_timestamp_millis()
-            }
-        };
-    } else {
-        builder.format_timestamp(None);
-    }
-    builder.init();
+    logger().init();
 
     // Load bytecode first, since if it fails there is no point in going further
     let code = load_bytecode().expect("failed to load bytecode");
 
-    // Initialize the global environment
-    sys::env::init(std::env::args_os()).unwrap();
-
-    // Initialize global uniqueness data
-    self::unique::init(NUM_SCHEDULERS, 0, 0);
-
-    // Initialize the distribution service
-    services::distribution::init(NoDistribution::new());
+    init(&code, NUM_SCHEDULERS);
 
     // Create a new multi-threaded async runtime
     let runtime = tokio::runtime::Builder::new_multi_thread()
@@ -113,19 +98,23 @@ pub fn main() -> i32 {
     }
     // Set up the system dispatcher
     runtime.spawn(sys::dispatcher::start());
-    // Get a clone of the async runtime handle to give to each scheduler
-    let handle = runtime.handle().clone();
     // Get the global work-stealing task queue shared by the schedulers
     let injector = Arc::new(Injector::new());
+    // If requested, wait for a debugger to attach before starting any processes
+    if let Ok(addr) = env::var("ERTS_DEBUGGER") {
+        if let Err(err) = debugger::start(&addr, code.clone(), injector.clone()) {
+            eprintln!("Unable to start debugger on '{}': {}", addr, err);
+            return ExitCode::FAILURE.report().to_i32();
+        }
+    }
     // Spawn a task for each instance of emulator acting as a scheduler
     let mut handles = Vec::with_capacity(NUM_SCHEDULERS);
     for i in 0..NUM_SCHEDULERS {
-        let emu_handle = handle.clone();
         let emu_injector = injector.clone();
         let emu_code = code.clone();
         handles.push(runtime.spawn_blocking(move || {
             let emulator = scheduler::create(move |id| {
-                Ok::<_, Infallible>(Emulator::new(id, emu_code, emu_injector, emu_handle))
+                Ok::<_, Infallible>(Emulator::new(id, emu_code, emu_injector))
             })
             .unwrap();
             let spawn_init = i == 0;
@@ -145,12 +134,14 @@ pub fn main() -> i32 {
             Ok(result) => match result {
                 Ok(_) => continue,
                 Err(EmulatorError::Halt(0)) => {
+                    write_profile();
                     // Give some time for any outstanding background tasks to clean up
                     runtime.shutdown_timeout(Duration::from_millis(50));
 
                     return ExitCode::SUCCESS.report().to_i32();
                 }
                 Err(EmulatorError::Halt(n)) => {
+                    write_profile();
                     // Give some time for any outstanding background tasks to clean up
                     runtime.shutdown_timeout(Duration::from_secs(5));
 
@@ -171,7 +162,80 @@ pub fn main() -> i32 {
     ExitCode::SUCCESS.report().to_i32()
 }
 
+/// Returns a logger configured from the `ERTS_TRACE` and `ERTS_TRACE_WITH_TIME` environment
+/// variables
+fn logger() -> env_logger::Builder {
+    let mut builder = env_logger::Builder::from_env("ERTS_TRACE");
+    builder.format_indent(Some(2));
+    if let Ok(precision) = env::var("ERTS_TRACE_WITH_TIME") {
+        match precision.as_str() {
+            "s" => builder.format_timestamp_secs(),
+            "ms" => builder.format_timestamp_millis(),
+            "us" => builder.format_timestamp_micros(),
+            "ns" => builder.format_timestamp_nanos(),
+            other => {
+                eprintln!("Ignoring invalid ERTS_TRACE_WITH_TIME value, expected one of [s, ms, us, ns], got '{}'. Using 'ms' instead..", other);
+                builder.format_timestamp_millis()
+            }
+        };
+    } else {
+        builder.format_timestamp(None);
+    }
+    builder
+}
+
+/// Performs the one-time initialization of global runtime state required before any schedulers
+/// are started
+fn init(code: &Arc<ByteCode<Atom, GlobalAtomTable>>, num_schedulers: usize) {
+    // Initialize the global environment
+    sys::env::init(std::env::args_os()).unwrap();
+
+    // Initialize global uniqueness data
+    self::unique::init(num_schedulers, 0, 0);
+
+    // Initialize Erlang time using the selected time warp mode
+    let time_warp_mode = match env::var("ERTS_TIME_WARP_MODE") {
+        Ok(mode) => mode.parse().unwrap_or_else(|_| {
+            eprintln!("Ignoring invalid ERTS_TIME_WARP_MODE value, expected one of [no_time_warp, single_time_warp, multi_time_warp], got '{}'. Using 'no_time_warp' instead..", mode);
+            TimeWarpMode::NoTimeWarp
+        }),
+        Err(_) => TimeWarpMode::NoTimeWarp,
+    };
+    self::time::init(time_warp_mode);
+
+    // Start the dirty schedulers
+    self::dirty::init(num_schedulers);
+
+    // Start profiling immediately, if requested
+    profiler::init(code.clone());
+    match profiler::Options::from_env() {
+        Ok(Some(options)) => {
+            profiler::start(options);
+        }
+        Ok(None) => (),
+        Err(err) => eprintln!("Ignoring invalid ERTS_PROFILE value, {}", err),
+    }
+
+    // Initialize the distribution service
+    services::distribution::init(NoDistribution::new());
+}
+
+/// Writes the results of profiling started at boot, or not yet stopped from Erlang, if any
+fn write_profile() {
+    if let Some(report) = profiler::stop() {
+        if let Err(err) = report.write() {
+            eprintln!("Unable to write profile: {}", err);
+        }
+    }
+}
+
 fn load_bytecode() -> Result<Arc<ByteCode<Atom, GlobalAtomTable>>, ReadError<GlobalAtomTable>> {
+    let reader = BytecodeReader::new(bytecode());
+    reader.read().map(|code| Arc::new(code))
+}
+
+/// Returns the raw bytecode linked into the executable
+fn bytecode() -> &'static [u8] {
     use core::slice;
 
     extern "C" {
@@ -182,8 +246,5 @@ fn load_bytecode() -> Result<Arc<ByteCode<Atom, GlobalAtomTable>>, ReadError<Glo
         static BYTECODE_DATA: u8;
     }
 
-    let bytes = unsafe { slice::from_raw_parts(&BYTECODE_DATA, BYTECODE_LEN) };
-
-    let reader = BytecodeReader::new(bytes);
-    reader.read().map(|code| Arc::new(code))
+    unsafe { slice::from_raw_parts(&BYTECODE_DATA, BYTECODE_LEN) }
 }
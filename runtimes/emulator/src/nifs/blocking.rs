@@ -0,0 +1,76 @@
+use std::alloc::AllocError;
+use std::panic::{self, AssertUnwindSafe};
+
+use firefly_rt::error::ExceptionFlags;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::garbage_collect;
+use firefly_rt::process::{ContinuationResult, Generator, ProcessLock};
+use firefly_rt::term::*;
+
+use crate::dirty::{self, DirtyKind};
+
+/// Converts the result of a blocking operation to a term on the given process heap
+pub type ToTerm<T> = fn(&T, &ProcessLock) -> Result<OpaqueTerm, AllocError>;
+
+/// The state of a process awaiting the result of a blocking operation
+struct Pending<T, F> {
+    /// The operation, until it has run
+    op: Option<F>,
+    /// The result of the operation, or `Err` if it panicked
+    result: Option<Result<T, ()>>,
+    to_term: ToTerm<T>,
+}
+
+/// Runs `op` on a dirty I/O scheduler, and suspends the calling process until it completes,
+/// returning the result converted to a term by `to_term`
+///
+/// The result is converted once the process is back on a normal scheduler, so `to_term` is free
+/// to use the state of the current scheduler.
+///
+/// If `op` panics, the calling process raises an error with reason `eio`.
+pub fn offload<T, F>(process: &mut ProcessLock, op: F, to_term: ToTerm<T>) -> ErlangResult
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let state = Box::into_raw(Box::new(Pending {
+        op: Some(op),
+        result: None,
+        to_term,
+    }));
+    let generator = Generator::new(poll::<T, F>, state.cast());
+    dirty::schedule(process, DirtyKind::Io, generator)
+}
+
+/// The continuation used by `offload`, which runs the operation when first resumed on a dirty
+/// scheduler, and converts its result when next resumed on a normal scheduler
+extern "C-unwind" fn poll<T, F>(process: &mut ProcessLock, state: *mut ()) -> ContinuationResult
+where
+    F: FnOnce() -> T,
+{
+    let pending = state.cast::<Pending<T, F>>();
+    if let Some(op) = unsafe { (*pending).op.take() } {
+        let result = panic::catch_unwind(AssertUnwindSafe(op)).map_err(|_| ());
+        unsafe {
+            (*pending).result = Some(result);
+        }
+        dirty::leave(process);
+        return ContinuationResult::Yield(poll::<T, F>, ());
+    }
+    let pending = unsafe { *Box::from_raw(pending) };
+    let Ok(result) = pending.result.unwrap() else {
+        // The operation panicked before it could produce a result
+        process.exception_info.flags = ExceptionFlags::ERROR;
+        process.exception_info.reason = atoms::Eio.into();
+        process.exception_info.value = atoms::Eio.into();
+        process.exception_info.args = None;
+        process.exception_info.trace = None;
+        return ContinuationResult::Complete(Err(()));
+    };
+    loop {
+        match (pending.to_term)(&result, process) {
+            Ok(term) => return ContinuationResult::Complete(Ok(term)),
+            Err(_) => assert!(garbage_collect(process, Default::default()).is_ok()),
+        }
+    }
+}
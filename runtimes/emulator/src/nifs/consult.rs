@@ -0,0 +1,544 @@
+//! A reader for files containing Erlang terms, as used by `file:consult/1`
+//!
+//! Only literal terms are supported, i.e. there are no expressions, records or macros, which is
+//! sufficient for configuration files such as `sys.config`.
+use std::alloc::AllocError;
+use std::fmt;
+
+use firefly_number::Int;
+use firefly_rt::gc::Gc;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+
+use crate::bifs::erlang::list_to_term;
+
+/// A term read from a file, which can be converted to a term on a process heap with [`to_term`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Atom(String),
+    Integer(Int),
+    Float(f64),
+    /// A string literal, i.e. a list of characters
+    String(String),
+    Binary(Vec<u8>),
+    Tuple(Vec<Value>),
+    /// A list, with its tail if improper
+    List(Vec<Value>, Option<Box<Value>>),
+    Map(Vec<(Value, Value)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.line, &self.message)
+    }
+}
+
+/// Parses all of the terms in `source`, each of which must be terminated by a `.`
+pub fn parse(source: &str) -> Result<Vec<Value>, ParseError> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    let mut terms = vec![];
+    loop {
+        parser.skip_whitespace();
+        if parser.peek().is_none() {
+            break;
+        }
+        terms.push(parser.parse_term()?);
+        parser.skip_whitespace();
+        parser.expect('.')?;
+        // The terminating dot must be followed by whitespace, a comment, or the end of input
+        match parser.peek() {
+            None | Some('%') => (),
+            Some(c) if c.is_whitespace() => (),
+            Some(c) => return Err(parser.error(format!("unexpected '{}' after '.'", c))),
+        }
+    }
+    Ok(terms)
+}
+
+/// Converts `value` to a term on `heap`
+pub fn to_term(value: &Value, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    match value {
+        Value::Atom(name) => Ok(Atom::str_to_term(name)),
+        Value::Integer(Int::Small(i)) => Ok((*i).try_into().unwrap()),
+        Value::Integer(Int::Big(i)) => Ok(Gc::new_in(BigInt::from(i.clone()), heap)?.into()),
+        Value::Float(f) => Ok((*f).into()),
+        Value::String(s) => Ok(Cons::charlist_from_str(s, heap)?
+            .map(|list| list.into())
+            .unwrap_or(OpaqueTerm::NIL)),
+        Value::Binary(bytes) if bytes.len() > BinaryData::MAX_HEAP_BYTES => {
+            Ok(BinaryData::from_bytes(bytes).into())
+        }
+        Value::Binary(bytes) => Ok(BinaryData::from_small_bytes(bytes, heap)?.into()),
+        Value::Tuple(elements) => {
+            let elements = elements
+                .iter()
+                .map(|element| to_term(element, heap))
+                .try_collect::<Vec<_>>()?;
+            Ok(Tuple::from_slice(&elements, heap)?.into())
+        }
+        Value::List(elements, None) => {
+            let elements = elements
+                .iter()
+                .map(|element| to_term(element, heap))
+                .try_collect::<Vec<_>>()?;
+            list_to_term(&elements, heap)
+        }
+        Value::List(elements, Some(tail)) => {
+            let mut list = to_term(tail, heap)?;
+            for element in elements.iter().rev() {
+                let head = to_term(element, heap)?;
+                list = Cons::new_in(Cons::cons(head.into(), list.into()), heap)?.into();
+            }
+            Ok(list)
+        }
+        Value::Map(pairs) => {
+            let pairs = pairs
+                .iter()
+                .map(|(k, v)| Ok::<_, AllocError>((to_term(k, heap)?, to_term(v, heap)?)))
+                .try_collect::<Vec<_>>()?;
+            // The parser rejects maps which are too large to be represented, so the only possible
+            // error here is allocation failure
+            let map: Term = Map::from_iter(pairs.into_iter(), heap)
+                .map_err(|_| AllocError)?
+                .into();
+            Ok(map.into())
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+impl Parser {
+    fn error(&self, message: String) -> ParseError {
+        ParseError {
+            line: self.line,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        self.chars.get(self.pos + 1).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn next_or_eof(&mut self) -> Result<char, ParseError> {
+        self.next()
+            .ok_or_else(|| self.error("unexpected end of input".to_string()))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(format!("expected '{}', got '{}'", expected, c))),
+            None => Err(self.error(format!("expected '{}', got end of input", expected))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '%' {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.next();
+                }
+            } else if c.is_whitespace() {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parses a sequence of terms separated by commas, up to and including `close`
+    fn parse_sequence(&mut self, close: char) -> Result<Vec<Value>, ParseError> {
+        let mut elements = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.next();
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.parse_term()?);
+            self.skip_whitespace();
+            match self.next_or_eof()? {
+                ',' => continue,
+                c if c == close => return Ok(elements),
+                c => return Err(self.error(format!("expected ',' or '{}', got '{}'", close, c))),
+            }
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("unexpected end of input".to_string())),
+            Some('{') => {
+                self.next();
+                Ok(Value::Tuple(self.parse_sequence('}')?))
+            }
+            Some('[') => {
+                self.next();
+                self.parse_list()
+            }
+            Some('#') => {
+                self.next();
+                self.expect('{')?;
+                self.parse_map()
+            }
+            Some('<') => {
+                self.next();
+                self.expect('<')?;
+                self.parse_binary()
+            }
+            Some('"') => Ok(Value::String(self.parse_strings()?)),
+            Some('\'') => {
+                self.next();
+                Ok(Value::Atom(self.parse_quoted('\'')?))
+            }
+            Some('$') => {
+                self.next();
+                let c = match self.next_or_eof()? {
+                    '\\' => self.parse_escape()?,
+                    c => c,
+                };
+                Ok(Value::Integer(Int::from(c)))
+            }
+            Some('-') | Some('+') => {
+                let negative = self.next() == Some('-');
+                match self.peek() {
+                    Some(c) if c.is_ascii_digit() => self.parse_number(negative),
+                    _ => Err(self.error("expected a number after sign".to_string())),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => self.parse_number(false),
+            Some(c) if c.is_lowercase() => {
+                let mut name = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '@' {
+                        name.push(c);
+                        self.next();
+                    } else {
+                        break;
+                    }
+                }
+                Ok(Value::Atom(name))
+            }
+            Some(c) if c.is_uppercase() || c == '_' => {
+                Err(self.error("variables are not allowed in terms".to_string()))
+            }
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Value, ParseError> {
+        let mut elements = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Value::List(elements, None));
+        }
+        loop {
+            elements.push(self.parse_term()?);
+            self.skip_whitespace();
+            match self.next_or_eof()? {
+                ',' => continue,
+                ']' => return Ok(Value::List(elements, None)),
+                '|' => {
+                    let tail = self.parse_term()?;
+                    self.skip_whitespace();
+                    self.expect(']')?;
+                    // Normalize proper lists written with an explicit tail
+                    return Ok(match tail {
+                        Value::List(mut rest, tail) => {
+                            elements.append(&mut rest);
+                            Value::List(elements, tail)
+                        }
+                        Value::String(s) if s.is_empty() => Value::List(elements, None),
+                        tail => Value::List(elements, Some(Box::new(tail))),
+                    });
+                }
+                c => return Err(self.error(format!("expected ',', '|' or ']', got '{}'", c))),
+            }
+        }
+    }
+
+    fn parse_map(&mut self) -> Result<Value, ParseError> {
+        let mut pairs = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Value::Map(pairs));
+        }
+        loop {
+            let key = self.parse_term()?;
+            self.skip_whitespace();
+            self.expect('=')?;
+            self.expect('>')?;
+            let value = self.parse_term()?;
+            pairs.push((key, value));
+            self.skip_whitespace();
+            match self.next_or_eof()? {
+                ',' => continue,
+                '}' => break,
+                c => return Err(self.error(format!("expected ',' or '}}', got '{}'", c))),
+            }
+        }
+        if pairs.len() > SMALL_MAP_LIMIT {
+            return Err(self.error(format!(
+                "maps with more than {} keys are not supported",
+                SMALL_MAP_LIMIT
+            )));
+        }
+        Ok(Value::Map(pairs))
+    }
+
+    fn parse_binary(&mut self) -> Result<Value, ParseError> {
+        let mut bytes = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('>') {
+            self.next();
+            self.expect('>')?;
+            return Ok(Value::Binary(bytes));
+        }
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('"') => bytes.extend_from_slice(self.parse_strings()?.as_bytes()),
+                _ => match self.parse_term()? {
+                    Value::Integer(i) => match i.to_usize().filter(|i| *i < 256) {
+                        Some(byte) => bytes.push(byte as u8),
+                        None => return Err(self.error("binary segment out of range".to_string())),
+                    },
+                    _ => {
+                        return Err(
+                            self.error("binary segments must be strings or integers".to_string())
+                        )
+                    }
+                },
+            }
+            self.skip_whitespace();
+            match self.next_or_eof()? {
+                ',' => continue,
+                '>' => {
+                    self.expect('>')?;
+                    return Ok(Value::Binary(bytes));
+                }
+                c => return Err(self.error(format!("expected ',' or '>>', got '{}'", c))),
+            }
+        }
+    }
+
+    /// Parses one or more adjacent string literals, which are concatenated
+    fn parse_strings(&mut self) -> Result<String, ParseError> {
+        let mut s = String::new();
+        while self.peek() == Some('"') {
+            self.next();
+            s.push_str(&self.parse_quoted('"')?);
+            self.skip_whitespace();
+        }
+        Ok(s)
+    }
+
+    /// Parses the remainder of a string or quoted atom, after the opening quote
+    fn parse_quoted(&mut self, quote: char) -> Result<String, ParseError> {
+        let mut s = String::new();
+        loop {
+            match self.next_or_eof()? {
+                c if c == quote => return Ok(s),
+                '\\' => s.push(self.parse_escape()?),
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Parses an escape sequence, after the backslash
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let c = match self.next_or_eof()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            's' => ' ',
+            'b' => '\x08',
+            'd' => '\x7f',
+            'e' => '\x1b',
+            'f' => '\x0c',
+            'v' => '\x0b',
+            '^' => {
+                let c = self.next_or_eof()?;
+                char::from_u32(c as u32 & 0x1f).unwrap()
+            }
+            'x' => {
+                let mut digits = String::new();
+                if self.peek() == Some('{') {
+                    self.next();
+                    loop {
+                        match self.next_or_eof()? {
+                            '}' => break,
+                            c => digits.push(c),
+                        }
+                    }
+                } else {
+                    for _ in 0..2 {
+                        digits.push(self.next_or_eof()?);
+                    }
+                }
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("invalid hexadecimal escape".to_string()))?
+            }
+            c @ '0'..='7' => {
+                let mut value = c.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match self.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            self.next();
+                            value = value * 8 + digit;
+                        }
+                        None => break,
+                    }
+                }
+                char::from_u32(value).unwrap()
+            }
+            c => c,
+        };
+        Ok(c)
+    }
+
+    fn parse_digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_digit(radix) {
+                digits.push(c);
+            } else if c != '_' || !self.peek_next().map(|c| c.is_digit(radix)).unwrap_or(false) {
+                break;
+            }
+            self.next();
+        }
+        digits
+    }
+
+    fn parse_number(&mut self, negative: bool) -> Result<Value, ParseError> {
+        let sign = if negative { "-" } else { "" };
+        let digits = self.parse_digits(10);
+
+        // Integers with an explicit base, e.g. 16#ff
+        if self.peek() == Some('#') {
+            let radix = digits
+                .parse::<u32>()
+                .ok()
+                .filter(|radix| (2..=36).contains(radix))
+                .ok_or_else(|| self.error("invalid base".to_string()))?;
+            self.next();
+            let digits = self.parse_digits(radix);
+            return Int::from_string_radix(&format!("{}{}", sign, digits), radix)
+                .map(Value::Integer)
+                .ok_or_else(|| self.error("invalid integer".to_string()));
+        }
+
+        let is_fraction = self.peek() == Some('.')
+            && self
+                .peek_next()
+                .map(|c| c.is_ascii_digit())
+                .unwrap_or(false);
+        if !is_fraction {
+            return Int::from_string_radix(&format!("{}{}", sign, digits), 10)
+                .map(Value::Integer)
+                .ok_or_else(|| self.error("invalid integer".to_string()));
+        }
+
+        self.next();
+        let mut float = format!("{}{}.{}", sign, digits, self.parse_digits(10));
+        if let Some(e @ ('e' | 'E')) = self.peek() {
+            self.next();
+            float.push(e);
+            if let Some(s @ ('-' | '+')) = self.peek() {
+                self.next();
+                float.push(s);
+            }
+            float.push_str(&self.parse_digits(10));
+        }
+        float
+            .parse::<f64>()
+            .map(Value::Float)
+            .map_err(|_| self.error("invalid float".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn consult_test() {
+        let source = r#"
+            %% A comment
+            [{kernel, [{logger_level, info}]},
+             {my_app, [{port, 8080}, {name, "my app\n"}, {'Quoted', <<"bin", 0>>},
+                       {ratio, -1.5e3}, {flags, 16#ff}, {opts, #{a => [1 | 2]}}]}].
+            $a.
+        "#;
+        let terms = parse(source).unwrap();
+        assert_eq!(terms.len(), 2);
+        let Value::List(apps, None) = &terms[0] else { panic!("expected a list") };
+        let Value::Tuple(app) = &apps[1] else { panic!("expected a tuple") };
+        assert_eq!(app[0], Value::Atom("my_app".to_string()));
+        let Value::List(env, None) = &app[1] else { panic!("expected a list") };
+        assert_eq!(
+            env[1],
+            Value::Tuple(vec![
+                Value::Atom("name".to_string()),
+                Value::String("my app\n".to_string())
+            ])
+        );
+        assert_eq!(
+            env[2],
+            Value::Tuple(vec![
+                Value::Atom("Quoted".to_string()),
+                Value::Binary(b"bin\0".to_vec())
+            ])
+        );
+        assert_eq!(
+            env[3],
+            Value::Tuple(vec![Value::Atom("ratio".to_string()), Value::Float(-1500.0)])
+        );
+        assert_eq!(
+            env[4],
+            Value::Tuple(vec![
+                Value::Atom("flags".to_string()),
+                Value::Integer(Int::new(255))
+            ])
+        );
+        assert_eq!(terms[1], Value::Integer(Int::new(97)));
+    }
+
+    #[test]
+    fn consult_error_test() {
+        assert_eq!(parse("[a, b]").unwrap_err().message, "expected '.', got end of input");
+        assert_eq!(parse("\n{X}.").unwrap_err().line, 2);
+        assert!(parse("[a | b].").is_ok());
+    }
+}
@@ -1,8 +1,683 @@
+use std::alloc::AllocError;
+use std::env;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use firefly_binary::Bitstring;
 use firefly_rt::function::ErlangResult;
+use firefly_rt::gc::Gc;
 use firefly_rt::process::ProcessLock;
+use firefly_rt::scheduler::Scheduler;
 use firefly_rt::term::*;
 
+use crate::badarg;
+use crate::bifs::erlang::{alloc_result, datetime_to_term, int_to_term, list_to_term};
+use crate::emulator::current_scheduler;
+use crate::nifs::{blocking, consult};
+use crate::time::DateTime;
+
 #[export_name = "file:native_name_encoding/0"]
 pub extern "C-unwind" fn native_name_encoding(_process: &mut ProcessLock) -> ErlangResult {
     ErlangResult::Ok(atoms::Utf8.into())
 }
+
+/// Opens the file `name` with the given list of modes, returning `{ok, Fd}`
+///
+/// The file descriptor is of the form `{file_descriptor, prim_file, Handle}`, where `Handle`
+/// is a magic reference to the open file.
+#[export_name = "prim_file:open/2"]
+pub extern "C-unwind" fn open(
+    process: &mut ProcessLock,
+    name: OpaqueTerm,
+    modes: OpaqueTerm,
+) -> ErlangResult {
+    let Some(path) = filename_from_term(name) else { badarg!(process, name) };
+    let Some((options, binary)) = open_options_from_term(modes) else {
+        return reply_result(process, Reply::Error(atoms::Badarg));
+    };
+    offload(process, move || {
+        let file = options.open(path).map_err(posix_error)?;
+        Ok(Reply::Opened(Arc::new(FileHandle::new(file, binary))))
+    })
+}
+
+#[export_name = "prim_file:read/2"]
+pub extern "C-unwind" fn read(
+    process: &mut ProcessLock,
+    fd: OpaqueTerm,
+    size: OpaqueTerm,
+) -> ErlangResult {
+    let Some(handle) = handle_from_term(fd) else { badarg!(process, fd) };
+    let Some(size) = size_from_term(size) else { badarg!(process, size) };
+    offload(process, move || {
+        let data = handle.with_file(|file| read_up_to(file, size))?;
+        Ok(data_reply(data, size, handle.binary))
+    })
+}
+
+#[export_name = "prim_file:write/2"]
+pub extern "C-unwind" fn write(
+    process: &mut ProcessLock,
+    fd: OpaqueTerm,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    let Some(handle) = handle_from_term(fd) else { badarg!(process, fd) };
+    let Some(data) = iodata_from_term(data) else { badarg!(process, data) };
+    offload(process, move || {
+        handle.with_file(|file| file.write_all(&data))?;
+        Ok(Reply::Ok)
+    })
+}
+
+/// Reads `size` bytes at `location` without changing the current position of the file
+#[export_name = "prim_file:pread/3"]
+pub extern "C-unwind" fn pread(
+    process: &mut ProcessLock,
+    fd: OpaqueTerm,
+    location: OpaqueTerm,
+    size: OpaqueTerm,
+) -> ErlangResult {
+    let Some(handle) = handle_from_term(fd) else { badarg!(process, fd) };
+    let Some(location) = location_from_term(location) else {
+        return reply_result(process, Reply::Error(atoms::Einval));
+    };
+    let Some(size) = size_from_term(size) else { badarg!(process, size) };
+    offload(process, move || {
+        let data = handle.with_file(|file| at_location(file, location, |f| read_up_to(f, size)))?;
+        Ok(data_reply(data, size, handle.binary))
+    })
+}
+
+/// Writes `data` at `location` without changing the current position of the file
+#[export_name = "prim_file:pwrite/3"]
+pub extern "C-unwind" fn pwrite(
+    process: &mut ProcessLock,
+    fd: OpaqueTerm,
+    location: OpaqueTerm,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    let Some(handle) = handle_from_term(fd) else { badarg!(process, fd) };
+    let Some(location) = location_from_term(location) else {
+        return reply_result(process, Reply::Error(atoms::Einval));
+    };
+    let Some(data) = iodata_from_term(data) else { badarg!(process, data) };
+    offload(process, move || {
+        handle.with_file(|file| at_location(file, location, |f| f.write_all(&data)))?;
+        Ok(Reply::Ok)
+    })
+}
+
+#[export_name = "prim_file:position/2"]
+pub extern "C-unwind" fn position(
+    process: &mut ProcessLock,
+    fd: OpaqueTerm,
+    location: OpaqueTerm,
+) -> ErlangResult {
+    let Some(handle) = handle_from_term(fd) else { badarg!(process, fd) };
+    let Some(location) = location_from_term(location) else {
+        return reply_result(process, Reply::Error(atoms::Einval));
+    };
+    offload(process, move || {
+        let position = handle.with_file(|file| file.seek(location))?;
+        Ok(Reply::Position(position))
+    })
+}
+
+#[export_name = "prim_file:close/1"]
+pub extern "C-unwind" fn close(process: &mut ProcessLock, fd: OpaqueTerm) -> ErlangResult {
+    let Some(handle) = handle_from_term(fd) else { badarg!(process, fd) };
+    offload(process, move || {
+        let file = handle.file.lock().unwrap().take().ok_or(atoms::Einval)?;
+        drop(file);
+        Ok(Reply::Ok)
+    })
+}
+
+#[export_name = "prim_file:read_file/1"]
+pub extern "C-unwind" fn read_file(process: &mut ProcessLock, name: OpaqueTerm) -> ErlangResult {
+    let Some(path) = filename_from_term(name) else { badarg!(process, name) };
+    offload(process, move || {
+        let data = fs::read(path).map_err(posix_error)?;
+        Ok(Reply::Data(data, true))
+    })
+}
+
+/// Reads the Erlang terms in `name`, separated by periods, returning `{ok, Terms}`
+///
+/// Syntax errors are returned as `{error, {Line, erl_parse, Message}}`.
+#[export_name = "file:consult/1"]
+pub extern "C-unwind" fn consult(process: &mut ProcessLock, name: OpaqueTerm) -> ErlangResult {
+    let Some(path) = filename_from_term(name) else { badarg!(process, name) };
+    offload(process, move || {
+        let data = fs::read_to_string(path).map_err(posix_error)?;
+        match consult::parse(&data) {
+            Ok(terms) => Ok(Reply::Terms(terms)),
+            Err(err) => Ok(Reply::ParseError(err)),
+        }
+    })
+}
+
+#[export_name = "prim_file:write_file/2"]
+pub extern "C-unwind" fn write_file(
+    process: &mut ProcessLock,
+    name: OpaqueTerm,
+    data: OpaqueTerm,
+) -> ErlangResult {
+    let Some(path) = filename_from_term(name) else { badarg!(process, name) };
+    let Some(data) = iodata_from_term(data) else { badarg!(process, data) };
+    offload(process, move || {
+        fs::write(path, data).map_err(posix_error)?;
+        Ok(Reply::Ok)
+    })
+}
+
+/// Returns `{ok, #file_info{}}` for `name`, with times in local time
+#[export_name = "prim_file:read_file_info/1"]
+pub extern "C-unwind" fn read_file_info(
+    process: &mut ProcessLock,
+    name: OpaqueTerm,
+) -> ErlangResult {
+    let Some(path) = filename_from_term(name) else { badarg!(process, name) };
+    offload(process, move || {
+        let info = fs::metadata(path).map_err(posix_error)?;
+        Ok(Reply::Info(info))
+    })
+}
+
+#[export_name = "prim_file:list_dir/1"]
+pub extern "C-unwind" fn list_dir(process: &mut ProcessLock, name: OpaqueTerm) -> ErlangResult {
+    let Some(path) = filename_from_term(name) else { badarg!(process, name) };
+    offload(process, move || {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(path).map_err(posix_error)? {
+            let entry = entry.map_err(posix_error)?;
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        Ok(Reply::Names(names))
+    })
+}
+
+#[export_name = "prim_file:make_dir/1"]
+pub extern "C-unwind" fn make_dir(process: &mut ProcessLock, name: OpaqueTerm) -> ErlangResult {
+    let Some(path) = filename_from_term(name) else { badarg!(process, name) };
+    offload(process, move || {
+        fs::create_dir(path).map_err(posix_error)?;
+        Ok(Reply::Ok)
+    })
+}
+
+#[export_name = "prim_file:del_file/1"]
+pub extern "C-unwind" fn del_file(process: &mut ProcessLock, name: OpaqueTerm) -> ErlangResult {
+    let Some(path) = filename_from_term(name) else { badarg!(process, name) };
+    offload(process, move || {
+        fs::remove_file(path).map_err(posix_error)?;
+        Ok(Reply::Ok)
+    })
+}
+
+#[export_name = "prim_file:rename/2"]
+pub extern "C-unwind" fn rename(
+    process: &mut ProcessLock,
+    from: OpaqueTerm,
+    to: OpaqueTerm,
+) -> ErlangResult {
+    let Some(from_path) = filename_from_term(from) else { badarg!(process, from) };
+    let Some(to_path) = filename_from_term(to) else { badarg!(process, to) };
+    offload(process, move || {
+        fs::rename(from_path, to_path).map_err(posix_error)?;
+        Ok(Reply::Ok)
+    })
+}
+
+#[export_name = "prim_file:get_cwd/0"]
+pub extern "C-unwind" fn get_cwd(process: &mut ProcessLock) -> ErlangResult {
+    offload(process, || {
+        let cwd = env::current_dir().map_err(posix_error)?;
+        Ok(Reply::Name(cwd.to_string_lossy().into_owned()))
+    })
+}
+
+/// The state behind a file descriptor returned by `prim_file:open/2`
+///
+/// The file is closed by `prim_file:close/1`, or when the last reference to the handle is dropped.
+struct FileHandle {
+    file: Mutex<Option<File>>,
+    /// When true, data read from the file is returned as a binary rather than a list
+    binary: bool,
+}
+impl FileHandle {
+    fn new(file: File, binary: bool) -> Self {
+        Self {
+            file: Mutex::new(Some(file)),
+            binary,
+        }
+    }
+
+    /// Runs `f` with the underlying file, failing with `einval` if it has been closed
+    fn with_file<T, F>(&self, f: F) -> Result<T, Atom>
+    where
+        F: FnOnce(&mut File) -> io::Result<T>,
+    {
+        let mut file = self.file.lock().unwrap();
+        let file = file.as_mut().ok_or(atoms::Einval)?;
+        f(file).map_err(posix_error)
+    }
+}
+
+/// The outcome of a file operation, which is converted to a term by the process awaiting it
+enum Reply {
+    Ok,
+    Eof,
+    Opened(Arc<FileHandle>),
+    /// Data read from a file, and whether it should be returned as a binary
+    Data(Vec<u8>, bool),
+    Position(u64),
+    Info(Metadata),
+    Name(String),
+    Names(Vec<String>),
+    Terms(Vec<consult::Value>),
+    ParseError(consult::ParseError),
+    Error(Atom),
+}
+
+/// Runs `op` on a dirty I/O scheduler, see `blocking::offload`
+///
+/// Errors are returned as `{error, Reason}`, where `Reason` is a POSIX error code.
+fn offload<F>(process: &mut ProcessLock, op: F) -> ErlangResult
+where
+    F: FnOnce() -> Result<Reply, Atom> + Send + 'static,
+{
+    blocking::offload(
+        process,
+        move || op().unwrap_or_else(Reply::Error),
+        reply_to_term,
+    )
+}
+
+fn reply_result(process: &mut ProcessLock, reply: Reply) -> ErlangResult {
+    alloc_result(process, |heap| reply_to_term(&reply, heap))
+}
+
+fn reply_to_term(reply: &Reply, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    let value: OpaqueTerm = match reply {
+        Reply::Ok => return Ok(atoms::Ok.into()),
+        Reply::Eof => return Ok(atoms::Eof.into()),
+        Reply::Error(reason) => {
+            let tuple = Tuple::from_slice(&[atoms::Error.into(), (*reason).into()], heap)?;
+            return Ok(tuple.into());
+        }
+        Reply::ParseError(err) => {
+            let line = int_to_term(err.line as u64, heap)?;
+            let message = charlist_to_term(&err.message, heap)?;
+            let reason = Tuple::from_slice(&[line, atoms::ErlParse.into(), message], heap)?;
+            let tuple = Tuple::from_slice(&[atoms::Error.into(), reason.into()], heap)?;
+            return Ok(tuple.into());
+        }
+        Reply::Opened(handle) => {
+            // Reference ids are allocated by the current scheduler, so this can't be done on the
+            // thread which opened the file
+            let mut id = current_scheduler().next_reference_id();
+            id.set_magic();
+            let reference = Gc::new_in(Reference::new_magic(id, handle.clone()), heap)?;
+            let elements = [
+                atoms::FileDescriptor.into(),
+                atoms::PrimFile.into(),
+                reference.into(),
+            ];
+            Tuple::from_slice(&elements, heap)?.into()
+        }
+        Reply::Data(data, true) if data.len() > BinaryData::MAX_HEAP_BYTES => {
+            BinaryData::from_bytes(data).into()
+        }
+        Reply::Data(data, true) => BinaryData::from_small_bytes(data, heap)?.into(),
+        Reply::Data(data, false) => Cons::from_bytes(data, heap)?
+            .map(|list| list.into())
+            .unwrap_or(OpaqueTerm::NIL),
+        Reply::Position(position) => int_to_term(*position, heap)?,
+        Reply::Info(info) => file_info_to_term(info, heap)?,
+        Reply::Name(name) => charlist_to_term(name, heap)?,
+        Reply::Names(names) => {
+            let mut elements = Vec::with_capacity(names.len());
+            for name in names.iter() {
+                elements.push(charlist_to_term(name, heap)?);
+            }
+            list_to_term(&elements, heap)?
+        }
+        Reply::Terms(terms) => {
+            let mut elements = Vec::with_capacity(terms.len());
+            for term in terms.iter() {
+                elements.push(consult::to_term(term, heap)?);
+            }
+            list_to_term(&elements, heap)?
+        }
+    };
+    Ok(Tuple::from_slice(&[atoms::Ok.into(), value], heap)?.into())
+}
+
+/// Converts file metadata to a `#file_info{}` record
+fn file_info_to_term(info: &Metadata, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    let file_type = info.file_type();
+    let ty = if file_type.is_dir() {
+        atoms::Directory
+    } else if file_type.is_file() {
+        atoms::Regular
+    } else if file_type.is_symlink() {
+        atoms::Symlink
+    } else if is_device(&file_type) {
+        atoms::Device
+    } else {
+        atoms::Other
+    };
+    let access = if info.permissions().readonly() {
+        atoms::Read
+    } else {
+        atoms::ReadWrite
+    };
+    let stat = Stat::from(info);
+    let datetime = |time| datetime_to_term(DateTime::local_from_unix_seconds(time), heap);
+    let elements = [
+        atoms::FileInfo.into(),
+        int_to_term(info.len(), heap)?,
+        ty.into(),
+        access.into(),
+        datetime(unix_seconds(info.accessed()))?,
+        datetime(unix_seconds(info.modified()))?,
+        datetime(stat.ctime)?,
+        int_to_term(stat.mode as u64, heap)?,
+        int_to_term(stat.links, heap)?,
+        int_to_term(stat.major_device, heap)?,
+        int_to_term(stat.minor_device, heap)?,
+        int_to_term(stat.inode, heap)?,
+        int_to_term(stat.uid as u64, heap)?,
+        int_to_term(stat.gid as u64, heap)?,
+    ];
+    Ok(Tuple::from_slice(&elements, heap)?.into())
+}
+
+/// The fields of `#file_info{}` which are only available on Unix-like platforms
+#[derive(Default)]
+struct Stat {
+    ctime: i64,
+    mode: u32,
+    links: u64,
+    major_device: u64,
+    minor_device: u64,
+    inode: u64,
+    uid: u32,
+    gid: u32,
+}
+#[cfg(unix)]
+impl From<&Metadata> for Stat {
+    fn from(info: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        Self {
+            ctime: info.ctime(),
+            mode: info.mode(),
+            links: info.nlink(),
+            major_device: info.dev(),
+            minor_device: info.rdev(),
+            inode: info.ino(),
+            uid: info.uid(),
+            gid: info.gid(),
+        }
+    }
+}
+#[cfg(not(unix))]
+impl From<&Metadata> for Stat {
+    fn from(info: &Metadata) -> Self {
+        Self {
+            ctime: unix_seconds(info.modified()),
+            links: 1,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_device(file_type: &fs::FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    file_type.is_block_device() || file_type.is_char_device()
+}
+
+#[cfg(not(unix))]
+fn is_device(_file_type: &fs::FileType) -> bool {
+    false
+}
+
+fn unix_seconds(time: io::Result<SystemTime>) -> i64 {
+    time.ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn charlist_to_term(s: &str, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    Ok(Cons::charlist_from_str(s, heap)?
+        .map(|list| list.into())
+        .unwrap_or(OpaqueTerm::NIL))
+}
+
+/// Reads at most `size` bytes from the current position of `file`
+fn read_up_to(file: &mut File, size: usize) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    Read::take(&mut *file, size as u64).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Runs `f` with `file` positioned at `location`, restoring the original position afterwards
+fn at_location<T, F>(file: &mut File, location: SeekFrom, f: F) -> io::Result<T>
+where
+    F: FnOnce(&mut File) -> io::Result<T>,
+{
+    let current = file.stream_position()?;
+    file.seek(location)?;
+    let result = f(file);
+    file.seek(SeekFrom::Start(current))?;
+    result
+}
+
+/// Returns the reply for a read of `size` bytes, which is `eof` if nothing could be read
+fn data_reply(data: Vec<u8>, size: usize, binary: bool) -> Reply {
+    if data.is_empty() && size > 0 {
+        Reply::Eof
+    } else {
+        Reply::Data(data, binary)
+    }
+}
+
+/// Maps an I/O error to the POSIX error code used to represent it in Erlang
+pub(crate) fn posix_error(err: io::Error) -> Atom {
+    #[cfg(unix)]
+    if let Some(errno) = err.raw_os_error() {
+        match errno {
+            libc::EACCES => return atoms::Eacces,
+            libc::EAGAIN => return atoms::Eagain,
+            libc::EBADF => return atoms::Ebadf,
+            libc::EBUSY => return atoms::Ebusy,
+            libc::EEXIST => return atoms::Eexist,
+            libc::EFBIG => return atoms::Efbig,
+            libc::EINTR => return atoms::Eintr,
+            libc::EINVAL => return atoms::Einval,
+            libc::EIO => return atoms::Eio,
+            libc::EISDIR => return atoms::Eisdir,
+            libc::ELOOP => return atoms::Eloop,
+            libc::EMFILE => return atoms::Emfile,
+            libc::ENAMETOOLONG => return atoms::Enametoolong,
+            libc::ENFILE => return atoms::Enfile,
+            libc::ENODEV => return atoms::Enodev,
+            libc::ENOENT => return atoms::Enoent,
+            libc::ENOMEM => return atoms::Enomem,
+            libc::ENOSPC => return atoms::Enospc,
+            libc::ENOTDIR => return atoms::Enotdir,
+            libc::ENOTEMPTY => return atoms::Enotempty,
+            libc::ENOTSUP => return atoms::Enotsup,
+            libc::EPERM => return atoms::Eperm,
+            libc::EROFS => return atoms::Erofs,
+            libc::ESPIPE => return atoms::Espipe,
+            libc::EXDEV => return atoms::Exdev,
+            _ => (),
+        }
+    }
+    match err.kind() {
+        io::ErrorKind::NotFound => atoms::Enoent,
+        io::ErrorKind::PermissionDenied => atoms::Eacces,
+        io::ErrorKind::AlreadyExists => atoms::Eexist,
+        io::ErrorKind::WouldBlock => atoms::Eagain,
+        io::ErrorKind::InvalidInput => atoms::Einval,
+        io::ErrorKind::Interrupted => atoms::Eintr,
+        io::ErrorKind::Unsupported => atoms::Enotsup,
+        io::ErrorKind::OutOfMemory => atoms::Enomem,
+        _ => atoms::Eio,
+    }
+}
+
+/// Parses the list of modes given to `prim_file:open/2`
+///
+/// Returns the options to open the file with, and whether the file is in binary mode. Files are
+/// opened for reading if neither `write` nor `append` is given, and `write` truncates the file
+/// unless combined with `read`. Tuple options, e.g. `{encoding, Encoding}`, are ignored.
+fn open_options_from_term(modes: OpaqueTerm) -> Option<(OpenOptions, bool)> {
+    let mut read = false;
+    let mut write = false;
+    let mut append = false;
+    let mut exclusive = false;
+    let mut binary = false;
+    match modes.into() {
+        Term::Nil => (),
+        Term::Cons(list) => {
+            for mode in list.iter() {
+                match mode.ok()? {
+                    Term::Atom(a) if a == atoms::Read => read = true,
+                    Term::Atom(a) if a == atoms::Write => write = true,
+                    Term::Atom(a) if a == atoms::Append => append = true,
+                    Term::Atom(a) if a == atoms::Exclusive => exclusive = true,
+                    Term::Atom(a) if a == atoms::Binary => binary = true,
+                    Term::Atom(a) if a == atoms::Raw => (),
+                    Term::Atom(a) if a == atoms::ReadAhead => (),
+                    Term::Atom(a) if a == atoms::DelayedWrite => (),
+                    Term::Tuple(_) => (),
+                    _ => return None,
+                }
+            }
+        }
+        _ => return None,
+    }
+    if !write && !append {
+        read = true;
+    }
+
+    let mut options = OpenOptions::new();
+    options.read(read).write(write).append(append);
+    if exclusive {
+        options.create_new(true);
+    } else if write || append {
+        options.create(true);
+    }
+    if write && !read && !append {
+        options.truncate(true);
+    }
+    Some((options, binary))
+}
+
+/// Extracts the file handle from a `{file_descriptor, prim_file, Handle}` tuple
+fn handle_from_term(fd: OpaqueTerm) -> Option<Arc<FileHandle>> {
+    let Term::Tuple(fd) = fd.into() else { return None };
+    let [tag, module, handle] = fd.as_slice() else { return None };
+    if *tag != atoms::FileDescriptor || *module != atoms::PrimFile {
+        return None;
+    }
+    let Term::Reference(reference) = (*handle).into() else { return None };
+    reference.magic()?.downcast::<FileHandle>().ok()
+}
+
+/// Parses a file location, i.e. an absolute offset, `bof`, `cur`, `eof`, or a tuple of one of
+/// the latter with a relative offset
+fn location_from_term(location: OpaqueTerm) -> Option<SeekFrom> {
+    match location.into() {
+        Term::Int(offset) => Some(SeekFrom::Start(offset.try_into().ok()?)),
+        Term::Atom(a) if a == atoms::Bof => Some(SeekFrom::Start(0)),
+        Term::Atom(a) if a == atoms::Cur => Some(SeekFrom::Current(0)),
+        Term::Atom(a) if a == atoms::Eof => Some(SeekFrom::End(0)),
+        Term::Tuple(tuple) => {
+            let [from, offset] = tuple.as_slice() else { return None };
+            let Term::Int(offset) = (*offset).into() else { return None };
+            match (*from).into() {
+                Term::Atom(a) if a == atoms::Bof => Some(SeekFrom::Start(offset.try_into().ok()?)),
+                Term::Atom(a) if a == atoms::Cur => Some(SeekFrom::Current(offset)),
+                Term::Atom(a) if a == atoms::Eof => Some(SeekFrom::End(offset)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn size_from_term(size: OpaqueTerm) -> Option<usize> {
+    let Term::Int(size) = size.into() else { return None };
+    size.try_into().ok()
+}
+
+/// Converts a filename to a path
+///
+/// Filenames may be a binary, an atom, or a possibly deep list of characters, atoms and binaries.
+pub(crate) fn filename_from_term(name: OpaqueTerm) -> Option<PathBuf> {
+    let mut path = String::new();
+    push_filename(name.into(), &mut path)?;
+    Some(PathBuf::from(path))
+}
+
+fn push_filename(name: Term, path: &mut String) -> Option<()> {
+    match name {
+        Term::Nil => (),
+        Term::Atom(a) => path.push_str(a.as_str()),
+        Term::Int(c) => path.push(char::from_u32(c.try_into().ok()?)?),
+        Term::Cons(list) => {
+            for element in list.iter() {
+                push_filename(element.ok()?, path)?;
+            }
+        }
+        name => path.push_str(name.as_binary()?.as_str()?),
+    }
+    Some(())
+}
+
+/// Flattens iodata into a byte buffer
+pub(crate) fn iodata_from_term(data: OpaqueTerm) -> Option<Vec<u8>> {
+    let mut buffer = Vec::new();
+    push_iodata(data.into(), &mut buffer)?;
+    Some(buffer)
+}
+
+fn push_iodata(data: Term, buffer: &mut Vec<u8>) -> Option<()> {
+    match data {
+        Term::Nil => (),
+        Term::Int(byte) => buffer.push(byte.try_into().ok()?),
+        Term::Cons(list) => {
+            for element in list.iter() {
+                match element {
+                    Ok(element) => push_iodata(element, buffer)?,
+                    // Only a binary is permitted as the tail of an iolist
+                    Err(improper) => {
+                        let bytes = improper.tail.as_binary()?;
+                        push_bytes(bytes, buffer);
+                    }
+                }
+            }
+        }
+        data => push_bytes(data.as_binary()?, buffer),
+    }
+    Some(())
+}
+
+fn push_bytes(bytes: &dyn Bitstring, buffer: &mut Vec<u8>) {
+    if bytes.is_aligned() {
+        buffer.extend_from_slice(unsafe { bytes.as_bytes_unchecked() });
+    } else {
+        buffer.extend(bytes.bytes());
+    }
+}
@@ -0,0 +1,36 @@
+use firefly_bytecode::Function;
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+
+use crate::bifs::erlang::{alloc_result, list_to_term};
+use crate::emulator::current_scheduler;
+
+/// Returns `{Module, Function}` for each function named by an `-on_load` attribute
+///
+/// All modules are loaded when the system starts, so `init` runs these before starting any
+/// applications, rather than as each module is loaded.
+#[export_name = "init:on_load_functions/0"]
+pub extern "C-unwind" fn on_load_functions(process: &mut ProcessLock) -> ErlangResult {
+    let functions = current_scheduler()
+        .code()
+        .functions
+        .iter()
+        .filter_map(|fun| match fun {
+            Function::Bytecode {
+                is_on_load: true,
+                mfa,
+                ..
+            } => Some((mfa.module, mfa.function)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    alloc_result(process, |heap| {
+        let mut elements = Vec::with_capacity(functions.len());
+        for (module, function) in functions.iter().copied() {
+            let tuple = Tuple::from_slice(&[module.into(), function.into()], heap)?;
+            elements.push(tuple.into());
+        }
+        list_to_term(&elements, heap)
+    })
+}
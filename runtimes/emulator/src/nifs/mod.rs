@@ -1,3 +1,8 @@
+pub mod blocking;
+pub mod consult;
 pub mod file;
+pub mod init;
 pub mod lists;
+pub mod prof;
+pub mod stdio;
 pub mod unicode;
@@ -0,0 +1,110 @@
+use std::alloc::AllocError;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::{ProcessId, ProcessLock};
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::bifs::erlang::alloc_result;
+use crate::nifs::blocking;
+use crate::nifs::file::{filename_from_term, posix_error};
+use crate::profiler::{self, Mode, Options};
+
+/// Starts the profiler with the given list of options
+///
+/// * `calls` counts the calls, reductions and time spent in each function, this is the default
+/// * `sample` or `{sample, IntervalMs}` periodically samples the call stacks of running processes
+/// * `{procs, Pids}` only profiles the given processes, `{procs, all}` profiles every process
+/// * `{file, Filename}` writes the results to `Filename` instead of standard error
+///
+/// Returns `{error, already_started}` if the profiler is already running.
+#[export_name = "firefly_prof:start/1"]
+pub extern "C-unwind" fn start(process: &mut ProcessLock, options: OpaqueTerm) -> ErlangResult {
+    let Some(options) = options_from_term(options) else {
+        badarg!(process, options)
+    };
+    if profiler::start(options) {
+        ErlangResult::Ok(atoms::Ok.into())
+    } else {
+        alloc_result(process, |heap| error_to_term(atoms::AlreadyStarted, heap))
+    }
+}
+
+/// Stops the profiler, and writes its results
+///
+/// Returns `{error, not_started}` if the profiler is not running.
+#[export_name = "firefly_prof:stop/0"]
+pub extern "C-unwind" fn stop(process: &mut ProcessLock) -> ErlangResult {
+    match profiler::stop() {
+        Some(report) => {
+            let write = move || report.write().map_err(posix_error);
+            blocking::offload(process, write, result_to_term)
+        }
+        None => alloc_result(process, |heap| error_to_term(atoms::NotStarted, heap)),
+    }
+}
+
+fn options_from_term(term: OpaqueTerm) -> Option<Options> {
+    let mut options = Options::default();
+    match term.into() {
+        Term::Nil => (),
+        Term::Cons(list) => {
+            for option in list.iter() {
+                match option.ok()? {
+                    Term::Atom(a) if a == atoms::Calls => options.mode = Mode::Calls,
+                    Term::Atom(a) if a == atoms::Sample => options.mode = Mode::default_sample(),
+                    Term::Tuple(tuple) => {
+                        let [key, value] = tuple.as_slice() else { return None };
+                        match (*key).into() {
+                            Term::Atom(a) if a == atoms::Sample => {
+                                let Term::Int(ms) = (*value).into() else { return None };
+                                let ms = u64::try_from(ms).ok().filter(|ms| *ms > 0)?;
+                                options.mode = Mode::Sample(Duration::from_millis(ms));
+                            }
+                            Term::Atom(a) if a == atoms::Procs => {
+                                options.processes = processes_from_term(*value)?;
+                            }
+                            Term::Atom(a) if a == atoms::File => {
+                                options.output = Some(filename_from_term(*value)?);
+                            }
+                            _ => return None,
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some(options)
+}
+
+/// Converts `all` or a list of pids to the set of processes to profile, `None` meaning all
+fn processes_from_term(term: OpaqueTerm) -> Option<Option<BTreeSet<ProcessId>>> {
+    match term.into() {
+        Term::Atom(a) if a == atoms::All => Some(None),
+        Term::Nil => Some(Some(BTreeSet::new())),
+        Term::Cons(list) => {
+            let mut processes = BTreeSet::new();
+            for pid in list.iter() {
+                let Term::Pid(pid) = pid.ok()? else { return None };
+                processes.insert(pid.id());
+            }
+            Some(Some(processes))
+        }
+        _ => None,
+    }
+}
+
+fn result_to_term(result: &Result<(), Atom>, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    match result {
+        Ok(_) => Ok(atoms::Ok.into()),
+        Err(reason) => error_to_term(*reason, heap),
+    }
+}
+
+fn error_to_term(reason: Atom, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    Ok(Tuple::from_slice(&[atoms::Error.into(), reason.into()], heap)?.into())
+}
@@ -0,0 +1,176 @@
+use std::alloc::AllocError;
+use std::io::{self, BufRead, Write};
+
+use firefly_binary::{Bitstring, Encoding};
+use firefly_rt::function::ErlangResult;
+use firefly_rt::process::ProcessLock;
+use firefly_rt::term::*;
+
+use crate::badarg;
+use crate::bifs::erlang::alloc_result;
+use crate::nifs::blocking;
+use crate::nifs::file::posix_error;
+
+/// Writes `chars` to standard output
+///
+/// `chars` is chardata in `in_encoding`, and is written to standard output encoded according to
+/// `out_encoding`. If `out_encoding` is `latin1` and `chars` contains characters which cannot be
+/// represented in it, `{error, {no_translation, unicode, latin1}}` is returned.
+#[export_name = "prim_stdio:write/3"]
+pub extern "C-unwind" fn write(
+    process: &mut ProcessLock,
+    chars: OpaqueTerm,
+    in_encoding: OpaqueTerm,
+    out_encoding: OpaqueTerm,
+) -> ErlangResult {
+    let Some(in_latin1) = is_latin1(in_encoding) else { badarg!(process, in_encoding) };
+    let Some(out_latin1) = is_latin1(out_encoding) else { badarg!(process, out_encoding) };
+    let Some(chars) = chars_from_term(chars, in_latin1) else { badarg!(process, chars) };
+
+    let bytes = if out_latin1 {
+        let bytes = chars
+            .iter()
+            .map(|c| u8::try_from(*c))
+            .try_collect::<Vec<u8>>();
+        let Ok(bytes) = bytes else {
+            return alloc_result(process, |heap| {
+                let reason = Tuple::from_slice(
+                    &[
+                        atoms::NoTranslation.into(),
+                        atoms::Unicode.into(),
+                        atoms::Latin1.into(),
+                    ],
+                    heap,
+                )?;
+                Ok(Tuple::from_slice(&[atoms::Error.into(), reason.into()], heap)?.into())
+            });
+        };
+        bytes
+    } else {
+        chars.into_iter().collect::<String>().into_bytes()
+    };
+
+    blocking::offload(
+        process,
+        move || write_stdout(&bytes).map_err(posix_error),
+        result_to_term,
+    )
+}
+
+/// Reads the next line from standard input, including the trailing newline, if present
+///
+/// Returns `{ok, Chars}`, where the line is decoded according to `encoding`, or `eof` if the end
+/// of input has been reached.
+#[export_name = "prim_stdio:read_line/1"]
+pub extern "C-unwind" fn read_line(
+    process: &mut ProcessLock,
+    encoding: OpaqueTerm,
+) -> ErlangResult {
+    let Some(latin1) = is_latin1(encoding) else { badarg!(process, encoding) };
+
+    blocking::offload(
+        process,
+        move || {
+            let line = read_stdin_line().map_err(posix_error)?;
+            if latin1 {
+                Ok(line.into_iter().map(char::from).collect::<String>())
+            } else {
+                Ok(String::from_utf8_lossy(&line).into_owned())
+            }
+        },
+        line_to_term,
+    )
+}
+
+/// Returns true if `encoding` is `latin1`, or false if it is `unicode` or `utf8`
+fn is_latin1(encoding: OpaqueTerm) -> Option<bool> {
+    let Term::Atom(encoding) = encoding.into() else { return None };
+    match encoding.as_str().parse::<Encoding>().ok()? {
+        Encoding::Latin1 => Some(true),
+        Encoding::Utf8 => Some(false),
+        _ => None,
+    }
+}
+
+/// Flattens chardata into a sequence of characters
+///
+/// When `latin1` is true, integers must be bytes and binaries are latin1-encoded, otherwise
+/// integers are unicode codepoints and binaries are UTF-8 encoded.
+fn chars_from_term(chars: OpaqueTerm, latin1: bool) -> Option<Vec<char>> {
+    let mut result = Vec::new();
+    push_chars(chars.into(), latin1, &mut result)?;
+    Some(result)
+}
+
+fn push_chars(chars: Term, latin1: bool, result: &mut Vec<char>) -> Option<()> {
+    match chars {
+        Term::Nil => (),
+        Term::Cons(list) => {
+            for element in list.iter() {
+                match element {
+                    Ok(element) => push_chars(element, latin1, result)?,
+                    // Only a binary is permitted as the tail of chardata
+                    Err(improper) => {
+                        improper.tail.as_binary()?;
+                        push_chars(improper.tail, latin1, result)?;
+                    }
+                }
+            }
+        }
+        Term::Int(codepoint) => {
+            let c = char::from_u32(codepoint.try_into().ok()?)?;
+            if latin1 && c as u32 > 0xff {
+                return None;
+            }
+            result.push(c);
+        }
+        chars => {
+            let bytes = chars.as_binary()?.bytes().collect::<Vec<u8>>();
+            if latin1 {
+                result.extend(bytes.into_iter().map(char::from));
+            } else {
+                result.extend(std::str::from_utf8(&bytes).ok()?.chars());
+            }
+        }
+    }
+    Some(())
+}
+
+fn write_stdout(bytes: &[u8]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(bytes)?;
+    stdout.flush()
+}
+
+fn read_stdin_line() -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    io::stdin().lock().read_until(b'\n', &mut line)?;
+    Ok(line)
+}
+
+fn result_to_term(result: &Result<(), Atom>, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    match result {
+        Ok(_) => Ok(atoms::Ok.into()),
+        Err(reason) => error_to_term(*reason, heap),
+    }
+}
+
+fn line_to_term(
+    result: &Result<String, Atom>,
+    heap: &ProcessLock,
+) -> Result<OpaqueTerm, AllocError> {
+    match result {
+        Ok(line) if line.is_empty() => Ok(atoms::Eof.into()),
+        Ok(line) => {
+            let chars = Cons::charlist_from_str(line, heap)?
+                .map(|list| list.into())
+                .unwrap_or(OpaqueTerm::NIL);
+            Ok(Tuple::from_slice(&[atoms::Ok.into(), chars], heap)?.into())
+        }
+        Err(reason) => error_to_term(*reason, heap),
+    }
+}
+
+fn error_to_term(reason: Atom, heap: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    Ok(Tuple::from_slice(&[atoms::Error.into(), reason.into()], heap)?.into())
+}
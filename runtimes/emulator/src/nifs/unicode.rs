@@ -1,8 +1,9 @@
+use std::alloc::AllocError;
 use std::collections::VecDeque;
 
 use firefly_binary::{BitVec, Bitstring, Encoding};
 use firefly_rt::function::ErlangResult;
-use firefly_rt::gc::garbage_collect;
+use firefly_rt::gc::{garbage_collect, RootSet};
 use firefly_rt::process::ProcessLock;
 use firefly_rt::term::*;
 
@@ -75,6 +76,136 @@ pub extern "C-unwind" fn characters_to_list(
     }
 }
 
+#[export_name = "unicode:characters_to_binary/2"]
+pub extern "C-unwind" fn characters_to_binary(
+    process: &mut ProcessLock,
+    data: OpaqueTerm,
+    encoding: OpaqueTerm,
+) -> ErlangResult {
+    let Term::Atom(e) = encoding.into() else { badarg!(process, encoding) };
+    let Ok(encoding) = e.as_str().parse::<Encoding>() else { badarg!(process, encoding) };
+
+    let latin1 = encoding == Encoding::Latin1 || encoding == Encoding::Raw;
+
+    // Convert the input chardata into a UTF-8 string
+    let mut buffer = String::new();
+
+    let mut queue = VecDeque::new();
+    queue.push_back(data.into());
+    while let Some(term) = queue.pop_front() {
+        match term {
+            Term::Nil => continue,
+            Term::Cons(cons) => {
+                let mut items = flat_map_maybe_improper(&cons, vec![]);
+                queue.extend(items.drain(..));
+            }
+            Term::Int(codepoint) if codepoint >= 0 => {
+                let c = u32::try_from(codepoint).ok().and_then(char::from_u32);
+                match c {
+                    Some(c) if !latin1 || (c as u32) <= 0xff => buffer.push(c),
+                    // Not a valid code point in the input encoding
+                    _ => {
+                        let rest = Rest::Term(term.into());
+                        return conversion_error(process, atoms::Error, &buffer, rest, queue);
+                    }
+                }
+            }
+            term => {
+                let Some(bin) = term.as_binary() else { badarg!(process, data) };
+                if latin1 {
+                    buffer.extend(bin.bytes().map(char::from));
+                } else {
+                    let bytes = bin.bytes().collect::<Vec<u8>>();
+                    match std::str::from_utf8(&bytes) {
+                        Ok(s) => buffer.push_str(s),
+                        Err(err) => {
+                            let (valid, invalid) = bytes.split_at(err.valid_up_to());
+                            buffer.push_str(unsafe { std::str::from_utf8_unchecked(valid) });
+                            // A truncated sequence at the very end of the input may be completed
+                            // by the caller, anything else is an error
+                            let tag = if err.error_len().is_none() && queue.is_empty() {
+                                atoms::Incomplete
+                            } else {
+                                atoms::Error
+                            };
+                            let rest = Rest::Bytes(invalid.to_vec());
+                            return conversion_error(process, tag, &buffer, rest, queue);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if buffer.len() > BinaryData::MAX_HEAP_BYTES {
+        return ErlangResult::Ok(BinaryData::from_str(&buffer).into());
+    }
+    loop {
+        match BinaryData::from_small_str(&buffer, process) {
+            Ok(bin) => return ErlangResult::Ok(bin.into()),
+            Err(_) => {
+                // We don't need to pass any roots, because all our live data is in the buffer
+                assert!(garbage_collect(process, Default::default()).is_ok());
+            }
+        }
+    }
+}
+
+/// The first element of the data which `characters_to_binary` was unable to convert
+enum Rest {
+    /// An invalid code point
+    Term(OpaqueTerm),
+    /// The remaining bytes of a binary, starting at the first invalid or incomplete sequence
+    Bytes(Vec<u8>),
+}
+
+/// Returns `{Tag, Converted, Rest}` from `characters_to_binary`, where `Converted` is a binary
+/// of the data converted so far, and `Rest` is a list of `rest` followed by the unvisited data
+/// in `queue`
+fn conversion_error(
+    process: &mut ProcessLock,
+    tag: Atom,
+    converted: &str,
+    rest: Rest,
+    queue: VecDeque<Term>,
+) -> ErlangResult {
+    let mut unvisited = queue.into_iter().map(OpaqueTerm::from).collect::<Vec<_>>();
+
+    let mut layout = LayoutBuilder::new();
+    layout.build_binary(converted.len());
+    if let Rest::Bytes(ref bytes) = rest {
+        layout.build_binary(bytes.len());
+    }
+    layout.build_list(unvisited.len() + 1).build_tuple(3);
+    let needed = layout.finish().size();
+    if process.heap.heap_available() < needed {
+        process.gc_needed = needed;
+        let mut roots = RootSet::default();
+        for term in unvisited.iter_mut() {
+            roots += term as *mut _;
+        }
+        assert!(garbage_collect(process, roots).is_ok());
+    }
+
+    let converted = binary_from_bytes(converted.as_bytes(), process).unwrap();
+    let rest = match rest {
+        Rest::Term(term) => term,
+        Rest::Bytes(bytes) => binary_from_bytes(&bytes, process).unwrap(),
+    };
+    unvisited.insert(0, rest);
+    let rest = Cons::from_slice(&unvisited, process).unwrap().unwrap();
+    let tuple = Tuple::from_slice(&[tag.into(), converted, rest.into()], process).unwrap();
+    ErlangResult::Ok(tuple.into())
+}
+
+fn binary_from_bytes(bytes: &[u8], process: &ProcessLock) -> Result<OpaqueTerm, AllocError> {
+    if bytes.len() > BinaryData::MAX_HEAP_BYTES {
+        Ok(BinaryData::from_bytes(bytes).into())
+    } else {
+        Ok(BinaryData::from_small_bytes(bytes, process)?.into())
+    }
+}
+
 fn flat_map_maybe_improper(cons: &Cons, acc: Vec<Term>) -> Vec<Term> {
     cons.iter()
         .fold(acc, |mut acc, maybe_improper| match maybe_improper {
@@ -0,0 +1,463 @@
+//! A profiler for Erlang code executed by the emulator
+//!
+//! Two modes of profiling are supported:
+//!
+//! * `calls`, which like `eprof` attributes call counts, reductions, and the wall time spent
+//! executing, to each function. Time spent in natively-implemented functions is attributed to
+//! the function which called them.
+//! * `sample`, which periodically records the call stack of the process running on each
+//! scheduler, producing folded stacks (one line per unique stack, with the number of times it
+//! was sampled), as consumed by flamegraph tools such as `inferno` or `flamegraph.pl`.
+//!
+//! Profiling can be started at boot by setting `ERTS_PROFILE` to `calls`, `sample`, or
+//! `sample:<interval in milliseconds>`, in which case the results are written to the file named by
+//! `ERTS_PROFILE_OUTPUT` (or standard error) when the system halts. It can also be started and
+//! stopped from Erlang, using `firefly_prof:start/1` and `firefly_prof:stop/0`.
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use firefly_bytecode::{ByteCode, FunId};
+use firefly_rt::process::{ProcessId, ProcessLock};
+use firefly_rt::term::{atom::GlobalAtomTable, Atom};
+
+use crate::emulator::call_frames;
+
+/// The interval at which stacks are sampled if none is given
+const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+const INACTIVE: u8 = 0;
+const CALLS: u8 = 1;
+const SAMPLE: u8 = 2;
+
+/// The mode the profiler is running in, if any, checked by schedulers before each instruction
+static MODE: AtomicU8 = AtomicU8::new(INACTIVE);
+/// Incremented each time the profiler is started, so that state left behind on a scheduler by a
+/// previous run is not attributed to the current one
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+/// Incremented each sampling interval, each scheduler takes one sample per increment
+static EPOCH: AtomicUsize = AtomicUsize::new(0);
+static CODE: OnceLock<Arc<ByteCode<Atom, GlobalAtomTable>>> = OnceLock::new();
+static PROFILE: Mutex<Option<Profile>> = Mutex::new(None);
+
+thread_local! {
+    /// The function being executed by the process running on this scheduler
+    static CURRENT: RefCell<Option<Current>> = RefCell::new(None);
+    /// The sampling epoch in which this scheduler last took a sample
+    static SAMPLED: Cell<usize> = Cell::new(0);
+}
+
+/// How the profiler collects its results
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// Count calls, reductions and time spent in each function
+    Calls,
+    /// Sample the call stack of running processes at the given interval
+    Sample(Duration),
+}
+impl Mode {
+    /// Sampling at the default interval
+    pub const fn default_sample() -> Self {
+        Self::Sample(DEFAULT_SAMPLE_INTERVAL)
+    }
+}
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "calls" => Ok(Self::Calls),
+            "sample" => Ok(Self::default_sample()),
+            other => match other.strip_prefix("sample:").map(|ms| ms.parse::<u64>()) {
+                Some(Ok(ms)) if ms > 0 => Ok(Self::Sample(Duration::from_millis(ms))),
+                _ => Err(format!(
+                    "expected one of [calls, sample, sample:<milliseconds>], got '{}'",
+                    other
+                )),
+            },
+        }
+    }
+}
+
+pub struct Options {
+    pub mode: Mode,
+    /// The processes to profile, or all processes if `None`
+    pub processes: Option<BTreeSet<ProcessId>>,
+    /// The file to write the results to, or standard error if `None`
+    pub output: Option<PathBuf>,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Calls,
+            processes: None,
+            output: None,
+        }
+    }
+}
+impl Options {
+    /// Reads the options for profiling at boot from the environment, if requested
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(mode) = env::var("ERTS_PROFILE") else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            mode: mode.parse()?,
+            processes: None,
+            output: env::var_os("ERTS_PROFILE_OUTPUT").map(PathBuf::from),
+        }))
+    }
+}
+
+/// The results of a profiling run, rendered for output
+pub struct Report {
+    output: Option<PathBuf>,
+    contents: String,
+}
+impl Report {
+    /// Writes this report to the file requested when profiling was started, or standard error
+    pub fn write(&self) -> io::Result<()> {
+        match self.output.as_ref() {
+            Some(path) => fs::write(path, &self.contents),
+            None => io::stderr().write_all(self.contents.as_bytes()),
+        }
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+struct FunctionStats {
+    calls: u64,
+    reductions: u64,
+    time: Duration,
+}
+
+struct Profile {
+    options: Options,
+    generation: usize,
+    started: Instant,
+    functions: HashMap<FunId, FunctionStats>,
+    /// The number of times each call stack was sampled, outermost call first
+    stacks: HashMap<Vec<FunId>, u64>,
+    /// Set to stop the sampling thread, if there is one
+    sampler: Option<Arc<AtomicBool>>,
+}
+impl Profile {
+    fn is_profiled(&self, id: ProcessId) -> bool {
+        match self.options.processes.as_ref() {
+            None => true,
+            Some(processes) => processes.contains(&id),
+        }
+    }
+
+    /// Attributes the time and reductions spent since `current` was entered to its function
+    fn record(&mut self, current: &Current, now: Instant, reductions: usize) {
+        let stats = self.functions.entry(current.function).or_default();
+        stats.time += now.saturating_duration_since(current.since);
+        stats.reductions += reductions.saturating_sub(current.reductions) as u64;
+    }
+
+    fn report(self, code: &ByteCode<Atom, GlobalAtomTable>) -> Report {
+        let contents = match self.options.mode {
+            Mode::Calls => self.render_calls(code),
+            Mode::Sample(_) => self.render_stacks(code),
+        };
+        Report {
+            output: self.options.output,
+            contents,
+        }
+    }
+
+    fn render_calls(&self, code: &ByteCode<Atom, GlobalAtomTable>) -> String {
+        let mut rows = self
+            .functions
+            .iter()
+            .map(|(id, stats)| (function_name(code, *id), *stats))
+            .collect::<Vec<_>>();
+        rows.sort_by(|(a, a_stats), (b, b_stats)| {
+            b_stats.time.cmp(&a_stats.time).then_with(|| a.cmp(b))
+        });
+        let total = rows
+            .iter()
+            .fold(FunctionStats::default(), |total, (_, stats)| {
+                FunctionStats {
+                    calls: total.calls + stats.calls,
+                    reductions: total.reductions + stats.reductions,
+                    time: total.time + stats.time,
+                }
+            });
+        let width = rows
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("FUNCTION".len());
+
+        let mut out = String::new();
+        writeln!(
+            &mut out,
+            "Profiled for {:.3}s\n",
+            self.started.elapsed().as_secs_f64()
+        )
+        .unwrap();
+        writeln!(
+            &mut out,
+            "{:<width$} {:>10} {:>12} {:>12} {:>7} {:>9}",
+            "FUNCTION", "CALLS", "REDUCTIONS", "TIME (us)", "% TIME", "us/CALL"
+        )
+        .unwrap();
+        for (name, stats) in rows.iter().chain([("TOTAL".to_string(), total)].iter()) {
+            let micros = stats.time.as_secs_f64() * 1_000_000.0;
+            let percent = if total.time.is_zero() {
+                0.0
+            } else {
+                stats.time.as_secs_f64() / total.time.as_secs_f64() * 100.0
+            };
+            let per_call = if stats.calls == 0 {
+                0.0
+            } else {
+                micros / stats.calls as f64
+            };
+            writeln!(
+                &mut out,
+                "{:<width$} {:>10} {:>12} {:>12.0} {:>7.2} {:>9.2}",
+                name, stats.calls, stats.reductions, micros, percent, per_call
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    fn render_stacks(&self, code: &ByteCode<Atom, GlobalAtomTable>) -> String {
+        let mut lines = self
+            .stacks
+            .iter()
+            .map(|(stack, count)| {
+                let frames = stack
+                    .iter()
+                    .map(|id| function_name(code, *id))
+                    .collect::<Vec<_>>();
+                format!("{} {}\n", frames.join(";"), count)
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.concat()
+    }
+}
+
+/// The function being executed by a profiled process
+struct Current {
+    generation: usize,
+    /// Whether the process is being profiled, if not, nothing is recorded until it is scheduled out
+    profiled: bool,
+    function: FunId,
+    /// The instructions belonging to `function`
+    range: Range<usize>,
+    since: Instant,
+    /// The reductions consumed by the process in its current time slice, when entering `function`
+    reductions: usize,
+}
+
+/// Provides the profiler with the loaded code, which is used to resolve function names
+pub fn init(code: Arc<ByteCode<Atom, GlobalAtomTable>>) {
+    CODE.set(code).ok();
+}
+
+/// Returns true if schedulers must call [`observe`] before executing each instruction
+#[inline(always)]
+pub fn is_active() -> bool {
+    MODE.load(Ordering::Relaxed) != INACTIVE
+}
+
+/// Starts profiling with the given options
+///
+/// Returns false if the profiler is already running.
+pub fn start(options: Options) -> bool {
+    let mut profile = PROFILE.lock().unwrap();
+    if profile.is_some() {
+        return false;
+    }
+
+    let generation = GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    let (mode, sampler) = match options.mode {
+        Mode::Calls => (CALLS, None),
+        Mode::Sample(interval) => {
+            let stop = Arc::new(AtomicBool::new(false));
+            let stopped = stop.clone();
+            thread::Builder::new()
+                .name("profiler".to_string())
+                .spawn(move || {
+                    while !stopped.load(Ordering::Relaxed) {
+                        thread::sleep(interval);
+                        EPOCH.fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+                .expect("unable to spawn profiler sampling thread");
+            (SAMPLE, Some(stop))
+        }
+    };
+    *profile = Some(Profile {
+        options,
+        generation,
+        started: Instant::now(),
+        functions: HashMap::new(),
+        stacks: HashMap::new(),
+        sampler,
+    });
+    MODE.store(mode, Ordering::Release);
+
+    true
+}
+
+/// Stops profiling, returning the results, or `None` if the profiler was not running
+///
+/// Processes which are executing when the profiler is stopped do not contribute the remainder of
+/// their current time slice to the results.
+pub fn stop() -> Option<Report> {
+    let profile = PROFILE.lock().unwrap().take()?;
+    MODE.store(INACTIVE, Ordering::Release);
+    if let Some(stop) = profile.sampler.as_ref() {
+        stop.store(true, Ordering::Relaxed);
+    }
+    let code = CODE
+        .get()
+        .expect("profiler was started before code was loaded");
+    Some(profile.report(code))
+}
+
+/// Called by a scheduler before it executes the instruction at `ip` in `process`
+pub fn observe(code: &ByteCode<Atom, GlobalAtomTable>, process: &ProcessLock, ip: usize) {
+    // Exits and traps do not belong to any function
+    if code.is_stub(ip) {
+        return;
+    }
+    match MODE.load(Ordering::Relaxed) {
+        CALLS => trace(code, process, ip),
+        SAMPLE => sample(code, process, ip),
+        _ => (),
+    }
+}
+
+/// Called by a scheduler when the process it was executing is scheduled out, having consumed
+/// `reductions` during its time slice
+pub fn schedule_out(reductions: usize) {
+    let Some(current) = CURRENT.with(|current| current.borrow_mut().take()) else {
+        return;
+    };
+    if !current.profiled {
+        return;
+    }
+    let now = Instant::now();
+    let mut profile = PROFILE.lock().unwrap();
+    if let Some(profile) = profile
+        .as_mut()
+        .filter(|profile| profile.generation == current.generation)
+    {
+        profile.record(&current, now, reductions);
+    }
+}
+
+fn trace(code: &ByteCode<Atom, GlobalAtomTable>, process: &ProcessLock, ip: usize) {
+    let generation = GENERATION.load(Ordering::Relaxed);
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        // Most instructions stay within the current function, unless it calls itself
+        if let Some(current) = current
+            .as_ref()
+            .filter(|current| current.generation == generation)
+        {
+            if !current.profiled || (current.range.contains(&ip) && ip != current.range.start) {
+                return;
+            }
+        }
+
+        let now = Instant::now();
+        let mut profile = PROFILE.lock().unwrap();
+        let Some(profile) = profile
+            .as_mut()
+            .filter(|profile| profile.generation == generation)
+        else {
+            *current = None;
+            return;
+        };
+        match current.take() {
+            Some(previous) if previous.generation == generation => {
+                profile.record(&previous, now, process.reductions);
+            }
+            _ => {
+                if !profile.is_profiled(process.id()) {
+                    *current = Some(Current {
+                        generation,
+                        profiled: false,
+                        function: code.function_by_ip(ip).id(),
+                        range: 0..0,
+                        since: now,
+                        reductions: 0,
+                    });
+                    return;
+                }
+            }
+        }
+
+        let range = code.function_range(ip);
+        let function = code.function_by_ip(ip).id();
+        // Calls always begin at the first instruction of the callee, returns never do
+        if ip == range.start {
+            profile.functions.entry(function).or_default().calls += 1;
+        }
+        *current = Some(Current {
+            generation,
+            profiled: true,
+            function,
+            range,
+            since: now,
+            reductions: process.reductions,
+        });
+    });
+}
+
+fn sample(code: &ByteCode<Atom, GlobalAtomTable>, process: &ProcessLock, ip: usize) {
+    let epoch = EPOCH.load(Ordering::Relaxed);
+    if SAMPLED.with(|sampled| sampled.replace(epoch)) == epoch {
+        return;
+    }
+
+    let mut profile = PROFILE.lock().unwrap();
+    if let Some(profile) = profile.as_mut() {
+        if profile.is_profiled(process.id()) {
+            *profile
+                .stacks
+                .entry(call_stack(code, process, ip))
+                .or_default() += 1;
+        }
+    }
+}
+
+/// Returns the functions on the call stack of `process`, outermost call first
+fn call_stack(
+    code: &ByteCode<Atom, GlobalAtomTable>,
+    process: &ProcessLock,
+    ip: usize,
+) -> Vec<FunId> {
+    let mut functions = call_frames(code, process, ip)
+        .map(|frame| code.function_by_ip(frame.ip).id())
+        .collect::<Vec<_>>();
+    functions.reverse();
+    functions
+}
+
+fn function_name(code: &ByteCode<Atom, GlobalAtomTable>, id: FunId) -> String {
+    match code.function_by_id(id).mfa() {
+        Some(mfa) => mfa.to_string(),
+        None => "<native>".to_string(),
+    }
+}
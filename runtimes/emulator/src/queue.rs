@@ -77,6 +77,8 @@ pub trait TaskQueue {
 
     /// Returns true if the queue is empty
     fn is_empty(&self) -> bool;
+    /// Returns the number of tasks in the queue
+    fn len(&self) -> usize;
     /// Pushes a task at the end of the queue.
     ///
     /// In general this should allow all other tasks in the queue to be seen
@@ -211,6 +213,10 @@ impl<Q: TaskQueue> TaskQueue for RunQueue<Q> {
         self.max.is_empty() && self.hi.is_empty() && self.normal.is_empty()
     }
 
+    fn len(&self) -> usize {
+        self.max.len() + self.hi.len() + self.normal.len()
+    }
+
     fn scheduled(&self) -> u8 {
         self.max.scheduled() + self.hi.scheduled() + self.normal.scheduled()
     }
@@ -363,6 +369,11 @@ impl TaskQueue for LocalProcessQueue {
         self.tasks.is_empty()
     }
 
+    #[inline]
+    fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
     #[inline]
     fn push(&self, task: Self::Task) {
         self.tasks.push(task);
@@ -471,6 +482,11 @@ mod tests {
             self.inner().tasks.is_empty()
         }
 
+        #[inline]
+        fn len(&self) -> usize {
+            self.inner().tasks.len()
+        }
+
         #[inline]
         fn push(&self, task: Self::Task) {
             self.inner_mut().tasks.push_back(task);
@@ -0,0 +1,394 @@
+//! This module implements Erlang time, i.e. Erlang monotonic time and Erlang system time.
+//!
+//! Erlang monotonic time is derived from the OS monotonic clock. Erlang system time is
+//! Erlang monotonic time plus the time offset, and how that offset behaves depends on the
+//! time warp mode selected at startup:
+//!
+//! * `no_time_warp`, the time offset is determined at startup and never changes
+//! * `single_time_warp`, the time offset follows OS system time until it is finalized via
+//! `erlang:system_flag(time_offset, finalize)`, after which it never changes
+//! * `multi_time_warp`, the time offset always follows OS system time
+//!
+//! All times in this module are in nanoseconds unless stated otherwise, use [`convert_time_unit`]
+//! to get a value in some other unit.
+use core::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::OnceLock;
+
+use firefly_number::Int;
+use firefly_rt::term::{atoms, Atom, OpaqueTerm, Term};
+use firefly_system::time::{Duration, MonotonicTime, SystemTime, TimeUnit, UNIX_EPOCH};
+
+static TIME_WARP_MODE: OnceLock<TimeWarpMode> = OnceLock::new();
+static TIME_OFFSET: AtomicI64 = AtomicI64::new(0);
+static TIME_OFFSET_FINAL: AtomicBool = AtomicBool::new(false);
+
+/// Determines how Erlang system time is allowed to change relative to Erlang monotonic time
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TimeWarpMode {
+    #[default]
+    NoTimeWarp,
+    SingleTimeWarp,
+    MultiTimeWarp,
+}
+impl TimeWarpMode {
+    pub fn as_atom(&self) -> Atom {
+        match self {
+            Self::NoTimeWarp => atoms::NoTimeWarp,
+            Self::SingleTimeWarp => atoms::SingleTimeWarp,
+            Self::MultiTimeWarp => atoms::MultiTimeWarp,
+        }
+    }
+}
+impl FromStr for TimeWarpMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "no_time_warp" => Ok(Self::NoTimeWarp),
+            "single_time_warp" => Ok(Self::SingleTimeWarp),
+            "multi_time_warp" => Ok(Self::MultiTimeWarp),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The state of the time offset, as reported by `erlang:system_info(time_offset)`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimeOffsetState {
+    /// The time offset may still change, but will be finalized at some point
+    Preliminary,
+    /// The time offset will never change again
+    Final,
+    /// The time offset may change at any time
+    Volatile,
+}
+impl TimeOffsetState {
+    pub fn as_atom(&self) -> Atom {
+        match self {
+            Self::Preliminary => atoms::Preliminary,
+            Self::Final => atoms::Final,
+            Self::Volatile => atoms::Volatile,
+        }
+    }
+}
+
+/// Initialize the global time state with the given time warp mode
+pub fn init(mode: TimeWarpMode) {
+    TIME_OFFSET.store(os_time_offset(), Ordering::Relaxed);
+    TIME_OFFSET_FINAL.store(mode == TimeWarpMode::NoTimeWarp, Ordering::Release);
+    TIME_WARP_MODE
+        .set(mode)
+        .expect("time was already initialized");
+}
+
+/// Returns the time warp mode selected at startup
+pub fn time_warp_mode() -> TimeWarpMode {
+    TIME_WARP_MODE.get().copied().unwrap_or_default()
+}
+
+/// Returns the current state of the time offset
+pub fn time_offset_state() -> TimeOffsetState {
+    match time_warp_mode() {
+        TimeWarpMode::MultiTimeWarp => TimeOffsetState::Volatile,
+        _ if TIME_OFFSET_FINAL.load(Ordering::Acquire) => TimeOffsetState::Final,
+        _ => TimeOffsetState::Preliminary,
+    }
+}
+
+/// Finalizes the time offset if it is preliminary, returning the state it was in beforehand
+///
+/// This has no effect unless the time warp mode is `single_time_warp`.
+pub fn finalize_time_offset() -> TimeOffsetState {
+    if time_warp_mode() != TimeWarpMode::SingleTimeWarp {
+        return time_offset_state();
+    }
+    let offset = os_time_offset();
+    match TIME_OFFSET_FINAL.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => {
+            TIME_OFFSET.store(offset, Ordering::Release);
+            TimeOffsetState::Preliminary
+        }
+        Err(_) => TimeOffsetState::Final,
+    }
+}
+
+/// Returns the current Erlang monotonic time
+pub fn monotonic_time() -> i64 {
+    MonotonicTime::now().elapsed().as_nanos() as i64
+}
+
+/// Returns the current Erlang system time
+pub fn system_time() -> i64 {
+    monotonic_time() + time_offset()
+}
+
+/// Returns the current time offset between Erlang monotonic time and Erlang system time
+pub fn time_offset() -> i64 {
+    match time_offset_state() {
+        TimeOffsetState::Final => TIME_OFFSET.load(Ordering::Acquire),
+        TimeOffsetState::Preliminary | TimeOffsetState::Volatile => os_time_offset(),
+    }
+}
+
+/// Returns the current OS system time, which is unaffected by the time warp mode
+pub fn os_system_time() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => since_epoch.as_nanos() as i64,
+        Err(err) => -(err.duration().as_nanos() as i64),
+    }
+}
+
+fn os_time_offset() -> i64 {
+    os_system_time() - monotonic_time()
+}
+
+/// Converts `time` from `from` units to `to` units, rounding towards negative infinity
+pub fn convert_time_unit(time: Int, from: TimeUnit, to: TimeUnit) -> Int {
+    let from_hz = from.hertz() as i64;
+    let to_hz = to.hertz() as i64;
+    if from_hz == to_hz {
+        return time;
+    }
+    let scaled = time * to_hz;
+    let scaled = if scaled < 0i64 {
+        scaled - (from_hz - 1)
+    } else {
+        scaled
+    };
+    (scaled / from_hz).unwrap()
+}
+
+/// Parses a time unit term, i.e. one of the unit atoms accepted by the time BIFs, or a positive
+/// integer representing units per second
+pub fn time_unit_from_term(term: OpaqueTerm) -> Option<TimeUnit> {
+    match term.into() {
+        Term::Atom(a) if a == atoms::Second || a == atoms::Seconds => Some(TimeUnit::Second),
+        Term::Atom(a) if a == atoms::Millisecond || a == atoms::Milliseconds => {
+            Some(TimeUnit::Millisecond)
+        }
+        Term::Atom(a) if a == atoms::Microsecond || a == atoms::Microseconds => {
+            Some(TimeUnit::Microsecond)
+        }
+        Term::Atom(a) if a == atoms::Nanosecond || a == atoms::Nanoseconds => {
+            Some(TimeUnit::Nanosecond)
+        }
+        Term::Atom(a) if a == atoms::Native => Some(TimeUnit::Native),
+        Term::Atom(a) if a == atoms::PerfCounter => Some(TimeUnit::PerformanceCounter),
+        Term::Int(hertz) => TimeUnit::try_from(hertz).ok(),
+        _ => None,
+    }
+}
+
+/// Returns the current Erlang monotonic time in the given unit
+pub fn monotonic_time_in(unit: TimeUnit) -> Int {
+    convert_time_unit(Int::new(monotonic_time()), TimeUnit::Nanosecond, unit)
+}
+
+/// Returns the total CPU time consumed by this OS process, in user and system mode
+#[cfg(not(target_family = "wasm"))]
+pub fn cpu_time() -> Duration {
+    let mut usage: libc::rusage = unsafe { core::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return Duration::ZERO;
+    }
+    let to_duration =
+        |tv: libc::timeval| Duration::new(tv.tv_sec as u64, (tv.tv_usec as u32) * 1_000);
+    to_duration(usage.ru_utime) + to_duration(usage.ru_stime)
+}
+
+/// There is no way to measure CPU time on this platform, so wall time is used instead
+#[cfg(target_family = "wasm")]
+pub fn cpu_time() -> Duration {
+    Duration::from_nanos(monotonic_time() as u64)
+}
+
+/// A calendar date and time, without any associated time zone
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+impl DateTime {
+    /// Returns the current date and time in UTC, according to the OS system clock
+    pub fn universal_now() -> Self {
+        Self::from_unix_seconds(os_system_time().div_euclid(1_000_000_000))
+    }
+
+    /// Returns the current date and time in the local time zone, according to the OS system clock
+    pub fn local_now() -> Self {
+        Self::local_from_unix_seconds(os_system_time().div_euclid(1_000_000_000))
+    }
+
+    /// Returns the date and time in the local time zone corresponding to the given number of
+    /// seconds since the Unix epoch
+    #[cfg(not(target_family = "wasm"))]
+    pub fn local_from_unix_seconds(seconds: i64) -> Self {
+        let time = seconds as libc::time_t;
+        let mut tm: libc::tm = unsafe { core::mem::zeroed() };
+        if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+            return Self::from_unix_seconds(seconds);
+        }
+        Self {
+            year: tm.tm_year as i64 + 1900,
+            month: (tm.tm_mon + 1) as u8,
+            day: tm.tm_mday as u8,
+            hour: tm.tm_hour as u8,
+            minute: tm.tm_min as u8,
+            // Leap seconds are folded into the last second of the minute
+            second: tm.tm_sec.min(59) as u8,
+        }
+    }
+
+    /// There is no notion of a local time zone on this platform, so local time is UTC
+    #[cfg(target_family = "wasm")]
+    pub fn local_from_unix_seconds(seconds: i64) -> Self {
+        Self::from_unix_seconds(seconds)
+    }
+
+    /// Converts this date and time from the local time zone to UTC
+    ///
+    /// Returns `None` if this is not a valid date and time, or it cannot be represented
+    #[cfg(not(target_family = "wasm"))]
+    pub fn local_to_universal(&self) -> Option<Self> {
+        if !self.is_valid() {
+            return None;
+        }
+        let mut tm: libc::tm = unsafe { core::mem::zeroed() };
+        tm.tm_year = (self.year - 1900).try_into().ok()?;
+        tm.tm_mon = self.month as libc::c_int - 1;
+        tm.tm_mday = self.day as libc::c_int;
+        tm.tm_hour = self.hour as libc::c_int;
+        tm.tm_min = self.minute as libc::c_int;
+        tm.tm_sec = self.second as libc::c_int;
+        // Let the system determine whether daylight saving time is in effect
+        tm.tm_isdst = -1;
+        let time = unsafe { libc::mktime(&mut tm) };
+        if time == -1 {
+            return None;
+        }
+        Some(Self::from_unix_seconds(time as i64))
+    }
+
+    /// There is no notion of a local time zone on this platform, so local time is UTC
+    #[cfg(target_family = "wasm")]
+    pub fn local_to_universal(&self) -> Option<Self> {
+        if self.is_valid() {
+            Some(*self)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the date and time in UTC corresponding to the given number of seconds since the Unix epoch
+    pub fn from_unix_seconds(seconds: i64) -> Self {
+        let days = seconds.div_euclid(86_400);
+        let seconds = seconds.rem_euclid(86_400);
+
+        // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+
+        Self {
+            year,
+            month: month as u8,
+            day: day as u8,
+            hour: (seconds / 3600) as u8,
+            minute: (seconds % 3600 / 60) as u8,
+            second: (seconds % 60) as u8,
+        }
+    }
+
+    /// Returns true if this is a valid calendar date and time
+    pub fn is_valid(&self) -> bool {
+        let days_in_month = match self.month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if self.is_leap_year() => 29,
+            2 => 28,
+            _ => return false,
+        };
+        self.day >= 1
+            && self.day <= days_in_month
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    fn is_leap_year(&self) -> bool {
+        (self.year % 4 == 0 && self.year % 100 != 0) || self.year % 400 == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_time_unit_rounds_down_test() {
+        let convert = |time: i64, from, to| convert_time_unit(Int::new(time), from, to);
+
+        assert_eq!(
+            convert(1_999, TimeUnit::Millisecond, TimeUnit::Second),
+            Int::new(1)
+        );
+        assert_eq!(
+            convert(-1, TimeUnit::Millisecond, TimeUnit::Second),
+            Int::new(-1)
+        );
+        assert_eq!(
+            convert(-1_000, TimeUnit::Millisecond, TimeUnit::Second),
+            Int::new(-1)
+        );
+        assert_eq!(
+            convert(3, TimeUnit::Second, TimeUnit::Microsecond),
+            Int::new(3_000_000)
+        );
+        let hz = TimeUnit::try_from(3i64).unwrap();
+        assert_eq!(convert(1_000, TimeUnit::Millisecond, hz), Int::new(3));
+        assert_eq!(convert(2, hz, TimeUnit::Millisecond), Int::new(666));
+    }
+
+    #[test]
+    fn datetime_from_unix_seconds_test() {
+        let epoch = DateTime::from_unix_seconds(0);
+        assert_eq!((epoch.year, epoch.month, epoch.day), (1970, 1, 1));
+
+        let leap_day = DateTime::from_unix_seconds(951_827_696);
+        assert_eq!(
+            leap_day,
+            DateTime {
+                year: 2000,
+                month: 2,
+                day: 29,
+                hour: 12,
+                minute: 34,
+                second: 56,
+            }
+        );
+
+        let before_epoch = DateTime::from_unix_seconds(-1);
+        assert_eq!(
+            before_epoch,
+            DateTime {
+                year: 1969,
+                month: 12,
+                day: 31,
+                hour: 23,
+                minute: 59,
+                second: 59,
+            }
+        );
+    }
+}
@@ -1,6 +1,6 @@
 -module(erts_internal).
 
--export([is_process_alive/1, is_process_alive/2]).
+-export([is_process_alive/1, is_process_alive/2, process_info/2, process_info/3]).
 
 -spec erts_internal:is_process_alive(Pid) -> boolean() when
       Pid :: pid().
@@ -17,3 +17,25 @@ is_process_alive(Pid) ->
       Ref :: reference().
 is_process_alive(_Pid, _Ref) ->
     erlang:nif_error(undefined).
+
+-spec erts_internal:process_info(Pid, Item) -> {Item, term()} | 'undefined' when
+      Pid :: pid(),
+      Item :: atom().
+process_info(Pid, Item) ->
+    Ref = make_ref(),
+    case erts_internal:process_info(Pid, Item, Ref) of
+        true ->
+            receive
+                {Ref, Res} ->
+                    Res
+            end;
+        false ->
+            undefined
+    end.
+
+-spec erts_internal:process_info(Pid, Item, Ref) -> boolean() when
+      Pid :: pid(),
+      Item :: atom(),
+      Ref :: reference().
+process_info(_Pid, _Item, _Ref) ->
+    erlang:nif_error(undefined).
@@ -0,0 +1,28 @@
+%% RUN: @firefly compile --bin -o @tempfile @file && (sleep 1; echo world) | @tempfile
+
+%% A get_line waiting on stdin must not hold up output requests sent after it
+%% CHECK: hello
+%% CHECK: put_chars
+%% CHECK: "world\n"
+-module(init).
+
+-export([boot/1]).
+
+boot(_) ->
+    User = whereis(user),
+    User ! {io_request, self(), get_line, {get_line, unicode, ''}},
+    User ! {io_request, self(), put_chars, {put_chars, unicode, <<"hello\n">>}},
+    receive
+        {io_reply, ReplyAs, _} ->
+            erlang:display(ReplyAs)
+    after
+        5000 ->
+            erlang:display(timeout)
+    end,
+    receive
+        {io_reply, get_line, Line} ->
+            erlang:display(Line)
+    after
+        5000 ->
+            erlang:display(timeout)
+    end.